@@ -24,16 +24,28 @@ for target in $(yq --exit-status --output-format json --indent 0 '.targets[]' "$
     volume_internal="/extracted_keys"
     key_output="${name}_${version}_${os}_${arch}"
 
+    # Debian ships ASCII-armored keys for the archive and the security archive so those are copied instead of exported
+    if [ "$name" == "debian" ]; then
+      extract_command="cp /etc/apt/trusted.gpg.d/debian-archive-\$(. /etc/os-release && echo \$VERSION_CODENAME)-automatic.asc /$volume_internal/$key_output"
+      extract_security_command="cp /etc/apt/trusted.gpg.d/debian-archive-\$(. /etc/os-release && echo \$VERSION_CODENAME)-security-automatic.asc /$volume_internal/${key_output}_security"
+      extract_command="$extract_command && $extract_security_command"
+    else
+      extract_command="apt update && apt install -y gnupg && apt-key export 'ftpmaster@ubuntu.com' > /$volume_internal/$key_output"
+    fi
+
     echo "Extracting keyring from $docker_image ($platform)..."
     docker run \
       --volume "$volume_external:$volume_internal:rw" \
       --platform "$platform" \
       --rm -it "$docker_image" \
-      bash -c "apt update && apt install -y gnupg && apt-key export 'ftpmaster@ubuntu.com' > /$volume_internal/$key_output"
-
-    checksum=$(md5sum "$volume_external/$key_output" | cut -d ' ' -f 1)
-    mv "$volume_external/$key_output" "$volume_external/$key_output.$checksum.asc"
-
-    echo "Saved to $volume_external/$key_output.$checksum.asc"
+      bash -c "$extract_command"
+
+    for extracted_key in "$volume_external/$key_output" "$volume_external/${key_output}_security"; do
+      if [ -f "$extracted_key" ]; then
+        checksum=$(md5sum "$extracted_key" | cut -d ' ' -f 1)
+        mv "$extracted_key" "$extracted_key.$checksum.asc"
+        echo "Saved to $extracted_key.$checksum.asc"
+      fi
+    done
   done
 done
@@ -0,0 +1,382 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+// An alternative registered by a package's postinst script with
+// `update-alternatives --install <link> <name> <path> <priority> [--slave <link> <name> <path>]...`
+//
+// Maintainer scripts can't run against the layer so the links update-alternatives would have
+// created are emulated instead.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Alternative {
+    pub(crate) name: String,
+    pub(crate) link: PathBuf,
+    pub(crate) path: PathBuf,
+    pub(crate) priority: i64,
+    pub(crate) slaves: Vec<AlternativeSlave>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) struct AlternativeSlave {
+    pub(crate) name: String,
+    pub(crate) link: PathBuf,
+    pub(crate) path: PathBuf,
+}
+
+impl Display for Alternative {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} -> {} ({})",
+            self.link.display(),
+            self.path.display(),
+            self.name
+        )
+    }
+}
+
+// Only literal arguments are understood. Calls that use shell variables (e.g.; `$MULTIARCH`) are
+// skipped since the script isn't evaluated.
+pub(crate) fn parse_alternatives(postinst_script: &str) -> Vec<Alternative> {
+    postinst_script
+        .replace("\\\n", " ")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let args = line
+                .split_whitespace()
+                .map(|arg| arg.trim_matches(|c| c == '"' || c == '\''))
+                .skip_while(|arg| !arg.ends_with("update-alternatives"))
+                .collect::<Vec<_>>();
+            parse_install_args(&args)
+        })
+        .collect()
+}
+
+fn parse_install_args(args: &[&str]) -> Option<Alternative> {
+    if args
+        .iter()
+        .any(|arg| arg.contains('$') || arg.contains('`'))
+    {
+        return None;
+    }
+
+    let install_index = args.iter().position(|arg| *arg == "--install")?;
+    let [link, name, path, priority] = args.get(install_index + 1..install_index + 5)? else {
+        return None;
+    };
+
+    let slaves = args
+        .iter()
+        .enumerate()
+        .filter(|(_, arg)| **arg == "--slave")
+        .filter_map(|(index, _)| match args.get(index + 1..index + 4)? {
+            [link, name, path] => Some(AlternativeSlave {
+                name: (*name).to_string(),
+                link: normalize_path(link)?,
+                path: normalize_path(path)?,
+            }),
+            _ => None,
+        })
+        .collect();
+
+    Some(Alternative {
+        name: (*name).to_string(),
+        link: normalize_path(link)?,
+        path: normalize_path(path)?,
+        priority: priority.parse().ok()?,
+        slaves,
+    })
+}
+
+// update-alternatives only accepts absolute paths. They're normalized (e.g.; `/usr/bin/../lib/x`
+// to `/usr/lib/x`) so links are created where the script intended and a path with a `..` that
+// climbs above the root is rejected instead of escaping the layer.
+fn normalize_path(path: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+    if !path.is_absolute() {
+        return None;
+    }
+    let mut normalized = PathBuf::from("/");
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+        }
+    }
+    Some(normalized)
+}
+
+// Picks the highest priority alternative for each name whose path was installed into the layer,
+// the same choice update-alternatives makes in automatic mode, and links it into the layer. The
+// links are relative so they resolve wherever the layer is mounted. Existing symlinks (e.g.; a
+// link into `/etc/alternatives` shipped by the package) are replaced but regular files are kept.
+pub(crate) fn create_alternatives(
+    install_dir: &Path,
+    alternatives: &BTreeMap<String, Vec<Alternative>>,
+) -> Result<Vec<Alternative>, CreateAlternativesError> {
+    let mut selected: BTreeMap<&str, &Alternative> = BTreeMap::new();
+    for alternative in alternatives.values().flatten() {
+        if !layer_path(install_dir, &alternative.path).exists() {
+            continue;
+        }
+        selected
+            .entry(&alternative.name)
+            .and_modify(|current| {
+                if alternative.priority > current.priority {
+                    *current = alternative;
+                }
+            })
+            .or_insert(alternative);
+    }
+
+    for alternative in selected.values() {
+        create_alternative_link(install_dir, &alternative.link, &alternative.path)?;
+        for slave in &alternative.slaves {
+            if layer_path(install_dir, &slave.path).exists() {
+                create_alternative_link(install_dir, &slave.link, &slave.path)?;
+            }
+        }
+    }
+
+    Ok(selected.into_values().cloned().collect())
+}
+
+fn create_alternative_link(
+    install_dir: &Path,
+    link: &Path,
+    path: &Path,
+) -> Result<(), CreateAlternativesError> {
+    let link_path = layer_path(install_dir, link);
+    let map_err = |e| CreateAlternativesError(link_path.clone(), e);
+
+    match fs::symlink_metadata(&link_path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            fs::remove_file(&link_path).map_err(map_err)?;
+        }
+        Ok(_) => return Ok(()),
+        Err(_) => {
+            if let Some(parent) = link_path.parent() {
+                fs::create_dir_all(parent).map_err(map_err)?;
+            }
+        }
+    }
+
+    symlink(relative_target(link, path), &link_path).map_err(map_err)
+}
+
+// Only the normal components are joined so a path can never resolve outside of the layer.
+fn layer_path(install_dir: &Path, path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .fold(install_dir.to_path_buf(), |layer_path, component| {
+            layer_path.join(component)
+        })
+}
+
+// e.g.; a link at /usr/bin/editor to /usr/bin/vim.basic targets `vim.basic` and a link at
+// /usr/bin/pager to /bin/less targets `../../bin/less`
+fn relative_target(link: &Path, path: &Path) -> PathBuf {
+    let link_dir = link
+        .parent()
+        .map(|parent| {
+            parent
+                .components()
+                .filter(|component| matches!(component, Component::Normal(_)))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let path_components = path
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect::<Vec<_>>();
+
+    let common = link_dir
+        .iter()
+        .zip(&path_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    std::iter::repeat(Component::ParentDir)
+        .take(link_dir.len() - common)
+        .chain(path_components[common..].iter().copied())
+        .collect()
+}
+
+#[derive(Debug)]
+pub(crate) struct CreateAlternativesError(pub(crate) PathBuf, pub(crate) std::io::Error);
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_alternatives() {
+        let postinst_script = indoc! { r#"
+            #!/bin/sh
+            set -e
+
+            if [ "$1" = "configure" ]; then
+                update-alternatives --install /usr/bin/editor editor /usr/bin/vim.basic 30 \
+                    --slave /usr/share/man/man1/editor.1.gz editor.1.gz /usr/share/man/man1/vim.1.gz
+                update-alternatives --quiet --install "/usr/bin/vi" vi "/usr/bin/vim.basic" 30
+                # update-alternatives --install /usr/bin/ex ex /usr/bin/vim.basic 30
+                update-alternatives --install /usr/lib/$MULTIARCH/libblas.so libblas.so /usr/lib/$MULTIARCH/blas/libblas.so 10
+                update-alternatives --remove view /usr/bin/vim.basic
+            fi
+        "# };
+
+        assert_eq!(
+            parse_alternatives(postinst_script),
+            vec![
+                Alternative {
+                    name: "editor".to_string(),
+                    link: PathBuf::from("/usr/bin/editor"),
+                    path: PathBuf::from("/usr/bin/vim.basic"),
+                    priority: 30,
+                    slaves: vec![AlternativeSlave {
+                        name: "editor.1.gz".to_string(),
+                        link: PathBuf::from("/usr/share/man/man1/editor.1.gz"),
+                        path: PathBuf::from("/usr/share/man/man1/vim.1.gz"),
+                    }],
+                },
+                Alternative {
+                    name: "vi".to_string(),
+                    link: PathBuf::from("/usr/bin/vi"),
+                    path: PathBuf::from("/usr/bin/vim.basic"),
+                    priority: 30,
+                    slaves: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_alternatives_normalizes_paths() {
+        let postinst_script = indoc! { r"
+            update-alternatives --install /usr/bin/./pager pager /usr/lib/../bin/less 77 \
+                --slave /usr/bin/../../../etc/passwd pager.1.gz /usr/share/man/man1/less.1.gz
+            update-alternatives --install /usr/bin/more more ../../etc/passwd 10
+            update-alternatives --install /usr/bin/view view /../../etc/shadow 10
+        " };
+
+        assert_eq!(
+            parse_alternatives(postinst_script),
+            vec![Alternative {
+                name: "pager".to_string(),
+                link: PathBuf::from("/usr/bin/pager"),
+                path: PathBuf::from("/usr/bin/less"),
+                priority: 77,
+                slaves: vec![],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_layer_path() {
+        assert_eq!(
+            layer_path(Path::new("/layers/packages"), Path::new("/usr/bin/less")),
+            PathBuf::from("/layers/packages/usr/bin/less")
+        );
+        assert_eq!(
+            layer_path(
+                Path::new("/layers/packages"),
+                Path::new("/../../etc/passwd")
+            ),
+            PathBuf::from("/layers/packages/etc/passwd")
+        );
+    }
+
+    #[test]
+    fn test_create_alternatives() {
+        let install_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(install_dir.path().join("usr/bin")).unwrap();
+        fs::create_dir_all(install_dir.path().join("bin")).unwrap();
+        fs::write(install_dir.path().join("usr/bin/vim.basic"), "").unwrap();
+        fs::write(install_dir.path().join("bin/less"), "").unwrap();
+        symlink(
+            "/etc/alternatives/editor",
+            install_dir.path().join("usr/bin/editor"),
+        )
+        .unwrap();
+
+        let alternative = |name: &str, link: &str, path: &str, priority| Alternative {
+            name: name.to_string(),
+            link: PathBuf::from(link),
+            path: PathBuf::from(path),
+            priority,
+            slaves: vec![],
+        };
+        let alternatives = BTreeMap::from([
+            (
+                "nano".to_string(),
+                vec![alternative("editor", "/usr/bin/editor", "/bin/nano", 40)],
+            ),
+            (
+                "less".to_string(),
+                vec![alternative("pager", "/usr/bin/pager", "/bin/less", 77)],
+            ),
+            (
+                "vim".to_string(),
+                vec![
+                    alternative("editor", "/usr/bin/editor", "/usr/bin/vim.basic", 30),
+                    alternative("missing", "/usr/bin/missing", "/usr/bin/missing.real", 10),
+                ],
+            ),
+        ]);
+
+        assert_eq!(
+            create_alternatives(install_dir.path(), &alternatives).unwrap(),
+            vec![
+                alternative("editor", "/usr/bin/editor", "/usr/bin/vim.basic", 30),
+                alternative("pager", "/usr/bin/pager", "/bin/less", 77),
+            ]
+        );
+        assert_eq!(
+            fs::read_link(install_dir.path().join("usr/bin/editor")).unwrap(),
+            PathBuf::from("vim.basic")
+        );
+        assert_eq!(
+            fs::read_link(install_dir.path().join("usr/bin/pager")).unwrap(),
+            PathBuf::from("../../bin/less")
+        );
+        assert!(!install_dir.path().join("usr/bin/missing").exists());
+    }
+
+    #[test]
+    fn test_create_alternatives_keeps_regular_files() {
+        let install_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(install_dir.path().join("usr/bin")).unwrap();
+        fs::write(install_dir.path().join("usr/bin/python3"), "").unwrap();
+        fs::write(install_dir.path().join("usr/bin/python"), "real").unwrap();
+
+        let alternatives = BTreeMap::from([(
+            "python-is-python3".to_string(),
+            vec![Alternative {
+                name: "python".to_string(),
+                link: PathBuf::from("/usr/bin/python"),
+                path: PathBuf::from("/usr/bin/python3"),
+                priority: 1,
+                slaves: vec![],
+            }],
+        )]);
+
+        create_alternatives(install_dir.path(), &alternatives).unwrap();
+        assert_eq!(
+            fs::read_to_string(install_dir.path().join("usr/bin/python")).unwrap(),
+            "real"
+        );
+    }
+}
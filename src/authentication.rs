@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use http::Extensions;
+use libcnb::Env;
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{Middleware, Next};
+
+// Credentials for private repositories are read from `.netrc` formatted content so the same
+// file used by other tools (e.g.; curl, git) can be shared with this buildpack. The content is
+// read from the `BP_DEB_PACKAGES_NETRC` variable of the platform environment (e.g.;
+// `pack build --env BP_DEB_PACKAGES_NETRC=...`) and falls back to `$HOME/.netrc`.
+//
+// Like apt's `auth.conf`, only `machine` entries are used. A `default` entry would send its
+// credentials to every host, including the distribution mirrors that are only served over plain
+// HTTP, so it's ignored. Credentials are also never sent over plain HTTP, even to a `machine`
+// host, since they would be readable by anyone between the builder and the repository.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub(crate) struct Credentials {
+    machines: HashMap<String, Login>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Login {
+    login: String,
+    password: String,
+}
+
+// A `machine` (or `default` when `host` is `None`) entry while it's being parsed.
+#[derive(Debug, Default)]
+struct NetrcEntry {
+    host: Option<String>,
+    login: Option<String>,
+    password: Option<String>,
+}
+
+impl Credentials {
+    pub(crate) fn from_env(platform_env: &Env, process_env: &Env) -> Self {
+        platform_env
+            .get("BP_DEB_PACKAGES_NETRC")
+            .and_then(|value| value.to_str().map(ToString::to_string))
+            .or_else(|| {
+                process_env
+                    .get("HOME")
+                    .map(|home| PathBuf::from(home).join(".netrc"))
+                    .and_then(|netrc_file| std::fs::read_to_string(netrc_file).ok())
+            })
+            .map(|contents| Credentials::parse_netrc(&contents))
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn parse_netrc(contents: &str) -> Self {
+        let mut credentials = Credentials::default();
+        let mut tokens = contents.split_whitespace().peekable();
+        let mut current: Option<NetrcEntry> = None;
+
+        while let Some(token) = tokens.next() {
+            match token {
+                "machine" | "default" | "macdef" => {
+                    if let Some(entry) = current.take() {
+                        credentials.add_entry(entry);
+                    }
+                    match token {
+                        "machine" => {
+                            current = tokens.next().map(|host| NetrcEntry {
+                                host: Some(host.to_ascii_lowercase()),
+                                ..NetrcEntry::default()
+                            });
+                        }
+                        "default" => current = Some(NetrcEntry::default()),
+                        // macro definitions are not supported so skip over their body
+                        _ => {
+                            tokens.next();
+                            while tokens
+                                .peek()
+                                .is_some_and(|token| !matches!(*token, "machine" | "default"))
+                            {
+                                tokens.next();
+                            }
+                        }
+                    }
+                }
+                "login" => {
+                    if let (Some(entry), Some(login)) = (current.as_mut(), tokens.next()) {
+                        entry.login = Some(login.to_string());
+                    }
+                }
+                "password" => {
+                    if let (Some(entry), Some(password)) = (current.as_mut(), tokens.next()) {
+                        entry.password = Some(password.to_string());
+                    }
+                }
+                "account" => {
+                    tokens.next();
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(entry) = current.take() {
+            credentials.add_entry(entry);
+        }
+
+        credentials
+    }
+
+    fn add_entry(&mut self, entry: NetrcEntry) {
+        if let (Some(host), Some(login), Some(password)) = (entry.host, entry.login, entry.password)
+        {
+            self.machines
+                .entry(host)
+                .or_insert(Login { login, password });
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.machines.is_empty()
+    }
+
+    fn authorization_for_url(&self, url: &Url) -> Option<HeaderValue> {
+        if url.scheme() != "https" {
+            return None;
+        }
+        url.host_str()
+            .and_then(|host| self.authorization_for_host(host))
+    }
+
+    fn authorization_for_host(&self, host: &str) -> Option<HeaderValue> {
+        self.machines
+            .get(&host.to_ascii_lowercase())
+            .and_then(|login| {
+                let encoded = STANDARD.encode(format!("{}:{}", login.login, login.password));
+                let mut header_value = HeaderValue::from_str(&format!("Basic {encoded}")).ok()?;
+                header_value.set_sensitive(true);
+                Some(header_value)
+            })
+    }
+}
+
+// Adds a basic `Authorization` header to requests sent to hosts with configured credentials. This
+// is registered on the shared HTTP client so that downloading release files, package indexes, and
+// packages from private repositories all use the same credentials.
+pub(crate) struct AuthenticationMiddleware {
+    credentials: Credentials,
+}
+
+impl AuthenticationMiddleware {
+    pub(crate) fn new(credentials: Credentials) -> Self {
+        Self { credentials }
+    }
+}
+
+#[async_trait]
+impl Middleware for AuthenticationMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        if !req.headers().contains_key(AUTHORIZATION) {
+            if let Some(header_value) = self.credentials.authorization_for_url(req.url()) {
+                req.headers_mut().insert(AUTHORIZATION, header_value);
+            }
+        }
+        next.run(req, extensions).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_netrc() {
+        let credentials = Credentials::parse_netrc(indoc! {"
+            machine repo.example.com
+              login deploy
+              password s3cr3t
+
+            macdef init
+            cd /pub
+
+            machine Other.Example.com login other password p@ss account ignored
+            default login anonymous password guest
+        "});
+        assert_eq!(
+            credentials.machines.get("repo.example.com"),
+            Some(&Login {
+                login: "deploy".to_string(),
+                password: "s3cr3t".to_string()
+            })
+        );
+        assert_eq!(
+            credentials.machines.get("other.example.com"),
+            Some(&Login {
+                login: "other".to_string(),
+                password: "p@ss".to_string()
+            })
+        );
+        assert_eq!(credentials.machines.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_netrc_ignores_default_entry() {
+        let credentials = Credentials::parse_netrc("default login anonymous password guest");
+        assert!(credentials.is_empty());
+        assert_eq!(
+            credentials.authorization_for_host("archive.ubuntu.com"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_netrc_ignores_incomplete_entries() {
+        let credentials = Credentials::parse_netrc("machine repo.example.com login deploy");
+        assert!(credentials.is_empty());
+    }
+
+    #[test]
+    fn test_authorization_for_host() {
+        let credentials =
+            Credentials::parse_netrc("machine repo.example.com login deploy password s3cr3t");
+        assert_eq!(
+            credentials
+                .authorization_for_host("REPO.example.com")
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "Basic ZGVwbG95OnMzY3IzdA=="
+        );
+        assert_eq!(
+            credentials.authorization_for_host("archive.ubuntu.com"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_authorization_for_url_requires_https() {
+        let credentials =
+            Credentials::parse_netrc("machine repo.example.com login deploy password s3cr3t");
+        assert!(credentials
+            .authorization_for_url(&Url::parse("https://repo.example.com/dists/stable").unwrap())
+            .is_some());
+        assert_eq!(
+            credentials.authorization_for_url(
+                &Url::parse("http://repo.example.com/dists/stable").unwrap()
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_from_env() {
+        let mut platform_env = Env::new();
+        platform_env.insert(
+            "BP_DEB_PACKAGES_NETRC",
+            "machine repo.example.com login deploy password s3cr3t",
+        );
+        let mut process_env = Env::new();
+        process_env.insert("HOME", "/does/not/exist");
+
+        assert!(!Credentials::from_env(&platform_env, &process_env).is_empty());
+        assert!(Credentials::from_env(&Env::new(), &process_env).is_empty());
+    }
+}
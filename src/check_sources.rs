@@ -0,0 +1,242 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::Stdout;
+use std::time::Duration;
+
+use bullet_stream::state::Bullet;
+use bullet_stream::Print;
+use reqwest::StatusCode;
+use reqwest_middleware::ClientWithMiddleware;
+use tokio::task::{JoinError, JoinSet};
+
+use crate::debian::{SignaturePolicy, Source};
+use crate::{BuildpackResult, DebianPackagesBuildpackError};
+
+const CHECK_SOURCE_TIMEOUT: Duration = Duration::from_secs(15);
+
+// Sends a HEAD request for the Release file of every configured source before the package index
+// is updated. This client should not retry requests so that an unreachable mirror is reported
+// quickly and all failures are grouped into a single error instead of failing on whichever
+// request gave up first.
+pub(crate) async fn check_sources(
+    client: &ClientWithMiddleware,
+    sources: &[Source],
+    log: Print<Bullet<Stdout>>,
+) -> BuildpackResult<Print<Bullet<Stdout>>> {
+    let timer = log
+        .bullet("Checking package sources")
+        .start_timer("Checking");
+
+    let mut check_source_handles = JoinSet::new();
+    for source in sources {
+        for suite in &source.suites {
+            check_source_handles.spawn(check_source(
+                client.clone(),
+                release_file_urls(source, suite),
+            ));
+        }
+    }
+
+    let mut unreachable_sources = vec![];
+    while let Some(check_source_handle) = check_source_handles.join_next().await {
+        if let Some(unreachable_source) =
+            check_source_handle.map_err(CheckSourcesError::TaskFailed)?
+        {
+            unreachable_sources.push(unreachable_source);
+        }
+    }
+
+    if !unreachable_sources.is_empty() {
+        unreachable_sources.sort_by(|a, b| a.url.cmp(&b.url));
+        Err(CheckSourcesError::UnreachableSources(unreachable_sources))?;
+    }
+
+    Ok(timer.done().done())
+}
+
+// The Release file is located through the repository layout of the source, the same as when the
+// package index is created. Unsigned repositories often only publish the Release file and signed
+// repositories without an InRelease file publish it with a detached signature instead.
+fn release_file_urls(source: &Source, suite: &str) -> Vec<String> {
+    let release_file_names = match source.signature_policy {
+        SignaturePolicy::Required => vec!["InRelease", "Release"],
+        SignaturePolicy::Optional | SignaturePolicy::Disabled => vec!["Release"],
+    };
+    let suite_url = source.repository().suite_url(suite);
+    release_file_names
+        .into_iter()
+        .map(|release_file_name| format!("{suite_url}/{release_file_name}"))
+        .collect()
+}
+
+// The next url is only checked when the previous one wasn't found. The first failure is reported.
+async fn check_source(
+    client: ClientWithMiddleware,
+    urls: Vec<String>,
+) -> Option<UnreachableSource> {
+    let mut first_unreachable_source = None;
+    for url in urls {
+        let unreachable_source = check_url(&client, url).await?;
+        let is_not_found =
+            unreachable_source.reason == UnreachableReason::HttpStatus(StatusCode::NOT_FOUND);
+        first_unreachable_source.get_or_insert(unreachable_source);
+        if !is_not_found {
+            break;
+        }
+    }
+    first_unreachable_source
+}
+
+async fn check_url(client: &ClientWithMiddleware, url: String) -> Option<UnreachableSource> {
+    let reason = match client.head(&url).timeout(CHECK_SOURCE_TIMEOUT).send().await {
+        // some servers don't allow HEAD requests but responding at all means the host is reachable
+        Ok(response)
+            if response.status().is_success()
+                || response.status() == StatusCode::METHOD_NOT_ALLOWED =>
+        {
+            return None;
+        }
+        Ok(response) => UnreachableReason::HttpStatus(response.status()),
+        Err(reqwest_middleware::Error::Reqwest(e)) => UnreachableReason::from(&e),
+        Err(reqwest_middleware::Error::Middleware(e)) => UnreachableReason::Other(e.to_string()),
+    };
+    Some(UnreachableSource { url, reason })
+}
+
+#[derive(Debug)]
+pub(crate) struct UnreachableSource {
+    pub(crate) url: String,
+    pub(crate) reason: UnreachableReason,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum UnreachableReason {
+    Dns(String),
+    Tls(String),
+    Connect(String),
+    Timeout,
+    HttpStatus(StatusCode),
+    Other(String),
+}
+
+impl From<&reqwest::Error> for UnreachableReason {
+    fn from(error: &reqwest::Error) -> Self {
+        let root_cause = root_cause(error);
+        if error.is_timeout() {
+            UnreachableReason::Timeout
+        } else if is_dns_error(error) {
+            UnreachableReason::Dns(root_cause)
+        } else if is_tls_error(error) {
+            UnreachableReason::Tls(root_cause)
+        } else if error.is_connect() {
+            UnreachableReason::Connect(root_cause)
+        } else {
+            UnreachableReason::Other(root_cause)
+        }
+    }
+}
+
+impl Display for UnreachableReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnreachableReason::Dns(e) => write!(f, "DNS lookup failed ({e})"),
+            UnreachableReason::Tls(e) => write!(f, "TLS handshake failed ({e})"),
+            UnreachableReason::Connect(e) => write!(f, "Connection failed ({e})"),
+            UnreachableReason::Timeout => {
+                write!(f, "Timed out after {}s", CHECK_SOURCE_TIMEOUT.as_secs())
+            }
+            UnreachableReason::HttpStatus(status) => write!(f, "HTTP status {status}"),
+            UnreachableReason::Other(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+// The reqwest error types don't expose which part of establishing the connection failed so the
+// messages from the underlying resolver and TLS errors are inspected instead.
+fn error_chain(error: &reqwest::Error) -> impl Iterator<Item = &(dyn Error + 'static)> {
+    std::iter::successors(Some(error as &(dyn Error + 'static)), |&e| e.source())
+}
+
+fn error_messages(error: &reqwest::Error) -> impl Iterator<Item = String> + '_ {
+    error_chain(error).map(|e| e.to_string().to_ascii_lowercase())
+}
+
+fn is_dns_error(error: &reqwest::Error) -> bool {
+    error_messages(error).any(|message| {
+        message.contains("dns error") || message.contains("failed to lookup address")
+    })
+}
+
+fn is_tls_error(error: &reqwest::Error) -> bool {
+    error_messages(error).any(|message| {
+        message.contains("certificate") || message.contains("tls") || message.contains("handshake")
+    })
+}
+
+fn root_cause(error: &reqwest::Error) -> String {
+    error_chain(error)
+        .last()
+        .map_or_else(|| error.to_string(), ToString::to_string)
+}
+
+#[derive(Debug)]
+pub(crate) enum CheckSourcesError {
+    TaskFailed(JoinError),
+    UnreachableSources(Vec<UnreachableSource>),
+}
+
+impl From<CheckSourcesError> for libcnb::Error<DebianPackagesBuildpackError> {
+    fn from(value: CheckSourcesError) -> Self {
+        Self::BuildpackError(DebianPackagesBuildpackError::CheckSources(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::debian::ArchitectureName;
+
+    use super::*;
+
+    #[test]
+    fn test_release_file_urls() {
+        let source = Source::new(
+            "http://archive.ubuntu.com/ubuntu",
+            ["noble-updates"],
+            ["main"],
+            "",
+            ArchitectureName::AMD_64,
+        );
+        assert_eq!(
+            release_file_urls(&source, "noble-updates"),
+            vec![
+                "http://archive.ubuntu.com/ubuntu/dists/noble-updates/InRelease",
+                "http://archive.ubuntu.com/ubuntu/dists/noble-updates/Release"
+            ]
+        );
+
+        let source = Source {
+            signature_policy: SignaturePolicy::Disabled,
+            ..source
+        };
+        assert_eq!(
+            release_file_urls(&source, "noble-updates"),
+            vec!["http://archive.ubuntu.com/ubuntu/dists/noble-updates/Release"]
+        );
+    }
+
+    #[test]
+    fn test_unreachable_reason_display() {
+        assert_eq!(
+            UnreachableReason::HttpStatus(StatusCode::NOT_FOUND).to_string(),
+            "HTTP status 404 Not Found"
+        );
+        assert_eq!(
+            UnreachableReason::Dns("failed to lookup address information".to_string()).to_string(),
+            "DNS lookup failed (failed to lookup address information)"
+        );
+        assert_eq!(
+            UnreachableReason::Timeout.to_string(),
+            "Timed out after 15s"
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use http::Extensions;
+use reqwest::{Client, Identity, Request, Response, Url};
+use reqwest_middleware::{Middleware, Next};
+
+use crate::config::CustomSource;
+use crate::debian::RepositoryUri;
+
+// Sends requests for sources configured with a client certificate through an HTTP client that
+// presents it to the repository (mutual TLS). reqwest only supports a single identity per client
+// so a client is built for each of these sources and this is registered as the last middleware
+// of the shared HTTP client to hand off matching requests to them. Any middleware registered
+// before it (e.g.; retries and authentication) still applies to these requests.
+pub(crate) struct ClientCertificateMiddleware {
+    clients: Vec<(RepositoryUri, Client)>,
+}
+
+impl ClientCertificateMiddleware {
+    pub(crate) fn new(
+        app_dir: &Path,
+        custom_sources: &[CustomSource],
+        new_client_builder: impl Fn() -> reqwest::ClientBuilder,
+    ) -> Result<Self, ClientCertificateError> {
+        let clients = custom_sources
+            .iter()
+            .filter_map(|source| {
+                source
+                    .client_identity
+                    .as_ref()
+                    .map(|client_identity| (&source.uri, client_identity))
+            })
+            .map(|(uri, client_identity)| {
+                let read_pem = |path: &PathBuf| {
+                    let path = app_dir.join(path);
+                    std::fs::read(&path)
+                        .map_err(|e| ClientCertificateError::ReadFile(uri.clone(), path, e))
+                };
+                let mut pem = read_pem(&client_identity.certificate)?;
+                pem.push(b'\n');
+                pem.extend(read_pem(&client_identity.key)?);
+
+                let client = Identity::from_pem(&pem)
+                    .and_then(|identity| new_client_builder().identity(identity).build())
+                    .map_err(|e| ClientCertificateError::InvalidIdentity(uri.clone(), e))?;
+                Ok((uri.clone(), client))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { clients })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    pub(crate) fn sources(&self) -> impl Iterator<Item = &RepositoryUri> {
+        self.clients.iter().map(|(uri, _)| uri)
+    }
+
+    fn client_for(&self, url: &Url) -> Option<&Client> {
+        self.clients
+            .iter()
+            .find(|(uri, _)| {
+                url.as_str()
+                    .strip_prefix(uri.as_str())
+                    .is_some_and(|path| path.is_empty() || path.starts_with('/'))
+            })
+            .map(|(_, client)| client)
+    }
+}
+
+#[async_trait]
+impl Middleware for ClientCertificateMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        match self.client_for(req.url()) {
+            Some(client) => client
+                .execute(req)
+                .await
+                .map_err(reqwest_middleware::Error::from),
+            None => next.run(req, extensions).await,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum ClientCertificateError {
+    ReadFile(RepositoryUri, PathBuf, std::io::Error),
+    InvalidIdentity(RepositoryUri, reqwest::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::config::ClientIdentity;
+    use crate::debian::SignaturePolicy;
+
+    use super::*;
+
+    fn custom_source(uri: &str, client_identity: Option<ClientIdentity>) -> CustomSource {
+        CustomSource {
+            uri: RepositoryUri::from(uri),
+            suites: vec!["noble".to_string()],
+            components: vec!["main".to_string()],
+            signed_by: None,
+            signed_by_file: None,
+            key_fingerprint: None,
+            signature_policy: SignaturePolicy::Disabled,
+            trusted: false,
+            allow_weak_hashes: false,
+            client_identity,
+            mirrors: vec![],
+            download_url: None,
+            architectures: vec![],
+        }
+    }
+
+    fn client_identity() -> ClientIdentity {
+        ClientIdentity {
+            certificate: PathBuf::from("client.crt"),
+            key: PathBuf::from("client.key"),
+        }
+    }
+
+    #[test]
+    fn test_client_for() {
+        let middleware = ClientCertificateMiddleware {
+            clients: vec![(
+                RepositoryUri::from("https://repo.example.com/ubuntu"),
+                Client::new(),
+            )],
+        };
+        for (url, expected) in [
+            (
+                "https://repo.example.com/ubuntu/dists/noble/InRelease",
+                true,
+            ),
+            ("https://repo.example.com/ubuntu", true),
+            (
+                "https://repo.example.com/ubuntu-ports/dists/noble/InRelease",
+                false,
+            ),
+            (
+                "http://archive.ubuntu.com/ubuntu/dists/noble/InRelease",
+                false,
+            ),
+        ] {
+            assert_eq!(
+                middleware
+                    .client_for(&Url::from_str(url).unwrap())
+                    .is_some(),
+                expected,
+                "{url}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_new_without_client_identities() {
+        let middleware = ClientCertificateMiddleware::new(
+            Path::new("/app"),
+            &[custom_source("https://repo.example.com/ubuntu", None)],
+            Client::builder,
+        )
+        .unwrap();
+        assert!(middleware.is_empty());
+    }
+
+    #[test]
+    fn test_new_with_missing_certificate() {
+        let app_dir = tempfile::tempdir().unwrap();
+        let error = ClientCertificateMiddleware::new(
+            app_dir.path(),
+            &[custom_source(
+                "https://repo.example.com/ubuntu",
+                Some(client_identity()),
+            )],
+            Client::builder,
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(
+            error,
+            ClientCertificateError::ReadFile(_, path, _) if path == app_dir.path().join("client.crt")
+        ));
+    }
+
+    #[test]
+    fn test_new_with_invalid_identity() {
+        let app_dir = tempfile::tempdir().unwrap();
+        std::fs::write(app_dir.path().join("client.crt"), "not a certificate").unwrap();
+        std::fs::write(app_dir.path().join("client.key"), "not a key").unwrap();
+        let error = ClientCertificateMiddleware::new(
+            app_dir.path(),
+            &[custom_source(
+                "https://repo.example.com/ubuntu",
+                Some(client_identity()),
+            )],
+            Client::builder,
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(error, ClientCertificateError::InvalidIdentity(..)));
+    }
+}
@@ -1,16 +1,71 @@
+use std::collections::HashSet;
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
 use std::str::FromStr;
 
-use indexmap::IndexSet;
-use toml_edit::{DocumentMut, TableLike};
+use indexmap::{IndexMap, IndexSet};
+use libcnb::data::buildpack_plan::BuildpackPlan;
+use libcnb::Env;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use toml_edit::{DocumentMut, Item, TableLike, Value};
 
-use crate::config::{ParseRequestedPackageError, RequestedPackage};
+use crate::client_certificates::ClientCertificateError;
+use crate::config::{
+    find_deprecations, parse_deb822_sources, CustomSource, Deprecation, EnvScope,
+    ExistingFilesPolicy, InvalidProxyError, Locale, NetworkSettings, PackageGlob,
+    ParseCustomSourceError, ParseEnvScopeError, ParseExistingFilesPolicyError, ParseLocaleError,
+    ParseNetworkSettingsError, ParsePackageGlobError, ParsePhasedUpdatesPolicyError, ParsePpaError,
+    ParsePresetError, ParseRequestedPackageError, ParseSnapshotError, PhasedUpdatesPolicy, Ppa,
+    Preset, RequestedPackage, RequestedPackagePattern, Snapshot, DEPRECATIONS,
+};
+use crate::debian::{
+    DistroCodename, PackageName, ParsePackageNameError, RepositoryUri,
+    UnsupportedTargetCodenameError,
+};
+use crate::pgp::armor_signing_key;
 use crate::{BuildpackResult, DebianPackagesBuildpackError};
 
+// Packages can also be listed in this environment variable (e.g.; `ffmpeg, libvips-tools`) for
+// applications that don't have a project.toml. They're added to any packages configured there.
+pub(crate) const INSTALL_ENV_VAR: &str = "BP_DEB_PACKAGES_INSTALL";
+
 #[derive(Debug, Default, Eq, PartialEq)]
 pub(crate) struct BuildpackConfig {
     pub(crate) install: IndexSet<RequestedPackage>,
+    // `install` entries with wildcards which are expanded once the package index is built
+    pub(crate) install_patterns: IndexSet<RequestedPackagePattern>,
+    pub(crate) tasks: Vec<String>,
+    pub(crate) presets: Vec<Preset>,
+    pub(crate) exclude: IndexSet<PackageGlob>,
+    pub(crate) max_dependencies: Option<usize>,
+    pub(crate) source_priority: Vec<RepositoryUri>,
+    pub(crate) env_scope: EnvScope,
+    pub(crate) sources: Vec<CustomSource>,
+    pub(crate) sources_file: Option<PathBuf>,
+    pub(crate) ppas: Vec<Ppa>,
+    pub(crate) network: NetworkSettings,
+    pub(crate) snapshot: Option<Snapshot>,
+    pub(crate) components: Vec<String>,
+    pub(crate) backports: Option<bool>,
+    pub(crate) proposed: Option<bool>,
+    pub(crate) check_release_dates: Option<bool>,
+    pub(crate) package_index_cache_days: Option<u64>,
+    pub(crate) resolve_virtual_packages: Option<bool>,
+    pub(crate) phased_updates: PhasedUpdatesPolicy,
+    pub(crate) strict_extraction: Option<bool>,
+    pub(crate) existing_files: ExistingFilesPolicy,
+    pub(crate) subset_package_index: Option<bool>,
+    pub(crate) verify_layout: Option<bool>,
+    pub(crate) self_check: Option<bool>,
+    pub(crate) provenance: Option<bool>,
+    pub(crate) locales: Vec<Locale>,
+    pub(crate) imagemagick_policy: Option<PathBuf>,
+    pub(crate) remove: IndexSet<PackageName>,
+    // the release the run image is built from when it differs from the build image
+    pub(crate) target_codename: Option<DistroCodename>,
+    pub(crate) deprecations: Vec<Deprecation>,
 }
 
 impl BuildpackConfig {
@@ -20,6 +75,140 @@ impl BuildpackConfig {
             .try_exists()
             .map_err(|e| ConfigError::CheckExists(config_file.as_ref().to_path_buf(), e))?)
     }
+
+    pub(crate) fn has_install_env(env: &Env) -> bool {
+        env.get(INSTALL_ENV_VAR)
+            .is_some_and(|value| !value.to_string_lossy().trim().is_empty())
+    }
+
+    pub(crate) fn load(config_file: impl AsRef<Path>, env: &Env) -> BuildpackResult<Self> {
+        let mut config = if BuildpackConfig::exists(config_file.as_ref())? {
+            BuildpackConfig::try_from(config_file.as_ref().to_path_buf())?
+        } else {
+            BuildpackConfig::default()
+        };
+        config.merge_sources_file(config_file.as_ref())?;
+        config.load_signing_key_files(config_file.as_ref())?;
+        config.merge_install_env(env)?;
+        config
+            .network
+            .merge_env(env)
+            .map_err(ConfigError::ParseNetworkEnv)?;
+        Ok(config)
+    }
+
+    // The sources file is relative to the app directory since that's where project.toml lives.
+    fn merge_sources_file(&mut self, config_file: &Path) -> Result<(), ConfigError> {
+        let Some(sources_file) = &self.sources_file else {
+            return Ok(());
+        };
+        let sources_file = config_file.parent().map_or_else(
+            || sources_file.clone(),
+            |app_dir| app_dir.join(sources_file),
+        );
+        let contents = fs::read_to_string(&sources_file)
+            .map_err(|e| ConfigError::ReadSourcesFile(sources_file.clone(), e))?;
+        self.sources.extend(
+            parse_deb822_sources(&contents)
+                .map_err(|e| ConfigError::ParseSourcesFile(sources_file, e))?,
+        );
+        Ok(())
+    }
+
+    // As with the sources file, signing key files are relative to the app directory. The key is
+    // used for that source's Release file the same as a `signed_by` value.
+    fn load_signing_key_files(&mut self, config_file: &Path) -> Result<(), ConfigError> {
+        for source in &mut self.sources {
+            let Some(signed_by_file) = &source.signed_by_file else {
+                continue;
+            };
+            let signed_by_file = config_file.parent().map_or_else(
+                || signed_by_file.clone(),
+                |app_dir| app_dir.join(signed_by_file),
+            );
+            let contents = fs::read(&signed_by_file)
+                .map_err(|e| ConfigError::ReadSigningKeyFile(signed_by_file.clone(), e))?;
+            source.signed_by = Some(
+                armor_signing_key(&contents)
+                    .map_err(|e| ConfigError::InvalidSigningKeyFile(signed_by_file, e))?,
+            );
+        }
+        Ok(())
+    }
+
+    // Packages already configured in project.toml keep their options (e.g.; `force`).
+    fn merge_install_env(&mut self, env: &Env) -> Result<(), ConfigError> {
+        let Some(value) = env.get(INSTALL_ENV_VAR) else {
+            return Ok(());
+        };
+
+        for package_name in value
+            .to_string_lossy()
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|package_name| !package_name.is_empty())
+        {
+            self.add_package(
+                PackageName::from_str(package_name).map_err(ConfigError::ParseInstallEnv)?,
+            );
+        }
+
+        Ok(())
+    }
+
+    // Other buildpacks can request packages by requiring `deb-packages` in the build plan with
+    // metadata like `packages = ["libgdal-dev"]`. As with the environment variable, packages
+    // already configured keep their options.
+    pub(crate) fn merge_build_plan(
+        &mut self,
+        buildpack_plan: &BuildpackPlan,
+        build_plan_name: &str,
+    ) -> Result<(), ConfigError> {
+        for entry in buildpack_plan
+            .entries
+            .iter()
+            .filter(|entry| entry.name == build_plan_name)
+        {
+            let metadata = entry
+                .metadata::<BuildPlanMetadata>()
+                .map_err(ConfigError::ParseBuildPlan)?;
+            for package_name in metadata.packages {
+                self.add_package(
+                    PackageName::from_str(&package_name)
+                        .map_err(ConfigError::ParseBuildPlanPackage)?,
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_package(&mut self, name: PackageName) {
+        if !self.install.iter().any(|installed| installed.name == name) {
+            self.install.insert(RequestedPackage {
+                name,
+                architecture: None,
+                skip_dependencies: false,
+                force: false,
+                env_scope: None,
+                optional: false,
+                maintainer_scripts: None,
+                version: None,
+            });
+        }
+    }
+
+    // An application configured only through the environment has no project.toml to hash.
+    pub(crate) fn sha256(config_file: impl AsRef<Path>) -> BuildpackResult<String> {
+        let contents = match fs::read(config_file.as_ref()) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => vec![],
+            Err(e) => Err(ConfigError::ReadConfig(
+                config_file.as_ref().to_path_buf(),
+                e,
+            ))?,
+        };
+        Ok(format!("{:x}", Sha256::digest(contents)))
+    }
 }
 
 impl TryFrom<PathBuf> for BuildpackConfig {
@@ -38,7 +227,8 @@ impl FromStr for BuildpackConfig {
     type Err = ParseConfigError;
 
     fn from_str(contents: &str) -> Result<Self, Self::Err> {
-        let doc = DocumentMut::from_str(contents).map_err(Self::Err::InvalidToml)?;
+        let doc =
+            DocumentMut::from_str(contents).map_err(|e| Self::Err::InvalidToml(Box::new(e)))?;
 
         // the root config is the table named `[com.heroku.buildpacks.deb-packages]` in project.toml
         let root_config_item = doc
@@ -63,20 +253,531 @@ impl FromStr for BuildpackConfig {
 impl TryFrom<&dyn TableLike> for BuildpackConfig {
     type Error = ParseConfigError;
 
+    #[allow(clippy::too_many_lines)]
     fn try_from(config_item: &dyn TableLike) -> Result<Self, Self::Error> {
-        let mut install = IndexSet::new();
+        let package_groups = match config_item.get("groups") {
+            Some(item) => parse_package_groups(item)?,
+            None => IndexMap::new(),
+        };
+
+        // each requested package along with whether it was added by a group
+        let mut requested_packages = vec![];
+        let mut install_patterns = IndexSet::new();
 
         if let Some(install_values) = config_item.get("install").and_then(|item| item.as_array()) {
             for install_value in install_values {
-                install.insert(
-                    RequestedPackage::try_from(install_value)
-                        .map_err(Self::Error::ParseRequestedPackage)?,
+                if let Some(group) = group_reference(install_value) {
+                    let package_group = package_groups.get(group).ok_or_else(|| {
+                        Self::Error::UnknownPackageGroup {
+                            group: group.to_string(),
+                            available_groups: package_groups.keys().cloned().collect(),
+                        }
+                    })?;
+                    requested_packages.extend(
+                        package_group
+                            .packages
+                            .iter()
+                            .map(|requested_package| (requested_package.clone(), true)),
+                    );
+                    install_patterns.extend(package_group.patterns.iter().cloned());
+                } else {
+                    let mut entry = PackageGroup::default();
+                    entry.add(install_value)?;
+                    requested_packages.extend(
+                        entry
+                            .packages
+                            .into_iter()
+                            .map(|requested_package| (requested_package, false)),
+                    );
+                    install_patterns.extend(entry.patterns);
+                }
+            }
+        }
+        let install = merge_group_packages(requested_packages);
+
+        let tasks = match config_item.get("tasks") {
+            Some(item) => parse_tasks(item)?,
+            None => vec![],
+        };
+
+        let presets = match config_item.get("preset") {
+            Some(item) => parse_presets(item)?,
+            None => vec![],
+        };
+
+        let mut exclude = IndexSet::new();
+
+        if let Some(item) = config_item.get("exclude") {
+            let exclude_values = item
+                .as_array()
+                .ok_or_else(|| Self::Error::InvalidExclude(item.to_string()))?;
+            for exclude_value in exclude_values {
+                exclude.insert(
+                    PackageGlob::try_from(exclude_value).map_err(Self::Error::ParseExclude)?,
                 );
             }
         }
 
-        Ok(BuildpackConfig { install })
+        let max_dependencies = match config_item.get("max_dependencies") {
+            Some(item) => Some(
+                item.as_integer()
+                    .and_then(|value| usize::try_from(value).ok())
+                    .ok_or_else(|| Self::Error::InvalidMaxDependencies(item.to_string()))?,
+            ),
+            None => None,
+        };
+
+        let source_priority = match config_item.get("source_priority") {
+            Some(item) => parse_source_priority(item)?,
+            None => vec![],
+        };
+
+        let env_scope = match config_item.get("env_scope") {
+            Some(item) => item
+                .as_str()
+                .ok_or_else(|| ParseEnvScopeError(item.to_string()))
+                .and_then(EnvScope::from_str)
+                .map_err(Self::Error::InvalidEnvScope)?,
+            None => EnvScope::default(),
+        };
+
+        let sources = match config_item.get("sources") {
+            Some(item) => CustomSource::parse_all(item).map_err(Self::Error::ParseSource)?,
+            None => vec![],
+        };
+
+        let sources_file = match config_item.get("sources_file") {
+            Some(item) => Some(
+                item.as_str()
+                    .filter(|value| !value.trim().is_empty())
+                    .map(PathBuf::from)
+                    .ok_or_else(|| Self::Error::InvalidSourcesFile(item.to_string()))?,
+            ),
+            None => None,
+        };
+
+        let ppas = match config_item.get("ppas") {
+            Some(item) => parse_ppas(item).map_err(Self::Error::ParsePpa)?,
+            None => vec![],
+        };
+
+        let network = match config_item.get("network") {
+            Some(item) => item
+                .as_table_like()
+                .ok_or_else(|| ParseNetworkSettingsError::UnexpectedTomlValue(item.to_string()))
+                .and_then(NetworkSettings::try_from)
+                .map_err(Self::Error::ParseNetwork)?,
+            None => NetworkSettings::default(),
+        };
+
+        let snapshot = match config_item.get("snapshot") {
+            Some(item) => Some(
+                item.as_str()
+                    .ok_or_else(|| ParseSnapshotError(item.to_string()))
+                    .and_then(Snapshot::from_str)
+                    .map_err(Self::Error::InvalidSnapshot)?,
+            ),
+            None => None,
+        };
+
+        let components = match config_item.get("components") {
+            Some(item) => parse_components(item)?,
+            None => vec![],
+        };
+
+        let backports = match config_item.get("backports") {
+            Some(item) => Some(
+                item.as_bool()
+                    .ok_or_else(|| Self::Error::InvalidBackports(item.to_string()))?,
+            ),
+            None => None,
+        };
+
+        let proposed = match config_item.get("proposed") {
+            Some(item) => Some(
+                item.as_bool()
+                    .ok_or_else(|| Self::Error::InvalidProposed(item.to_string()))?,
+            ),
+            None => None,
+        };
+
+        let check_release_dates = match config_item.get("check_release_dates") {
+            Some(item) => Some(
+                item.as_bool()
+                    .ok_or_else(|| Self::Error::InvalidCheckReleaseDates(item.to_string()))?,
+            ),
+            None => None,
+        };
+
+        let package_index_cache_days = match config_item.get("package_index_cache_days") {
+            Some(item) => Some(
+                item.as_integer()
+                    .and_then(|value| u64::try_from(value).ok())
+                    .ok_or_else(|| Self::Error::InvalidPackageIndexCacheDays(item.to_string()))?,
+            ),
+            None => None,
+        };
+
+        let resolve_virtual_packages = match config_item.get("resolve_virtual_packages") {
+            Some(item) => Some(
+                item.as_bool()
+                    .ok_or_else(|| Self::Error::InvalidResolveVirtualPackages(item.to_string()))?,
+            ),
+            None => None,
+        };
+
+        let strict_extraction = match config_item.get("strict_extraction") {
+            Some(item) => Some(
+                item.as_bool()
+                    .ok_or_else(|| Self::Error::InvalidStrictExtraction(item.to_string()))?,
+            ),
+            None => None,
+        };
+
+        let phased_updates = match config_item.get("phased_updates") {
+            Some(item) => item
+                .as_str()
+                .ok_or_else(|| ParsePhasedUpdatesPolicyError(item.to_string()))
+                .and_then(PhasedUpdatesPolicy::from_str)
+                .map_err(Self::Error::InvalidPhasedUpdates)?,
+            None => PhasedUpdatesPolicy::default(),
+        };
+
+        let existing_files = match config_item.get("existing_files") {
+            Some(item) => item
+                .as_str()
+                .ok_or_else(|| ParseExistingFilesPolicyError(item.to_string()))
+                .and_then(ExistingFilesPolicy::from_str)
+                .map_err(Self::Error::InvalidExistingFiles)?,
+            None => ExistingFilesPolicy::default(),
+        };
+
+        let subset_package_index = match config_item.get("subset_package_index") {
+            Some(item) => Some(
+                item.as_bool()
+                    .ok_or_else(|| Self::Error::InvalidSubsetPackageIndex(item.to_string()))?,
+            ),
+            None => None,
+        };
+
+        // a debugging option that checks the installed layer for signs of an extraction bug
+        let verify_layout = match config_item.get("verify_layout") {
+            Some(item) => Some(
+                item.as_bool()
+                    .ok_or_else(|| Self::Error::InvalidVerifyLayout(item.to_string()))?,
+            ),
+            None => None,
+        };
+
+        let self_check = match config_item.get("self_check") {
+            Some(item) => Some(
+                item.as_bool()
+                    .ok_or_else(|| Self::Error::InvalidSelfCheck(item.to_string()))?,
+            ),
+            None => None,
+        };
+
+        let provenance = match config_item.get("provenance") {
+            Some(item) => Some(
+                item.as_bool()
+                    .ok_or_else(|| Self::Error::InvalidProvenance(item.to_string()))?,
+            ),
+            None => None,
+        };
+
+        let locales = match config_item.get("locales") {
+            Some(item) => parse_locales(item)?,
+            None => vec![],
+        };
+
+        let imagemagick_policy = config_item
+            .get("imagemagick_policy")
+            .map(parse_imagemagick_policy)
+            .transpose()?;
+
+        let remove = config_item
+            .get("remove")
+            .map(parse_remove)
+            .transpose()?
+            .unwrap_or_default();
+
+        let target_codename = config_item
+            .get("target_codename")
+            .map(parse_target_codename)
+            .transpose()?;
+
+        Ok(BuildpackConfig {
+            install,
+            install_patterns,
+            tasks,
+            presets,
+            exclude,
+            max_dependencies,
+            source_priority,
+            env_scope,
+            sources,
+            sources_file,
+            ppas,
+            network,
+            snapshot,
+            components,
+            backports,
+            proposed,
+            check_release_dates,
+            package_index_cache_days,
+            resolve_virtual_packages,
+            phased_updates,
+            strict_extraction,
+            existing_files,
+            subset_package_index,
+            verify_layout,
+            self_check,
+            provenance,
+            locales,
+            imagemagick_policy,
+            remove,
+            target_codename,
+            deprecations: find_deprecations(config_item, DEPRECATIONS),
+        })
+    }
+}
+
+fn parse_tasks(item: &Item) -> Result<Vec<String>, ParseConfigError> {
+    let task_values = item
+        .as_array()
+        .ok_or_else(|| ParseConfigError::InvalidTasks(item.to_string()))?;
+    let mut tasks = vec![];
+    for task_value in task_values {
+        let task = task_value
+            .as_str()
+            .map(str::trim)
+            .filter(|task| !task.is_empty())
+            .ok_or_else(|| ParseConfigError::InvalidTasks(task_value.to_string()))?;
+        if !tasks.iter().any(|other| other == task) {
+            tasks.push(task.to_string());
+        }
+    }
+    Ok(tasks)
+}
+
+// The entries of an `install` list which are split into the packages it names and the patterns
+// that are expanded once the package index is built.
+#[derive(Debug, Default)]
+struct PackageGroup {
+    packages: IndexSet<RequestedPackage>,
+    patterns: IndexSet<RequestedPackagePattern>,
+}
+
+impl PackageGroup {
+    fn add(&mut self, value: &Value) -> Result<(), ParseConfigError> {
+        if RequestedPackagePattern::is_pattern(value) {
+            self.patterns.insert(
+                RequestedPackagePattern::try_from(value)
+                    .map_err(ParseConfigError::ParseRequestedPackage)?,
+            );
+        } else {
+            self.packages.insert(
+                RequestedPackage::try_from(value)
+                    .map_err(ParseConfigError::ParseRequestedPackage)?,
+            );
+        }
+        Ok(())
+    }
+}
+
+// Groups are named lists of `install` entries (e.g.; `media = ["ffmpeg", "libvips-tools"]`) that
+// are requested in `install` with an `@` before their name (e.g.; `"@media"`) so the same
+// packages can be shared by several applications. Every group is parsed even when it isn't used
+// so a shared group with an invalid entry fails every build that includes it. Groups can't
+// reference other groups.
+fn parse_package_groups(item: &Item) -> Result<IndexMap<String, PackageGroup>, ParseConfigError> {
+    let groups_table = item
+        .as_table_like()
+        .ok_or_else(|| ParseConfigError::InvalidPackageGroup("groups".to_string()))?;
+    let mut package_groups = IndexMap::new();
+    for (name, group_item) in groups_table.iter() {
+        let group_values = group_item
+            .as_array()
+            .ok_or_else(|| ParseConfigError::InvalidPackageGroup(name.to_string()))?;
+        let mut package_group = PackageGroup::default();
+        for group_value in group_values {
+            if let Some(referenced_group) = group_reference(group_value) {
+                Err(ParseConfigError::NestedPackageGroup {
+                    group: name.to_string(),
+                    referenced_group: referenced_group.to_string(),
+                })?;
+            }
+            package_group.add(group_value)?;
+        }
+        package_groups.insert(name.to_string(), package_group);
+    }
+    Ok(package_groups)
+}
+
+// The name of the group an `install` entry like `"@media"` refers to.
+fn group_reference(value: &Value) -> Option<&str> {
+    value.as_str().and_then(|value| value.strip_prefix('@'))
+}
+
+// A package that's listed in `install` as well as in a referenced group (e.g.; to set `force` on
+// one package of a shared group) keeps the options from `install`. When more than one group lists
+// the same package, the first entry is kept.
+fn merge_group_packages(
+    requested_packages: Vec<(RequestedPackage, bool)>,
+) -> IndexSet<RequestedPackage> {
+    let listed_names = requested_packages
+        .iter()
+        .filter(|(_, from_group)| !from_group)
+        .map(|(requested_package, _)| requested_package.qualified_name())
+        .collect::<HashSet<_>>();
+    let mut grouped_names = HashSet::new();
+    requested_packages
+        .into_iter()
+        .filter(|(requested_package, from_group)| {
+            let name = requested_package.qualified_name();
+            !from_group || (!listed_names.contains(&name) && grouped_names.insert(name))
+        })
+        .map(|(requested_package, _)| requested_package)
+        .collect()
+}
+
+// A single preset can be given as a string (e.g.; `preset = "geo"`) and several as an array.
+fn parse_presets(item: &Item) -> Result<Vec<Preset>, ParseConfigError> {
+    let preset_values = match item.as_value() {
+        Some(value @ Value::String(_)) => vec![value],
+        _ => item
+            .as_array()
+            .ok_or_else(|| ParseConfigError::InvalidPreset(ParsePresetError(item.to_string())))?
+            .iter()
+            .collect(),
+    };
+    let mut presets = vec![];
+    for preset_value in preset_values {
+        let preset = preset_value
+            .as_str()
+            .ok_or_else(|| ParsePresetError(preset_value.to_string()))
+            .and_then(|preset| Preset::from_str(preset.trim()))
+            .map_err(ParseConfigError::InvalidPreset)?;
+        if !presets.contains(&preset) {
+            presets.push(preset);
+        }
+    }
+    Ok(presets)
+}
+
+// Components are part of the package index paths in the Release file so they can't contain
+// whitespace (e.g.; `multiverse` or `non-free-firmware`).
+fn parse_components(item: &Item) -> Result<Vec<String>, ParseConfigError> {
+    let component_values = item
+        .as_array()
+        .filter(|component_values| !component_values.is_empty())
+        .ok_or_else(|| ParseConfigError::InvalidComponents(item.to_string()))?;
+    let mut components = vec![];
+    for component_value in component_values {
+        let component = component_value
+            .as_str()
+            .map(str::trim)
+            .filter(|component| !component.is_empty() && !component.contains(char::is_whitespace))
+            .ok_or_else(|| ParseConfigError::InvalidComponents(component_value.to_string()))?;
+        if !components.iter().any(|other| other == component) {
+            components.push(component.to_string());
+        }
+    }
+    Ok(components)
+}
+
+fn parse_ppas(item: &Item) -> Result<Vec<Ppa>, ParsePpaError> {
+    let ppa_values = item
+        .as_array()
+        .ok_or_else(|| ParsePpaError(item.to_string()))?;
+    let mut ppas = vec![];
+    for ppa_value in ppa_values {
+        let ppa = ppa_value
+            .as_str()
+            .ok_or_else(|| ParsePpaError(ppa_value.to_string()))
+            .and_then(|value| Ppa::from_str(value.trim()))?;
+        if !ppas.contains(&ppa) {
+            ppas.push(ppa);
+        }
+    }
+    Ok(ppas)
+}
+
+fn parse_source_priority(item: &Item) -> Result<Vec<RepositoryUri>, ParseConfigError> {
+    let source_priority_values = item
+        .as_array()
+        .ok_or_else(|| ParseConfigError::InvalidSourcePriority(item.to_string()))?;
+    let mut source_priority = vec![];
+    for source_priority_value in source_priority_values {
+        let repository_uri = source_priority_value
+            .as_str()
+            .map(|uri| RepositoryUri::from(uri.trim_end_matches('/')))
+            .ok_or_else(|| {
+                ParseConfigError::InvalidSourcePriority(source_priority_value.to_string())
+            })?;
+        source_priority.push(repository_uri);
+    }
+    Ok(source_priority)
+}
+
+// Locales are deduplicated but keep their configured order so they're generated and reported in
+// the order the user listed them.
+fn parse_locales(item: &Item) -> Result<Vec<Locale>, ParseConfigError> {
+    let locale_values = item
+        .as_array()
+        .ok_or_else(|| ParseConfigError::InvalidLocales(item.to_string()))?;
+    let mut locales = vec![];
+    for locale_value in locale_values {
+        let locale = Locale::try_from(locale_value).map_err(ParseConfigError::ParseLocale)?;
+        if !locales.contains(&locale) {
+            locales.push(locale);
+        }
     }
+    Ok(locales)
+}
+
+// Relative to the app directory since that's where project.toml lives. Paths that could point
+// outside of it (e.g.; `/etc/passwd` or `../policy.xml`) are rejected.
+fn parse_imagemagick_policy(item: &Item) -> Result<PathBuf, ParseConfigError> {
+    item.as_str()
+        .filter(|value| !value.trim().is_empty())
+        .map(PathBuf::from)
+        .filter(|path| {
+            path.components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        })
+        .ok_or_else(|| ParseConfigError::InvalidImageMagickPolicy(item.to_string()))
+}
+
+// Codenames are a single lowercase word (e.g.; `jammy` or `bookworm`). Whether the buildpack has
+// sources for it is checked against the supported distributions once the build image is detected.
+fn parse_target_codename(item: &Item) -> Result<DistroCodename, ParseConfigError> {
+    item.as_str()
+        .map(str::trim)
+        .filter(|value| !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric()))
+        .map(DistroCodename::from)
+        .ok_or_else(|| ParseConfigError::InvalidTargetCodename(item.to_string()))
+}
+
+fn parse_remove(item: &Item) -> Result<IndexSet<PackageName>, ParseConfigError> {
+    let remove_values = item
+        .as_array()
+        .ok_or_else(|| ParseConfigError::InvalidRemove(item.to_string()))?;
+    remove_values
+        .iter()
+        .map(|remove_value| {
+            remove_value
+                .as_str()
+                .and_then(|package_name| PackageName::from_str(package_name).ok())
+                .ok_or_else(|| ParseConfigError::InvalidRemove(remove_value.to_string()))
+        })
+        .collect()
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct BuildPlanMetadata {
+    #[serde(default)]
+    packages: Vec<String>,
 }
 
 #[derive(Debug)]
@@ -84,13 +785,64 @@ pub(crate) enum ConfigError {
     CheckExists(PathBuf, std::io::Error),
     ReadConfig(PathBuf, std::io::Error),
     ParseConfig(PathBuf, ParseConfigError),
+    RemovedOptions(PathBuf, Vec<Deprecation>),
+    ParseInstallEnv(ParsePackageNameError),
+    ParseBuildPlan(toml::de::Error),
+    ParseBuildPlanPackage(ParsePackageNameError),
+    InvalidProxy(InvalidProxyError),
+    ClientCertificate(ClientCertificateError),
+    ReadSourcesFile(PathBuf, std::io::Error),
+    ParseSourcesFile(PathBuf, ParseCustomSourceError),
+    ReadSigningKeyFile(PathBuf, std::io::Error),
+    InvalidSigningKeyFile(PathBuf, anyhow::Error),
+    ParseNetworkEnv(ParseNetworkSettingsError),
+    UnsupportedTargetCodename(UnsupportedTargetCodenameError),
 }
 
 #[derive(Debug)]
 pub(crate) enum ParseConfigError {
-    InvalidToml(toml_edit::TomlError),
+    InvalidToml(Box<toml_edit::TomlError>),
     WrongConfigType,
     ParseRequestedPackage(ParseRequestedPackageError),
+    InvalidTasks(String),
+    InvalidPreset(ParsePresetError),
+    InvalidPackageGroup(String),
+    NestedPackageGroup {
+        group: String,
+        referenced_group: String,
+    },
+    UnknownPackageGroup {
+        group: String,
+        available_groups: Vec<String>,
+    },
+    ParseExclude(ParsePackageGlobError),
+    InvalidExclude(String),
+    InvalidMaxDependencies(String),
+    InvalidSourcePriority(String),
+    InvalidEnvScope(ParseEnvScopeError),
+    ParseSource(ParseCustomSourceError),
+    InvalidSourcesFile(String),
+    ParsePpa(ParsePpaError),
+    ParseNetwork(ParseNetworkSettingsError),
+    InvalidSnapshot(ParseSnapshotError),
+    InvalidComponents(String),
+    InvalidBackports(String),
+    InvalidProposed(String),
+    InvalidCheckReleaseDates(String),
+    InvalidPackageIndexCacheDays(String),
+    InvalidResolveVirtualPackages(String),
+    InvalidPhasedUpdates(ParsePhasedUpdatesPolicyError),
+    InvalidStrictExtraction(String),
+    InvalidExistingFiles(ParseExistingFilesPolicyError),
+    InvalidSubsetPackageIndex(String),
+    InvalidVerifyLayout(String),
+    InvalidSelfCheck(String),
+    InvalidProvenance(String),
+    InvalidLocales(String),
+    ParseLocale(ParseLocaleError),
+    InvalidImageMagickPolicy(String),
+    InvalidRemove(String),
+    InvalidTargetCodename(String),
 }
 
 impl From<ConfigError> for DebianPackagesBuildpackError {
@@ -107,9 +859,12 @@ impl From<ConfigError> for libcnb::Error<DebianPackagesBuildpackError> {
 
 #[cfg(test)]
 mod test {
+    use crate::config::{MAX_RETRIES_ENV_VAR, TIMEOUT_ENV_VAR};
     use crate::debian::PackageName;
     use indexmap::IndexSet;
+    use sequoia_openpgp::Cert;
     use std::str::FromStr;
+    use std::time::Duration;
 
     use super::*;
 
@@ -134,115 +889,1602 @@ install = [
                 install: IndexSet::from([
                     RequestedPackage {
                         name: PackageName::from_str("package1").unwrap(),
+                        architecture: None,
                         skip_dependencies: false,
                         force: false,
+                        env_scope: None,
+                        optional: false,
+                        maintainer_scripts: None,
+                        version: None,
                     },
                     RequestedPackage {
                         name: PackageName::from_str("package2").unwrap(),
+                        architecture: None,
                         skip_dependencies: false,
                         force: false,
+                        env_scope: None,
+                        optional: false,
+                        maintainer_scripts: None,
+                        version: None,
                     },
                     RequestedPackage {
                         name: PackageName::from_str("package3").unwrap(),
+                        architecture: None,
                         skip_dependencies: true,
                         force: true,
+                        env_scope: None,
+                        optional: false,
+                        maintainer_scripts: None,
+                        version: None,
                     }
-                ])
+                ]),
+                install_patterns: IndexSet::new(),
+                tasks: vec![],
+                presets: vec![],
+                exclude: IndexSet::new(),
+                max_dependencies: None,
+                source_priority: vec![],
+                env_scope: EnvScope::All,
+                sources: vec![],
+                sources_file: None,
+                ppas: vec![],
+                network: NetworkSettings::default(),
+                snapshot: None,
+                components: vec![],
+                backports: None,
+                proposed: None,
+                check_release_dates: None,
+                package_index_cache_days: None,
+                resolve_virtual_packages: None,
+                phased_updates: PhasedUpdatesPolicy::Skip,
+                strict_extraction: None,
+                existing_files: ExistingFilesPolicy::Overwrite,
+                subset_package_index: None,
+                verify_layout: None,
+                self_check: None,
+                provenance: None,
+                locales: vec![],
+                imagemagick_policy: None,
+                remove: IndexSet::new(),
+                target_codename: None,
+                deprecations: vec![],
             }
-        );    
+        );
     }
 
     #[test]
-    fn test_deserialize_invalid_config() {
+    fn test_deserialize_with_tasks() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+tasks = ["ubuntu-server", "cloud-image", "ubuntu-server"]
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(
+            config.tasks,
+            vec!["ubuntu-server".to_string(), "cloud-image".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_deserialize_with_install_patterns() {
         let toml = r#"
 [_]
 schema-version = "0.2"
 
 [com.heroku.buildpacks.deb-packages]
 install = [
-    { name = 123 },
+    "git",
+    "libicu*",
+    { name = "postgresql-client-1?", env_scope = "launch" },
+    { pattern = "^libreoffice-core.*", max_matches = 3 },
 ]
         "#
         .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(
+            config.install,
+            IndexSet::from([RequestedPackage::from_str("git").unwrap()])
+        );
+        assert_eq!(
+            config
+                .install_patterns
+                .iter()
+                .map(|package_pattern| (
+                    package_pattern.pattern.to_string(),
+                    package_pattern.env_scope,
+                    package_pattern.max_matches()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("libicu*".to_string(), None, None),
+                (
+                    "postgresql-client-1?".to_string(),
+                    Some(EnvScope::Launch),
+                    None
+                ),
+                ("^libreoffice-core.*".to_string(), None, Some(3)),
+            ]
+        );
+    }
 
-        let result = BuildpackConfig::from_str(toml);
-        assert!(result.is_err());
+    #[test]
+    fn test_deserialize_with_package_groups() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+install = ["git", "@media", "@video", { name = "ffmpeg", force = true }]
+
+[com.heroku.buildpacks.deb-packages.groups]
+media = ["ffmpeg", { name = "libvips-tools", env_scope = "build" }, "libavcodec*"]
+video = [{ name = "libvips-tools", env_scope = "launch" }, "libx264-164"]
+geo = ["gdal-bin"]
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        // the explicit entry for ffmpeg replaces the one from the group and the first group that
+        // lists libvips-tools wins
+        assert_eq!(
+            config.install,
+            IndexSet::from([
+                RequestedPackage::from_str("git").unwrap(),
+                RequestedPackage {
+                    env_scope: Some(EnvScope::Build),
+                    ..RequestedPackage::from_str("libvips-tools").unwrap()
+                },
+                RequestedPackage::from_str("libx264-164").unwrap(),
+                RequestedPackage {
+                    force: true,
+                    ..RequestedPackage::from_str("ffmpeg").unwrap()
+                },
+            ])
+        );
+        assert_eq!(
+            config.install_patterns,
+            IndexSet::from([RequestedPackagePattern::from_str("libavcodec*").unwrap()])
+        );
     }
 
     #[test]
-    fn test_empty_root_config() {
+    fn test_deserialize_with_invalid_package_groups() {
+        let parse_error = |groups: &str, install: &str| {
+            let toml = format!(
+                r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+install = {install}
+{groups}
+            "#
+            );
+            BuildpackConfig::from_str(toml.trim()).unwrap_err()
+        };
+
+        match parse_error("groups = [\"ffmpeg\"]", "[]") {
+            ParseConfigError::InvalidPackageGroup(group) => assert_eq!(group, "groups"),
+            e => panic!("Not the expected error - {e:?}"),
+        }
+        match parse_error("groups = { media = \"ffmpeg\" }", "[]") {
+            ParseConfigError::InvalidPackageGroup(group) => assert_eq!(group, "media"),
+            e => panic!("Not the expected error - {e:?}"),
+        }
+        match parse_error("groups = { media = [\"ffmpeg\"] }", "[\"@meda\"]") {
+            ParseConfigError::UnknownPackageGroup {
+                group,
+                available_groups,
+            } => {
+                assert_eq!(group, "meda");
+                assert_eq!(available_groups, vec!["media".to_string()]);
+            }
+            e => panic!("Not the expected error - {e:?}"),
+        }
+        match parse_error(
+            "groups = { media = [\"@geo\"], geo = [\"gdal-bin\"] }",
+            "[]",
+        ) {
+            ParseConfigError::NestedPackageGroup {
+                group,
+                referenced_group,
+            } => {
+                assert_eq!(group, "media");
+                assert_eq!(referenced_group, "geo");
+            }
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_presets() {
+        for (value, expected) in [
+            ("\"geo\"", vec![Preset::Geo]),
+            (
+                "[\"imaging\", \"media\", \"imaging\"]",
+                vec![Preset::Imaging, Preset::Media],
+            ),
+        ] {
+            let toml = format!(
+                r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+preset = {value}
+            "#
+            );
+            let config = BuildpackConfig::from_str(toml.trim()).unwrap();
+            assert_eq!(config.presets, expected);
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_preset() {
+        for (value, invalid_value) in [("\"gis\"", "gis"), ("[\"geo\", 1]", "1"), ("true", "true")]
+        {
+            let toml = format!(
+                r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+preset = {value}
+            "#
+            );
+            match BuildpackConfig::from_str(toml.trim()).unwrap_err() {
+                ParseConfigError::InvalidPreset(ParsePresetError(value)) => {
+                    assert_eq!(value.trim(), invalid_value);
+                }
+                e => panic!("Not the expected error - {e:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_tasks() {
+        for value in ["\"ubuntu-server\"", "[\"\"]", "[1]"] {
+            let toml = format!(
+                r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+tasks = {value}
+            "#
+            );
+            match BuildpackConfig::from_str(toml.trim()).unwrap_err() {
+                ParseConfigError::InvalidTasks(_) => {}
+                e => panic!("Not the expected error - {e:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_exclude() {
         let toml = r#"
 [_]
 schema-version = "0.2"
 
 [com.heroku.buildpacks.deb-packages]
+install = ["package1"]
+exclude = ["*-doc", "*-dbg"]
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(
+            config.exclude,
+            IndexSet::from([
+                PackageGlob::from_str("*-doc").unwrap(),
+                PackageGlob::from_str("*-dbg").unwrap(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_deserialize_with_max_dependencies() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
 
+[com.heroku.buildpacks.deb-packages]
+install = ["package1"]
+max_dependencies = 1000
         "#
         .trim();
         let config = BuildpackConfig::from_str(toml).unwrap();
-        assert_eq!(config, BuildpackConfig::default());
+        assert_eq!(config.max_dependencies, Some(1000));
     }
 
     #[test]
-    fn test_missing_root_config() {
+    fn test_deserialize_with_invalid_max_dependencies() {
+        for value in ["-1", "\"1000\""] {
+            let toml = format!(
+                r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+max_dependencies = {value}
+            "#
+            );
+            match BuildpackConfig::from_str(toml.trim()).unwrap_err() {
+                ParseConfigError::InvalidMaxDependencies(_) => {}
+                e => panic!("Not the expected error - {e:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_source_priority() {
         let toml = r#"
 [_]
 schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+source_priority = ["https://mirror.example.com/ubuntu/", "http://archive.ubuntu.com/ubuntu"]
         "#
         .trim();
         let config = BuildpackConfig::from_str(toml).unwrap();
-        assert_eq!(config, BuildpackConfig::default());
+        assert_eq!(
+            config.source_priority,
+            vec![
+                RepositoryUri::from("https://mirror.example.com/ubuntu"),
+                RepositoryUri::from("http://archive.ubuntu.com/ubuntu"),
+            ]
+        );
     }
 
     #[test]
-    fn test_deserialize_with_invalid_package_name_as_string() {
+    fn test_deserialize_with_invalid_source_priority() {
         let toml = r#"
 [_]
 schema-version = "0.2"
 
 [com.heroku.buildpacks.deb-packages]
-install = [
-    "not-a-package*",
-]
+source_priority = [1, 2]
         "#
         .trim();
         match BuildpackConfig::from_str(toml).unwrap_err() {
-            ParseConfigError::ParseRequestedPackage(_) => {}
+            ParseConfigError::InvalidSourcePriority(_) => {}
             e => panic!("Not the expected error - {e:?}"),
         }
     }
 
     #[test]
-    fn test_deserialize_with_invalid_package_name_in_object() {
+    fn test_deserialize_with_env_scope() {
         let toml = r#"
 [_]
 schema-version = "0.2"
 
 [com.heroku.buildpacks.deb-packages]
-install = [
-    { name = "not-a-package*" },
-]
+install = ["package1", { name = "package2", env_scope = "build" }]
+env_scope = "launch"
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(config.env_scope, EnvScope::Launch);
+        assert_eq!(
+            config
+                .install
+                .iter()
+                .map(|requested_package| requested_package.env_scope)
+                .collect::<Vec<_>>(),
+            vec![None, Some(EnvScope::Build)]
+        );
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_env_scope() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+env_scope = "runtime"
         "#
         .trim();
         match BuildpackConfig::from_str(toml).unwrap_err() {
-            ParseConfigError::ParseRequestedPackage(_) => {}
+            ParseConfigError::InvalidEnvScope(_) => {}
             e => panic!("Not the expected error - {e:?}"),
         }
     }
 
     #[test]
-    fn test_root_config_not_a_table() {
-        let toml = r#"
+    fn test_deserialize_with_sources() {
+        let toml = format!(
+            r#"
 [_]
 schema-version = "0.2"
 
-[com.heroku.buildpacks]
-deb-packages = ["wrong"]
+[com.heroku.buildpacks.deb-packages]
+install = ["package1"]
+
+[[com.heroku.buildpacks.deb-packages.sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signed_by = """{signed_by}"""
+            "#,
+            signed_by = include_str!("../../keys/ubuntu_24.04.asc")
+        );
+        let config = BuildpackConfig::from_str(toml.trim()).unwrap();
+        assert_eq!(
+            config
+                .sources
+                .iter()
+                .map(|source| source.uri.as_str())
+                .collect::<Vec<_>>(),
+            vec!["https://repo.example.com/ubuntu"]
+        );
+    }
 
+    #[test]
+    fn test_deserialize_with_invalid_sources() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+sources = ["https://repo.example.com/ubuntu"]
         "#
         .trim();
         match BuildpackConfig::from_str(toml).unwrap_err() {
-            ParseConfigError::WrongConfigType => {}
+            ParseConfigError::ParseSource(_) => {}
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_sources_file() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+sources_file = ["example.sources"]
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::InvalidSourcesFile(value) => {
+                assert_eq!(value.trim(), r#"["example.sources"]"#);
+            }
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_ppas() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+ppas = ["ppa:deadsnakes/ppa", "ppa:ondrej/php", "ppa:deadsnakes"]
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(
+            config.ppas,
+            vec![
+                Ppa::from_str("ppa:deadsnakes/ppa").unwrap(),
+                Ppa::from_str("ppa:ondrej/php").unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_ppas() {
+        for (value, invalid_value) in [
+            (r#""ppa:deadsnakes/ppa""#, r#""ppa:deadsnakes/ppa""#),
+            (r#"["deadsnakes/ppa"]"#, "deadsnakes/ppa"),
+            ("[1]", "1"),
+        ] {
+            let toml = format!(
+                r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+ppas = {value}
+            "#
+            );
+            match BuildpackConfig::from_str(toml.trim()).unwrap_err() {
+                ParseConfigError::ParsePpa(ParsePpaError(value)) => {
+                    assert_eq!(value.trim(), invalid_value);
+                }
+                e => panic!("Not the expected error - {e:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_network() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages.network]
+timeout = 60
+max_retries = 10
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(config.network.timeout, Duration::from_secs(60));
+        assert_eq!(config.network.max_retries, 10);
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_network() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+network = 60
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::ParseNetwork(ParseNetworkSettingsError::UnexpectedTomlValue(_)) => {}
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_resolve_virtual_packages() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+resolve_virtual_packages = false
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(config.resolve_virtual_packages, Some(false));
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_resolve_virtual_packages() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+resolve_virtual_packages = "no"
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::InvalidResolveVirtualPackages(_) => {}
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_strict_extraction() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+strict_extraction = true
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(config.strict_extraction, Some(true));
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_strict_extraction() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+strict_extraction = 1
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::InvalidStrictExtraction(_) => {}
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_subset_package_index() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+subset_package_index = true
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(config.subset_package_index, Some(true));
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_subset_package_index() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+subset_package_index = "yes"
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::InvalidSubsetPackageIndex(_) => {}
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_verify_layout() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+verify_layout = true
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(config.verify_layout, Some(true));
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_verify_layout() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+verify_layout = 1
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::InvalidVerifyLayout(_) => {}
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_phased_updates() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+phased_updates = "include"
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(config.phased_updates, PhasedUpdatesPolicy::Include);
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_phased_updates() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+phased_updates = "force"
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::InvalidPhasedUpdates(ParsePhasedUpdatesPolicyError(value)) => {
+                assert_eq!(value, "force");
+            }
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_existing_files() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+existing_files = "skip"
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(config.existing_files, ExistingFilesPolicy::Skip);
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_existing_files() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+existing_files = "replace"
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::InvalidExistingFiles(ParseExistingFilesPolicyError(value)) => {
+                assert_eq!(value, "replace");
+            }
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_snapshot() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+snapshot = "20240301T000000Z"
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(
+            config.snapshot,
+            Some(Snapshot::from_str("20240301T000000Z").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_snapshot() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+snapshot = "2024-03-01"
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::InvalidSnapshot(ParseSnapshotError(value)) => {
+                assert_eq!(value, "2024-03-01");
+            }
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_components() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+components = ["main", "universe", "multiverse", "restricted", "main"]
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(
+            config.components,
+            vec![
+                "main".to_string(),
+                "universe".to_string(),
+                "multiverse".to_string(),
+                "restricted".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_components() {
+        for value in ["\"main\"", "[]", "[\"\"]", "[\"non free\"]", "[1]"] {
+            let toml = format!(
+                r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+components = {value}
+            "#
+            );
+            match BuildpackConfig::from_str(toml.trim()).unwrap_err() {
+                ParseConfigError::InvalidComponents(_) => {}
+                e => panic!("Not the expected error - {e:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_optional_suites() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+backports = true
+proposed = false
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(config.backports, Some(true));
+        assert_eq!(config.proposed, Some(false));
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_optional_suites() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+backports = "yes"
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::InvalidBackports(value) => assert_eq!(value.trim(), "\"yes\""),
+            e => panic!("Not the expected error - {e:?}"),
+        }
+
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+proposed = 1
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::InvalidProposed(value) => assert_eq!(value.trim(), "1"),
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_cache_freshness() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+check_release_dates = false
+package_index_cache_days = 7
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(config.check_release_dates, Some(false));
+        assert_eq!(config.package_index_cache_days, Some(7));
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_check_release_dates() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+check_release_dates = "no"
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::InvalidCheckReleaseDates(_) => {}
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_package_index_cache_days() {
+        for value in ["-1", "\"7\""] {
+            let toml = format!(
+                r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+package_index_cache_days = {value}
+            "#
+            );
+            match BuildpackConfig::from_str(toml.trim()).unwrap_err() {
+                ParseConfigError::InvalidPackageIndexCacheDays(_) => {}
+                e => panic!("Not the expected error - {e:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_self_check() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+self_check = true
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(config.self_check, Some(true));
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_self_check() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+self_check = "yes"
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::InvalidSelfCheck(_) => {}
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_provenance() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+provenance = true
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(config.provenance, Some(true));
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_provenance() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+provenance = "yes"
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::InvalidProvenance(_) => {}
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_locales() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+locales = ["en_US.UTF-8", "de_DE.UTF-8", "en_US.UTF-8"]
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(
+            config.locales,
+            vec![
+                Locale::from_str("en_US.UTF-8").unwrap(),
+                Locale::from_str("de_DE.UTF-8").unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_locales() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+locales = "en_US.UTF-8"
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::InvalidLocales(_) => {}
+            e => panic!("Not the expected error - {e:?}"),
+        }
+
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+locales = ["en_US"]
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::ParseLocale(ParseLocaleError::InvalidLocale(value)) => {
+                assert_eq!(value, "en_US");
+            }
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_imagemagick_policy() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+imagemagick_policy = "config/imagemagick-policy.xml"
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(
+            config.imagemagick_policy,
+            Some(PathBuf::from("config/imagemagick-policy.xml"))
+        );
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_imagemagick_policy() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+imagemagick_policy = true
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::InvalidImageMagickPolicy(_) => {}
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_imagemagick_policy_outside_of_app_dir() {
+        for policy_file in [
+            "/etc/ImageMagick-6/policy.xml",
+            "../policy.xml",
+            "config/../../policy.xml",
+        ] {
+            let toml = format!(
+                r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+imagemagick_policy = "{policy_file}"
+                "#
+            );
+            match BuildpackConfig::from_str(toml.trim()).unwrap_err() {
+                ParseConfigError::InvalidImageMagickPolicy(_) => {}
+                e => panic!("Not the expected error for {policy_file} - {e:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_target_codename() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+target_codename = "Jammy"
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(config.target_codename, Some(DistroCodename::from("jammy")));
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_target_codename() {
+        for value in ["22.04", "\"\"", "\"ubuntu 22.04\""] {
+            let toml = format!(
+                r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+target_codename = {value}
+        "#
+            );
+            match BuildpackConfig::from_str(toml.trim()).unwrap_err() {
+                ParseConfigError::InvalidTargetCodename(_) => {}
+                e => panic!("Not the expected error - {e:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_remove() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+install = ["git"]
+remove = ["perl", "perl-modules-5.38"]
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(
+            config.remove,
+            IndexSet::from([
+                PackageName::from_str("perl").unwrap(),
+                PackageName::from_str("perl-modules-5.38").unwrap(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_remove() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+remove = ["perl", "not a package"]
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::InvalidRemove(value) => {
+                assert_eq!(value.trim(), "\"not a package\"");
+            }
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_exclude() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+exclude = ["*_doc"]
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::ParseExclude(_) => {}
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_exclude_that_is_not_an_array() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+exclude = "*-doc"
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::InvalidExclude(value) => {
+                assert_eq!(value.trim(), "\"*-doc\"");
+            }
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_invalid_config() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+install = [
+    { name = 123 },
+]
+        "#
+        .trim();
+
+        let result = BuildpackConfig::from_str(toml);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_empty_root_config() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(config, BuildpackConfig::default());
+    }
+
+    #[test]
+    fn test_missing_root_config() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+        "#
+        .trim();
+        let config = BuildpackConfig::from_str(toml).unwrap();
+        assert_eq!(config, BuildpackConfig::default());
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_package_name_as_string() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+install = [
+    "not_a_package",
+]
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::ParseRequestedPackage(_) => {}
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_package_name_in_object() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks.deb-packages]
+install = [
+    { name = "not_a_package" },
+]
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::ParseRequestedPackage(_) => {}
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_root_config_not_a_table() {
+        let toml = r#"
+[_]
+schema-version = "0.2"
+
+[com.heroku.buildpacks]
+deb-packages = ["wrong"]
+
+        "#
+        .trim();
+        match BuildpackConfig::from_str(toml).unwrap_err() {
+            ParseConfigError::WrongConfigType => {}
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_load_merges_install_env() {
+        let app_dir = tempfile::tempdir().unwrap();
+        let config_file = app_dir.path().join("project.toml");
+        fs::write(
+            &config_file,
+            r#"
+[com.heroku.buildpacks.deb-packages]
+install = [{ name = "ffmpeg", force = true }]
+            "#,
+        )
+        .unwrap();
+        let mut env = Env::new();
+        env.insert(INSTALL_ENV_VAR, "ffmpeg, libvips-tools");
+
+        let config = BuildpackConfig::load(&config_file, &env).unwrap();
+        assert_eq!(
+            config.install,
+            IndexSet::from([
+                RequestedPackage {
+                    name: PackageName::from_str("ffmpeg").unwrap(),
+                    architecture: None,
+                    skip_dependencies: false,
+                    force: true,
+                    env_scope: None,
+                    optional: false,
+                    maintainer_scripts: None,
+                    version: None,
+                },
+                RequestedPackage::from_str("libvips-tools").unwrap(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_load_merges_network_env() {
+        let app_dir = tempfile::tempdir().unwrap();
+        let config_file = app_dir.path().join("project.toml");
+        fs::write(
+            &config_file,
+            r"
+[com.heroku.buildpacks.deb-packages.network]
+timeout = 60
+max_retries = 10
+            ",
+        )
+        .unwrap();
+        let mut env = Env::new();
+        env.insert(TIMEOUT_ENV_VAR, "600");
+
+        let config = BuildpackConfig::load(&config_file, &env).unwrap();
+        assert_eq!(config.network.timeout, Duration::from_secs(600));
+        assert_eq!(config.network.max_retries, 10);
+    }
+
+    #[test]
+    fn test_load_merges_sources_file() {
+        let app_dir = tempfile::tempdir().unwrap();
+        let config_file = app_dir.path().join("project.toml");
+        fs::write(
+            &config_file,
+            r#"
+[com.heroku.buildpacks.deb-packages]
+sources_file = "config/example.sources"
+
+[[com.heroku.buildpacks.deb-packages.sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signature_policy = "disabled"
+            "#,
+        )
+        .unwrap();
+        fs::create_dir(app_dir.path().join("config")).unwrap();
+        fs::write(
+            app_dir.path().join("config/example.sources"),
+            r"
+Types: deb
+URIs: https://other.example.com/ubuntu
+Suites: noble
+Components: main
+Signature-Policy: disabled
+            ",
+        )
+        .unwrap();
+
+        let config = BuildpackConfig::load(&config_file, &Env::new()).unwrap();
+        assert_eq!(
+            config
+                .sources
+                .iter()
+                .map(|source| source.uri.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "https://repo.example.com/ubuntu",
+                "https://other.example.com/ubuntu"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_missing_sources_file() {
+        let app_dir = tempfile::tempdir().unwrap();
+        let config_file = app_dir.path().join("project.toml");
+        fs::write(
+            &config_file,
+            r#"
+[com.heroku.buildpacks.deb-packages]
+sources_file = "example.sources"
+            "#,
+        )
+        .unwrap();
+
+        match BuildpackConfig::load(&config_file, &Env::new()).unwrap_err() {
+            libcnb::Error::BuildpackError(DebianPackagesBuildpackError::Config(
+                ConfigError::ReadSourcesFile(path, _),
+            )) => assert_eq!(path, app_dir.path().join("example.sources")),
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_load_signing_key_file() {
+        let app_dir = tempfile::tempdir().unwrap();
+        let config_file = app_dir.path().join("project.toml");
+        fs::write(
+            &config_file,
+            r#"
+[com.heroku.buildpacks.deb-packages]
+sources_file = "example.sources"
+
+[[com.heroku.buildpacks.deb-packages.sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signed_by_file = "keys/example.asc"
+            "#,
+        )
+        .unwrap();
+        fs::write(
+            app_dir.path().join("example.sources"),
+            r"
+Types: deb
+URIs: https://other.example.com/ubuntu
+Suites: noble
+Components: main
+Signature-Policy: disabled
+            ",
+        )
+        .unwrap();
+        fs::create_dir(app_dir.path().join("keys")).unwrap();
+        fs::write(
+            app_dir.path().join("keys/example.asc"),
+            include_str!("../../keys/ubuntu_24.04.asc"),
+        )
+        .unwrap();
+
+        let config = BuildpackConfig::load(&config_file, &Env::new()).unwrap();
+        assert_eq!(
+            config.sources[0]
+                .signed_by
+                .as_deref()
+                .map(|signed_by| Cert::from_str(signed_by).unwrap()),
+            Some(Cert::from_str(include_str!("../../keys/ubuntu_24.04.asc")).unwrap())
+        );
+        assert_eq!(config.sources[1].signed_by, None);
+    }
+
+    #[test]
+    fn test_load_invalid_signing_key_file() {
+        let app_dir = tempfile::tempdir().unwrap();
+        let config_file = app_dir.path().join("project.toml");
+        fs::write(
+            &config_file,
+            r#"
+[[com.heroku.buildpacks.deb-packages.sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signed_by_file = "example.asc"
+            "#,
+        )
+        .unwrap();
+
+        match BuildpackConfig::load(&config_file, &Env::new()).unwrap_err() {
+            libcnb::Error::BuildpackError(DebianPackagesBuildpackError::Config(
+                ConfigError::ReadSigningKeyFile(path, _),
+            )) => assert_eq!(path, app_dir.path().join("example.asc")),
+            e => panic!("Not the expected error - {e:?}"),
+        }
+
+        fs::write(app_dir.path().join("example.asc"), "not a key").unwrap();
+        match BuildpackConfig::load(&config_file, &Env::new()).unwrap_err() {
+            libcnb::Error::BuildpackError(DebianPackagesBuildpackError::Config(
+                ConfigError::InvalidSigningKeyFile(path, _),
+            )) => assert_eq!(path, app_dir.path().join("example.asc")),
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_load_invalid_network_env() {
+        let app_dir = tempfile::tempdir().unwrap();
+        let mut env = Env::new();
+        env.insert(MAX_RETRIES_ENV_VAR, "-1");
+
+        match BuildpackConfig::load(app_dir.path().join("project.toml"), &env).unwrap_err() {
+            libcnb::Error::BuildpackError(DebianPackagesBuildpackError::Config(
+                ConfigError::ParseNetworkEnv(ParseNetworkSettingsError::InvalidValue(
+                    MAX_RETRIES_ENV_VAR,
+                    _,
+                )),
+            )) => {}
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_load_install_env_without_project_toml() {
+        let app_dir = tempfile::tempdir().unwrap();
+        let config_file = app_dir.path().join("project.toml");
+        let mut env = Env::new();
+        env.insert(INSTALL_ENV_VAR, "ffmpeg,libvips-tools");
+
+        assert!(BuildpackConfig::has_install_env(&env));
+        assert!(!BuildpackConfig::has_install_env(&Env::new()));
+        assert_eq!(
+            BuildpackConfig::load(&config_file, &env).unwrap().install,
+            IndexSet::from([
+                RequestedPackage::from_str("ffmpeg").unwrap(),
+                RequestedPackage::from_str("libvips-tools").unwrap(),
+            ])
+        );
+        assert!(BuildpackConfig::sha256(&config_file).is_ok());
+    }
+
+    #[test]
+    fn test_load_invalid_install_env() {
+        let app_dir = tempfile::tempdir().unwrap();
+        let mut env = Env::new();
+        env.insert(INSTALL_ENV_VAR, "ffmpeg; rm");
+
+        match BuildpackConfig::load(app_dir.path().join("project.toml"), &env).unwrap_err() {
+            libcnb::Error::BuildpackError(DebianPackagesBuildpackError::Config(
+                ConfigError::ParseInstallEnv(error),
+            )) => assert_eq!(error.package_name, "ffmpeg;"),
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_merge_build_plan() {
+        let mut config = BuildpackConfig::from_str(
+            r#"
+[com.heroku.buildpacks.deb-packages]
+install = [{ name = "libgdal-dev", force = true }]
+            "#,
+        )
+        .unwrap();
+        let buildpack_plan: BuildpackPlan = toml::from_str(
+            r#"
+[[entries]]
+name = "deb-packages"
+metadata = { packages = ["libgdal-dev", "libgeos-dev"] }
+
+[[entries]]
+name = "deb-packages"
+
+[[entries]]
+name = "node"
+metadata = { packages = ["ignored"] }
+            "#,
+        )
+        .unwrap();
+
+        config
+            .merge_build_plan(&buildpack_plan, "deb-packages")
+            .unwrap();
+        assert_eq!(
+            config.install,
+            IndexSet::from([
+                RequestedPackage {
+                    name: PackageName::from_str("libgdal-dev").unwrap(),
+                    architecture: None,
+                    skip_dependencies: false,
+                    force: true,
+                    env_scope: None,
+                    optional: false,
+                    maintainer_scripts: None,
+                    version: None,
+                },
+                RequestedPackage::from_str("libgeos-dev").unwrap(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_merge_invalid_build_plan() {
+        let buildpack_plan: BuildpackPlan = toml::from_str(
+            r#"
+[[entries]]
+name = "deb-packages"
+metadata = { packages = "libgdal-dev" }
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(
+            BuildpackConfig::default()
+                .merge_build_plan(&buildpack_plan, "deb-packages")
+                .unwrap_err(),
+            ConfigError::ParseBuildPlan(_)
+        ));
+
+        let buildpack_plan: BuildpackPlan = toml::from_str(
+            r#"
+[[entries]]
+name = "deb-packages"
+metadata = { packages = ["libgdal dev"] }
+            "#,
+        )
+        .unwrap();
+        match BuildpackConfig::default()
+            .merge_build_plan(&buildpack_plan, "deb-packages")
+            .unwrap_err()
+        {
+            ConfigError::ParseBuildPlanPackage(error) => {
+                assert_eq!(error.package_name, "libgdal dev");
+            }
             e => panic!("Not the expected error - {e:?}"),
         }
     }
@@ -0,0 +1,866 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use sequoia_openpgp::Cert;
+use toml_edit::{Item, TableLike, Value};
+
+use crate::config::parse_deb822_sources;
+use crate::debian::{ArchitectureName, RepositoryUri, SignaturePolicy, Source};
+
+// An additional repository configured in project.toml using the same fields as the built-in
+// sources (see `Source`) or in the deb822 format (see `parse_deb822_sources`). A source is only
+// used for the architecture being built and is skipped when `architectures` doesn't include it.
+// The signing key is only required when the signature policy is `required` (the default) and can
+// be read from a file in the application directory instead (see `signed_by_file`) or downloaded
+// from a keyserver (see `key_fingerprint`). Setting `trusted` is the same as disabling signature
+// verification. Packages can be downloaded from a different host than the package indexes (see
+// `download_url`).
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub(crate) struct CustomSource {
+    pub(crate) uri: RepositoryUri,
+    pub(crate) suites: Vec<String>,
+    pub(crate) components: Vec<String>,
+    pub(crate) signed_by: Option<String>,
+    // Relative to the application directory. The key is read into `signed_by` when the
+    // configuration is loaded.
+    pub(crate) signed_by_file: Option<PathBuf>,
+    // The full fingerprint (uppercase hex) of a key downloaded from the keyserver into `signed_by`
+    // before the package index is built.
+    pub(crate) key_fingerprint: Option<String>,
+    pub(crate) signature_policy: SignaturePolicy,
+    // Set with `trusted = true` which is logged as a warning since the repository isn't verified.
+    pub(crate) trusted: bool,
+    // Accepts SHA1 or MD5 hashes when the repository doesn't publish SHA256 hashes.
+    pub(crate) allow_weak_hashes: bool,
+    pub(crate) client_identity: Option<ClientIdentity>,
+    pub(crate) mirrors: Vec<RepositoryUri>,
+    // A url template containing `{filename}` that's replaced with the `Filename` of the package
+    // index entry (e.g.; a CDN in front of the repository's pool).
+    pub(crate) download_url: Option<String>,
+    // Empty when the source is used for every architecture.
+    pub(crate) architectures: Vec<ArchitectureName>,
+}
+
+// The PEM encoded client certificate and private key presented to repositories that require
+// mutual TLS. The paths are relative to the application directory but can also be absolute (e.g.;
+// a certificate provided by a platform binding) so the private key doesn't need to be committed
+// with the application.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub(crate) struct ClientIdentity {
+    pub(crate) certificate: PathBuf,
+    pub(crate) key: PathBuf,
+}
+
+impl CustomSource {
+    pub(crate) fn is_used_for(&self, arch: &ArchitectureName) -> bool {
+        self.architectures.is_empty() || self.architectures.contains(arch)
+    }
+
+    pub(crate) fn to_source(&self, arch: &ArchitectureName) -> Source {
+        Source {
+            arch: arch.clone(),
+            components: self.components.clone(),
+            signed_by: self.signed_by.clone(),
+            signature_policy: self.signature_policy,
+            allow_weak_hashes: self.allow_weak_hashes,
+            suites: self.suites.clone(),
+            uri: self.uri.clone(),
+            mirrors: self.mirrors.clone(),
+            download_url: self.download_url.clone(),
+        }
+    }
+
+    pub(crate) fn parse_all(item: &Item) -> Result<Vec<CustomSource>, ParseCustomSourceError> {
+        if let Some(array_of_tables) = item.as_array_of_tables() {
+            array_of_tables
+                .iter()
+                .map(|table| CustomSource::try_from(table as &dyn TableLike))
+                .collect()
+        } else if let Some(array) = item.as_array() {
+            array
+                .iter()
+                .map(|value| match value {
+                    Value::InlineTable(inline_table) => {
+                        CustomSource::try_from(inline_table as &dyn TableLike)
+                    }
+                    _ => Err(ParseCustomSourceError::UnexpectedTomlValue(
+                        value.to_string(),
+                    )),
+                })
+                .collect()
+        } else if let Some(deb822_sources) = item.as_str() {
+            parse_deb822_sources(deb822_sources)
+        } else {
+            Err(ParseCustomSourceError::UnexpectedTomlValue(
+                item.to_string(),
+            ))
+        }
+    }
+}
+
+impl TryFrom<&dyn TableLike> for CustomSource {
+    type Error = ParseCustomSourceError;
+
+    fn try_from(table: &dyn TableLike) -> Result<Self, Self::Error> {
+        let uri = get_string(table, URI_KEY)?;
+        let suites = get_string_array(table, SUITES_KEY)?;
+        let components = get_string_array(table, COMPONENTS_KEY)?;
+
+        let trusted = get_bool(table, TRUSTED_KEY)?;
+        if trusted && table.contains_key(SIGNATURE_POLICY_KEY) {
+            Err(ParseCustomSourceError::ConflictingKeys(
+                TRUSTED_KEY,
+                SIGNATURE_POLICY_KEY,
+            ))?;
+        }
+
+        let signature_policy = match table.get(SIGNATURE_POLICY_KEY) {
+            _ if trusted => SignaturePolicy::Disabled,
+            Some(item) => item
+                .as_str()
+                .and_then(|value| SignaturePolicy::from_str(value).ok())
+                .ok_or_else(|| {
+                    ParseCustomSourceError::InvalidValue(SIGNATURE_POLICY_KEY, item.to_string())
+                })?,
+            None => SignaturePolicy::default(),
+        };
+
+        // only one of the ways to provide the signing key can be used
+        if let [key, other_key, ..] = [SIGNED_BY_KEY, SIGNED_BY_FILE_KEY, KEY_FINGERPRINT_KEY]
+            .into_iter()
+            .filter(|key| table.contains_key(key))
+            .collect::<Vec<_>>()[..]
+        {
+            Err(ParseCustomSourceError::ConflictingKeys(key, other_key))?;
+        }
+
+        let signed_by_file = if table.contains_key(SIGNED_BY_FILE_KEY) {
+            Some(PathBuf::from(get_string(table, SIGNED_BY_FILE_KEY)?))
+        } else {
+            None
+        };
+
+        let key_fingerprint = if table.contains_key(KEY_FINGERPRINT_KEY) {
+            let value = get_string(table, KEY_FINGERPRINT_KEY)?;
+            Some(
+                parse_key_fingerprint(&value).ok_or(ParseCustomSourceError::InvalidValue(
+                    KEY_FINGERPRINT_KEY,
+                    value,
+                ))?,
+            )
+        } else {
+            None
+        };
+
+        let signed_by = match (signature_policy, table.contains_key(SIGNED_BY_KEY)) {
+            (_, false) if signed_by_file.is_some() || key_fingerprint.is_some() => None,
+            (SignaturePolicy::Required, _) | (_, true) => Some(get_string(table, SIGNED_BY_KEY)?),
+            (_, false) => None,
+        };
+
+        if let Some(Err(e)) = signed_by.as_deref().map(Cert::from_str) {
+            Err(ParseCustomSourceError::InvalidValue(
+                SIGNED_BY_KEY,
+                e.to_string(),
+            ))?;
+        }
+
+        Ok(CustomSource {
+            uri: RepositoryUri::from(uri.trim_end_matches('/')),
+            suites,
+            components,
+            signed_by,
+            signed_by_file,
+            key_fingerprint,
+            signature_policy,
+            trusted,
+            allow_weak_hashes: get_bool(table, ALLOW_WEAK_HASHES_KEY)?,
+            client_identity: get_client_identity(table)?,
+            mirrors: get_mirrors(table)?,
+            download_url: get_download_url(table)?,
+            architectures: get_architectures(table)?,
+        })
+    }
+}
+
+// The certificate and key are only valid together.
+fn get_client_identity(
+    table: &dyn TableLike,
+) -> Result<Option<ClientIdentity>, ParseCustomSourceError> {
+    match (
+        table.contains_key(CLIENT_CERTIFICATE_KEY),
+        table.contains_key(CLIENT_KEY_KEY),
+    ) {
+        (false, false) => Ok(None),
+        _ => Ok(Some(ClientIdentity {
+            certificate: PathBuf::from(get_string(table, CLIENT_CERTIFICATE_KEY)?),
+            key: PathBuf::from(get_string(table, CLIENT_KEY_KEY)?),
+        })),
+    }
+}
+
+fn get_mirrors(table: &dyn TableLike) -> Result<Vec<RepositoryUri>, ParseCustomSourceError> {
+    if !table.contains_key(MIRRORS_KEY) {
+        return Ok(vec![]);
+    }
+    Ok(get_string_array(table, MIRRORS_KEY)?
+        .iter()
+        .map(|mirror| RepositoryUri::from(mirror.trim_end_matches('/')))
+        .collect())
+}
+
+// The template must contain the placeholder or every package would be downloaded from one URL.
+fn get_download_url(table: &dyn TableLike) -> Result<Option<String>, ParseCustomSourceError> {
+    if !table.contains_key(DOWNLOAD_URL_KEY) {
+        return Ok(None);
+    }
+    let value = get_string(table, DOWNLOAD_URL_KEY)?;
+    if value.contains(DOWNLOAD_URL_FILENAME) {
+        Ok(Some(value))
+    } else {
+        Err(ParseCustomSourceError::InvalidValue(
+            DOWNLOAD_URL_KEY,
+            value,
+        ))
+    }
+}
+
+// Fingerprints are often copied with spaces between the groups of hex digits (e.g.; from
+// `gpg --fingerprint`). Short and long key ids aren't accepted since they can collide.
+fn parse_key_fingerprint(value: &str) -> Option<String> {
+    let fingerprint = value
+        .trim()
+        .trim_start_matches("0x")
+        .chars()
+        .filter(|char| !char.is_whitespace())
+        .collect::<String>()
+        .to_ascii_uppercase();
+    Some(fingerprint).filter(|fingerprint| {
+        matches!(fingerprint.len(), 40 | 64)
+            && fingerprint.chars().all(|char| char.is_ascii_hexdigit())
+    })
+}
+
+// Without the key the source is used for every architecture.
+fn get_architectures(
+    table: &dyn TableLike,
+) -> Result<Vec<ArchitectureName>, ParseCustomSourceError> {
+    if !table.contains_key(ARCHITECTURES_KEY) {
+        return Ok(vec![]);
+    }
+    get_string_array(table, ARCHITECTURES_KEY)?
+        .into_iter()
+        .map(|value| {
+            ArchitectureName::from_str(&value)
+                .map_err(|_| ParseCustomSourceError::InvalidValue(ARCHITECTURES_KEY, value))
+        })
+        .collect()
+}
+
+fn get_string(table: &dyn TableLike, key: &'static str) -> Result<String, ParseCustomSourceError> {
+    let item = table
+        .get(key)
+        .ok_or(ParseCustomSourceError::MissingKey(key))?;
+    item.as_str()
+        .filter(|value| !value.trim().is_empty())
+        .map(ToString::to_string)
+        .ok_or_else(|| ParseCustomSourceError::InvalidValue(key, item.to_string()))
+}
+
+// Optional flags default to false.
+fn get_bool(table: &dyn TableLike, key: &'static str) -> Result<bool, ParseCustomSourceError> {
+    match table.get(key) {
+        Some(item) => item
+            .as_bool()
+            .ok_or_else(|| ParseCustomSourceError::InvalidValue(key, item.to_string())),
+        None => Ok(false),
+    }
+}
+
+fn get_string_array(
+    table: &dyn TableLike,
+    key: &'static str,
+) -> Result<Vec<String>, ParseCustomSourceError> {
+    let item = table
+        .get(key)
+        .ok_or(ParseCustomSourceError::MissingKey(key))?;
+    let values = item
+        .as_array()
+        .filter(|values| !values.is_empty())
+        .ok_or_else(|| ParseCustomSourceError::InvalidValue(key, item.to_string()))?;
+    values
+        .iter()
+        .map(|value| {
+            value
+                .as_str()
+                .map(ToString::to_string)
+                .ok_or_else(|| ParseCustomSourceError::InvalidValue(key, value.to_string()))
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub(crate) enum ParseCustomSourceError {
+    MissingKey(&'static str),
+    InvalidValue(&'static str, String),
+    UnexpectedTomlValue(String),
+    InvalidDeb822(String),
+    ConflictingKeys(&'static str, &'static str),
+}
+
+const URI_KEY: &str = "uri";
+const SUITES_KEY: &str = "suites";
+const COMPONENTS_KEY: &str = "components";
+const SIGNED_BY_KEY: &str = "signed_by";
+const SIGNED_BY_FILE_KEY: &str = "signed_by_file";
+const KEY_FINGERPRINT_KEY: &str = "key_fingerprint";
+const SIGNATURE_POLICY_KEY: &str = "signature_policy";
+const TRUSTED_KEY: &str = "trusted";
+const ALLOW_WEAK_HASHES_KEY: &str = "allow_weak_hashes";
+const CLIENT_CERTIFICATE_KEY: &str = "client_certificate";
+const CLIENT_KEY_KEY: &str = "client_key";
+const MIRRORS_KEY: &str = "mirrors";
+const DOWNLOAD_URL_KEY: &str = "download_url";
+const ARCHITECTURES_KEY: &str = "architectures";
+
+// The placeholder in `download_url` that's replaced with the path of the package in the repository.
+pub(crate) const DOWNLOAD_URL_FILENAME: &str = "{filename}";
+
+#[cfg(test)]
+mod tests {
+    use toml_edit::DocumentMut;
+
+    use super::*;
+
+    const SIGNED_BY: &str = include_str!("../../keys/ubuntu_24.04.asc");
+
+    fn parse_sources(toml: &str) -> Result<Vec<CustomSource>, ParseCustomSourceError> {
+        let doc = DocumentMut::from_str(toml).unwrap();
+        CustomSource::parse_all(doc.get("sources").unwrap())
+    }
+
+    #[test]
+    fn test_parse_array_of_tables() {
+        let sources = parse_sources(&format!(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu/"
+suites = ["noble"]
+components = ["main"]
+signed_by = """{SIGNED_BY}"""
+            "#
+        ))
+        .unwrap();
+        assert_eq!(
+            sources,
+            vec![CustomSource {
+                uri: RepositoryUri::from("https://repo.example.com/ubuntu"),
+                suites: vec!["noble".to_string()],
+                components: vec!["main".to_string()],
+                signed_by: Some(SIGNED_BY.to_string()),
+                signed_by_file: None,
+                key_fingerprint: None,
+                signature_policy: SignaturePolicy::Required,
+                trusted: false,
+                allow_weak_hashes: false,
+                client_identity: None,
+                mirrors: vec![],
+                download_url: None,
+                architectures: vec![],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_mirrors() {
+        let sources = parse_sources(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signature_policy = "disabled"
+mirrors = ["https://mirror-1.example.com/ubuntu/", "https://mirror-2.example.com/ubuntu"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            sources[0].mirrors,
+            vec![
+                RepositoryUri::from("https://mirror-1.example.com/ubuntu"),
+                RepositoryUri::from("https://mirror-2.example.com/ubuntu")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_client_identity() {
+        let sources = parse_sources(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signature_policy = "disabled"
+client_certificate = "certs/client.crt"
+client_key = "/platform/bindings/apt-mirror/client.key"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            sources[0].client_identity,
+            Some(ClientIdentity {
+                certificate: PathBuf::from("certs/client.crt"),
+                key: PathBuf::from("/platform/bindings/apt-mirror/client.key"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_client_certificate_without_client_key() {
+        let error = parse_sources(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signature_policy = "disabled"
+client_certificate = "certs/client.crt"
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            ParseCustomSourceError::MissingKey(CLIENT_KEY_KEY)
+        ));
+    }
+
+    #[test]
+    fn test_parse_signature_policy_without_signed_by() {
+        let sources = parse_sources(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signature_policy = "disabled"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(sources[0].signature_policy, SignaturePolicy::Disabled);
+        assert_eq!(sources[0].signed_by, None);
+    }
+
+    #[test]
+    fn test_parse_trusted() {
+        let sources = parse_sources(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+trusted = true
+            "#,
+        )
+        .unwrap();
+        assert!(sources[0].trusted);
+        assert_eq!(sources[0].signature_policy, SignaturePolicy::Disabled);
+        assert_eq!(sources[0].signed_by, None);
+    }
+
+    #[test]
+    fn test_parse_not_trusted() {
+        let error = parse_sources(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+trusted = false
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            ParseCustomSourceError::MissingKey(SIGNED_BY_KEY)
+        ));
+    }
+
+    #[test]
+    fn test_parse_invalid_trusted() {
+        let error = parse_sources(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+trusted = "yes"
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            ParseCustomSourceError::InvalidValue(TRUSTED_KEY, _)
+        ));
+    }
+
+    #[test]
+    fn test_parse_trusted_with_signature_policy() {
+        let error = parse_sources(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+trusted = true
+signature_policy = "optional"
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            ParseCustomSourceError::ConflictingKeys(TRUSTED_KEY, SIGNATURE_POLICY_KEY)
+        ));
+    }
+
+    #[test]
+    fn test_parse_allow_weak_hashes() {
+        let sources = parse_sources(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/debian"
+suites = ["stable"]
+components = ["main"]
+signature_policy = "disabled"
+allow_weak_hashes = true
+
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signature_policy = "disabled"
+            "#,
+        )
+        .unwrap();
+        assert!(sources[0].allow_weak_hashes);
+        assert!(!sources[1].allow_weak_hashes);
+    }
+
+    #[test]
+    fn test_parse_download_url() {
+        let sources = parse_sources(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signature_policy = "disabled"
+download_url = "https://cdn.example.com/ubuntu/{filename}"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            sources[0].download_url,
+            Some("https://cdn.example.com/ubuntu/{filename}".to_string())
+        );
+        assert_eq!(
+            sources[0].to_source(&ArchitectureName::AMD_64).download_url,
+            sources[0].download_url
+        );
+    }
+
+    #[test]
+    fn test_parse_download_url_without_filename() {
+        let error = parse_sources(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signature_policy = "disabled"
+download_url = "https://cdn.example.com/ubuntu"
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            ParseCustomSourceError::InvalidValue(DOWNLOAD_URL_KEY, _)
+        ));
+    }
+
+    #[test]
+    fn test_parse_architectures() {
+        let sources = parse_sources(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signature_policy = "disabled"
+architectures = ["amd64"]
+
+[[sources]]
+uri = "https://other.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signature_policy = "disabled"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(sources[0].architectures, vec![ArchitectureName::AMD_64]);
+        assert!(sources[0].is_used_for(&ArchitectureName::AMD_64));
+        assert!(!sources[0].is_used_for(&ArchitectureName::ARM_64));
+        assert!(sources[1].architectures.is_empty());
+        assert!(sources[1].is_used_for(&ArchitectureName::ARM_64));
+    }
+
+    #[test]
+    fn test_parse_unsupported_architecture() {
+        let error = parse_sources(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signature_policy = "disabled"
+architectures = ["mips"]
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            ParseCustomSourceError::InvalidValue(ARCHITECTURES_KEY, value) if value == "mips"
+        ));
+    }
+
+    #[test]
+    fn test_parse_optional_signature_policy_with_signed_by() {
+        let sources = parse_sources(&format!(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signature_policy = "optional"
+signed_by = """{SIGNED_BY}"""
+            "#
+        ))
+        .unwrap();
+        assert_eq!(sources[0].signature_policy, SignaturePolicy::Optional);
+        assert_eq!(sources[0].signed_by, Some(SIGNED_BY.to_string()));
+    }
+
+    #[test]
+    fn test_parse_invalid_signature_policy() {
+        let error = parse_sources(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signature_policy = "sometimes"
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            ParseCustomSourceError::InvalidValue(SIGNATURE_POLICY_KEY, _)
+        ));
+    }
+
+    #[test]
+    fn test_parse_required_signature_policy_without_signed_by() {
+        let error = parse_sources(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signature_policy = "required"
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            ParseCustomSourceError::MissingKey(SIGNED_BY_KEY)
+        ));
+    }
+
+    #[test]
+    fn test_parse_signed_by_file() {
+        let sources = parse_sources(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signed_by_file = "keys/example.asc"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(sources[0].signed_by, None);
+        assert_eq!(
+            sources[0].signed_by_file,
+            Some(PathBuf::from("keys/example.asc"))
+        );
+        assert_eq!(sources[0].signature_policy, SignaturePolicy::Required);
+    }
+
+    #[test]
+    fn test_parse_signed_by_with_signed_by_file() {
+        let error = parse_sources(&format!(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signed_by = """{SIGNED_BY}"""
+signed_by_file = "keys/example.asc"
+            "#
+        ))
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            ParseCustomSourceError::ConflictingKeys(SIGNED_BY_KEY, SIGNED_BY_FILE_KEY)
+        ));
+    }
+
+    #[test]
+    fn test_parse_key_fingerprint() {
+        let sources = parse_sources(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+key_fingerprint = "f23c 5a6c f475 9775 95c8  9f51 ba69 3236 6a75 5776"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(sources[0].signed_by, None);
+        assert_eq!(
+            sources[0].key_fingerprint.as_deref(),
+            Some("F23C5A6CF475977595C89F51BA6932366A755776")
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_key_fingerprint() {
+        for fingerprint in ["BA6932366A755776", "not a fingerprint", ""] {
+            let error = parse_sources(&format!(
+                r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+key_fingerprint = "{fingerprint}"
+            "#
+            ))
+            .unwrap_err();
+            assert!(
+                matches!(
+                    error,
+                    ParseCustomSourceError::InvalidValue(KEY_FINGERPRINT_KEY, _)
+                ),
+                "{fingerprint}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_signed_by_file_with_key_fingerprint() {
+        let error = parse_sources(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signed_by_file = "keys/example.asc"
+key_fingerprint = "F23C5A6CF475977595C89F51BA6932366A755776"
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            ParseCustomSourceError::ConflictingKeys(SIGNED_BY_FILE_KEY, KEY_FINGERPRINT_KEY)
+        ));
+    }
+
+    #[test]
+    fn test_parse_missing_key() {
+        let error = parse_sources(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+signed_by = "key"
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            ParseCustomSourceError::MissingKey(COMPONENTS_KEY)
+        ));
+    }
+
+    #[test]
+    fn test_parse_invalid_signed_by() {
+        let error = parse_sources(
+            r#"
+[[sources]]
+uri = "https://repo.example.com/ubuntu"
+suites = ["noble"]
+components = ["main"]
+signed_by = "not a key"
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            ParseCustomSourceError::InvalidValue(SIGNED_BY_KEY, _)
+        ));
+    }
+
+    #[test]
+    fn test_parse_deb822_string() {
+        let sources = parse_sources(
+            r#"
+sources = """
+Types: deb
+URIs: https://repo.example.com/ubuntu
+Suites: noble
+Components: main
+Signature-Policy: disabled
+"""
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            sources,
+            vec![CustomSource {
+                uri: RepositoryUri::from("https://repo.example.com/ubuntu"),
+                suites: vec!["noble".to_string()],
+                components: vec!["main".to_string()],
+                signed_by: None,
+                signed_by_file: None,
+                key_fingerprint: None,
+                signature_policy: SignaturePolicy::Disabled,
+                trusted: false,
+                allow_weak_hashes: false,
+                client_identity: None,
+                mirrors: vec![],
+                download_url: None,
+                architectures: vec![],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_unexpected_toml_value() {
+        let error = parse_sources("sources = 42").unwrap_err();
+        assert!(matches!(
+            error,
+            ParseCustomSourceError::UnexpectedTomlValue(_)
+        ));
+    }
+}
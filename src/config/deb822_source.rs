@@ -0,0 +1,309 @@
+use toml_edit::{Array, InlineTable, TableLike, Value};
+
+use crate::config::{CustomSource, ParseCustomSourceError};
+
+// Sources can also be written in the deb822 format used by `.sources` files in
+// `/etc/apt/sources.list.d` (see `man sources.list`). Each stanza is converted to the same fields
+// as a source table in project.toml so both formats are validated the same way. A stanza with
+// more than one URI becomes one source per URI, stanzas with `Enabled: no` or without the `deb`
+// type are skipped, and `Architectures` limits the source to the listed architectures.
+// `Signed-By` must contain the ASCII-armored key since keyring files from the build image aren't
+// used.
+pub(crate) fn parse_deb822_sources(
+    contents: &str,
+) -> Result<Vec<CustomSource>, ParseCustomSourceError> {
+    let mut sources = vec![];
+    for stanza in parse_stanzas(contents)? {
+        if get_field(&stanza, ENABLED_FIELD).is_some_and(|enabled| enabled == "no") {
+            continue;
+        }
+
+        let types = get_required_field(&stanza, TYPES_FIELD)?;
+        if !types.split_whitespace().any(|value| value == "deb") {
+            continue;
+        }
+
+        for uri in get_required_field(&stanza, URIS_FIELD)?.split_whitespace() {
+            let mut table = InlineTable::new();
+            table.insert("uri", Value::from(uri));
+            for (field, key, is_list) in SOURCE_FIELDS {
+                if let Some(value) = get_field(&stanza, field) {
+                    let value = if *is_list {
+                        Value::Array(value.split_whitespace().collect::<Array>())
+                    } else {
+                        Value::from(value)
+                    };
+                    table.insert(*key, value);
+                }
+            }
+            sources
+                .push(CustomSource::try_from(&table as &dyn TableLike).map_err(to_deb822_error)?);
+        }
+    }
+    Ok(sources)
+}
+
+// The deb822 fields and the source table keys they're converted to. Values of list fields are
+// separated by whitespace.
+const SOURCE_FIELDS: &[(&str, &str, bool)] = &[
+    ("Suites", "suites", true),
+    ("Components", "components", true),
+    ("Signed-By", "signed_by", false),
+    ("Signature-Policy", "signature_policy", false),
+    ("Client-Certificate", "client_certificate", false),
+    ("Client-Key", "client_key", false),
+    ("Mirrors", "mirrors", true),
+    ("Architectures", "architectures", true),
+];
+
+const TYPES_FIELD: &str = "Types";
+const URIS_FIELD: &str = "URIs";
+const ENABLED_FIELD: &str = "Enabled";
+
+// Errors name the deb822 field instead of the source table key it was converted to.
+fn to_deb822_error(error: ParseCustomSourceError) -> ParseCustomSourceError {
+    let field_name = |key: &'static str| {
+        SOURCE_FIELDS
+            .iter()
+            .find(|(_, source_key, _)| *source_key == key)
+            .map_or(key, |(field, _, _)| *field)
+    };
+    match error {
+        ParseCustomSourceError::MissingKey(key) => {
+            ParseCustomSourceError::MissingKey(field_name(key))
+        }
+        ParseCustomSourceError::InvalidValue(key, value) => {
+            ParseCustomSourceError::InvalidValue(field_name(key), value)
+        }
+        error => error,
+    }
+}
+
+type Stanza = Vec<(String, String)>;
+
+// Field names are case-insensitive.
+fn get_field<'a>(stanza: &'a Stanza, name: &str) -> Option<&'a str> {
+    stanza
+        .iter()
+        .find(|(field, _)| field.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+        .filter(|value| !value.is_empty())
+}
+
+fn get_required_field<'a>(
+    stanza: &'a Stanza,
+    name: &'static str,
+) -> Result<&'a str, ParseCustomSourceError> {
+    get_field(stanza, name).ok_or(ParseCustomSourceError::MissingKey(name))
+}
+
+// Stanzas are separated by blank lines and lines starting with `#` are comments. A line starting
+// with whitespace continues the value of the previous field and a continuation line containing
+// only `.` is an empty line (e.g.; between the headers and the body of an armored key).
+fn parse_stanzas(contents: &str) -> Result<Vec<Stanza>, ParseCustomSourceError> {
+    let mut stanzas = vec![];
+    let mut stanza = Stanza::new();
+    for line in contents.lines().filter(|line| !line.starts_with('#')) {
+        if line.trim().is_empty() {
+            if !stanza.is_empty() {
+                stanzas.push(std::mem::take(&mut stanza));
+            }
+        } else if line.starts_with([' ', '\t']) {
+            let (_, value) = stanza
+                .last_mut()
+                .ok_or_else(|| ParseCustomSourceError::InvalidDeb822(line.to_string()))?;
+            let line = line.trim();
+            value.push('\n');
+            if line != "." {
+                value.push_str(line);
+            }
+        } else {
+            let (field, value) = line
+                .split_once(':')
+                .filter(|(field, _)| {
+                    !field.trim().is_empty() && !field.trim().contains(char::is_whitespace)
+                })
+                .ok_or_else(|| ParseCustomSourceError::InvalidDeb822(line.to_string()))?;
+            stanza.push((field.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    if !stanza.is_empty() {
+        stanzas.push(stanza);
+    }
+    for (_, value) in stanzas.iter_mut().flatten() {
+        *value = value.trim().to_string();
+    }
+    Ok(stanzas)
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::{formatdoc, indoc};
+
+    use crate::debian::{ArchitectureName, RepositoryUri, SignaturePolicy};
+
+    use super::*;
+
+    const SIGNED_BY: &str = include_str!("../../keys/ubuntu_24.04.asc");
+
+    // Armored keys are embedded as continuation lines with `.` for empty lines.
+    fn embedded_key() -> String {
+        SIGNED_BY
+            .trim()
+            .lines()
+            .map(|line| {
+                if line.is_empty() {
+                    " .".to_string()
+                } else {
+                    format!(" {line}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_parse_deb822_sources() {
+        let sources = parse_deb822_sources(&formatdoc! { "
+            # Repository for the example packages
+            Types: deb deb-src
+            URIs: https://repo.example.com/ubuntu/ https://repo-2.example.com/ubuntu
+            Suites: noble noble-updates
+            Components: main
+            Architectures: amd64
+            Signed-By:
+            {embedded_key}
+
+            Types: deb
+            URIs: https://other.example.com/ubuntu
+            Suites: noble
+            Components: main universe
+            Signature-Policy: disabled
+            Mirrors: https://mirror.example.com/ubuntu
+        ", embedded_key = embedded_key() })
+        .unwrap();
+
+        assert_eq!(sources.len(), 3);
+        assert_eq!(
+            sources[0],
+            CustomSource {
+                uri: RepositoryUri::from("https://repo.example.com/ubuntu"),
+                suites: vec!["noble".to_string(), "noble-updates".to_string()],
+                components: vec!["main".to_string()],
+                signed_by: Some(SIGNED_BY.trim().to_string()),
+                signed_by_file: None,
+                key_fingerprint: None,
+                signature_policy: SignaturePolicy::Required,
+                trusted: false,
+                allow_weak_hashes: false,
+                client_identity: None,
+                mirrors: vec![],
+                download_url: None,
+                architectures: vec![ArchitectureName::AMD_64],
+            }
+        );
+        assert_eq!(sources[1].uri.as_str(), "https://repo-2.example.com/ubuntu");
+        assert!(sources[2].architectures.is_empty());
+        assert_eq!(sources[2].signature_policy, SignaturePolicy::Disabled);
+        assert_eq!(sources[2].signed_by, None);
+        assert_eq!(
+            sources[2].components,
+            vec!["main".to_string(), "universe".to_string()]
+        );
+        assert_eq!(
+            sources[2].mirrors,
+            vec![RepositoryUri::from("https://mirror.example.com/ubuntu")]
+        );
+    }
+
+    #[test]
+    fn test_parse_deb822_sources_skips_disabled_and_source_only_stanzas() {
+        let sources = parse_deb822_sources(indoc! { "
+            Types: deb
+            URIs: https://repo.example.com/ubuntu
+            Suites: noble
+            Components: main
+            Signature-Policy: disabled
+            Enabled: no
+
+            types: deb-src
+            uris: https://repo.example.com/ubuntu
+            suites: noble
+            components: main
+        " })
+        .unwrap();
+        assert!(sources.is_empty());
+    }
+
+    #[test]
+    fn test_parse_deb822_sources_missing_field() {
+        let error = parse_deb822_sources(indoc! { "
+            Types: deb
+            URIs: https://repo.example.com/ubuntu
+            Suites: noble
+            Signature-Policy: disabled
+        " })
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            ParseCustomSourceError::MissingKey("Components")
+        ));
+
+        let error = parse_deb822_sources(indoc! { "
+            URIs: https://repo.example.com/ubuntu
+            Suites: noble
+            Components: main
+        " })
+        .unwrap_err();
+        assert!(matches!(error, ParseCustomSourceError::MissingKey("Types")));
+    }
+
+    #[test]
+    fn test_parse_deb822_sources_invalid_signed_by() {
+        let error = parse_deb822_sources(indoc! { "
+            Types: deb
+            URIs: https://repo.example.com/ubuntu
+            Suites: noble
+            Components: main
+            Signed-By: /usr/share/keyrings/example.gpg
+        " })
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            ParseCustomSourceError::InvalidValue("Signed-By", _)
+        ));
+    }
+
+    #[test]
+    fn test_parse_deb822_sources_invalid_architectures() {
+        let error = parse_deb822_sources(indoc! { "
+            Types: deb
+            URIs: https://repo.example.com/ubuntu
+            Suites: noble
+            Components: main
+            Signature-Policy: disabled
+            Architectures: amd64 mips
+        " })
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            ParseCustomSourceError::InvalidValue("Architectures", value) if value == "mips"
+        ));
+    }
+
+    #[test]
+    fn test_parse_deb822_sources_invalid_syntax() {
+        let error = parse_deb822_sources(indoc! { "
+            Types: deb
+            deb https://repo.example.com/ubuntu noble main
+        " })
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            ParseCustomSourceError::InvalidDeb822(line) if line == "deb https://repo.example.com/ubuntu noble main"
+        ));
+
+        let error = parse_deb822_sources(" continued\n").unwrap_err();
+        assert!(matches!(error, ParseCustomSourceError::InvalidDeb822(_)));
+    }
+}
@@ -0,0 +1,132 @@
+use libcnb::data::buildpack::BuildpackVersion;
+use toml_edit::TableLike;
+
+// A configuration option in `[com.heroku.buildpacks.deb-packages]` that's been deprecated.
+//
+// Deprecated options keep working (with a warning) until the buildpack reaches the major version
+// they're removed in. From that version on, using the option fails the build with the same hint
+// so the parsing code for it can be deleted in a later release.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) struct Deprecation {
+    pub(crate) option: &'static str,
+    pub(crate) hint: &'static str,
+    pub(crate) removed_in_major_version: u64,
+}
+
+// Add an entry here when deprecating an option, e.g.;
+//
+// Deprecation {
+//     option: "old_option",
+//     hint: "Use `new_option` instead.",
+//     removed_in_major_version: 1,
+// }
+pub(crate) const DEPRECATIONS: &[Deprecation] = &[];
+
+pub(crate) fn find_deprecations(
+    config_item: &dyn TableLike,
+    deprecations: &[Deprecation],
+) -> Vec<Deprecation> {
+    deprecations
+        .iter()
+        .filter(|deprecation| config_item.contains_key(deprecation.option))
+        .copied()
+        .collect()
+}
+
+// Splits the deprecated options that are in use into those that only warn and those that have
+// been removed as of the given buildpack version.
+pub(crate) fn partition_deprecations(
+    deprecations: &[Deprecation],
+    buildpack_version: &BuildpackVersion,
+) -> (Vec<Deprecation>, Vec<Deprecation>) {
+    deprecations
+        .iter()
+        .copied()
+        .partition(|deprecation| buildpack_version.major < deprecation.removed_in_major_version)
+}
+
+// All the deprecated options are reported in a single warning so they can be fixed together.
+pub(crate) fn deprecation_warning(deprecations: &[Deprecation]) -> String {
+    let options = deprecations
+        .iter()
+        .map(|deprecation| {
+            format!(
+                "- `{}` will be removed in v{}.0.0. {}",
+                deprecation.option, deprecation.removed_in_major_version, deprecation.hint
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        "The following options in `[com.heroku.buildpacks.deb-packages]` are deprecated:\n\n{options}"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use toml_edit::DocumentMut;
+
+    use super::*;
+
+    const TEST_DEPRECATIONS: &[Deprecation] = &[
+        Deprecation {
+            option: "old_option",
+            hint: "Use `new_option` instead.",
+            removed_in_major_version: 1,
+        },
+        Deprecation {
+            option: "older_option",
+            hint: "It no longer has any effect.",
+            removed_in_major_version: 0,
+        },
+        Deprecation {
+            option: "unused_option",
+            hint: "Use `another_option` instead.",
+            removed_in_major_version: 1,
+        },
+    ];
+
+    #[test]
+    fn test_find_deprecations() {
+        let doc = DocumentMut::from_str(indoc::indoc! { r#"
+            install = ["curl"]
+            old_option = true
+            older_option = "value"
+        "# })
+        .unwrap();
+
+        assert_eq!(
+            find_deprecations(doc.as_table(), TEST_DEPRECATIONS),
+            vec![TEST_DEPRECATIONS[0], TEST_DEPRECATIONS[1]]
+        );
+    }
+
+    #[test]
+    fn test_partition_deprecations() {
+        let (warnings, removed) =
+            partition_deprecations(TEST_DEPRECATIONS, &BuildpackVersion::new(0, 9, 0));
+        assert_eq!(warnings, vec![TEST_DEPRECATIONS[0], TEST_DEPRECATIONS[2]]);
+        assert_eq!(removed, vec![TEST_DEPRECATIONS[1]]);
+
+        let (warnings, removed) =
+            partition_deprecations(TEST_DEPRECATIONS, &BuildpackVersion::new(1, 0, 0));
+        assert!(warnings.is_empty());
+        assert_eq!(removed, TEST_DEPRECATIONS.to_vec());
+    }
+
+    #[test]
+    fn test_deprecation_warning() {
+        assert_eq!(
+            deprecation_warning(&[TEST_DEPRECATIONS[0], TEST_DEPRECATIONS[2]]),
+            indoc::indoc! { "
+                The following options in `[com.heroku.buildpacks.deb-packages]` are deprecated:
+
+                - `old_option` will be removed in v1.0.0. Use `new_option` instead.
+                - `unused_option` will be removed in v1.0.0. Use `another_option` instead."
+            }
+        );
+    }
+}
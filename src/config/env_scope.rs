@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use indexmap::IndexSet;
+use libcnb::layer_env::Scope;
+
+use crate::config::RequestedPackage;
+
+// Controls whether the environment variables exported by the installed packages are available
+// while building (to later buildpacks), at launch, or both.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
+pub(crate) enum EnvScope {
+    Build,
+    Launch,
+    #[default]
+    All,
+}
+
+impl FromStr for EnvScope {
+    type Err = ParseEnvScopeError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "build" => Ok(EnvScope::Build),
+            "launch" => Ok(EnvScope::Launch),
+            "all" => Ok(EnvScope::All),
+            _ => Err(ParseEnvScopeError(value.to_string())),
+        }
+    }
+}
+
+impl Display for EnvScope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnvScope::Build => write!(f, "build"),
+            EnvScope::Launch => write!(f, "launch"),
+            EnvScope::All => write!(f, "all"),
+        }
+    }
+}
+
+impl From<EnvScope> for Scope {
+    fn from(value: EnvScope) -> Self {
+        match value {
+            EnvScope::Build => Scope::Build,
+            EnvScope::Launch => Scope::Launch,
+            EnvScope::All => Scope::All,
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct ParseEnvScopeError(pub(crate) String);
+
+// The scope configured for the installation along with any overrides from requested packages.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub(crate) struct EnvScopes {
+    default: EnvScope,
+    packages: HashMap<String, EnvScope>,
+}
+
+impl EnvScopes {
+    pub(crate) fn new(default: EnvScope, requested_packages: &IndexSet<RequestedPackage>) -> Self {
+        EnvScopes {
+            default,
+            packages: requested_packages
+                .iter()
+                .filter_map(|requested_package| {
+                    requested_package
+                        .env_scope
+                        .map(|env_scope| (requested_package.qualified_name(), env_scope))
+                })
+                .collect(),
+        }
+    }
+
+    pub(crate) fn default_scope(&self) -> Scope {
+        self.default.into()
+    }
+
+    pub(crate) fn package_scope(&self, package_name: &str) -> Scope {
+        self.packages
+            .get(package_name)
+            .copied()
+            .unwrap_or(self.default)
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debian::PackageName;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(EnvScope::from_str("build").unwrap(), EnvScope::Build);
+        assert_eq!(EnvScope::from_str("launch").unwrap(), EnvScope::Launch);
+        assert_eq!(EnvScope::from_str("all").unwrap(), EnvScope::All);
+        assert_eq!(
+            EnvScope::from_str("Build").unwrap_err(),
+            ParseEnvScopeError("Build".to_string())
+        );
+    }
+
+    #[test]
+    fn test_package_scope() {
+        let env_scopes = EnvScopes::new(
+            EnvScope::Launch,
+            &IndexSet::from([
+                RequestedPackage {
+                    name: PackageName::from_str("ffmpeg").unwrap(),
+                    architecture: None,
+                    skip_dependencies: false,
+                    force: false,
+                    env_scope: None,
+                    optional: false,
+                    maintainer_scripts: None,
+                    version: None,
+                },
+                RequestedPackage {
+                    name: PackageName::from_str("git").unwrap(),
+                    architecture: None,
+                    skip_dependencies: false,
+                    force: false,
+                    env_scope: Some(EnvScope::Build),
+                    optional: false,
+                    maintainer_scripts: None,
+                    version: None,
+                },
+            ]),
+        );
+        assert_eq!(env_scopes.default_scope(), Scope::Launch);
+        assert_eq!(env_scopes.package_scope("ffmpeg"), Scope::Launch);
+        assert_eq!(env_scopes.package_scope("git"), Scope::Build);
+        assert_eq!(env_scopes.package_scope("libgit2-1.7"), Scope::Launch);
+    }
+}
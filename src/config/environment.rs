@@ -1,11 +1,12 @@
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
-use toml_edit::{DocumentMut};
+use toml_edit::DocumentMut;
 
 #[derive(Debug, Default)]
 pub(crate) struct Environment {
     variables: HashMap<String, String>,
+    packages: HashMap<String, String>,
 }
 
 impl Environment {
@@ -24,6 +25,10 @@ impl Environment {
                 .and_then(|item| item.as_array())
             {
                 for table in array_of_tables.iter() {
+                    let package_name = table
+                        .as_inline_table()
+                        .and_then(|t| t.get("name"))
+                        .and_then(|n| n.as_str());
                     if let Some(env_table) = table
                         .as_inline_table()
                         .and_then(|t| t.get("env"))
@@ -31,8 +36,14 @@ impl Environment {
                     {
                         for (key, value) in env_table.iter() {
                             if let Some(value_str) = value.as_str() {
-                                let value_with_install_dir = value_str.replace("{install_dir}", install_dir);
-                                env.variables.insert(key.to_string(), value_with_install_dir);
+                                let value_with_install_dir =
+                                    value_str.replace("{install_dir}", install_dir);
+                                env.variables
+                                    .insert(key.to_string(), value_with_install_dir);
+                                if let Some(package_name) = package_name {
+                                    env.packages
+                                        .insert(key.to_string(), package_name.to_string());
+                                }
                             }
                         }
                     }
@@ -47,6 +58,10 @@ impl Environment {
         &self.variables
     }
 
+    /// Get the name of the package that configured an environment variable.
+    pub(crate) fn get_package(&self, key: &str) -> Option<&str> {
+        self.packages.get(key).map(String::as_str)
+    }
 }
 
 #[cfg(test)]
@@ -83,9 +98,23 @@ mod tests {
         // println!("GS_LIB: {:?}", variables.get("GS_LIB"));
         // println!("GS_FONTPATH: {:?}", variables.get("GS_FONTPATH"));
 
-        assert_eq!(variables.get("GIT_EXEC_PATH"), Some(&"/build/usr/lib/git-core".to_string()));
-        assert_eq!(variables.get("GIT_TEMPLATE_DIR"), Some(&"/build/usr/lib/git-core/templates".to_string()));
-        assert_eq!(variables.get("GS_LIB"), Some(&"/build/var/lib/ghostscript".to_string()));
-        assert_eq!(variables.get("GS_FONTPATH"), Some(&"/build/var/lib/ghostscript/fonts".to_string()));
+        assert_eq!(
+            variables.get("GIT_EXEC_PATH"),
+            Some(&"/build/usr/lib/git-core".to_string())
+        );
+        assert_eq!(
+            variables.get("GIT_TEMPLATE_DIR"),
+            Some(&"/build/usr/lib/git-core/templates".to_string())
+        );
+        assert_eq!(
+            variables.get("GS_LIB"),
+            Some(&"/build/var/lib/ghostscript".to_string())
+        );
+        assert_eq!(
+            variables.get("GS_FONTPATH"),
+            Some(&"/build/var/lib/ghostscript/fonts".to_string())
+        );
+        assert_eq!(env.get_package("GIT_EXEC_PATH"), Some("git"));
+        assert_eq!(env.get_package("GS_LIB"), Some("ghostscript"));
     }
 }
@@ -0,0 +1,62 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+// Controls what happens when a package contains a file that already exists in the packages layer
+// when it's extracted (e.g.; a file kept from a restored layer or written by another package's
+// `postinst` script). Directories are always shared and never count as existing files.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
+pub(crate) enum ExistingFilesPolicy {
+    #[default]
+    Overwrite,
+    Skip,
+    Error,
+}
+
+impl FromStr for ExistingFilesPolicy {
+    type Err = ParseExistingFilesPolicyError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "overwrite" => Ok(ExistingFilesPolicy::Overwrite),
+            "skip" => Ok(ExistingFilesPolicy::Skip),
+            "error" => Ok(ExistingFilesPolicy::Error),
+            _ => Err(ParseExistingFilesPolicyError(value.to_string())),
+        }
+    }
+}
+
+impl Display for ExistingFilesPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExistingFilesPolicy::Overwrite => write!(f, "overwrite"),
+            ExistingFilesPolicy::Skip => write!(f, "skip"),
+            ExistingFilesPolicy::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct ParseExistingFilesPolicyError(pub(crate) String);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_existing_files_policy() {
+        for policy in [
+            ExistingFilesPolicy::Overwrite,
+            ExistingFilesPolicy::Skip,
+            ExistingFilesPolicy::Error,
+        ] {
+            assert_eq!(
+                ExistingFilesPolicy::from_str(&policy.to_string()),
+                Ok(policy)
+            );
+        }
+        assert_eq!(
+            ExistingFilesPolicy::from_str("replace"),
+            Err(ParseExistingFilesPolicyError("replace".to_string()))
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use toml_edit::Value;
+
+// A locale to generate in the form `language[_territory].codeset[@modifier]` (e.g.; `en_US.UTF-8`,
+// `de_DE.UTF-8@euro`). The codeset is required since it selects the charmap the locale is compiled
+// with.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub(crate) struct Locale {
+    name: String,
+    language: String,
+    codeset: String,
+    modifier: Option<String>,
+}
+
+impl Locale {
+    // The locale definition to compile from `/usr/share/i18n/locales` (e.g.; `de_DE@euro`).
+    pub(crate) fn input(&self) -> String {
+        match &self.modifier {
+            Some(modifier) => format!("{}@{modifier}", self.language),
+            None => self.language.clone(),
+        }
+    }
+
+    // The charmap to compile with from `/usr/share/i18n/charmaps` (e.g.; `UTF-8`).
+    pub(crate) fn charmap(&self) -> &str {
+        &self.codeset
+    }
+
+    // The directory name glibc looks for under `LOCPATH`. The codeset is normalized the same way
+    // glibc does when loading a locale so `en_US.UTF-8` is stored as `en_US.utf8`.
+    pub(crate) fn directory_name(&self) -> String {
+        let codeset = self
+            .codeset
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .map(|c| c.to_ascii_lowercase())
+            .collect::<String>();
+        match &self.modifier {
+            Some(modifier) => format!("{}.{codeset}@{modifier}", self.language),
+            None => format!("{}.{codeset}", self.language),
+        }
+    }
+}
+
+impl FromStr for Locale {
+    type Err = ParseLocaleError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid_locale = || ParseLocaleError::InvalidLocale(value.to_string());
+
+        let (value_without_modifier, modifier) = match value.split_once('@') {
+            Some((value, modifier)) => (value, Some(modifier)),
+            None => (value, None),
+        };
+        let (language, codeset) = value_without_modifier
+            .split_once('.')
+            .ok_or_else(invalid_locale)?;
+
+        let is_valid_language = !language.is_empty()
+            && language
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+        let is_valid_codeset = !codeset.is_empty()
+            && codeset
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        let is_valid_modifier = modifier.map_or(true, |modifier| {
+            !modifier.is_empty() && modifier.chars().all(|c| c.is_ascii_alphanumeric())
+        });
+
+        if is_valid_language && is_valid_codeset && is_valid_modifier {
+            Ok(Locale {
+                name: value.to_string(),
+                language: language.to_string(),
+                codeset: codeset.to_string(),
+                modifier: modifier.map(ToString::to_string),
+            })
+        } else {
+            Err(invalid_locale())
+        }
+    }
+}
+
+impl TryFrom<&Value> for Locale {
+    type Error = ParseLocaleError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(formatted_string) => Locale::from_str(formatted_string.value()),
+            _ => Err(ParseLocaleError::UnexpectedTomlValue(Box::new(
+                value.clone(),
+            ))),
+        }
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", &self.name)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum ParseLocaleError {
+    InvalidLocale(String),
+    UnexpectedTomlValue(Box<Value>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        let locale = Locale::from_str("en_US.UTF-8").unwrap();
+        assert_eq!(locale.to_string(), "en_US.UTF-8");
+        assert_eq!(locale.input(), "en_US");
+        assert_eq!(locale.charmap(), "UTF-8");
+        assert_eq!(locale.directory_name(), "en_US.utf8");
+
+        let locale = Locale::from_str("de_DE.ISO-8859-15@euro").unwrap();
+        assert_eq!(locale.input(), "de_DE@euro");
+        assert_eq!(locale.charmap(), "ISO-8859-15");
+        assert_eq!(locale.directory_name(), "de_DE.iso885915@euro");
+    }
+
+    #[test]
+    fn test_from_str_invalid() {
+        for invalid_locale in [
+            "",
+            "en_US",
+            ".UTF-8",
+            "en_US.",
+            "en_US.UTF-8@",
+            "../en.UTF-8",
+        ] {
+            assert!(
+                Locale::from_str(invalid_locale).is_err(),
+                "{invalid_locale} should be invalid"
+            );
+        }
+    }
+
+    #[test]
+    fn test_try_from_unexpected_toml_value() {
+        assert!(matches!(
+            Locale::try_from(&Value::from(123)).unwrap_err(),
+            ParseLocaleError::UnexpectedTomlValue(_)
+        ));
+    }
+}
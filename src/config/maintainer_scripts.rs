@@ -0,0 +1,132 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use indexmap::IndexSet;
+
+use crate::config::RequestedPackage;
+
+// Controls what happens when the postinst script of a package that opted into running its
+// maintainer scripts exits with a non-zero status.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
+pub(crate) enum ScriptFailurePolicy {
+    Warn,
+    #[default]
+    Error,
+}
+
+impl FromStr for ScriptFailurePolicy {
+    type Err = ParseScriptFailurePolicyError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "warn" => Ok(ScriptFailurePolicy::Warn),
+            "error" => Ok(ScriptFailurePolicy::Error),
+            _ => Err(ParseScriptFailurePolicyError(value.to_string())),
+        }
+    }
+}
+
+impl Display for ScriptFailurePolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScriptFailurePolicy::Warn => write!(f, "warn"),
+            ScriptFailurePolicy::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct ParseScriptFailurePolicyError(pub(crate) String);
+
+// The requested packages whose maintainer scripts should be run along with the policy for each one.
+// Scripts of any other package (including the dependencies of an opted-in package) are never run.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub(crate) struct MaintainerScripts {
+    packages: HashMap<String, ScriptFailurePolicy>,
+}
+
+impl MaintainerScripts {
+    pub(crate) fn new(requested_packages: &IndexSet<RequestedPackage>) -> Self {
+        MaintainerScripts {
+            packages: requested_packages
+                .iter()
+                .filter_map(|requested_package| {
+                    requested_package
+                        .maintainer_scripts
+                        .map(|policy| (requested_package.qualified_name(), policy))
+                })
+                .collect(),
+        }
+    }
+
+    pub(crate) fn failure_policy(&self, package_name: &str) -> Option<ScriptFailurePolicy> {
+        self.packages.get(package_name).copied()
+    }
+
+    // The configured packages and policies in a stable order for the layer metadata.
+    pub(crate) fn policies(&self) -> BTreeMap<String, String> {
+        self.packages
+            .iter()
+            .map(|(package_name, policy)| (package_name.clone(), policy.to_string()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debian::PackageName;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(
+            ScriptFailurePolicy::from_str("warn").unwrap(),
+            ScriptFailurePolicy::Warn
+        );
+        assert_eq!(
+            ScriptFailurePolicy::from_str("error").unwrap(),
+            ScriptFailurePolicy::Error
+        );
+        assert_eq!(
+            ScriptFailurePolicy::from_str("ignore").unwrap_err(),
+            ParseScriptFailurePolicyError("ignore".to_string())
+        );
+    }
+
+    #[test]
+    fn test_failure_policy() {
+        let maintainer_scripts = MaintainerScripts::new(&IndexSet::from([
+            RequestedPackage {
+                name: PackageName::from_str("ffmpeg").unwrap(),
+                architecture: None,
+                skip_dependencies: false,
+                force: false,
+                env_scope: None,
+                optional: false,
+                maintainer_scripts: None,
+                version: None,
+            },
+            RequestedPackage {
+                name: PackageName::from_str("ca-certificates-java").unwrap(),
+                architecture: None,
+                skip_dependencies: false,
+                force: false,
+                env_scope: None,
+                optional: false,
+                maintainer_scripts: Some(ScriptFailurePolicy::Warn),
+                version: None,
+            },
+        ]));
+        assert_eq!(maintainer_scripts.failure_policy("ffmpeg"), None);
+        assert_eq!(
+            maintainer_scripts.failure_policy("ca-certificates-java"),
+            Some(ScriptFailurePolicy::Warn)
+        );
+        assert_eq!(maintainer_scripts.failure_policy("openjdk-17-jre"), None);
+        assert_eq!(
+            maintainer_scripts.policies(),
+            BTreeMap::from([("ca-certificates-java".to_string(), "warn".to_string())])
+        );
+    }
+}
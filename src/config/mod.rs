@@ -1,6 +1,38 @@
 pub(crate) use buildpack_config::*;
+pub(crate) use custom_source::*;
+pub(crate) use deb822_source::*;
+pub(crate) use deprecation::*;
+pub(crate) use env_scope::*;
+pub(crate) use existing_files::*;
+pub(crate) use locale::*;
+pub(crate) use maintainer_scripts::*;
+pub(crate) use network::*;
+pub(crate) use package_glob::*;
+pub(crate) use package_regex::*;
+pub(crate) use phased_updates::*;
+pub(crate) use ppa::*;
+pub(crate) use preset::*;
+pub(crate) use proxy::*;
 pub(crate) use requested_package::*;
+pub(crate) use snapshot::*;
+pub(crate) use version_constraint::*;
 
 pub(crate) mod buildpack_config;
-pub(crate) mod requested_package;
+pub(crate) mod custom_source;
+pub(crate) mod deb822_source;
+pub(crate) mod deprecation;
+pub(crate) mod env_scope;
 pub(crate) mod environment;
+pub(crate) mod existing_files;
+pub(crate) mod locale;
+pub(crate) mod maintainer_scripts;
+pub(crate) mod network;
+pub(crate) mod package_glob;
+pub(crate) mod package_regex;
+pub(crate) mod phased_updates;
+pub(crate) mod ppa;
+pub(crate) mod preset;
+pub(crate) mod proxy;
+pub(crate) mod requested_package;
+pub(crate) mod snapshot;
+pub(crate) mod version_constraint;
@@ -0,0 +1,261 @@
+use std::time::Duration;
+
+use libcnb::Env;
+use reqwest_retry::policies::ExponentialBackoff;
+use toml_edit::TableLike;
+
+pub(crate) const TIMEOUT_ENV_VAR: &str = "BP_DEB_PACKAGES_TIMEOUT";
+pub(crate) const MAX_RETRIES_ENV_VAR: &str = "BP_DEB_PACKAGES_MAX_RETRIES";
+pub(crate) const MIN_RETRY_INTERVAL_ENV_VAR: &str = "BP_DEB_PACKAGES_MIN_RETRY_INTERVAL";
+pub(crate) const MAX_RETRY_INTERVAL_ENV_VAR: &str = "BP_DEB_PACKAGES_MAX_RETRY_INTERVAL";
+
+// How long requests to package repositories can take and how failed requests are retried. Each
+// setting can be configured in the `network` table of project.toml and overridden by an
+// environment variable so a CI environment can tune them without changing the application. All
+// durations are whole seconds.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) struct NetworkSettings {
+    pub(crate) timeout: Duration,
+    pub(crate) max_retries: u32,
+    pub(crate) min_retry_interval: Duration,
+    pub(crate) max_retry_interval: Duration,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        NetworkSettings {
+            timeout: Duration::from_secs(60 * 5),
+            max_retries: 5,
+            min_retry_interval: Duration::from_secs(1),
+            max_retry_interval: Duration::from_secs(60 * 30),
+        }
+    }
+}
+
+impl NetworkSettings {
+    pub(crate) fn merge_env(&mut self, env: &Env) -> Result<(), ParseNetworkSettingsError> {
+        let lookup = |name: &'static str| {
+            env.get(name)
+                .map(|value| value.to_string_lossy().trim().to_string())
+                .filter(|value| !value.is_empty())
+                .map(|value| {
+                    value
+                        .parse::<u64>()
+                        .map_err(|_| ParseNetworkSettingsError::InvalidValue(name, value))
+                })
+                .transpose()
+        };
+
+        if let Some(timeout) = lookup(TIMEOUT_ENV_VAR)? {
+            self.timeout = parse_timeout(TIMEOUT_ENV_VAR, timeout)?;
+        }
+        if let Some(max_retries) = lookup(MAX_RETRIES_ENV_VAR)? {
+            self.max_retries = parse_max_retries(MAX_RETRIES_ENV_VAR, max_retries)?;
+        }
+        if let Some(min_retry_interval) = lookup(MIN_RETRY_INTERVAL_ENV_VAR)? {
+            self.min_retry_interval = Duration::from_secs(min_retry_interval);
+        }
+        if let Some(max_retry_interval) = lookup(MAX_RETRY_INTERVAL_ENV_VAR)? {
+            self.max_retry_interval = Duration::from_secs(max_retry_interval);
+        }
+
+        self.validate()
+    }
+
+    pub(crate) fn retry_policy(&self) -> ExponentialBackoff {
+        ExponentialBackoff::builder()
+            .retry_bounds(self.min_retry_interval, self.max_retry_interval)
+            .build_with_max_retries(self.max_retries)
+    }
+
+    // The retry policy panics if the bounds are reversed.
+    fn validate(&self) -> Result<(), ParseNetworkSettingsError> {
+        if self.min_retry_interval > self.max_retry_interval {
+            Err(ParseNetworkSettingsError::InvalidRetryIntervals(
+                self.min_retry_interval,
+                self.max_retry_interval,
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<&dyn TableLike> for NetworkSettings {
+    type Error = ParseNetworkSettingsError;
+
+    fn try_from(table: &dyn TableLike) -> Result<Self, Self::Error> {
+        let get_integer = |key: &'static str| {
+            table
+                .get(key)
+                .map(|item| {
+                    item.as_integer()
+                        .and_then(|value| u64::try_from(value).ok())
+                        .ok_or_else(|| {
+                            ParseNetworkSettingsError::InvalidValue(key, item.to_string())
+                        })
+                })
+                .transpose()
+        };
+
+        let mut network_settings = NetworkSettings::default();
+        if let Some(timeout) = get_integer(TIMEOUT_KEY)? {
+            network_settings.timeout = parse_timeout(TIMEOUT_KEY, timeout)?;
+        }
+        if let Some(max_retries) = get_integer(MAX_RETRIES_KEY)? {
+            network_settings.max_retries = parse_max_retries(MAX_RETRIES_KEY, max_retries)?;
+        }
+        if let Some(min_retry_interval) = get_integer(MIN_RETRY_INTERVAL_KEY)? {
+            network_settings.min_retry_interval = Duration::from_secs(min_retry_interval);
+        }
+        if let Some(max_retry_interval) = get_integer(MAX_RETRY_INTERVAL_KEY)? {
+            network_settings.max_retry_interval = Duration::from_secs(max_retry_interval);
+        }
+        network_settings.validate()?;
+        Ok(network_settings)
+    }
+}
+
+fn parse_timeout(name: &'static str, value: u64) -> Result<Duration, ParseNetworkSettingsError> {
+    if value == 0 {
+        Err(ParseNetworkSettingsError::InvalidValue(
+            name,
+            value.to_string(),
+        ))?;
+    }
+    Ok(Duration::from_secs(value))
+}
+
+fn parse_max_retries(name: &'static str, value: u64) -> Result<u32, ParseNetworkSettingsError> {
+    u32::try_from(value)
+        .map_err(|_| ParseNetworkSettingsError::InvalidValue(name, value.to_string()))
+}
+
+// The name is either the key in the `network` table or the environment variable the value was
+// read from.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum ParseNetworkSettingsError {
+    InvalidValue(&'static str, String),
+    InvalidRetryIntervals(Duration, Duration),
+    UnexpectedTomlValue(String),
+}
+
+const TIMEOUT_KEY: &str = "timeout";
+const MAX_RETRIES_KEY: &str = "max_retries";
+const MIN_RETRY_INTERVAL_KEY: &str = "min_retry_interval";
+const MAX_RETRY_INTERVAL_KEY: &str = "max_retry_interval";
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use toml_edit::DocumentMut;
+
+    use super::*;
+
+    fn parse_network_settings(toml: &str) -> Result<NetworkSettings, ParseNetworkSettingsError> {
+        let doc = DocumentMut::from_str(toml).unwrap();
+        NetworkSettings::try_from(doc.get("network").unwrap().as_table_like().unwrap())
+    }
+
+    fn env(variables: &[(&str, &str)]) -> Env {
+        let mut env = Env::new();
+        for (name, value) in variables {
+            env.insert(name, value);
+        }
+        env
+    }
+
+    #[test]
+    fn test_parse_network_settings() {
+        let network_settings = parse_network_settings(
+            r"
+[network]
+timeout = 60
+max_retries = 0
+min_retry_interval = 2
+max_retry_interval = 10
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            network_settings,
+            NetworkSettings {
+                timeout: Duration::from_secs(60),
+                max_retries: 0,
+                min_retry_interval: Duration::from_secs(2),
+                max_retry_interval: Duration::from_secs(10),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_network_settings_uses_defaults() {
+        assert_eq!(
+            parse_network_settings("[network]").unwrap(),
+            NetworkSettings::default()
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_timeout() {
+        for value in ["0", "-1", "\"60\""] {
+            match parse_network_settings(&format!("network = {{ timeout = {value} }}")) {
+                Err(ParseNetworkSettingsError::InvalidValue(TIMEOUT_KEY, invalid_value)) => {
+                    assert_eq!(invalid_value.trim(), value);
+                }
+                result => panic!("Not the expected result - {result:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_retry_intervals() {
+        assert_eq!(
+            parse_network_settings(
+                "network = { min_retry_interval = 60, max_retry_interval = 10 }"
+            ),
+            Err(ParseNetworkSettingsError::InvalidRetryIntervals(
+                Duration::from_secs(60),
+                Duration::from_secs(10)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_merge_env() {
+        let mut network_settings = NetworkSettings::default();
+        network_settings
+            .merge_env(&env(&[
+                (TIMEOUT_ENV_VAR, "30"),
+                (MAX_RETRIES_ENV_VAR, " 10 "),
+                (MAX_RETRY_INTERVAL_ENV_VAR, ""),
+            ]))
+            .unwrap();
+        assert_eq!(
+            network_settings,
+            NetworkSettings {
+                timeout: Duration::from_secs(30),
+                max_retries: 10,
+                ..NetworkSettings::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_merge_env_with_invalid_value() {
+        assert_eq!(
+            NetworkSettings::default().merge_env(&env(&[(MAX_RETRIES_ENV_VAR, "many")])),
+            Err(ParseNetworkSettingsError::InvalidValue(
+                MAX_RETRIES_ENV_VAR,
+                "many".to_string()
+            ))
+        );
+        assert_eq!(
+            NetworkSettings::default().merge_env(&env(&[(MIN_RETRY_INTERVAL_ENV_VAR, "3600")])),
+            Err(ParseNetworkSettingsError::InvalidRetryIntervals(
+                Duration::from_secs(3600),
+                Duration::from_secs(60 * 30)
+            ))
+        );
+    }
+}
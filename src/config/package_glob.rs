@@ -0,0 +1,142 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use toml_edit::Value;
+
+// A package name pattern where `*` matches any sequence of characters and `?` matches a
+// single character (e.g.; `*-doc`, `lib*-dbg`).
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub(crate) struct PackageGlob(String);
+
+impl PackageGlob {
+    pub(crate) fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub(crate) fn matches(&self, package_name: &str) -> bool {
+        let pattern = self.0.as_bytes();
+        let name = package_name.as_bytes();
+
+        let (mut p, mut n) = (0, 0);
+        // position of the last `*` seen in the pattern and the name position it was matched at
+        let mut backtrack: Option<(usize, usize)> = None;
+
+        while n < name.len() {
+            match pattern.get(p) {
+                Some(b'*') => {
+                    backtrack = Some((p, n));
+                    p += 1;
+                }
+                Some(b'?') => {
+                    p += 1;
+                    n += 1;
+                }
+                Some(c) if *c == name[n] => {
+                    p += 1;
+                    n += 1;
+                }
+                _ => match backtrack {
+                    Some((star_p, star_n)) => {
+                        backtrack = Some((star_p, star_n + 1));
+                        p = star_p + 1;
+                        n = star_n + 1;
+                    }
+                    None => return false,
+                },
+            }
+        }
+
+        pattern[p..].iter().all(|c| *c == b'*')
+    }
+}
+
+impl FromStr for PackageGlob {
+    type Err = ParsePackageGlobError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        // Patterns use the same characters allowed in package names along with the `*` and `?`
+        // wildcards.
+        let is_valid_package_glob = !value.is_empty()
+            && value
+                .chars()
+                .all(|c| matches!(c, 'a'..='z' | '0'..='9' | '+' | '-' | '.' | '*' | '?'));
+
+        if is_valid_package_glob {
+            Ok(PackageGlob(value.to_string()))
+        } else {
+            Err(ParsePackageGlobError::InvalidPackageGlob(value.to_string()))
+        }
+    }
+}
+
+impl TryFrom<&Value> for PackageGlob {
+    type Error = ParsePackageGlobError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(formatted_string) => PackageGlob::from_str(formatted_string.value()),
+            _ => Err(ParsePackageGlobError::UnexpectedTomlValue(Box::new(
+                value.clone(),
+            ))),
+        }
+    }
+}
+
+impl Display for PackageGlob {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", &self.0)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum ParsePackageGlobError {
+    InvalidPackageGlob(String),
+    UnexpectedTomlValue(Box<Value>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let cases = [
+            ("*-doc", "libgit2-doc", true),
+            ("*-doc", "libgit2-dev", false),
+            ("*-doc", "-doc", true),
+            ("lib*-dbg", "libssl3-dbg", true),
+            ("lib*-dbg", "openssl-dbg", false),
+            ("lib?", "liba", true),
+            ("lib?", "libab", false),
+            ("*", "anything", true),
+            ("git", "git", true),
+            ("git", "git-man", false),
+            ("*a*b*", "xxaxxbxx", true),
+            ("*a*b*", "xxbxxaxx", false),
+        ];
+        for (pattern, package_name, expected) in cases {
+            assert_eq!(
+                PackageGlob::from_str(pattern)
+                    .unwrap()
+                    .matches(package_name),
+                expected,
+                "{pattern} matching {package_name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_str_invalid() {
+        for invalid_pattern in ["", "*_doc", "Lib*", "[a-z]*"] {
+            assert!(PackageGlob::from_str(invalid_pattern).is_err());
+        }
+    }
+
+    #[test]
+    fn test_try_from_unexpected_toml_value() {
+        assert!(matches!(
+            PackageGlob::try_from(&Value::from(123)).unwrap_err(),
+            ParsePackageGlobError::UnexpectedTomlValue(_)
+        ));
+    }
+}
@@ -0,0 +1,101 @@
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use regex::Regex;
+
+// A regular expression matched against package names (e.g.; `^libreoffice-core.*`). Like with
+// `apt-get install` it isn't anchored so it matches any name that contains a match.
+#[derive(Debug, Clone)]
+pub(crate) struct PackageRegex(Regex);
+
+impl PackageRegex {
+    pub(crate) fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub(crate) fn matches(&self, package_name: &str) -> bool {
+        self.0.is_match(package_name)
+    }
+}
+
+impl PartialEq for PackageRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for PackageRegex {}
+
+impl Hash for PackageRegex {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl FromStr for PackageRegex {
+    type Err = ParsePackageRegexError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.trim().is_empty() {
+            return Err(ParsePackageRegexError {
+                value: value.to_string(),
+                reason: "the pattern is empty".to_string(),
+            });
+        }
+        Regex::new(value)
+            .map(PackageRegex)
+            .map_err(|e| ParsePackageRegexError {
+                value: value.to_string(),
+                reason: e.to_string(),
+            })
+    }
+}
+
+impl Display for PackageRegex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ParsePackageRegexError {
+    pub(crate) value: String,
+    pub(crate) reason: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let cases = [
+            ("^libreoffice-core.*", "libreoffice-core", true),
+            ("^libreoffice-core.*", "libreoffice-core-nogui", true),
+            ("^libreoffice-core.*", "libreoffice-common", false),
+            ("libicu[0-9]+$", "libicu74", true),
+            ("libicu[0-9]+$", "libicu-dev", false),
+            ("icu", "icu-devtools", true),
+        ];
+        for (pattern, package_name, expected) in cases {
+            assert_eq!(
+                PackageRegex::from_str(pattern)
+                    .unwrap()
+                    .matches(package_name),
+                expected,
+                "{pattern} matching {package_name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_str_invalid() {
+        for invalid_pattern in ["", " ", "^libreoffice-(core", "lib[icu"] {
+            assert!(
+                PackageRegex::from_str(invalid_pattern).is_err(),
+                "{invalid_pattern}"
+            );
+        }
+    }
+}
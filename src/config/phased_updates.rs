@@ -0,0 +1,56 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+// Controls whether versions still being phased in (see `RepositoryPackage::is_phased_update`) are
+// installed. Like apt, phased updates are skipped by default in favor of the highest version that
+// has been released to every machine so builds don't pick up an update most machines haven't
+// received yet.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
+pub(crate) enum PhasedUpdatesPolicy {
+    #[default]
+    Skip,
+    Include,
+}
+
+impl FromStr for PhasedUpdatesPolicy {
+    type Err = ParsePhasedUpdatesPolicyError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "skip" => Ok(PhasedUpdatesPolicy::Skip),
+            "include" => Ok(PhasedUpdatesPolicy::Include),
+            _ => Err(ParsePhasedUpdatesPolicyError(value.to_string())),
+        }
+    }
+}
+
+impl Display for PhasedUpdatesPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PhasedUpdatesPolicy::Skip => write!(f, "skip"),
+            PhasedUpdatesPolicy::Include => write!(f, "include"),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct ParsePhasedUpdatesPolicyError(pub(crate) String);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_phased_updates_policy() {
+        for policy in [PhasedUpdatesPolicy::Skip, PhasedUpdatesPolicy::Include] {
+            assert_eq!(
+                PhasedUpdatesPolicy::from_str(&policy.to_string()),
+                Ok(policy)
+            );
+        }
+        assert_eq!(
+            PhasedUpdatesPolicy::from_str("force"),
+            Err(ParsePhasedUpdatesPolicyError("force".to_string()))
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::debian::RepositoryUri;
+
+// A Launchpad Personal Package Archive written with the `ppa:<owner>/<name>` shorthand used by
+// `add-apt-repository`. As with that tool, `ppa:<owner>` refers to the archive named `ppa`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct Ppa {
+    pub(crate) owner: String,
+    pub(crate) name: String,
+}
+
+impl Ppa {
+    pub(crate) fn uri(&self) -> RepositoryUri {
+        RepositoryUri::from(
+            format!(
+                "https://ppa.launchpadcontent.net/{}/{}/ubuntu",
+                self.owner, self.name
+            )
+            .as_str(),
+        )
+    }
+
+    // The Launchpad API resource describing the archive (e.g.; the fingerprint of its signing key).
+    pub(crate) fn launchpad_api_url(&self) -> String {
+        format!(
+            "https://api.launchpad.net/1.0/~{}/+archive/ubuntu/{}",
+            self.owner, self.name
+        )
+    }
+}
+
+impl FromStr for Ppa {
+    type Err = ParsePpaError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let error = || ParsePpaError(value.to_string());
+        let (owner, name) = value
+            .strip_prefix(PPA_PREFIX)
+            .map(|ppa| ppa.split_once('/').unwrap_or((ppa, DEFAULT_PPA_NAME)))
+            .ok_or_else(error)?;
+        if is_launchpad_name(owner) && is_launchpad_name(name) {
+            Ok(Ppa {
+                owner: owner.to_string(),
+                name: name.to_string(),
+            })
+        } else {
+            Err(error())
+        }
+    }
+}
+
+impl Display for Ppa {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{PPA_PREFIX}{}/{}", self.owner, self.name)
+    }
+}
+
+// Launchpad names start with a letter or number followed by lowercase letters, numbers, `+`,
+// `-`, or `.`.
+fn is_launchpad_name(value: &str) -> bool {
+    value
+        .chars()
+        .next()
+        .is_some_and(|char| char.is_ascii_lowercase() || char.is_ascii_digit())
+        && value.chars().all(|char| {
+            char.is_ascii_lowercase() || char.is_ascii_digit() || matches!(char, '+' | '-' | '.')
+        })
+}
+
+const PPA_PREFIX: &str = "ppa:";
+const DEFAULT_PPA_NAME: &str = "ppa";
+
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct ParsePpaError(pub(crate) String);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ppa() {
+        let ppa = Ppa::from_str("ppa:deadsnakes/nightly").unwrap();
+        assert_eq!(
+            ppa,
+            Ppa {
+                owner: "deadsnakes".to_string(),
+                name: "nightly".to_string()
+            }
+        );
+        assert_eq!(ppa.to_string(), "ppa:deadsnakes/nightly");
+        assert_eq!(
+            ppa.uri().as_str(),
+            "https://ppa.launchpadcontent.net/deadsnakes/nightly/ubuntu"
+        );
+        assert_eq!(
+            ppa.launchpad_api_url(),
+            "https://api.launchpad.net/1.0/~deadsnakes/+archive/ubuntu/nightly"
+        );
+    }
+
+    #[test]
+    fn test_parse_ppa_with_default_name() {
+        assert_eq!(
+            Ppa::from_str("ppa:ondrej").unwrap().to_string(),
+            "ppa:ondrej/ppa"
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_ppa() {
+        for value in [
+            "deadsnakes/ppa",
+            "ppa:",
+            "ppa:/ppa",
+            "ppa:deadsnakes/",
+            "ppa:Deadsnakes/ppa",
+            "ppa:deadsnakes/ppa/extra",
+        ] {
+            assert_eq!(
+                Ppa::from_str(value),
+                Err(ParsePpaError(value.to_string())),
+                "{value}"
+            );
+        }
+    }
+}
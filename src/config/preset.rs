@@ -0,0 +1,126 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::debian::DistroCodename;
+
+// A curated set of packages for a common use case that replaces a dedicated buildpack (e.g.; the
+// geo buildpack for GDAL, GEOS, and PROJ). Library package names include the soname which changes
+// between releases so each preset lists the packages for every supported distribution.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub(crate) enum Preset {
+    Geo,
+    Imaging,
+    Media,
+}
+
+impl Preset {
+    // The packages in the preset for the given distribution or `None` when the preset hasn't been
+    // vetted for it.
+    pub(crate) fn packages(self, codename: &DistroCodename) -> Option<&'static [&'static str]> {
+        let packages: &[&str] = match (self, codename.as_str()) {
+            (Preset::Geo, "focal") => &["gdal-bin", "libgdal26", "libgeos-c1v5", "libproj15"],
+            (Preset::Geo, "jammy") => &["gdal-bin", "libgdal30", "libgeos-c1v5", "libproj22"],
+            (Preset::Geo, "noble") => &["gdal-bin", "libgdal34t64", "libgeos-c1t64", "libproj25"],
+            (Preset::Geo, "bookworm") => &["gdal-bin", "libgdal32", "libgeos-c1v5", "libproj25"],
+            (Preset::Geo, "trixie") => &["gdal-bin", "libgdal36", "libgeos-c1t64", "libproj25"],
+            (Preset::Imaging, "focal" | "jammy" | "bookworm") => &[
+                "libvips42",
+                "libvips-tools",
+                "imagemagick",
+                "libmagickwand-6.q16-6",
+            ],
+            (Preset::Imaging, "noble") => &[
+                "libvips42t64",
+                "libvips-tools",
+                "imagemagick",
+                "libmagickwand-6.q16-7t64",
+            ],
+            (Preset::Imaging, "trixie") => &[
+                "libvips42t64",
+                "libvips-tools",
+                "imagemagick",
+                "libmagickwand-7.q16-10",
+            ],
+            (Preset::Media, "focal" | "jammy") => {
+                &["ffmpeg", "libavcodec58", "libavformat58", "libswscale5"]
+            }
+            (Preset::Media, "noble") => &["ffmpeg", "libavcodec60", "libavformat60", "libswscale7"],
+            (Preset::Media, "bookworm") => {
+                &["ffmpeg", "libavcodec59", "libavformat59", "libswscale6"]
+            }
+            (Preset::Media, "trixie") => {
+                &["ffmpeg", "libavcodec61", "libavformat61", "libswscale8"]
+            }
+            _ => return None,
+        };
+        Some(packages)
+    }
+}
+
+impl FromStr for Preset {
+    type Err = ParsePresetError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "geo" => Ok(Preset::Geo),
+            "imaging" => Ok(Preset::Imaging),
+            "media" => Ok(Preset::Media),
+            _ => Err(ParsePresetError(value.to_string())),
+        }
+    }
+}
+
+impl Display for Preset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Preset::Geo => write!(f, "geo"),
+            Preset::Imaging => write!(f, "imaging"),
+            Preset::Media => write!(f, "media"),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct ParsePresetError(pub(crate) String);
+
+#[cfg(test)]
+mod tests {
+    use crate::debian::{BuildpackMetadata, PackageName};
+    use libcnb::data::buildpack::ComponentBuildpackDescriptor;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_preset() {
+        for preset in [Preset::Geo, Preset::Imaging, Preset::Media] {
+            assert_eq!(Preset::from_str(&preset.to_string()), Ok(preset));
+        }
+        assert_eq!(
+            Preset::from_str("gis"),
+            Err(ParsePresetError("gis".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_packages_for_every_supported_distro() {
+        let supported_distros = toml::from_str::<ComponentBuildpackDescriptor<BuildpackMetadata>>(
+            include_str!("../../buildpack.toml"),
+        )
+        .unwrap()
+        .metadata
+        .distros;
+
+        for supported_distro in supported_distros {
+            let codename = DistroCodename::from(supported_distro.version_codename.as_str());
+            for preset in [Preset::Geo, Preset::Imaging, Preset::Media] {
+                let packages = preset
+                    .packages(&codename)
+                    .unwrap_or_else(|| panic!("{preset} should be available for {codename}"));
+                for package in packages {
+                    assert!(PackageName::from_str(package).is_ok(), "{package}");
+                }
+            }
+        }
+        assert_eq!(Preset::Geo.packages(&DistroCodename::from("bionic")), None);
+    }
+}
@@ -1,34 +1,73 @@
-use std::str::FromStr;
+use std::fmt::{Display, Formatter};
 use std::hash::{Hash, Hasher};
+use std::str::FromStr;
 
 use toml_edit::{Formatted, InlineTable, Value};
 
-use crate::debian::{PackageName, ParsePackageNameError};
+use crate::config::{
+    EnvScope, PackageGlob, PackageRegex, ParseEnvScopeError, ParsePackageRegexError,
+    ParseScriptFailurePolicyError, ParseVersionConstraintError, ScriptFailurePolicy,
+    VersionConstraint,
+};
+use crate::debian::{ArchitectureName, PackageName, ParsePackageNameError};
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub(crate) struct RequestedPackage {
     pub(crate) name: PackageName,
+    // set when the package is requested for an architecture other than the one being built for
+    // (e.g.; `libc6:i386`)
+    pub(crate) architecture: Option<ArchitectureName>,
     pub(crate) skip_dependencies: bool,
     pub(crate) force: bool,
+    pub(crate) env_scope: Option<EnvScope>,
+    pub(crate) optional: bool,
+    // the policy for a failing postinst script when the package opted into running it
+    pub(crate) maintainer_scripts: Option<ScriptFailurePolicy>,
+    // limits the versions the package can be installed at (e.g.; `>= 7.0, < 8`)
+    pub(crate) version: Option<VersionConstraint>,
 }
 
 impl Hash for RequestedPackage {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.name.hash(state);
+        self.architecture.hash(state);
         self.skip_dependencies.hash(state);
         self.force.hash(state);
+        self.env_scope.hash(state);
+        self.optional.hash(state);
+        self.maintainer_scripts.hash(state);
+        self.version.hash(state);
+    }
+}
+
+impl RequestedPackage {
+    // The name dpkg uses for the package which includes the architecture when it's requested for a
+    // foreign architecture (e.g.; `libc6:i386`).
+    pub(crate) fn qualified_name(&self) -> String {
+        match &self.architecture {
+            Some(architecture) => format!("{}:{architecture}", self.name),
+            None => self.name.to_string(),
+        }
     }
 }
 
 impl FromStr for RequestedPackage {
     type Err = ParseRequestedPackageError;
 
+    // A specific version can be requested the same way as with `apt-get install` (e.g.;
+    // `redis-tools=5:7.0.15-1build2`), which can be older than the latest version in the index.
     fn from_str(package_name: &str) -> Result<Self, Self::Err> {
+        let (package_name, version) = split_version(package_name)?;
+        let (name, architecture) = parse_qualified_name(package_name)?;
         Ok(RequestedPackage {
-            name: PackageName::from_str(package_name)
-                .map_err(ParseRequestedPackageError::InvalidPackageName)?,
+            name,
+            architecture,
             skip_dependencies: false,
             force: false,
+            env_scope: None,
+            optional: false,
+            maintainer_scripts: None,
+            version,
         })
     }
 }
@@ -40,9 +79,9 @@ impl TryFrom<&Value> for RequestedPackage {
         match value {
             Value::String(formatted_string) => RequestedPackage::try_from(formatted_string),
             Value::InlineTable(inline_table) => RequestedPackage::try_from(inline_table),
-            _ => Err(ParseRequestedPackageError::UnexpectedTomlValue(
+            _ => Err(ParseRequestedPackageError::UnexpectedTomlValue(Box::new(
                 value.clone(),
-            )),
+            ))),
         }
     }
 }
@@ -59,32 +98,330 @@ impl TryFrom<&InlineTable> for RequestedPackage {
     type Error = ParseRequestedPackageError;
 
     fn try_from(table: &InlineTable) -> Result<Self, Self::Error> {
+        let (name, version) = parse_name_and_version(table)?;
+        let (name, architecture) = parse_qualified_name(name)?;
         Ok(RequestedPackage {
-            name: PackageName::from_str(
-                table
-                    .get("name")
-                    .and_then(Value::as_str)
-                    .unwrap_or_default(),
-            )
-            .map_err(ParseRequestedPackageError::InvalidPackageName)?,
+            name,
+            architecture,
+            skip_dependencies: parse_flag(table, "skip_dependencies"),
+            force: parse_flag(table, "force"),
+            env_scope: parse_env_scope(table)?,
+            optional: parse_flag(table, "optional"),
+            maintainer_scripts: parse_maintainer_scripts(table)?,
+            version,
+        })
+    }
+}
+
+// An `install` entry that selects packages by name instead of naming one, either with a name that
+// contains the `*` or `?` wildcards (e.g.; `libicu*`) or a regular expression set as its `pattern`
+// (e.g.; `{ pattern = "^libreoffice-core.*" }`). It's expanded to every package in the package
+// index with a matching name and its options are applied to each of them.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub(crate) struct RequestedPackagePattern {
+    pub(crate) pattern: PackagePattern,
+    pub(crate) architecture: Option<ArchitectureName>,
+    pub(crate) skip_dependencies: bool,
+    pub(crate) force: bool,
+    pub(crate) env_scope: Option<EnvScope>,
+    pub(crate) optional: bool,
+    pub(crate) maintainer_scripts: Option<ScriptFailurePolicy>,
+    pub(crate) version: Option<VersionConstraint>,
+    // the most packages the pattern can match before the build fails
+    pub(crate) max_matches: Option<usize>,
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub(crate) enum PackagePattern {
+    Glob(PackageGlob),
+    Regex(PackageRegex),
+}
+
+// Regular expressions can easily match far more packages than intended (e.g.; `lib.*`) so they're
+// limited unless `max_matches` is configured.
+pub(crate) const DEFAULT_MAX_REGEX_MATCHES: usize = 50;
+
+impl PackagePattern {
+    pub(crate) fn matches(&self, package_name: &str) -> bool {
+        match self {
+            PackagePattern::Glob(package_glob) => package_glob.matches(package_name),
+            PackagePattern::Regex(package_regex) => package_regex.matches(package_name),
+        }
+    }
+}
+
+impl Display for PackagePattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PackagePattern::Glob(package_glob) => write!(f, "{package_glob}"),
+            PackagePattern::Regex(package_regex) => write!(f, "{package_regex}"),
+        }
+    }
+}
+
+impl RequestedPackagePattern {
+    // Whether the `install` entry selects packages with a pattern instead of naming one.
+    pub(crate) fn is_pattern(value: &Value) -> bool {
+        let name = match value {
+            Value::String(formatted_string) => Some(formatted_string.value().as_str()),
+            Value::InlineTable(inline_table) if inline_table.contains_key("pattern") => {
+                return true
+            }
+            Value::InlineTable(inline_table) => inline_table.get("name").and_then(Value::as_str),
+            _ => None,
+        };
+        name.is_some_and(|name| name.contains(['*', '?']))
+    }
 
-            skip_dependencies: table
-                .get("skip_dependencies")
-                .and_then(Value::as_bool)
-                .unwrap_or_default(),
+    pub(crate) fn max_matches(&self) -> Option<usize> {
+        match self.pattern {
+            PackagePattern::Glob(_) => self.max_matches,
+            PackagePattern::Regex(_) => Some(self.max_matches.unwrap_or(DEFAULT_MAX_REGEX_MATCHES)),
+        }
+    }
+
+    pub(crate) fn qualified_pattern(&self) -> String {
+        match &self.architecture {
+            Some(architecture) => format!("{}:{architecture}", self.pattern),
+            None => self.pattern.to_string(),
+        }
+    }
+
+    // The package requested for a name that matches the pattern.
+    pub(crate) fn requested_package(&self, name: PackageName) -> RequestedPackage {
+        RequestedPackage {
+            name,
+            architecture: self.architecture.clone(),
+            skip_dependencies: self.skip_dependencies,
+            force: self.force,
+            env_scope: self.env_scope,
+            optional: self.optional,
+            maintainer_scripts: self.maintainer_scripts,
+            version: self.version.clone(),
+        }
+    }
+}
+
+impl FromStr for RequestedPackagePattern {
+    type Err = ParseRequestedPackageError;
 
-            force: table
-                .get("force")
-                .and_then(Value::as_bool)
-                .unwrap_or_default(),
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        let (pattern, version) = split_version(pattern)?;
+        let (pattern, architecture) = parse_qualified_pattern(pattern)?;
+        Ok(RequestedPackagePattern {
+            pattern: PackagePattern::Glob(pattern),
+            architecture,
+            skip_dependencies: false,
+            force: false,
+            env_scope: None,
+            optional: false,
+            maintainer_scripts: None,
+            version,
+            max_matches: None,
         })
     }
 }
 
+impl TryFrom<&Value> for RequestedPackagePattern {
+    type Error = ParseRequestedPackageError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(formatted_string) => {
+                RequestedPackagePattern::from_str(formatted_string.value())
+            }
+            Value::InlineTable(inline_table) => RequestedPackagePattern::try_from(inline_table),
+            _ => Err(ParseRequestedPackageError::UnexpectedTomlValue(Box::new(
+                value.clone(),
+            ))),
+        }
+    }
+}
+
+impl TryFrom<&InlineTable> for RequestedPackagePattern {
+    type Error = ParseRequestedPackageError;
+
+    fn try_from(table: &InlineTable) -> Result<Self, Self::Error> {
+        // a regular expression can't also have a foreign architecture since it could contain a `:`
+        let (name, version) = parse_name_and_version(table)?;
+        let (pattern, architecture) = match table.get("pattern") {
+            Some(_) if table.contains_key("name") => {
+                Err(ParseRequestedPackageError::NameAndPattern)?
+            }
+            Some(value) => (
+                PackagePattern::Regex(
+                    value
+                        .as_str()
+                        .ok_or_else(|| ParsePackageRegexError {
+                            value: value.to_string(),
+                            reason: "the pattern must be a string".to_string(),
+                        })
+                        .and_then(PackageRegex::from_str)
+                        .map_err(ParseRequestedPackageError::InvalidPackageRegex)?,
+                ),
+                None,
+            ),
+            None => {
+                let (pattern, architecture) = parse_qualified_pattern(name)?;
+                (PackagePattern::Glob(pattern), architecture)
+            }
+        };
+        Ok(RequestedPackagePattern {
+            pattern,
+            architecture,
+            skip_dependencies: parse_flag(table, "skip_dependencies"),
+            force: parse_flag(table, "force"),
+            env_scope: parse_env_scope(table)?,
+            optional: parse_flag(table, "optional"),
+            maintainer_scripts: parse_maintainer_scripts(table)?,
+            version,
+            max_matches: match table.get("max_matches") {
+                Some(value) => Some(
+                    value
+                        .as_integer()
+                        .and_then(|max_matches| usize::try_from(max_matches).ok())
+                        .filter(|max_matches| *max_matches > 0)
+                        .ok_or_else(|| {
+                            ParseRequestedPackageError::InvalidMaxMatches(value.to_string())
+                        })?,
+                ),
+                None => None,
+            },
+        })
+    }
+}
+
+fn split_version(
+    value: &str,
+) -> Result<(&str, Option<VersionConstraint>), ParseRequestedPackageError> {
+    match value.split_once('=') {
+        Some((value, version)) => Ok((
+            value,
+            Some(
+                VersionConstraint::exact(version)
+                    .map_err(ParseRequestedPackageError::InvalidVersionConstraint)?,
+            ),
+        )),
+        None => Ok((value, None)),
+    }
+}
+
+// Package names can't contain a `:` so anything after one is the architecture the package is
+// requested for.
+fn split_architecture(
+    value: &str,
+) -> Result<(&str, Option<ArchitectureName>), ParseRequestedPackageError> {
+    match value.split_once(':') {
+        Some((value, architecture)) => Ok((
+            value,
+            Some(ArchitectureName::from_str(architecture).map_err(|_| {
+                ParseRequestedPackageError::UnsupportedArchitecture(architecture.to_string())
+            })?),
+        )),
+        None => Ok((value, None)),
+    }
+}
+
+fn parse_qualified_name(
+    value: &str,
+) -> Result<(PackageName, Option<ArchitectureName>), ParseRequestedPackageError> {
+    let (package_name, architecture) = split_architecture(value)?;
+    Ok((
+        PackageName::from_str(package_name)
+            .map_err(ParseRequestedPackageError::InvalidPackageName)?,
+        architecture,
+    ))
+}
+
+fn parse_qualified_pattern(
+    value: &str,
+) -> Result<(PackageGlob, Option<ArchitectureName>), ParseRequestedPackageError> {
+    let (pattern, architecture) = split_architecture(value)?;
+    Ok((
+        PackageGlob::from_str(pattern)
+            .map_err(|_| ParseRequestedPackageError::InvalidPackageGlob(pattern.to_string()))?,
+        architecture,
+    ))
+}
+
+fn parse_flag(table: &InlineTable, key: &str) -> bool {
+    table.get(key).and_then(Value::as_bool).unwrap_or_default()
+}
+
+fn parse_env_scope(table: &InlineTable) -> Result<Option<EnvScope>, ParseRequestedPackageError> {
+    match table.get("env_scope") {
+        Some(value) => Ok(Some(
+            value
+                .as_str()
+                .ok_or_else(|| ParseEnvScopeError(value.to_string()))
+                .and_then(EnvScope::from_str)
+                .map_err(ParseRequestedPackageError::InvalidEnvScope)?,
+        )),
+        None => Ok(None),
+    }
+}
+
+// The failure policy is validated even when the scripts aren't run so a typo isn't only noticed
+// once the scripts are enabled.
+fn parse_maintainer_scripts(
+    table: &InlineTable,
+) -> Result<Option<ScriptFailurePolicy>, ParseRequestedPackageError> {
+    let on_script_failure = match table.get("on_script_failure") {
+        Some(value) => value
+            .as_str()
+            .ok_or_else(|| ParseScriptFailurePolicyError(value.to_string()))
+            .and_then(ScriptFailurePolicy::from_str)
+            .map_err(ParseRequestedPackageError::InvalidScriptFailurePolicy)?,
+        None => ScriptFailurePolicy::default(),
+    };
+    Ok(parse_flag(table, "run_scripts").then_some(on_script_failure))
+}
+
+// The version can be part of the name the same way as with `apt-get install` (e.g.;
+// `{ name = "redis-tools=5:7.0.15-1build2" }`) but then it can't also be set with `version`.
+fn parse_name_and_version(
+    table: &InlineTable,
+) -> Result<(&str, Option<VersionConstraint>), ParseRequestedPackageError> {
+    let (name, name_version) = split_version(
+        table
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default(),
+    )?;
+    match (name_version, parse_version(table)?) {
+        (Some(_), Some(_)) => Err(ParseRequestedPackageError::NameAndVersion),
+        (name_version, version) => Ok((name, name_version.or(version))),
+    }
+}
+
+fn parse_version(
+    table: &InlineTable,
+) -> Result<Option<VersionConstraint>, ParseRequestedPackageError> {
+    match table.get("version") {
+        Some(value) => Ok(Some(
+            value
+                .as_str()
+                .ok_or_else(|| ParseVersionConstraintError(value.to_string()))
+                .and_then(VersionConstraint::from_str)
+                .map_err(ParseRequestedPackageError::InvalidVersionConstraint)?,
+        )),
+        None => Ok(None),
+    }
+}
+
 #[derive(Debug)]
 pub(crate) enum ParseRequestedPackageError {
     InvalidPackageName(ParsePackageNameError),
-    UnexpectedTomlValue(Value),
+    UnsupportedArchitecture(String),
+    UnexpectedTomlValue(Box<Value>),
+    InvalidEnvScope(ParseEnvScopeError),
+    InvalidScriptFailurePolicy(ParseScriptFailurePolicyError),
+    InvalidVersionConstraint(ParseVersionConstraintError),
+    InvalidPackageGlob(String),
+    InvalidPackageRegex(ParsePackageRegexError),
+    NameAndPattern,
+    NameAndVersion,
+    InvalidMaxMatches(String),
 }
 
 #[cfg(test)]
@@ -99,12 +436,119 @@ mod tests {
             package,
             RequestedPackage {
                 name: PackageName::from_str("package1").unwrap(),
+                architecture: None,
                 skip_dependencies: false,
                 force: false,
+                env_scope: None,
+                optional: false,
+                maintainer_scripts: None,
+                version: None,
             }
         );
     }
 
+    #[test]
+    fn test_from_str_with_architecture() {
+        let package = RequestedPackage::from_str("libc6:i386").unwrap();
+        assert_eq!(package.name, PackageName::from_str("libc6").unwrap());
+        assert_eq!(package.architecture, Some(ArchitectureName::I_386));
+        assert_eq!(package.qualified_name(), "libc6:i386");
+
+        assert_eq!(
+            RequestedPackage::from_str("libc6")
+                .unwrap()
+                .qualified_name(),
+            "libc6"
+        );
+        assert!(matches!(
+            RequestedPackage::from_str("libc6:mips").unwrap_err(),
+            ParseRequestedPackageError::UnsupportedArchitecture(value) if value == "mips"
+        ));
+        assert!(matches!(
+            RequestedPackage::from_str("libc6:").unwrap_err(),
+            ParseRequestedPackageError::UnsupportedArchitecture(value) if value.is_empty()
+        ));
+    }
+
+    #[test]
+    fn test_try_from_with_architecture() {
+        let mut table = InlineTable::new();
+        table.insert("name", Value::from("libstdc++6:armhf"));
+
+        let package = RequestedPackage::try_from(&table).unwrap();
+        assert_eq!(package.name, PackageName::from_str("libstdc++6").unwrap());
+        assert_eq!(package.architecture, Some(ArchitectureName::ARM_HF));
+    }
+
+    #[test]
+    fn test_from_str_with_version() {
+        let package = RequestedPackage::from_str("redis-tools:i386=5:7.0.15-1build2").unwrap();
+        assert_eq!(package.name, PackageName::from_str("redis-tools").unwrap());
+        assert_eq!(package.architecture, Some(ArchitectureName::I_386));
+        assert_eq!(
+            package.version,
+            Some(VersionConstraint::from_str("= 5:7.0.15-1build2").unwrap())
+        );
+
+        for invalid_package in ["redis-tools=", "redis-tools=>= 7.0", "redis-tools=7.0=1"] {
+            assert!(
+                matches!(
+                    RequestedPackage::from_str(invalid_package).unwrap_err(),
+                    ParseRequestedPackageError::InvalidVersionConstraint(_)
+                ),
+                "{invalid_package}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_try_from_with_version() {
+        let mut table = InlineTable::new();
+        table.insert("name", Value::from("redis-tools"));
+        table.insert("version", Value::from(">= 7.0, < 8"));
+
+        let package = RequestedPackage::try_from(&table).unwrap();
+        assert_eq!(
+            package.version,
+            Some(VersionConstraint::from_str(">= 7.0, < 8").unwrap())
+        );
+
+        for invalid_version in [Value::from("~> 7.0"), Value::from(7)] {
+            table.insert("version", invalid_version);
+            assert!(matches!(
+                RequestedPackage::try_from(&table).unwrap_err(),
+                ParseRequestedPackageError::InvalidVersionConstraint(_)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_try_from_with_version_in_name() {
+        let mut table = InlineTable::new();
+        table.insert("name", Value::from("redis-tools:i386=5:7.0.15-1build2"));
+
+        let package = RequestedPackage::try_from(&table).unwrap();
+        assert_eq!(package.name, PackageName::from_str("redis-tools").unwrap());
+        assert_eq!(package.architecture, Some(ArchitectureName::I_386));
+        assert_eq!(
+            package.version,
+            Some(VersionConstraint::from_str("= 5:7.0.15-1build2").unwrap())
+        );
+
+        table.insert("name", Value::from("redis-tools="));
+        assert!(matches!(
+            RequestedPackage::try_from(&table).unwrap_err(),
+            ParseRequestedPackageError::InvalidVersionConstraint(_)
+        ));
+
+        table.insert("name", Value::from("redis-tools=7.0"));
+        table.insert("version", Value::from(">= 7.0"));
+        assert!(matches!(
+            RequestedPackage::try_from(&table).unwrap_err(),
+            ParseRequestedPackageError::NameAndVersion
+        ));
+    }
+
     #[test]
     fn test_try_from_with_env() {
         let mut table = InlineTable::new();
@@ -118,8 +562,13 @@ mod tests {
             package,
             RequestedPackage {
                 name: PackageName::from_str("package1").unwrap(),
+                architecture: None,
                 skip_dependencies: false,
                 force: false,
+                env_scope: None,
+                optional: false,
+                maintainer_scripts: None,
+                version: None,
             }
         );
     }
@@ -138,8 +587,13 @@ mod tests {
             package,
             RequestedPackage {
                 name: PackageName::from_str("package1").unwrap(),
+                architecture: None,
                 skip_dependencies: false,
                 force: false,
+                env_scope: None,
+                optional: false,
+                maintainer_scripts: None,
+                version: None,
             }
         );
     }
@@ -152,4 +606,191 @@ mod tests {
         let result = RequestedPackage::try_from(&table);
         assert!(result.is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_try_from_with_env_scope() {
+        let mut table = InlineTable::new();
+        table.insert("name", Value::from("package1"));
+        table.insert("env_scope", Value::from("launch"));
+
+        let package = RequestedPackage::try_from(&table).unwrap();
+        assert_eq!(package.env_scope, Some(EnvScope::Launch));
+    }
+
+    #[test]
+    fn test_try_from_optional() {
+        let mut table = InlineTable::new();
+        table.insert("name", Value::from("package1"));
+        table.insert("optional", Value::from(true));
+
+        let package = RequestedPackage::try_from(&table).unwrap();
+        assert!(package.optional);
+    }
+
+    #[test]
+    fn test_try_from_run_scripts() {
+        let mut table = InlineTable::new();
+        table.insert("name", Value::from("package1"));
+        table.insert("on_script_failure", Value::from("warn"));
+        assert_eq!(
+            RequestedPackage::try_from(&table)
+                .unwrap()
+                .maintainer_scripts,
+            None
+        );
+
+        table.insert("run_scripts", Value::from(true));
+        assert_eq!(
+            RequestedPackage::try_from(&table)
+                .unwrap()
+                .maintainer_scripts,
+            Some(ScriptFailurePolicy::Warn)
+        );
+
+        table.remove("on_script_failure");
+        assert_eq!(
+            RequestedPackage::try_from(&table)
+                .unwrap()
+                .maintainer_scripts,
+            Some(ScriptFailurePolicy::Error)
+        );
+    }
+
+    #[test]
+    fn test_try_from_invalid_on_script_failure() {
+        let mut table = InlineTable::new();
+        table.insert("name", Value::from("package1"));
+        table.insert("on_script_failure", Value::from("ignore"));
+
+        assert!(matches!(
+            RequestedPackage::try_from(&table).unwrap_err(),
+            ParseRequestedPackageError::InvalidScriptFailurePolicy(ParseScriptFailurePolicyError(value)) if value == "ignore"
+        ));
+    }
+
+    #[test]
+    fn test_try_from_invalid_env_scope() {
+        let mut table = InlineTable::new();
+        table.insert("name", Value::from("package1"));
+        table.insert("env_scope", Value::from("runtime"));
+
+        assert!(matches!(
+            RequestedPackage::try_from(&table).unwrap_err(),
+            ParseRequestedPackageError::InvalidEnvScope(ParseEnvScopeError(value)) if value == "runtime"
+        ));
+    }
+
+    #[test]
+    fn test_is_pattern() {
+        let mut table = InlineTable::new();
+        table.insert("name", Value::from("postgresql-client-1?"));
+
+        assert!(RequestedPackagePattern::is_pattern(&Value::from("libicu*")));
+        assert!(RequestedPackagePattern::is_pattern(&Value::InlineTable(
+            table
+        )));
+        assert!(!RequestedPackagePattern::is_pattern(&Value::from(
+            "libicu74"
+        )));
+        assert!(!RequestedPackagePattern::is_pattern(&Value::from(1)));
+
+        let mut table = InlineTable::new();
+        table.insert("pattern", Value::from("^libreoffice-core.*"));
+        assert!(RequestedPackagePattern::is_pattern(&Value::InlineTable(
+            table
+        )));
+    }
+
+    #[test]
+    fn test_regex_pattern_try_from() {
+        let mut table = InlineTable::new();
+        table.insert("pattern", Value::from("^libreoffice-core.*"));
+
+        let package_pattern = RequestedPackagePattern::try_from(&table).unwrap();
+        assert_eq!(
+            package_pattern.pattern,
+            PackagePattern::Regex(PackageRegex::from_str("^libreoffice-core.*").unwrap())
+        );
+        assert_eq!(package_pattern.architecture, None);
+        assert_eq!(
+            package_pattern.max_matches(),
+            Some(DEFAULT_MAX_REGEX_MATCHES)
+        );
+
+        table.insert("max_matches", Value::from(5));
+        assert_eq!(
+            RequestedPackagePattern::try_from(&table)
+                .unwrap()
+                .max_matches(),
+            Some(5)
+        );
+
+        for invalid_max_matches in [Value::from(0), Value::from(-1), Value::from("5")] {
+            table.insert("max_matches", invalid_max_matches);
+            assert!(matches!(
+                RequestedPackagePattern::try_from(&table).unwrap_err(),
+                ParseRequestedPackageError::InvalidMaxMatches(_)
+            ));
+        }
+        table.remove("max_matches");
+
+        table.insert("pattern", Value::from("^libreoffice-(core"));
+        assert!(matches!(
+            RequestedPackagePattern::try_from(&table).unwrap_err(),
+            ParseRequestedPackageError::InvalidPackageRegex(ParsePackageRegexError { value, .. }) if value == "^libreoffice-(core"
+        ));
+
+        table.insert("pattern", Value::from("^libreoffice-core.*"));
+        table.insert("name", Value::from("libreoffice-core"));
+        assert!(matches!(
+            RequestedPackagePattern::try_from(&table).unwrap_err(),
+            ParseRequestedPackageError::NameAndPattern
+        ));
+    }
+
+    #[test]
+    fn test_pattern_from_str() {
+        let package_pattern =
+            RequestedPackagePattern::from_str("libicu*:i386=74.2-1ubuntu3").unwrap();
+        assert_eq!(
+            package_pattern.pattern,
+            PackagePattern::Glob(PackageGlob::from_str("libicu*").unwrap())
+        );
+        assert_eq!(package_pattern.architecture, Some(ArchitectureName::I_386));
+        assert_eq!(
+            package_pattern.version,
+            Some(VersionConstraint::from_str("= 74.2-1ubuntu3").unwrap())
+        );
+        assert_eq!(package_pattern.qualified_pattern(), "libicu*:i386");
+        assert_eq!(package_pattern.max_matches(), None);
+
+        assert!(matches!(
+            RequestedPackagePattern::from_str("LibICU*").unwrap_err(),
+            ParseRequestedPackageError::InvalidPackageGlob(value) if value == "LibICU*"
+        ));
+    }
+
+    #[test]
+    fn test_pattern_try_from() {
+        let mut table = InlineTable::new();
+        table.insert("name", Value::from("postgresql-client-1?"));
+        table.insert("env_scope", Value::from("build"));
+        table.insert("optional", Value::from(true));
+
+        let package_pattern = RequestedPackagePattern::try_from(&table).unwrap();
+        assert_eq!(
+            package_pattern
+                .requested_package(PackageName::from_str("postgresql-client-16").unwrap()),
+            RequestedPackage {
+                name: PackageName::from_str("postgresql-client-16").unwrap(),
+                architecture: None,
+                skip_dependencies: false,
+                force: false,
+                env_scope: Some(EnvScope::Build),
+                optional: true,
+                maintainer_scripts: None,
+                version: None,
+            }
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::debian::{RepositoryUri, Source};
+
+// A point in time to resolve packages at using the snapshot services of Ubuntu and Debian, which
+// serve every version of their archives that was ever published. The distribution's sources are
+// rewritten to the snapshot taken at (or just before) the timestamp so builds keep installing the
+// same package versions. Additional sources configured in project.toml aren't rewritten.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct Snapshot(String);
+
+// Archives with a snapshot service and the URI each is rewritten to (before the timestamp).
+const SNAPSHOT_SERVICES: &[(&str, &str)] = &[
+    (
+        "archive.ubuntu.com/ubuntu",
+        "https://snapshot.ubuntu.com/ubuntu",
+    ),
+    (
+        "security.ubuntu.com/ubuntu",
+        "https://snapshot.ubuntu.com/ubuntu",
+    ),
+    (
+        "ports.ubuntu.com/ubuntu-ports",
+        "https://snapshot.ubuntu.com/ubuntu-ports",
+    ),
+    (
+        "deb.debian.org/debian",
+        "https://snapshot.debian.org/archive/debian",
+    ),
+    (
+        "deb.debian.org/debian-security",
+        "https://snapshot.debian.org/archive/debian-security",
+    ),
+];
+
+impl Snapshot {
+    pub(crate) fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    // The snapshot URI for a source, if its archive has a snapshot service.
+    pub(crate) fn rewrite_uri(&self, uri: &RepositoryUri) -> Option<RepositoryUri> {
+        let uri = uri.as_str().trim_end_matches('/');
+        let uri = uri
+            .strip_prefix("http://")
+            .or_else(|| uri.strip_prefix("https://"))?;
+        SNAPSHOT_SERVICES
+            .iter()
+            .find(|(archive, _)| *archive == uri)
+            .map(|(_, snapshot_service)| {
+                RepositoryUri::from(format!("{snapshot_service}/{}", self.0).as_str())
+            })
+    }
+
+    // Mirrors of the archive don't serve snapshots so they're dropped from rewritten sources.
+    pub(crate) fn apply(&self, sources: Vec<Source>) -> Vec<Source> {
+        sources
+            .into_iter()
+            .map(|source| match self.rewrite_uri(&source.uri) {
+                Some(uri) => Source {
+                    uri,
+                    mirrors: vec![],
+                    ..source
+                },
+                None => source,
+            })
+            .collect()
+    }
+}
+
+// Timestamps use the format of both snapshot services (e.g.; `20240301T000000Z`).
+impl FromStr for Snapshot {
+    type Err = ParseSnapshotError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let is_valid = value.len() == 16
+            && value.char_indices().all(|(index, char)| match index {
+                8 => char == 'T',
+                15 => char == 'Z',
+                _ => char.is_ascii_digit(),
+            });
+        if is_valid {
+            Ok(Snapshot(value.to_string()))
+        } else {
+            Err(ParseSnapshotError(value.to_string()))
+        }
+    }
+}
+
+impl Display for Snapshot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct ParseSnapshotError(pub(crate) String);
+
+#[cfg(test)]
+mod tests {
+    use crate::debian::ArchitectureName;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_snapshot() {
+        assert_eq!(
+            Snapshot::from_str("20240301T000000Z").unwrap().as_str(),
+            "20240301T000000Z"
+        );
+        for value in [
+            "2024-03-01",
+            "20240301",
+            "20240301T000000",
+            "2024030aT000000Z",
+        ] {
+            assert_eq!(
+                Snapshot::from_str(value),
+                Err(ParseSnapshotError(value.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_rewrite_uri() {
+        let snapshot = Snapshot::from_str("20240301T000000Z").unwrap();
+        for (uri, expected) in [
+            (
+                "http://archive.ubuntu.com/ubuntu",
+                Some("https://snapshot.ubuntu.com/ubuntu/20240301T000000Z"),
+            ),
+            (
+                "http://security.ubuntu.com/ubuntu/",
+                Some("https://snapshot.ubuntu.com/ubuntu/20240301T000000Z"),
+            ),
+            (
+                "http://ports.ubuntu.com/ubuntu-ports",
+                Some("https://snapshot.ubuntu.com/ubuntu-ports/20240301T000000Z"),
+            ),
+            (
+                "https://deb.debian.org/debian",
+                Some("https://snapshot.debian.org/archive/debian/20240301T000000Z"),
+            ),
+            (
+                "http://deb.debian.org/debian-security",
+                Some("https://snapshot.debian.org/archive/debian-security/20240301T000000Z"),
+            ),
+            ("https://repo.example.com/ubuntu", None),
+        ] {
+            assert_eq!(
+                snapshot
+                    .rewrite_uri(&RepositoryUri::from(uri))
+                    .as_ref()
+                    .map(RepositoryUri::as_str),
+                expected,
+                "{uri}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let snapshot = Snapshot::from_str("20240301T000000Z").unwrap();
+        let sources = snapshot.apply(vec![
+            Source {
+                mirrors: vec![RepositoryUri::from("https://mirror.example.com/ubuntu")],
+                ..Source::new(
+                    "http://archive.ubuntu.com/ubuntu",
+                    ["noble"],
+                    ["main"],
+                    "",
+                    ArchitectureName::AMD_64,
+                )
+            },
+            Source::new(
+                "https://repo.example.com/ubuntu",
+                ["noble"],
+                ["main"],
+                "",
+                ArchitectureName::AMD_64,
+            ),
+        ]);
+        assert_eq!(
+            sources[0].uri.as_str(),
+            "https://snapshot.ubuntu.com/ubuntu/20240301T000000Z"
+        );
+        assert!(sources[0].mirrors.is_empty());
+        assert_eq!(sources[1].uri.as_str(), "https://repo.example.com/ubuntu");
+    }
+}
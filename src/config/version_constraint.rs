@@ -0,0 +1,182 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use debversion::Version;
+
+// A comma-separated list of comparisons that a package version must all satisfy (e.g.;
+// `>= 7.0, < 8`). A version without an operator must match exactly.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub(crate) struct VersionConstraint(Vec<(Operator, Version)>);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+enum Operator {
+    Equal,
+    NotEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+    LessThan,
+    LessThanOrEqual,
+}
+
+impl VersionConstraint {
+    // Only the given version satisfies the constraint. Unlike the comparisons parsed from a
+    // constraint, the value can't start with an operator.
+    pub(crate) fn exact(version: &str) -> Result<Self, ParseVersionConstraintError> {
+        let version = version.trim();
+        if version.is_empty() || version.contains(char::is_whitespace) {
+            return Err(ParseVersionConstraintError(version.to_string()));
+        }
+        Version::from_str(version)
+            .map(|version| VersionConstraint(vec![(Operator::Equal, version)]))
+            .map_err(|_| ParseVersionConstraintError(version.to_string()))
+    }
+
+    pub(crate) fn matches(&self, version: &Version) -> bool {
+        self.0.iter().all(|(operator, constraint_version)| {
+            // Most packages in the Ubuntu and Debian archives have an epoch that isn't part of the
+            // upstream version (e.g.; `5:7.0.15-1` for redis-tools) so it's only compared when the
+            // constraint includes one.
+            let version = match constraint_version.epoch {
+                Some(_) => version.clone(),
+                None => Version {
+                    epoch: None,
+                    ..version.clone()
+                },
+            };
+            match operator {
+                Operator::Equal => version == *constraint_version,
+                Operator::NotEqual => version != *constraint_version,
+                Operator::GreaterThan => version > *constraint_version,
+                Operator::GreaterThanOrEqual => version >= *constraint_version,
+                Operator::LessThan => version < *constraint_version,
+                Operator::LessThanOrEqual => version <= *constraint_version,
+            }
+        })
+    }
+}
+
+impl FromStr for VersionConstraint {
+    type Err = ParseVersionConstraintError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseVersionConstraintError(value.to_string());
+        let mut comparisons = vec![];
+        for comparison in value.split(',').map(str::trim) {
+            // the two character operators are checked first since they start with the single
+            // character ones
+            let (operator, version) = [
+                (">=", Operator::GreaterThanOrEqual),
+                ("<=", Operator::LessThanOrEqual),
+                ("!=", Operator::NotEqual),
+                ("==", Operator::Equal),
+                (">", Operator::GreaterThan),
+                ("<", Operator::LessThan),
+                ("=", Operator::Equal),
+            ]
+            .into_iter()
+            .find_map(|(symbol, operator)| {
+                comparison
+                    .strip_prefix(symbol)
+                    .map(|version| (operator, version.trim()))
+            })
+            .unwrap_or((Operator::Equal, comparison));
+            if version.is_empty() || version.contains(char::is_whitespace) {
+                return Err(invalid());
+            }
+            let version = Version::from_str(version).map_err(|_| invalid())?;
+            comparisons.push((operator, version));
+        }
+        Ok(VersionConstraint(comparisons))
+    }
+}
+
+impl Display for VersionConstraint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let comparisons = self
+            .0
+            .iter()
+            .map(|(operator, version)| {
+                let symbol = match operator {
+                    Operator::Equal => "=",
+                    Operator::NotEqual => "!=",
+                    Operator::GreaterThan => ">",
+                    Operator::GreaterThanOrEqual => ">=",
+                    Operator::LessThan => "<",
+                    Operator::LessThanOrEqual => "<=",
+                };
+                format!("{symbol} {version}")
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{}", comparisons.join(", "))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ParseVersionConstraintError(pub(crate) String);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let cases = [
+            (">= 7.0, < 8", "7.0.15-1build2", true),
+            (">= 7.0, < 8", "5:7.0.15-1build2", true),
+            (">= 7.0, < 8", "8.0.1-1", false),
+            (">= 7.0, < 8", "6.0.16-1ubuntu1", false),
+            (">=7.0,<8", "7.2.4-1", true),
+            ("7.0.15-1", "7.0.15-1", true),
+            ("= 7.0.15-1", "7.0.15-2", false),
+            ("!= 7.0.15-1", "7.0.15-2", true),
+            ("> 1.0", "1.0", false),
+            ("<= 1.0", "1.0", true),
+            ("1:2.0", "2.0", false),
+            ("< 1:0", "5:7.0.15-1", false),
+        ];
+        for (constraint, version, expected) in cases {
+            assert_eq!(
+                VersionConstraint::from_str(constraint)
+                    .unwrap()
+                    .matches(&Version::from_str(version).unwrap()),
+                expected,
+                "{constraint} matching {version}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_str_invalid() {
+        for invalid_constraint in ["", ">=", ">= 7.0,", ">= 7 .0", "~> 7.0", ">= 7.0 < 8"] {
+            assert!(
+                VersionConstraint::from_str(invalid_constraint).is_err(),
+                "{invalid_constraint}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_exact() {
+        let constraint = VersionConstraint::exact("7.0.15-1build2").unwrap();
+        assert!(constraint.matches(&Version::from_str("5:7.0.15-1build2").unwrap()));
+        assert!(!constraint.matches(&Version::from_str("7.0.15-1ubuntu0.24.04.1").unwrap()));
+        assert_eq!(constraint.to_string(), "= 7.0.15-1build2");
+
+        for invalid_version in ["", ">= 7.0", "7.0 1"] {
+            assert!(
+                VersionConstraint::exact(invalid_version).is_err(),
+                "{invalid_version}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            VersionConstraint::from_str(">=7.0,  < 8, 7.0.15-1")
+                .unwrap()
+                .to_string(),
+            ">= 7.0, < 8, = 7.0.15-1"
+        );
+    }
+}
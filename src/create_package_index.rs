@@ -1,35 +1,47 @@
+use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
 use std::io::Stdout;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use std::str::FromStr;
 use std::sync::Arc;
-use std::time::{SystemTime, SystemTimeError, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, SystemTimeError, UNIX_EPOCH};
 
 use apt_parser::errors::APTError;
+use apt_parser::release::ReleaseHash;
 use apt_parser::Release;
-use async_compression::tokio::bufread::GzipDecoder;
+use async_compression::tokio::bufread::{GzipDecoder, XzDecoder, ZstdDecoder};
+use async_compression::tokio::write::ZstdEncoder;
 use bullet_stream::state::Bullet;
 use bullet_stream::{style, Print};
+use chrono::DateTime;
 use futures::io::AllowStdIo;
 use futures::TryStreamExt;
 use libcnb::build::BuildContext;
 use libcnb::data::layer::{LayerName, LayerNameError};
+use libcnb::data::layer_name;
 use libcnb::layer::{
-    CachedLayerDefinition, EmptyLayerCause, InvalidMetadataAction, LayerState, RestoredLayerAction
+    CachedLayerDefinition, EmptyLayerCause, InvalidMetadataAction, LayerState, RestoredLayerAction,
 };
-use rayon::iter::{Either, IntoParallelIterator, ParallelBridge, ParallelIterator};
-use reqwest::header::ETAG;
+use libcnb::Platform;
+use rayon::iter::{
+    Either, IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator,
+    IntoParallelRefMutIterator, ParallelBridge, ParallelIterator,
+};
+use reqwest::header::{ETAG, LAST_MODIFIED};
+use reqwest::{Response, StatusCode};
 use reqwest_middleware::ClientWithMiddleware;
 use reqwest_middleware::Error::Reqwest;
-use sequoia_openpgp::parse::stream::VerifierBuilder;
+use sequoia_openpgp::parse::stream::{DetachedVerifierBuilder, VerifierBuilder};
 use sequoia_openpgp::parse::Parse;
 use sequoia_openpgp::policy::StandardPolicy;
-use sequoia_openpgp::Cert;
+use sequoia_openpgp::{Cert, Fingerprint};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use tokio::fs::{read_to_string as async_read_to_string, write as async_write, File as AsyncFile};
 use tokio::io::{
-    copy as async_copy, AsyncWriteExt, BufReader as AsyncBufReader, BufWriter as AsyncBufWriter,
+    copy as async_copy, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader as AsyncBufReader,
+    BufWriter as AsyncBufWriter,
 };
 use tokio::sync::oneshot::channel;
 use tokio::sync::oneshot::error::RecvError;
@@ -37,50 +49,105 @@ use tokio::task::{JoinError, JoinSet};
 use tokio_util::compat::FuturesAsyncReadCompatExt;
 use tokio_util::io::InspectReader;
 
+use crate::config::{CustomSource, Snapshot};
 use crate::debian::{
-    ArchitectureName, Distro, PackageIndex, ParseRepositoryPackageError, RepositoryPackage,
-    RepositoryUri, Source,
+    apply_ed_script, ArchitectureName, ChecksumAlgorithm, Distro, OptionalSuite, PackageIndex,
+    PackageRepository, ParseRepositoryPackageError, PdiffError, PdiffIndex, RepositoryPackage,
+    RepositoryUri, SignaturePolicy, Source, SupportedDistro,
+};
+use crate::instrumentation::record_cache_event;
+use crate::package_index_cache::{
+    package_index_cache_path, read_package_index_cache, write_package_index_cache,
+    CachedPackageIndex, PackageIndexCacheMetadata, PACKAGE_INDEX_CACHE_FORMAT_VERSION,
 };
-use crate::pgp::CertHelper;
-use crate::{BuildpackResult, DebianPackagesBuildpack, DebianPackagesBuildpackError};
+use crate::pgp::{is_clearsigned, strip_clearsigned, CertHelper, SigningKeys};
+use crate::{
+    is_buildpack_debug_logging_enabled, BuildpackResult, DebianPackagesBuildpack,
+    DebianPackagesBuildpackError,
+};
+
+// How long cached repository metadata can be trusted. Release files are always downloaded again
+// once their `Valid-Until` date passes but package indexes are only checked against the hash from
+// the Release file so they can also be given a maximum age.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) struct CacheFreshness {
+    pub(crate) check_release_dates: bool,
+    pub(crate) max_package_index_age: Option<Duration>,
+}
 
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
 pub(crate) async fn create_package_index(
     context: &Arc<BuildContext<DebianPackagesBuildpack>>,
     client: &ClientWithMiddleware,
     distro: &Distro,
+    custom_sources: &[CustomSource],
+    signing_keys: &SigningKeys,
+    distro_source_options: DistroSourceOptions<'_>,
+    package_subset: Option<HashSet<String>>,
+    cache_freshness: CacheFreshness,
     log: Print<Bullet<Stdout>>,
-) -> BuildpackResult<(PackageIndex, Print<Bullet<Stdout>>)> {
+) -> BuildpackResult<(PackageIndex, Vec<IndexedSource>, Print<Bullet<Stdout>>)> {
     let log = log.h2("Creating package index");
 
-    let source_list = distro.get_source_list();
+    let source_list = get_source_list(
+        distro,
+        &context.buildpack_descriptor.metadata.distros,
+        signing_keys,
+        custom_sources,
+        distro_source_options,
+    );
 
     let log = source_list
         .iter()
         .fold(log.bullet("Package sources"), |log, source| {
-            source.suites.iter().fold(log, |log, suite| {
+            let log = source.suites.iter().fold(log, |log, suite| {
                 log.sub_bullet(format!(
                     "{repository_uri} {suite} [{components}]",
                     repository_uri = style::url(source.uri.as_str()),
                     components = source.components.join(", "),
                 ))
-            })
+            });
+            if source.mirrors.is_empty() {
+                log
+            } else {
+                log.sub_bullet(format!(
+                    "{repository_uri} falls back to {mirrors}",
+                    repository_uri = style::url(source.uri.as_str()),
+                    mirrors = source
+                        .mirrors
+                        .iter()
+                        .map(|mirror| style::url(mirror.as_str()))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                ))
+            }
         });
 
     let timer = log.start_timer("Updating");
-    let updated_sources = update_sources(context, client, &distro.get_source_list()).await?;
+    let updated_sources = update_sources(context, client, &source_list, cache_freshness).await?;
     let log = timer.done();
 
     let log = updated_sources
         .iter()
         .fold(log, |log, updated_source| {
+            record_cache_event(
+                "release_file",
+                matches!(
+                    updated_source.release_file.cache_state,
+                    UpdatedSourceCacheState::Cached
+                ),
+            );
+
             let update_source_log =
                 log.sub_bullet(match &updated_source.release_file.cache_state {
-                    UpdatedSourceCacheState::Cached => format!(
-                        "Restored release file from cache {url}",
-                        url = style::details(style::url(
-                            &updated_source.release_file.release_file_url
-                        ))
-                    ),
+                    UpdatedSourceCacheState::Cached | UpdatedSourceCacheState::Patched(_) => {
+                        format!(
+                            "Restored release file from cache {url}",
+                            url = style::details(style::url(
+                                &updated_source.release_file.release_file_url
+                            ))
+                        )
+                    }
                     UpdatedSourceCacheState::New => format!(
                         "Downloaded release file {url}",
                         url = style::url(&updated_source.release_file.release_file_url)
@@ -92,9 +159,48 @@ pub(crate) async fn create_package_index(
                     ),
                 });
 
+            let verification = &updated_source.release_file.verification;
+            let update_source_log = match (
+                &verification.unverified_reason,
+                verification.signature_policy,
+            ) {
+                // sources with signature verification disabled were explicitly configured that way
+                (Some(_), SignaturePolicy::Disabled) | (None, _)
+                    if is_buildpack_debug_logging_enabled(context.platform.env()) =>
+                {
+                    update_source_log.sub_bullet(verification.to_string())
+                }
+                (Some(reason), SignaturePolicy::Optional) => update_source_log.warning(format!(
+                    "Using the release file {url} without verifying it because {reason}",
+                    url = style::url(&updated_source.release_file.release_file_url)
+                )),
+                _ => update_source_log,
+            };
+
+            // only possible when the source allows weak hashes
+            let update_source_log = if updated_source.checksum_algorithm.is_weak() {
+                update_source_log.warning(format!(
+                    "The release file {url} doesn't list SHA256 hashes so its package indexes are \
+                    verified with weak {algorithm} hashes. Packages from this source can be \
+                    tampered with without the build noticing.",
+                    url = style::url(&updated_source.release_file.release_file_url),
+                    algorithm = style::value(updated_source.checksum_algorithm.release_field()),
+                ))
+            } else {
+                update_source_log
+            };
+
             updated_source.package_indexes.iter().fold(
                 update_source_log,
                 |update_source_log, updated_package_index| {
+                    record_cache_event(
+                        "package_index",
+                        matches!(
+                            updated_package_index.cache_state,
+                            UpdatedSourceCacheState::Cached | UpdatedSourceCacheState::Patched(_)
+                        ),
+                    );
+
                     update_source_log.sub_bullet(match &updated_package_index.cache_state {
                         UpdatedSourceCacheState::Cached => format!(
                             "Restored package index from cache {url}",
@@ -111,37 +217,247 @@ pub(crate) async fn create_package_index(
                             url = style::url(&updated_package_index.package_index_url),
                             reason = style::details(reason)
                         ),
+                        UpdatedSourceCacheState::Patched(patches_applied) => {
+                            format!(
+                            "Updated package index from cache {url} with {patches_applied} {diffs}",
+                            url = style::url(&updated_package_index.package_index_url),
+                            diffs = if *patches_applied == 1 { "diff" } else { "diffs" }
+                        )
+                        }
                     })
                 },
             )
         })
         .done();
 
+    let mut indexed_sources = updated_sources
+        .iter()
+        .map(IndexedSource::from)
+        .collect::<Vec<_>>();
+    indexed_sources.sort_by(|a, b| a.release_file_url.cmp(&b.release_file_url));
+
     let log = log.bullet("Building package index");
     let timer = log.start_timer("Processing package files");
-    let package_index = build_package_index(
+    let is_package_subset = package_subset.is_some();
+    let updated_package_indexes = updated_sources
+        .into_iter()
+        .flat_map(|updated_source| updated_source.package_indexes)
+        .collect();
+    let (package_index, restored_package_index) = match package_subset {
+        // a subset depends on the requested packages so only the full package index is cached
+        Some(_) => (
+            build_package_index(updated_package_indexes, package_subset).await?,
+            false,
+        ),
+        None => build_cached_package_index(context, updated_package_indexes).await?,
+    };
+    let log = timer.done();
+
+    record_cache_event("parsed_package_index", restored_package_index);
+    let log = if restored_package_index {
+        log.sub_bullet("Restored parsed package index from cache")
+    } else {
+        log
+    };
+
+    let log = log.sub_bullet(format!(
+        "Indexed {} packages",
+        package_index.packages_indexed
+    ));
+
+    let log = if is_package_subset {
+        log.sub_bullet("Only the requested packages and their dependencies were indexed")
+    } else {
+        log
+    };
+
+    let log = if is_buildpack_debug_logging_enabled(context.platform.env()) {
+        package_index
+            .duplicate_packages
+            .iter()
+            .fold(log, |log, duplicate_package| {
+                log.sub_bullet(format!(
+                    "Skipped duplicate entry for {name_with_version} from {repository_uri} {filename}",
+                    name_with_version = style::value(format!(
+                        "{name}@{version}",
+                        name = duplicate_package.name,
+                        version = duplicate_package.version
+                    )),
+                    repository_uri = style::url(duplicate_package.repository_uri.as_str()),
+                    filename = style::details(&duplicate_package.filename)
+                ))
+            })
+    } else {
+        log
+    }
+    .done();
+
+    Ok((package_index, indexed_sources, log))
+}
+
+// How the distribution's sources from `buildpack.toml` are changed by project.toml. Additional
+// sources aren't affected by any of these.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct DistroSourceOptions<'a> {
+    pub(crate) snapshot: Option<&'a Snapshot>,
+    pub(crate) components: &'a [String],
+    pub(crate) optional_suites: &'a [OptionalSuite],
+}
+
+// The sources for the distribution followed by any additional sources configured in project.toml.
+pub(crate) fn get_source_list(
+    distro: &Distro,
+    supported_distros: &[SupportedDistro],
+    signing_keys: &SigningKeys,
+    custom_sources: &[CustomSource],
+    distro_source_options: DistroSourceOptions<'_>,
+) -> Vec<Source> {
+    let mut source_list = get_distro_source_list(
+        distro,
+        supported_distros,
+        signing_keys,
+        distro_source_options,
+    );
+    source_list.extend(
+        custom_sources
+            .iter()
+            .filter(|custom_source| custom_source.is_used_for(&distro.architecture))
+            .map(|custom_source| custom_source.to_source(&distro.architecture)),
+    );
+    source_list
+}
+
+fn get_distro_source_list(
+    distro: &Distro,
+    supported_distros: &[SupportedDistro],
+    signing_keys: &SigningKeys,
+    distro_source_options: DistroSourceOptions<'_>,
+) -> Vec<Source> {
+    let DistroSourceOptions {
+        snapshot,
+        components,
+        optional_suites,
+    } = distro_source_options;
+    let mut source_list = distro.get_source_list(supported_distros, signing_keys);
+    // configured components replace the built-in ones (e.g.; to add `multiverse` on Ubuntu)
+    if !components.is_empty() {
+        for source in &mut source_list {
+            source.components = components.to_vec();
+        }
+    }
+    // optional suites are only added where the release suite is listed since they're published to
+    // the same repositories (e.g.; not the Debian security archive)
+    let optional_suites = distro.get_optional_suites(supported_distros, optional_suites);
+    let release_suite = distro.codename.to_string();
+    for source in &mut source_list {
+        if source.suites.contains(&release_suite) {
+            for optional_suite in &optional_suites {
+                if !source.suites.contains(optional_suite) {
+                    source.suites.push(optional_suite.clone());
+                }
+            }
+        }
+    }
+    match snapshot {
+        Some(snapshot) => snapshot.apply(source_list),
+        None => source_list,
+    }
+}
+
+// The package index for a foreign architecture that packages were requested for (e.g.; `libc6:i386`).
+pub(crate) struct ForeignPackageIndex {
+    pub(crate) architecture: ArchitectureName,
+    pub(crate) package_index: PackageIndex,
+}
+
+// Builds a package index for every foreign architecture that packages were requested for. These
+// only include the distribution's sources since additional sources are configured for the target
+// architecture.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn create_foreign_package_indexes(
+    context: &Arc<BuildContext<DebianPackagesBuildpack>>,
+    client: &ClientWithMiddleware,
+    distro: &Distro,
+    foreign_architectures: &[ArchitectureName],
+    signing_keys: &SigningKeys,
+    distro_source_options: DistroSourceOptions<'_>,
+    cache_freshness: CacheFreshness,
+    log: Print<Bullet<Stdout>>,
+) -> BuildpackResult<(Vec<ForeignPackageIndex>, Print<Bullet<Stdout>>)> {
+    if foreign_architectures.is_empty() {
+        return Ok((vec![], log));
+    }
+
+    let mut log = log.bullet("Foreign architectures");
+    let mut foreign_package_indexes = vec![];
+    for architecture in foreign_architectures {
+        let timer = log.start_timer(format!("Indexing packages for {architecture}"));
+        let package_index = create_package_index_for_architecture(
+            context,
+            client,
+            distro,
+            architecture,
+            signing_keys,
+            distro_source_options,
+            cache_freshness,
+        )
+        .await?;
+        log = timer.done().sub_bullet(format!(
+            "Indexed {count} packages for {architecture}",
+            count = package_index.packages_indexed
+        ));
+        foreign_package_indexes.push(ForeignPackageIndex {
+            architecture: architecture.clone(),
+            package_index,
+        });
+    }
+
+    Ok((foreign_package_indexes, log.done()))
+}
+
+// Builds a package index for the given architecture of the distribution. This is used for packages
+// requested for a foreign architecture and to explain why a requested package couldn't be found so
+// nothing is logged for the sources here.
+pub(crate) async fn create_package_index_for_architecture(
+    context: &Arc<BuildContext<DebianPackagesBuildpack>>,
+    client: &ClientWithMiddleware,
+    distro: &Distro,
+    architecture: &ArchitectureName,
+    signing_keys: &SigningKeys,
+    distro_source_options: DistroSourceOptions<'_>,
+    cache_freshness: CacheFreshness,
+) -> BuildpackResult<PackageIndex> {
+    let distro = Distro {
+        architecture: architecture.clone(),
+        ..distro.clone()
+    };
+    let updated_sources = update_sources(
+        context,
+        client,
+        &get_distro_source_list(
+            &distro,
+            &context.buildpack_descriptor.metadata.distros,
+            signing_keys,
+            distro_source_options,
+        ),
+        cache_freshness,
+    )
+    .await?;
+    build_package_index(
         updated_sources
             .into_iter()
             .flat_map(|updated_source| updated_source.package_indexes)
             .collect(),
+        None,
     )
-    .await?;
-    let log = timer.done();
-
-    let log = log
-        .sub_bullet(format!(
-            "Indexed {} packages",
-            package_index.packages_indexed
-        ))
-        .done();
-
-    Ok((package_index, log))
+    .await
 }
 
 async fn update_sources(
     context: &Arc<BuildContext<DebianPackagesBuildpack>>,
     client: &ClientWithMiddleware,
     sources: &[Source],
+    cache_freshness: CacheFreshness,
 ) -> BuildpackResult<Vec<UpdatedSource>> {
     if sources.is_empty() {
         Err(CreatePackageIndexError::NoSources)?;
@@ -149,45 +465,63 @@ async fn update_sources(
 
     let mut update_source_handles = JoinSet::new();
 
-    for source in sources {
-        for suite in &source.suites {
-            update_source_handles.spawn(update_source(
-                context.clone(),
-                client.clone(),
-                source.uri.clone(),
-                suite.to_string(),
-                source.components.clone(),
-                source.arch.clone(),
-                source.signed_by.to_string(),
-            ));
-        }
+    let source_suites = sources
+        .iter()
+        .flat_map(|source| source.suites.iter().map(move |suite| (source, suite)));
+    for (position, (source, suite)) in source_suites.enumerate() {
+        let updated_source = update_source(
+            context.clone(),
+            client.clone(),
+            source.repository(),
+            suite.to_string(),
+            source.components.clone(),
+            source.arch.clone(),
+            source.signed_by.clone(),
+            source.signature_policy,
+            source.allow_weak_hashes,
+            source.download_url.clone(),
+            cache_freshness,
+        );
+        update_source_handles.spawn(async move { (position, updated_source.await) });
     }
 
     let mut updated_sources = vec![];
     while let Some(update_source_handle) = update_source_handles.join_next().await {
-        let updated_source =
-            update_source_handle.map_err(CreatePackageIndexError::TaskFailed)??;
-        updated_sources.push(updated_source);
+        let (position, updated_source) =
+            update_source_handle.map_err(CreatePackageIndexError::TaskFailed)?;
+        updated_sources.push((position, updated_source?));
     }
 
-    Ok(updated_sources)
+    // kept in the order the sources are configured so duplicate package entries are resolved the
+    // same way on every build (see `PackageIndex::add_package`)
+    updated_sources.sort_by_key(|(position, _)| *position);
+    Ok(updated_sources
+        .into_iter()
+        .map(|(_, updated_source)| updated_source)
+        .collect())
 }
 
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
 async fn update_source(
     context: Arc<BuildContext<DebianPackagesBuildpack>>,
     client: ClientWithMiddleware,
-    repository_uri: RepositoryUri,
+    repository: Arc<dyn PackageRepository>,
     suite: String,
     components: Vec<String>,
     arch: ArchitectureName,
-    signed_by: String,
+    signed_by: Option<String>,
+    signature_policy: SignaturePolicy,
+    allow_weak_hashes: bool,
+    download_url: Option<String>,
+    cache_freshness: CacheFreshness,
 ) -> BuildpackResult<UpdatedSource> {
     let updated_release_file = get_release(
         context.clone(),
         client.clone(),
-        repository_uri.clone(),
-        suite.clone(),
+        repository.as_ref(),
+        &suite,
         signed_by,
+        signature_policy,
     )
     .await?;
 
@@ -208,91 +542,211 @@ async fn update_source(
             })
         })?;
 
-    let mut get_package_list_handles = JoinSet::new();
+    if cache_freshness.check_release_dates {
+        check_release_dates(
+            &updated_release_file.release_file_url,
+            &release,
+            unix_timestamp()?,
+        )?;
+    }
+
+    let (checksum_algorithm, release_hashes) = release_hashes(&release, allow_weak_hashes).ok_or(
+        CreatePackageIndexError::MissingSha256ReleaseHashes(repository.uri().clone()),
+    )?;
+
+    // resolve the package index for every component up front so that all the missing entries
+    // can be reported together instead of failing on the first one
+    let mut package_index_requests = vec![];
+    let mut missing_package_indexes = vec![];
 
     for component in components {
-        let package_index = format!("{component}/binary-{arch}/Packages.gz");
-        let package_index_release_hash = release
-            .sha256sum
-            .as_ref()
-            .ok_or(CreatePackageIndexError::MissingSha256ReleaseHashes(
-                repository_uri.clone(),
-            ))?
+        let Some((compression, package_index_release_hash)) =
+            find_package_index(repository.as_ref(), &component, &arch, release_hashes)
+        else {
+            missing_package_indexes.push(repository.package_index_path(
+                &component,
+                &arch,
+                PackageIndexCompression::Gzip.package_index_file_name(),
+            ));
+            continue;
+        };
+
+        let mut package_index_request = PackageIndexRequest::new(
+            repository.as_ref(),
+            &suite,
+            &component,
+            &arch,
+            compression,
+            checksum_algorithm,
+            &package_index_release_hash.hash,
+            release.acquire_by_hash.unwrap_or_default(),
+        );
+
+        // the diffs are listed with SHA256 hashes in the pdiff index
+        let pdiff_index = repository.package_index_path(&component, &arch, PDIFF_INDEX_FILE_NAME);
+        if let Some(pdiff_index_release_hash) = release_hashes
             .iter()
-            .find(|release_hash| release_hash.filename == package_index)
-            .ok_or(CreatePackageIndexError::MissingPackageIndexReleaseHash(
-                repository_uri.clone(),
-                package_index,
-            ))?;
+            .filter(|_| !checksum_algorithm.is_weak())
+            .find(|release_hash| release_hash.filename == pdiff_index)
+        {
+            package_index_request = package_index_request.with_pdiff_index(
+                repository.as_ref(),
+                &suite,
+                &component,
+                &arch,
+                &pdiff_index_release_hash.hash,
+            );
+        }
 
-        let package_release_url = if release.acquire_by_hash.unwrap_or_default() {
-            format!(
-                "{}/dists/{suite}/{component}/binary-{arch}/by-hash/SHA256/{}",
-                repository_uri.as_str(),
-                package_index_release_hash.hash
-            )
-        } else {
-            format!(
-                "{}/dists/{suite}/{component}/binary-{arch}/Packages.gz",
-                repository_uri.as_str()
-            )
-        };
+        package_index_requests.push(package_index_request);
+    }
+
+    if !missing_package_indexes.is_empty() {
+        Err(CreatePackageIndexError::MissingPackageIndexReleaseHash(
+            repository.uri().clone(),
+            missing_package_indexes,
+        ))?;
+    }
+
+    let mut get_package_list_handles = JoinSet::new();
 
-        get_package_list_handles.spawn(get_package_list(
+    for (position, package_index_request) in package_index_requests.into_iter().enumerate() {
+        let updated_package_index = get_package_list(
             context.clone(),
             client.clone(),
-            repository_uri.clone(),
-            package_release_url,
-            package_index_release_hash.hash.to_string(),
-        ));
+            repository.uri().clone(),
+            package_index_request,
+            allow_weak_hashes,
+            download_url.clone(),
+            cache_freshness.max_package_index_age,
+        );
+        get_package_list_handles.spawn(async move { (position, updated_package_index.await) });
     }
 
+    let not_automatic = is_not_automatic(&release);
     let mut updated_package_indexes = vec![];
     while let Some(get_package_list_handle) = get_package_list_handles.join_next().await {
-        updated_package_indexes
-            .push(get_package_list_handle.map_err(CreatePackageIndexError::TaskFailed)??);
+        let (position, updated_package_index) =
+            get_package_list_handle.map_err(CreatePackageIndexError::TaskFailed)?;
+        updated_package_indexes.push((
+            position,
+            UpdatedPackageIndex {
+                not_automatic,
+                suite: suite.clone(),
+                ..updated_package_index?
+            },
+        ));
     }
+    // kept in the order the components are configured
+    updated_package_indexes.sort_by_key(|(position, _)| *position);
+    let updated_package_indexes = updated_package_indexes
+        .into_iter()
+        .map(|(_, updated_package_index)| updated_package_index)
+        .collect();
 
     Ok(UpdatedSource {
         release_file: updated_release_file,
+        checksum_algorithm,
         package_indexes: updated_package_indexes,
     })
 }
 
+// Some repositories only publish the package index with xz compression (e.g.; the Debian updates
+// and security suites) and some flat or internal repositories only publish it uncompressed so gzip
+// is preferred but the others are used when they're the only ones listed.
+fn find_package_index<'a>(
+    repository: &dyn PackageRepository,
+    component: &str,
+    arch: &ArchitectureName,
+    release_hashes: &'a [ReleaseHash],
+) -> Option<(PackageIndexCompression, &'a ReleaseHash)> {
+    [
+        PackageIndexCompression::Gzip,
+        PackageIndexCompression::Xz,
+        PackageIndexCompression::Uncompressed,
+    ]
+    .into_iter()
+    .find_map(|compression| {
+        let package_index =
+            repository.package_index_path(component, arch, compression.package_index_file_name());
+        release_hashes
+            .iter()
+            .find(|release_hash| release_hash.filename == package_index)
+            .map(|release_hash| (compression, release_hash))
+    })
+}
+
+// The Release file must list SHA256 hashes unless the source allows weak hashes, in which case the
+// strongest hashes it lists are used.
+fn release_hashes(
+    release: &Release,
+    allow_weak_hashes: bool,
+) -> Option<(ChecksumAlgorithm, &Vec<ReleaseHash>)> {
+    ChecksumAlgorithm::allowed(allow_weak_hashes).find_map(|algorithm| {
+        match algorithm {
+            ChecksumAlgorithm::Sha256 => release.sha256sum.as_ref(),
+            ChecksumAlgorithm::Sha1 => release.sha1sum.as_ref(),
+            ChecksumAlgorithm::Md5 => release.md5sum.as_ref(),
+        }
+        .map(|release_hashes| (algorithm, release_hashes))
+    })
+}
+
+#[allow(clippy::too_many_lines)]
 async fn get_release(
     context: Arc<BuildContext<DebianPackagesBuildpack>>,
     client: ClientWithMiddleware,
-    uri: RepositoryUri,
-    suite: String,
-    signed_by: String,
+    repository: &dyn PackageRepository,
+    suite: &str,
+    signed_by: Option<String>,
+    signature_policy: SignaturePolicy,
 ) -> BuildpackResult<UpdatedReleaseFile> {
-    let release_file_url = format!("{}/dists/{suite}/InRelease", uri.as_str());
+    let cert_helper = get_cert_helper(signed_by, signature_policy, &repository.suite_url(suite))?;
+    let certificate_user_id = cert_helper
+        .as_ref()
+        .and_then(CertHelper::certificate_user_id);
+    let certificate_fingerprint = cert_helper
+        .as_ref()
+        .map(|cert_helper| cert_helper.certificate_fingerprint().to_hex());
+
+    let now = unix_timestamp()?;
+
+    // the signing key fingerprint is only known after verification so it's excluded here
+    let is_reusable = |old_metadata: &ReleaseFileMetadata| {
+        old_metadata.certificate_fingerprint == certificate_fingerprint
+            && old_metadata.signature_policy == signature_policy
+            && !old_metadata.is_expired(now)
+    };
 
-    let response = client
-        .get(&release_file_url)
-        .send()
-        .await
-        .and_then(|res| res.error_for_status().map_err(Reqwest))
-        .map_err(CreatePackageIndexError::GetReleaseRequest)?;
+    let suite_url = repository.suite_url(suite);
+    let mut release_file_url = format!("{suite_url}/InRelease");
+
+    // the client revalidates the release file with the repository using the validators stored by
+    // its HTTP cache so an unchanged file is returned without downloading it again
+    let response = match client.get(&release_file_url).send().await {
+        // repositories that aren't signed often only publish the unsigned Release file and some
+        // signed repositories only publish it with a detached signature in Release.gpg
+        Ok(res) if res.status() == StatusCode::NOT_FOUND => {
+            release_file_url = format!("{suite_url}/Release");
+            client.get(&release_file_url).send().await
+        }
+        res => res,
+    }
+    .and_then(|res| res.error_for_status().map_err(Reqwest))
+    .map_err(CreatePackageIndexError::GetReleaseRequest)?;
 
-    // it would be nice to use the url as the layer name but urls don't make for good file names
-    // so instead we'll convert the url to a sha256 hex value
-    let layer_name = LayerName::from_str(&format!("{:x}", Sha256::digest(&release_file_url)))
-        .map_err(|e| CreatePackageIndexError::InvalidLayerName(release_file_url.clone(), e))?;
+    let layer_name = release_file_layer_name(&release_file_url)?;
 
     let new_metadata = ReleaseFileMetadata {
-        etag: response.headers().get(ETAG).and_then(|header_value| {
-            if let Ok(etag) = header_value.to_str() {
-                Some(etag.to_string())
-            } else {
-                None
-            }
-        }),
-        timestamp: SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(CreatePackageIndexError::SystemTimeError)?
-            .as_secs(), // Adding the timestamp here
-    };        
+        certificate_fingerprint: certificate_fingerprint.clone(),
+        signature_policy,
+        signing_key_fingerprint: None,
+        unverified_reason: None,
+        etag: get_header(&response, ETAG),
+        last_modified: get_header(&response, LAST_MODIFIED),
+        valid_until: None,
+        timestamp: now,
+    };
 
     let release_file_layer = context.cached_layer(
         layer_name,
@@ -300,10 +754,32 @@ async fn get_release(
             build: true,
             launch: false,
             restored_layer_action: &|old_metadata: &ReleaseFileMetadata, _| {
-                if old_metadata == &new_metadata {
-                    RestoredLayerAction::KeepLayer
+                // the validators only match when the repository serves the same release file
+                let is_unchanged = match (&old_metadata.etag, &old_metadata.last_modified) {
+                    (Some(etag), _) => new_metadata.etag.as_ref() == Some(etag),
+                    (None, Some(last_modified)) => {
+                        new_metadata.last_modified.as_ref() == Some(last_modified)
+                    }
+                    (None, None) => false,
+                };
+                if old_metadata.is_expired(now) {
+                    (
+                        RestoredLayerAction::DeleteLayer,
+                        Err("Valid-Until date has passed".to_string()),
+                    )
+                } else if is_unchanged && is_reusable(old_metadata) {
+                    (
+                        RestoredLayerAction::KeepLayer,
+                        Ok((
+                            old_metadata.signing_key_fingerprint.clone(),
+                            old_metadata.unverified_reason.clone(),
+                        )),
+                    )
                 } else {
-                    RestoredLayerAction::DeleteLayer
+                    (
+                        RestoredLayerAction::DeleteLayer,
+                        Err("Stored ETag did not match".to_string()),
+                    )
                 }
             },
             invalid_metadata_action: &|_| InvalidMetadataAction::DeleteLayer,
@@ -312,11 +788,23 @@ async fn get_release(
 
     let release_file_path = release_file_layer.path().join("release");
 
-    let cache_state = match release_file_layer.state {
-        LayerState::Restored { .. } => UpdatedSourceCacheState::Cached,
+    let (cache_state, verification) = match &release_file_layer.state {
+        LayerState::Restored {
+            cause: Ok((signing_key_fingerprint, unverified_reason)),
+        } => {
+            let verification = ReleaseFileVerification {
+                certificate_user_id,
+                certificate_fingerprint: new_metadata.certificate_fingerprint.clone(),
+                signing_key_fingerprint: signing_key_fingerprint.clone(),
+                signature_policy,
+                unverified_reason: unverified_reason.clone(),
+            };
+            (UpdatedSourceCacheState::Cached, verification)
+        }
+        LayerState::Restored { cause: Err(_) } => {
+            unreachable!("Release files are only kept when they can be reused")
+        }
         LayerState::Empty { cause } => {
-            release_file_layer.write_metadata(new_metadata)?;
-
             let raw_release_url_path = release_file_layer.path().join(".url");
             async_write(&raw_release_url_path, &release_file_url)
                 .await
@@ -324,47 +812,104 @@ async fn get_release(
 
             // println!("  [GET] {url}", url = &release_url);
 
-            let unverified_response_body = response
-                .text()
-                .await
-                .map_err(CreatePackageIndexError::ReadGetReleaseResponse)?;
-
-            // GPG verification
-            let policy = StandardPolicy::new();
-            let cert_helper = Cert::from_str(&signed_by)
-                .map_err(CreatePackageIndexError::CreatePgpCertificate)
-                .map(CertHelper::new)?;
-
-            let mut reader = FuturesAsyncReadCompatExt::compat(AllowStdIo::new(
-                VerifierBuilder::from_bytes(&unverified_response_body)
-                    .map_err(CreatePackageIndexError::CreatePgpVerifier)
-                    .and_then(|verifier_builder| {
-                        verifier_builder
-                            .with_policy(&policy, None, cert_helper)
-                            .map_err(CreatePackageIndexError::CreatePgpVerifier)
-                    })?,
-            ));
+            let etag = get_header(&response, ETAG);
+            let last_modified = get_header(&response, LAST_MODIFIED);
+            // errors reading the body don't include the URL, which can differ from the requested
+            // one when the request was redirected
+            let final_url = response.url().clone();
+            let unverified_response_body = response.text().await.map_err(|e| {
+                CreatePackageIndexError::ReadGetReleaseResponse(e.with_url(final_url))
+            })?;
 
-            let mut writer = AsyncFile::create(&release_file_path)
+            let detached_signature = match &cert_helper {
+                Some(_) if !is_clearsigned(&unverified_response_body) => {
+                    get_detached_signature(&client, &release_file_url).await?
+                }
+                _ => None,
+            };
+
+            let unverified_reason = match (signature_policy, &cert_helper) {
+                (SignaturePolicy::Disabled, _) => Some("signature verification is disabled"),
+                (SignaturePolicy::Optional, None) => Some("no signing key is configured"),
+                (SignaturePolicy::Optional, Some(_))
+                    if !is_clearsigned(&unverified_response_body)
+                        && detached_signature.is_none() =>
+                {
+                    Some("it isn't signed")
+                }
+                _ => None,
+            }
+            .map(ToString::to_string);
+
+            let signing_key_fingerprint = if let (Some(cert_helper), None) =
+                (cert_helper, &unverified_reason)
+            {
+                match &detached_signature {
+                    Some(detached_signature) => {
+                        verify_detached_release_file(
+                            &unverified_response_body,
+                            detached_signature,
+                            cert_helper,
+                            &release_file_path,
+                        )
+                        .await?
+                    }
+                    None if !is_clearsigned(&unverified_response_body) => Err(
+                        CreatePackageIndexError::MissingReleaseSignature(release_file_url.clone()),
+                    )?,
+                    None => {
+                        verify_release_file(
+                            &unverified_response_body,
+                            cert_helper,
+                            &release_file_path,
+                        )
+                        .await?
+                    }
+                }
+            } else {
+                async_write(
+                    &release_file_path,
+                    strip_clearsigned(&unverified_response_body),
+                )
                 .await
                 .map_err(|e| {
                     CreatePackageIndexError::WriteReleaseLayer(release_file_path.clone(), e)
-                })
-                .map(AsyncBufWriter::new)?;
-
-            async_copy(&mut reader, &mut writer).await.map_err(|e| {
-                CreatePackageIndexError::WriteReleaseLayer(release_file_path.clone(), e)
+                })?;
+                None
+            };
+
+            release_file_layer.write_metadata(ReleaseFileMetadata {
+                signing_key_fingerprint: signing_key_fingerprint.clone(),
+                unverified_reason: unverified_reason.clone(),
+                etag,
+                last_modified,
+                valid_until: find_release_field(&unverified_response_body, "Valid-Until")
+                    .and_then(parse_release_date),
+                ..new_metadata.clone()
             })?;
 
-            match cause {
+            let cache_state = match cause {
                 EmptyLayerCause::NewlyCreated => UpdatedSourceCacheState::New,
                 EmptyLayerCause::InvalidMetadataAction { .. } => {
                     UpdatedSourceCacheState::Invalidated("Invalid metadata".to_string())
                 }
-                EmptyLayerCause::RestoredLayerAction { .. } => {
-                    UpdatedSourceCacheState::Invalidated("Stored ETag did not match".to_string())
+                EmptyLayerCause::RestoredLayerAction { cause: Err(reason) } => {
+                    UpdatedSourceCacheState::Invalidated(reason.clone())
                 }
-            }
+                EmptyLayerCause::RestoredLayerAction { cause: Ok(_) } => {
+                    unreachable!("Release files are only deleted when they can't be reused")
+                }
+            };
+
+            let verification = ReleaseFileVerification {
+                certificate_user_id,
+                certificate_fingerprint: new_metadata.certificate_fingerprint,
+                signing_key_fingerprint,
+                signature_policy,
+                unverified_reason,
+            };
+
+            (cache_state, verification)
         }
     };
 
@@ -372,51 +917,330 @@ async fn get_release(
         release_file_url,
         release_file_path,
         cache_state,
+        verification,
+    })
+}
+
+// A source with a `required` signature policy must never have its Release file written without
+// verifying it so a missing signing key is an error rather than a reason to skip verification.
+fn get_cert_helper(
+    signed_by: Option<String>,
+    signature_policy: SignaturePolicy,
+    suite_url: &str,
+) -> Result<Option<CertHelper>, CreatePackageIndexError> {
+    let cert_helper = signed_by
+        .filter(|_| signature_policy != SignaturePolicy::Disabled)
+        .map(|signed_by| {
+            Cert::from_str(&signed_by)
+                .map_err(CreatePackageIndexError::CreatePgpCertificate)
+                .map(CertHelper::new)
+        })
+        .transpose()?;
+    match (signature_policy, cert_helper) {
+        (SignaturePolicy::Required, None) => Err(CreatePackageIndexError::MissingSigningKey(
+            suite_url.to_string(),
+        )),
+        (_, cert_helper) => Ok(cert_helper),
+    }
+}
+
+// it would be nice to use the url as the layer name but urls don't make for good file names
+// so instead we'll convert the url to a sha256 hex value
+fn release_file_layer_name(release_file_url: &str) -> Result<LayerName, CreatePackageIndexError> {
+    LayerName::from_str(&format!("{:x}", Sha256::digest(release_file_url)))
+        .map_err(|e| CreatePackageIndexError::InvalidLayerName(release_file_url.to_string(), e))
+}
+
+// Repositories that are updated regularly (e.g.; the Debian security suite) set a `Valid-Until`
+// date after which their Release file shouldn't be trusted so a mirror serving an outdated copy is
+// detected. A `Date` in the future is refused for the same reason, allowing for some clock skew.
+fn check_release_dates(
+    release_file_url: &str,
+    release: &Release,
+    now: u64,
+) -> Result<(), CreatePackageIndexError> {
+    if let Some(valid_until) = &release.valid_until {
+        if parse_release_date(valid_until).is_some_and(|valid_until| valid_until <= now) {
+            Err(CreatePackageIndexError::ExpiredReleaseFile(
+                release_file_url.to_string(),
+                valid_until.clone(),
+            ))?;
+        }
+    }
+    if let Some(date) = &release.date {
+        if parse_release_date(date).is_some_and(|date| date > now + MAX_RELEASE_DATE_SKEW) {
+            Err(CreatePackageIndexError::ReleaseFileNotValidYet(
+                release_file_url.to_string(),
+                date.clone(),
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+// Release file dates are RFC 2822 dates which Debian and Ubuntu write with a `UTC` time zone
+// (e.g.; `Thu, 25 Apr 2024 15:10:33 UTC`) that isn't one of the zone names RFC 2822 allows.
+fn parse_release_date(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let value = match value.strip_suffix("UTC") {
+        Some(value) => format!("{value}+0000"),
+        None => value.to_string(),
+    };
+    DateTime::parse_from_rfc2822(&value)
+        .ok()
+        .and_then(|date| u64::try_from(date.timestamp()).ok())
+}
+
+// Reads a field from the start of a release file without parsing the (much longer) hash lists.
+// Suites like backports set `NotAutomatic` so apt only installs their packages when they're
+// requested explicitly. `ButAutomaticUpgrades` additionally allows upgrading packages that are
+// already installed from them but the packages of the base image are never upgraded here so
+// both are treated the same way.
+fn is_not_automatic(release: &Release) -> bool {
+    release.not_automatic.unwrap_or(false)
+}
+
+fn find_release_field<'a>(release: &'a str, name: &str) -> Option<&'a str> {
+    release.lines().find_map(|line| {
+        line.strip_prefix(name)
+            .and_then(|line| line.strip_prefix(':'))
+            .map(str::trim)
     })
 }
 
+fn unix_timestamp() -> Result<u64, CreatePackageIndexError> {
+    Ok(SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(CreatePackageIndexError::SystemTimeError)?
+        .as_secs())
+}
+
+fn get_header(response: &Response, name: reqwest::header::HeaderName) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|header_value| header_value.to_str().ok())
+        .map(ToString::to_string)
+}
+
+// A Release file that isn't clearsigned can have a detached signature published next to it in
+// Release.gpg. Returns `None` when the repository doesn't publish one.
+async fn get_detached_signature(
+    client: &ClientWithMiddleware,
+    release_file_url: &str,
+) -> Result<Option<Vec<u8>>, CreatePackageIndexError> {
+    let response = client
+        .get(format!("{release_file_url}.gpg"))
+        .send()
+        .await
+        .map_err(CreatePackageIndexError::GetReleaseRequest)?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let response = response
+        .error_for_status()
+        .map_err(|e| CreatePackageIndexError::GetReleaseRequest(Reqwest(e)))?;
+    let final_url = response.url().clone();
+    response
+        .bytes()
+        .await
+        .map(|bytes| Some(bytes.to_vec()))
+        .map_err(|e| CreatePackageIndexError::ReadGetReleaseResponse(e.with_url(final_url)))
+}
+
+// Writes the release file once it's verified against its detached signature and returns the
+// fingerprint of the key that signed it.
+async fn verify_detached_release_file(
+    unverified_release_file: &str,
+    detached_signature: &[u8],
+    cert_helper: CertHelper,
+    release_file_path: &Path,
+) -> Result<Option<String>, CreatePackageIndexError> {
+    let policy = StandardPolicy::new();
+
+    let mut verifier = DetachedVerifierBuilder::from_bytes(detached_signature)
+        .map_err(CreatePackageIndexError::CreatePgpVerifier)
+        .and_then(|verifier_builder| {
+            verifier_builder
+                .with_policy(&policy, None, cert_helper)
+                .map_err(CreatePackageIndexError::CreatePgpVerifier)
+        })?;
+    verifier
+        .verify_bytes(unverified_release_file)
+        .map_err(CreatePackageIndexError::CreatePgpVerifier)?;
+
+    async_write(release_file_path, unverified_release_file)
+        .await
+        .map_err(|e| {
+            CreatePackageIndexError::WriteReleaseLayer(release_file_path.to_path_buf(), e)
+        })?;
+
+    Ok(verifier
+        .into_helper()
+        .signing_key_fingerprint()
+        .map(Fingerprint::to_hex))
+}
+
+// Writes the verified contents of the release file and returns the fingerprint of the key that
+// signed it.
+async fn verify_release_file(
+    unverified_release_file: &str,
+    cert_helper: CertHelper,
+    release_file_path: &Path,
+) -> Result<Option<String>, CreatePackageIndexError> {
+    let policy = StandardPolicy::new();
+
+    let mut reader = FuturesAsyncReadCompatExt::compat(AllowStdIo::new(
+        VerifierBuilder::from_bytes(unverified_release_file)
+            .map_err(CreatePackageIndexError::CreatePgpVerifier)
+            .and_then(|verifier_builder| {
+                verifier_builder
+                    .with_policy(&policy, None, cert_helper)
+                    .map_err(CreatePackageIndexError::CreatePgpVerifier)
+            })?,
+    ));
+
+    let mut writer = AsyncFile::create(release_file_path)
+        .await
+        .map_err(|e| CreatePackageIndexError::WriteReleaseLayer(release_file_path.to_path_buf(), e))
+        .map(AsyncBufWriter::new)?;
+
+    async_copy(&mut reader, &mut writer).await.map_err(|e| {
+        CreatePackageIndexError::WriteReleaseLayer(release_file_path.to_path_buf(), e)
+    })?;
+
+    Ok(reader
+        .into_inner()
+        .into_inner()
+        .into_helper()
+        .signing_key_fingerprint()
+        .map(Fingerprint::to_hex))
+}
+
+#[allow(clippy::too_many_lines)]
 async fn get_package_list(
     context: Arc<BuildContext<DebianPackagesBuildpack>>,
     client: ClientWithMiddleware,
     repository_uri: RepositoryUri,
-    package_index_url: String,
-    hash: String,
+    package_index_request: PackageIndexRequest,
+    allow_weak_hashes: bool,
+    download_url: Option<String>,
+    max_package_index_age: Option<Duration>,
 ) -> BuildpackResult<UpdatedPackageIndex> {
+    let PackageIndexRequest {
+        url: package_index_url,
+        canonical_url,
+        checksum_algorithm,
+        hash,
+        compression,
+        pdiff_index,
+    } = package_index_request;
+
     // it would be nice to use the url as the layer name but urls don't make for good file names
-    // so instead we'll convert the url to a sha256 hex value
-    let layer_name = LayerName::from_str(&format!("{:x}", Sha256::digest(&package_index_url)))
+    // so instead we'll convert the url to a sha256 hex value. The canonical url is used when
+    // downloading by hash so an outdated package index is restored and can be updated with diffs.
+    let layer_url = canonical_url.as_ref().unwrap_or(&package_index_url);
+    let layer_name = LayerName::from_str(&format!("{:x}", Sha256::digest(layer_url)))
         .map_err(|e| CreatePackageIndexError::InvalidLayerName(package_index_url.clone(), e))?;
 
     // Create new metadata with a timestamp
     let new_metadata = PackageIndexMetadata {
         hash: hash.to_string(),
-        timestamp: SystemTime::now().duration_since(UNIX_EPOCH)
-            .map_err(CreatePackageIndexError::SystemTimeError)?
-            .as_secs(), // Adding the timestamp here
+        compression: PACKAGE_INDEX_COMPRESSION.to_string(),
+        timestamp: unix_timestamp()?, // Adding the timestamp here
+        uncompressed_hash: None,
     };        
 
+    // the timestamp is only updated when the package index is downloaded or patched so a package
+    // index that's restored as-is keeps getting older
+    let is_too_old = |old_metadata: &PackageIndexMetadata| {
+        max_package_index_age.is_some_and(|max_package_index_age| {
+            new_metadata
+                .timestamp
+                .saturating_sub(old_metadata.timestamp)
+                > max_package_index_age.as_secs()
+        })
+    };
+
     let package_index_layer = context.cached_layer(
         layer_name,
         CachedLayerDefinition {
             build: true,
             launch: false,
             restored_layer_action: &|old_metadata: &PackageIndexMetadata, _| {
-                if old_metadata == &new_metadata {
-                    RestoredLayerAction::KeepLayer
+                if old_metadata.compression != new_metadata.compression {
+                    (
+                        RestoredLayerAction::DeleteLayer,
+                        Err("Stored checksum did not match".to_string()),
+                    )
+                } else if is_too_old(old_metadata) {
+                    (
+                        RestoredLayerAction::DeleteLayer,
+                        Err("Cached package index is too old".to_string()),
+                    )
+                } else if old_metadata.hash == new_metadata.hash {
+                    (RestoredLayerAction::KeepLayer, Ok(None))
                 } else {
-                    RestoredLayerAction::DeleteLayer
+                    // an outdated package index is kept when the repository publishes diffs for it
+                    match (&old_metadata.uncompressed_hash, &pdiff_index) {
+                        (Some(uncompressed_hash), Some(_)) => (
+                            RestoredLayerAction::KeepLayer,
+                            Ok(Some(uncompressed_hash.clone())),
+                        ),
+                        _ => (
+                            RestoredLayerAction::DeleteLayer,
+                            Err("Stored checksum did not match".to_string()),
+                        ),
+                    }
                 }
             },
             invalid_metadata_action: &|_| InvalidMetadataAction::DeleteLayer,
         },
     )?;
 
-    let package_index_path = package_index_layer.path().join("package_index");
+    let package_index_path = package_index_layer.path().join("package_index.zst");
 
-    let cache_state = match package_index_layer.state {
-        LayerState::Restored { .. } => UpdatedSourceCacheState::Cached,
-        LayerState::Empty { cause } => {
-            package_index_layer.write_metadata(new_metadata)?;
+    let cache_state = match (&package_index_layer.state, &pdiff_index) {
+        (LayerState::Restored { cause: Ok(None) }, _) => UpdatedSourceCacheState::Cached,
+        (
+            LayerState::Restored {
+                cause: Ok(Some(outdated_hash)),
+            },
+            Some(pdiff_index),
+        ) => match patch_package_index(&client, pdiff_index, outdated_hash, &package_index_path)
+            .await
+        {
+            Ok((patches_applied, uncompressed_hash)) => {
+                package_index_layer.write_metadata(PackageIndexMetadata {
+                    uncompressed_hash: Some(uncompressed_hash),
+                    ..new_metadata
+                })?;
+                UpdatedSourceCacheState::Patched(patches_applied)
+            }
+            Err(e) => {
+                let uncompressed_hash = download_package_index(
+                    &client,
+                    &package_index_url,
+                    canonical_url.as_ref(),
+                    checksum_algorithm,
+                    &hash,
+                    compression,
+                    &package_index_path,
+                )
+                .await?;
+                package_index_layer.write_metadata(PackageIndexMetadata {
+                    uncompressed_hash: Some(uncompressed_hash),
+                    ..new_metadata
+                })?;
+                UpdatedSourceCacheState::Invalidated(format!("Failed to apply diffs: {e}"))
+            }
+        },
+        (LayerState::Restored { .. }, _) => unreachable!(
+            "Package indexes are only kept with a different hash when diffs are published"
+        ),
+        (LayerState::Empty { cause }, _) => {
+            // written first so a package index that fails to download is replaced next time
+            package_index_layer.write_metadata(new_metadata.clone())?;
 
             let package_index_url_path = package_index_layer.path().join(".url");
             async_write(&package_index_url_path, &package_index_url)
@@ -425,72 +1249,31 @@ async fn get_package_list(
                     CreatePackageIndexError::WritePackagesLayer(package_index_url_path, e)
                 })?;
 
-            let response = client
-                .get(&package_index_url)
-                .send()
-                .await
-                .and_then(|res| res.error_for_status().map_err(Reqwest))
-                .map_err(CreatePackageIndexError::GetPackagesRequest)?;
-
-            let mut hasher = Sha256::new();
-
-            // the package list we request uses gzip compression so we'll decode that directly from the response
-            let mut reader = GzipDecoder::new(AsyncBufReader::new(
-                // the inspect reader lets us pipe this decompressed output to both the ouptut file and the hash digest
-                InspectReader::new(
-                    // and we need to convert the http stream into an async reader
-                    FuturesAsyncReadCompatExt::compat(
-                        response
-                            .bytes_stream()
-                            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
-                            .into_async_read(),
-                    ),
-                    |bytes| hasher.update(bytes),
-                ),
-            ));
-
-            // Enable support for multistream gz files. In this mode, the reader expects the input to
-            // be a sequence of individually gzipped data streams, each with its own header and trailer,
-            // ending at EOF. This is standard behavior for gzip readers.
-            reader.multiple_members(true);
-
-            let mut writer = AsyncFile::create(&package_index_path).await.map_err(|e| {
-                CreatePackageIndexError::WritePackagesLayer(package_index_path.clone(), e)
+            let uncompressed_hash = download_package_index(
+                &client,
+                &package_index_url,
+                canonical_url.as_ref(),
+                checksum_algorithm,
+                &hash,
+                compression,
+                &package_index_path,
+            )
+            .await?;
+            package_index_layer.write_metadata(PackageIndexMetadata {
+                uncompressed_hash: Some(uncompressed_hash),
+                ..new_metadata
             })?;
 
-            async_copy(&mut reader, &mut writer).await.map_err(|e| {
-                CreatePackageIndexError::WritePackageIndexFromResponse(
-                    package_index_path.clone(),
-                    e,
-                )
-            })?;
-
-            writer.flush().await.map_err(|e| {
-                CreatePackageIndexError::WritePackageIndexFromResponse(
-                    package_index_path.clone(),
-                    e,
-                )
-            })?;
-
-            let calculated_hash = format!("{:x}", hasher.finalize());
-
-            if hash != calculated_hash {
-                Err(CreatePackageIndexError::ChecksumFailed {
-                    url: package_index_url.clone(),
-                    expected: hash,
-                    actual: calculated_hash,
-                })?;
-            }
-
             match cause {
                 EmptyLayerCause::NewlyCreated => UpdatedSourceCacheState::New,
                 EmptyLayerCause::InvalidMetadataAction { .. } => {
                     UpdatedSourceCacheState::Invalidated("Invalid metadata".to_string())
                 }
-                EmptyLayerCause::RestoredLayerAction { .. } => {
-                    UpdatedSourceCacheState::Invalidated(
-                        "Stored checksum did not match".to_string(),
-                    )
+                EmptyLayerCause::RestoredLayerAction { cause: Err(reason) } => {
+                    UpdatedSourceCacheState::Invalidated(reason.clone())
+                }
+                EmptyLayerCause::RestoredLayerAction { cause: Ok(_) } => {
+                    unreachable!("Package indexes are only deleted when they can't be reused")
                 }
             }
         }
@@ -500,21 +1283,236 @@ async fn get_package_list(
         repository_uri,
         package_index_path,
         package_index_url,
+        checksum_algorithm,
+        hash,
+        allow_weak_hashes,
+        download_url,
+        not_automatic: false,
+        suite: String::new(),
         cache_state,
     })
 }
 
+// Downloads the package index, verifies it against the hash from the Release file, and writes it
+// to the given path. Returns the hash of the uncompressed package index which is what diffs are
+// published against.
+async fn download_package_index(
+    client: &ClientWithMiddleware,
+    package_index_url: &str,
+    canonical_url: Option<&String>,
+    checksum_algorithm: ChecksumAlgorithm,
+    hash: &str,
+    compression: PackageIndexCompression,
+    package_index_path: &Path,
+) -> BuildpackResult<String> {
+    let response = match (client.get(package_index_url).send().await, canonical_url) {
+        // a mirror that's still syncing can be missing the by-hash file listed in the Release
+        // file so the canonical path is requested instead and verified with the same hash
+        (Ok(res), Some(canonical_url)) if res.status() == StatusCode::NOT_FOUND => {
+            client.get(canonical_url).send().await
+        }
+        (res, _) => res,
+    }
+    .and_then(|res| res.error_for_status().map_err(Reqwest))
+    .map_err(CreatePackageIndexError::GetPackagesRequest)?;
+
+    let mut hasher = checksum_algorithm.hasher();
+    let final_url = response.url().clone();
+
+    // the package list we request is usually compressed so we'll decode that directly from the
+    // response
+    let compressed_reader = AsyncBufReader::new(
+        // the inspect reader lets us pipe this decompressed output to both the ouptut file and the hash digest
+        InspectReader::new(
+            // and we need to convert the http stream into an async reader
+            FuturesAsyncReadCompatExt::compat(
+                response
+                    .bytes_stream()
+                    .map_err(|e| {
+                        std::io::Error::new(
+                            std::io::ErrorKind::Other,
+                            e.with_url(final_url.clone()),
+                        )
+                    })
+                    .into_async_read(),
+            ),
+            |bytes| hasher.update(bytes),
+        ),
+    );
+
+    let decompressed_reader: Pin<Box<dyn AsyncRead + Send + '_>> = match compression {
+        PackageIndexCompression::Gzip => {
+            let mut gzip_reader = GzipDecoder::new(compressed_reader);
+            // Enable support for multistream gz files. In this mode, the reader expects the input to
+            // be a sequence of individually gzipped data streams, each with its own header and trailer,
+            // ending at EOF. This is standard behavior for gzip readers.
+            gzip_reader.multiple_members(true);
+            Box::pin(gzip_reader)
+        }
+        PackageIndexCompression::Xz => {
+            let mut xz_reader = XzDecoder::new(compressed_reader);
+            xz_reader.multiple_members(true);
+            Box::pin(xz_reader)
+        }
+        PackageIndexCompression::Uncompressed => Box::pin(compressed_reader),
+    };
+
+    let mut uncompressed_hasher = Sha256::new();
+    let mut reader = InspectReader::new(decompressed_reader, |bytes| {
+        uncompressed_hasher.update(bytes);
+    });
+
+    // the decompressed package index is re-compressed with zstd before it's written to the
+    // cache since it's much smaller on disk and still fast to decompress when it's read
+    let mut writer = ZstdEncoder::new(AsyncBufWriter::new(
+        AsyncFile::create(package_index_path).await.map_err(|e| {
+            CreatePackageIndexError::WritePackagesLayer(package_index_path.to_path_buf(), e)
+        })?,
+    ));
+
+    async_copy(&mut reader, &mut writer).await.map_err(|e| {
+        CreatePackageIndexError::WritePackageIndexFromResponse(package_index_path.to_path_buf(), e)
+    })?;
+
+    // shutdown is required to write the final zstd frame and flush the underlying file
+    writer.shutdown().await.map_err(|e| {
+        CreatePackageIndexError::WritePackageIndexFromResponse(package_index_path.to_path_buf(), e)
+    })?;
+
+    // the reader holds onto the hashers until it's dropped
+    drop(reader);
+    let calculated_hash = hasher.finalize();
+
+    if hash != calculated_hash {
+        Err(CreatePackageIndexError::ChecksumFailed {
+            url: package_index_url.to_string(),
+            expected: hash.to_string(),
+            actual: calculated_hash,
+        })?;
+    }
+
+    Ok(format!("{:x}", uncompressed_hasher.finalize()))
+}
+
+// Updates an outdated package index in place by applying the diffs published since it was
+// downloaded. Returns the number of diffs applied and the hash of the updated package index. Any
+// error means the whole package index needs to be downloaded instead.
+async fn patch_package_index(
+    client: &ClientWithMiddleware,
+    pdiff_index_request: &PdiffIndexRequest,
+    outdated_hash: &str,
+    package_index_path: &Path,
+) -> Result<(usize, String), PatchPackageIndexError> {
+    let pdiff_index_contents =
+        get_verified_file(client, &pdiff_index_request.url, &pdiff_index_request.hash).await?;
+    let pdiff_index = PdiffIndex::parse(&String::from_utf8_lossy(&pdiff_index_contents))
+        .map_err(PatchPackageIndexError::Pdiff)?;
+    let patches = pdiff_index
+        .patches_for(outdated_hash)
+        .ok_or(PatchPackageIndexError::NotInHistory)?;
+
+    let mut contents = String::new();
+    ZstdDecoder::new(AsyncBufReader::new(
+        AsyncFile::open(package_index_path)
+            .await
+            .map_err(PatchPackageIndexError::Io)?,
+    ))
+    .read_to_string(&mut contents)
+    .await
+    .map_err(PatchPackageIndexError::Io)?;
+
+    for patch in &patches {
+        let patch_url = pdiff_index_request.patch_url(&patch.name);
+        let compressed_patch = get_file(client, &patch_url).await?;
+        let mut script = String::new();
+        GzipDecoder::new(compressed_patch.as_slice())
+            .read_to_string(&mut script)
+            .await
+            .map_err(PatchPackageIndexError::Io)?;
+        if format!("{:x}", Sha256::digest(&script)) != patch.hash {
+            return Err(PatchPackageIndexError::ChecksumFailed(patch_url));
+        }
+        contents = apply_ed_script(&contents, &script).map_err(PatchPackageIndexError::Pdiff)?;
+    }
+
+    let patched_hash = format!("{:x}", Sha256::digest(&contents));
+    if patched_hash != pdiff_index.current {
+        return Err(PatchPackageIndexError::ChecksumFailed(
+            package_index_path.to_string_lossy().to_string(),
+        ));
+    }
+
+    let mut writer = ZstdEncoder::new(AsyncBufWriter::new(
+        AsyncFile::create(package_index_path)
+            .await
+            .map_err(PatchPackageIndexError::Io)?,
+    ));
+    writer
+        .write_all(contents.as_bytes())
+        .await
+        .map_err(PatchPackageIndexError::Io)?;
+    writer
+        .shutdown()
+        .await
+        .map_err(PatchPackageIndexError::Io)?;
+
+    Ok((patches.len(), patched_hash))
+}
+
+async fn get_file(
+    client: &ClientWithMiddleware,
+    url: &str,
+) -> Result<Vec<u8>, PatchPackageIndexError> {
+    client
+        .get(url)
+        .send()
+        .await
+        .and_then(|res| res.error_for_status().map_err(Reqwest))
+        .map_err(PatchPackageIndexError::Request)?
+        .bytes()
+        .await
+        .map(|bytes| bytes.to_vec())
+        .map_err(|e| PatchPackageIndexError::Request(Reqwest(e)))
+}
+
+async fn get_verified_file(
+    client: &ClientWithMiddleware,
+    url: &str,
+    hash: &str,
+) -> Result<Vec<u8>, PatchPackageIndexError> {
+    let contents = get_file(client, url).await?;
+    if format!("{:x}", Sha256::digest(&contents)) == hash {
+        Ok(contents)
+    } else {
+        Err(PatchPackageIndexError::ChecksumFailed(url.to_string()))
+    }
+}
+
 async fn build_package_index(
     updated_sources: Vec<UpdatedPackageIndex>,
+    package_subset: Option<HashSet<String>>,
 ) -> BuildpackResult<PackageIndex> {
+    if let Some(requested_packages) = package_subset {
+        return build_package_index_subset(updated_sources, requested_packages).await;
+    }
+
     let mut get_packages_handles = JoinSet::new();
-    for update_source in updated_sources {
-        get_packages_handles.spawn(read_packages(update_source));
+    for (position, update_source) in updated_sources.into_iter().enumerate() {
+        get_packages_handles.spawn(async move { (position, read_packages(update_source).await) });
     }
 
-    let mut package_index = PackageIndex::default();
+    let mut read_package_indexes = vec![];
     while let Some(get_package_handle) = get_packages_handles.join_next().await {
-        let packages = get_package_handle.map_err(CreatePackageIndexError::TaskFailed)??;
+        let (position, packages) =
+            get_package_handle.map_err(CreatePackageIndexError::TaskFailed)?;
+        read_package_indexes.push((position, packages?));
+    }
+
+    // added in the order the package indexes were listed so the first of any duplicate entries
+    // is always the one that's kept
+    read_package_indexes.sort_by_key(|(position, _)| *position);
+    let mut package_index = PackageIndex::default();
+    for (_, packages) in read_package_indexes {
         for package in packages {
             package_index.add_package(package);
         }
@@ -523,18 +1521,248 @@ async fn build_package_index(
     Ok(package_index)
 }
 
+// Reuses the package index parsed by a previous build when it was built from the same package
+// indexes, otherwise the package indexes are parsed and the result is cached for the next build.
+async fn build_cached_package_index(
+    context: &Arc<BuildContext<DebianPackagesBuildpack>>,
+    updated_package_indexes: Vec<UpdatedPackageIndex>,
+) -> BuildpackResult<(PackageIndex, bool)> {
+    let new_metadata = PackageIndexCacheMetadata {
+        format_version: PACKAGE_INDEX_CACHE_FORMAT_VERSION,
+        package_indexes: updated_package_indexes
+            .iter()
+            .map(|updated_package_index| CachedPackageIndex {
+                url: updated_package_index.package_index_url.clone(),
+                hash: updated_package_index.hash.clone(),
+                allow_weak_hashes: updated_package_index.allow_weak_hashes,
+                download_url: updated_package_index.download_url.clone(),
+                not_automatic: updated_package_index.not_automatic,
+                suite: updated_package_index.suite.clone(),
+            })
+            .collect(),
+    };
+
+    let package_index_cache_layer = context.cached_layer(
+        layer_name!("parsed_package_index"),
+        CachedLayerDefinition {
+            build: false,
+            launch: false,
+            invalid_metadata_action: &|_| InvalidMetadataAction::DeleteLayer,
+            restored_layer_action: &|old_metadata: &PackageIndexCacheMetadata, _| {
+                if old_metadata == &new_metadata {
+                    RestoredLayerAction::KeepLayer
+                } else {
+                    RestoredLayerAction::DeleteLayer
+                }
+            },
+        },
+    )?;
+
+    if let LayerState::Restored { .. } = package_index_cache_layer.state {
+        if let Some(package_index) =
+            read_package_index_cache(&package_index_cache_layer.path()).await
+        {
+            return Ok((package_index, true));
+        }
+    }
+
+    let package_index = build_package_index(updated_package_indexes, None).await?;
+
+    package_index_cache_layer.write_metadata(new_metadata)?;
+    write_package_index_cache(&package_index_cache_layer.path(), &package_index)
+        .await
+        .map_err(|e| {
+            CreatePackageIndexError::WritePackagesLayer(
+                package_index_cache_path(&package_index_cache_layer.path()),
+                e,
+            )
+        })?;
+
+    Ok((package_index, false))
+}
+
+// Every package index is read before parsing since the dependencies of a package can be listed in
+// any of them but only the entries needed for the requested packages are parsed.
+async fn build_package_index_subset(
+    updated_sources: Vec<UpdatedPackageIndex>,
+    requested_packages: HashSet<String>,
+) -> BuildpackResult<PackageIndex> {
+    let mut read_package_index_handles = JoinSet::new();
+    for (position, updated_source) in updated_sources.into_iter().enumerate() {
+        read_package_index_handles.spawn(async move {
+            let package_index_contents =
+                read_package_index(&updated_source)
+                    .await
+                    .map(|contents| PackageIndexContents {
+                        repository_uri: updated_source.repository_uri,
+                        package_index_path: updated_source.package_index_path,
+                        allow_weak_hashes: updated_source.allow_weak_hashes,
+                        download_url: updated_source.download_url,
+                        not_automatic: updated_source.not_automatic,
+                        suite: updated_source.suite,
+                        contents,
+                    });
+            (position, package_index_contents)
+        });
+    }
+
+    let mut package_indexes = vec![];
+    while let Some(read_package_index_handle) = read_package_index_handles.join_next().await {
+        let (position, package_index_contents) =
+            read_package_index_handle.map_err(CreatePackageIndexError::TaskFailed)?;
+        package_indexes.push((position, package_index_contents?));
+    }
+    // kept in the order the package indexes were listed so the subset doesn't depend on the order
+    // they were read in
+    package_indexes.sort_by_key(|(position, _)| *position);
+    let package_indexes = package_indexes
+        .into_iter()
+        .map(|(_, package_index_contents)| package_index_contents)
+        .collect::<Vec<_>>();
+
+    let (send, recv) = channel();
+    rayon::spawn(move || {
+        let _ = send.send(parse_package_subset(&package_indexes, &requested_packages));
+    });
+    let packages = recv
+        .await
+        .map_err(CreatePackageIndexError::CpuTaskFailed)??;
+
+    let mut package_index = PackageIndex::default();
+    for package in packages {
+        package_index.add_package(package);
+    }
+    Ok(package_index)
+}
+
+struct PackageIndexContents {
+    repository_uri: RepositoryUri,
+    package_index_path: PathBuf,
+    allow_weak_hashes: bool,
+    download_url: Option<String>,
+    not_automatic: bool,
+    suite: String,
+    contents: String,
+}
+
+// Parses the entries for the requested packages and, in rounds, the entries for the dependencies
+// found in the previous round. Each round only reads a package index until every name being
+// looked for has been found in it (either as a package name or a provided virtual package) so
+// later entries with the same name, like other providers of a virtual package, are skipped.
+fn parse_package_subset(
+    package_indexes: &[PackageIndexContents],
+    requested_packages: &HashSet<String>,
+) -> Result<Vec<RepositoryPackage>, CreatePackageIndexError> {
+    let mut parsed_entries = vec![HashSet::new(); package_indexes.len()];
+    let mut wanted_names = requested_packages.clone();
+    let mut pending_names = requested_packages.clone();
+    let mut packages = vec![];
+
+    while !pending_names.is_empty() {
+        let results = package_indexes
+            .par_iter()
+            .zip(parsed_entries.par_iter_mut())
+            .map(|(package_index, parsed_entries)| {
+                parse_package_index_entries(package_index, &pending_names, parsed_entries)
+            })
+            .collect::<Vec<_>>();
+
+        pending_names = HashSet::new();
+        for result in results {
+            for package in result? {
+                for dependency in package.get_dependencies() {
+                    if wanted_names.insert(dependency.to_string()) {
+                        pending_names.insert(dependency.to_string());
+                    }
+                }
+                packages.push(package);
+            }
+        }
+    }
+
+    Ok(packages)
+}
+
+fn parse_package_index_entries(
+    package_index: &PackageIndexContents,
+    names: &HashSet<String>,
+    parsed_entries: &mut HashSet<usize>,
+) -> Result<Vec<RepositoryPackage>, CreatePackageIndexError> {
+    let mut remaining_names = names.iter().map(String::as_str).collect::<HashSet<_>>();
+    let mut packages = vec![];
+    let mut errors = vec![];
+
+    for (entry_index, entry) in package_index.contents.trim().split("\n\n").enumerate() {
+        if remaining_names.is_empty() {
+            break;
+        }
+
+        let (package_name, provides) = RepositoryPackage::parse_names(entry);
+        let found_names = package_name
+            .into_iter()
+            .chain(provides)
+            .filter(|name| names.contains(*name))
+            .collect::<Vec<_>>();
+        if found_names.is_empty() {
+            continue;
+        }
+        for name in found_names {
+            remaining_names.remove(name);
+        }
+
+        if parsed_entries.insert(entry_index) {
+            match RepositoryPackage::parse_parallel(
+                package_index.repository_uri.clone(),
+                entry,
+                package_index.allow_weak_hashes,
+            ) {
+                Ok(package) => packages.push(RepositoryPackage {
+                    download_url_template: package_index.download_url.clone(),
+                    not_automatic: package_index.not_automatic,
+                    suite: package_index.suite.clone(),
+                    ..package
+                }),
+                Err(e) => errors.push(e),
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(packages)
+    } else {
+        Err(CreatePackageIndexError::ParsePackages(
+            package_index.package_index_path.clone(),
+            errors,
+        ))
+    }
+}
+
+async fn read_package_index(updated_source: &UpdatedPackageIndex) -> BuildpackResult<String> {
+    let mut reader = ZstdDecoder::new(AsyncBufReader::new(
+        AsyncFile::open(&updated_source.package_index_path)
+            .await
+            .map_err(|e| {
+                CreatePackageIndexError::ReadPackagesFile(
+                    updated_source.package_index_path.clone(),
+                    e,
+                )
+            })?,
+    ));
+
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents).await.map_err(|e| {
+        CreatePackageIndexError::ReadPackagesFile(updated_source.package_index_path.clone(), e)
+    })?;
+
+    Ok(contents.replace("\r\n", "\n").replace('\0', ""))
+}
+
 // NOTE: Rayon is used here since this is a fairly CPU-intensive operation.
 //       See - https://ryhl.io/blog/async-what-is-blocking/
 async fn read_packages(
     updated_source: UpdatedPackageIndex,
 ) -> BuildpackResult<Vec<RepositoryPackage>> {
-    let contents = async_read_to_string(&updated_source.package_index_path)
-        .await
-        .map_err(|e| {
-            CreatePackageIndexError::ReadPackagesFile(updated_source.package_index_path.clone(), e)
-        })?
-        .replace("\r\n", "\n")
-        .replace('\0', "");
+    let contents = read_package_index(&updated_source).await?;
 
     let (send, recv) = channel();
     rayon::spawn(move || {
@@ -547,7 +1775,14 @@ async fn read_packages(
                 RepositoryPackage::parse_parallel(
                     updated_source.repository_uri.clone(),
                     package_data,
+                    updated_source.allow_weak_hashes,
                 )
+                .map(|package| RepositoryPackage {
+                    download_url_template: updated_source.download_url.clone(),
+                    not_automatic: updated_source.not_automatic,
+                    suite: updated_source.suite.clone(),
+                    ..package
+                })
                 .map_or_else(Either::Left, Either::Right)
             });
         let _ = send.send((packages, errors));
@@ -563,6 +1798,33 @@ async fn read_packages(
     }
 }
 
+// Failures while updating a package index with diffs are reported as the reason the package index
+// was downloaded again instead of failing the build.
+#[derive(Debug)]
+enum PatchPackageIndexError {
+    Request(reqwest_middleware::Error),
+    Io(std::io::Error),
+    ChecksumFailed(String),
+    NotInHistory,
+    Pdiff(PdiffError),
+}
+
+impl Display for PatchPackageIndexError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatchPackageIndexError::Request(e) => write!(f, "{e}"),
+            PatchPackageIndexError::Io(e) => write!(f, "{e}"),
+            PatchPackageIndexError::ChecksumFailed(url) => {
+                write!(f, "checksum did not match for {url}")
+            }
+            PatchPackageIndexError::NotInHistory => {
+                write!(f, "cached package index is older than the published diffs")
+            }
+            PatchPackageIndexError::Pdiff(e) => write!(f, "{e}"),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) enum CreatePackageIndexError {
     NoSources,
@@ -572,11 +1834,15 @@ pub(crate) enum CreatePackageIndexError {
     ReadGetReleaseResponse(reqwest::Error),
     CreatePgpCertificate(anyhow::Error),
     CreatePgpVerifier(anyhow::Error),
+    MissingReleaseSignature(String),
+    MissingSigningKey(String),
     WriteReleaseLayer(PathBuf, std::io::Error),
     ReadReleaseFile(PathBuf, std::io::Error),
     ParseReleaseFile(PathBuf, APTError),
+    ExpiredReleaseFile(String, String),
+    ReleaseFileNotValidYet(String, String),
     MissingSha256ReleaseHashes(RepositoryUri),
-    MissingPackageIndexReleaseHash(RepositoryUri, String),
+    MissingPackageIndexReleaseHash(RepositoryUri, Vec<String>),
     GetPackagesRequest(reqwest_middleware::Error),
     WritePackagesLayer(PathBuf, std::io::Error),
     WritePackageIndexFromResponse(PathBuf, std::io::Error),
@@ -603,21 +1869,168 @@ impl From<CreatePackageIndexError> for libcnb::Error<DebianPackagesBuildpackErro
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+// Package indexes are stored compressed in the cache layer so changing this will invalidate any
+// previously cached package indexes that were written with a different compression format.
+const PACKAGE_INDEX_COMPRESSION: &str = "zstd";
+
+// How far in the future the `Date` of a release file can be before it's refused (in seconds).
+const MAX_RELEASE_DATE_SKEW: u64 = 60 * 10;
+
+// Where to download a package index from and the hash it's expected to have from the Release file.
+// When the repository supports by-hash requests, the canonical path is kept as a fallback.
+#[derive(Debug, Eq, PartialEq)]
+struct PackageIndexRequest {
+    url: String,
+    canonical_url: Option<String>,
+    checksum_algorithm: ChecksumAlgorithm,
+    hash: String,
+    compression: PackageIndexCompression,
+    pdiff_index: Option<PdiffIndexRequest>,
+}
+
+impl PackageIndexRequest {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        repository: &dyn PackageRepository,
+        suite: &str,
+        component: &str,
+        arch: &ArchitectureName,
+        compression: PackageIndexCompression,
+        checksum_algorithm: ChecksumAlgorithm,
+        hash: &str,
+        acquire_by_hash: bool,
+    ) -> Self {
+        let suite_url = repository.suite_url(suite);
+        let canonical_url = format!(
+            "{suite_url}/{}",
+            repository.package_index_path(component, arch, compression.package_index_file_name())
+        );
+        if acquire_by_hash {
+            PackageIndexRequest {
+                url: format!(
+                    "{suite_url}/{}",
+                    repository.package_index_path(
+                        component,
+                        arch,
+                        &format!("by-hash/{}/{hash}", checksum_algorithm.release_field())
+                    )
+                ),
+                canonical_url: Some(canonical_url),
+                checksum_algorithm,
+                hash: hash.to_string(),
+                compression,
+                pdiff_index: None,
+            }
+        } else {
+            PackageIndexRequest {
+                url: canonical_url,
+                canonical_url: None,
+                checksum_algorithm,
+                hash: hash.to_string(),
+                compression,
+                pdiff_index: None,
+            }
+        }
+    }
+
+    // Used when the Release file lists a `Packages.diff/Index` for the package index.
+    fn with_pdiff_index(
+        self,
+        repository: &dyn PackageRepository,
+        suite: &str,
+        component: &str,
+        arch: &ArchitectureName,
+        pdiff_index_hash: &str,
+    ) -> Self {
+        PackageIndexRequest {
+            pdiff_index: Some(PdiffIndexRequest {
+                url: format!(
+                    "{}/{}",
+                    repository.suite_url(suite),
+                    repository.package_index_path(component, arch, PDIFF_INDEX_FILE_NAME)
+                ),
+                hash: pdiff_index_hash.to_string(),
+            }),
+            ..self
+        }
+    }
+}
+
+// The index of the diffs published for a package index and its hash from the Release file.
+#[derive(Debug, Eq, PartialEq)]
+struct PdiffIndexRequest {
+    url: String,
+    hash: String,
+}
+
+impl PdiffIndexRequest {
+    // diffs are published gzipped next to the index
+    fn patch_url(&self, name: &str) -> String {
+        format!(
+            "{}{name}.gz",
+            self.url
+                .trim_end_matches(PDIFF_INDEX_FILE_NAME.trim_start_matches("Packages.diff/"))
+        )
+    }
+}
+
+const PDIFF_INDEX_FILE_NAME: &str = "Packages.diff/Index";
+
+// The compression used by the package index published in the repository.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum PackageIndexCompression {
+    Gzip,
+    Xz,
+    Uncompressed,
+}
+
+impl PackageIndexCompression {
+    fn package_index_file_name(self) -> &'static str {
+        match self {
+            PackageIndexCompression::Gzip => "Packages.gz",
+            PackageIndexCompression::Xz => "Packages.xz",
+            PackageIndexCompression::Uncompressed => "Packages",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
 struct PackageIndexMetadata {
     hash: String,
-    timestamp: u64,  // Timestamp to track when the index was cached
+    compression: String,
+    timestamp: u64, // Timestamp to track when the index was cached
+    // the hash of the package index before it was compressed with zstd, which is what package
+    // index diffs are published against
+    #[serde(default)]
+    uncompressed_hash: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
 struct ReleaseFileMetadata {
+    certificate_fingerprint: Option<String>,
+    #[serde(default)]
+    signature_policy: SignaturePolicy,
+    signing_key_fingerprint: Option<String>,
+    unverified_reason: Option<String>,
     etag: Option<String>,
-    timestamp: u64,  // Timestamp to track when the release file was cached
+    #[serde(default)]
+    last_modified: Option<String>,
+    // the `Valid-Until` date from the release file as a Unix timestamp
+    valid_until: Option<u64>,
+    timestamp: u64, // Timestamp to track when the release file was cached
+}
+
+impl ReleaseFileMetadata {
+    fn is_expired(&self, now: u64) -> bool {
+        self.valid_until
+            .is_some_and(|valid_until| valid_until <= now)
+    }
 }
 
 #[derive(Debug)]
 struct UpdatedSource {
     release_file: UpdatedReleaseFile,
+    checksum_algorithm: ChecksumAlgorithm,
     package_indexes: Vec<UpdatedPackageIndex>,
 }
 
@@ -626,6 +2039,8 @@ enum UpdatedSourceCacheState {
     Cached,
     New,
     Invalidated(String),
+    // the number of diffs applied to the cached copy
+    Patched(usize),
 }
 
 #[derive(Debug)]
@@ -633,6 +2048,52 @@ struct UpdatedReleaseFile {
     release_file_url: String,
     release_file_path: PathBuf,
     cache_state: UpdatedSourceCacheState,
+    verification: ReleaseFileVerification,
+}
+
+#[derive(Debug)]
+struct ReleaseFileVerification {
+    certificate_user_id: Option<String>,
+    certificate_fingerprint: Option<String>,
+    signing_key_fingerprint: Option<String>,
+    signature_policy: SignaturePolicy,
+    // set when the signature policy allowed the release file to be used without verifying it
+    unverified_reason: Option<String>,
+}
+
+impl Display for ReleaseFileVerification {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (Some(certificate_fingerprint), None) =
+            (&self.certificate_fingerprint, &self.unverified_reason)
+        else {
+            return write!(
+                f,
+                "Not verified because {reason} (signature policy {signature_policy})",
+                reason = self
+                    .unverified_reason
+                    .as_deref()
+                    .unwrap_or("no signing key is configured"),
+                signature_policy = style::value(self.signature_policy.to_string())
+            );
+        };
+        let certificate = match &self.certificate_user_id {
+            Some(user_id) => format!("{user_id} [{certificate_fingerprint}]"),
+            None => certificate_fingerprint.clone(),
+        };
+        match &self.signing_key_fingerprint {
+            Some(signing_key_fingerprint) => write!(
+                f,
+                "Verified by certificate {certificate} using key {signing_key_fingerprint}",
+                certificate = style::value(certificate),
+                signing_key_fingerprint = style::value(signing_key_fingerprint)
+            ),
+            None => write!(
+                f,
+                "Verified by certificate {certificate}",
+                certificate = style::value(certificate)
+            ),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -640,9 +2101,75 @@ struct UpdatedPackageIndex {
     repository_uri: RepositoryUri,
     package_index_path: PathBuf,
     package_index_url: String,
+    checksum_algorithm: ChecksumAlgorithm,
+    hash: String,
+    allow_weak_hashes: bool,
+    download_url: Option<String>,
+    // set from the Release file after the package index is updated (see `is_not_automatic`)
+    not_automatic: bool,
+    // set along with `not_automatic` from the suite the Release file was downloaded for
+    suite: String,
     cache_state: UpdatedSourceCacheState,
 }
 
+// The inputs used to build the package index which are recorded so an installation can be
+// traced back to the exact release files, package indexes, and keys it was resolved from.
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
+pub(crate) struct IndexedSource {
+    pub(crate) release_file_url: String,
+    pub(crate) certificate_fingerprint: Option<String>,
+    pub(crate) signing_key_fingerprint: Option<String>,
+    #[serde(default)]
+    pub(crate) signature_policy: SignaturePolicy,
+    pub(crate) verified: bool,
+    pub(crate) package_indexes: Vec<IndexedPackageIndex>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
+pub(crate) struct IndexedPackageIndex {
+    pub(crate) url: String,
+    #[serde(alias = "sha256")]
+    pub(crate) checksum: String,
+    #[serde(default)]
+    pub(crate) checksum_algorithm: ChecksumAlgorithm,
+}
+
+impl From<&UpdatedSource> for IndexedSource {
+    fn from(updated_source: &UpdatedSource) -> Self {
+        let mut package_indexes = updated_source
+            .package_indexes
+            .iter()
+            .map(|package_index| IndexedPackageIndex {
+                url: package_index.package_index_url.clone(),
+                checksum: package_index.hash.clone(),
+                checksum_algorithm: package_index.checksum_algorithm,
+            })
+            .collect::<Vec<_>>();
+        package_indexes.sort_by(|a, b| a.url.cmp(&b.url));
+
+        IndexedSource {
+            release_file_url: updated_source.release_file.release_file_url.clone(),
+            certificate_fingerprint: updated_source
+                .release_file
+                .verification
+                .certificate_fingerprint
+                .clone(),
+            signing_key_fingerprint: updated_source
+                .release_file
+                .verification
+                .signing_key_fingerprint
+                .clone(),
+            signature_policy: updated_source.release_file.verification.signature_policy,
+            verified: updated_source
+                .release_file
+                .verification
+                .unverified_reason
+                .is_none(),
+            package_indexes,
+        }
+    }
+}
+
 impl Display for UpdatedSourceCacheState {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -651,6 +2178,576 @@ impl Display for UpdatedSourceCacheState {
             UpdatedSourceCacheState::Invalidated(reason) => {
                 write!(f, "updated {}", style::details(reason))
             }
+            UpdatedSourceCacheState::Patched(patches_applied) => {
+                write!(
+                    f,
+                    "patched {}",
+                    style::details(format!("{patches_applied} diffs"))
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use indoc::indoc;
+    use libcnb::data::buildpack::ComponentBuildpackDescriptor;
+    use sequoia_openpgp::cert::CertBuilder;
+    use sequoia_openpgp::serialize::stream::{Message, Signer};
+    use sequoia_openpgp::serialize::SerializeInto;
+
+    use crate::debian::{BuildpackMetadata, DistroCodename, DistsRepository};
+
+    use super::*;
+
+    #[test]
+    fn test_get_source_list_with_components() {
+        let metadata = toml::from_str::<ComponentBuildpackDescriptor<BuildpackMetadata>>(
+            include_str!("../buildpack.toml"),
+        )
+        .unwrap()
+        .metadata;
+        let signing_keys = metadata
+            .load_signing_keys(Path::new(env!("CARGO_MANIFEST_DIR")))
+            .unwrap();
+        let supported_distros = metadata.distros;
+        let distro = Distro {
+            name: "ubuntu".to_string(),
+            version: "24.04".to_string(),
+            codename: DistroCodename::from("noble"),
+            architecture: ArchitectureName::AMD_64,
+        };
+
+        let source_list = get_source_list(
+            &distro,
+            &supported_distros,
+            &signing_keys,
+            &[],
+            DistroSourceOptions::default(),
+        );
+        assert!(source_list
+            .iter()
+            .all(|source| source.components == vec!["main", "universe"]));
+
+        let components = vec![
+            "main".to_string(),
+            "universe".to_string(),
+            "multiverse".to_string(),
+            "restricted".to_string(),
+        ];
+        let source_list = get_source_list(
+            &distro,
+            &supported_distros,
+            &signing_keys,
+            &[],
+            DistroSourceOptions {
+                components: &components,
+                ..DistroSourceOptions::default()
+            },
+        );
+        assert!(!source_list.is_empty());
+        assert!(source_list
+            .iter()
+            .all(|source| source.components == components));
+    }
+
+    #[test]
+    fn test_get_source_list_with_optional_suites() {
+        let metadata = toml::from_str::<ComponentBuildpackDescriptor<BuildpackMetadata>>(
+            include_str!("../buildpack.toml"),
+        )
+        .unwrap()
+        .metadata;
+        let signing_keys = metadata
+            .load_signing_keys(Path::new(env!("CARGO_MANIFEST_DIR")))
+            .unwrap();
+        let supported_distros = metadata.distros;
+        let distro = |codename| Distro {
+            name: String::new(),
+            version: String::new(),
+            codename: DistroCodename::from(codename),
+            architecture: ArchitectureName::AMD_64,
+        };
+        let suites = |distro: &Distro, optional_suites: &[OptionalSuite]| {
+            get_source_list(
+                distro,
+                &supported_distros,
+                &signing_keys,
+                &[],
+                DistroSourceOptions {
+                    optional_suites,
+                    ..DistroSourceOptions::default()
+                },
+            )
+            .into_iter()
+            .map(|source| source.suites)
+            .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            suites(&distro("noble"), &[]),
+            vec![vec!["noble", "noble-updates"], vec!["noble-security"]]
+        );
+        assert_eq!(
+            suites(
+                &distro("noble"),
+                &[OptionalSuite::Backports, OptionalSuite::Proposed]
+            ),
+            vec![
+                vec![
+                    "noble",
+                    "noble-updates",
+                    "noble-backports",
+                    "noble-proposed"
+                ],
+                vec!["noble-security"]
+            ]
+        );
+        assert_eq!(
+            suites(&distro("bookworm"), &[OptionalSuite::Proposed]),
+            vec![
+                vec!["bookworm", "bookworm-updates", "bookworm-proposed-updates"],
+                vec!["bookworm-security"]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_package_index_request_by_hash() {
+        assert_eq!(
+            PackageIndexRequest::new(
+                &DistsRepository::new(RepositoryUri::from("http://archive.ubuntu.com/ubuntu")),
+                "noble",
+                "main",
+                &ArchitectureName::AMD_64,
+                PackageIndexCompression::Gzip,
+                ChecksumAlgorithm::Sha256,
+                "abc123",
+                true,
+            ),
+            PackageIndexRequest {
+                url: "http://archive.ubuntu.com/ubuntu/dists/noble/main/binary-amd64/by-hash/SHA256/abc123".to_string(),
+                canonical_url: Some(
+                    "http://archive.ubuntu.com/ubuntu/dists/noble/main/binary-amd64/Packages.gz".to_string()
+                ),
+                checksum_algorithm: ChecksumAlgorithm::Sha256,
+                hash: "abc123".to_string(),
+                compression: PackageIndexCompression::Gzip,
+                pdiff_index: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_package_index_request_without_by_hash() {
+        assert_eq!(
+            PackageIndexRequest::new(
+                &DistsRepository::new(RepositoryUri::from("http://deb.debian.org/debian")),
+                "bookworm-updates",
+                "main",
+                &ArchitectureName::ARM_64,
+                PackageIndexCompression::Xz,
+                ChecksumAlgorithm::Sha256,
+                "abc123",
+                false,
+            ),
+            PackageIndexRequest {
+                url: "http://deb.debian.org/debian/dists/bookworm-updates/main/binary-arm64/Packages.xz".to_string(),
+                canonical_url: None,
+                checksum_algorithm: ChecksumAlgorithm::Sha256,
+                hash: "abc123".to_string(),
+                compression: PackageIndexCompression::Xz,
+                pdiff_index: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_package_index_request_by_weak_hash() {
+        assert_eq!(
+            PackageIndexRequest::new(
+                &DistsRepository::new(RepositoryUri::from("https://repo.example.com/debian")),
+                "stable",
+                "main",
+                &ArchitectureName::AMD_64,
+                PackageIndexCompression::Gzip,
+                ChecksumAlgorithm::Md5,
+                "abc123",
+                true,
+            )
+            .url,
+            "https://repo.example.com/debian/dists/stable/main/binary-amd64/by-hash/MD5Sum/abc123"
+        );
+    }
+
+    #[test]
+    fn test_release_hashes() {
+        let release = Release::from(indoc! { "
+            Origin: Example
+            Architectures: amd64
+            Components: main
+            MD5Sum:
+             0123456789abcdef0123456789abcdef 1234 main/binary-amd64/Packages.gz
+            SHA1:
+             0123456789abcdef0123456789abcdef01234567 1234 main/binary-amd64/Packages.gz
+        " })
+        .unwrap();
+
+        assert!(release_hashes(&release, false).is_none());
+        let (checksum_algorithm, release_hashes) = release_hashes(&release, true).unwrap();
+        assert_eq!(checksum_algorithm, ChecksumAlgorithm::Sha1);
+        assert_eq!(
+            release_hashes[0].hash,
+            "0123456789abcdef0123456789abcdef01234567"
+        );
+    }
+
+    #[test]
+    fn test_find_package_index() {
+        let repository =
+            DistsRepository::new(RepositoryUri::from("https://repo.example.com/debian"));
+        let release = Release::from(indoc! { "
+            Origin: Example
+            Architectures: amd64 arm64
+            Components: main contrib
+            SHA256:
+             0000000000000000000000000000000000000000000000000000000000000001 1234 main/binary-amd64/Packages
+             0000000000000000000000000000000000000000000000000000000000000002 567 main/binary-amd64/Packages.gz
+             0000000000000000000000000000000000000000000000000000000000000003 1234 contrib/binary-amd64/Packages
+             0000000000000000000000000000000000000000000000000000000000000004 234 main/binary-arm64/Packages.xz
+             0000000000000000000000000000000000000000000000000000000000000005 1234 main/binary-arm64/Packages
+        " })
+        .unwrap();
+        let (_, release_hashes) = release_hashes(&release, false).unwrap();
+
+        let find = |component: &str, arch: &ArchitectureName| {
+            find_package_index(&repository, component, arch, release_hashes)
+                .map(|(compression, release_hash)| (compression, release_hash.hash.as_str()))
+        };
+        assert_eq!(
+            find("main", &ArchitectureName::AMD_64),
+            Some((
+                PackageIndexCompression::Gzip,
+                "0000000000000000000000000000000000000000000000000000000000000002"
+            ))
+        );
+        assert_eq!(
+            find("main", &ArchitectureName::ARM_64),
+            Some((
+                PackageIndexCompression::Xz,
+                "0000000000000000000000000000000000000000000000000000000000000004"
+            ))
+        );
+        assert_eq!(
+            find("contrib", &ArchitectureName::AMD_64),
+            Some((
+                PackageIndexCompression::Uncompressed,
+                "0000000000000000000000000000000000000000000000000000000000000003"
+            ))
+        );
+        assert_eq!(find("contrib", &ArchitectureName::ARM_64), None);
+    }
+
+    #[test]
+    fn test_package_index_request_with_pdiff_index() {
+        let repository =
+            DistsRepository::new(RepositoryUri::from("http://archive.ubuntu.com/ubuntu"));
+        let package_index_request = PackageIndexRequest::new(
+            &repository,
+            "noble",
+            "main",
+            &ArchitectureName::AMD_64,
+            PackageIndexCompression::Gzip,
+            ChecksumAlgorithm::Sha256,
+            "abc123",
+            false,
+        )
+        .with_pdiff_index(
+            &repository,
+            "noble",
+            "main",
+            &ArchitectureName::AMD_64,
+            "def456",
+        );
+        let pdiff_index = package_index_request.pdiff_index.unwrap();
+        assert_eq!(
+            pdiff_index,
+            PdiffIndexRequest {
+                url: "http://archive.ubuntu.com/ubuntu/dists/noble/main/binary-amd64/Packages.diff/Index".to_string(),
+                hash: "def456".to_string(),
+            }
+        );
+        assert_eq!(
+            pdiff_index.patch_url("2024-10-01-0200.00"),
+            "http://archive.ubuntu.com/ubuntu/dists/noble/main/binary-amd64/Packages.diff/2024-10-01-0200.00.gz"
+        );
+    }
+
+    #[test]
+    fn test_parse_package_subset() {
+        let package_index = |path: &str, entries: &[&str]| PackageIndexContents {
+            repository_uri: RepositoryUri::from("http://archive.ubuntu.com/ubuntu"),
+            package_index_path: PathBuf::from(path),
+            allow_weak_hashes: false,
+            download_url: None,
+            not_automatic: false,
+            suite: String::new(),
+            contents: entries
+                .iter()
+                .map(|entry| format!("{entry}\nFilename: pool/{path}.deb\nSHA256: abc123"))
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+        };
+        let package_indexes = [
+            package_index(
+                "noble/main",
+                &[
+                    "Package: libfoo\nVersion: 1.0",
+                    "Package: app\nVersion: 1.0\nDepends: libfoo (>= 1.0), awk | mawk",
+                    "Package: unrelated\nVersion: 1.0",
+                    "Package: mawk\nVersion: 1.0\nProvides: awk",
+                    "Package: gawk\nVersion: 1.0\nProvides: awk",
+                ],
+            ),
+            package_index(
+                "noble-updates/main",
+                &[
+                    "Package: libfoo\nVersion: 1.1",
+                    "Package: gawk\nVersion: 1.1",
+                ],
+            ),
+        ];
+
+        let mut packages =
+            parse_package_subset(&package_indexes, &HashSet::from(["app".to_string()]))
+                .unwrap()
+                .into_iter()
+                .map(|package| format!("{}={}", package.name, package.version))
+                .collect::<Vec<_>>();
+        packages.sort();
+
+        assert_eq!(
+            packages,
+            vec!["app=1.0", "libfoo=1.0", "libfoo=1.1", "mawk=1.0"]
+        );
+    }
+
+    #[test]
+    fn test_release_file_verification_display() {
+        let verified = ReleaseFileVerification {
+            certificate_user_id: None,
+            certificate_fingerprint: Some("ABC123".to_string()),
+            signing_key_fingerprint: None,
+            signature_policy: SignaturePolicy::Required,
+            unverified_reason: None,
+        };
+        assert!(verified.to_string().starts_with("Verified by certificate"));
+
+        let unverified = ReleaseFileVerification {
+            signature_policy: SignaturePolicy::Optional,
+            unverified_reason: Some("it isn't signed".to_string()),
+            ..verified
+        };
+        assert!(unverified
+            .to_string()
+            .starts_with("Not verified because it isn't signed"));
+    }
+
+    #[test]
+    fn test_get_cert_helper() {
+        let (cert, _) = CertBuilder::general_purpose(None, Some("Example <repo@example.com>"))
+            .generate()
+            .unwrap();
+        let signed_by = String::from_utf8(cert.armored().to_vec().unwrap()).unwrap();
+        let suite_url = "https://repo.example.com/debian/dists/stable";
+
+        assert!(get_cert_helper(
+            Some(signed_by.clone()),
+            SignaturePolicy::Required,
+            suite_url
+        )
+        .unwrap()
+        .is_some());
+        assert!(
+            get_cert_helper(Some(signed_by), SignaturePolicy::Disabled, suite_url)
+                .unwrap()
+                .is_none()
+        );
+        assert!(get_cert_helper(None, SignaturePolicy::Optional, suite_url)
+            .unwrap()
+            .is_none());
+        match get_cert_helper(None, SignaturePolicy::Required, suite_url) {
+            Err(CreatePackageIndexError::MissingSigningKey(url)) => assert_eq!(url, suite_url),
+            _ => panic!("expected a missing signing key error"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_detached_release_file() {
+        let (cert, _) = CertBuilder::general_purpose(None, Some("Example <repo@example.com>"))
+            .generate()
+            .unwrap();
+        let signing_keypair = cert
+            .keys()
+            .unencrypted_secret()
+            .with_policy(&StandardPolicy::new(), None)
+            .for_signing()
+            .next()
+            .unwrap()
+            .key()
+            .clone()
+            .into_keypair()
+            .unwrap();
+
+        let release_file = "Origin: Example\nSuite: stable\n";
+        let mut detached_signature = vec![];
+        let mut signer = Signer::new(Message::new(&mut detached_signature), signing_keypair)
+            .detached()
+            .build()
+            .unwrap();
+        signer.write_all(release_file.as_bytes()).unwrap();
+        signer.finalize().unwrap();
+
+        let release_file_dir = tempfile::tempdir().unwrap();
+        let release_file_path = release_file_dir.path().join("release");
+
+        let signing_key_fingerprint = verify_detached_release_file(
+            release_file,
+            &detached_signature,
+            CertHelper::new(cert.clone()),
+            &release_file_path,
+        )
+        .await
+        .unwrap();
+        assert!(signing_key_fingerprint.is_some());
+        assert_eq!(
+            std::fs::read_to_string(&release_file_path).unwrap(),
+            release_file
+        );
+
+        assert!(matches!(
+            verify_detached_release_file(
+                "Origin: Tampered\nSuite: stable\n",
+                &detached_signature,
+                CertHelper::new(cert),
+                &release_file_path,
+            )
+            .await,
+            Err(CreatePackageIndexError::CreatePgpVerifier(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_release_date() {
+        assert_eq!(
+            parse_release_date("Thu, 25 Apr 2024 15:10:33 UTC"),
+            Some(1_714_057_833)
+        );
+        assert_eq!(
+            parse_release_date("Thu, 25 Apr 2024 17:10:33 +0200"),
+            Some(1_714_057_833)
+        );
+        assert_eq!(parse_release_date("yesterday"), None);
+    }
+
+    #[test]
+    fn test_is_not_automatic() {
+        let release = |fields: &str| {
+            let release = indoc! { "
+                Origin: Ubuntu
+                Suite: noble-backports
+                Architectures: amd64
+                Components: main
+                SHA256:
+                 0123456789abcdef 1234 main/binary-amd64/Packages.xz
+            " };
+            Release::from(&format!("{fields}{release}")).unwrap()
+        };
+        assert!(!is_not_automatic(&release("")));
+        assert!(is_not_automatic(&release("NotAutomatic: yes\n")));
+        assert!(is_not_automatic(&release(
+            "NotAutomatic: yes\nButAutomaticUpgrades: yes\n"
+        )));
+        assert!(!is_not_automatic(&release("NotAutomatic: no\n")));
+    }
+
+    #[test]
+    fn test_find_release_field() {
+        let release = indoc! { "
+            -----BEGIN PGP SIGNED MESSAGE-----
+            Hash: SHA512
+
+            Origin: Debian
+            Date: Sat, 12 Oct 2024 08:29:10 UTC
+            Valid-Until: Sat, 19 Oct 2024 08:29:10 UTC
+            SHA256:
+             0123456789abcdef 1234 main/binary-amd64/Packages.xz
+        " };
+        assert_eq!(
+            find_release_field(release, "Valid-Until"),
+            Some("Sat, 19 Oct 2024 08:29:10 UTC")
+        );
+        assert_eq!(find_release_field(release, "Codename"), None);
+    }
+
+    #[test]
+    fn test_check_release_dates() {
+        let release = Release::from(indoc! { "
+            Origin: Debian
+            Architectures: amd64
+            Components: main
+            Date: Sat, 12 Oct 2024 08:29:10 UTC
+            Valid-Until: Sat, 19 Oct 2024 08:29:10 UTC
+        " })
+        .unwrap();
+        let url = "http://deb.debian.org/debian-security/dists/bookworm-security/InRelease";
+        let date = parse_release_date("Sat, 12 Oct 2024 08:29:10 UTC").unwrap();
+        let valid_until = parse_release_date("Sat, 19 Oct 2024 08:29:10 UTC").unwrap();
+
+        assert!(check_release_dates(url, &release, date + 60).is_ok());
+        assert!(matches!(
+            check_release_dates(url, &release, valid_until),
+            Err(CreatePackageIndexError::ExpiredReleaseFile(..))
+        ));
+        assert!(matches!(
+            check_release_dates(url, &release, date - MAX_RELEASE_DATE_SKEW - 1),
+            Err(CreatePackageIndexError::ReleaseFileNotValidYet(..))
+        ));
+    }
+
+    #[test]
+    fn test_release_file_metadata_is_expired() {
+        let metadata = ReleaseFileMetadata {
+            certificate_fingerprint: None,
+            signature_policy: SignaturePolicy::Optional,
+            signing_key_fingerprint: None,
+            unverified_reason: None,
+            etag: None,
+            last_modified: None,
+            valid_until: Some(1_000),
+            timestamp: 0,
+        };
+        assert!(!metadata.is_expired(999));
+        assert!(metadata.is_expired(1_000));
+        assert!(!ReleaseFileMetadata {
+            valid_until: None,
+            ..metadata
         }
+        .is_expired(u64::MAX));
+    }
+
+    #[test]
+    fn test_indexed_package_index_reads_sha256_field() {
+        // snapshots recorded before the field was renamed
+        let package_index = toml::from_str::<IndexedPackageIndex>(indoc! {r#"
+            url = "http://archive.ubuntu.com/ubuntu/dists/noble/main/binary-amd64/Packages.gz"
+            sha256 = "abc123"
+        "#})
+        .unwrap();
+        assert_eq!(package_index.checksum, "abc123");
+        assert_eq!(package_index.checksum_algorithm, ChecksumAlgorithm::Sha256);
     }
 }
@@ -3,12 +3,39 @@ use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, Eq)]
+// Serialized with the Debian name (e.g.; `amd64`). Older layer metadata stored these as the enum
+// variants (e.g.; `AMD_64`) so those are still accepted when deserialized.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, Eq, Hash)]
+#[serde(try_from = "String", into = "String")]
 #[allow(non_camel_case_types)]
 // https://wiki.debian.org/Multiarch/Tuples
 pub(crate) enum ArchitectureName {
     AMD_64,
     ARM_64,
+    ARM_HF,
+    PPC_64_EL,
+    S390_X,
+    RISCV_64,
+    I_386,
+}
+
+impl ArchitectureName {
+    // Targets use the Go/OCI names for architectures which differ from the Debian names for 32-bit
+    // ARM (`arm` with the `v7` variant) and little-endian POWER (`ppc64le`).
+    pub(crate) fn from_target(
+        arch: &str,
+        arch_variant: Option<&str>,
+    ) -> Result<Self, UnsupportedArchitectureNameError> {
+        match (arch, arch_variant) {
+            ("arm", None | Some("v7")) => Ok(ArchitectureName::ARM_HF),
+            ("ppc64le", _) => Ok(ArchitectureName::PPC_64_EL),
+            ("arm" | "armhf" | "ppc64el", _) => Err(UnsupportedArchitectureNameError(
+                arch_variant
+                    .map_or_else(|| arch.to_string(), |variant| format!("{arch}/{variant}")),
+            )),
+            _ => ArchitectureName::from_str(arch),
+        }
+    }
 }
 
 impl FromStr for ArchitectureName {
@@ -18,6 +45,11 @@ impl FromStr for ArchitectureName {
         match value {
             "amd64" => Ok(ArchitectureName::AMD_64),
             "arm64" => Ok(ArchitectureName::ARM_64),
+            "armhf" => Ok(ArchitectureName::ARM_HF),
+            "ppc64el" => Ok(ArchitectureName::PPC_64_EL),
+            "s390x" => Ok(ArchitectureName::S390_X),
+            "riscv64" => Ok(ArchitectureName::RISCV_64),
+            "i386" => Ok(ArchitectureName::I_386),
             _ => Err(UnsupportedArchitectureNameError(value.to_string())),
         }
     }
@@ -28,19 +60,47 @@ impl Display for ArchitectureName {
         match self {
             ArchitectureName::AMD_64 => write!(f, "amd64"),
             ArchitectureName::ARM_64 => write!(f, "arm64"),
+            ArchitectureName::ARM_HF => write!(f, "armhf"),
+            ArchitectureName::PPC_64_EL => write!(f, "ppc64el"),
+            ArchitectureName::S390_X => write!(f, "s390x"),
+            ArchitectureName::RISCV_64 => write!(f, "riscv64"),
+            ArchitectureName::I_386 => write!(f, "i386"),
+        }
+    }
+}
+
+impl TryFrom<String> for ArchitectureName {
+    type Error = UnsupportedArchitectureNameError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "AMD_64" => Ok(ArchitectureName::AMD_64),
+            "ARM_64" => Ok(ArchitectureName::ARM_64),
+            "ARM_HF" => Ok(ArchitectureName::ARM_HF),
+            "PPC_64_EL" => Ok(ArchitectureName::PPC_64_EL),
+            "S390_X" => Ok(ArchitectureName::S390_X),
+            "RISCV_64" => Ok(ArchitectureName::RISCV_64),
+            "I_386" => Ok(ArchitectureName::I_386),
+            _ => ArchitectureName::from_str(&value),
         }
     }
 }
 
+impl From<ArchitectureName> for String {
+    fn from(value: ArchitectureName) -> Self {
+        value.to_string()
+    }
+}
+
 #[derive(Debug)]
-// Due to how this error rolls into the broader `Distro::try_from(Target)` implementation, the
-// architecture name stored in this struct isn't used directly which triggers a `dead_code`
-// warning. I could eliminate this by not capturing the architecture name that failed to parse
-// but that doesn't seem right. I'd rather keep this information attached to this struct even
-// if it's not used at this point in time.
-#[allow(dead_code)]
 pub(crate) struct UnsupportedArchitectureNameError(String);
 
+impl Display for UnsupportedArchitectureNameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}` is not a supported architecture", self.0)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -51,6 +111,42 @@ mod tests {
             ArchitectureName::AMD_64,
             ArchitectureName::from_str("amd64").unwrap()
         );
+        assert_eq!(
+            ArchitectureName::PPC_64_EL,
+            ArchitectureName::from_str("ppc64el").unwrap()
+        );
+        assert_eq!(
+            ArchitectureName::I_386,
+            ArchitectureName::from_str("i386").unwrap()
+        );
+    }
+
+    #[test]
+    fn architecture_name_from_target() {
+        for (arch, arch_variant, expected) in [
+            ("amd64", None, ArchitectureName::AMD_64),
+            ("arm64", Some("v8"), ArchitectureName::ARM_64),
+            ("arm", None, ArchitectureName::ARM_HF),
+            ("arm", Some("v7"), ArchitectureName::ARM_HF),
+            ("ppc64le", None, ArchitectureName::PPC_64_EL),
+            ("s390x", None, ArchitectureName::S390_X),
+            ("riscv64", None, ArchitectureName::RISCV_64),
+        ] {
+            assert_eq!(
+                ArchitectureName::from_target(arch, arch_variant).unwrap(),
+                expected
+            );
+        }
+        for (arch, arch_variant, unsupported) in [
+            ("arm", Some("v6"), "arm/v6"),
+            ("armhf", None, "armhf"),
+            ("ppc64el", None, "ppc64el"),
+            ("386", None, "386"),
+        ] {
+            match ArchitectureName::from_target(arch, arch_variant).unwrap_err() {
+                UnsupportedArchitectureNameError(value) => assert_eq!(value, unsupported),
+            }
+        }
     }
 
     #[test]
@@ -64,5 +160,26 @@ mod tests {
     fn display_architecture_name() {
         assert_eq!(ArchitectureName::AMD_64.to_string(), "amd64");
         assert_eq!(ArchitectureName::ARM_64.to_string(), "arm64");
+        assert_eq!(ArchitectureName::ARM_HF.to_string(), "armhf");
+        assert_eq!(ArchitectureName::PPC_64_EL.to_string(), "ppc64el");
+        assert_eq!(ArchitectureName::S390_X.to_string(), "s390x");
+        assert_eq!(ArchitectureName::RISCV_64.to_string(), "riscv64");
+    }
+
+    #[test]
+    fn serialize_architecture_name() {
+        assert_eq!(
+            serde_json::to_string(&ArchitectureName::AMD_64).unwrap(),
+            "\"amd64\""
+        );
+        assert_eq!(
+            serde_json::from_str::<ArchitectureName>("\"amd64\"").unwrap(),
+            ArchitectureName::AMD_64
+        );
+        assert_eq!(
+            serde_json::from_str::<ArchitectureName>("\"AMD_64\"").unwrap(),
+            ArchitectureName::AMD_64
+        );
+        assert!(serde_json::from_str::<ArchitectureName>("\"???\"").is_err());
     }
 }
@@ -0,0 +1,145 @@
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha1collisiondetection::Sha1CD;
+use sha2::{Digest, Sha256};
+
+// The hash used to verify package indexes and packages from a source. Repositories are required to
+// publish SHA256 hashes but some legacy repositories only publish SHA1 or MD5 hashes which are only
+// accepted when the source allows weak hashes.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ChecksumAlgorithm {
+    #[default]
+    Sha256,
+    Sha1,
+    Md5,
+}
+
+impl ChecksumAlgorithm {
+    // In order of preference when weak hashes are allowed.
+    pub(crate) const VALUES: [ChecksumAlgorithm; 3] = [
+        ChecksumAlgorithm::Sha256,
+        ChecksumAlgorithm::Sha1,
+        ChecksumAlgorithm::Md5,
+    ];
+
+    // The algorithms that can be used, in order of preference.
+    pub(crate) fn allowed(allow_weak_hashes: bool) -> impl Iterator<Item = ChecksumAlgorithm> {
+        ChecksumAlgorithm::VALUES
+            .into_iter()
+            .filter(move |algorithm| allow_weak_hashes || !algorithm.is_weak())
+    }
+
+    pub(crate) fn is_weak(self) -> bool {
+        self != ChecksumAlgorithm::Sha256
+    }
+
+    // The name of the section in a Release file, which is also the directory used for by-hash
+    // requests.
+    pub(crate) fn release_field(self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha256 => "SHA256",
+            ChecksumAlgorithm::Sha1 => "SHA1",
+            ChecksumAlgorithm::Md5 => "MD5Sum",
+        }
+    }
+
+    // The name of the field in an entry of a package index.
+    pub(crate) fn package_field(self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha256 => "SHA256",
+            ChecksumAlgorithm::Sha1 => "SHA1",
+            ChecksumAlgorithm::Md5 => "MD5sum",
+        }
+    }
+
+    pub(crate) fn hasher(self) -> Hasher {
+        match self {
+            ChecksumAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            ChecksumAlgorithm::Sha1 => Hasher::Sha1(Box::default()),
+            ChecksumAlgorithm::Md5 => Hasher::Md5(Md5::new()),
+        }
+    }
+}
+
+pub(crate) enum Hasher {
+    Sha256(Sha256),
+    // the collision detection state is much larger than the other hashers
+    Sha1(Box<Sha1CD>),
+    Md5(Md5),
+}
+
+impl Hasher {
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Hasher::Sha256(hasher) => Digest::update(hasher, bytes),
+            Hasher::Sha1(hasher) => Digest::update(hasher.as_mut(), bytes),
+            Hasher::Md5(hasher) => Digest::update(hasher, bytes),
+        }
+    }
+
+    // Returns the hash as lowercase hex, the format used by Release files and package indexes.
+    pub(crate) fn finalize(self) -> String {
+        match self {
+            Hasher::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            Hasher::Sha1(hasher) => format!("{:x}", (*hasher).finalize()),
+            Hasher::Md5(hasher) => format!("{:x}", hasher.finalize()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(algorithm: ChecksumAlgorithm, data: &str) -> String {
+        let mut hasher = algorithm.hasher();
+        hasher.update(data.as_bytes());
+        hasher.finalize()
+    }
+
+    #[test]
+    fn test_digest() {
+        assert_eq!(
+            digest(ChecksumAlgorithm::Sha256, "hello"),
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(
+            digest(ChecksumAlgorithm::Sha1, "hello"),
+            "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d"
+        );
+        assert_eq!(
+            digest(ChecksumAlgorithm::Md5, "hello"),
+            "5d41402abc4b2a76b9719d911017c592"
+        );
+    }
+
+    #[test]
+    fn test_hasher() {
+        for algorithm in ChecksumAlgorithm::VALUES {
+            let mut hasher = algorithm.hasher();
+            hasher.update(b"hel");
+            hasher.update(b"lo");
+            assert_eq!(hasher.finalize(), digest(algorithm, "hello"));
+        }
+    }
+
+    #[test]
+    fn test_allowed() {
+        assert_eq!(
+            ChecksumAlgorithm::allowed(false).collect::<Vec<_>>(),
+            vec![ChecksumAlgorithm::Sha256]
+        );
+        assert_eq!(
+            ChecksumAlgorithm::allowed(true).collect::<Vec<_>>(),
+            ChecksumAlgorithm::VALUES.to_vec()
+        );
+    }
+
+    #[test]
+    fn test_is_weak() {
+        assert!(!ChecksumAlgorithm::Sha256.is_weak());
+        assert!(ChecksumAlgorithm::Sha1.is_weak());
+        assert!(ChecksumAlgorithm::Md5.is_weak());
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+// The license information from a machine-readable copyright file installed by a package at
+// `usr/share/doc/<package>/copyright`. Copyright files that don't use the machine-readable format
+// are free-form text so no license information can be extracted from them.
+//
+// See: https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub(crate) struct Copyright {
+    // The license short names (e.g.; `GPL-2+ or Artistic`) that apply to the files in the package.
+    pub(crate) licenses: Vec<String>,
+    // The full text of any stand-alone license paragraphs keyed by their short name.
+    pub(crate) license_texts: HashMap<String, String>,
+}
+
+impl Copyright {
+    pub(crate) fn read(path: &Path) -> Option<Self> {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| Copyright::parse(&contents))
+    }
+
+    pub(crate) fn parse(contents: &str) -> Option<Self> {
+        let mut paragraphs = parse_paragraphs(contents).into_iter();
+
+        let header = paragraphs.next()?;
+        if !header.contains_key("format") {
+            return None;
+        }
+
+        let mut copyright = Copyright::default();
+        if let Some(license) = header.get("license") {
+            copyright.add_license(license);
+        }
+
+        for paragraph in paragraphs {
+            let Some(license) = paragraph.get("license") else {
+                continue;
+            };
+            if paragraph.contains_key("files") {
+                copyright.add_license(license);
+            } else if let Some((name, text)) = license.split_once('\n') {
+                copyright
+                    .license_texts
+                    .entry(name.trim().to_string())
+                    .or_insert_with(|| text.to_string());
+            }
+        }
+
+        Some(copyright)
+    }
+
+    // The first line of a license field is its short name, any other lines are the license text.
+    fn add_license(&mut self, license: &str) {
+        let (name, text) = license.split_once('\n').unwrap_or((license, ""));
+        let name = name.trim();
+        if name.is_empty() {
+            return;
+        }
+        if !self.licenses.iter().any(|license| license == name) {
+            self.licenses.push(name.to_string());
+        }
+        if !text.is_empty() {
+            self.license_texts
+                .entry(name.to_string())
+                .or_insert_with(|| text.to_string());
+        }
+    }
+}
+
+// Paragraphs are separated by blank lines and field names are case-insensitive. Continuation lines
+// start with whitespace and a line containing only `.` stands for an empty line.
+fn parse_paragraphs(contents: &str) -> Vec<HashMap<String, String>> {
+    let mut paragraphs = vec![];
+    let mut paragraph: HashMap<String, String> = HashMap::new();
+    let mut current_field: Option<String> = None;
+
+    for line in contents.lines() {
+        if line.trim().is_empty() {
+            if !paragraph.is_empty() {
+                paragraphs.push(std::mem::take(&mut paragraph));
+            }
+            current_field = None;
+        } else if line.starts_with([' ', '\t']) {
+            if let Some(value) = current_field
+                .as_ref()
+                .and_then(|field| paragraph.get_mut(field))
+            {
+                let line = line.trim();
+                value.push('\n');
+                if line != "." {
+                    value.push_str(line);
+                }
+            }
+        } else if let Some((field, value)) = line.split_once(':').filter(|_| !line.starts_with('#'))
+        {
+            let field = field.trim().to_lowercase();
+            paragraph.insert(field.clone(), value.trim().to_string());
+            current_field = Some(field);
+        }
+    }
+
+    if !paragraph.is_empty() {
+        paragraphs.push(paragraph);
+    }
+
+    paragraphs
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_machine_readable_copyright() {
+        let copyright = Copyright::parse(indoc! { "
+            Format: https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/
+            Upstream-Name: curl
+            Source: https://curl.se/
+
+            Files: *
+            Copyright: 1996-2024, Daniel Stenberg <daniel@haxx.se>
+            License: curl
+
+            Files: lib/krb5.c
+            Copyright: 1995-2002, Kungliga Tekniska Högskolan
+            License: BSD-3-Clause
+
+            Files: debian/*
+            Copyright: 2006-2024, Debian curl maintainers
+            license: curl
+
+            License: curl
+             All rights reserved.
+             .
+             Permission to use, copy, modify, and distribute this software for any purpose
+             with or without fee is hereby granted.
+        " })
+        .unwrap();
+
+        assert_eq!(copyright.licenses, vec!["curl", "BSD-3-Clause"]);
+        assert_eq!(
+            copyright.license_texts,
+            HashMap::from([(
+                "curl".to_string(),
+                "All rights reserved.\n\nPermission to use, copy, modify, and distribute this software for any purpose\nwith or without fee is hereby granted.".to_string()
+            )])
+        );
+    }
+
+    #[test]
+    fn test_parse_header_license() {
+        let copyright = Copyright::parse(indoc! { "
+            Format: https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/
+            License: GPL-2+ or Artistic
+
+            Files: *
+            Copyright: 2020, Someone
+            License: GPL-2+ or Artistic
+        " })
+        .unwrap();
+
+        assert_eq!(copyright.licenses, vec!["GPL-2+ or Artistic"]);
+        assert!(copyright.license_texts.is_empty());
+    }
+
+    #[test]
+    fn test_parse_free_form_copyright() {
+        assert_eq!(
+            Copyright::parse(indoc! { "
+                This package was debianized by Someone on Mon, 1 Jan 2001.
+
+                Copyright: 2001 Someone
+                License: GPL-2
+            " }),
+            None
+        );
+    }
+}
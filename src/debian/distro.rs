@@ -1,12 +1,17 @@
+use std::path::Path;
 use std::str::FromStr;
 
+use libcnb::data::buildpack::BuildpackTarget;
 use libcnb::Target;
-use serde::{Deserialize, Serialize};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize};
 
-use crate::debian::ArchitectureName::{AMD_64, ARM_64};
-use crate::debian::{ArchitectureName, DistroCodename, Source};
+use crate::debian::{ArchitectureName, DistroCodename, OsRelease, RepositoryUri, Source};
+use crate::pgp::{LoadSigningKeyError, SigningKeys};
 use crate::DebianPackagesBuildpackError;
 
+const OS_RELEASE_PATH: &str = "/etc/os-release";
+
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub(crate) struct Distro {
     pub(crate) name: String,
@@ -16,106 +21,353 @@ pub(crate) struct Distro {
 }
 
 impl Distro {
-    pub(crate) fn get_source_list(&self) -> Vec<Source> {
-        let source_list = match self.codename {
-            DistroCodename::Jammy => get_jammy_source_list(),
-            DistroCodename::Noble => get_noble_source_list(),
+    // The distribution is detected from the os-release file of the build image. If that can't be
+    // read, the distribution name and version reported by the platform for the target are used.
+    // Only the distributions and architectures declared in the `[[targets]]` table of
+    // `buildpack.toml` are supported.
+    pub(crate) fn detect(
+        target: &Target,
+        buildpack_targets: &[BuildpackTarget],
+        supported_distros: &[SupportedDistro],
+    ) -> Result<Self, UnsupportedDistroError> {
+        Distro::from_os_release(
+            OsRelease::read(Path::new(OS_RELEASE_PATH)),
+            target,
+            buildpack_targets,
+            supported_distros,
+        )
+    }
+
+    fn from_os_release(
+        os_release: Option<OsRelease>,
+        target: &Target,
+        buildpack_targets: &[BuildpackTarget],
+        supported_distros: &[SupportedDistro],
+    ) -> Result<Self, UnsupportedDistroError> {
+        let os_release = os_release.unwrap_or_else(|| OsRelease {
+            id: target.distro_name.to_lowercase(),
+            version_id: Some(target.distro_version.to_string()),
+            version_codename: None,
+        });
+
+        let unsupported_distro_error = || UnsupportedDistroError {
+            name: os_release.id.clone(),
+            version: os_release
+                .version_id
+                .clone()
+                .or_else(|| os_release.version_codename.clone())
+                .unwrap_or_default(),
+            architecture: target.arch.to_string(),
+            supported_distros: supported_distros
+                .iter()
+                .filter_map(|supported_distro| supported_distro.describe_targets(buildpack_targets))
+                .collect(),
+        };
+
+        let architecture =
+            ArchitectureName::from_target(&target.arch, target.arch_variant.as_deref())
+                .map_err(|_| unsupported_distro_error())?;
+
+        let supported_distro = supported_distros
+            .iter()
+            .find(|supported_distro| supported_distro.matches(&os_release))
+            .filter(|supported_distro| {
+                supported_distro
+                    .target_architectures(buildpack_targets)
+                    .contains(&architecture)
+            })
+            .ok_or_else(unsupported_distro_error)?;
+
+        Ok(Distro {
+            name: os_release.id.clone(),
+            version: supported_distro.version_id.clone(),
+            codename: DistroCodename::from(supported_distro.version_codename.as_str()),
+            architecture,
+        })
+    }
+
+    // The run image can be built from a different release than the build image (e.g.; an Ubuntu
+    // 22.04 run image with an Ubuntu 24.04 build image) so packages can be resolved for the release
+    // configured in `target_codename` instead. The architecture is still the one being built for.
+    pub(crate) fn with_target_codename(
+        self,
+        codename: &DistroCodename,
+        buildpack_targets: &[BuildpackTarget],
+        supported_distros: &[SupportedDistro],
+    ) -> Result<Self, UnsupportedTargetCodenameError> {
+        let is_target = |supported_distro: &SupportedDistro| {
+            supported_distro
+                .target_architectures(buildpack_targets)
+                .contains(&self.architecture)
         };
+        let supported_distro = supported_distros
+            .iter()
+            .find(|supported_distro| {
+                &DistroCodename::from(supported_distro.version_codename.as_str()) == codename
+            })
+            .filter(|supported_distro| is_target(supported_distro))
+            .ok_or_else(|| UnsupportedTargetCodenameError {
+                codename: codename.clone(),
+                architecture: self.architecture.clone(),
+                supported_codenames: supported_distros
+                    .iter()
+                    .filter(|supported_distro| is_target(supported_distro))
+                    .map(|supported_distro| supported_distro.version_codename.clone())
+                    .collect(),
+            })?;
+
+        Ok(Distro {
+            name: supported_distro.id.clone(),
+            version: supported_distro.version_id.clone(),
+            codename: codename.clone(),
+            architecture: self.architecture,
+        })
+    }
 
-        source_list
+    pub(crate) fn get_source_list(
+        &self,
+        supported_distros: &[SupportedDistro],
+        signing_keys: &SigningKeys,
+    ) -> Vec<Source> {
+        self.find_supported_distro(supported_distros)
+            .map(|supported_distro| supported_distro.sources(signing_keys))
+            .unwrap_or_default()
             .into_iter()
             .filter(|source| source.arch == self.architecture)
             .collect()
     }
+
+    // Other architectures with sources available for this distribution (e.g.; arm64 when building
+    // for amd64 on Ubuntu 24.04).
+    pub(crate) fn get_other_architectures(
+        &self,
+        supported_distros: &[SupportedDistro],
+    ) -> Vec<ArchitectureName> {
+        self.find_supported_distro(supported_distros)
+            .map(SupportedDistro::architectures)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|arch| arch != &self.architecture)
+            .collect()
+    }
+
+    // The optional suites that are available for this distribution, in the order they're listed.
+    pub(crate) fn get_optional_suites(
+        &self,
+        supported_distros: &[SupportedDistro],
+        optional_suites: &[OptionalSuite],
+    ) -> Vec<String> {
+        let Some(supported_distro) = self.find_supported_distro(supported_distros) else {
+            return vec![];
+        };
+        optional_suites
+            .iter()
+            .filter_map(|optional_suite| match optional_suite {
+                OptionalSuite::Backports => supported_distro.backports_suite.clone(),
+                OptionalSuite::Proposed => supported_distro.proposed_suite.clone(),
+            })
+            .collect()
+    }
+
+    fn find_supported_distro<'a>(
+        &self,
+        supported_distros: &'a [SupportedDistro],
+    ) -> Option<&'a SupportedDistro> {
+        supported_distros.iter().find(|supported_distro| {
+            DistroCodename::from(supported_distro.version_codename.as_str()) == self.codename
+        })
+    }
 }
 
-impl TryFrom<&Target> for Distro {
-    type Error = UnsupportedDistroError;
+// Suites that aren't used unless they're enabled in project.toml since their packages are either
+// not as well tested (backports) or not released yet (proposed).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum OptionalSuite {
+    Backports,
+    Proposed,
+}
 
-    fn try_from(target: &Target) -> Result<Self, Self::Error> {
-        let name = target.distro_name.to_string();
-        let version = target.distro_version.to_string();
-        let target_arch = target.arch.to_string();
+// The `[metadata]` table of `buildpack.toml`. It's read by libcnb when the buildpack runs so the
+// supported distributions and their package sources can be changed by editing `buildpack.toml`
+// (e.g.; to point at a mirror) without changing any code.
+#[derive(Debug, Deserialize)]
+pub(crate) struct BuildpackMetadata {
+    pub(crate) distros: Vec<SupportedDistro>,
+}
 
-        let architecture =
-            ArchitectureName::from_str(&target_arch).map_err(|_| UnsupportedDistroError {
-                name: name.to_string(),
-                version: version.to_string(),
-                architecture: target_arch.to_string(),
-            })?;
+impl BuildpackMetadata {
+    // Reads the signing keys referenced by the distro table from the `keys` directory of the
+    // buildpack so a key that's missing or invalid fails the build before any source is used.
+    pub(crate) fn load_signing_keys(
+        &self,
+        buildpack_dir: &Path,
+    ) -> Result<SigningKeys, LoadSigningKeyError> {
+        SigningKeys::load(
+            &buildpack_dir.join("keys"),
+            self.distros
+                .iter()
+                .flat_map(|distro| &distro.sources)
+                .map(|source| source.signed_by.as_str()),
+        )
+    }
+}
 
-        match (name.to_lowercase().as_str(), version.as_str()) {
-            ("ubuntu", "22.04") => Ok(Distro {
-                name,
-                version,
-                architecture,
-                codename: DistroCodename::Jammy,
-            }),
-            ("ubuntu", "24.04") => Ok(Distro {
-                name,
-                version,
-                architecture,
-                codename: DistroCodename::Noble,
-            }),
-            _ => Err(UnsupportedDistroError {
-                name,
-                version,
-                architecture: target_arch,
-            }),
-        }
+// A distribution from the `[[metadata.distros]]` table of `buildpack.toml`. Supporting a new
+// release only requires adding an entry to that table and, if needed, its signing key to `keys`.
+#[derive(Debug, Deserialize)]
+pub(crate) struct SupportedDistro {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) version_id: String,
+    pub(crate) version_codename: String,
+    // suites with newer package versions that are only added to the sources of the release suite
+    // when enabled (e.g.; `noble-backports`)
+    #[serde(default)]
+    backports_suite: Option<String>,
+    #[serde(default)]
+    proposed_suite: Option<String>,
+    sources: Vec<SupportedSource>,
+}
+
+// The signing key names and architectures are checked when the metadata is read so a typo in
+// `buildpack.toml` fails the build immediately instead of when the sources are used.
+#[derive(Debug, Deserialize)]
+struct SupportedSource {
+    uri: String,
+    suites: Vec<String>,
+    components: Vec<String>,
+    #[serde(deserialize_with = "deserialize_signing_key_name")]
+    signed_by: String,
+    #[serde(deserialize_with = "deserialize_architectures")]
+    architectures: Vec<ArchitectureName>,
+    #[serde(default)]
+    mirrors: Vec<String>,
+}
+
+fn deserialize_signing_key_name<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let name = String::deserialize(deserializer)?;
+    // the key is read from the `keys` directory of the buildpack so the name can't be a path
+    if Path::new(&name).file_name() == Some(name.as_ref()) {
+        Ok(name)
+    } else {
+        Err(D::Error::custom(format!(
+            "`{name}` is not the file name of a signing key in the buildpack's `keys` directory"
+        )))
     }
 }
 
-// NOTE: Regarding http versus https for the repository urls that follow - these sources are extracted
-//       from the default sources configured on these distributions which do not use https. This is
-//       a trade-off between performance and privacy.
-//
-//       But, for security, we can verify no tampering of packages has occurred since release files
-//       are validated with PGP and everything from that point on using checksums.
-//
-//       See: https://wiki.debian.org/SecureApt
-//
-//       The corresponding certificates used to validate the PGP signatures can be regenerated by
-//       running <project-root>/scripts/extract_keys.sh.
-
-fn get_jammy_source_list() -> Vec<Source> {
-    vec![Source::new(
-        // see note above for why http is used here instead of https
-        "http://archive.ubuntu.com/ubuntu",
-        vec!["jammy", "jammy-security", "jammy-updates"],
-        vec!["main", "universe"],
-        include_str!("../../keys/ubuntu_22.04.asc"),
-        AMD_64,
-    )]
+fn deserialize_architectures<'de, D>(deserializer: D) -> Result<Vec<ArchitectureName>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|arch| {
+            ArchitectureName::from_str(arch)
+                .map_err(|_| D::Error::custom(format!("`{arch}` is not a supported architecture")))
+        })
+        .collect()
 }
 
-fn get_noble_source_list() -> Vec<Source> {
-    vec![
-        Source::new(
-            // see note above for why http is used here instead of https
-            "http://archive.ubuntu.com/ubuntu",
-            vec!["noble", "noble-updates"],
-            vec!["main", "universe"],
-            include_str!("../../keys/ubuntu_24.04.asc"),
-            AMD_64,
-        ),
-        Source::new(
-            // see note above for why http is used here instead of https
-            "http://security.ubuntu.com/ubuntu",
-            vec!["noble-security"],
-            vec!["main", "universe"],
-            include_str!("../../keys/ubuntu_24.04.asc"),
-            AMD_64,
-        ),
-        Source::new(
-            // see note above for why http is used here instead of https
-            "http://ports.ubuntu.com/ubuntu-ports",
-            vec!["noble", "noble-updates", "noble-security"],
-            vec!["main", "universe"],
-            include_str!("../../keys/ubuntu_24.04.asc"),
-            ARM_64,
-        ),
-    ]
+impl SupportedDistro {
+    // The codename is preferred since it's what the package sources are named after but not every
+    // os-release file includes it (e.g.; Debian testing).
+    fn matches(&self, os_release: &OsRelease) -> bool {
+        self.id == os_release.id
+            && match (&os_release.version_codename, &os_release.version_id) {
+                (Some(version_codename), _) => &self.version_codename == version_codename,
+                (None, Some(version_id)) => &self.version_id == version_id,
+                (None, None) => false,
+            }
+    }
+
+    pub(crate) fn architectures(&self) -> Vec<ArchitectureName> {
+        self.sources
+            .iter()
+            .flat_map(|source| &source.architectures)
+            .fold(vec![], |mut architectures, arch| {
+                if !architectures.contains(arch) {
+                    architectures.push(arch.clone());
+                }
+                architectures
+            })
+    }
+
+    // The architectures this distribution is built for, which are those declared for it in the
+    // `[[targets]]` table of `buildpack.toml` that it also has sources for. Sources for other
+    // architectures (e.g.; `i386`) are only used for packages requested for a foreign architecture.
+    fn target_architectures(&self, buildpack_targets: &[BuildpackTarget]) -> Vec<ArchitectureName> {
+        let architectures = self.architectures();
+        buildpack_targets
+            .iter()
+            .filter(|buildpack_target| {
+                buildpack_target
+                    .distros
+                    .iter()
+                    .any(|distro| distro.name == self.id && distro.version == self.version_id)
+            })
+            .filter_map(|buildpack_target| {
+                ArchitectureName::from_target(
+                    buildpack_target.arch.as_deref()?,
+                    buildpack_target.variant.as_deref(),
+                )
+                .ok()
+            })
+            .filter(|arch| architectures.contains(arch))
+            .fold(vec![], |mut target_architectures, arch| {
+                if !target_architectures.contains(&arch) {
+                    target_architectures.push(arch);
+                }
+                target_architectures
+            })
+    }
+
+    // Describes the distribution and the architectures it's built for (e.g.; `Ubuntu 24.04 (amd64,
+    // arm64)`) or returns `None` when it isn't declared as a target.
+    fn describe_targets(&self, buildpack_targets: &[BuildpackTarget]) -> Option<String> {
+        let target_architectures = self.target_architectures(buildpack_targets);
+        if target_architectures.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "{} {} ({})",
+            self.name,
+            self.version_id,
+            target_architectures
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    }
+
+    fn sources(&self, signing_keys: &SigningKeys) -> Vec<Source> {
+        self.sources
+            .iter()
+            .flat_map(|source| {
+                let signed_by = signing_keys
+                    .get(&source.signed_by)
+                    .expect("The signing keys should be loaded from the buildpack metadata");
+                source.architectures.iter().map(move |arch| Source {
+                    mirrors: source
+                        .mirrors
+                        .iter()
+                        .map(|mirror| RepositoryUri::from(mirror.trim_end_matches('/')))
+                        .collect(),
+                    ..Source::new(
+                        source.uri.as_str(),
+                        source.suites.iter().map(String::as_str),
+                        source.components.iter().map(String::as_str),
+                        signed_by,
+                        arch.clone(),
+                    )
+                })
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug)]
@@ -123,6 +375,15 @@ pub(crate) struct UnsupportedDistroError {
     pub(crate) name: String,
     pub(crate) version: String,
     pub(crate) architecture: String,
+    pub(crate) supported_distros: Vec<String>,
+}
+
+#[derive(Debug)]
+pub(crate) struct UnsupportedTargetCodenameError {
+    pub(crate) codename: DistroCodename,
+    pub(crate) architecture: ArchitectureName,
+    // the codenames of the supported distributions with sources for the architecture
+    pub(crate) supported_codenames: Vec<String>,
 }
 
 impl From<UnsupportedDistroError> for libcnb::Error<DebianPackagesBuildpackError> {
@@ -130,3 +391,419 @@ impl From<UnsupportedDistroError> for libcnb::Error<DebianPackagesBuildpackError
         Self::BuildpackError(DebianPackagesBuildpackError::UnsupportedDistro(value))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use libcnb::data::buildpack::ComponentBuildpackDescriptor;
+
+    use crate::debian::ArchitectureName::{
+        AMD_64, ARM_64, ARM_HF, I_386, PPC_64_EL, RISCV_64, S390_X,
+    };
+
+    use super::*;
+
+    fn supported_distros() -> Vec<SupportedDistro> {
+        toml::from_str::<ComponentBuildpackDescriptor<BuildpackMetadata>>(include_str!(
+            "../../buildpack.toml"
+        ))
+        .unwrap()
+        .metadata
+        .distros
+    }
+
+    fn signing_keys() -> SigningKeys {
+        BuildpackMetadata {
+            distros: supported_distros(),
+        }
+        .load_signing_keys(Path::new(env!("CARGO_MANIFEST_DIR")))
+        .unwrap()
+    }
+
+    fn buildpack_targets() -> Vec<BuildpackTarget> {
+        toml::from_str::<ComponentBuildpackDescriptor<BuildpackMetadata>>(include_str!(
+            "../../buildpack.toml"
+        ))
+        .unwrap()
+        .targets
+    }
+
+    fn target(arch: &str) -> Target {
+        Target {
+            os: "linux".to_string(),
+            arch: arch.to_string(),
+            arch_variant: None,
+            distro_name: "ubuntu".to_string(),
+            distro_version: "22.04".to_string(),
+        }
+    }
+
+    fn os_release(id: &str, version_id: &str, version_codename: Option<&str>) -> OsRelease {
+        OsRelease {
+            id: id.to_string(),
+            version_id: Some(version_id.to_string()),
+            version_codename: version_codename.map(ToString::to_string),
+        }
+    }
+
+    #[test]
+    fn test_detect_distro_from_os_release() {
+        assert_eq!(
+            Distro::from_os_release(
+                Some(os_release("debian", "12", Some("bookworm"))),
+                &target("arm64"),
+                &buildpack_targets(),
+                &supported_distros()
+            )
+            .unwrap(),
+            Distro {
+                name: "debian".to_string(),
+                version: "12".to_string(),
+                codename: DistroCodename::from("bookworm"),
+                architecture: ARM_64,
+            }
+        );
+        assert_eq!(
+            Distro::from_os_release(
+                Some(os_release("ubuntu", "20.04", None)),
+                &target("amd64"),
+                &buildpack_targets(),
+                &supported_distros()
+            )
+            .unwrap()
+            .codename,
+            DistroCodename::from("focal")
+        );
+    }
+
+    #[test]
+    fn test_detect_distro_falls_back_to_target() {
+        assert_eq!(
+            Distro::from_os_release(
+                None,
+                &target("amd64"),
+                &buildpack_targets(),
+                &supported_distros()
+            )
+            .unwrap(),
+            Distro {
+                name: "ubuntu".to_string(),
+                version: "22.04".to_string(),
+                codename: DistroCodename::from("jammy"),
+                architecture: AMD_64,
+            }
+        );
+    }
+
+    #[test]
+    fn test_detect_unsupported_distro() {
+        let error = Distro::from_os_release(
+            Some(os_release("ubuntu", "18.04", Some("bionic"))),
+            &target("amd64"),
+            &buildpack_targets(),
+            &supported_distros(),
+        )
+        .unwrap_err();
+        assert_eq!(error.name, "ubuntu");
+        assert_eq!(error.version, "18.04");
+
+        let error = Distro::from_os_release(
+            Some(os_release("ubuntu", "22.04", Some("jammy"))),
+            &target("arm64"),
+            &buildpack_targets(),
+            &supported_distros(),
+        )
+        .unwrap_err();
+        assert_eq!(error.architecture, "arm64");
+
+        let error = Distro::from_os_release(
+            Some(os_release("debian", "12", Some("bookworm"))),
+            &target("riscv64"),
+            &buildpack_targets(),
+            &supported_distros(),
+        )
+        .unwrap_err();
+        assert_eq!(error.architecture, "riscv64");
+    }
+
+    #[test]
+    fn test_detect_distro_not_declared_as_target() {
+        let mut buildpack_targets = buildpack_targets();
+        for buildpack_target in &mut buildpack_targets {
+            if buildpack_target.arch.as_deref() == Some("arm64") {
+                buildpack_target
+                    .distros
+                    .retain(|distro| distro.name != "ubuntu");
+            }
+        }
+
+        // Ubuntu 24.04 has sources for arm64 but it's no longer declared as a target
+        let error = Distro::from_os_release(
+            Some(os_release("ubuntu", "24.04", Some("noble"))),
+            &target("arm64"),
+            &buildpack_targets,
+            &supported_distros(),
+        )
+        .unwrap_err();
+        assert_eq!(error.architecture, "arm64");
+        assert_eq!(
+            error.supported_distros,
+            vec![
+                "Ubuntu 24.04 (amd64, armhf, ppc64el, s390x, riscv64)",
+                "Ubuntu 22.04 (amd64)",
+                "Ubuntu 20.04 (amd64)",
+                "Debian 13 (amd64, arm64, armhf, ppc64el, s390x, riscv64)",
+                "Debian 12 (amd64, arm64, armhf, ppc64el, s390x)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_detect_distro_for_ports_architectures() {
+        for (arch, arch_variant, architecture) in [
+            ("arm", Some("v7"), ARM_HF),
+            ("ppc64le", None, PPC_64_EL),
+            ("s390x", None, S390_X),
+            ("riscv64", None, RISCV_64),
+        ] {
+            let distro = Distro::from_os_release(
+                Some(os_release("ubuntu", "24.04", Some("noble"))),
+                &Target {
+                    arch_variant: arch_variant.map(ToString::to_string),
+                    ..target(arch)
+                },
+                &buildpack_targets(),
+                &supported_distros(),
+            )
+            .unwrap();
+            assert_eq!(distro.architecture, architecture);
+            assert_eq!(
+                distro
+                    .get_source_list(&supported_distros(), &signing_keys())
+                    .iter()
+                    .map(|source| source.uri.as_str())
+                    .collect::<Vec<_>>(),
+                vec!["http://ports.ubuntu.com/ubuntu-ports"]
+            );
+        }
+    }
+
+    #[test]
+    fn test_with_target_codename() {
+        let distro = Distro::from_os_release(
+            Some(os_release("ubuntu", "24.04", Some("noble"))),
+            &target("amd64"),
+            &buildpack_targets(),
+            &supported_distros(),
+        )
+        .unwrap();
+        assert_eq!(
+            distro
+                .clone()
+                .with_target_codename(
+                    &DistroCodename::from("jammy"),
+                    &buildpack_targets(),
+                    &supported_distros(),
+                )
+                .unwrap(),
+            Distro {
+                name: "ubuntu".to_string(),
+                version: "22.04".to_string(),
+                codename: DistroCodename::from("jammy"),
+                architecture: AMD_64,
+            }
+        );
+
+        let error = distro
+            .with_target_codename(
+                &DistroCodename::from("bionic"),
+                &buildpack_targets(),
+                &supported_distros(),
+            )
+            .unwrap_err();
+        assert_eq!(error.codename, DistroCodename::from("bionic"));
+        assert_eq!(error.architecture, AMD_64);
+        assert!(error.supported_codenames.contains(&"noble".to_string()));
+
+        // Ubuntu 22.04 doesn't publish riscv64 packages
+        let error = Distro::from_os_release(
+            Some(os_release("ubuntu", "24.04", Some("noble"))),
+            &target("riscv64"),
+            &buildpack_targets(),
+            &supported_distros(),
+        )
+        .unwrap()
+        .with_target_codename(
+            &DistroCodename::from("jammy"),
+            &buildpack_targets(),
+            &supported_distros(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.supported_codenames,
+            vec!["noble".to_string(), "trixie".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_get_other_architectures() {
+        let distro = |codename, architecture| Distro {
+            name: "Ubuntu".to_string(),
+            version: String::new(),
+            codename: DistroCodename::from(codename),
+            architecture,
+        };
+        assert_eq!(
+            distro("noble", AMD_64).get_other_architectures(&supported_distros()),
+            vec![I_386, ARM_64, ARM_HF, PPC_64_EL, S390_X, RISCV_64]
+        );
+        assert_eq!(
+            distro("noble", ARM_64).get_other_architectures(&supported_distros()),
+            vec![AMD_64, I_386, ARM_HF, PPC_64_EL, S390_X, RISCV_64]
+        );
+        assert_eq!(
+            distro("focal", AMD_64).get_other_architectures(&supported_distros()),
+            vec![I_386]
+        );
+        assert_eq!(
+            distro("jammy", AMD_64).get_other_architectures(&supported_distros()),
+            vec![I_386]
+        );
+        assert_eq!(
+            distro("bookworm", ARM_64).get_other_architectures(&supported_distros()),
+            vec![AMD_64, ARM_HF, PPC_64_EL, S390_X, I_386]
+        );
+    }
+
+    #[test]
+    fn test_debian_source_list() {
+        let distro = Distro {
+            name: "Debian".to_string(),
+            version: "12".to_string(),
+            codename: DistroCodename::from("bookworm"),
+            architecture: ARM_64,
+        };
+        let sources = distro.get_source_list(&supported_distros(), &signing_keys());
+        assert_eq!(
+            sources
+                .iter()
+                .map(|source| (source.uri.as_str(), source.suites.join(",")))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "http://deb.debian.org/debian",
+                    "bookworm,bookworm-updates".to_string()
+                ),
+                (
+                    "http://deb.debian.org/debian-security",
+                    "bookworm-security".to_string()
+                )
+            ]
+        );
+        assert!(sources.iter().all(|source| source.arch == ARM_64));
+    }
+
+    #[test]
+    fn test_supported_distros_match_targets() {
+        let buildpack_descriptor =
+            toml::from_str::<ComponentBuildpackDescriptor<BuildpackMetadata>>(include_str!(
+                "../../buildpack.toml"
+            ))
+            .unwrap();
+        let signing_keys = buildpack_descriptor
+            .metadata
+            .load_signing_keys(Path::new(env!("CARGO_MANIFEST_DIR")))
+            .unwrap();
+        for target in buildpack_descriptor.targets {
+            let arch =
+                ArchitectureName::from_target(&target.arch.unwrap(), target.variant.as_deref())
+                    .unwrap();
+            for distro in target.distros {
+                let supported_distro = buildpack_descriptor
+                    .metadata
+                    .distros
+                    .iter()
+                    .find(|supported_distro| {
+                        supported_distro.id == distro.name
+                            && supported_distro.version_id == distro.version
+                    })
+                    .unwrap_or_else(|| panic!("{} {} has no sources", distro.name, distro.version));
+                assert!(supported_distro.architectures().contains(&arch));
+                assert!(!supported_distro.sources(&signing_keys).is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_supported_distro_with_unknown_signing_key() {
+        let metadata = toml::from_str::<BuildpackMetadata>(indoc! { r#"
+            [[distros]]
+            id = "ubuntu"
+            name = "Ubuntu"
+            version_id = "24.04"
+            version_codename = "noble"
+
+            [[distros.sources]]
+            uri = "http://mirror.example.com/ubuntu"
+            suites = ["noble"]
+            components = ["main"]
+            signed_by = "ubuntu_25.04.asc"
+            architectures = ["amd64"]
+        "# })
+        .unwrap();
+        match metadata
+            .load_signing_keys(Path::new(env!("CARGO_MANIFEST_DIR")))
+            .unwrap_err()
+        {
+            LoadSigningKeyError::Unknown(name, _) => {
+                assert_eq!(name, "ubuntu_25.04.asc");
+            }
+            e => panic!("Unexpected error: {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_supported_distro_with_signing_key_path() {
+        let error = toml::from_str::<BuildpackMetadata>(indoc! { r#"
+            [[distros]]
+            id = "ubuntu"
+            name = "Ubuntu"
+            version_id = "24.04"
+            version_codename = "noble"
+
+            [[distros.sources]]
+            uri = "http://mirror.example.com/ubuntu"
+            suites = ["noble"]
+            components = ["main"]
+            signed_by = "../ubuntu_24.04.asc"
+            architectures = ["amd64"]
+        "# })
+        .unwrap_err();
+        assert!(error.to_string().contains(
+            "`../ubuntu_24.04.asc` is not the file name of a signing key in the buildpack's `keys` \
+            directory"
+        ));
+    }
+
+    #[test]
+    fn test_supported_distro_with_unknown_architecture() {
+        let error = toml::from_str::<BuildpackMetadata>(indoc! { r#"
+            [[distros]]
+            id = "ubuntu"
+            name = "Ubuntu"
+            version_id = "24.04"
+            version_codename = "noble"
+
+            [[distros.sources]]
+            uri = "http://mirror.example.com/ubuntu"
+            suites = ["noble"]
+            components = ["main"]
+            signed_by = "ubuntu_24.04.asc"
+            architectures = ["mips64el"]
+        "# })
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("`mips64el` is not a supported architecture"));
+    }
+}
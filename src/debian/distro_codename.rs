@@ -2,17 +2,55 @@ use std::fmt::{Display, Formatter};
 
 use serde::{Deserialize, Serialize};
 
+// The release codename of a distribution (e.g.; noble, bookworm) which is read from the
+// `VERSION_CODENAME` field in `/etc/os-release`. Older layer metadata stored these as capitalized
+// enum variants (e.g.; `Jammy`) so codenames are normalized to lowercase when deserialized.
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
-pub(crate) enum DistroCodename {
-    Jammy,
-    Noble,
+#[serde(from = "String", into = "String")]
+pub(crate) struct DistroCodename(String);
+
+impl DistroCodename {
+    pub(crate) fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for DistroCodename {
+    fn from(value: String) -> Self {
+        DistroCodename(value.to_lowercase())
+    }
+}
+
+impl From<&str> for DistroCodename {
+    fn from(value: &str) -> Self {
+        DistroCodename::from(value.to_string())
+    }
+}
+
+impl From<DistroCodename> for String {
+    fn from(value: DistroCodename) -> Self {
+        value.0
+    }
 }
 
 impl Display for DistroCodename {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            DistroCodename::Jammy => write!(f, "jammy"),
-            DistroCodename::Noble => write!(f, "noble"),
-        }
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Metadata {
+        codename: DistroCodename,
+    }
+
+    #[test]
+    fn test_deserialize_legacy_codename() {
+        let metadata = toml::from_str::<Metadata>(r#"codename = "Jammy""#).unwrap();
+        assert_eq!(metadata.codename, DistroCodename::from("jammy"));
     }
 }
@@ -1,19 +1,31 @@
 pub(crate) use architecture_name::*;
+pub(crate) use checksum_algorithm::*;
+pub(crate) use copyright::*;
 pub(crate) use distro::*;
 pub(crate) use distro_codename::*;
 pub(crate) use multiarch_name::*;
+pub(crate) use os_release::*;
 pub(crate) use package_index::*;
 pub(crate) use package_name::*;
+pub(crate) use package_repository::*;
+pub(crate) use pdiff::*;
 pub(crate) use repository_package::*;
 pub(crate) use repository_uri::*;
+pub(crate) use signature_policy::*;
 pub(crate) use source::*;
 
 mod architecture_name;
+mod checksum_algorithm;
+mod copyright;
 mod distro;
 mod distro_codename;
 mod multiarch_name;
+mod os_release;
 mod package_index;
 pub(crate) mod package_name;
+mod package_repository;
+mod pdiff;
 pub(crate) mod repository_package;
 mod repository_uri;
+mod signature_policy;
 mod source;
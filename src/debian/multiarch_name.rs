@@ -9,6 +9,11 @@ use std::str::FromStr;
 pub(crate) enum MultiarchName {
     X86_64_LINUX_GNU,
     AARCH_64_LINUX_GNU,
+    ARM_LINUX_GNUEABIHF,
+    POWERPC_64LE_LINUX_GNU,
+    S390X_LINUX_GNU,
+    RISCV_64_LINUX_GNU,
+    I386_LINUX_GNU,
 }
 
 impl From<&ArchitectureName> for MultiarchName {
@@ -16,6 +21,11 @@ impl From<&ArchitectureName> for MultiarchName {
         match value {
             ArchitectureName::AMD_64 => MultiarchName::X86_64_LINUX_GNU,
             ArchitectureName::ARM_64 => MultiarchName::AARCH_64_LINUX_GNU,
+            ArchitectureName::ARM_HF => MultiarchName::ARM_LINUX_GNUEABIHF,
+            ArchitectureName::PPC_64_EL => MultiarchName::POWERPC_64LE_LINUX_GNU,
+            ArchitectureName::S390_X => MultiarchName::S390X_LINUX_GNU,
+            ArchitectureName::RISCV_64 => MultiarchName::RISCV_64_LINUX_GNU,
+            ArchitectureName::I_386 => MultiarchName::I386_LINUX_GNU,
         }
     }
 }
@@ -25,6 +35,11 @@ impl Display for MultiarchName {
         match self {
             MultiarchName::X86_64_LINUX_GNU => write!(f, "x86_64-linux-gnu"),
             MultiarchName::AARCH_64_LINUX_GNU => write!(f, "aarch64-linux-gnu"),
+            MultiarchName::ARM_LINUX_GNUEABIHF => write!(f, "arm-linux-gnueabihf"),
+            MultiarchName::POWERPC_64LE_LINUX_GNU => write!(f, "powerpc64le-linux-gnu"),
+            MultiarchName::S390X_LINUX_GNU => write!(f, "s390x-linux-gnu"),
+            MultiarchName::RISCV_64_LINUX_GNU => write!(f, "riscv64-linux-gnu"),
+            MultiarchName::I386_LINUX_GNU => write!(f, "i386-linux-gnu"),
         }
     }
 }
@@ -36,6 +51,11 @@ impl FromStr for MultiarchName {
         match s {
             "x86_64-linux-gnu" => Ok(MultiarchName::X86_64_LINUX_GNU),
             "aarch64-linux-gnu" => Ok(MultiarchName::AARCH_64_LINUX_GNU),
+            "arm-linux-gnueabihf" => Ok(MultiarchName::ARM_LINUX_GNUEABIHF),
+            "powerpc64le-linux-gnu" => Ok(MultiarchName::POWERPC_64LE_LINUX_GNU),
+            "s390x-linux-gnu" => Ok(MultiarchName::S390X_LINUX_GNU),
+            "riscv64-linux-gnu" => Ok(MultiarchName::RISCV_64_LINUX_GNU),
+            "i386-linux-gnu" => Ok(MultiarchName::I386_LINUX_GNU),
             _ => Err(()),
         }
     }
@@ -49,8 +69,14 @@ mod tests {
     #[test]
     fn test_multiarch_name_from_str() {
         // Test valid strings
-        assert_eq!(MultiarchName::from_str("x86_64-linux-gnu").unwrap(), MultiarchName::X86_64_LINUX_GNU);
-        assert_eq!(MultiarchName::from_str("aarch64-linux-gnu").unwrap(), MultiarchName::AARCH_64_LINUX_GNU);
+        assert_eq!(
+            MultiarchName::from_str("x86_64-linux-gnu").unwrap(),
+            MultiarchName::X86_64_LINUX_GNU
+        );
+        assert_eq!(
+            MultiarchName::from_str("aarch64-linux-gnu").unwrap(),
+            MultiarchName::AARCH_64_LINUX_GNU
+        );
 
         // Test invalid string
         assert!(MultiarchName::from_str("invalid-arch").is_err());
@@ -66,6 +92,26 @@ mod tests {
             MultiarchName::from(&ArchitectureName::ARM_64),
             MultiarchName::AARCH_64_LINUX_GNU
         );
+        assert_eq!(
+            MultiarchName::from(&ArchitectureName::ARM_HF),
+            MultiarchName::ARM_LINUX_GNUEABIHF
+        );
+        assert_eq!(
+            MultiarchName::from(&ArchitectureName::PPC_64_EL),
+            MultiarchName::POWERPC_64LE_LINUX_GNU
+        );
+        assert_eq!(
+            MultiarchName::from(&ArchitectureName::S390_X),
+            MultiarchName::S390X_LINUX_GNU
+        );
+        assert_eq!(
+            MultiarchName::from(&ArchitectureName::RISCV_64),
+            MultiarchName::RISCV_64_LINUX_GNU
+        );
+        assert_eq!(
+            MultiarchName::from(&ArchitectureName::I_386),
+            MultiarchName::I386_LINUX_GNU
+        );
     }
 
     #[test]
@@ -78,5 +124,17 @@ mod tests {
             MultiarchName::AARCH_64_LINUX_GNU.to_string(),
             "aarch64-linux-gnu"
         );
+        for multiarch_name in [
+            MultiarchName::ARM_LINUX_GNUEABIHF,
+            MultiarchName::POWERPC_64LE_LINUX_GNU,
+            MultiarchName::S390X_LINUX_GNU,
+            MultiarchName::RISCV_64_LINUX_GNU,
+            MultiarchName::I386_LINUX_GNU,
+        ] {
+            assert_eq!(
+                MultiarchName::from_str(&multiarch_name.to_string()),
+                Ok(multiarch_name)
+            );
+        }
     }
 }
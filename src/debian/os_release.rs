@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+// The identifying fields from an os-release file.
+//
+// See: https://www.freedesktop.org/software/systemd/man/latest/os-release.html
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct OsRelease {
+    pub(crate) id: String,
+    pub(crate) version_id: Option<String>,
+    pub(crate) version_codename: Option<String>,
+}
+
+impl OsRelease {
+    pub(crate) fn read(path: &Path) -> Option<Self> {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| OsRelease::parse(&contents))
+    }
+
+    pub(crate) fn parse(contents: &str) -> Option<Self> {
+        let fields = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim(), unquote(value.trim())))
+            .filter(|(_, value)| !value.is_empty())
+            .collect::<HashMap<_, _>>();
+
+        Some(OsRelease {
+            id: fields.get("ID")?.to_lowercase(),
+            version_id: fields.get("VERSION_ID").cloned(),
+            version_codename: fields
+                .get("VERSION_CODENAME")
+                .map(|value| value.to_lowercase()),
+        })
+    }
+}
+
+// values may be enclosed in single or double quotes with shell-style escapes for the quote
+// characters, `$`, `` ` ``, and `\`
+fn unquote(value: &str) -> String {
+    let unquoted = ['"', '\'']
+        .into_iter()
+        .find_map(|quote| {
+            value
+                .strip_prefix(quote)
+                .and_then(|value| value.strip_suffix(quote))
+        })
+        .unwrap_or(value);
+
+    let mut result = String::with_capacity(unquoted.len());
+    let mut chars = unquoted.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            result.extend(chars.next());
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_os_release() {
+        let os_release = OsRelease::parse(indoc! {r#"
+            PRETTY_NAME="Ubuntu 24.04.1 LTS"
+            NAME="Ubuntu"
+            VERSION_ID="24.04"
+            VERSION="24.04.1 LTS (Noble Numbat)"
+            VERSION_CODENAME=noble
+            ID=ubuntu
+            ID_LIKE=debian
+            # comments are ignored
+            UBUNTU_CODENAME='noble'
+        "#})
+        .unwrap();
+        assert_eq!(
+            os_release,
+            OsRelease {
+                id: "ubuntu".to_string(),
+                version_id: Some("24.04".to_string()),
+                version_codename: Some("noble".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_os_release_without_codename() {
+        let os_release = OsRelease::parse(indoc! {r#"
+            ID="debian"
+            VERSION_ID="13"
+            VERSION_CODENAME=
+        "#})
+        .unwrap();
+        assert_eq!(os_release.version_codename, None);
+    }
+
+    #[test]
+    fn test_parse_os_release_without_id() {
+        assert_eq!(OsRelease::parse(r#"NAME="Some \"Linux\"""#), None);
+    }
+}
@@ -1,14 +1,28 @@
-use crate::debian::RepositoryPackage;
+use crate::debian::{RepositoryPackage, RepositoryUri};
 use indexmap::{IndexMap, IndexSet};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::str::FromStr;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub(crate) struct PackageIndex {
     name_to_repository_packages: IndexMap<String, Vec<RepositoryPackage>>,
     // NOTE: virtual packages are declared in the `Provides` field of a package
     //       https://www.debian.org/doc/debian-policy/ch-relationships.html#virtual-packages-provides
     virtual_package_to_implementing_packages: IndexMap<String, Vec<RepositoryPackage>>,
     pub(crate) packages_indexed: usize,
+    // entries that were skipped because the same version of the package was already added from
+    // the same repository (e.g.; when it's listed in more than one component)
+    pub(crate) duplicate_packages: Vec<RepositoryPackage>,
+    // repositories listed first are preferred when the same version of a package is available
+    // from more than one repository
+    #[serde(skip)]
+    source_priority: Vec<RepositoryUri>,
+    #[serde(skip)]
+    skip_phased_updates: bool,
+    // packages from `NotAutomatic` suites (e.g.; backports) are only preferred for these
+    #[serde(skip)]
+    requested_packages: HashSet<String>,
 }
 
 impl PackageIndex {
@@ -19,16 +33,41 @@ impl PackageIndex {
         self.name_to_repository_packages
             .get(package_name)
             .and_then(|repository_packages| {
+                // phased updates are only skipped when there's a version that isn't being phased
+                // in to install instead (e.g.; from the release pocket)
+                let skip_phased_updates = self.skip_phased_updates
+                    && repository_packages
+                        .iter()
+                        .any(|repository_package| !repository_package.is_phased_update());
                 let mut sorted_repository_packages = Vec::with_capacity(repository_packages.len());
-                for repository_package in repository_packages {
+                for repository_package in repository_packages.iter().filter(|repository_package| {
+                    !(skip_phased_updates && repository_package.is_phased_update())
+                }) {
                     let parsed_version =
                         debversion::Version::from_str(repository_package.version.as_str())
                             .expect("Packages should always have a valid debian version");
                     sorted_repository_packages.push((repository_package, parsed_version));
                 }
 
-                sorted_repository_packages
-                    .sort_by(|(_, version_a), (_, version_b)| version_b.cmp(version_a));
+                // like with apt, a higher version from a `NotAutomatic` suite is only installed
+                // when the package was requested, otherwise only when no other suite has it
+                let is_deprioritized = |repository_package: &RepositoryPackage| {
+                    repository_package.not_automatic
+                        && !self.requested_packages.contains(package_name)
+                };
+                sorted_repository_packages.sort_by(
+                    |(package_a, version_a), (package_b, version_b)| {
+                        is_deprioritized(package_a)
+                            .cmp(&is_deprioritized(package_b))
+                            .then_with(|| version_b.cmp(version_a))
+                            .then_with(|| {
+                                self.compare_source_priority(
+                                    &package_a.repository_uri,
+                                    &package_b.repository_uri,
+                                )
+                            })
+                    },
+                );
 
                 sorted_repository_packages
                     .first()
@@ -36,7 +75,96 @@ impl PackageIndex {
             })
     }
 
+    pub(crate) fn set_source_priority(&mut self, source_priority: Vec<RepositoryUri>) {
+        self.source_priority = source_priority;
+    }
+
+    pub(crate) fn set_skip_phased_updates(&mut self, skip_phased_updates: bool) {
+        self.skip_phased_updates = skip_phased_updates;
+    }
+
+    pub(crate) fn set_requested_packages(&mut self, requested_packages: HashSet<String>) {
+        self.requested_packages = requested_packages;
+    }
+
+    // Returns the highest phased update of the given package that was skipped in favor of it.
+    pub(crate) fn get_skipped_phased_update(
+        &self,
+        repository_package: &RepositoryPackage,
+    ) -> Option<&RepositoryPackage> {
+        if !self.skip_phased_updates || repository_package.is_phased_update() {
+            return None;
+        }
+        let version = debversion::Version::from_str(&repository_package.version).ok()?;
+        self.name_to_repository_packages
+            .get(&repository_package.name)?
+            .iter()
+            .filter(|other| other.is_phased_update())
+            .filter_map(|other| {
+                debversion::Version::from_str(&other.version)
+                    .ok()
+                    .map(|other_version| (other, other_version))
+            })
+            .filter(|(_, other_version)| other_version > &version)
+            .max_by(|(_, version_a), (_, version_b)| version_a.cmp(version_b))
+            .map(|(other, _)| other)
+    }
+
+    // Returns the other repositories which provide the exact same version of the given package.
+    pub(crate) fn get_alternate_sources(
+        &self,
+        repository_package: &RepositoryPackage,
+    ) -> Vec<&RepositoryUri> {
+        self.name_to_repository_packages
+            .get(&repository_package.name)
+            .map(|repository_packages| {
+                repository_packages
+                    .iter()
+                    .filter(|other| {
+                        other.version == repository_package.version
+                            && other.repository_uri != repository_package.repository_uri
+                    })
+                    .map(|other| &other.repository_uri)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // Repositories without a configured priority rank after those with one and are ordered by
+    // their uri so the result doesn't depend on the order the package indexes were downloaded in.
+    fn compare_source_priority(
+        &self,
+        repository_uri_a: &RepositoryUri,
+        repository_uri_b: &RepositoryUri,
+    ) -> std::cmp::Ordering {
+        let rank = |repository_uri: &RepositoryUri| {
+            self.source_priority
+                .iter()
+                .position(|preferred| preferred == repository_uri)
+                .unwrap_or(self.source_priority.len())
+        };
+        rank(repository_uri_a)
+            .cmp(&rank(repository_uri_b))
+            .then_with(|| repository_uri_a.as_str().cmp(repository_uri_b.as_str()))
+    }
+
+    // Package indexes are added in the order their sources and components are configured so, when a
+    // repository lists the same version of a package more than once, the first entry is kept.
     pub(crate) fn add_package(&mut self, package: RepositoryPackage) {
+        let is_duplicate = self
+            .name_to_repository_packages
+            .get(&package.name)
+            .is_some_and(|repository_packages| {
+                repository_packages.iter().any(|other| {
+                    other.version == package.version
+                        && other.repository_uri == package.repository_uri
+                })
+            });
+        if is_duplicate {
+            self.duplicate_packages.push(package);
+            return;
+        }
+
         for provides in package.provides_dependencies() {
             self.virtual_package_to_implementing_packages
                 .entry(provides.to_string())
@@ -52,6 +180,52 @@ impl PackageIndex {
         self.packages_indexed += 1;
     }
 
+    // Removes every entry for the package that doesn't match so it resolves to the pinned one.
+    // Returns false if the index doesn't contain a match, in which case the package is left as-is.
+    pub(crate) fn pin_package(
+        &mut self,
+        package_name: &str,
+        is_pinned: impl Fn(&RepositoryPackage) -> bool,
+    ) -> bool {
+        let Some(repository_packages) = self.name_to_repository_packages.get_mut(package_name)
+        else {
+            return false;
+        };
+        if !repository_packages.iter().any(&is_pinned) {
+            return false;
+        }
+        repository_packages.retain(&is_pinned);
+
+        for implementing_packages in self.virtual_package_to_implementing_packages.values_mut() {
+            implementing_packages
+                .retain(|package| package.name != package_name || is_pinned(package));
+        }
+        true
+    }
+
+    // Every version of the package in the index from highest to lowest.
+    pub(crate) fn get_versions(&self, package_name: &str) -> Vec<&str> {
+        let mut versions = self
+            .name_to_repository_packages
+            .get(package_name)
+            .map(|repository_packages| {
+                repository_packages
+                    .iter()
+                    .map(|repository_package| repository_package.version.as_str())
+                    .collect::<IndexSet<_>>()
+            })
+            .unwrap_or_default()
+            .into_iter()
+            .collect::<Vec<_>>();
+        versions.sort_by_cached_key(|version| {
+            std::cmp::Reverse(
+                debversion::Version::from_str(version)
+                    .expect("Packages should always have a valid debian version"),
+            )
+        });
+        versions
+    }
+
     pub(crate) fn get_providers(&self, package: &str) -> IndexSet<&str> {
         self.virtual_package_to_implementing_packages
             .get(package)
@@ -78,25 +252,60 @@ impl PackageIndex {
         package_names.extend(virtual_package_names.iter());
         package_names
     }
+
+    // Returns the names of the packages accepted by the filter sorted by name. Unlike
+    // `get_package_names` this doesn't include virtual packages.
+    pub(crate) fn find_package_names(&self, filter: impl Fn(&str) -> bool) -> Vec<&str> {
+        let mut package_names = self
+            .name_to_repository_packages
+            .keys()
+            .map(String::as_str)
+            .filter(|package_name| filter(package_name))
+            .collect::<Vec<_>>();
+        package_names.sort_unstable();
+        package_names
+    }
+
+    // Returns the names of the packages in the given task (see `RepositoryPackage::tasks`) sorted
+    // by name. Only the version that would be installed is checked since a package can be added to
+    // or removed from a task by an update.
+    pub(crate) fn get_task_packages(&self, task: &str) -> Vec<&str> {
+        let mut package_names = self
+            .name_to_repository_packages
+            .iter()
+            .filter(|(_, repository_packages)| {
+                repository_packages
+                    .iter()
+                    .any(|repository_package| repository_package.tasks().any(|other| other == task))
+            })
+            .filter(|(package_name, _)| {
+                self.get_highest_available_version(package_name)
+                    .is_some_and(|repository_package| {
+                        repository_package.tasks().any(|other| other == task)
+                    })
+            })
+            .map(|(package_name, _)| package_name.as_str())
+            .collect::<Vec<_>>();
+        package_names.sort_unstable();
+        package_names
+    }
 }
 
 #[cfg(test)]
 mod test {
     use crate::debian::RepositoryUri;
+    use crate::test_support;
 
     use super::*;
 
     fn default_test_repository_package() -> RepositoryPackage {
-        RepositoryPackage {
-            repository_uri: RepositoryUri::from("test-repository"),
-            name: "test-name".to_string(),
-            version: "test-version".to_string(),
-            filename: "test-filename".to_string(),
-            sha256sum: "test-sha256sum".to_string(),
-            depends: None,
-            pre_depends: None,
-            provides: None,
-        }
+        test_support::create_repository_package()
+            .name("test-name")
+            .version("test-version")
+            .repository_uri("test-repository")
+            .filename("test-filename")
+            .checksum("test-checksum")
+            .call()
     }
 
     fn create_repository_package(name: &str, version: &str) -> RepositoryPackage {
@@ -151,6 +360,244 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_get_task_packages() {
+        let mut package_index = PackageIndex::default();
+        for (name, version, task) in [
+            ("openssh-server", "1.0", Some("ubuntu-server, cloud-image")),
+            ("curl", "1.0", Some("ubuntu-server")),
+            ("vim", "1.0", Some("ubuntu-server")),
+            ("vim", "2.0", None),
+            ("firefox", "1.0", Some("ubuntu-desktop")),
+        ] {
+            package_index.add_package(RepositoryPackage {
+                task: task.map(ToString::to_string),
+                not_automatic: false,
+                suite: String::new(),
+                ..create_repository_package(name, version)
+            });
+        }
+
+        assert_eq!(
+            package_index.get_task_packages("ubuntu-server"),
+            vec!["curl", "openssh-server"]
+        );
+        assert_eq!(
+            package_index.get_task_packages("cloud-image"),
+            vec!["openssh-server"]
+        );
+        assert!(package_index
+            .get_task_packages("kubuntu-desktop")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_find_package_names() {
+        let mut package_index = PackageIndex::default();
+        for name in ["libicu74", "libicu-dev", "icu-devtools"] {
+            package_index.add_package(create_repository_package(name, "1.0"));
+        }
+        package_index.add_package(RepositoryPackage {
+            provides: Some("libicu-virtual".to_string()),
+            ..create_repository_package("libicu-provider", "1.0")
+        });
+
+        assert_eq!(
+            package_index.find_package_names(|package_name| package_name.starts_with("libicu")),
+            vec!["libicu-dev", "libicu-provider", "libicu74"]
+        );
+    }
+
+    #[test]
+    fn test_skip_phased_updates() {
+        let mut package_index = PackageIndex::default();
+        let phased_package = RepositoryPackage {
+            phased_update_percentage: Some(30),
+            ..create_repository_package("my-package", "2.0.0")
+        };
+        package_index.add_package(create_repository_package("my-package", "1.0.0"));
+        package_index.add_package(phased_package.clone());
+        package_index.add_package(RepositoryPackage {
+            phased_update_percentage: Some(30),
+            ..create_repository_package("my-phased-package", "1.0.0")
+        });
+
+        assert_eq!(
+            package_index.get_highest_available_version("my-package"),
+            Some(&phased_package)
+        );
+
+        package_index.set_skip_phased_updates(true);
+        let package = package_index
+            .get_highest_available_version("my-package")
+            .unwrap();
+        assert_eq!(package, &create_repository_package("my-package", "1.0.0"));
+        assert_eq!(
+            package_index.get_skipped_phased_update(package),
+            Some(&phased_package)
+        );
+        assert_eq!(
+            package_index
+                .get_highest_available_version("my-phased-package")
+                .map(|package| package.version.as_str()),
+            Some("1.0.0")
+        );
+    }
+
+    #[test]
+    fn test_duplicate_package_entries_keep_the_first() {
+        let mut package_index = PackageIndex::default();
+        let main_package = RepositoryPackage {
+            filename: "pool/main/m/my-package.deb".to_string(),
+            provides: Some("my-virtual-package".to_string()),
+            ..create_repository_package("my-package", "1.0.0")
+        };
+        let universe_package = RepositoryPackage {
+            filename: "pool/universe/m/my-package.deb".to_string(),
+            ..main_package.clone()
+        };
+        package_index.add_package(main_package.clone());
+        package_index.add_package(universe_package.clone());
+
+        assert_eq!(
+            package_index.get_highest_available_version("my-package"),
+            Some(&main_package)
+        );
+        assert_eq!(package_index.packages_indexed, 1);
+        assert_eq!(package_index.duplicate_packages, vec![universe_package]);
+        assert_eq!(
+            package_index.get_providers("my-virtual-package"),
+            IndexSet::from(["my-package"])
+        );
+    }
+
+    #[test]
+    fn test_retrieving_package_version_from_preferred_source() {
+        let public_package = RepositoryPackage {
+            repository_uri: RepositoryUri::from("http://public.example.com"),
+            ..create_repository_package("my-package", "1.0.0")
+        };
+        let private_package = RepositoryPackage {
+            repository_uri: RepositoryUri::from("http://private.example.com"),
+            ..create_repository_package("my-package", "1.0.0")
+        };
+
+        let mut package_index = PackageIndex::default();
+        package_index.add_package(public_package.clone());
+        package_index.add_package(private_package.clone());
+
+        // without a priority the result is ordered by repository uri
+        assert_eq!(
+            package_index.get_highest_available_version("my-package"),
+            Some(&private_package)
+        );
+
+        package_index.set_source_priority(vec![RepositoryUri::from("http://public.example.com")]);
+        assert_eq!(
+            package_index.get_highest_available_version("my-package"),
+            Some(&public_package)
+        );
+        assert_eq!(
+            package_index.get_alternate_sources(&public_package),
+            vec![&private_package.repository_uri]
+        );
+    }
+
+    #[test]
+    fn test_pin_package() {
+        let mut package_index = PackageIndex::default();
+        package_index.add_package(create_repository_package_with_provides(
+            "my-package",
+            "1.0.0",
+            "my-virtual-package",
+        ));
+        package_index.add_package(create_repository_package_with_provides(
+            "my-package",
+            "2.0.0",
+            "my-virtual-package",
+        ));
+
+        assert!(!package_index.pin_package("my-package", |package| package.version == "3.0.0"));
+        assert!(!package_index.pin_package("other-package", |_| true));
+        assert_eq!(
+            package_index.get_highest_available_version("my-package"),
+            Some(&create_repository_package_with_provides(
+                "my-package",
+                "2.0.0",
+                "my-virtual-package"
+            ))
+        );
+
+        assert!(package_index.pin_package("my-package", |package| package.version == "1.0.0"));
+        assert_eq!(
+            package_index.get_highest_available_version("my-package"),
+            Some(&create_repository_package_with_provides(
+                "my-package",
+                "1.0.0",
+                "my-virtual-package"
+            ))
+        );
+        assert_eq!(
+            package_index.virtual_package_to_implementing_packages["my-virtual-package"].len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_source_priority_does_not_override_higher_version() {
+        let public_package = RepositoryPackage {
+            repository_uri: RepositoryUri::from("http://public.example.com"),
+            ..create_repository_package("my-package", "2.0.0")
+        };
+        let private_package = RepositoryPackage {
+            repository_uri: RepositoryUri::from("http://private.example.com"),
+            ..create_repository_package("my-package", "1.0.0")
+        };
+
+        let mut package_index = PackageIndex::default();
+        package_index.add_package(public_package.clone());
+        package_index.add_package(private_package);
+        package_index.set_source_priority(vec![RepositoryUri::from("http://private.example.com")]);
+
+        assert_eq!(
+            package_index.get_highest_available_version("my-package"),
+            Some(&public_package)
+        );
+    }
+
+    #[test]
+    fn test_not_automatic_packages_are_only_preferred_when_requested() {
+        let release_package = create_repository_package("my-package", "1.0.0");
+        let backports_package = RepositoryPackage {
+            not_automatic: true,
+            ..create_repository_package("my-package", "2.0.0")
+        };
+        let backports_only_package = RepositoryPackage {
+            not_automatic: true,
+            ..create_repository_package("my-backported-package", "1.0.0")
+        };
+
+        let mut package_index = PackageIndex::default();
+        package_index.add_package(release_package.clone());
+        package_index.add_package(backports_package.clone());
+        package_index.add_package(backports_only_package.clone());
+
+        assert_eq!(
+            package_index.get_highest_available_version("my-package"),
+            Some(&release_package)
+        );
+        assert_eq!(
+            package_index.get_highest_available_version("my-backported-package"),
+            Some(&backports_only_package)
+        );
+
+        package_index.set_requested_packages(HashSet::from(["my-package".to_string()]));
+        assert_eq!(
+            package_index.get_highest_available_version("my-package"),
+            Some(&backports_package)
+        );
+    }
+
     #[test]
     fn test_get_virtual_package_providers() {
         let mut package_index = PackageIndex::default();
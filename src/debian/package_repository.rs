@@ -0,0 +1,80 @@
+use std::fmt::Debug;
+
+use crate::debian::{ArchitectureName, RepositoryUri};
+
+// The layout of an apt repository which decides where the Release file of a suite is published
+// and where the package indexes listed in it are found. Building the package index only goes
+// through this trait so supporting another apt-compatible layout (e.g.; a flat repository without
+// a `dists` directory) is a new implementation instead of more branches in `update_source`.
+pub(crate) trait PackageRepository: Debug + Send + Sync {
+    // The URI identifying the repository in logs, errors, and the package index.
+    fn uri(&self) -> &RepositoryUri;
+
+    // The URL of the directory holding the `InRelease` and `Release` files of a suite. The paths
+    // listed in the Release file are relative to this directory.
+    fn suite_url(&self, suite: &str) -> String;
+
+    // The path of a file in the package index directory of a component (e.g.; `Packages.gz`) as
+    // listed in the Release file.
+    fn package_index_path(
+        &self,
+        component: &str,
+        arch: &ArchitectureName,
+        file_name: &str,
+    ) -> String;
+}
+
+// The standard layout used by the Ubuntu and Debian archives where each suite is published under
+// `dists/<suite>` with a `<component>/binary-<arch>` directory for each package index.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct DistsRepository {
+    uri: RepositoryUri,
+}
+
+impl DistsRepository {
+    pub(crate) fn new(uri: RepositoryUri) -> Self {
+        DistsRepository { uri }
+    }
+}
+
+impl PackageRepository for DistsRepository {
+    fn uri(&self) -> &RepositoryUri {
+        &self.uri
+    }
+
+    fn suite_url(&self, suite: &str) -> String {
+        format!("{}/dists/{suite}", self.uri.as_str())
+    }
+
+    fn package_index_path(
+        &self,
+        component: &str,
+        arch: &ArchitectureName,
+        file_name: &str,
+    ) -> String {
+        format!("{component}/binary-{arch}/{file_name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dists_repository() {
+        let repository =
+            DistsRepository::new(RepositoryUri::from("http://archive.ubuntu.com/ubuntu"));
+        assert_eq!(
+            repository.uri().as_str(),
+            "http://archive.ubuntu.com/ubuntu"
+        );
+        assert_eq!(
+            repository.suite_url("noble-updates"),
+            "http://archive.ubuntu.com/ubuntu/dists/noble-updates"
+        );
+        assert_eq!(
+            repository.package_index_path("universe", &ArchitectureName::ARM_64, "Packages.xz"),
+            "universe/binary-arm64/Packages.xz"
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use std::fmt::{Display, Formatter};
+
+// The `Packages.diff/Index` file published next to a package index that lists the patches which
+// update an older copy of the uncompressed package index to the current one. All hashes are
+// SHA-256 since the Release file only lists the SHA-256 hash of the index.
+//
+// See: https://wiki.debian.org/DebianRepository/Format#diff_Indices
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub(crate) struct PdiffIndex {
+    // The hash of the uncompressed package index after every patch is applied.
+    pub(crate) current: String,
+    // The hash of the uncompressed package index each patch applies to, in the order they were
+    // published.
+    pub(crate) history: Vec<PdiffEntry>,
+    // The hash of each uncompressed patch.
+    pub(crate) patches: Vec<PdiffEntry>,
+    // Merged patches each update the package index from their history entry straight to the
+    // current one instead of to the next history entry.
+    pub(crate) merged: bool,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct PdiffEntry {
+    pub(crate) hash: String,
+    pub(crate) name: String,
+}
+
+impl PdiffIndex {
+    pub(crate) fn parse(contents: &str) -> Result<Self, PdiffError> {
+        let mut index = PdiffIndex::default();
+        let mut field = None;
+        for line in contents.lines() {
+            if line.starts_with([' ', '\t']) {
+                let mut values = line.split_whitespace();
+                let (Some(hash), Some(_size), Some(name)) =
+                    (values.next(), values.next(), values.next())
+                else {
+                    return Err(PdiffError::InvalidIndex(line.to_string()));
+                };
+                let entry = PdiffEntry {
+                    hash: hash.to_string(),
+                    name: name.to_string(),
+                };
+                match field {
+                    Some(SHA256_HISTORY_KEY) => index.history.push(entry),
+                    Some(SHA256_PATCHES_KEY) => index.patches.push(entry),
+                    _ => {}
+                }
+            } else if let Some((key, value)) = line.split_once(':') {
+                field = [SHA256_HISTORY_KEY, SHA256_PATCHES_KEY]
+                    .into_iter()
+                    .find(|known_key| *known_key == key);
+                if key == SHA256_CURRENT_KEY {
+                    index.current = value
+                        .split_whitespace()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string();
+                } else if key == PATCH_PRECEDENCE_KEY {
+                    index.merged = value.trim() == "merged";
+                }
+            }
+        }
+
+        if index.current.is_empty() {
+            return Err(PdiffError::InvalidIndex(format!(
+                "missing {SHA256_CURRENT_KEY}"
+            )));
+        }
+        Ok(index)
+    }
+
+    // Returns the patches, in the order they need to be applied, that update the package index with
+    // the given hash to the current one. Returns `None` when it's too old to be in the history.
+    pub(crate) fn patches_for(&self, hash: &str) -> Option<Vec<&PdiffEntry>> {
+        if hash == self.current {
+            return Some(vec![]);
+        }
+        let position = self.history.iter().position(|entry| entry.hash == hash)?;
+        let history_name = &self.history[position].name;
+        let patch_position = self
+            .patches
+            .iter()
+            .position(|patch| &patch.name == history_name)?;
+        if self.merged {
+            Some(vec![&self.patches[patch_position]])
+        } else {
+            Some(self.patches[patch_position..].iter().collect())
+        }
+    }
+}
+
+// Applies a patch in the `ed` format produced by `diff --ed`, which is the format used for
+// package index diffs. The commands are listed from the end of the file to the start so line
+// numbers don't need to be adjusted as they're applied.
+pub(crate) fn apply_ed_script(contents: &str, script: &str) -> Result<String, PdiffError> {
+    let mut lines = contents.lines().collect::<Vec<_>>();
+    let mut unescaped_lines = vec![];
+    let mut script_lines = script.lines();
+
+    while let Some(command) = script_lines.next() {
+        // a line containing a single dot can't be written in an ed script so it's added as `..`
+        // and the extra dot is removed with this substitution
+        if command == "s/.//" {
+            let last_line = unescaped_lines
+                .pop()
+                .ok_or_else(|| PdiffError::InvalidCommand(command.to_string()))?;
+            let line: &mut &str = &mut lines[last_line];
+            *line = line.strip_prefix('.').unwrap_or(line);
+            continue;
+        }
+
+        // the action is the last character which may not be ASCII in a malformed script
+        let (range, action) = command
+            .char_indices()
+            .last()
+            .map(|(index, _)| command.split_at(index))
+            .ok_or_else(|| PdiffError::InvalidCommand(command.to_string()))?;
+        let (start, end) =
+            parse_range(range).ok_or_else(|| PdiffError::InvalidCommand(command.to_string()))?;
+        let is_valid_range = match action {
+            "a" => start == end && end <= lines.len(),
+            _ => start >= 1 && start <= end && end <= lines.len(),
+        };
+        if !is_valid_range {
+            return Err(PdiffError::InvalidCommand(command.to_string()));
+        }
+
+        let mut read_lines = || {
+            let mut new_lines = vec![];
+            loop {
+                match script_lines.next() {
+                    Some(".") => return Ok(new_lines),
+                    Some(line) => new_lines.push(line),
+                    None => return Err(PdiffError::UnterminatedCommand(command.to_string())),
+                }
+            }
+        };
+
+        match action {
+            "a" => {
+                let new_lines = read_lines()?;
+                let inserted_at = end;
+                unescaped_lines = (inserted_at..inserted_at + new_lines.len()).collect();
+                lines.splice(end..end, new_lines);
+            }
+            "c" => {
+                let new_lines = read_lines()?;
+                let inserted_at = start - 1;
+                unescaped_lines = (inserted_at..inserted_at + new_lines.len()).collect();
+                lines.splice(start - 1..end, new_lines);
+            }
+            "d" => {
+                unescaped_lines = vec![];
+                lines.drain(start - 1..end);
+            }
+            _ => return Err(PdiffError::InvalidCommand(command.to_string())),
+        }
+    }
+
+    let mut patched = lines.join("\n");
+    if !patched.is_empty() {
+        patched.push('\n');
+    }
+    Ok(patched)
+}
+
+// Ranges are either a single line number or a `start,end` pair. Line 0 is only valid for appends
+// at the start of the file.
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    if let Some((start, end)) = range.split_once(',') {
+        Some((start.parse().ok()?, end.parse().ok()?))
+    } else {
+        let line = range.parse().ok()?;
+        Some((line, line))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum PdiffError {
+    InvalidIndex(String),
+    InvalidCommand(String),
+    UnterminatedCommand(String),
+}
+
+impl Display for PdiffError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PdiffError::InvalidIndex(value) => write!(f, "invalid diff index ({value})"),
+            PdiffError::InvalidCommand(command) => write!(f, "invalid diff command `{command}`"),
+            PdiffError::UnterminatedCommand(command) => {
+                write!(
+                    f,
+                    "diff command `{command}` is missing its terminating line"
+                )
+            }
+        }
+    }
+}
+
+const SHA256_CURRENT_KEY: &str = "SHA256-Current";
+const SHA256_HISTORY_KEY: &str = "SHA256-History";
+const SHA256_PATCHES_KEY: &str = "SHA256-Patches";
+const PATCH_PRECEDENCE_KEY: &str = "X-Patch-Precedence";
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    const PDIFF_INDEX: &str = indoc! { "
+        SHA256-Current: cccc 300
+        SHA256-History:
+         aaaa 100 2024-10-01-0200.00
+         bbbb 200 2024-10-01-0800.00
+        SHA256-Patches:
+         1111 10 2024-10-01-0200.00
+         2222 20 2024-10-01-0800.00
+        SHA256-Download:
+         3333 5 2024-10-01-0200.00.gz
+         4444 6 2024-10-01-0800.00.gz
+    " };
+
+    #[test]
+    fn test_parse_pdiff_index() {
+        let index = PdiffIndex::parse(PDIFF_INDEX).unwrap();
+        assert_eq!(index.current, "cccc");
+        assert_eq!(
+            index.history,
+            vec![
+                PdiffEntry {
+                    hash: "aaaa".to_string(),
+                    name: "2024-10-01-0200.00".to_string()
+                },
+                PdiffEntry {
+                    hash: "bbbb".to_string(),
+                    name: "2024-10-01-0800.00".to_string()
+                }
+            ]
+        );
+        assert_eq!(index.patches.len(), 2);
+        assert_eq!(index.patches[1].hash, "2222");
+        assert!(!index.merged);
+    }
+
+    #[test]
+    fn test_parse_pdiff_index_without_current() {
+        assert!(matches!(
+            PdiffIndex::parse("SHA256-History:\n aaaa 100 2024-10-01-0200.00\n"),
+            Err(PdiffError::InvalidIndex(_))
+        ));
+    }
+
+    #[test]
+    fn test_patches_for() {
+        let index = PdiffIndex::parse(PDIFF_INDEX).unwrap();
+        let names = |patches: Option<Vec<&PdiffEntry>>| {
+            patches.map(|patches| {
+                patches
+                    .into_iter()
+                    .map(|patch| patch.name.clone())
+                    .collect::<Vec<_>>()
+            })
+        };
+        assert_eq!(
+            names(index.patches_for("aaaa")),
+            Some(vec![
+                "2024-10-01-0200.00".to_string(),
+                "2024-10-01-0800.00".to_string()
+            ])
+        );
+        assert_eq!(
+            names(index.patches_for("bbbb")),
+            Some(vec!["2024-10-01-0800.00".to_string()])
+        );
+        assert_eq!(names(index.patches_for("cccc")), Some(vec![]));
+        assert_eq!(names(index.patches_for("dddd")), None);
+    }
+
+    #[test]
+    fn test_merged_patches_for() {
+        let index =
+            PdiffIndex::parse(&format!("X-Patch-Precedence: merged\n{PDIFF_INDEX}")).unwrap();
+        assert!(index.merged);
+        assert_eq!(
+            index
+                .patches_for("aaaa")
+                .unwrap()
+                .into_iter()
+                .map(|patch| patch.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["2024-10-01-0200.00"]
+        );
+    }
+
+    #[test]
+    fn test_apply_ed_script() {
+        let contents = "one\ntwo\nthree\nfour\nfive\n";
+        let script = indoc! { "
+            5a
+            six
+            .
+            3,4c
+            THREE
+            .
+            1d
+            0a
+            zero
+            .
+        " };
+        assert_eq!(
+            apply_ed_script(contents, script).unwrap(),
+            "zero\ntwo\nTHREE\nfive\nsix\n"
+        );
+    }
+
+    #[test]
+    fn test_apply_ed_script_with_escaped_dot() {
+        let script = "1a\n..\n.\ns/.//\n";
+        assert_eq!(apply_ed_script("one\n", script).unwrap(), "one\n.\n");
+    }
+
+    #[test]
+    fn test_apply_invalid_ed_script() {
+        assert!(matches!(
+            apply_ed_script("one\n", "9d\n"),
+            Err(PdiffError::InvalidCommand(_))
+        ));
+        assert!(matches!(
+            apply_ed_script("one\n", "1a\ntwo\n"),
+            Err(PdiffError::UnterminatedCommand(_))
+        ));
+        assert!(matches!(
+            apply_ed_script("one\n", "1x\n"),
+            Err(PdiffError::InvalidCommand(_))
+        ));
+        assert!(matches!(
+            apply_ed_script("one\n", "1é\n"),
+            Err(PdiffError::InvalidCommand(_))
+        ));
+        assert!(matches!(
+            apply_ed_script("one\n", "\n"),
+            Err(PdiffError::InvalidCommand(_))
+        ));
+    }
+}
@@ -1,19 +1,39 @@
-use crate::debian::RepositoryUri;
+use crate::debian::{ChecksumAlgorithm, RepositoryUri};
 use bullet_stream::style;
 use rayon::iter::{IntoParallelIterator, ParallelBridge, ParallelIterator};
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter};
 
-#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[derive(Debug, Eq, PartialEq, Clone, Hash, Serialize, Deserialize)]
 pub(crate) struct RepositoryPackage {
     pub(crate) repository_uri: RepositoryUri,
     pub(crate) name: String,
     pub(crate) version: String,
     pub(crate) filename: String,
-    pub(crate) sha256sum: String,
+    // the hash of the package archive using `checksum_algorithm`, which is only SHA1 or MD5 when
+    // the source allows weak hashes and the package index doesn't list a SHA256 hash
+    #[serde(alias = "sha256sum")]
+    pub(crate) checksum: String,
+    pub(crate) checksum_algorithm: ChecksumAlgorithm,
+    // Set from the source's `download_url` when packages aren't downloaded from the repository uri
+    // (see `build_download_url`).
+    pub(crate) download_url_template: Option<String>,
     pub(crate) depends: Option<String>,
     pub(crate) pre_depends: Option<String>,
     pub(crate) provides: Option<String>,
+    pub(crate) size: Option<u64>,
+    pub(crate) section: Option<String>,
+    pub(crate) phased_update_percentage: Option<u8>,
+    pub(crate) task: Option<String>,
+    // Set when the Release file of the suite the package is from sets `NotAutomatic` (e.g.;
+    // backports) so the package isn't chosen over the versions from other suites.
+    #[serde(default)]
+    pub(crate) not_automatic: bool,
+    // The suite of the package index the package was read from (e.g.; `noble-updates`), which is
+    // empty for packages that weren't read from a package index.
+    #[serde(default)]
+    pub(crate) suite: String,
 }
 
 impl RepositoryPackage {
@@ -23,6 +43,7 @@ impl RepositoryPackage {
     pub(crate) fn parse_parallel(
         repository_uri: RepositoryUri,
         contents: &str,
+        allow_weak_hashes: bool,
     ) -> Result<RepositoryPackage, ParseRepositoryPackageError> {
         let values = contents
             .lines()
@@ -34,9 +55,15 @@ impl RepositoryPackage {
                     VERSION_KEY,
                     FILENAME_KEY,
                     SHA256_KEY,
+                    SHA1_KEY,
+                    MD5SUM_KEY,
                     DEPENDS_KEY,
                     PRE_DEPENDS_KEY,
                     PROVIDES_KEY,
+                    SIZE_KEY,
+                    SECTION_KEY,
+                    PHASED_UPDATE_PERCENTAGE_KEY,
+                    TASK_KEY,
                 ]
                 .iter()
                 .any(|key| line.starts_with(key))
@@ -49,6 +76,14 @@ impl RepositoryPackage {
             .map(|v| v.trim().to_string())
             .ok_or(ParseRepositoryPackageError::MissingPackageName)?;
 
+        let (checksum_algorithm, checksum) = ChecksumAlgorithm::allowed(allow_weak_hashes)
+            .find_map(|algorithm| {
+                values
+                    .get(algorithm.package_field())
+                    .map(|v| (algorithm, v.trim().to_string()))
+            })
+            .ok_or_else(|| ParseRepositoryPackageError::MissingSha256(package_name.clone()))?;
+
         Ok(RepositoryPackage {
             repository_uri,
             name: package_name.clone(),
@@ -64,16 +99,40 @@ impl RepositoryPackage {
                 .ok_or(ParseRepositoryPackageError::MissingFilename(
                     package_name.clone(),
                 ))?,
-            sha256sum: values
-                .get(SHA256_KEY)
-                .map(|v| v.trim().to_string())
-                .ok_or(ParseRepositoryPackageError::MissingSha256(package_name))?,
+            checksum,
+            checksum_algorithm,
+            download_url_template: None,
             depends: values.get(DEPENDS_KEY).map(|v| v.trim().to_string()),
             pre_depends: values.get(PRE_DEPENDS_KEY).map(|v| v.trim().to_string()),
             provides: values.get(PROVIDES_KEY).map(|v| v.trim().to_string()),
+            size: values.get(SIZE_KEY).and_then(|v| v.trim().parse().ok()),
+            section: values.get(SECTION_KEY).map(|v| v.trim().to_string()),
+            phased_update_percentage: values
+                .get(PHASED_UPDATE_PERCENTAGE_KEY)
+                .and_then(|v| v.trim().parse().ok()),
+            task: values.get(TASK_KEY).map(|v| v.trim().to_string()),
+            not_automatic: false,
+            suite: String::new(),
         })
     }
 
+    // Reads only the package name and the virtual packages it provides from an entry in a Packages
+    // file. This is used to find the entries worth parsing without parsing every entry.
+    pub(crate) fn parse_names(contents: &str) -> (Option<&str>, Vec<&str>) {
+        let mut package_name = None;
+        let mut provides = vec![];
+        for line in contents.lines() {
+            if let Some((key, value)) = line.split_once(':') {
+                if key == PACKAGE_KEY {
+                    package_name = Some(value.trim());
+                } else if key == PROVIDES_KEY {
+                    provides.extend(provided_names(value));
+                }
+            }
+        }
+        (package_name, provides)
+    }
+
     // NOTE: This list deliberately ignores alternative dependencies specified by "|"
     //       as described by the debian package spec for relationship fields
     //       https://www.debian.org/doc/debian-policy/ch-relationships#syntax-of-relationship-fields
@@ -104,22 +163,52 @@ impl RepositoryPackage {
         results
     }
 
+    // Metapackages only depend on other packages (e.g.; `ubuntu-restricted-addons`) and are filed
+    // under the `metapackages` section, which can be prefixed by the component (e.g.;
+    // `universe/metapackages`).
+    pub(crate) fn is_metapackage(&self) -> bool {
+        self.section
+            .as_deref()
+            .and_then(|section| section.rsplit('/').next())
+            .is_some_and(|section| section == "metapackages")
+    }
+
+    // Ubuntu releases stable updates to a percentage of machines at a time, which is listed in the
+    // `Phased-Update-Percentage` field until the update has been released to every machine.
+    // See: https://wiki.ubuntu.com/PhasedUpdates
+    pub(crate) fn is_phased_update(&self) -> bool {
+        self.phased_update_percentage
+            .is_some_and(|percentage| percentage < 100)
+    }
+
+    // Ubuntu groups packages into tasks (e.g.; `ubuntu-server`) that are listed in the `Task` field
+    // of each package in the group, separated by commas.
+    pub(crate) fn tasks(&self) -> impl Iterator<Item = &str> {
+        self.task
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|task| !task.is_empty())
+    }
+
     pub(crate) fn provides_dependencies(&self) -> HashSet<&str> {
-        let mut results = HashSet::new();
-        if let Some(provides) = &self.provides {
-            for provide in provides.split(',') {
-                if let Some(name) = provide.trim().split(' ').next() {
-                    let name = name.trim();
-                    if !name.is_empty() {
-                        results.insert(name);
-                    }
-                }
-            }
-        }
-        results
+        self.provides
+            .as_deref()
+            .map(|provides| provided_names(provides).collect())
+            .unwrap_or_default()
     }
 }
 
+// virtual packages are separated by commas and may include a version (e.g.; `foo (= 1.0)`)
+fn provided_names(provides: &str) -> impl Iterator<Item = &str> {
+    provides
+        .split(',')
+        .filter_map(|provide| provide.trim().split(' ').next())
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
 #[derive(Debug)]
 pub(crate) enum ParseRepositoryPackageError {
     MissingPackageName,
@@ -170,15 +259,24 @@ static PACKAGE_KEY: &str = "Package";
 static VERSION_KEY: &str = "Version";
 static FILENAME_KEY: &str = "Filename";
 static SHA256_KEY: &str = "SHA256";
+static SHA1_KEY: &str = "SHA1";
+static MD5SUM_KEY: &str = "MD5sum";
 static DEPENDS_KEY: &str = "Depends";
 static PRE_DEPENDS_KEY: &str = "Pre-Depends";
 static PROVIDES_KEY: &str = "Provides";
+static SIZE_KEY: &str = "Size";
+static SECTION_KEY: &str = "Section";
+static PHASED_UPDATE_PERCENTAGE_KEY: &str = "Phased-Update-Percentage";
+static TASK_KEY: &str = "Task";
 
 #[cfg(test)]
 mod test {
     use std::collections::HashSet;
 
-    use crate::debian::{RepositoryPackage, RepositoryUri};
+    use crate::debian::{
+        ChecksumAlgorithm, ParseRepositoryPackageError, RepositoryPackage, RepositoryUri,
+    };
+    use crate::test_support;
 
     fn create_repository_package(
         depends: Option<&str>,
@@ -186,14 +284,16 @@ mod test {
         provides: Option<&str>,
     ) -> RepositoryPackage {
         RepositoryPackage {
-            repository_uri: RepositoryUri::from("test-repository"),
-            name: "test-name".to_string(),
-            version: "test-version".to_string(),
-            filename: "test-filename".to_string(),
-            sha256sum: "test-sha256sum".to_string(),
             depends: depends.map(ToString::to_string),
             pre_depends: pre_depends.map(ToString::to_string),
             provides: provides.map(ToString::to_string),
+            ..test_support::create_repository_package()
+                .name("test-name")
+                .version("test-version")
+                .repository_uri("test-repository")
+                .filename("test-filename")
+                .checksum("test-checksum")
+                .call()
         }
     }
 
@@ -267,4 +367,134 @@ mod test {
             HashSet::from([])
         );
     }
+
+    #[test]
+    fn test_parse_size() {
+        let repository_package = RepositoryPackage::parse_parallel(
+            RepositoryUri::from("test-repository"),
+            "Package: curl\nVersion: 8.5.0-2ubuntu10.6\nInstalled-Size: 534\n\
+             Filename: pool/main/c/curl/curl_8.5.0-2ubuntu10.6_amd64.deb\nSize: 226672\nSHA256: abc123",
+            false,
+        )
+        .unwrap();
+        assert_eq!(repository_package.size, Some(226_672));
+    }
+
+    #[test]
+    fn test_parse_metapackage() {
+        let repository_package = RepositoryPackage::parse_parallel(
+            RepositoryUri::from("test-repository"),
+            "Package: ubuntu-restricted-addons\nVersion: 28\nSection: multiverse/metapackages\n\
+             Filename: pool/multiverse/u/ubuntu-restricted-addons/ubuntu-restricted-addons_28_amd64.deb\n\
+             Depends: gstreamer1.0-libav, gstreamer1.0-plugins-ugly\nSHA256: abc123",
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            repository_package.section,
+            Some("multiverse/metapackages".to_string())
+        );
+        assert!(repository_package.is_metapackage());
+
+        let repository_package = RepositoryPackage {
+            section: Some("net".to_string()),
+            ..repository_package
+        };
+        assert!(!repository_package.is_metapackage());
+    }
+
+    #[test]
+    fn test_parse_phased_update() {
+        let repository_package = RepositoryPackage::parse_parallel(
+            RepositoryUri::from("test-repository"),
+            "Package: curl\nVersion: 8.5.0-2ubuntu10.6\nPhased-Update-Percentage: 30\n\
+             Filename: pool/main/c/curl/curl_8.5.0-2ubuntu10.6_amd64.deb\nSHA256: abc123",
+            false,
+        )
+        .unwrap();
+        assert_eq!(repository_package.phased_update_percentage, Some(30));
+        assert!(repository_package.is_phased_update());
+
+        let repository_package = RepositoryPackage {
+            phased_update_percentage: None,
+            ..repository_package
+        };
+        assert!(!repository_package.is_phased_update());
+    }
+
+    #[test]
+    fn test_parse_task() {
+        let repository_package = RepositoryPackage::parse_parallel(
+            RepositoryUri::from("test-repository"),
+            "Package: openssh-server\nVersion: 1:9.6p1-3ubuntu13\nTask: ubuntu-server, cloud-image\n\
+             Filename: pool/main/o/openssh/openssh-server_9.6p1-3ubuntu13_amd64.deb\nSHA256: abc123",
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            repository_package.tasks().collect::<Vec<_>>(),
+            vec!["ubuntu-server", "cloud-image"]
+        );
+
+        let repository_package = RepositoryPackage {
+            task: None,
+            not_automatic: false,
+            suite: String::new(),
+            ..repository_package
+        };
+        assert_eq!(repository_package.tasks().count(), 0);
+    }
+
+    #[test]
+    fn test_parse_weak_hashes() {
+        let contents = "Package: curl\nVersion: 7.68.0\n\
+             Filename: pool/main/c/curl/curl_7.68.0_amd64.deb\nMD5sum: abc123\nSHA1: def456";
+
+        assert!(matches!(
+            RepositoryPackage::parse_parallel(
+                RepositoryUri::from("test-repository"),
+                contents,
+                false
+            ),
+            Err(ParseRepositoryPackageError::MissingSha256(package_name)) if package_name == "curl"
+        ));
+
+        let repository_package = RepositoryPackage::parse_parallel(
+            RepositoryUri::from("test-repository"),
+            contents,
+            true,
+        )
+        .unwrap();
+        assert_eq!(repository_package.checksum, "def456");
+        assert_eq!(
+            repository_package.checksum_algorithm,
+            ChecksumAlgorithm::Sha1
+        );
+
+        let repository_package = RepositoryPackage::parse_parallel(
+            RepositoryUri::from("test-repository"),
+            &format!("{contents}\nSHA256: 789abc"),
+            true,
+        )
+        .unwrap();
+        assert_eq!(repository_package.checksum, "789abc");
+        assert_eq!(
+            repository_package.checksum_algorithm,
+            ChecksumAlgorithm::Sha256
+        );
+    }
+
+    #[test]
+    fn test_parse_names() {
+        assert_eq!(
+            RepositoryPackage::parse_names(
+                "Package: mawk\nVersion: 1.3.4\nProvides: awk (= 1.3.4), mawk-awk\nSHA256: abc123"
+            ),
+            (Some("mawk"), vec!["awk", "mawk-awk"])
+        );
+        assert_eq!(
+            RepositoryPackage::parse_names("Version: 1.3.4"),
+            (None, vec![])
+        );
+    }
 }
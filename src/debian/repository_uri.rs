@@ -1,4 +1,6 @@
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub(crate) struct RepositoryUri(pub String);
 
 impl RepositoryUri {
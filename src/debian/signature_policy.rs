@@ -0,0 +1,84 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+// How the release files of a source are verified. The sources for the distribution always require
+// a valid signature but additional sources can relax this for repositories that aren't signed.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum SignaturePolicy {
+    // The release file must be signed by the configured key.
+    #[default]
+    Required,
+    // The release file is verified when it's signed and a key is configured, otherwise a warning is
+    // logged and it's used as-is. A signature that doesn't verify is still an error.
+    Optional,
+    // The release file is never verified.
+    Disabled,
+}
+
+impl SignaturePolicy {
+    pub(crate) const VALUES: [SignaturePolicy; 3] = [
+        SignaturePolicy::Required,
+        SignaturePolicy::Optional,
+        SignaturePolicy::Disabled,
+    ];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            SignaturePolicy::Required => "required",
+            SignaturePolicy::Optional => "optional",
+            SignaturePolicy::Disabled => "disabled",
+        }
+    }
+}
+
+impl Display for SignaturePolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for SignaturePolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        SignaturePolicy::VALUES
+            .into_iter()
+            .find(|policy| policy.as_str() == value)
+            .ok_or_else(|| value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(
+            SignaturePolicy::from_str("required"),
+            Ok(SignaturePolicy::Required)
+        );
+        assert_eq!(
+            SignaturePolicy::from_str("optional"),
+            Ok(SignaturePolicy::Optional)
+        );
+        assert_eq!(
+            SignaturePolicy::from_str("disabled"),
+            Ok(SignaturePolicy::Disabled)
+        );
+        assert_eq!(
+            SignaturePolicy::from_str("Required"),
+            Err("Required".to_string())
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        for policy in SignaturePolicy::VALUES {
+            assert_eq!(SignaturePolicy::from_str(&policy.to_string()), Ok(policy));
+        }
+    }
+}
@@ -1,20 +1,33 @@
-use crate::debian::{ArchitectureName, RepositoryUri};
+use std::sync::Arc;
+
+use crate::debian::{
+    ArchitectureName, DistsRepository, PackageRepository, RepositoryUri, SignaturePolicy,
+};
 
 // NOTE: This is meant to be similar in structure to the Deb822 Source Format described at
 //       https://manpages.ubuntu.com/manpages/jammy/man5/sources.list.5.html#deb822-style%20format.
 //
 //       Some differences between this and documented Deb822 Source Format are:
 //       - Type is omitted because we aren't supporting building from source (deb-src), only pre-compiled binaries (deb)
-//       - Only one URI is allowed even though the source format says URIs is an array
+//       - Only the first URI is used to identify the source, any others are mirrors that requests
+//         fall back to when the first URI fails
 //       - Enabled is always true, so it's omitted here
-//       - Only the Signed-By option is supported
+//       - Only the Signed-By option is supported along with a signature policy that takes the place
+//         of the Trusted option
 #[derive(Debug)]
 pub(crate) struct Source {
     pub(crate) arch: ArchitectureName,
     pub(crate) components: Vec<String>,
-    pub(crate) signed_by: String,
+    pub(crate) signed_by: Option<String>,
+    pub(crate) signature_policy: SignaturePolicy,
+    // SHA1 or MD5 hashes are used when the Release file doesn't list SHA256 hashes
+    pub(crate) allow_weak_hashes: bool,
     pub(crate) suites: Vec<String>,
     pub(crate) uri: RepositoryUri,
+    pub(crate) mirrors: Vec<RepositoryUri>,
+    // Packages are downloaded from this url template instead of the uri when it's set but are still
+    // verified with the hashes from the package index.
+    pub(crate) download_url: Option<String>,
 }
 
 impl Source {
@@ -32,10 +45,19 @@ impl Source {
     {
         Source {
             components: components.into_iter().map(Into::into).collect(),
-            signed_by: signed_by.into(),
+            signed_by: Some(signed_by.into()),
+            signature_policy: SignaturePolicy::Required,
+            allow_weak_hashes: false,
             suites: suites.into_iter().map(Into::into).collect(),
             uri: uri.into(),
+            mirrors: vec![],
+            download_url: None,
             arch,
         }
     }
+
+    // Every source currently uses the standard `dists` layout.
+    pub(crate) fn repository(&self) -> Arc<dyn PackageRepository> {
+        Arc::new(DistsRepository::new(self.uri.clone()))
+    }
 }
@@ -1,86 +1,168 @@
-use crate::config::RequestedPackage;
-use crate::debian::{PackageIndex, RepositoryPackage};
+use crate::config::{PackageGlob, RequestedPackage, VersionConstraint};
+use crate::create_package_index::ForeignPackageIndex;
+use crate::debian::{ArchitectureName, Distro, PackageIndex, RepositoryPackage, RepositoryUri};
+use crate::lockfile::{LockedPackage, Lockfile, IGNORE_LOCKFILE_ENV_VAR, LOCKFILE_NAME};
 use crate::{BuildpackResult, DebianPackagesBuildpackError};
 use apt_parser::Control;
 use bullet_stream::state::Bullet;
 use bullet_stream::{style, Print};
 use edit_distance::edit_distance;
 use indexmap::IndexSet;
-use std::collections::HashSet;
-use std::fmt::{Display, Formatter};
-use std::fs::read_to_string;
+use libcnb::Env;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::fs::{read, read_to_string};
 use std::io::Stdout;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::time::SystemTimeError;
 
+// A requested package that adds more dependencies than this is most likely a mistake (e.g.; a
+// desktop meta-package) so the build is stopped unless the limit is raised in the configuration.
+pub(crate) const DEFAULT_MAX_DEPENDENCIES: usize = 500;
+
+// Where the packages installed in the base image are read from. Some minimal images don't ship a
+// dpkg database so a different status file can be provided with this variable.
+pub(crate) const DPKG_STATUS_ENV_VAR: &str = "BP_DEB_PACKAGES_DPKG_STATUS";
+const DEFAULT_DPKG_STATUS_PATH: &str = "/var/lib/dpkg/status";
+
 const SPECIAL_CASE_MAP: &[(&str, &[&str])] = &[
     ("portaudio19-dev", &["libportaudio2"]),
     ("7zip", &["7zip-standalone"]),
     ("enchant-2", &["libenchant-2-dev"]),
-    ("hunspell", &["libhunspell-dev", "libncurses5-dev", "libreadline-dev"]),
+    (
+        "hunspell",
+        &["libhunspell-dev", "libncurses5-dev", "libreadline-dev"],
+    ),
 ];
 
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
 pub(crate) fn determine_packages_to_install(
     package_index: &PackageIndex,
+    architecture: &ArchitectureName,
+    foreign_package_indexes: &[ForeignPackageIndex],
     requested_packages: IndexSet<RequestedPackage>,
+    excluded_packages: &IndexSet<PackageGlob>,
+    max_dependencies: usize,
+    resolve_virtual_packages: bool,
+    env: &Env,
     mut log: Print<Bullet<Stdout>>,
-) -> BuildpackResult<(Vec<RepositoryPackage>, Vec<RequestedPackage>, Print<Bullet<Stdout>>)> {
+) -> BuildpackResult<(
+    Vec<RepositoryPackage>,
+    Vec<RequestedPackage>,
+    BTreeMap<String, Vec<String>>,
+    Print<Bullet<Stdout>>,
+)> {
     log = log.h2("Determining packages to install");
 
-    let sub_bullet = log.bullet("Collecting system install information");
-    let system_packages_path = PathBuf::from("/var/lib/dpkg/status");
-    let system_packages = read_to_string(&system_packages_path)
-        .map_err(|e| {
-            DeterminePackagesToInstallError::ReadSystemPackages(system_packages_path.clone(), e)
-        })?
-        .trim()
-        .split("\n\n")
-        .map(|control_data| {
-            Control::from(control_data)
-                .map_err(|e| {
-                    DeterminePackagesToInstallError::ParseSystemPackage(
-                        system_packages_path.clone(),
-                        control_data.to_string(),
-                        e,
-                    )
-                })
-                .map(SystemPackage::from)
-        })
-        .collect::<Result<IndexSet<_>, _>>()?;
+    let mut sub_bullet = log.bullet("Collecting system install information");
+    let (system_packages_path, is_configured) = dpkg_status_path(env);
+    let system_packages = if let Some(system_packages) =
+        read_system_packages(&system_packages_path, is_configured)?
+    {
+        system_packages
+    } else {
+        sub_bullet = sub_bullet.warning(format!(
+            "No dpkg status file found at {path} so no packages are considered installed in \
+                the base image. Set {env_var} to read the installed packages from another path.",
+            path = style::value(system_packages_path.to_string_lossy()),
+            env_var = style::value(DPKG_STATUS_ENV_VAR)
+        ));
+        IndexSet::new()
+    };
     log = sub_bullet.done();
 
-    let mut packages_marked_for_install = IndexSet::new();
-    let mut skipped_packages = Vec::new();
+    // packages are resolved against the package index for their architecture and only count as
+    // installed when the base image has them for that architecture (e.g.; `libfoo:i386` doesn't
+    // satisfy a request for `libfoo`)
+    let native_system_packages = system_packages_for_architecture(&system_packages, architecture);
+    let foreign_system_packages = foreign_package_indexes
+        .iter()
+        .map(|foreign_package_index| {
+            system_packages_for_architecture(&system_packages, &foreign_package_index.architecture)
+        })
+        .collect::<Vec<_>>();
 
-    let special_case_map: HashMap<&str, Vec<&str>> = SPECIAL_CASE_MAP
+    let mut resolver = PackageResolver::new(
+        package_index,
+        &native_system_packages,
+        excluded_packages,
+        max_dependencies,
+        resolve_virtual_packages,
+    );
+    let mut foreign_resolvers = foreign_package_indexes
         .iter()
-        .cloned()
-        .map(|(special, additionals)| (special, additionals.to_vec()))
-        .collect();
+        .zip(&foreign_system_packages)
+        .map(|(foreign_package_index, foreign_system_packages)| {
+            PackageResolver::new(
+                &foreign_package_index.package_index,
+                foreign_system_packages,
+                excluded_packages,
+                max_dependencies,
+                resolve_virtual_packages,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut skipped_packages = Vec::new();
+    let mut dependency_paths = BTreeMap::new();
 
     for requested_package in requested_packages {
+        let resolver = match &requested_package.architecture {
+            Some(architecture) => foreign_package_indexes
+                .iter()
+                .position(|foreign_package_index| {
+                    &foreign_package_index.architecture == architecture
+                })
+                .and_then(|position| foreign_resolvers.get_mut(position))
+                .expect("A package index should be created for every foreign architecture"),
+            None => &mut resolver,
+        };
+
         let mut notification_log = log.bullet(format!(
             "Determining install requirements for requested package {package}",
-            package = style::value(requested_package.name.as_str())
+            package = style::value(requested_package.qualified_name())
         ));
-        let mut visit_stack = IndexSet::new();
-        let mut package_notifications = IndexSet::new();
 
-        if !visit(
-            requested_package.name.as_str(),
-            requested_package.skip_dependencies,
-            requested_package.force,
-            &system_packages,
-            package_index,
-            &mut packages_marked_for_install,
-            &mut visit_stack,
-            &mut package_notifications,
-            &special_case_map,
-        )? {
-            skipped_packages.push(requested_package.clone());
+        let Some(resolved_package) = resolver.resolve(&requested_package)? else {
+            log = notification_log
+                .warning(format!(
+                    "Skipping optional package {package} since it isn't available from any of the \
+                    configured sources",
+                    package = style::value(requested_package.qualified_name())
+                ))
+                .done();
+            continue;
         };
 
+        if !resolved_package.marked_for_install {
+            skipped_packages.push(requested_package.clone());
+        }
+
+        let package_notifications = resolved_package.package_notifications;
+
+        // keep the first chain a package was added through, which is the one reported in the log
+        for package_notification in &package_notifications {
+            if let PackageNotification::Added {
+                repository_package,
+                dependency_path,
+                ..
+            } = package_notification
+            {
+                let package_name = match &requested_package.architecture {
+                    Some(architecture) => qualified_package_name(repository_package, architecture),
+                    None => repository_package.name.clone(),
+                };
+                dependency_paths
+                    .entry(package_name)
+                    .or_insert_with(|| dependency_path.clone());
+            }
+        }
+
+        let metapackage_expansion = describe_metapackage_expansion(&package_notifications);
+
         if package_notifications.is_empty() {
             notification_log = notification_log.sub_bullet("Nothing to add");
         } else {
@@ -89,15 +171,513 @@ pub(crate) fn determine_packages_to_install(
             }
         }
 
+        if let Some(metapackage_expansion) = metapackage_expansion {
+            notification_log = notification_log.sub_bullet(metapackage_expansion);
+        }
+
         log = notification_log.done();
     }
 
-    let packages_to_install = packages_marked_for_install
-        .into_iter()
-        .map(|package_marked_for_install| package_marked_for_install.repository_package)
-        .collect();
+    let mut packages_to_install = resolver.into_packages();
+    for (foreign_package_index, foreign_resolver) in
+        foreign_package_indexes.iter().zip(foreign_resolvers)
+    {
+        add_foreign_packages(
+            &mut packages_to_install,
+            &foreign_package_index.architecture,
+            foreign_resolver.into_packages(),
+        );
+    }
+
+    Ok((packages_to_install, skipped_packages, dependency_paths, log))
+}
+
+// The packages that requested packages and their dependencies can be resolved to. During a build
+// this is the package index created from the configured sources but resolution only needs these
+// lookups so it can run against any set of packages (e.g.; one built in memory for a test).
+pub(crate) trait PackageProvider {
+    fn get_highest_available_version(&self, package_name: &str) -> Option<&RepositoryPackage>;
+
+    fn get_providers(&self, package: &str) -> IndexSet<&str>;
+
+    fn get_alternate_sources(&self, repository_package: &RepositoryPackage) -> Vec<&RepositoryUri>;
+
+    fn get_skipped_phased_update(
+        &self,
+        repository_package: &RepositoryPackage,
+    ) -> Option<&RepositoryPackage>;
+
+    fn get_package_names(&self) -> IndexSet<&str>;
+}
+
+impl PackageProvider for PackageIndex {
+    fn get_highest_available_version(&self, package_name: &str) -> Option<&RepositoryPackage> {
+        PackageIndex::get_highest_available_version(self, package_name)
+    }
+
+    fn get_providers(&self, package: &str) -> IndexSet<&str> {
+        PackageIndex::get_providers(self, package)
+    }
+
+    fn get_alternate_sources(&self, repository_package: &RepositoryPackage) -> Vec<&RepositoryUri> {
+        PackageIndex::get_alternate_sources(self, repository_package)
+    }
+
+    fn get_skipped_phased_update(
+        &self,
+        repository_package: &RepositoryPackage,
+    ) -> Option<&RepositoryPackage> {
+        PackageIndex::get_skipped_phased_update(self, repository_package)
+    }
+
+    fn get_package_names(&self) -> IndexSet<&str> {
+        PackageIndex::get_package_names(self)
+    }
+}
+
+// The packages that are already installed and won't be installed again unless they're forced.
+// During a build these are read from the dpkg status file of the base image.
+pub(crate) trait SystemPackageProvider {
+    fn get_system_package(&self, package_name: &str) -> Option<&SystemPackage>;
+}
+
+impl SystemPackageProvider for IndexSet<SystemPackage> {
+    fn get_system_package(&self, package_name: &str) -> Option<&SystemPackage> {
+        self.iter()
+            .find(|system_package| system_package.package_name == package_name)
+    }
+}
+
+// Resolves requested packages and their dependencies against the given providers. Nothing is read
+// from the environment or logged here so the same resolution can be reused outside of a build.
+// Packages marked for install by one request are shared with the requests resolved after it.
+pub(crate) struct PackageResolver<'a> {
+    package_provider: &'a dyn PackageProvider,
+    system_package_provider: &'a dyn SystemPackageProvider,
+    excluded_packages: &'a IndexSet<PackageGlob>,
+    max_dependencies: usize,
+    resolve_virtual_packages: bool,
+    special_case_map: HashMap<&'static str, Vec<&'static str>>,
+    packages_marked_for_install: IndexSet<PackageMarkedForInstall>,
+}
+
+#[derive(Debug)]
+pub(crate) struct ResolvedPackage {
+    // false when the requested package wasn't marked for install (e.g.; it's already installed)
+    pub(crate) marked_for_install: bool,
+    pub(crate) package_notifications: IndexSet<PackageNotification>,
+}
+
+impl<'a> PackageResolver<'a> {
+    pub(crate) fn new(
+        package_provider: &'a dyn PackageProvider,
+        system_package_provider: &'a dyn SystemPackageProvider,
+        excluded_packages: &'a IndexSet<PackageGlob>,
+        max_dependencies: usize,
+        resolve_virtual_packages: bool,
+    ) -> Self {
+        Self {
+            package_provider,
+            system_package_provider,
+            excluded_packages,
+            max_dependencies,
+            resolve_virtual_packages,
+            special_case_map: SPECIAL_CASE_MAP
+                .iter()
+                .cloned()
+                .map(|(special, additionals)| (special, additionals.to_vec()))
+                .collect(),
+            packages_marked_for_install: IndexSet::new(),
+        }
+    }
+
+    // Returns `None` when the requested package is optional and not available.
+    pub(crate) fn resolve(
+        &mut self,
+        requested_package: &RequestedPackage,
+    ) -> Result<Option<ResolvedPackage>, DeterminePackagesToInstallError> {
+        let package = requested_package.name.as_str();
+
+        if requested_package.optional && is_missing_package(package, self.package_provider) {
+            return Ok(None);
+        }
+
+        if !self.resolve_virtual_packages {
+            check_not_virtual_package(package, self.package_provider)?;
+        }
+
+        let mut visit_stack = IndexSet::new();
+        let mut package_notifications = IndexSet::new();
+
+        let marked_for_install = visit(
+            package,
+            requested_package.skip_dependencies,
+            requested_package.force,
+            self.system_package_provider,
+            self.package_provider,
+            &mut self.packages_marked_for_install,
+            &mut visit_stack,
+            &mut package_notifications,
+            &self.special_case_map,
+            self.excluded_packages,
+        )?;
+
+        check_dependency_count(package, &package_notifications, self.max_dependencies)?;
+
+        Ok(Some(ResolvedPackage {
+            marked_for_install,
+            package_notifications,
+        }))
+    }
+
+    // The packages marked for install by every request resolved so far, in the order they were
+    // marked.
+    pub(crate) fn into_packages(self) -> Vec<RepositoryPackage> {
+        self.packages_marked_for_install
+            .into_iter()
+            .map(|package_marked_for_install| package_marked_for_install.repository_package)
+            .collect()
+    }
+}
+
+// Packages for a foreign architecture are installed with the name dpkg gives them (e.g.;
+// `libc6:i386`) so they're kept apart from the packages with the same name for the target
+// architecture. Packages that aren't architecture-specific are listed in the package index for
+// every architecture so they're only installed once.
+fn add_foreign_packages(
+    packages_to_install: &mut Vec<RepositoryPackage>,
+    architecture: &ArchitectureName,
+    foreign_packages: impl IntoIterator<Item = RepositoryPackage>,
+) {
+    for mut foreign_package in foreign_packages {
+        if packages_to_install
+            .iter()
+            .any(|package| package.filename == foreign_package.filename)
+        {
+            continue;
+        }
+        foreign_package.name = qualified_package_name(&foreign_package, architecture);
+        packages_to_install.push(foreign_package);
+    }
+}
+
+// Packages in the pool are named `<name>_<version>_<architecture>.deb` so those that aren't
+// architecture-specific end with `_all.deb` and keep their name.
+fn qualified_package_name(package: &RepositoryPackage, architecture: &ArchitectureName) -> String {
+    if package.filename.ends_with("_all.deb") {
+        package.name.clone()
+    } else {
+        format!("{}:{architecture}", package.name)
+    }
+}
+
+// The packages installed in the base image for the given architecture, including those that aren't
+// architecture-specific.
+fn system_packages_for_architecture(
+    system_packages: &IndexSet<SystemPackage>,
+    architecture: &ArchitectureName,
+) -> IndexSet<SystemPackage> {
+    let architecture = architecture.to_string();
+    system_packages
+        .iter()
+        .filter(|system_package| {
+            system_package.architecture == "all" || system_package.architecture == architecture
+        })
+        .cloned()
+        .collect()
+}
+
+// Returns the status file to read the system packages from and whether it was configured with
+// `DPKG_STATUS_ENV_VAR`.
+fn dpkg_status_path(env: &Env) -> (PathBuf, bool) {
+    let configured_path = env
+        .get_string_lossy(DPKG_STATUS_ENV_VAR)
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from);
+    let is_configured = configured_path.is_some();
+    (
+        configured_path.unwrap_or_else(|| PathBuf::from(DEFAULT_DPKG_STATUS_PATH)),
+        is_configured,
+    )
+}
+
+// Recorded in the packages layer metadata so a base image that adds or removes system packages
+// between builds invalidates the layer. `None` when there's no status file to read.
+pub(crate) fn system_packages_sha256(env: &Env) -> Option<String> {
+    let (system_packages_path, _) = dpkg_status_path(env);
+    read(system_packages_path)
+        .ok()
+        .map(|contents| format!("{:x}", Sha256::digest(contents)))
+}
+
+// Returns `None` when the default status file doesn't exist (e.g.; a minimal image without dpkg) so
+// the build can continue without any system packages. A configured status file must exist.
+fn read_system_packages(
+    system_packages_path: &Path,
+    is_configured: bool,
+) -> Result<Option<IndexSet<SystemPackage>>, DeterminePackagesToInstallError> {
+    let contents = match read_to_string(system_packages_path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && !is_configured => return Ok(None),
+        Err(e) => Err(DeterminePackagesToInstallError::ReadSystemPackages(
+            system_packages_path.to_path_buf(),
+            e,
+        ))?,
+    };
+    contents
+        .trim()
+        .split("\n\n")
+        .filter(|control_data| !control_data.trim().is_empty())
+        .map(|control_data| {
+            Control::from(control_data)
+                .map_err(|e| {
+                    DeterminePackagesToInstallError::ParseSystemPackage(
+                        system_packages_path.to_path_buf(),
+                        control_data.to_string(),
+                        Box::new(e),
+                    )
+                })
+                .map(SystemPackage::from)
+        })
+        .collect::<Result<IndexSet<_>, _>>()
+        .map(Some)
+}
+
+// A lockfile in the app directory pins every package to the exact file that was installed when it
+// was written. Other versions are removed from the package index before resolving so dependencies
+// are also resolved against the locked versions.
+pub(crate) fn enforce_lockfile(
+    app_dir: &Path,
+    env: &Env,
+    distro: &Distro,
+    package_index: &mut PackageIndex,
+    foreign_package_indexes: &mut [ForeignPackageIndex],
+    mut log: Print<Bullet<Stdout>>,
+) -> BuildpackResult<(Option<Lockfile>, Print<Bullet<Stdout>>)> {
+    let lockfile_path = app_dir.join(LOCKFILE_NAME);
+    if !lockfile_path.is_file() {
+        return Ok((None, log));
+    }
+
+    log = log.h2("Enforcing lockfile");
+
+    if env
+        .get_string_lossy(IGNORE_LOCKFILE_ENV_VAR)
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case("true"))
+    {
+        log = log
+            .bullet(format!(
+                "Ignoring {lockfile} since {env_var} is set",
+                lockfile = style::value(LOCKFILE_NAME),
+                env_var = style::value(IGNORE_LOCKFILE_ENV_VAR)
+            ))
+            .done();
+        return Ok((None, log));
+    }
+
+    let contents = read_to_string(&lockfile_path)
+        .map_err(|e| DeterminePackagesToInstallError::ReadLockfile(lockfile_path.clone(), e))?;
+    let lockfile = Lockfile::from_toml(&contents).map_err(|e| {
+        DeterminePackagesToInstallError::ParseLockfile(lockfile_path.clone(), Box::new(e))
+    })?;
+
+    if lockfile.distro.name != distro.name
+        || lockfile.distro.version != distro.version
+        || lockfile.distro.codename != distro.codename
+    {
+        Err(DeterminePackagesToInstallError::LockfileDistroMismatch(
+            Box::new(lockfile.distro.clone()),
+            Box::new(distro.clone()),
+        ))?;
+    }
+
+    // an app built for more than one architecture can only commit the lockfile from one of them so
+    // builds for the other architectures resolve packages as if there was no lockfile
+    if lockfile.distro.architecture != distro.architecture {
+        log = log
+            .bullet(format!(
+                "Ignoring {lockfile_name} since it was written for {locked_architecture}",
+                lockfile_name = style::value(LOCKFILE_NAME),
+                locked_architecture = style::value(lockfile.distro.architecture.to_string())
+            ))
+            .warning(format!(
+                "Packages for {architecture} aren't locked and are resolved from the package \
+                index instead.",
+                architecture = style::value(distro.architecture.to_string())
+            ))
+            .done();
+        return Ok((None, log));
+    }
+
+    // packages for a foreign architecture (e.g.; `libc6:i386`) are pinned in the package index for
+    // their architecture and are skipped if it's no longer requested since they won't be installed
+    let unavailable_packages = lockfile
+        .packages
+        .iter()
+        .filter(|locked_package| {
+            let package_index = match locked_package.architecture() {
+                Some(architecture) => {
+                    match foreign_package_indexes
+                        .iter_mut()
+                        .find(|foreign_package_index| {
+                            foreign_package_index.architecture.to_string() == architecture
+                        }) {
+                        Some(foreign_package_index) => &mut foreign_package_index.package_index,
+                        None => return false,
+                    }
+                }
+                None => &mut *package_index,
+            };
+            !package_index.pin_package(locked_package.package_name(), |package| {
+                locked_package.matches(package)
+            })
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    if !unavailable_packages.is_empty() {
+        Err(DeterminePackagesToInstallError::LockedPackagesUnavailable(
+            unavailable_packages,
+        ))?;
+    }
+
+    log = log
+        .bullet(format!(
+            "Resolving packages against {lockfile}",
+            lockfile = style::value(LOCKFILE_NAME)
+        ))
+        .sub_bullet(format!(
+            "Pinned {count} packages to their locked versions",
+            count = lockfile.packages.len()
+        ))
+        .done();
+
+    Ok((Some(lockfile), log))
+}
+
+// Versions that don't satisfy the constraint configured for a requested package are removed from
+// the package index before resolving, the same as for a lockfile, so the highest version that
+// satisfies it is installed and any dependencies on the package are resolved against it as well.
+pub(crate) fn apply_version_constraints(
+    package_index: &mut PackageIndex,
+    foreign_package_indexes: &mut [ForeignPackageIndex],
+    requested_packages: &mut IndexSet<RequestedPackage>,
+    mut log: Print<Bullet<Stdout>>,
+) -> BuildpackResult<Print<Bullet<Stdout>>> {
+    let constrained_packages = requested_packages
+        .iter()
+        .filter(|requested_package| requested_package.version.is_some())
+        .cloned()
+        .collect::<Vec<_>>();
+    if constrained_packages.is_empty() {
+        return Ok(log);
+    }
+
+    log = log.h2("Applying version constraints");
+
+    for requested_package in constrained_packages {
+        let Some(version_constraint) = &requested_package.version else {
+            continue;
+        };
+        let package_index = match &requested_package.architecture {
+            Some(architecture) => {
+                &mut foreign_package_indexes
+                    .iter_mut()
+                    .find(|foreign_package_index| {
+                        &foreign_package_index.architecture == architecture
+                    })
+                    .expect("A package index should be created for every foreign architecture")
+                    .package_index
+            }
+            None => &mut *package_index,
+        };
+        let package = requested_package.name.as_str();
+
+        // packages that aren't in the index (e.g.; virtual packages) are reported when the
+        // requested packages are validated
+        let available_versions = package_index
+            .get_versions(package)
+            .into_iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        if available_versions.is_empty() {
+            continue;
+        }
+
+        let sub_bullet = log.bullet(format!(
+            "Limiting {package} to versions {version_constraint}",
+            package = style::value(requested_package.qualified_name()),
+            version_constraint = style::value(version_constraint.to_string())
+        ));
+
+        if package_index.pin_package(package, |repository_package| {
+            satisfies_version_constraint(repository_package, version_constraint)
+        }) {
+            let highest_version = package_index
+                .get_highest_available_version(package)
+                .map(|repository_package| repository_package.version.clone())
+                .unwrap_or_default();
+            log = sub_bullet
+                .sub_bullet(format!(
+                    "Highest matching version is {version}",
+                    version = style::value(highest_version)
+                ))
+                .done();
+        } else if requested_package.optional {
+            requested_packages.shift_remove(&requested_package);
+            log = sub_bullet
+                .warning(format!(
+                    "Skipping optional package {package} since none of its available versions \
+                    satisfy the constraint",
+                    package = style::value(requested_package.qualified_name())
+                ))
+                .done();
+        } else {
+            return Err(
+                DeterminePackagesToInstallError::VersionConstraintNotSatisfied {
+                    package: requested_package.qualified_name(),
+                    version_constraint: version_constraint.clone(),
+                    available_versions,
+                }
+                .into(),
+            );
+        }
+    }
+
+    Ok(log)
+}
+
+fn satisfies_version_constraint(
+    repository_package: &RepositoryPackage,
+    version_constraint: &VersionConstraint,
+) -> bool {
+    debversion::Version::from_str(&repository_package.version)
+        .is_ok_and(|version| version_constraint.matches(&version))
+}
 
-    Ok((packages_to_install, skipped_packages, log))
+// Packages added since the lockfile was written (e.g.; a new entry in project.toml) would be
+// installed at whatever version the index currently offers.
+pub(crate) fn check_locked_packages(
+    lockfile: &Lockfile,
+    packages_to_install: &[RepositoryPackage],
+) -> Result<(), DeterminePackagesToInstallError> {
+    let unlocked_packages = packages_to_install
+        .iter()
+        .filter(|package| {
+            !lockfile
+                .packages
+                .iter()
+                .any(|locked_package| locked_package.matches(package))
+        })
+        .map(|package| package.name.clone())
+        .collect::<Vec<_>>();
+    if unlocked_packages.is_empty() {
+        Ok(())
+    } else {
+        Err(DeterminePackagesToInstallError::PackagesNotLocked(
+            unlocked_packages,
+        ))
+    }
 }
 
 // NOTE: Since this buildpack is not meant to be a replacement for a fully-featured dependency
@@ -120,19 +700,20 @@ pub(crate) fn determine_packages_to_install(
 //       The dependency solving done here is mostly for convenience. Any transitive packages added
 //       will be reported to the user and, if they aren't correct, the user may disable this dependency
 //       resolution on a per-package basis and specify a more appropriate set of packages.
-#[allow(clippy::too_many_arguments)]
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
 fn visit(
     package: &str,
     skip_dependencies: bool,
     force_if_installed_on_system: bool,
-    system_packages: &IndexSet<SystemPackage>,
-    package_index: &PackageIndex,
+    system_packages: &dyn SystemPackageProvider,
+    package_index: &dyn PackageProvider,
     packages_marked_for_install: &mut IndexSet<PackageMarkedForInstall>,
     visit_stack: &mut IndexSet<String>,
     package_notifications: &mut IndexSet<PackageNotification>,
     special_case_map: &HashMap<&str, Vec<&str>>,
-) -> BuildpackResult<bool> {
-    if let Some(system_package) = find_system_package_by_name(package, system_packages) {
+    excluded_packages: &IndexSet<PackageGlob>,
+) -> Result<bool, DeterminePackagesToInstallError> {
+    if let Some(system_package) = system_packages.get_system_package(package) {
         // When a package is already installed on the system we skip installing it. However, there are
         // cases where a package might be installed in the build image but not the run image. There's
         // no easy method to check for that condition at build-time so we allow the user to "force" the
@@ -160,7 +741,19 @@ fn visit(
         // Special case handling: Ensure additional packages are installed before special case packages
         if let Some(additional_packages) = special_case_map.get(package) {
             for &additional_package in additional_packages {
-                if should_visit_dependency(additional_package, system_packages, packages_marked_for_install) {
+                if is_excluded_dependency(
+                    additional_package,
+                    excluded_packages,
+                    visit_stack,
+                    package_notifications,
+                ) {
+                    continue;
+                }
+                if should_visit_dependency(
+                    additional_package,
+                    system_packages,
+                    packages_marked_for_install,
+                ) {
                     visit(
                         additional_package,
                         skip_dependencies,
@@ -171,6 +764,7 @@ fn visit(
                         visit_stack,
                         package_notifications,
                         special_case_map,
+                        excluded_packages,
                     )?;
                 }
             }
@@ -187,10 +781,36 @@ fn visit(
             forced_install: force_if_installed_on_system,
         });
 
+        let alternate_sources = package_index.get_alternate_sources(repository_package);
+        if !alternate_sources.is_empty() {
+            package_notifications.insert(PackageNotification::PreferredSource {
+                repository_package: repository_package.clone(),
+                alternate_sources: alternate_sources.into_iter().cloned().collect(),
+            });
+        }
+
+        if let Some(phased_package) = package_index.get_skipped_phased_update(repository_package) {
+            package_notifications.insert(PackageNotification::SkippedPhasedUpdate {
+                repository_package: repository_package.clone(),
+                phased_version: phased_package.version.clone(),
+                phased_update_percentage: phased_package
+                    .phased_update_percentage
+                    .unwrap_or_default(),
+            });
+        }
+
         visit_stack.insert(repository_package.name.to_string());
 
         if !skip_dependencies {
             for dependency in repository_package.get_dependencies() {
+                if is_excluded_dependency(
+                    dependency,
+                    excluded_packages,
+                    visit_stack,
+                    package_notifications,
+                ) {
+                    continue;
+                }
                 if should_visit_dependency(dependency, system_packages, packages_marked_for_install)
                 {
                     visit(
@@ -203,6 +823,7 @@ fn visit(
                         visit_stack,
                         package_notifications,
                         special_case_map,
+                        excluded_packages,
                     )?;
                 }
             }
@@ -225,6 +846,7 @@ fn visit(
             visit_stack,
             package_notifications,
             special_case_map,
+            excluded_packages,
         )?;
 
         visit_stack.shift_remove(package);
@@ -233,13 +855,99 @@ fn visit(
     Ok(true)
 }
 
+// A requested metapackage doesn't install any files of its own so the packages it expanded to are
+// summarized to explain why a single request can add so many packages.
+fn describe_metapackage_expansion(
+    package_notifications: &IndexSet<PackageNotification>,
+) -> Option<String> {
+    let metapackage = package_notifications
+        .iter()
+        .find_map(|package_notification| match package_notification {
+            PackageNotification::Added {
+                repository_package,
+                dependency_path,
+                ..
+            } if dependency_path.is_empty() && repository_package.is_metapackage() => {
+                Some(repository_package)
+            }
+            _ => None,
+        })?;
+
+    // nested metapackages are left out since they don't install anything either
+    let mut expanded_packages = package_notifications
+        .iter()
+        .filter_map(|package_notification| match package_notification {
+            PackageNotification::Added {
+                repository_package,
+                dependency_path,
+                ..
+            } if !dependency_path.is_empty() && !repository_package.is_metapackage() => {
+                Some(repository_package.name.as_str())
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    // dependencies are visited in no particular order
+    expanded_packages.sort_unstable();
+
+    let metapackage_name = style::value(&metapackage.name);
+    Some(match expanded_packages.len() {
+        0 => format!(
+            "{metapackage_name} is a metapackage but none of the packages it depends on were added"
+        ),
+        count => format!(
+            "{metapackage_name} is a metapackage that expanded to {count} {packages}: {expanded_packages}",
+            packages = if count == 1 { "package" } else { "packages" },
+            expanded_packages = expanded_packages
+                .into_iter()
+                .map(style::value)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    })
+}
+
+// An optional package is skipped instead of failing the build when it would be reported as not
+// found (e.g.; a package that's only published for some codenames). Missing dependencies of an
+// optional package still fail the build.
+fn is_missing_package(package: &str, package_index: &dyn PackageProvider) -> bool {
+    package_index
+        .get_highest_available_version(package)
+        .is_none()
+        && package_index.get_providers(package).is_empty()
+}
+
+// When virtual package resolution is disabled, requesting a virtual package is an error even if
+// only one package provides it. Dependencies are still resolved through their providers.
+fn check_not_virtual_package(
+    package: &str,
+    package_index: &dyn PackageProvider,
+) -> Result<(), DeterminePackagesToInstallError> {
+    if package_index
+        .get_highest_available_version(package)
+        .is_some()
+    {
+        return Ok(());
+    }
+    let providers = package_index.get_providers(package);
+    if providers.is_empty() {
+        return Ok(());
+    }
+    Err(
+        DeterminePackagesToInstallError::VirtualPackageResolutionDisabled(
+            package.to_string(),
+            providers.into_iter().map(ToString::to_string).collect(),
+        ),
+    )
+}
+
 fn get_provider_for_virtual_package<'a>(
     package: &str,
-    package_index: &'a PackageIndex,
+    package_index: &'a dyn PackageProvider,
     package_install_details: &mut IndexSet<PackageNotification>,
-) -> BuildpackResult<&'a RepositoryPackage> {
+) -> Result<&'a RepositoryPackage, DeterminePackagesToInstallError> {
     let providers = package_index.get_providers(package);
-    Ok(match providers.iter().collect::<Vec<_>>().as_slice() {
+    match providers.iter().collect::<Vec<_>>().as_slice() {
         [providing_package] => package_index
             .get_highest_available_version(providing_package)
             .inspect(|repository_package| {
@@ -267,16 +975,7 @@ fn get_provider_for_virtual_package<'a>(
                     .collect::<HashSet<_>>(),
             ),
         ),
-    }?)
-}
-
-fn find_system_package_by_name<'a>(
-    package_name: &str,
-    system_packages: &'a IndexSet<SystemPackage>,
-) -> Option<&'a SystemPackage> {
-    system_packages
-        .iter()
-        .find(|system_package| system_package.package_name == package_name)
+    }
 }
 
 fn find_package_marked_for_install_by_name<'a>(
@@ -292,7 +991,7 @@ fn find_package_marked_for_install_by_name<'a>(
 
 fn should_visit_dependency(
     dependency: &str,
-    system_packages: &IndexSet<SystemPackage>,
+    system_packages: &dyn SystemPackageProvider,
     packages_marked_for_install: &IndexSet<PackageMarkedForInstall>,
 ) -> bool {
     // Don't bother looking at any dependencies we've already seen or that are already
@@ -300,15 +999,89 @@ fn should_visit_dependency(
     // output details about requested packages and any transitive dependencies added.
     matches!(
         (
-            find_system_package_by_name(dependency, system_packages),
+            system_packages.get_system_package(dependency),
             find_package_marked_for_install_by_name(dependency, packages_marked_for_install)
         ),
         (None, None)
     )
 }
 
-fn find_suggested_packages(package: &str, package_index: &PackageIndex) -> Vec<String> {
-    let mut suggested_packages = package_index
+fn is_excluded_dependency(
+    dependency: &str,
+    excluded_packages: &IndexSet<PackageGlob>,
+    visit_stack: &IndexSet<String>,
+    package_notifications: &mut IndexSet<PackageNotification>,
+) -> bool {
+    // Exclusions only apply to packages pulled in while walking dependencies. Packages that were
+    // explicitly requested are always installed.
+    if let Some(pattern) = excluded_packages
+        .iter()
+        .find(|pattern| pattern.matches(dependency))
+    {
+        package_notifications.insert(PackageNotification::Excluded {
+            package: dependency.to_string(),
+            pattern: pattern.clone(),
+            dependency_path: visit_stack.iter().cloned().collect(),
+        });
+        true
+    } else {
+        false
+    }
+}
+
+fn check_dependency_count(
+    package: &str,
+    package_notifications: &IndexSet<PackageNotification>,
+    max_dependencies: usize,
+) -> Result<(), DeterminePackagesToInstallError> {
+    // a limit of 0 disables the check
+    if max_dependencies == 0 {
+        return Ok(());
+    }
+
+    let dependency_paths = package_notifications
+        .iter()
+        .filter_map(|package_notification| match package_notification {
+            PackageNotification::Added {
+                repository_package,
+                dependency_path,
+                ..
+            } if !dependency_path.is_empty() => Some((&repository_package.name, dependency_path)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    if dependency_paths.len() <= max_dependencies {
+        return Ok(());
+    }
+
+    // the size of each subtree is the direct dependency itself plus everything added through it
+    let mut largest_subtrees = dependency_paths
+        .iter()
+        .filter(|(_, dependency_path)| dependency_path.len() == 1)
+        .map(|(direct_dependency, _)| {
+            let count = dependency_paths
+                .iter()
+                .filter(|(_, dependency_path)| dependency_path.get(1) == Some(*direct_dependency))
+                .count();
+            ((*direct_dependency).clone(), count + 1)
+        })
+        .collect::<Vec<_>>();
+    largest_subtrees.sort_by(|(a_name, a_count), (b_name, b_count)| {
+        b_count.cmp(a_count).then(a_name.cmp(b_name))
+    });
+    largest_subtrees.truncate(5);
+
+    Err(DeterminePackagesToInstallError::TooManyDependencies {
+        package: package.to_string(),
+        dependency_count: dependency_paths.len(),
+        max_dependencies,
+        largest_subtrees,
+    })
+}
+
+fn find_suggested_packages(package: &str, package_index: &dyn PackageProvider) -> Vec<String> {
+    let mut suggested_packages = package_index
         .get_package_names()
         .iter()
         .filter_map(|name| {
@@ -334,10 +1107,27 @@ fn find_suggested_packages(package: &str, package_index: &PackageIndex) -> Vec<S
 #[derive(Debug)]
 pub(crate) enum DeterminePackagesToInstallError {
     ReadSystemPackages(PathBuf, std::io::Error),
-    ParseSystemPackage(PathBuf, String, apt_parser::errors::APTError),
+    ParseSystemPackage(PathBuf, String, Box<apt_parser::errors::APTError>),
     PackageNotFound(String, Vec<String>),
     VirtualPackageMustBeSpecified(String, HashSet<String>),
+    VirtualPackageResolutionDisabled(String, HashSet<String>),
+    TooManyDependencies {
+        package: String,
+        dependency_count: usize,
+        max_dependencies: usize,
+        largest_subtrees: Vec<(String, usize)>,
+    },
     SystemTimeError(SystemTimeError),
+    ReadLockfile(PathBuf, std::io::Error),
+    ParseLockfile(PathBuf, Box<toml::de::Error>),
+    LockfileDistroMismatch(Box<Distro>, Box<Distro>),
+    LockedPackagesUnavailable(Vec<LockedPackage>),
+    PackagesNotLocked(Vec<String>),
+    VersionConstraintNotSatisfied {
+        package: String,
+        version_constraint: VersionConstraint,
+        available_versions: Vec<String>,
+    },
 }
 
 impl From<DeterminePackagesToInstallError> for libcnb::Error<DebianPackagesBuildpackError> {
@@ -349,7 +1139,7 @@ impl From<DeterminePackagesToInstallError> for libcnb::Error<DebianPackagesBuild
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
-enum PackageNotification {
+pub(crate) enum PackageNotification {
     Added {
         repository_package: RepositoryPackage,
         dependency_path: Vec<String>,
@@ -367,9 +1157,24 @@ enum PackageNotification {
         requested_package: String,
         implementor: RepositoryPackage,
     },
+    Excluded {
+        package: String,
+        pattern: PackageGlob,
+        dependency_path: Vec<String>,
+    },
+    PreferredSource {
+        repository_package: RepositoryPackage,
+        alternate_sources: Vec<RepositoryUri>,
+    },
+    SkippedPhasedUpdate {
+        repository_package: RepositoryPackage,
+        phased_version: String,
+        phased_update_percentage: u8,
+    },
 }
 
 impl Display for PackageNotification {
+    #[allow(clippy::too_many_lines)]
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             PackageNotification::Added {
@@ -377,9 +1182,14 @@ impl Display for PackageNotification {
                 dependency_path,
                 forced_install,
             } => {
+                let metapackage = if repository_package.is_metapackage() {
+                    " (metapackage)"
+                } else {
+                    ""
+                };
                 if dependency_path.is_empty() {
                     let added = format!(
-                        "Adding {name_with_version}",
+                        "Adding {name_with_version}{metapackage}",
                         name_with_version = style::value(format!(
                             "{name}@{version}",
                             name = repository_package.name,
@@ -394,7 +1204,7 @@ impl Display for PackageNotification {
                 } else {
                     write!(
                         f,
-                        "Adding {name_with_version} [from {path}]",
+                        "Adding {name_with_version}{metapackage} [from {path}]",
                         name_with_version = style::value(format!(
                             "{name}@{version}",
                             name = repository_package.name,
@@ -445,6 +1255,60 @@ impl Display for PackageNotification {
                     )),
                 )
             }
+            PackageNotification::Excluded {
+                package,
+                pattern,
+                dependency_path,
+            } => {
+                write!(
+                    f,
+                    "Skipping {package} because it matches the exclude pattern {pattern} [from {path}]",
+                    package = style::value(package),
+                    pattern = style::value(pattern.as_str()),
+                    path = dependency_path
+                        .iter()
+                        .rev()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(" ← ")
+                )
+            }
+            PackageNotification::PreferredSource {
+                repository_package,
+                alternate_sources,
+            } => {
+                write!(
+                    f,
+                    "Using {name_with_version} from {source} (also available from {alternate_sources})",
+                    name_with_version = style::value(format!(
+                        "{name}@{version}",
+                        name = repository_package.name,
+                        version = repository_package.version
+                    )),
+                    source = style::url(repository_package.repository_uri.as_str()),
+                    alternate_sources = alternate_sources
+                        .iter()
+                        .map(|alternate_source| style::url(alternate_source.as_str()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
+            PackageNotification::SkippedPhasedUpdate {
+                repository_package,
+                phased_version,
+                phased_update_percentage,
+            } => {
+                write!(
+                    f,
+                    "Using {name_with_version} instead of the phased update {phased_version} ({phased_update_percentage}% phased)",
+                    name_with_version = style::value(format!(
+                        "{name}@{version}",
+                        name = repository_package.name,
+                        version = repository_package.version
+                    )),
+                    phased_version = style::value(phased_version),
+                )
+            }
         }
     }
 }
@@ -456,9 +1320,10 @@ struct PackageMarkedForInstall {
 }
 
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
-struct SystemPackage {
-    package_name: String,
-    package_version: String,
+pub(crate) struct SystemPackage {
+    pub(crate) package_name: String,
+    pub(crate) package_version: String,
+    pub(crate) architecture: String,
 }
 
 impl From<Control> for SystemPackage {
@@ -466,6 +1331,7 @@ impl From<Control> for SystemPackage {
         Self {
             package_name: value.package,
             package_version: value.version,
+            architecture: value.architecture,
         }
     }
 }
@@ -478,7 +1344,210 @@ mod test {
 
     use bon::builder;
 
-    use crate::debian::RepositoryUri;
+    use crate::test_support::{create_repository_package, DEFAULT_VERSION};
+
+    #[test]
+    fn test_read_system_packages() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let status_path = temp_dir.path().join("status");
+
+        assert_eq!(read_system_packages(&status_path, false).unwrap(), None);
+        assert!(matches!(
+            read_system_packages(&status_path, true),
+            Err(DeterminePackagesToInstallError::ReadSystemPackages(path, _)) if path == status_path
+        ));
+
+        std::fs::write(&status_path, "").unwrap();
+        assert_eq!(
+            read_system_packages(&status_path, true).unwrap(),
+            Some(IndexSet::new())
+        );
+
+        std::fs::write(
+            &status_path,
+            "Package: package-a\nStatus: install ok installed\nArchitecture: amd64\nMaintainer: Ubuntu\nVersion: 1.0.0\nDescription: test\n\n\
+            Package: package-b\nStatus: install ok installed\nArchitecture: amd64\nMaintainer: Ubuntu\nVersion: 2.0.0\nDescription: test\n",
+        )
+        .unwrap();
+        assert_eq!(
+            read_system_packages(&status_path, false).unwrap(),
+            Some(IndexSet::from([
+                create_system_package()
+                    .package_name("package-a")
+                    .package_version("1.0.0")
+                    .call(),
+                create_system_package()
+                    .package_name("package-b")
+                    .package_version("2.0.0")
+                    .call(),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_system_packages_sha256() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let status_path = temp_dir.path().join("status");
+        let mut env = Env::new();
+        env.insert(DPKG_STATUS_ENV_VAR, &status_path);
+
+        assert_eq!(system_packages_sha256(&env), None);
+
+        std::fs::write(&status_path, "Package: package-a\nVersion: 1.0.0\n").unwrap();
+        let sha256 = system_packages_sha256(&env).unwrap();
+        assert_eq!(system_packages_sha256(&env), Some(sha256.clone()));
+
+        std::fs::write(
+            &status_path,
+            "Package: package-a\nVersion: 1.0.0\n\nPackage: package-b\nVersion: 2.0.0\n",
+        )
+        .unwrap();
+        assert_ne!(system_packages_sha256(&env), Some(sha256));
+    }
+
+    #[test]
+    fn test_system_packages_for_architecture() {
+        let system_packages = IndexSet::from([
+            create_system_package().package_name("libc6").call(),
+            create_system_package()
+                .package_name("libgcc-s1")
+                .architecture("i386")
+                .call(),
+            create_system_package()
+                .package_name("tzdata")
+                .architecture("all")
+                .call(),
+        ]);
+        assert_eq!(
+            system_packages_for_architecture(&system_packages, &ArchitectureName::I_386),
+            IndexSet::from([
+                create_system_package()
+                    .package_name("libgcc-s1")
+                    .architecture("i386")
+                    .call(),
+                create_system_package()
+                    .package_name("tzdata")
+                    .architecture("all")
+                    .call(),
+            ])
+        );
+        assert_eq!(
+            system_packages_for_architecture(&system_packages, &ArchitectureName::AMD_64),
+            IndexSet::from([
+                create_system_package().package_name("libc6").call(),
+                create_system_package()
+                    .package_name("tzdata")
+                    .architecture("all")
+                    .call(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_add_foreign_packages() {
+        let with_filename = |name: &str, filename: &str| RepositoryPackage {
+            filename: filename.to_string(),
+            ..create_repository_package().name(name).call()
+        };
+        let mut packages_to_install = vec![
+            with_filename("libc6", "pool/main/g/glibc/libc6_2.39_amd64.deb"),
+            with_filename("tzdata", "pool/main/t/tzdata/tzdata_2024a_all.deb"),
+        ];
+        add_foreign_packages(
+            &mut packages_to_install,
+            &ArchitectureName::I_386,
+            [
+                with_filename("libc6", "pool/main/g/glibc/libc6_2.39_i386.deb"),
+                with_filename("tzdata", "pool/main/t/tzdata/tzdata_2024a_all.deb"),
+                with_filename("locales", "pool/main/g/glibc/locales_2.39_all.deb"),
+            ],
+        );
+        assert_eq!(
+            packages_to_install
+                .iter()
+                .map(|package| package.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["libc6", "tzdata", "libc6:i386", "locales"]
+        );
+    }
+
+    #[test]
+    fn test_package_resolver() {
+        let package_c = create_repository_package().name("package-c").call();
+        let package_b = create_repository_package()
+            .name("package-b")
+            .depends(vec![&package_c])
+            .call();
+        let package_a = create_repository_package()
+            .name("package-a")
+            .depends(vec![&package_b])
+            .call();
+        let package_d = create_repository_package().name("package-d").call();
+
+        let mut package_index = PackageIndex::default();
+        for package in [&package_a, &package_b, &package_c, &package_d] {
+            package_index.add_package(package.clone());
+        }
+        let system_packages =
+            IndexSet::from([create_system_package().package_name(&package_d.name).call()]);
+        let excluded_packages = IndexSet::new();
+
+        let mut resolver = PackageResolver::new(
+            &package_index,
+            &system_packages,
+            &excluded_packages,
+            DEFAULT_MAX_DEPENDENCIES,
+            true,
+        );
+
+        let resolved_package = resolver
+            .resolve(&RequestedPackage::from_str("package-a").unwrap())
+            .unwrap()
+            .unwrap();
+        assert!(resolved_package.marked_for_install);
+        assert_eq!(resolved_package.package_notifications.len(), 3);
+
+        // packages marked for install by an earlier request aren't added again
+        let resolved_package = resolver
+            .resolve(&RequestedPackage::from_str("package-b").unwrap())
+            .unwrap()
+            .unwrap();
+        assert!(!resolved_package.marked_for_install);
+        assert_eq!(
+            resolved_package.package_notifications,
+            IndexSet::from([PackageNotification::AlreadyInstalledByOtherPackage {
+                installed_package: package_b.clone(),
+                installed_by: package_a.name.clone(),
+            }])
+        );
+
+        let resolved_package = resolver
+            .resolve(&RequestedPackage::from_str("package-d").unwrap())
+            .unwrap()
+            .unwrap();
+        assert!(!resolved_package.marked_for_install);
+
+        let missing_optional_package = RequestedPackage {
+            optional: true,
+            ..RequestedPackage::from_str("package-e").unwrap()
+        };
+        assert!(resolver
+            .resolve(&missing_optional_package)
+            .unwrap()
+            .is_none());
+
+        match resolver.resolve(&RequestedPackage::from_str("package-e").unwrap()) {
+            Err(DeterminePackagesToInstallError::PackageNotFound(package, _)) => {
+                assert_eq!(package, "package-e");
+            }
+            result => panic!("Expected a package not found error but got {result:?}"),
+        }
+
+        assert_eq!(
+            resolver.into_packages(),
+            vec![package_a, package_b, package_c]
+        );
+    }
 
     #[test]
     fn install_package_already_on_the_system() {
@@ -714,211 +1783,881 @@ mod test {
     }
 
     #[test]
-    fn install_package_that_does_not_exist_returns_max_5_suggestions_between_edit_distance_1_and_3()
-    {
-        let non_existent_package = "non-existent-package";
-
-        let package_with_edit_distance_1 = create_repository_package()
-            .name(&format!("{non_existent_package}1"))
-            .call();
-        let another_package_with_edit_distance_1 = create_repository_package()
-            .name(&format!("{non_existent_package}a"))
-            .call();
-        let package_with_virtual_package_edit_distance_2_and_3 = create_repository_package()
-            .name("another-virtual-package-provider")
-            .provides(vec![
-                &format!("{non_existent_package}12"),
-                &format!("{non_existent_package}123"),
-            ])
+    fn install_package_that_does_not_exist_returns_max_5_suggestions_between_edit_distance_1_and_3()
+    {
+        let non_existent_package = "non-existent-package";
+
+        let package_with_edit_distance_1 = create_repository_package()
+            .name(&format!("{non_existent_package}1"))
+            .call();
+        let another_package_with_edit_distance_1 = create_repository_package()
+            .name(&format!("{non_existent_package}a"))
+            .call();
+        let package_with_virtual_package_edit_distance_2_and_3 = create_repository_package()
+            .name("another-virtual-package-provider")
+            .provides(vec![
+                &format!("{non_existent_package}12"),
+                &format!("{non_existent_package}123"),
+            ])
+            .call();
+        let another_package_with_virtual_package_edit_distance_2_and_3 =
+            create_repository_package()
+                .name("yet-another-virtual-package-provider")
+                .provides(vec![
+                    &format!("{non_existent_package}ab"),
+                    &format!("{non_existent_package}abc"),
+                ])
+                .call();
+        let package_with_edit_distance_4 = create_repository_package()
+            .name(&format!("{non_existent_package}1234"))
+            .call();
+
+        let error = test_install_state()
+            .with_package_index(vec![
+                &package_with_edit_distance_1,
+                &package_with_virtual_package_edit_distance_2_and_3,
+                &package_with_edit_distance_4,
+                &another_package_with_edit_distance_1,
+                &another_package_with_virtual_package_edit_distance_2_and_3,
+            ])
+            .install(non_existent_package)
+            .call()
+            .unwrap_err();
+
+        if let libcnb::Error::BuildpackError(
+            DebianPackagesBuildpackError::DeterminePackagesToInstall(
+                DeterminePackagesToInstallError::PackageNotFound(name, suggestions),
+            ),
+        ) = error
+        {
+            assert_eq!(name, non_existent_package.to_string());
+            assert_eq!(
+                suggestions,
+                vec![
+                    format!("{non_existent_package}1"),
+                    format!("{non_existent_package}a"),
+                    format!("{non_existent_package}12"),
+                    format!("{non_existent_package}ab"),
+                    format!("{non_existent_package}123"),
+                ]
+            );
+        } else {
+            panic!("not the expected error: {error:?}")
+        }
+    }
+
+    #[test]
+    fn install_highest_version_of_package_when_there_are_multiple_versions() {
+        let package_name = "test-package";
+
+        let package_v0 = create_repository_package()
+            .name(package_name)
+            .version("1.2.2-2ubuntu0.22.04.2")
+            .call();
+
+        let package_v1 = create_repository_package()
+            .name(package_name)
+            .version("1.2.3-2ubuntu0.22.04.2")
+            .call();
+
+        assert!(
+            debversion::Version::from_str(package_v0.version.as_str()).unwrap()
+                < debversion::Version::from_str(package_v1.version.as_str()).unwrap()
+        );
+
+        let (new_packages_marked_for_install, package_notifications) = test_install_state()
+            .with_package_index(vec![&package_v0, &package_v1])
+            .install(package_name)
+            .call()
+            .unwrap();
+
+        assert_eq!(
+            new_packages_marked_for_install,
+            IndexSet::from([create_package_marked_for_install()
+                .repository_package(&package_v1)
+                .call()])
+        );
+
+        assert_eq!(
+            package_notifications,
+            IndexSet::from([PackageNotification::Added {
+                repository_package: package_v1,
+                dependency_path: vec![],
+                forced_install: false,
+            }])
+        );
+    }
+
+    #[test]
+    fn install_package_and_dependencies() {
+        let package_d = create_repository_package().name("package-d").call();
+
+        let package_c = create_repository_package()
+            .name("package-c")
+            .pre_depends(vec![&package_d])
+            .call();
+
+        let package_b = create_repository_package()
+            .name("package-b")
+            .depends(vec![&package_c])
+            .call();
+
+        let package_a = create_repository_package()
+            .name("package-a")
+            .depends(vec![&package_b])
+            .call();
+
+        let (new_packages_marked_for_install, package_notifications) = test_install_state()
+            .with_package_index(vec![&package_a, &package_b, &package_c, &package_d])
+            .install(&package_a.name)
+            .call()
+            .unwrap();
+
+        assert_eq!(
+            new_packages_marked_for_install,
+            IndexSet::from([
+                create_package_marked_for_install()
+                    .repository_package(&package_a)
+                    .call(),
+                create_package_marked_for_install()
+                    .repository_package(&package_b)
+                    .requested_by(&package_a.name)
+                    .call(),
+                create_package_marked_for_install()
+                    .repository_package(&package_c)
+                    .requested_by(&package_a.name)
+                    .call(),
+                create_package_marked_for_install()
+                    .repository_package(&package_d)
+                    .requested_by(&package_a.name)
+                    .call()
+            ])
+        );
+
+        assert_eq!(
+            package_notifications,
+            IndexSet::from([
+                PackageNotification::Added {
+                    repository_package: package_a.clone(),
+                    dependency_path: vec![],
+                    forced_install: false,
+                },
+                PackageNotification::Added {
+                    repository_package: package_b.clone(),
+                    dependency_path: vec![package_a.name.to_string()],
+                    forced_install: false,
+                },
+                PackageNotification::Added {
+                    repository_package: package_c.clone(),
+                    dependency_path: vec![package_a.name.to_string(), package_b.name.to_string()],
+                    forced_install: false,
+                },
+                PackageNotification::Added {
+                    repository_package: package_d,
+                    dependency_path: vec![
+                        package_a.name.to_string(),
+                        package_b.name.to_string(),
+                        package_c.name.to_string()
+                    ],
+                    forced_install: false,
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn install_package_but_skip_dependencies() {
+        let package_d = create_repository_package().name("package-d").call();
+
+        let package_c = create_repository_package()
+            .name("package-c")
+            .pre_depends(vec![&package_d])
+            .call();
+
+        let package_b = create_repository_package()
+            .name("package-b")
+            .depends(vec![&package_c])
+            .call();
+
+        let package_a = create_repository_package()
+            .name("package-a")
+            .depends(vec![&package_b])
+            .call();
+
+        let (new_packages_marked_for_install, package_notifications) = test_install_state()
+            .with_package_index(vec![&package_a, &package_b, &package_c, &package_d])
+            .install(&package_a.name)
+            .skip_dependencies(true)
+            .call()
+            .unwrap();
+
+        assert_eq!(
+            new_packages_marked_for_install,
+            IndexSet::from([create_package_marked_for_install()
+                .repository_package(&package_a)
+                .call(),])
+        );
+
+        assert_eq!(
+            package_notifications,
+            IndexSet::from([PackageNotification::Added {
+                repository_package: package_a,
+                dependency_path: vec![],
+                forced_install: false,
+            }])
+        );
+    }
+
+    #[test]
+    fn install_package_and_skip_excluded_dependencies() {
+        let package_c = create_repository_package().name("package-c").call();
+
+        let package_b_doc = create_repository_package()
+            .name("package-b-doc")
+            .depends(vec![&package_c])
+            .call();
+
+        let package_b = create_repository_package().name("package-b").call();
+
+        let package_a = create_repository_package()
+            .name("package-a")
+            .depends(vec![&package_b, &package_b_doc])
+            .call();
+
+        let (new_packages_marked_for_install, package_notifications) = test_install_state()
+            .with_package_index(vec![&package_a, &package_b, &package_b_doc, &package_c])
+            .install(&package_a.name)
+            .with_excluded(IndexSet::from([PackageGlob::from_str("*-doc").unwrap()]))
+            .call()
+            .unwrap();
+
+        assert_eq!(
+            new_packages_marked_for_install,
+            IndexSet::from([
+                create_package_marked_for_install()
+                    .repository_package(&package_a)
+                    .call(),
+                create_package_marked_for_install()
+                    .repository_package(&package_b)
+                    .requested_by(&package_a.name)
+                    .call(),
+            ])
+        );
+
+        assert_eq!(
+            package_notifications,
+            IndexSet::from([
+                PackageNotification::Added {
+                    repository_package: package_a.clone(),
+                    dependency_path: vec![],
+                    forced_install: false,
+                },
+                PackageNotification::Added {
+                    repository_package: package_b,
+                    dependency_path: vec![package_a.name.clone()],
+                    forced_install: false,
+                },
+                PackageNotification::Excluded {
+                    package: package_b_doc.name.clone(),
+                    pattern: PackageGlob::from_str("*-doc").unwrap(),
+                    dependency_path: vec![package_a.name.clone()],
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn install_requested_package_that_matches_an_exclude_pattern() {
+        let package_a_doc = create_repository_package().name("package-a-doc").call();
+
+        let (new_packages_marked_for_install, _) = test_install_state()
+            .with_package_index(vec![&package_a_doc])
+            .install(&package_a_doc.name)
+            .with_excluded(IndexSet::from([PackageGlob::from_str("*-doc").unwrap()]))
+            .call()
+            .unwrap();
+
+        assert_eq!(
+            new_packages_marked_for_install,
+            IndexSet::from([create_package_marked_for_install()
+                .repository_package(&package_a_doc)
+                .call()])
+        );
+    }
+
+    #[test]
+    fn enforce_lockfile_pins_locked_versions() {
+        let package_a_1 = create_repository_package().name("package-a").call();
+        let package_a_2 = create_repository_package()
+            .name("package-a")
+            .version("2.0.0")
+            .call();
+        let mut package_index = PackageIndex::default();
+        package_index.add_package(package_a_1.clone());
+        package_index.add_package(package_a_2.clone());
+
+        let distro = create_distro();
+        let app_dir = tempfile::tempdir().unwrap();
+        let log = Print::new(std::io::stdout()).h1("test");
+
+        // without a lockfile the index is unchanged
+        let (lockfile, log) = enforce_lockfile(
+            app_dir.path(),
+            &Env::new(),
+            &distro,
+            &mut package_index,
+            &mut [],
+            log,
+        )
+        .unwrap();
+        assert_eq!(lockfile, None);
+        assert_eq!(
+            package_index.get_highest_available_version("package-a"),
+            Some(&package_a_2)
+        );
+
+        std::fs::write(
+            app_dir.path().join(LOCKFILE_NAME),
+            Lockfile::new(&distro, std::slice::from_ref(&package_a_1))
+                .to_toml()
+                .unwrap(),
+        )
+        .unwrap();
+
+        let mut env = Env::new();
+        env.insert(IGNORE_LOCKFILE_ENV_VAR, "true");
+        let (lockfile, log) = enforce_lockfile(
+            app_dir.path(),
+            &env,
+            &distro,
+            &mut package_index,
+            &mut [],
+            log,
+        )
+        .unwrap();
+        assert_eq!(lockfile, None);
+
+        let (lockfile, _) = enforce_lockfile(
+            app_dir.path(),
+            &Env::new(),
+            &distro,
+            &mut package_index,
+            &mut [],
+            log,
+        )
+        .unwrap();
+        assert_eq!(
+            lockfile,
+            Some(Lockfile::new(&distro, std::slice::from_ref(&package_a_1)))
+        );
+        assert_eq!(
+            package_index.get_highest_available_version("package-a"),
+            Some(&package_a_1)
+        );
+    }
+
+    #[test]
+    fn enforce_lockfile_pins_foreign_packages() {
+        let package_a_1 = create_repository_package().name("package-a").call();
+        let package_a_2 = create_repository_package()
+            .name("package-a")
+            .version("2.0.0")
+            .call();
+        let mut foreign_package_indexes = [ForeignPackageIndex {
+            architecture: ArchitectureName::I_386,
+            package_index: PackageIndex::default(),
+        }];
+        foreign_package_indexes[0]
+            .package_index
+            .add_package(package_a_1.clone());
+        foreign_package_indexes[0]
+            .package_index
+            .add_package(package_a_2.clone());
+
+        let distro = create_distro();
+        let app_dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            app_dir.path().join(LOCKFILE_NAME),
+            Lockfile::new(
+                &distro,
+                &[RepositoryPackage {
+                    name: "package-a:i386".to_string(),
+                    ..package_a_1.clone()
+                }],
+            )
+            .to_toml()
+            .unwrap(),
+        )
+        .unwrap();
+
+        let mut package_index = PackageIndex::default();
+        enforce_lockfile(
+            app_dir.path(),
+            &Env::new(),
+            &distro,
+            &mut package_index,
+            &mut foreign_package_indexes,
+            Print::new(std::io::stdout()).h1("test"),
+        )
+        .unwrap();
+        assert_eq!(
+            foreign_package_indexes[0]
+                .package_index
+                .get_highest_available_version("package-a"),
+            Some(&package_a_1)
+        );
+
+        // the locked package is skipped when it's no longer requested for the architecture
+        enforce_lockfile(
+            app_dir.path(),
+            &Env::new(),
+            &distro,
+            &mut package_index,
+            &mut [],
+            Print::new(std::io::stdout()).h1("test"),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn enforce_lockfile_for_another_architecture() {
+        let package_a_1 = create_repository_package().name("package-a").call();
+        let package_a_2 = create_repository_package()
+            .name("package-a")
+            .version("2.0.0")
+            .call();
+        let mut package_index = PackageIndex::default();
+        package_index.add_package(package_a_1.clone());
+        package_index.add_package(package_a_2.clone());
+
+        let distro = create_distro();
+        let app_dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            app_dir.path().join(LOCKFILE_NAME),
+            Lockfile::new(
+                &Distro {
+                    architecture: ArchitectureName::ARM_64,
+                    ..distro.clone()
+                },
+                &[package_a_1],
+            )
+            .to_toml()
+            .unwrap(),
+        )
+        .unwrap();
+
+        let (lockfile, _) = enforce_lockfile(
+            app_dir.path(),
+            &Env::new(),
+            &distro,
+            &mut package_index,
+            &mut [],
+            Print::new(std::io::stdout()).h1("test"),
+        )
+        .unwrap();
+        assert_eq!(lockfile, None);
+        assert_eq!(
+            package_index.get_highest_available_version("package-a"),
+            Some(&package_a_2)
+        );
+
+        std::fs::write(
+            app_dir.path().join(LOCKFILE_NAME),
+            Lockfile::new(
+                &Distro {
+                    version: "22.04".to_string(),
+                    codename: "jammy".into(),
+                    ..distro.clone()
+                },
+                &[],
+            )
+            .to_toml()
+            .unwrap(),
+        )
+        .unwrap();
+        match enforce_lockfile(
+            app_dir.path(),
+            &Env::new(),
+            &distro,
+            &mut package_index,
+            &mut [],
+            Print::new(std::io::stdout()).h1("test"),
+        )
+        .unwrap_err()
+        {
+            libcnb::Error::BuildpackError(
+                DebianPackagesBuildpackError::DeterminePackagesToInstall(
+                    DeterminePackagesToInstallError::LockfileDistroMismatch(..),
+                ),
+            ) => {}
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn enforce_lockfile_with_unavailable_packages() {
+        let package_a = create_repository_package().name("package-a").call();
+        let mut package_index = PackageIndex::default();
+        package_index.add_package(create_repository_package().name("package-b").call());
+
+        let distro = create_distro();
+        let app_dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            app_dir.path().join(LOCKFILE_NAME),
+            Lockfile::new(&distro, &[package_a]).to_toml().unwrap(),
+        )
+        .unwrap();
+
+        match enforce_lockfile(
+            app_dir.path(),
+            &Env::new(),
+            &distro,
+            &mut package_index,
+            &mut [],
+            Print::new(std::io::stdout()).h1("test"),
+        )
+        .unwrap_err()
+        {
+            libcnb::Error::BuildpackError(
+                DebianPackagesBuildpackError::DeterminePackagesToInstall(
+                    DeterminePackagesToInstallError::LockedPackagesUnavailable(packages),
+                ),
+            ) => {
+                assert_eq!(
+                    packages
+                        .iter()
+                        .map(|package| package.name.as_str())
+                        .collect::<Vec<_>>(),
+                    vec!["package-a"]
+                );
+            }
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn apply_version_constraints_pins_highest_matching_version() {
+        let mut package_index = PackageIndex::default();
+        for version in ["5:6.0.16-1", "5:7.0.15-1", "5:7.2.4-1", "5:8.0.1-1"] {
+            package_index.add_package(
+                create_repository_package()
+                    .name("redis-tools")
+                    .version(version)
+                    .call(),
+            );
+        }
+        package_index.add_package(create_repository_package().name("curl").call());
+
+        let mut requested_packages = IndexSet::from([
+            RequestedPackage {
+                version: Some(VersionConstraint::from_str(">= 7.0, < 8").unwrap()),
+                ..RequestedPackage::from_str("redis-tools").unwrap()
+            },
+            RequestedPackage::from_str("curl").unwrap(),
+            // missing packages are left for validation to report
+            RequestedPackage {
+                version: Some(VersionConstraint::from_str("1.0").unwrap()),
+                ..RequestedPackage::from_str("not-indexed").unwrap()
+            },
+        ]);
+
+        apply_version_constraints(
+            &mut package_index,
+            &mut [],
+            &mut requested_packages,
+            Print::new(std::io::stdout()).h1("test"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            package_index.get_versions("redis-tools"),
+            vec!["5:7.2.4-1", "5:7.0.15-1"]
+        );
+        assert_eq!(
+            package_index
+                .get_highest_available_version("redis-tools")
+                .map(|package| package.version.as_str()),
+            Some("5:7.2.4-1")
+        );
+        assert_eq!(requested_packages.len(), 3);
+    }
+
+    #[test]
+    fn apply_version_constraints_selects_older_version() {
+        let mut package_index = PackageIndex::default();
+        for version in ["2.39-0ubuntu8", "2.39-0ubuntu8.3"] {
+            package_index.add_package(
+                create_repository_package()
+                    .name("libc6")
+                    .version(version)
+                    .call(),
+            );
+        }
+
+        apply_version_constraints(
+            &mut package_index,
+            &mut [],
+            &mut IndexSet::from([RequestedPackage::from_str("libc6=2.39-0ubuntu8").unwrap()]),
+            Print::new(std::io::stdout()).h1("test"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            package_index
+                .get_highest_available_version("libc6")
+                .map(|package| package.version.as_str()),
+            Some("2.39-0ubuntu8")
+        );
+    }
+
+    #[test]
+    fn apply_version_constraints_without_matching_version() {
+        let mut package_index = PackageIndex::default();
+        package_index.add_package(
+            create_repository_package()
+                .name("redis-tools")
+                .version("5:7.0.15-1")
+                .call(),
+        );
+        let requested_package = RequestedPackage {
+            version: Some(VersionConstraint::from_str(">= 8").unwrap()),
+            ..RequestedPackage::from_str("redis-tools").unwrap()
+        };
+
+        // optional packages are skipped
+        let mut requested_packages = IndexSet::from([RequestedPackage {
+            optional: true,
+            ..requested_package.clone()
+        }]);
+        apply_version_constraints(
+            &mut package_index,
+            &mut [],
+            &mut requested_packages,
+            Print::new(std::io::stdout()).h1("test"),
+        )
+        .unwrap();
+        assert!(requested_packages.is_empty());
+
+        match apply_version_constraints(
+            &mut package_index,
+            &mut [],
+            &mut IndexSet::from([requested_package]),
+            Print::new(std::io::stdout()).h1("test"),
+        )
+        .unwrap_err()
+        {
+            libcnb::Error::BuildpackError(
+                DebianPackagesBuildpackError::DeterminePackagesToInstall(
+                    DeterminePackagesToInstallError::VersionConstraintNotSatisfied {
+                        package,
+                        available_versions,
+                        ..
+                    },
+                ),
+            ) => {
+                assert_eq!(package, "redis-tools");
+                assert_eq!(available_versions, vec!["5:7.0.15-1"]);
+            }
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn check_locked_packages_reports_packages_missing_from_the_lockfile() {
+        let package_a = create_repository_package().name("package-a").call();
+        let package_b = create_repository_package().name("package-b").call();
+        let lockfile = Lockfile::new(&create_distro(), std::slice::from_ref(&package_a));
+
+        assert!(check_locked_packages(&lockfile, std::slice::from_ref(&package_a)).is_ok());
+        match check_locked_packages(&lockfile, &[package_a, package_b]).unwrap_err() {
+            DeterminePackagesToInstallError::PackagesNotLocked(packages) => {
+                assert_eq!(packages, vec!["package-b".to_string()]);
+            }
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn check_dependency_count_reports_largest_subtrees() {
+        let package_d = create_repository_package().name("package-d").call();
+        let package_c = create_repository_package().name("package-c").call();
+        let package_b = create_repository_package()
+            .name("package-b")
+            .depends(vec![&package_c, &package_d])
             .call();
-        let another_package_with_virtual_package_edit_distance_2_and_3 =
-            create_repository_package()
-                .name("another-virtual-package-provider")
-                .provides(vec![
-                    &format!("{non_existent_package}ab"),
-                    &format!("{non_existent_package}abc"),
-                ])
-                .call();
-        let package_with_edit_distance_4 = create_repository_package()
-            .name(&format!("{non_existent_package}1234"))
+        let package_e = create_repository_package().name("package-e").call();
+        let package_a = create_repository_package()
+            .name("package-a")
+            .depends(vec![&package_b, &package_e])
             .call();
 
-        let error = test_install_state()
+        let (_, package_notifications) = test_install_state()
             .with_package_index(vec![
-                &package_with_edit_distance_1,
-                &package_with_virtual_package_edit_distance_2_and_3,
-                &package_with_edit_distance_4,
-                &another_package_with_edit_distance_1,
-                &another_package_with_virtual_package_edit_distance_2_and_3,
+                &package_a, &package_b, &package_c, &package_d, &package_e,
             ])
-            .install(non_existent_package)
+            .install(&package_a.name)
             .call()
-            .unwrap_err();
+            .unwrap();
 
-        if let libcnb::Error::BuildpackError(
-            DebianPackagesBuildpackError::DeterminePackagesToInstall(
-                DeterminePackagesToInstallError::PackageNotFound(name, suggestions),
-            ),
-        ) = error
-        {
-            assert_eq!(name, non_existent_package.to_string());
-            assert_eq!(
-                suggestions,
-                vec![
-                    format!("{non_existent_package}1"),
-                    format!("{non_existent_package}a"),
-                    format!("{non_existent_package}12"),
-                    format!("{non_existent_package}ab"),
-                    format!("{non_existent_package}123"),
-                ]
-            );
-        } else {
-            panic!("not the expected error: {error:?}")
+        assert!(check_dependency_count(&package_a.name, &package_notifications, 0).is_ok());
+        assert!(check_dependency_count(&package_a.name, &package_notifications, 4).is_ok());
+
+        match check_dependency_count(&package_a.name, &package_notifications, 3).unwrap_err() {
+            DeterminePackagesToInstallError::TooManyDependencies {
+                package,
+                dependency_count,
+                max_dependencies,
+                largest_subtrees,
+            } => {
+                assert_eq!(package, "package-a");
+                assert_eq!(dependency_count, 4);
+                assert_eq!(max_dependencies, 3);
+                assert_eq!(
+                    largest_subtrees,
+                    vec![("package-b".to_string(), 3), ("package-e".to_string(), 1)]
+                );
+            }
+            e => panic!("Not the expected error - {e:?}"),
         }
     }
 
     #[test]
-    fn install_highest_version_of_package_when_there_are_multiple_versions() {
-        let package_name = "test-package";
-
-        let package_v0 = create_repository_package()
-            .name(package_name)
-            .version("1.2.2-2ubuntu0.22.04.2")
+    fn is_missing_package_checks_packages_and_providers() {
+        let package_a = create_repository_package()
+            .name("package-a")
+            .provides(vec!["virtual-package"])
             .call();
 
-        let package_v1 = create_repository_package()
-            .name(package_name)
-            .version("1.2.3-2ubuntu0.22.04.2")
+        let mut package_index = PackageIndex::default();
+        package_index.add_package(package_a.clone());
+
+        assert!(!is_missing_package("package-a", &package_index));
+        assert!(!is_missing_package("virtual-package", &package_index));
+        assert!(is_missing_package("package-b", &package_index));
+    }
+
+    #[test]
+    fn check_not_virtual_package_with_single_provider() {
+        let package_a = create_repository_package()
+            .name("package-a")
+            .provides(vec!["virtual-package"])
             .call();
 
-        assert!(
-            debversion::Version::from_str(package_v0.version.as_str()).unwrap()
-                < debversion::Version::from_str(package_v1.version.as_str()).unwrap()
-        );
+        let mut package_index = PackageIndex::default();
+        package_index.add_package(package_a.clone());
+
+        assert!(check_not_virtual_package(&package_a.name, &package_index).is_ok());
+        assert!(check_not_virtual_package("not-in-index", &package_index).is_ok());
+
+        match check_not_virtual_package("virtual-package", &package_index).unwrap_err() {
+            DeterminePackagesToInstallError::VirtualPackageResolutionDisabled(
+                package,
+                providers,
+            ) => {
+                assert_eq!(package, "virtual-package");
+                assert_eq!(providers, HashSet::from(["package-a".to_string()]));
+            }
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn install_package_available_from_multiple_sources() {
+        let public_package = RepositoryPackage {
+            repository_uri: RepositoryUri::from("http://public.example.com"),
+            ..create_repository_package().name("package-a").call()
+        };
+        let private_package = RepositoryPackage {
+            repository_uri: RepositoryUri::from("http://private.example.com"),
+            ..create_repository_package().name("package-a").call()
+        };
 
         let (new_packages_marked_for_install, package_notifications) = test_install_state()
-            .with_package_index(vec![&package_v0, &package_v1])
-            .install(package_name)
+            .with_package_index(vec![&public_package, &private_package])
+            .install(&public_package.name)
             .call()
             .unwrap();
 
         assert_eq!(
             new_packages_marked_for_install,
             IndexSet::from([create_package_marked_for_install()
-                .repository_package(&package_v1)
+                .repository_package(&private_package)
                 .call()])
         );
 
         assert_eq!(
             package_notifications,
-            IndexSet::from([PackageNotification::Added {
-                repository_package: package_v1,
-                dependency_path: vec![],
-                forced_install: false,
-            }])
+            IndexSet::from([
+                PackageNotification::Added {
+                    repository_package: private_package.clone(),
+                    dependency_path: vec![],
+                    forced_install: false,
+                },
+                PackageNotification::PreferredSource {
+                    repository_package: private_package,
+                    alternate_sources: vec![public_package.repository_uri],
+                },
+            ])
         );
     }
 
     #[test]
-    fn install_package_and_dependencies() {
-        let package_d = create_repository_package().name("package-d").call();
-
-        let package_c = create_repository_package()
-            .name("package-c")
-            .pre_depends(vec![&package_d])
-            .call();
-
-        let package_b = create_repository_package()
-            .name("package-b")
-            .depends(vec![&package_c])
-            .call();
-
-        let package_a = create_repository_package()
+    fn install_package_skipping_phased_update() {
+        let released_package = create_repository_package()
             .name("package-a")
-            .depends(vec![&package_b])
+            .version("1.0.0")
             .call();
+        let phased_package = RepositoryPackage {
+            phased_update_percentage: Some(10),
+            ..create_repository_package()
+                .name("package-a")
+                .version("1.0.1")
+                .call()
+        };
 
         let (new_packages_marked_for_install, package_notifications) = test_install_state()
-            .with_package_index(vec![&package_a, &package_b, &package_c, &package_d])
-            .install(&package_a.name)
+            .with_package_index(vec![&released_package, &phased_package])
+            .install(&released_package.name)
+            .skip_phased_updates(true)
             .call()
             .unwrap();
 
         assert_eq!(
             new_packages_marked_for_install,
-            IndexSet::from([
-                create_package_marked_for_install()
-                    .repository_package(&package_a)
-                    .call(),
-                create_package_marked_for_install()
-                    .repository_package(&package_b)
-                    .requested_by(&package_a.name)
-                    .call(),
-                create_package_marked_for_install()
-                    .repository_package(&package_c)
-                    .requested_by(&package_a.name)
-                    .call(),
-                create_package_marked_for_install()
-                    .repository_package(&package_d)
-                    .requested_by(&package_a.name)
-                    .call()
-            ])
+            IndexSet::from([create_package_marked_for_install()
+                .repository_package(&released_package)
+                .call()])
         );
 
         assert_eq!(
             package_notifications,
             IndexSet::from([
                 PackageNotification::Added {
-                    repository_package: package_a.clone(),
+                    repository_package: released_package.clone(),
                     dependency_path: vec![],
                     forced_install: false,
                 },
-                PackageNotification::Added {
-                    repository_package: package_b.clone(),
-                    dependency_path: vec![package_a.name.to_string()],
-                    forced_install: false,
-                },
-                PackageNotification::Added {
-                    repository_package: package_c.clone(),
-                    dependency_path: vec![package_a.name.to_string(), package_b.name.to_string()],
-                    forced_install: false,
+                PackageNotification::SkippedPhasedUpdate {
+                    repository_package: released_package,
+                    phased_version: "1.0.1".to_string(),
+                    phased_update_percentage: 10,
                 },
-                PackageNotification::Added {
-                    repository_package: package_d,
-                    dependency_path: vec![
-                        package_a.name.to_string(),
-                        package_b.name.to_string(),
-                        package_c.name.to_string()
-                    ],
-                    forced_install: false,
-                }
             ])
         );
     }
 
     #[test]
-    fn install_package_but_skip_dependencies() {
-        let package_d = create_repository_package().name("package-d").call();
-
-        let package_c = create_repository_package()
-            .name("package-c")
-            .pre_depends(vec![&package_d])
-            .call();
-
-        let package_b = create_repository_package()
-            .name("package-b")
-            .depends(vec![&package_c])
-            .call();
+    fn install_a_non_virtual_package_which_also_has_a_provider() {
+        let package_a = create_repository_package().name("package-a").call();
 
-        let package_a = create_repository_package()
-            .name("package-a")
-            .depends(vec![&package_b])
+        let package_providing_a = create_repository_package()
+            .name("package-a-provider")
+            .provides(vec![package_a.name.as_str()])
             .call();
 
         let (new_packages_marked_for_install, package_notifications) = test_install_state()
-            .with_package_index(vec![&package_a, &package_b, &package_c, &package_d])
+            .with_package_index(vec![&package_a, &package_providing_a])
             .install(&package_a.name)
-            .skip_dependencies(true)
             .call()
             .unwrap();
 
@@ -926,7 +2665,7 @@ mod test {
             new_packages_marked_for_install,
             IndexSet::from([create_package_marked_for_install()
                 .repository_package(&package_a)
-                .call(),])
+                .call()])
         );
 
         assert_eq!(
@@ -940,37 +2679,84 @@ mod test {
     }
 
     #[test]
-    fn install_a_non_virtual_package_which_also_has_a_provider() {
-        let package_a = create_repository_package().name("package-a").call();
+    fn install_metapackage_reports_expanded_packages() {
+        let package_c = create_repository_package().name("package-c").call();
 
-        let package_providing_a = create_repository_package()
-            .name("package-a-provider")
-            .provides(vec![package_a.name.as_str()])
+        let nested_metapackage = create_repository_package()
+            .name("nested-metapackage")
+            .section("universe/metapackages")
+            .depends(vec![&package_c])
             .call();
 
-        let (new_packages_marked_for_install, package_notifications) = test_install_state()
-            .with_package_index(vec![&package_a, &package_providing_a])
-            .install(&package_a.name)
+        let package_b = create_repository_package().name("package-b").call();
+
+        let metapackage = create_repository_package()
+            .name("metapackage")
+            .section("metapackages")
+            .depends(vec![&package_b, &nested_metapackage])
+            .call();
+
+        let (_, package_notifications) = test_install_state()
+            .with_package_index(vec![
+                &metapackage,
+                &package_b,
+                &nested_metapackage,
+                &package_c,
+            ])
+            .install(&metapackage.name)
             .call()
             .unwrap();
 
         assert_eq!(
-            new_packages_marked_for_install,
-            IndexSet::from([create_package_marked_for_install()
-                .repository_package(&package_a)
-                .call()])
+            strip_ansi_escapes::strip_str(
+                describe_metapackage_expansion(&package_notifications).unwrap_or_default()
+            ),
+            "`metapackage` is a metapackage that expanded to 2 packages: `package-b`, `package-c`"
+        );
+        assert!(
+            strip_ansi_escapes::strip_str(package_notifications[0].to_string())
+                .starts_with("Adding `metapackage@1.0.0` (metapackage)")
         );
+    }
+
+    #[test]
+    fn install_metapackage_without_dependencies() {
+        let package_b = create_repository_package().name("package-b").call();
+
+        let metapackage = create_repository_package()
+            .name("metapackage")
+            .section("metapackages")
+            .depends(vec![&package_b])
+            .call();
+
+        let (_, package_notifications) = test_install_state()
+            .with_package_index(vec![&metapackage, &package_b])
+            .install(&metapackage.name)
+            .skip_dependencies(true)
+            .call()
+            .unwrap();
 
         assert_eq!(
-            package_notifications,
-            IndexSet::from([PackageNotification::Added {
-                repository_package: package_a,
-                dependency_path: vec![],
-                forced_install: false,
-            }])
+            strip_ansi_escapes::strip_str(
+                describe_metapackage_expansion(&package_notifications).unwrap_or_default()
+            ),
+            "`metapackage` is a metapackage but none of the packages it depends on were added"
         );
     }
 
+    #[test]
+    fn install_package_without_metapackage_expansion() {
+        let package_a = create_repository_package().name("package-a").call();
+
+        let (_, package_notifications) = test_install_state()
+            .with_package_index(vec![&package_a])
+            .install(&package_a.name)
+            .call()
+            .unwrap();
+
+        assert_eq!(describe_metapackage_expansion(&package_notifications), None);
+    }
+
     #[test]
     fn handles_circular_dependencies() {
         let package_d = create_repository_package().name("package-d").call();
@@ -1381,6 +3167,8 @@ mod test {
         with_system_packages: Option<IndexSet<SystemPackage>>,
         skip_dependencies: Option<bool>,
         force: Option<bool>,
+        with_excluded: Option<IndexSet<PackageGlob>>,
+        skip_phased_updates: Option<bool>,
     ) -> BuildpackResult<(
         IndexSet<PackageMarkedForInstall>,
         IndexSet<PackageNotification>,
@@ -1395,6 +3183,7 @@ mod test {
         for value in with_package_index {
             package_index.add_package(value.clone());
         }
+        package_index.set_skip_phased_updates(skip_phased_updates.unwrap_or(false));
 
         let with_installed = with_installed.unwrap_or_default();
 
@@ -1406,6 +3195,8 @@ mod test {
 
         let mut visit_stack = IndexSet::new();
 
+        let excluded_packages = with_excluded.unwrap_or_default();
+
         let special_case_map: HashMap<&str, Vec<&str>> = SPECIAL_CASE_MAP
             .iter()
             .cloned()
@@ -1422,6 +3213,7 @@ mod test {
             &mut visit_stack,
             &mut package_notifications,
             &special_case_map,
+            &excluded_packages,
         )?;
 
         let new_packages_marked_for_install = packages_marked_for_install
@@ -1432,32 +3224,6 @@ mod test {
         Ok((new_packages_marked_for_install, package_notifications))
     }
 
-    #[builder]
-    fn create_repository_package(
-        name: &str,
-        version: Option<&str>,
-        provides: Option<Vec<&str>>,
-        depends: Option<Vec<&RepositoryPackage>>,
-        pre_depends: Option<Vec<&RepositoryPackage>>,
-    ) -> RepositoryPackage {
-        let join_deps = |vs: Vec<&RepositoryPackage>| {
-            vs.iter()
-                .map(|v| v.name.as_str())
-                .collect::<Vec<_>>()
-                .join(",")
-        };
-        RepositoryPackage {
-            name: name.to_string(),
-            version: version.unwrap_or(DEFAULT_VERSION).to_string(),
-            provides: provides.map(|vs| vs.join(",")),
-            repository_uri: RepositoryUri::from(""),
-            sha256sum: String::new(),
-            depends: depends.map(join_deps),
-            pre_depends: pre_depends.map(join_deps),
-            filename: String::new(),
-        }
-    }
-
     #[builder]
     fn create_package_marked_for_install(
         repository_package: &RepositoryPackage,
@@ -1470,12 +3236,24 @@ mod test {
     }
 
     #[builder]
-    fn create_system_package(package_name: &str, package_version: Option<&str>) -> SystemPackage {
+    fn create_system_package(
+        package_name: &str,
+        package_version: Option<&str>,
+        architecture: Option<&str>,
+    ) -> SystemPackage {
         SystemPackage {
             package_name: package_name.to_string(),
             package_version: package_version.unwrap_or(DEFAULT_VERSION).to_string(),
+            architecture: architecture.unwrap_or("amd64").to_string(),
         }
     }
 
-    const DEFAULT_VERSION: &str = "1.0.0";
+    fn create_distro() -> Distro {
+        Distro {
+            name: "ubuntu".to_string(),
+            version: "24.04".to_string(),
+            codename: "noble".into(),
+            architecture: crate::debian::ArchitectureName::AMD_64,
+        }
+    }
 }
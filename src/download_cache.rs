@@ -0,0 +1,188 @@
+use std::collections::HashSet;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::fs::{read_dir, remove_file, File};
+use tokio::io::AsyncReadExt;
+
+use crate::debian::RepositoryPackage;
+
+// Downloaded archives are kept in a cache-only layer named after their SHA-256 hash so they can be
+// reused whenever the packages layer is rebuilt (e.g.; when one of the requested packages changes)
+// without downloading the packages that stayed the same. The hash is verified before an archive is
+// moved into place and again before a cached archive is reused.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub(crate) struct DownloadCacheMetadata {
+    pub(crate) layout_version: u32,
+}
+
+pub(crate) const DOWNLOAD_CACHE_LAYOUT_VERSION: u32 = 1;
+
+const ARCHIVE_EXTENSION: &str = "deb";
+
+pub(crate) fn cached_archive_path(download_cache_dir: &Path, checksum: &str) -> PathBuf {
+    download_cache_dir.join(format!("{checksum}.{ARCHIVE_EXTENSION}"))
+}
+
+// Written here first and renamed once the hash matches.
+pub(crate) fn partial_archive_path(download_cache_dir: &Path, checksum: &str) -> PathBuf {
+    download_cache_dir.join(format!("{checksum}.{ARCHIVE_EXTENSION}.partial"))
+}
+
+// Returns the path of the package's archive when it's in the cache and still matches the hash. An
+// archive that doesn't (e.g.; one truncated by a full disk) is removed so it's downloaded again.
+pub(crate) async fn cached_archive(
+    download_cache_dir: &Path,
+    repository_package: &RepositoryPackage,
+) -> std::io::Result<Option<PathBuf>> {
+    let cached_path = cached_archive_path(download_cache_dir, &repository_package.checksum);
+    let mut file = match File::open(&cached_path).await {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let mut hasher = repository_package.checksum_algorithm.hasher();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    if hasher.finalize() == repository_package.checksum {
+        Ok(Some(cached_path))
+    } else {
+        remove_file(&cached_path).await?;
+        Ok(None)
+    }
+}
+
+// Removes the archives (and any partial downloads left by a failed build) that aren't used by the
+// packages to install so the cache doesn't grow with every package update. Returns the number of
+// files removed.
+pub(crate) async fn prune_download_cache(
+    download_cache_dir: &Path,
+    packages_to_install: &[RepositoryPackage],
+) -> std::io::Result<usize> {
+    let keep = packages_to_install
+        .iter()
+        .map(|package| cached_archive_path(download_cache_dir, &package.checksum))
+        .collect::<HashSet<_>>();
+
+    let mut entries = match read_dir(download_cache_dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+
+    let mut removed = 0;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let is_archive = path
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .is_some_and(|file_name| {
+                file_name.ends_with(&format!(".{ARCHIVE_EXTENSION}"))
+                    || file_name.ends_with(&format!(".{ARCHIVE_EXTENSION}.partial"))
+            });
+        if is_archive && !keep.contains(&path) {
+            remove_file(&path).await?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::debian::ChecksumAlgorithm;
+    use crate::test_support::create_repository_package;
+
+    use super::*;
+
+    fn package(name: &str, checksum: &str) -> RepositoryPackage {
+        create_repository_package()
+            .name(name)
+            .version("1.0")
+            .repository_uri("http://archive.ubuntu.com/ubuntu")
+            .filename(&format!("pool/main/{name}.deb"))
+            .checksum(checksum)
+            .call()
+    }
+
+    #[test]
+    fn test_cached_archive_path() {
+        assert_eq!(
+            cached_archive_path(Path::new("/layers/downloads"), "abc123"),
+            PathBuf::from("/layers/downloads/abc123.deb")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cached_archive() {
+        let download_cache_dir = tempfile::tempdir().unwrap();
+        let download_cache_path = download_cache_dir.path();
+        let mut hasher = ChecksumAlgorithm::Sha256.hasher();
+        hasher.update(b"archive");
+        let checksum = hasher.finalize();
+        let package = package("curl", &checksum);
+
+        assert_eq!(
+            cached_archive(download_cache_path, &package).await.unwrap(),
+            None
+        );
+
+        fs::write(
+            cached_archive_path(download_cache_path, &checksum),
+            "archive",
+        )
+        .unwrap();
+        assert_eq!(
+            cached_archive(download_cache_path, &package).await.unwrap(),
+            Some(cached_archive_path(download_cache_path, &checksum))
+        );
+
+        fs::write(cached_archive_path(download_cache_path, &checksum), "arch").unwrap();
+        assert_eq!(
+            cached_archive(download_cache_path, &package).await.unwrap(),
+            None
+        );
+        assert!(!cached_archive_path(download_cache_path, &checksum).exists());
+    }
+
+    #[tokio::test]
+    async fn test_prune_download_cache() {
+        let download_cache_dir = tempfile::tempdir().unwrap();
+        let download_cache_path = download_cache_dir.path();
+        for file_name in ["aaa.deb", "bbb.deb", "ccc.deb.partial", "notes.txt"] {
+            fs::write(download_cache_path.join(file_name), "").unwrap();
+        }
+
+        let removed = prune_download_cache(download_cache_path, &[package("a", "aaa")])
+            .await
+            .unwrap();
+
+        assert_eq!(removed, 2);
+        assert!(download_cache_path.join("aaa.deb").exists());
+        assert!(!download_cache_path.join("bbb.deb").exists());
+        assert!(!download_cache_path.join("ccc.deb.partial").exists());
+        assert!(download_cache_path.join("notes.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_prune_missing_download_cache() {
+        let download_cache_dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            prune_download_cache(&download_cache_dir.path().join("missing"), &[])
+                .await
+                .unwrap(),
+            0
+        );
+    }
+}
@@ -1,9 +1,30 @@
-use crate::config::{ConfigError, ParseConfigError, ParseRequestedPackageError};
+use crate::alternatives::CreateAlternativesError;
+use crate::check_sources::CheckSourcesError;
+use crate::client_certificates::ClientCertificateError;
+use crate::config::{
+    ConfigError, ParseConfigError, ParseCustomSourceError, ParseEnvScopeError,
+    ParseExistingFilesPolicyError, ParseLocaleError, ParseNetworkSettingsError,
+    ParsePackageGlobError, ParsePackageRegexError, ParsePhasedUpdatesPolicyError, ParsePpaError,
+    ParsePresetError, ParseRequestedPackageError, ParseScriptFailurePolicyError,
+    ParseSnapshotError, ParseVersionConstraintError, INSTALL_ENV_VAR, MAX_RETRIES_ENV_VAR,
+    MAX_RETRY_INTERVAL_ENV_VAR, MIN_RETRY_INTERVAL_ENV_VAR, TIMEOUT_ENV_VAR,
+};
 use crate::create_package_index::CreatePackageIndexError;
-use crate::debian::UnsupportedDistroError;
+use crate::debian::{UnsupportedDistroError, UnsupportedTargetCodenameError};
 use crate::determine_packages_to_install::DeterminePackagesToInstallError;
 use crate::errors::ErrorType::{Framework, Internal, UserFacing};
-use crate::install_packages::InstallPackagesError;
+use crate::fetch_signing_keys::FetchSigningKeysError;
+use crate::generate_locales::GenerateLocalesError;
+use crate::imagemagick::ImageMagickError;
+use crate::install_packages::{InstallPackagesError, ScriptFailure};
+use crate::java_keystore::JavaKeystoreError;
+use crate::keyserver::KeyserverError;
+use crate::lockfile::{IGNORE_LOCKFILE_ENV_VAR, LOCKFILE_NAME};
+use crate::package_integrations::PackageIntegrationError;
+use crate::pgp::LoadSigningKeyError;
+use crate::redirects::describe_request_error;
+use crate::resolve_ppas::ResolvePpasError;
+use crate::validate_requested_packages::ValidateRequestedPackagesError;
 use crate::DebianPackagesBuildpackError;
 use std::collections::BTreeSet;
 use std::time::SystemTimeError;
@@ -34,7 +55,14 @@ fn on_buildpack_error(error: DebianPackagesBuildpackError) -> ErrorMessage {
     match error {
         DebianPackagesBuildpackError::Config(e) => on_config_error(e),
         DebianPackagesBuildpackError::UnsupportedDistro(e) => on_unsupported_distro_error(e),
+        DebianPackagesBuildpackError::LoadSigningKey(e) => on_load_signing_key_error(e),
+        DebianPackagesBuildpackError::ResolvePpas(e) => on_resolve_ppas_error(e),
+        DebianPackagesBuildpackError::FetchSigningKeys(e) => on_fetch_signing_keys_error(e),
+        DebianPackagesBuildpackError::CheckSources(e) => on_check_sources_error(e),
         DebianPackagesBuildpackError::CreatePackageIndex(e) => on_create_package_index_error(e),
+        DebianPackagesBuildpackError::ValidateRequestedPackages(e) => {
+            on_validate_requested_packages_error(e)
+        }
         DebianPackagesBuildpackError::DeterminePackagesToInstall(e) => {
             on_determine_packages_to_install_error(e)
         }
@@ -143,6 +171,30 @@ fn on_config_error(error: ConfigError) -> ErrorMessage {
                             .call()
                     }
 
+                    ParseRequestedPackageError::UnsupportedArchitecture(architecture) => {
+                        let architecture = style::value(architecture);
+                        let example = style::value("libc6:i386");
+
+                        create_error()
+                            .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                            .header(format!("Error parsing {config_file} with unsupported package architecture"))
+                            .body(formatdoc! { "
+                                The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                                complete the build but we found a package requested for the unsupported \
+                                architecture {architecture} in the key {root_config_key}.
+
+                                A package can be requested for another architecture by adding the \
+                                Debian architecture name after its name (e.g.; {example}). The \
+                                architecture must be one of \"amd64\", \"arm64\", \"armhf\", \
+                                \"ppc64el\", \"s390x\", \"riscv64\", or \"i386\".
+
+                                Suggestions:
+                                - See the buildpack documentation for the proper usage for this configuration at \
+                                {configuration_doc_url}
+                            " })
+                            .call()
+                    }
+
                     ParseRequestedPackageError::UnexpectedTomlValue(value) => {
                         let string_example = "\"package-name\"";
                         let inline_table_example =
@@ -171,1232 +223,7493 @@ fn on_config_error(error: ConfigError) -> ErrorMessage {
                             .debug_info(format!("Invalid type {value_type} with value {value}"))
                             .call()
                     }
-                },
-            }
-        }
-    }
-}
 
-fn on_unsupported_distro_error(error: UnsupportedDistroError) -> ErrorMessage {
-    let UnsupportedDistroError {
-        name,
-        version,
-        architecture,
-    } = error;
+                    ParseRequestedPackageError::InvalidEnvScope(ParseEnvScopeError(value)) => {
+                        on_invalid_env_scope(&config_file, &root_config_key, &configuration_doc_url, &value)
+                    }
 
-    create_error()
-        .error_type(Internal)
-        .header("Unsupported distribution")
-        .body(formatdoc! { "
-            The {BUILDPACK_NAME} doesn't support the {name} {version} ({architecture}) distribution.
+                    ParseRequestedPackageError::InvalidScriptFailurePolicy(
+                        ParseScriptFailurePolicyError(value),
+                    ) => {
+                        let on_script_failure_key = style::value("on_script_failure");
+                        let value = style::value(value.trim());
 
-            Supported distributions:
-            - Ubuntu 24.04 (amd64, arm64)
-            - Ubuntu 22.04 (amd64)
-        " })
-        .call()
-}
+                        create_error()
+                            .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                            .header(format!("Error parsing {config_file} with invalid on_script_failure"))
+                            .body(formatdoc! { "
+                                The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                                complete the build but we found an invalid value {value} for the key \
+                                {on_script_failure_key} in {root_config_key}.
 
-#[allow(clippy::too_many_lines)]
-fn on_create_package_index_error(error: CreatePackageIndexError) -> ErrorMessage {
-    let canonical_status_url = get_canonical_status_url();
+                                The value must be one of \"warn\" or \"error\".
 
-    match error {
-        CreatePackageIndexError::NoSources => {
-            create_error()
-                .error_type(Internal)
-                .header("No sources to update")
-                .body(indoc! { "
-                    The distribution has no sources to update packages from.
-                " })
-                .call()
-        }
+                                Suggestions:
+                                - See the buildpack documentation for the proper usage for this configuration at \
+                                {configuration_doc_url}
+                            " })
+                            .call()
+                    }
 
-        CreatePackageIndexError::TaskFailed(e) => {
-            create_error()
-                .error_type(Internal)
-                .header("Task failure while updating sources")
-                .body(indoc! { "
-                    A background task responsible for updating sources failed to complete.
-                " })
-                .debug_info(e.to_string())
-                .call()
-        }
+                    ParseRequestedPackageError::InvalidVersionConstraint(
+                        ParseVersionConstraintError(value),
+                    ) => {
+                        let version_key = style::value("version");
+                        let value = style::value(value.trim());
 
-        CreatePackageIndexError::InvalidLayerName(url, e) => {
-            create_error()
-                .error_type(Internal)
-                .header("Invalid layer name")
-                .body(formatdoc! { "
-                    For caching purposes, a unique layer name is generated for Debian Release files \
-                    and Package indices based on their download urls. The generated name for the \
-                    following url was invalid:
-                    - {url}
+                        create_error()
+                            .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                            .header(format!("Error parsing {config_file} with invalid version"))
+                            .body(formatdoc! { "
+                                The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                                complete the build but we found an invalid value {value} for the key \
+                                {version_key} in {root_config_key}.
 
-                    You can find the invalid layer name in the debug information above.
-                " })
-                .debug_info(e.to_string())
-                .call()
-        }
+                                The value must be a comma-separated list of Debian versions that \
+                                can each start with one of the operators \"=\", \"!=\", \">\", \">=\", \
+                                \"<\", or \"<=\" (e.g.; \">= 7.0, < 8\").
 
-        CreatePackageIndexError::GetReleaseRequest(e) => {
-            create_error()
-                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::Yes))
-                .header("Failed to request Release file")
-                .body(formatdoc! { "
-                    While updating package sources, a request to download a Release file failed. \
-                    This error can occur due to an unstable network connection or an issue with the upstream \
-                    Debian package repository.
+                                Suggestions:
+                                - See the buildpack documentation for the proper usage for this configuration at \
+                                {configuration_doc_url}
+                            " })
+                            .call()
+                    }
 
-                    Suggestions:
-                    - Check the status of {canonical_status_url} for any reported issues.
-                " })
-                .debug_info(e.to_string())
-                .call()
-        }
+                    ParseRequestedPackageError::InvalidPackageGlob(package_glob) => {
+                        let package_glob = style::value(package_glob);
 
-        CreatePackageIndexError::ReadGetReleaseResponse(e) => {
-            create_error()
-                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::Yes))
-                .header("Failed to download Release file")
-                .body(formatdoc! { "
-                    While updating package sources, an error occurred while downloading a Release file. \
-                    This error can occur due to an unstable network connection or an issue with the upstream \
-                    Debian package repository.
+                        create_error()
+                            .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                            .header(format!("Error parsing {config_file} with invalid package pattern"))
+                            .body(formatdoc! { "
+                                The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                                complete the build but we found an invalid package pattern {package_glob} \
+                                in the key {root_config_key}.
 
-                    Suggestions:
-                    - Check the status of {canonical_status_url} for any reported issues.
-                " })
-                .debug_info(e.to_string())
-                .call()
-        }
+                                Package patterns must consist only of lowercase letters (a-z), \
+                                digits (0-9), plus (+) and minus (-) signs, periods (.), and the \
+                                wildcards (*) and (?).
 
-        CreatePackageIndexError::CreatePgpCertificate(e) => {
-            create_error()
-                .error_type(Internal)
-                .header("Failed to load verifying PGP certificate")
-                .body(indoc! { "
-                    The PGP certificate used to verify downloaded release files failed to load. This \
-                    error indicates there's a problem with the format of the certificate file the \
-                    distribution uses.
+                                Suggestions:
+                                - See the buildpack documentation for the proper usage for this configuration at \
+                                {configuration_doc_url}
+                            " })
+                            .call()
+                    }
 
-                    Suggestions:
-                    - Verify the format of the certificates found in the ./keys directory of this \
-                    buildpack's repository. See https://cirw.in/gpg-decoder
-                    - Extract new certificates by running the ./scripts/extract_keys.sh script found \
-                    in this buildpack's repository.
-                " })
-                .debug_info(e.to_string())
-                .call()
-        }
+                    ParseRequestedPackageError::InvalidPackageRegex(ParsePackageRegexError {
+                        value,
+                        reason,
+                    }) => {
+                        let pattern_key = style::value("pattern");
+                        let value = style::value(value);
 
-        CreatePackageIndexError::CreatePgpVerifier(e) => {
-            create_error()
-                .error_type(Internal)
-                .header("Failed to verify Release file")
-                .body(indoc! { "
-                    The PGP signature of the downloaded release file failed verification. This error can \
-                    occur if the maintainers of the Debian repository changed the process for signing \
-                    release files.
+                        create_error()
+                            .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                            .header(format!("Error parsing {config_file} with invalid package regex"))
+                            .body(formatdoc! { "
+                                The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                                complete the build but we found an invalid value {value} for the key \
+                                {pattern_key} in {root_config_key}.
 
-                    Suggestions:
-                    - Verify if the keys changed by running the ./scripts/extract_keys.sh \
-                    script found in this buildpack's repository.
-                " })
-                .debug_info(e.to_string())
-                .call()
-        }
+                                The value must be a string containing a regular expression that's \
+                                matched against package names (e.g.; \"^libreoffice-core.*\").
 
-        CreatePackageIndexError::WriteReleaseLayer(file, e) => {
-            let file = file_value(file);
-            create_error()
-                .error_type(Internal)
-                .header("Failed to write Release file to layer")
-                .body(formatdoc! { "
-                    An unexpected I/O error occurred while writing release data to {file}.
-                " })
-                .debug_info(e.to_string())
-                .call()
-        }
+                                Suggestions:
+                                - See the regular expression syntax at {regex_syntax_url}
+                                - See the buildpack documentation for the proper usage for this configuration at \
+                                {configuration_doc_url}
+                            ",
+                                regex_syntax_url = style::url("https://docs.rs/regex/latest/regex/#syntax"),
+                            })
+                            .debug_info(reason)
+                            .call()
+                    }
 
-        CreatePackageIndexError::ReadReleaseFile(file, e) => {
-            let file = file_value(file);
-            create_error()
-                .error_type(Internal)
-                .header("Failed to read Release file from layer")
-                .body(formatdoc! { "
-                    An unexpected I/O error occurred while reading Release data from {file}.
-                " })
-                .debug_info(e.to_string())
-                .call()
-        }
+                    ParseRequestedPackageError::NameAndPattern => {
+                        let name_key = style::value("name");
+                        let pattern_key = style::value("pattern");
 
-        CreatePackageIndexError::ParseReleaseFile(file, e) => {
-            let file = file_value(file);
-            create_error()
-                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::Yes))
-                .header("Failed to parse Release file data")
-                .body(formatdoc! { "
-                    We couldn't parse the Release file data stored in {file}. This error is most likely \
-                    a buildpack bug. It can also be caused by cached data that's no longer valid or an \
-                    issue with the upstream repository.
+                        create_error()
+                            .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                            .header(format!("Error parsing {config_file} with both name and pattern"))
+                            .body(formatdoc! { "
+                                The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                                complete the build but we found a package in the key {root_config_key} \
+                                that sets both {name_key} and {pattern_key}.
 
-                    Suggestions:
-                    - Run the build again with a clean cache.
-                " })
-                .debug_info(e.to_string())
-                .call()
-        }
+                                A package is either requested by its {name_key} or selected with a \
+                                regular expression set as its {pattern_key}, but not both.
 
-        CreatePackageIndexError::MissingSha256ReleaseHashes(release_uri) => {
-            let release_uri = style::url(release_uri.as_str());
-            create_error()
-                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::Yes))
-                .header("Missing SHA256 Release hash")
-                .body(formatdoc! { "
-                    The Release file from {release_uri} is missing the SHA256 key which is required \
-                    according to the documented Debian repository format. This error is most likely an issue \
-                    with the upstream repository. See https://wiki.debian.org/DebianRepository/Format
-                " })
-                .call()
-        }
+                                Suggestions:
+                                - See the buildpack documentation for the proper usage for this configuration at \
+                                {configuration_doc_url}
+                            " })
+                            .call()
+                    }
 
-        CreatePackageIndexError::MissingPackageIndexReleaseHash(release_uri, package_index) => {
-            let release_uri = style::url(release_uri.as_str());
-            let package_index = style::value(package_index);
-            create_error()
-                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::Yes))
-                .header("Missing Package Index")
-                .body(formatdoc! { "
-                    The Release file from {release_uri} is missing an entry for {package_index} within \
-                    the SHA256 section. This error is most likely a buildpack bug but can also \
-                    be an issue with the upstream repository.
+                    ParseRequestedPackageError::NameAndVersion => {
+                        let name_key = style::value("name");
+                        let version_key = style::value("version");
 
-                    Suggestions:
-                    - Verify if {package_index} is under the SHA256 section of {release_uri}
-                " })
-                .call()
-        }
+                        create_error()
+                            .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                            .header(format!("Error parsing {config_file} with a version in both name and version"))
+                            .body(formatdoc! { "
+                                The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                                complete the build but we found a package in the key {root_config_key} \
+                                that sets a version in both {name_key} and {version_key}.
 
-        CreatePackageIndexError::GetPackagesRequest(e) => {
-            create_error()
-                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::Yes))
-                .header("Failed to request Package Index file")
-                .body(formatdoc! { "
-                    While updating package sources, a request to download a Package Index file failed. \
-                    This error can occur due to an unstable network connection or an issue with the upstream \
-                    Debian package repository.
+                                A version can either be added to the {name_key} of a package (e.g.; \
+                                \"redis-tools=5:7.0.15-1build2\") or set as its {version_key}, but not both.
 
-                    Suggestions:
-                    - Check the status of {canonical_status_url} for any reported issues.
-                " })
-                .debug_info(e.to_string())
-                .call()
-        }
-
-        CreatePackageIndexError::WritePackagesLayer(file, e) => {
-            let file = file_value(file);
-            create_error()
-                .error_type(Internal)
-                .header("Failed to write Package Index file to layer")
-                .body(formatdoc! { "
-                    An unexpected I/O error occurred while writing Package Index data to {file}.
-                " })
-                .debug_info(e.to_string())
-                .call()
-        }
+                                Suggestions:
+                                - See the buildpack documentation for the proper usage for this configuration at \
+                                {configuration_doc_url}
+                            " })
+                            .call()
+                    }
 
-        CreatePackageIndexError::WritePackageIndexFromResponse(file, e) => {
-            let file = file_value(file);
-            create_error()
-                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::Yes))
-                .header("Failed to download Package Index file")
-                .body(formatdoc! { "
-                    While updating package sources, an error occurred while downloading a Package Index \
-                    file to {file}. This error can occur due to an unstable network connection or an issue \
-                    with the upstream Debian package repository.
+                    ParseRequestedPackageError::InvalidMaxMatches(value) => {
+                        let max_matches_key = style::value("max_matches");
+                        let value = style::value(value.trim());
 
-                    Suggestions:
-                    - Check the status of {canonical_status_url} for any reported issues.
-                " })
-                .debug_info(e.to_string())
-                .call()
-        }
+                        create_error()
+                            .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                            .header(format!("Error parsing {config_file} with invalid max_matches"))
+                            .body(formatdoc! { "
+                                The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                                complete the build but we found an invalid value {value} for the key \
+                                {max_matches_key} in {root_config_key}.
 
-        CreatePackageIndexError::ChecksumFailed {
-            url,
-            expected,
-            actual,
-        } => {
-            let url = style::url(url);
-            let expected = style::value(expected);
-            let actual = style::value(actual);
-            create_error()
-                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
-                .header("Package Index checksum verification failed")
-                .body(formatdoc! { "
-                    While updating package sources, an error occurred while verifying the checksum \
-                    of the Package Index at {url}. This error can occur due to an issue with the upstream \
-                    Debian package repository.
+                                The value must be a positive integer.
 
-                    Checksum:
-                    - Expected: {expected}
-                    - Actual: {actual}
-                " })
-                .call()
-        }
+                                Suggestions:
+                                - See the buildpack documentation for the proper usage for this configuration at \
+                                {configuration_doc_url}
+                            " })
+                            .call()
+                    }
+                },
 
-        CreatePackageIndexError::CpuTaskFailed(e) => {
-            create_error()
-                .error_type(Internal)
-                .header("Task failure while reading Package Index data")
-                .body(indoc! { "
-                    A background task responsible for reading Package Index data failed to complete.
-                " })
-                .debug_info(e.to_string())
-                .call()
-        }
+                ParseConfigError::InvalidEnvScope(ParseEnvScopeError(value)) => {
+                    on_invalid_env_scope(&config_file, &root_config_key, &configuration_doc_url, &value)
+                }
 
-        CreatePackageIndexError::ReadPackagesFile(file, e) => {
-            let file = file_value(file);
-            create_error()
-                .error_type(Internal)
-                .header("Failed to read Package Index file")
-                .body(formatdoc! { "
-                    An unexpected I/O error occurred while reading Package Index data from {file}.
-                " })
-                .debug_info(e.to_string())
-                .call()
-        }
+                ParseConfigError::ParseSource(error) => {
+                    let sources_key = style::value("sources");
+                    let source_example = indoc! { r#"
+                        [[com.heroku.buildpacks.deb-packages.sources]]
+                        uri = "https://repo.example.com/ubuntu"
+                        suites = ["noble"]
+                        components = ["main"]
+                        signed_by = """
+                        -----BEGIN PGP PUBLIC KEY BLOCK-----
+                        ...
+                        -----END PGP PUBLIC KEY BLOCK-----
+                        """
+                    "# };
+                    let (problem, debug_info) = describe_custom_source_error(error, &sources_key);
 
-        CreatePackageIndexError::ParsePackages(file, errors) => {
-            let file = file_value(file);
-            let body_start = formatdoc! { "
-                We can't parse the Package Index file data stored in {file}. This error is most likely \
-                a buildpack bug. It can also be caused by cached data that's no longer valid or an issue \
-                with the upstream repository.
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with invalid sources"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but {problem} in {root_config_key}.
+
+                            Each source must be a table with a {uri_key} string, non-empty {suites_key} \
+                            and {components_key} arrays, and a {signed_by_key} string containing the \
+                            ASCII-armored public key used to sign the repository (or a {signed_by_file_key} \
+                            string with the path of a key file in the application directory or a \
+                            {key_fingerprint_key} string to download the key from the Ubuntu keyserver). \
+                            The {signed_by_key} string can be left out when the optional {signature_policy_key} string is set \
+                            to {optional} or {disabled} instead of {required} (the default) or when \
+                            {trusted_key} is {true_value}. For example:
+
+                            {source_example}
+                            Suggestions:
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        ",
+                            uri_key = style::value("uri"),
+                            suites_key = style::value("suites"),
+                            components_key = style::value("components"),
+                            signed_by_key = style::value("signed_by"),
+                            signed_by_file_key = style::value("signed_by_file"),
+                            key_fingerprint_key = style::value("key_fingerprint"),
+                            signature_policy_key = style::value("signature_policy"),
+                            required = style::value("required"),
+                            optional = style::value("optional"),
+                            disabled = style::value("disabled"),
+                            trusted_key = style::value("trusted"),
+                            true_value = style::value("true"),
+                        })
+                        .maybe_debug_info(debug_info.map(|value| value.trim().to_string()))
+                        .call()
+                }
 
-                Parsing errors:
-            " }.trim_end().to_string();
-            let body_error_details = errors
-                .iter()
-                .map(|e| format!("- {e}"))
-                .collect::<Vec<_>>()
-                .join("\n");
-            let body_end = indoc! { "
-                Suggestions:
-                - Run the build again with a clean cache.
-            " };
-            create_error()
-                .error_type(Internal)
-                .header("Failed to parse Package Index file")
-                .body(format!(
-                    "{body_start}\n{body_error_details}\n\n{body_end}"
-                ))
-                .call()
-        }
+                ParseConfigError::ParseNetwork(error) => {
+                    let network_key = style::value("network");
+                    let network_example = indoc! { "
+                        [com.heroku.buildpacks.deb-packages.network]
+                        timeout = 300
+                        max_retries = 5
+                        min_retry_interval = 1
+                        max_retry_interval = 1800
+                    " };
+                    let problem = match error {
+                        ParseNetworkSettingsError::InvalidValue(key, value) => format!(
+                            "we found an invalid value {value} for the key {key} in the {network_key} table",
+                            value = style::value(value.trim()),
+                            key = style::value(key),
+                        ),
+                        ParseNetworkSettingsError::InvalidRetryIntervals(min, max) => format!(
+                            "the {min_key} ({min}s) is greater than the {max_key} ({max}s) in the \
+                            {network_key} table",
+                            min_key = style::value("min_retry_interval"),
+                            max_key = style::value("max_retry_interval"),
+                            min = min.as_secs(),
+                            max = max.as_secs(),
+                        ),
+                        ParseNetworkSettingsError::UnexpectedTomlValue(value) => format!(
+                            "we found an invalid value {value} for the key {network_key}",
+                            value = style::value(value.trim()),
+                        ),
+                    };
 
-        CreatePackageIndexError::SystemTimeError(e) => {
-            create_error()
-                .error_type(Internal)
-                .header("Failed to get system time in CreatePackageIndex")
-                .body(format!("System time error: {}", e))
-                .debug_info(e.to_string())                
-                .call()
-        }
-    }
-}
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with invalid network settings"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but {problem} in {root_config_key}.
 
-fn on_determine_packages_to_install_error(error: DeterminePackagesToInstallError) -> ErrorMessage {
-    match error {
-        DeterminePackagesToInstallError::ReadSystemPackages(file, e) => {
-            let file = file_value(file);
-            create_error()
-                .error_type(Internal)
-                .header("Failed to read system packages")
-                .body(formatdoc! { "
-                    An unexpected I/O error occurred while reading system packages from {file}.
-                "})
-                .debug_info(e.to_string())
-                .call()
-        }
+                            The {network_key} table can set the request {timeout_key} and the \
+                            {min_retry_interval_key} and {max_retry_interval_key} between retries in \
+                            seconds along with the {max_retries_key} for failed requests. Each value must \
+                            be a non-negative integer and the {timeout_key} must be greater than 0. \
+                            For example:
 
-        DeterminePackagesToInstallError::ParseSystemPackage(file, package_data, e) => {
-            let file = file_value(file);
-            create_error()
-                .error_type(Internal)
-                .header("Failed to parse system package")
-                .body(formatdoc! { "
-                    An unexpected parsing error occurred while reading system packages from {file}.
-                "})
-                .debug_info(format!("{e}\n\nPackage:\n{package_data}"))
-                .call()
-        }
+                            {network_example}
+                            Suggestions:
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        ",
+                            timeout_key = style::value("timeout"),
+                            max_retries_key = style::value("max_retries"),
+                            min_retry_interval_key = style::value("min_retry_interval"),
+                            max_retry_interval_key = style::value("max_retry_interval"),
+                        })
+                        .call()
+                }
 
-        DeterminePackagesToInstallError::PackageNotFound(package_name, suggested_packages) => {
-            let package_name = style::value(package_name);
-            let package_search_url = get_package_search_url();
-            let suggestions = if suggested_packages.is_empty() {
-                "- No similarly named packages found".to_string()
-            } else {
-                suggested_packages
-                    .into_iter()
-                    .map(|name| format!("- {}", style::value(name)))
-                    .collect::<Vec<_>>()
-                    .join("\n")
-            };
-            create_error()
-                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
-                .header("Package not found")
-                .body(formatdoc! { "
-                    We can't find {package_name} in the Package Index. If this package is listed in the \
-                    packages to install for this buildpack then the name is most likely misspelled. Otherwise, \
-                    it can be an issue with the upstream Debian package repository.
+                ParseConfigError::InvalidCheckReleaseDates(value) => on_invalid_boolean(
+                    &config_file,
+                    &root_config_key,
+                    &configuration_doc_url,
+                    "check_release_dates",
+                    &value,
+                ),
 
-                    Did you mean?
-                    {suggestions}
+                ParseConfigError::InvalidPackageIndexCacheDays(value) => {
+                    let package_index_cache_days_key = style::value("package_index_cache_days");
+                    let value = style::value(value.trim());
 
-                    Suggestions:
-                    - Verify the package name is correct and exists for the target distribution at \
-                    {package_search_url}
-                " })
-                .call()
-        }
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with invalid package_index_cache_days"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but we found an invalid value {value} for the key \
+                            {package_index_cache_days_key} in {root_config_key}.
 
-        DeterminePackagesToInstallError::VirtualPackageMustBeSpecified(package, providers) => {
-            let package = style::value(package);
-            let body_start = indoc! { "
-                Sometimes there are several packages which offer more-or-less the same functionality. \
-                In this case, Debian repositories define a virtual package and one or more actual \
-                packages provide an implementation for this virtual package. When multiple providers \
-                are found for a requested package, this buildpack can't automatically choose which \
-                one is the desired implementation.
+                            The value must be a non-negative integer. Use 0 to download the package \
+                            indexes on every build.
 
-                Providing packages:
-            " };
-            let body_provider_details = providers
-                .iter()
-                .collect::<BTreeSet<_>>()
-                .iter()
-                .map(|provider| format!("- {provider}"))
-                .collect::<Vec<_>>()
-                .join("\n");
-            let body_end = formatdoc! { "
-                Suggestions:
-                - Replace the virtual package {package} with one of the above providers.
-            " };
-            create_error()
-                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
-                .header(format!(
-                    "Multiple providers were found for the package {package}"
-                ))
-                .body(format!("{body_start}{body_provider_details}\n\n{body_end}"))
-                .call()
-        }
+                            Suggestions:
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        " })
+                        .call()
+                }
 
-        DeterminePackagesToInstallError::SystemTimeError(e) => {
-            create_error()
-                .error_type(Internal)
-                .header("Failed to get system time in DeterminePackagesToInstall")
-                .body(format!("System time error: {}", e))
-                .debug_info(e.to_string())                
-                .call()
-        }        
-    }
-}
+                ParseConfigError::InvalidSnapshot(ParseSnapshotError(value)) => {
+                    let snapshot_key = style::value("snapshot");
+                    let value = style::value(value.trim());
 
-#[allow(clippy::too_many_lines)]
-fn on_install_packages_error(error: InstallPackagesError) -> ErrorMessage {
-    let canonical_status_url = get_canonical_status_url();
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with invalid snapshot"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but we found an invalid value {value} for the key \
+                            {snapshot_key} in {root_config_key}.
 
-    match error {
-        InstallPackagesError::TaskFailed(e) => create_error()
-            .error_type(Internal)
-            .header("Task failure while installing packages")
-            .body(indoc! { "
-                A background task responsible for installing failed to complete.
-            " })
-            .debug_info(e.to_string())
-            .call(),
+                            The value must be a UTC timestamp in the format used by the Ubuntu and \
+                            Debian snapshot services (e.g.; {example}).
 
-        InstallPackagesError::InvalidFilename(package, filename) => {
-            let package = style::value(package);
-            let filename = style::value(filename);
-            create_error()
-                .error_type(Internal)
-                .header(format!("Could not determine file name for {package}"))
-                .body(formatdoc! { "
-                    The package information for {package} contains a Filename field of {filename} \
-                    which produces an invalid name to use as a download path.
-                " })
-                .call()
-        }
-
-        InstallPackagesError::RequestPackage(package, e) => {
-            let package = style::value(package.name);
-            create_error()
-                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::Yes))
-                .header("Failed to request package")
-                .body(formatdoc! { "
-                    While installing packages, an error occurred while downloading {package}. \
-                    This error can occur due to an unstable network connection or an issue \
-                    with the upstream Debian package repository.
+                            Suggestions:
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        ", example = style::value("20240301T000000Z") })
+                        .call()
+                }
 
-                    Suggestions:
-                    - Check the status of {canonical_status_url} for any reported issues.
-                " })
-                .debug_info(e.to_string())
-                .call()
-        }
+                ParseConfigError::InvalidComponents(value) => {
+                    let components_key = style::value("components");
+                    let value = style::value(value.trim());
 
-        InstallPackagesError::WritePackage(package, download_url, destination_path, e) => {
-            let package = style::value(package.name);
-            let download_url = style::url(download_url);
-            let destination_path = file_value(destination_path);
-            create_error()
-                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::Yes))
-                .header("Failed to download package")
-                .body(formatdoc! { "
-                    An unexpected I/O error occured while downloading {package} from {download_url} \
-                    to {destination_path}. This error can occur due to an unstable network connection or an issue \
-                    with the upstream Debian package repository.
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with invalid components"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but we found an invalid value {value} for the key \
+                            {components_key} in {root_config_key}.
 
-                    Suggestions:
-                    - Check the status of {canonical_status_url} for any reported issues.
-                " })
-                .debug_info(e.to_string())
-                .call()
-        }
+                            The value must be a non-empty array of component names used for the \
+                            distribution's package sources (e.g.; [\"main\", \"universe\", \
+                            \"multiverse\", \"restricted\"]).
 
-        InstallPackagesError::ChecksumFailed {
-            url,
-            expected,
-            actual,
-        } => {
-            let url = style::url(url);
-            let expected = style::value(expected);
-            let actual = style::value(actual);
-            create_error()
-                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
-                .header("Package checksum verification failed")
-                .body(formatdoc! { "
-                    An error occurred while verifying the checksum of the package at {url}. \
-                    This error can occur due to an issue with the upstream Debian package repository.
+                            Suggestions:
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        " })
+                        .call()
+                }
 
-                    Checksum:
-                    - Expected: {expected}
-                    - Actual: {actual}
-                " })
-                .call()
-        }
+                ParseConfigError::InvalidResolveVirtualPackages(value) => on_invalid_boolean(
+                    &config_file,
+                    &root_config_key,
+                    &configuration_doc_url,
+                    "resolve_virtual_packages",
+                    &value,
+                ),
 
-        InstallPackagesError::OpenPackageArchive(file, e) => {
-            let file = file_value(file);
-            create_error()
-                .error_type(Internal)
-                .header("Failed to open package archive")
-                .body(formatdoc! {
-                    "An unexpected I/O error occurred while trying to open the archive at {file}."
-                })
-                .debug_info(e.to_string())
-                .call()
-        }
+                ParseConfigError::InvalidStrictExtraction(value) => on_invalid_boolean(
+                    &config_file,
+                    &root_config_key,
+                    &configuration_doc_url,
+                    "strict_extraction",
+                    &value,
+                ),
 
-        InstallPackagesError::OpenPackageArchiveEntry(file, e) => {
-            let file = file_value(file);
-            create_error()
-                .error_type(Internal)
-                .header("Failed to read package archive entry")
-                .body(formatdoc! {
-                    "An unexpected I/O error occurred while trying to read the entries of the \
-                    archive at {file}."
-                })
-                .debug_info(e.to_string())
-                .call()
-        }
+                ParseConfigError::InvalidSubsetPackageIndex(value) => on_invalid_boolean(
+                    &config_file,
+                    &root_config_key,
+                    &configuration_doc_url,
+                    "subset_package_index",
+                    &value,
+                ),
 
-        InstallPackagesError::UnpackTarball(file, e) => {
-            let file = file_value(file);
-            create_error()
-                .error_type(Internal)
-                .header("Failed to unpack package archive")
-                .body(formatdoc! {
-                    "An unexpected I/O error occurred while trying to unpack the archive at {file}."
-                })
-                .debug_info(e.to_string())
-                .call()
-        }
+                ParseConfigError::InvalidVerifyLayout(value) => on_invalid_boolean(
+                    &config_file,
+                    &root_config_key,
+                    &configuration_doc_url,
+                    "verify_layout",
+                    &value,
+                ),
 
-        InstallPackagesError::UnsupportedCompression(file, format) => {
-            let file = file_value(file);
-            let format = style::value(format);
-            create_error()
-                .error_type(Internal)
-                .header("Unsupported compression format for package archive")
-                .body(formatdoc! {
-                    "An unexpected compression format ({format}) was used for the package archive at {file}."
-                })
-                .call()
-        }
+                ParseConfigError::InvalidSelfCheck(value) => on_invalid_boolean(
+                    &config_file,
+                    &root_config_key,
+                    &configuration_doc_url,
+                    "self_check",
+                    &value,
+                ),
 
-        InstallPackagesError::ReadPackageConfig(file, e) => {
-            let file = file_value(file);
-            create_error()
-                .error_type(Internal)
-                .header("Failed to read package config file")
-                .body(formatdoc! {
-                    "An unexpected I/O error occurred while reading the package config file at {file}."
-                })
-                .debug_info(e.to_string())
-                .call()
-        }
+                ParseConfigError::InvalidBackports(value) => on_invalid_boolean(
+                    &config_file,
+                    &root_config_key,
+                    &configuration_doc_url,
+                    "backports",
+                    &value,
+                ),
 
-        InstallPackagesError::WritePackageConfig(file, e) => {
-            let file = file_value(file);
-            create_error()
-                .error_type(Internal)
-                .header("Failed to write package config file")
-                .body(formatdoc! {
-                    "An unexpected I/O error occurred while writing the package config file to {file}."
-                })
-                .debug_info(e.to_string())
-                .call()
-        }
+                ParseConfigError::InvalidProposed(value) => on_invalid_boolean(
+                    &config_file,
+                    &root_config_key,
+                    &configuration_doc_url,
+                    "proposed",
+                    &value,
+                ),
 
-        InstallPackagesError::SetPermissions(file, e) => {
-            let file = file_value(file);
-            create_error()
-                .error_type(Internal)
-                .header("Failed to set permissions for postinst script")
-                .body(formatdoc! {
-                    "An unexpected I/O error occurred while setting permissions for the postinst script at {file}."
-                })
-                .debug_info(e.to_string())
-                .call()
-        }
+                ParseConfigError::InvalidProvenance(value) => on_invalid_boolean(
+                    &config_file,
+                    &root_config_key,
+                    &configuration_doc_url,
+                    "provenance",
+                    &value,
+                ),
 
-        InstallPackagesError::ExecutePostinstScript(e) => {
-            create_error()
-                .error_type(Internal)
-                .header("Failed to execute postinst script")
-                .body(formatdoc! {
-                    "An error occurred while trying to execute the postinst script."
-                })
-                .debug_info(e.to_string())
-                .call()
-        }        
+                ParseConfigError::InvalidPhasedUpdates(ParsePhasedUpdatesPolicyError(value)) => {
+                    let phased_updates_key = style::value("phased_updates");
+                    let value = style::value(value.trim());
 
-        InstallPackagesError::SystemTimeError(e) => {
-            create_error()
-                .error_type(Internal)
-                .header("Failed to get system time in InstallPackages")
-                .body(format!("System time error: {}", e))
-                .debug_info(e.to_string())                
-                .call()
-        }        
-    }
-}
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with invalid phased_updates"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but we found an invalid value {value} for the key \
+                            {phased_updates_key} in {root_config_key}.
 
-fn on_framework_error(error: &Error<DebianPackagesBuildpackError>) -> ErrorMessage {
-    create_error()
-        .error_type(Framework)
-        .header("Heroku Deb Packages Buildpack internal error")
-        .body(formatdoc! {"
-            The framework used by this buildpack encountered an unexpected error.
+                            The value must be one of \"skip\" or \"include\".
 
-            If you can’t deploy to Heroku due to this issue, check the official Heroku Status page at \
-            status.heroku.com for any ongoing incidents. After all incidents resolve, retry your build.
+                            Suggestions:
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        " })
+                        .call()
+                }
 
-            Use the debug information above to troubleshoot and retry your build. If you think you found a \
-            bug in the buildpack, reproduce the issue locally with a minimal example and file an issue here:
-            https://github.com/heroku/buildpacks-deb-packages/issues/new
-        "})
-        .debug_info(error.to_string())
-        .call()
-}
+                ParseConfigError::InvalidExistingFiles(ParseExistingFilesPolicyError(value)) => {
+                    let existing_files_key = style::value("existing_files");
+                    let value = style::value(value.trim());
 
-#[builder]
-fn create_error(
-    header: impl AsRef<str>,
-    body: impl AsRef<str>,
-    error_type: ErrorType,
-    debug_info: Option<String>,
-) -> ErrorMessage {
-    let mut message_parts = vec![
-        header.as_ref().trim().to_string(),
-        body.as_ref().trim().to_string(),
-    ];
-    let issues_url = style::url("https://github.com/heroku/buildpacks-deb-packages/issues/new");
-    let pack = style::value("pack");
-    let pack_url =
-        style::url("https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/");
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with invalid existing_files"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but we found an invalid value {value} for the key \
+                            {existing_files_key} in {root_config_key}.
 
-    match error_type {
-        Framework => {}
-        Internal => {
-            message_parts.push(formatdoc! { "
-                The causes for this error are unknown. We do not have suggestions for diagnosis or a \
-                workaround at this time. You can help our understanding by sharing your buildpack log \
-                and a description of the issue at:
-                {issues_url}
+                            The value must be one of \"overwrite\", \"skip\", or \"error\".
 
-                If you're able to reproduce the problem with an example application and the {pack} \
-                build tool ({pack_url}), adding that information to the discussion will also help. Once \
-                we have more information around the causes of this error we may update this message.
-            "});
-        }
-        UserFacing(suggest_retry_build, suggest_submit_issue) => {
-            if let SuggestRetryBuild::Yes = suggest_retry_build {
-                message_parts.push(
-                    formatdoc! { "
-                    Use the debug information above to troubleshoot and retry your build.
-                "}
-                    .trim()
-                    .to_string(),
-                );
-            }
+                            Suggestions:
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        " })
+                        .call()
+                }
 
-            if let SuggestSubmitIssue::Yes = suggest_submit_issue {
-                message_parts.push(formatdoc! { "
-                    If the issue persists and you think you found a bug in the buildpack, reproduce \
-                    the issue locally with a minimal example. Open an issue in the buildpack's GitHub \
-                    repository and include the details here:
-                    {issues_url}
-                "}.trim().to_string());
-            }
-        }
-    }
+                ParseConfigError::InvalidImageMagickPolicy(value) => {
+                    let imagemagick_policy_key = style::value("imagemagick_policy");
+                    let value = style::value(value.trim());
 
-    let message = message_parts.join("\n\n");
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with invalid imagemagick_policy"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but we found an invalid value {value} for the key \
+                            {imagemagick_policy_key} in {root_config_key}.
+
+                            The value must be the path to a policy file relative to the application \
+                            directory (e.g.; \"config/imagemagick-policy.xml\") that doesn't point \
+                            outside of it.
+
+                            Suggestions:
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        " })
+                        .call()
+                }
+
+                ParseConfigError::InvalidSourcesFile(value) => {
+                    let sources_file_key = style::value("sources_file");
+                    let value = style::value(value.trim());
+
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with invalid sources_file"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but we found an invalid value {value} for the key \
+                            {sources_file_key} in {root_config_key}.
+
+                            The value must be the path to a file of sources in the deb822 format \
+                            relative to the application directory (e.g.; \"config/example.sources\").
+
+                            Suggestions:
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        " })
+                        .call()
+                }
+
+                ParseConfigError::InvalidRemove(value) => {
+                    let remove_key = style::value("remove");
+                    let value = style::value(value.trim());
+
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with invalid remove"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but we found an invalid value {value} for the key \
+                            {remove_key} in {root_config_key}.
+
+                            The value must be an array of package names (e.g.; [\"perl\"]).
+
+                            Suggestions:
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        " })
+                        .call()
+                }
+
+                ParseConfigError::InvalidTargetCodename(value) => {
+                    let target_codename_key = style::value("target_codename");
+                    let value = style::value(value.trim());
+
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with invalid target_codename"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but we found an invalid value {value} for the key \
+                            {target_codename_key} in {root_config_key}.
+
+                            The value must be the codename of the distribution release the run \
+                            image is built from (e.g.; \"jammy\").
+
+                            Suggestions:
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        " })
+                        .call()
+                }
+
+                ParseConfigError::InvalidMaxDependencies(value) => {
+                    let max_dependencies_key = style::value("max_dependencies");
+                    let value = style::value(value.trim());
+
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with invalid max_dependencies"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but we found an invalid value {value} for the key \
+                            {max_dependencies_key} in {root_config_key}.
+
+                            The value must be a non-negative integer. Use 0 to disable the limit.
+
+                            Suggestions:
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        " })
+                        .call()
+                }
+
+                ParseConfigError::InvalidSourcePriority(value) => {
+                    let source_priority_key = style::value("source_priority");
+                    let value = style::value(value.trim());
+
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with invalid source_priority"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but we found an invalid value {value} for the key \
+                            {source_priority_key} in {root_config_key}.
+
+                            The value must be an array of repository URIs (e.g.; \
+                            [\"http://archive.ubuntu.com/ubuntu\"]) ordered from most to least preferred.
+
+                            Suggestions:
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        " })
+                        .call()
+                }
+
+                ParseConfigError::InvalidTasks(value) => {
+                    let tasks_key = style::value("tasks");
+                    let value = style::value(value.trim());
+
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with invalid tasks"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but we found an invalid value {value} for the key \
+                            {tasks_key} in {root_config_key}.
+
+                            The value must be an array of task names (e.g.; [\"ubuntu-server\"]) as \
+                            listed in the Task field of the packages in the task.
+
+                            Suggestions:
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        " })
+                        .call()
+                }
+
+                ParseConfigError::InvalidPreset(ParsePresetError(value)) => {
+                    let preset_key = style::value("preset");
+                    let value = style::value(value.trim());
+
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with invalid preset"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but we found an invalid value {value} for the key \
+                            {preset_key} in {root_config_key}.
+
+                            The value must be one of \"geo\", \"imaging\", or \"media\" or an array \
+                            of them (e.g.; [\"geo\", \"imaging\"]).
+
+                            Suggestions:
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        " })
+                        .call()
+                }
+
+                ParseConfigError::InvalidPackageGroup(group) => {
+                    let group = style::value(group);
+                    let groups_key = style::value("groups");
+
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with invalid package group"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but we found an invalid value for {group} in the \
+                            {groups_key} table of {root_config_key}.
+
+                            The {groups_key} key must be a table where each group is an array of \
+                            packages in the same format as the install list (e.g.; \
+                            media = [\"ffmpeg\", \"libvips-tools\"]).
+
+                            Suggestions:
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        " })
+                        .call()
+                }
+
+                ParseConfigError::NestedPackageGroup {
+                    group,
+                    referenced_group,
+                } => {
+                    let group = style::value(group);
+                    let referenced_group = style::value(format!("@{referenced_group}"));
+                    let groups_key = style::value("groups");
+
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with nested package group"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but the package group {group} in the {groups_key} \
+                            table of {root_config_key} references the group {referenced_group}. \
+                            Package groups can't reference other groups.
+
+                            Suggestions:
+                            - List the packages of {referenced_group} in {group} instead.
+                            - Reference both groups from the install list.
+                        " })
+                        .call()
+                }
+
+                ParseConfigError::UnknownPackageGroup {
+                    group,
+                    available_groups,
+                } => {
+                    let group = style::value(format!("@{group}"));
+                    let groups_key = style::value("groups");
+                    let available_groups = if available_groups.is_empty() {
+                        "none".to_string()
+                    } else {
+                        available_groups
+                            .iter()
+                            .map(style::value)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    };
+
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with unknown package group"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but the install list in {root_config_key} \
+                            references the package group {group} which isn't defined in the \
+                            {groups_key} table.
+
+                            Defined package groups: {available_groups}
+
+                            Suggestions:
+                            - Verify the group name is spelled correctly.
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        " })
+                        .call()
+                }
+
+                ParseConfigError::ParsePpa(ParsePpaError(value)) => {
+                    let ppas_key = style::value("ppas");
+                    let value = style::value(value.trim());
+
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with invalid ppas"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but we found an invalid value {value} for the key \
+                            {ppas_key} in {root_config_key}.
+
+                            The value must be an array of Launchpad PPAs written as \
+                            {ppa_format} (e.g.; [\"ppa:deadsnakes/ppa\"]).
+
+                            Suggestions:
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        ", ppa_format = style::value("ppa:<owner>/<name>") })
+                        .call()
+                }
+
+                ParseConfigError::InvalidLocales(value) => {
+                    let locales_key = style::value("locales");
+                    let value = style::value(value.trim());
+
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with invalid locales"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but we found an invalid value {value} for the key \
+                            {locales_key} in {root_config_key}.
+
+                            The value must be an array of locale names (e.g.; \
+                            [\"en_US.UTF-8\", \"de_DE.UTF-8\"]).
+
+                            Suggestions:
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        " })
+                        .call()
+                }
+
+                ParseConfigError::ParseLocale(error) => {
+                    let locales_key = style::value("locales");
+                    let value = style::value(match error {
+                        ParseLocaleError::InvalidLocale(value) => value,
+                        ParseLocaleError::UnexpectedTomlValue(value) => value.to_string(),
+                    });
+
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with invalid locale"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but we found an invalid locale {value} in the key \
+                            {locales_key} in {root_config_key}.
+
+                            Locales must be TOML strings in the form \
+                            language_TERRITORY.codeset[@modifier] (e.g.; \"en_US.UTF-8\"). The codeset \
+                            is required.
+
+                            Suggestions:
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        " })
+                        .call()
+                }
+
+                ParseConfigError::InvalidExclude(value) => {
+                    let exclude_key = style::value("exclude");
+                    let value = style::value(value.trim());
+
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                        .header(format!("Error parsing {config_file} with invalid exclude"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                            complete the build but we found an invalid value {value} for the key \
+                            {exclude_key} in {root_config_key}.
+
+                            The value must be an array of package patterns (e.g.; [\"*-doc\"]).
+
+                            Suggestions:
+                            - See the buildpack documentation for the proper usage for this configuration at \
+                            {configuration_doc_url}
+                        " })
+                        .call()
+                }
+
+                ParseConfigError::ParseExclude(error) => match error {
+                    ParsePackageGlobError::InvalidPackageGlob(package_glob) => {
+                        let package_glob = style::value(package_glob);
+
+                        create_error()
+                            .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                            .header(format!("Error parsing {config_file} with invalid exclude pattern"))
+                            .body(formatdoc! { "
+                                The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                                complete the build but we found an invalid exclude pattern {package_glob} \
+                                in the key {root_config_key}.
+
+                                Exclude patterns must consist only of lowercase letters (a-z), \
+                                digits (0-9), plus (+) and minus (-) signs, periods (.), and the \
+                                wildcards (*) and (?).
+
+                                Suggestions:
+                                - See the buildpack documentation for the proper usage for this configuration at \
+                                {configuration_doc_url}
+                            " })
+                            .call()
+                    }
+
+                    ParsePackageGlobError::UnexpectedTomlValue(value) => {
+                        let value_type = style::value(value.type_name());
+                        let value = style::value(value.to_string());
+
+                        create_error()
+                            .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                            .header(format!("Error parsing {config_file} with invalid exclude format"))
+                            .body(formatdoc! { "
+                                The {BUILDPACK_NAME} reads configuration from {config_file} to \
+                                complete the build but we found an invalid exclude pattern format in the \
+                                key {root_config_key}.
+
+                                Exclude patterns must be TOML strings (e.g.; \"*-doc\").
+
+                                Suggestions:
+                                - See the buildpack documentation for the proper usage for this configuration at \
+                                {configuration_doc_url}
+                                - See the TOML documentation for more details on the TOML string type at \
+                                {toml_spec_url}
+                            " })
+                            .debug_info(format!("Invalid type {value_type} with value {value}"))
+                            .call()
+                    }
+                },
+            }
+        }
+
+        ConfigError::RemovedOptions(config_file, removed_options) => {
+            let config_file = file_value(config_file);
+            let root_config_key = style::value("[com.heroku.buildpacks.deb-packages]");
+            let configuration_doc_url =
+                style::url("https://github.com/heroku/buildpacks-deb-packages#configuration");
+            let removed_options = removed_options
+                .iter()
+                .map(|removed_option| {
+                    format!(
+                        "- {} {}",
+                        style::value(removed_option.option),
+                        removed_option.hint
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header(format!("Error parsing {config_file} with removed options"))
+                .body(formatdoc! { "
+                    The {BUILDPACK_NAME} reads configuration from {config_file} to complete the \
+                    build but the following options in {root_config_key} are no longer supported:
+
+                    {removed_options}
+
+                    Suggestions:
+                    - See the buildpack documentation for the proper usage for this configuration at \
+                    {configuration_doc_url}
+                " })
+                .call()
+        }
+
+        ConfigError::ParseInstallEnv(error) => {
+            let install_env_var = style::value(INSTALL_ENV_VAR);
+            let debian_package_name_format_url = style::url(
+                "https://www.debian.org/doc/debian-policy/ch-controlfields.html#s-f-source",
+            );
+            let package_search_url = get_package_search_url();
+            let invalid_package_name = style::value(error.package_name);
+
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header(format!("Error parsing {install_env_var} with invalid package name"))
+                .body(formatdoc! { "
+                    The {BUILDPACK_NAME} reads the packages to install from the {install_env_var} \
+                    environment variable but we found an invalid package name {invalid_package_name}.
+
+                    Package names must consist only of lowercase letters (a-z), \
+                    digits (0-9), plus (+) and minus (-) signs, and periods (.). Names \
+                    must be at least two characters long and must start with an alphanumeric \
+                    character. See {debian_package_name_format_url}
+
+                    Suggestions:
+                    - Verify the package names are separated by commas (e.g.; `ffmpeg, libvips-tools`)
+                    - Verify the package name is correct and exists for the target distribution at \
+                     {package_search_url}
+                " })
+                .call()
+        }
+
+        ConfigError::ParseBuildPlan(error) => {
+            let build_plan_name = style::value("deb-packages");
+            let packages_key = style::value("packages");
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header("Error parsing the build plan")
+                .body(formatdoc! { "
+                    Another buildpack in this build requires {build_plan_name} with metadata the \
+                    {BUILDPACK_NAME} can't read. The {packages_key} key in the metadata must be an \
+                    array of package names (e.g.; packages = [\"libgdal-dev\"]).
+
+                    Suggestions:
+                    - Contact the maintainers of the buildpack that requires {build_plan_name}.
+                " })
+                .debug_info(error.to_string())
+                .call()
+        }
+
+        ConfigError::ParseBuildPlanPackage(error) => {
+            let build_plan_name = style::value("deb-packages");
+            let invalid_package_name = style::value(error.package_name);
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header("Error parsing the build plan with invalid package name")
+                .body(formatdoc! { "
+                    Another buildpack in this build requires {build_plan_name} to install the \
+                    package {invalid_package_name} but it's not a valid package name.
+
+                    Suggestions:
+                    - Contact the maintainers of the buildpack that requires {build_plan_name}.
+                " })
+                .call()
+        }
+
+        ConfigError::InvalidProxy(error) => {
+            let proxy_env_var = style::value(error.name);
+            let proxy_value = style::value(&error.value);
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header(format!("Invalid proxy configured in {proxy_env_var}"))
+                .body(formatdoc! { "
+                    The {BUILDPACK_NAME} sends requests for package repositories through the proxy \
+                    configured in the {proxy_env_var} environment variable but the value \
+                    {proxy_value} isn't a valid proxy URL.
+
+                    Suggestions:
+                    - Set {proxy_env_var} to the URL of the proxy (e.g.; `http://proxy.example.com:3128`).
+                " })
+                .debug_info(error.reason.clone())
+                .call()
+        }
+
+        ConfigError::ClientCertificate(ClientCertificateError::ReadFile(uri, path, e)) => {
+            let uri = style::url(uri.as_str());
+            let path = file_value(path);
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header(format!("Error reading client certificate for {uri}"))
+                .body(formatdoc! { "
+                    The {BUILDPACK_NAME} presents the client certificate configured for the source \
+                    {uri} to the repository but {path} can't be read.
+
+                    Suggestions:
+                    - Ensure the {client_certificate_key} and {client_key_key} paths are relative to \
+                    the application directory or absolute.
+                    - Ensure the file has read permissions.
+                ",
+                    client_certificate_key = style::value("client_certificate"),
+                    client_key_key = style::value("client_key"),
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        ConfigError::ClientCertificate(ClientCertificateError::InvalidIdentity(uri, e)) => {
+            let uri = style::url(uri.as_str());
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header(format!("Invalid client certificate for {uri}"))
+                .body(formatdoc! { "
+                    The {BUILDPACK_NAME} presents the client certificate configured for the source \
+                    {uri} to the repository but the certificate and private key can't be used.
+
+                    Suggestions:
+                    - Ensure {client_certificate_key} is a PEM encoded certificate and {client_key_key} \
+                    is its PEM encoded private key.
+                ",
+                    client_certificate_key = style::value("client_certificate"),
+                    client_key_key = style::value("client_key"),
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        ConfigError::ReadSourcesFile(sources_file, e) => {
+            let sources_file = file_value(sources_file);
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header(format!("Error reading {sources_file}"))
+                .body(formatdoc! { "
+                    The {BUILDPACK_NAME} reads additional sources from {sources_file} (configured with \
+                    {sources_file_key} in project.toml) but the file can't be read.
+
+                    Suggestions:
+                    - Ensure the {sources_file_key} path is relative to the application directory.
+                    - Ensure the file has read permissions.
+                ",
+                    sources_file_key = style::value("sources_file"),
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        ConfigError::ReadSigningKeyFile(signed_by_file, e) => {
+            let signed_by_file = file_value(signed_by_file);
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header(format!("Error reading {signed_by_file}"))
+                .body(formatdoc! { "
+                    The {BUILDPACK_NAME} reads the signing key of a source from {signed_by_file} \
+                    (configured with {signed_by_file_key} in project.toml) but the file can't be read.
+
+                    Suggestions:
+                    - Ensure the {signed_by_file_key} path is relative to the application directory.
+                    - Ensure the file has read permissions.
+                ",
+                    signed_by_file_key = style::value("signed_by_file"),
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        ConfigError::InvalidSigningKeyFile(signed_by_file, e) => {
+            let signed_by_file = file_value(signed_by_file);
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header(format!("Invalid signing key in {signed_by_file}"))
+                .body(formatdoc! { "
+                    The {BUILDPACK_NAME} reads the signing key of a source from {signed_by_file} \
+                    (configured with {signed_by_file_key} in project.toml) but the file doesn't \
+                    contain an OpenPGP public key.
+
+                    Suggestions:
+                    - Ensure the file contains the ASCII-armored or binary public key used to sign the \
+                    repository (e.g.; the output of {gpg_export}).
+                ",
+                    signed_by_file_key = style::value("signed_by_file"),
+                    gpg_export = style::value("gpg --export --armor <key-id>"),
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        ConfigError::ParseSourcesFile(sources_file, error) => {
+            let sources_file = file_value(sources_file);
+            let (problem, debug_info) =
+                describe_custom_source_error(error, &style::value("sources_file"));
+            let source_example = indoc! { "
+                Types: deb
+                URIs: https://repo.example.com/ubuntu
+                Suites: noble
+                Components: main
+                Signed-By:
+                 -----BEGIN PGP PUBLIC KEY BLOCK-----
+                 .
+                 ...
+                 -----END PGP PUBLIC KEY BLOCK-----
+            " };
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header(format!("Error parsing {sources_file} with invalid sources"))
+                .body(formatdoc! { "
+                    The {BUILDPACK_NAME} reads additional sources from {sources_file} to complete the \
+                    build but {problem}.
+
+                    Each source must be a deb822 stanza with {types_field}, {uris_field}, \
+                    {suites_field}, and {components_field} fields and a {signed_by_field} field \
+                    containing the ASCII-armored public key used to sign the repository (with a \
+                    {empty_line} for each empty line). The {signed_by_field} field can be left out \
+                    when the optional {signature_policy_field} field is set to {optional} or \
+                    {disabled}. For example:
+
+                    {source_example}
+                    Suggestions:
+                    - See the buildpack documentation for the proper usage for this configuration at \
+                    {configuration_doc_url}
+                ",
+                    types_field = style::value("Types"),
+                    uris_field = style::value("URIs"),
+                    suites_field = style::value("Suites"),
+                    components_field = style::value("Components"),
+                    signed_by_field = style::value("Signed-By"),
+                    empty_line = style::value(" ."),
+                    signature_policy_field = style::value("Signature-Policy"),
+                    optional = style::value("optional"),
+                    disabled = style::value("disabled"),
+                    configuration_doc_url =
+                        style::url("https://github.com/heroku/buildpacks-deb-packages#configuration"),
+                })
+                .maybe_debug_info(debug_info.map(|value| value.trim().to_string()))
+                .call()
+        }
+
+        ConfigError::ParseNetworkEnv(error) => {
+            let problem = match error {
+                ParseNetworkSettingsError::InvalidValue(name, value) => format!(
+                    "the {name} environment variable has the invalid value {value}",
+                    name = style::value(name),
+                    value = style::value(value),
+                ),
+                ParseNetworkSettingsError::InvalidRetryIntervals(min, max) => format!(
+                    "the minimum retry interval ({min}s) is greater than the maximum retry interval \
+                    ({max}s)",
+                    min = min.as_secs(),
+                    max = max.as_secs(),
+                ),
+                ParseNetworkSettingsError::UnexpectedTomlValue(value) => format!(
+                    "we found the invalid value {value}",
+                    value = style::value(value.trim()),
+                ),
+            };
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header("Invalid network settings")
+                .body(formatdoc! { "
+                    The {BUILDPACK_NAME} reads network settings from the environment to complete \
+                    the build but {problem}.
+
+                    Suggestions:
+                    - Set {timeout_env_var} to the request timeout in seconds (greater than 0).
+                    - Set {max_retries_env_var} to the number of times failed requests are retried.
+                    - Set {min_retry_interval_env_var} and {max_retry_interval_env_var} to the \
+                    bounds of the wait between retries in seconds.
+                ",
+                    timeout_env_var = style::value(TIMEOUT_ENV_VAR),
+                    max_retries_env_var = style::value(MAX_RETRIES_ENV_VAR),
+                    min_retry_interval_env_var = style::value(MIN_RETRY_INTERVAL_ENV_VAR),
+                    max_retry_interval_env_var = style::value(MAX_RETRY_INTERVAL_ENV_VAR),
+                })
+                .call()
+        }
+
+        ConfigError::UnsupportedTargetCodename(UnsupportedTargetCodenameError {
+            codename,
+            architecture,
+            supported_codenames,
+        }) => {
+            let target_codename_key = style::value("target_codename");
+            let codename = style::value(codename.to_string());
+            let supported_codenames = supported_codenames
+                .iter()
+                .map(style::value)
+                .collect::<Vec<_>>()
+                .join(", ");
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header(format!("Unsupported target codename {codename}"))
+                .body(formatdoc! { "
+                    The {BUILDPACK_NAME} was configured with {target_codename_key} to resolve \
+                    packages for the {codename} release but it doesn't have package sources for \
+                    that release on {architecture}.
+
+                    Suggestions:
+                    - Set {target_codename_key} to the codename of the release the run image is \
+                    built from. The supported codenames for {architecture} are: {supported_codenames}.
+                    - Remove {target_codename_key} from project.toml if the run image is built from \
+                    the same release as the build image.
+                " })
+                .call()
+        }
+    }
+}
+
+fn describe_custom_source_error(
+    error: ParseCustomSourceError,
+    sources_key: &str,
+) -> (String, Option<String>) {
+    match error {
+        ParseCustomSourceError::MissingKey(key) => (
+            format!("a source is missing the required key {}", style::value(key)),
+            None,
+        ),
+        ParseCustomSourceError::InvalidValue(key, value) => (
+            format!(
+                "a source has an invalid value for the key {}",
+                style::value(key)
+            ),
+            Some(value),
+        ),
+        ParseCustomSourceError::UnexpectedTomlValue(value) => (
+            format!("the key {sources_key} isn't an array of tables or a string"),
+            Some(value),
+        ),
+        ParseCustomSourceError::InvalidDeb822(line) => (
+            "a source in the deb822 format has a line that isn't a field or a continuation of one"
+                .to_string(),
+            Some(line),
+        ),
+        ParseCustomSourceError::ConflictingKeys(key, other_key) => (
+            format!(
+                "a source sets both {} and {} but only one of them can be used",
+                style::value(key),
+                style::value(other_key)
+            ),
+            None,
+        ),
+    }
+}
+
+fn on_invalid_env_scope(
+    config_file: &str,
+    root_config_key: &str,
+    configuration_doc_url: &str,
+    value: &str,
+) -> ErrorMessage {
+    let env_scope_key = style::value("env_scope");
+    let value = style::value(value.trim());
+
+    create_error()
+        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+        .header(format!(
+            "Error parsing {config_file} with invalid env_scope"
+        ))
+        .body(formatdoc! { "
+            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+            complete the build but we found an invalid value {value} for the key \
+            {env_scope_key} in {root_config_key}.
+
+            The value must be one of \"build\", \"launch\", or \"all\".
+
+            Suggestions:
+            - See the buildpack documentation for the proper usage for this configuration at \
+            {configuration_doc_url}
+        " })
+        .call()
+}
+
+fn describe_script_output(script_failure: &ScriptFailure) -> String {
+    let describe = |name: &str, output: &str| match output.trim_end() {
+        "" => format!("{name}: (empty)"),
+        output => format!("{name}:\n{output}"),
+    };
+    format!(
+        "{exit}\n\n{stdout}\n\n{stderr}",
+        exit = script_failure.exit_description(),
+        stdout = describe("stdout", &script_failure.stdout),
+        stderr = describe("stderr", &script_failure.stderr)
+    )
+}
+
+fn on_invalid_boolean(
+    config_file: &str,
+    root_config_key: &str,
+    configuration_doc_url: &str,
+    key: &str,
+    value: &str,
+) -> ErrorMessage {
+    let config_key = style::value(key);
+    let value = style::value(value.trim());
+
+    create_error()
+        .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+        .header(format!("Error parsing {config_file} with invalid {key}"))
+        .body(formatdoc! { "
+            The {BUILDPACK_NAME} reads configuration from {config_file} to \
+            complete the build but we found an invalid value {value} for the key \
+            {config_key} in {root_config_key}.
+
+            The value must be a boolean (true or false).
+
+            Suggestions:
+            - See the buildpack documentation for the proper usage for this configuration at \
+            {configuration_doc_url}
+        " })
+        .call()
+}
+
+fn on_unsupported_distro_error(error: UnsupportedDistroError) -> ErrorMessage {
+    let UnsupportedDistroError {
+        name,
+        version,
+        architecture,
+        supported_distros,
+    } = error;
+
+    let supported_distros = supported_distros
+        .iter()
+        .map(|supported_distro| format!("- {supported_distro}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    create_error()
+        .error_type(Internal)
+        .header("Unsupported distribution")
+        .body(formatdoc! { "
+            The {BUILDPACK_NAME} doesn't support the {name} {version} ({architecture}) distribution.
+
+            Supported distributions:
+            {supported_distros}
+        " })
+        .call()
+}
+
+fn on_load_signing_key_error(error: LoadSigningKeyError) -> ErrorMessage {
+    match error {
+        LoadSigningKeyError::Unknown(name, path) => {
+            let name = style::value(name);
+            let path = file_value(path);
+            create_error()
+                .error_type(Internal)
+                .header("Missing signing key")
+                .body(formatdoc! { "
+                    The {BUILDPACK_NAME} verifies the release files of the distribution's sources \
+                    with the signing key {name} but it wasn't found at {path}.
+                " })
+                .call()
+        }
+
+        LoadSigningKeyError::Read(path, e) => {
+            let path = file_value(path);
+            create_error()
+                .error_type(Internal)
+                .header("Unable to read signing key")
+                .body(formatdoc! { "
+                    An unexpected I/O error occurred while reading the signing key at {path}.
+                " })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        LoadSigningKeyError::Invalid(path, e) => {
+            let path = file_value(path);
+            create_error()
+                .error_type(Internal)
+                .header("Invalid signing key")
+                .body(formatdoc! { "
+                    The signing key at {path} isn't a valid OpenPGP certificate.
+                " })
+                .debug_info(e.to_string())
+                .call()
+        }
+    }
+}
+
+fn on_resolve_ppas_error(error: ResolvePpasError) -> ErrorMessage {
+    let ppas_key = style::value("ppas");
+    let launchpad_status_url = style::url("https://status.launchpad.net/");
+    match error {
+        ResolvePpasError::UnsupportedDistro(name) => create_error()
+            .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+            .header("PPAs aren't supported on this distribution")
+            .body(formatdoc! { "
+                The {BUILDPACK_NAME} was configured to add the PPAs listed in {ppas_key} but \
+                Launchpad only publishes PPAs for Ubuntu and this build is running on {name}.
+
+                Suggestions:
+                - Remove {ppas_key} from project.toml.
+                - Configure the repository with {sources_key} instead if it publishes packages for \
+                {name}.
+            ", sources_key = style::value("sources") })
+            .call(),
+
+        ResolvePpasError::GetArchiveRequest(ppa, e) => {
+            let ppa = style::value(ppa.to_string());
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header(format!("Failed to look up {ppa}"))
+                .body(formatdoc! { "
+                    The {BUILDPACK_NAME} requests the details of each PPA listed in {ppas_key} from \
+                    the Launchpad API to find its signing key but the request for {ppa} failed. \
+                    This error can occur when the PPA doesn't exist, due to an unstable network \
+                    connection, or an issue with Launchpad.
+
+                    Suggestions:
+                    - Verify the owner and name of {ppa} on https://launchpad.net/.
+                    - Check the status of {launchpad_status_url} for any reported issues.
+                " })
+                .debug_info(describe_request_error(&e))
+                .call()
+        }
+
+        ResolvePpasError::ParseArchiveResponse(ppa, e) => {
+            let ppa = style::value(ppa.to_string());
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::Yes))
+                .header(format!("Failed to look up {ppa}"))
+                .body(formatdoc! { "
+                    The {BUILDPACK_NAME} requests the details of each PPA listed in {ppas_key} from \
+                    the Launchpad API to find its signing key but the response for {ppa} couldn't \
+                    be read.
+
+                    Suggestions:
+                    - Check the status of {launchpad_status_url} for any reported issues.
+                " })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        ResolvePpasError::MissingSigningKeyFingerprint(ppa) => {
+            let ppa = style::value(ppa.to_string());
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header(format!("No signing key for {ppa}"))
+                .body(formatdoc! { "
+                    The {BUILDPACK_NAME} verifies the packages from each PPA listed in {ppas_key} \
+                    with the PPA's signing key but Launchpad doesn't report a signing key for {ppa}. \
+                    A PPA is only signed once its first package has been published.
+
+                    Suggestions:
+                    - Verify {ppa} has published packages on https://launchpad.net/.
+                " })
+                .call()
+        }
+    }
+}
+
+fn on_fetch_signing_keys_error(error: FetchSigningKeysError) -> ErrorMessage {
+    let key_fingerprint_key = style::value("key_fingerprint");
+    let keyserver_url = style::url("https://keyserver.ubuntu.com/");
+    match error {
+        FetchSigningKeysError::GetSigningKey(uri, fingerprint, e) => {
+            let uri = style::url(uri.as_str());
+            let fingerprint = style::value(fingerprint);
+            match e {
+                KeyserverError::Request(e) => create_error()
+                    .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                    .header(format!("Failed to download the signing key {fingerprint}"))
+                    .body(formatdoc! { "
+                        The {BUILDPACK_NAME} downloads the signing key configured with \
+                        {key_fingerprint_key} for the source {uri} from {keyserver_url} but the \
+                        request for {fingerprint} failed. This error can occur when the key hasn't \
+                        been published to the keyserver, due to an unstable network connection, or \
+                        an issue with the keyserver.
+
+                        Suggestions:
+                        - Verify the key can be found by searching for {fingerprint} on \
+                        {keyserver_url}.
+                        - Use {signed_by_key} or {signed_by_file_key} to provide the key instead.
+                    ",
+                        signed_by_key = style::value("signed_by"),
+                        signed_by_file_key = style::value("signed_by_file"),
+                    })
+                    .debug_info(describe_request_error(&e))
+                    .call(),
+
+                KeyserverError::InvalidSigningKey(e) => create_error()
+                    .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                    .header(format!("Invalid signing key {fingerprint}"))
+                    .body(formatdoc! { "
+                        The {BUILDPACK_NAME} downloads the signing key configured with \
+                        {key_fingerprint_key} for the source {uri} from {keyserver_url} but the key \
+                        downloaded for {fingerprint} isn't a valid OpenPGP key.
+                    " })
+                    .debug_info(e)
+                    .call(),
+
+                KeyserverError::FingerprintMismatch { expected, actual } => {
+                    let expected = style::value(expected);
+                    let actual = style::value(actual);
+                    create_error()
+                        .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                        .header(format!("Signing key mismatch for {uri}"))
+                        .body(formatdoc! { "
+                            The {BUILDPACK_NAME} downloads the signing key configured with \
+                            {key_fingerprint_key} for the source {uri} from {keyserver_url}. The key \
+                            {expected} was requested but the downloaded key has the fingerprint \
+                            {actual}.
+
+                            The key wasn't used to protect the build from an untrusted signing key.
+
+                            Suggestions:
+                            - Verify {key_fingerprint_key} is the full fingerprint of the key used to \
+                            sign the repository.
+                        " })
+                        .call()
+                }
+            }
+        }
+
+        FetchSigningKeysError::WriteSigningKey(path, e) => {
+            let path = file_value(path);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to write signing key")
+                .body(formatdoc! {"
+                    An unexpected I/O error occurred while writing the downloaded signing key to \
+                    {path}.
+                "})
+                .debug_info(e.to_string())
+                .call()
+        }
+    }
+}
+
+fn on_check_sources_error(error: CheckSourcesError) -> ErrorMessage {
+    match error {
+        CheckSourcesError::TaskFailed(e) => create_error()
+            .error_type(Internal)
+            .header("Task failure while checking sources")
+            .body(indoc! { "
+                A background task responsible for checking sources failed to complete.
+            " })
+            .debug_info(e.to_string())
+            .call(),
+
+        CheckSourcesError::UnreachableSources(unreachable_sources) => {
+            let canonical_status_url = get_canonical_status_url();
+            let unreachable_sources = unreachable_sources
+                .iter()
+                .map(|unreachable_source| {
+                    format!(
+                        "- {}: {}",
+                        style::url(&unreachable_source.url),
+                        unreachable_source.reason
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header("Unreachable package sources")
+                .body(formatdoc! { "
+                    Before updating package sources, each source is checked to make sure it can be \
+                    reached. The following sources could not be reached:
+
+                    {unreachable_sources}
+
+                    This error can occur due to an unstable network connection, a misconfigured \
+                    source in project.toml, or an issue with the upstream Debian package repository.
+
+                    Suggestions:
+                    - Verify the `uri` and `suites` of any sources configured in project.toml.
+                    - Check the status of {canonical_status_url} for any reported issues.
+                " })
+                .call()
+        }
+    }
+}
+
+#[allow(clippy::too_many_lines)]
+fn on_create_package_index_error(error: CreatePackageIndexError) -> ErrorMessage {
+    let canonical_status_url = get_canonical_status_url();
+
+    match error {
+        CreatePackageIndexError::NoSources => {
+            create_error()
+                .error_type(Internal)
+                .header("No sources to update")
+                .body(indoc! { "
+                    The distribution has no sources to update packages from.
+                " })
+                .call()
+        }
+
+        CreatePackageIndexError::TaskFailed(e) => {
+            create_error()
+                .error_type(Internal)
+                .header("Task failure while updating sources")
+                .body(indoc! { "
+                    A background task responsible for updating sources failed to complete.
+                " })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        CreatePackageIndexError::InvalidLayerName(url, e) => {
+            create_error()
+                .error_type(Internal)
+                .header("Invalid layer name")
+                .body(formatdoc! { "
+                    For caching purposes, a unique layer name is generated for Debian Release files \
+                    and Package indices based on their download urls. The generated name for the \
+                    following url was invalid:
+                    - {url}
+
+                    You can find the invalid layer name in the debug information above.
+                " })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        CreatePackageIndexError::GetReleaseRequest(e) => {
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::Yes))
+                .header("Failed to request Release file")
+                .body(formatdoc! { "
+                    While updating package sources, a request to download a Release file failed. \
+                    This error can occur due to an unstable network connection or an issue with the upstream \
+                    Debian package repository.
+
+                    Suggestions:
+                    - Check the status of {canonical_status_url} for any reported issues.
+                " })
+                .debug_info(describe_request_error(&e))
+                .call()
+        }
+
+        CreatePackageIndexError::ReadGetReleaseResponse(e) => {
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::Yes))
+                .header("Failed to download Release file")
+                .body(formatdoc! { "
+                    While updating package sources, an error occurred while downloading a Release file. \
+                    This error can occur due to an unstable network connection or an issue with the upstream \
+                    Debian package repository.
+
+                    Suggestions:
+                    - Check the status of {canonical_status_url} for any reported issues.
+                " })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        CreatePackageIndexError::CreatePgpCertificate(e) => {
+            create_error()
+                .error_type(Internal)
+                .header("Failed to load verifying PGP certificate")
+                .body(indoc! { "
+                    The PGP certificate used to verify downloaded release files failed to load. This \
+                    error indicates there's a problem with the format of the certificate file the \
+                    distribution uses.
+
+                    Suggestions:
+                    - Verify the format of the certificates found in the ./keys directory of this \
+                    buildpack's repository. See https://cirw.in/gpg-decoder
+                    - Extract new certificates by running the ./scripts/extract_keys.sh script found \
+                    in this buildpack's repository.
+                " })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        CreatePackageIndexError::CreatePgpVerifier(e) => {
+            create_error()
+                .error_type(Internal)
+                .header("Failed to verify Release file")
+                .body(indoc! { "
+                    The PGP signature of the downloaded release file failed verification. This error can \
+                    occur if the maintainers of the Debian repository changed the process for signing \
+                    release files.
+
+                    Suggestions:
+                    - Verify if the keys changed by running the ./scripts/extract_keys.sh \
+                    script found in this buildpack's repository.
+                " })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        CreatePackageIndexError::MissingReleaseSignature(release_file_url) => {
+            let signature_url = style::url(format!("{release_file_url}.gpg"));
+            let release_file_url = style::url(release_file_url);
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header("Missing Release file signature")
+                .body(formatdoc! { "
+                    The repository doesn't publish an InRelease file so the {BUILDPACK_NAME} \
+                    downloaded {release_file_url} instead but there's no detached signature at \
+                    {signature_url} to verify it with. The signature policy of the source is \
+                    {signature_policy} so the Release file can't be used without verifying it.
+
+                    Suggestions:
+                    - Verify the repository is signed and the source has the right URI.
+                    - If the repository isn't signed, set {signature_policy_key} to {optional} or \
+                    {disabled} on the source in project.toml.
+                ",
+                    signature_policy = style::value("required"),
+                    signature_policy_key = style::value("signature_policy"),
+                    optional = style::value("optional"),
+                    disabled = style::value("disabled"),
+                })
+                .call()
+        }
+
+        CreatePackageIndexError::MissingSigningKey(suite_url) => {
+            let suite_url = style::url(suite_url);
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header("Missing signing key")
+                .body(formatdoc! { "
+                    The signature policy of the source for {suite_url} is {signature_policy} but \
+                    no signing key is configured so the {BUILDPACK_NAME} can't verify its \
+                    Release file.
+
+                    Suggestions:
+                    - Set {signed_by}, {signed_by_file}, or {key_fingerprint} on the source in \
+                    project.toml.
+                    - If the repository isn't signed, set {signature_policy_key} to {optional} or \
+                    {disabled} on the source in project.toml.
+                ",
+                    signature_policy = style::value("required"),
+                    signed_by = style::value("signed_by"),
+                    signed_by_file = style::value("signed_by_file"),
+                    key_fingerprint = style::value("key_fingerprint"),
+                    signature_policy_key = style::value("signature_policy"),
+                    optional = style::value("optional"),
+                    disabled = style::value("disabled"),
+                })
+                .call()
+        }
+
+        CreatePackageIndexError::WriteReleaseLayer(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to write Release file to layer")
+                .body(formatdoc! { "
+                    An unexpected I/O error occurred while writing release data to {file}.
+                " })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        CreatePackageIndexError::ReadReleaseFile(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to read Release file from layer")
+                .body(formatdoc! { "
+                    An unexpected I/O error occurred while reading Release data from {file}.
+                " })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        CreatePackageIndexError::ParseReleaseFile(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::Yes))
+                .header("Failed to parse Release file data")
+                .body(formatdoc! { "
+                    We couldn't parse the Release file data stored in {file}. This error is most likely \
+                    a buildpack bug. It can also be caused by cached data that's no longer valid or an \
+                    issue with the upstream repository.
+
+                    Suggestions:
+                    - Run the build again with a clean cache.
+                " })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        CreatePackageIndexError::ExpiredReleaseFile(release_file_url, valid_until) => {
+            let release_file_url = style::url(release_file_url);
+            let valid_until = style::value(valid_until);
+            let check_release_dates = style::value("check_release_dates = false");
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header("Expired Release file")
+                .body(formatdoc! { "
+                    The Release file downloaded from {release_file_url} expired on {valid_until} \
+                    (see its `Valid-Until` field). This usually means the repository or a mirror \
+                    in front of it is serving an outdated copy that can't be trusted to have the \
+                    latest security updates.
+
+                    Suggestions:
+                    - Retry the build once the repository has been updated.
+                    - For repositories that publish snapshots that are never updated (e.g.; \
+                    snapshot.debian.org), set {check_release_dates} in project.toml.
+                " })
+                .call()
+        }
+
+        CreatePackageIndexError::ReleaseFileNotValidYet(release_file_url, date) => {
+            let release_file_url = style::url(release_file_url);
+            let date = style::value(date);
+            let check_release_dates = style::value("check_release_dates = false");
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header("Release file not valid yet")
+                .body(formatdoc! { "
+                    The Release file downloaded from {release_file_url} was published on {date} \
+                    (see its `Date` field) which is in the future. This usually means the clock \
+                    of the build environment is wrong.
+
+                    Suggestions:
+                    - Check the date and time of the build environment.
+                    - Set {check_release_dates} in project.toml to skip this check.
+                " })
+                .call()
+        }
+
+        CreatePackageIndexError::MissingSha256ReleaseHashes(release_uri) => {
+            let release_uri = style::url(release_uri.as_str());
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::Yes))
+                .header("Missing SHA256 Release hash")
+                .body(formatdoc! { "
+                    The Release file from {release_uri} is missing the SHA256 key which is required \
+                    according to the documented Debian repository format. This error is most likely an issue \
+                    with the upstream repository. See https://wiki.debian.org/DebianRepository/Format
+
+                    Suggestions:
+                    - If this is an additional source that only publishes {md5sum} or {sha1} hashes, \
+                    set {allow_weak_hashes} on the source in project.toml to verify it with those \
+                    instead. Only do this for repositories you trust.
+                ",
+                    md5sum = style::value("MD5Sum"),
+                    sha1 = style::value("SHA1"),
+                    allow_weak_hashes = style::value("allow_weak_hashes = true"),
+                })
+                .call()
+        }
+
+        CreatePackageIndexError::MissingPackageIndexReleaseHash(release_uri, package_indexes) => {
+            let release_uri = style::url(release_uri.as_str());
+            let package_indexes = package_indexes
+                .iter()
+                .map(|package_index| format!("- {}", style::value(package_index)))
+                .collect::<Vec<_>>()
+                .join("\n");
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::Yes))
+                .header("Missing Package Index")
+                .body(formatdoc! { "
+                    The Release file from {release_uri} is missing entries within the SHA256 section \
+                    for the following package indexes. This error is most likely a buildpack bug but \
+                    can also be an issue with the upstream repository.
+
+                    Missing package indexes:
+                    {package_indexes}
+
+                    Suggestions:
+                    - Verify if the package indexes are under the SHA256 section of {release_uri}
+                " })
+                .call()
+        }
+
+        CreatePackageIndexError::GetPackagesRequest(e) => {
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::Yes))
+                .header("Failed to request Package Index file")
+                .body(formatdoc! { "
+                    While updating package sources, a request to download a Package Index file failed. \
+                    This error can occur due to an unstable network connection or an issue with the upstream \
+                    Debian package repository.
+
+                    Suggestions:
+                    - Check the status of {canonical_status_url} for any reported issues.
+                " })
+                .debug_info(describe_request_error(&e))
+                .call()
+        }
+
+        CreatePackageIndexError::WritePackagesLayer(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to write Package Index file to layer")
+                .body(formatdoc! { "
+                    An unexpected I/O error occurred while writing Package Index data to {file}.
+                " })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        CreatePackageIndexError::WritePackageIndexFromResponse(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::Yes))
+                .header("Failed to download Package Index file")
+                .body(formatdoc! { "
+                    While updating package sources, an error occurred while downloading a Package Index \
+                    file to {file}. This error can occur due to an unstable network connection or an issue \
+                    with the upstream Debian package repository.
+
+                    Suggestions:
+                    - Check the status of {canonical_status_url} for any reported issues.
+                " })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        CreatePackageIndexError::ChecksumFailed {
+            url,
+            expected,
+            actual,
+        } => {
+            let url = style::url(url);
+            let expected = style::value(expected);
+            let actual = style::value(actual);
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header("Package Index checksum verification failed")
+                .body(formatdoc! { "
+                    While updating package sources, an error occurred while verifying the checksum \
+                    of the Package Index at {url}. This error can occur due to an issue with the upstream \
+                    Debian package repository.
+
+                    Checksum:
+                    - Expected: {expected}
+                    - Actual: {actual}
+                " })
+                .call()
+        }
+
+        CreatePackageIndexError::CpuTaskFailed(e) => {
+            create_error()
+                .error_type(Internal)
+                .header("Task failure while reading Package Index data")
+                .body(indoc! { "
+                    A background task responsible for reading Package Index data failed to complete.
+                " })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        CreatePackageIndexError::ReadPackagesFile(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to read Package Index file")
+                .body(formatdoc! { "
+                    An unexpected I/O error occurred while reading Package Index data from {file}.
+                " })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        CreatePackageIndexError::ParsePackages(file, errors) => {
+            let file = file_value(file);
+            let body_start = formatdoc! { "
+                We can't parse the Package Index file data stored in {file}. This error is most likely \
+                a buildpack bug. It can also be caused by cached data that's no longer valid or an issue \
+                with the upstream repository.
+
+                Parsing errors:
+            " }.trim_end().to_string();
+            let body_error_details = errors
+                .iter()
+                .map(|e| format!("- {e}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let body_end = indoc! { "
+                Suggestions:
+                - Run the build again with a clean cache.
+            " };
+            create_error()
+                .error_type(Internal)
+                .header("Failed to parse Package Index file")
+                .body(format!(
+                    "{body_start}\n{body_error_details}\n\n{body_end}"
+                ))
+                .call()
+        }
+
+        CreatePackageIndexError::SystemTimeError(e) => {
+            create_error()
+                .error_type(Internal)
+                .header("Failed to get system time in CreatePackageIndex")
+                .body(format!("System time error: {}", e))
+                .debug_info(e.to_string())                
+                .call()
+        }
+    }
+}
+
+#[allow(clippy::too_many_lines)]
+fn on_validate_requested_packages_error(error: ValidateRequestedPackagesError) -> ErrorMessage {
+    match error {
+        ValidateRequestedPackagesError::PackageNotAvailableForArchitecture {
+            package,
+            architecture,
+            available_architecture,
+        } => {
+            let package = style::value(package);
+            let architecture = style::value(architecture.to_string());
+            let available_architecture = style::value(available_architecture.to_string());
+            let package_search_url = get_package_search_url();
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header("Package not available for architecture")
+                .body(formatdoc! { "
+                    The package {package} is available on {available_architecture} but not on \
+                    {architecture} which is the architecture this application is being built for.
+
+                    Suggestions:
+                    - Remove {package} from the packages to install when building for {architecture}.
+                    - Check if an alternative package is available for {architecture} at \
+                    {package_search_url}
+                " })
+                .call()
+        }
+
+        ValidateRequestedPackagesError::ForeignArchitectureNotAvailable {
+            package,
+            architecture,
+            available_architectures,
+        } => {
+            let package = style::value(package);
+            let architecture = style::value(architecture.to_string());
+            let available_architectures = if available_architectures.is_empty() {
+                "none".to_string()
+            } else {
+                available_architectures
+                    .iter()
+                    .map(|available_architecture| style::value(available_architecture.to_string()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header("Foreign architecture not available")
+                .body(formatdoc! { "
+                    The package {package} was requested for {architecture} but the distribution \
+                    this application is being built for has no sources for that architecture.
+
+                    Foreign architectures available for this distribution: {available_architectures}
+
+                    Suggestions:
+                    - Request the package for one of the available architectures or remove the \
+                    architecture from its name to install it for the architecture being built for.
+                " })
+                .call()
+        }
+
+        ValidateRequestedPackagesError::TaskNotFound(task) => {
+            let task = style::value(task);
+            let tasks_key = style::value("tasks");
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header("Task not found")
+                .body(formatdoc! { "
+                    The task {task} was requested in {tasks_key} but no package in the package \
+                    index lists it in its Task field.
+
+                    Suggestions:
+                    - Verify the task name is spelled correctly (e.g.; {example_task}).
+                    - Check that the task is available for the distribution this application is \
+                    being built for.
+                ",
+                    example_task = style::value("ubuntu-server"),
+                })
+                .call()
+        }
+
+        ValidateRequestedPackagesError::PackagePatternNotMatched(pattern) => {
+            let pattern = style::value(pattern);
+            let optional_key = style::value("optional = true");
+            let package_search_url = get_package_search_url();
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header("Package pattern not matched")
+                .body(formatdoc! { "
+                    The package pattern {pattern} was requested but it doesn't match the name of \
+                    any package in the package index.
+
+                    Suggestions:
+                    - Verify the pattern is spelled correctly and check which packages are \
+                    available for the distribution this application is being built for at \
+                    {package_search_url}
+                    - Add {optional_key} to the pattern if it's allowed to match no packages.
+                " })
+                .call()
+        }
+
+        ValidateRequestedPackagesError::PresetNotAvailable { preset, codename } => {
+            let preset = style::value(preset.to_string());
+            let codename = style::value(codename.to_string());
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::Yes))
+                .header("Preset not available for distribution")
+                .body(formatdoc! { "
+                    The preset {preset} doesn't have a list of packages for {codename} which is \
+                    the distribution this application is being built for.
+
+                    Suggestions:
+                    - Replace the preset with the packages it should install in {install_key}.
+                ",
+                    install_key = style::value("install"),
+                })
+                .call()
+        }
+
+        ValidateRequestedPackagesError::TooManyPatternMatches {
+            pattern,
+            matched_packages,
+            max_matches,
+        } => {
+            let pattern = style::value(pattern);
+            let max_matches_key = style::value("max_matches");
+            let count = matched_packages.len();
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header("Package pattern matched too many packages")
+                .body(formatdoc! { "
+                    The package pattern {pattern} matched {count} packages but at most \
+                    {max_matches} are allowed. Patterns are limited so a pattern that's broader \
+                    than intended doesn't install an unexpected number of packages.
+
+                    Suggestions:
+                    - Make the pattern more specific (e.g.; anchor it with \"^\" and \"$\").
+                    - Set {max_matches_key} for the pattern if every matched package should be \
+                    installed.
+                " })
+                .debug_info(format!("Matched packages: {}", matched_packages.join(", ")))
+                .call()
+        }
+    }
+}
+
+#[allow(clippy::too_many_lines)]
+fn on_determine_packages_to_install_error(error: DeterminePackagesToInstallError) -> ErrorMessage {
+    match error {
+        DeterminePackagesToInstallError::ReadSystemPackages(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to read system packages")
+                .body(formatdoc! { "
+                    An unexpected I/O error occurred while reading system packages from {file}.
+                "})
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        DeterminePackagesToInstallError::ParseSystemPackage(file, package_data, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to parse system package")
+                .body(formatdoc! { "
+                    An unexpected parsing error occurred while reading system packages from {file}.
+                "})
+                .debug_info(format!("{e}\n\nPackage:\n{package_data}"))
+                .call()
+        }
+
+        DeterminePackagesToInstallError::PackageNotFound(package_name, suggested_packages) => {
+            let package_name = style::value(package_name);
+            let package_search_url = get_package_search_url();
+            let suggestions = if suggested_packages.is_empty() {
+                "- No similarly named packages found".to_string()
+            } else {
+                suggested_packages
+                    .into_iter()
+                    .map(|name| format!("- {}", style::value(name)))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header("Package not found")
+                .body(formatdoc! { "
+                    We can't find {package_name} in the Package Index. If this package is listed in the \
+                    packages to install for this buildpack then the name is most likely misspelled. Otherwise, \
+                    it can be an issue with the upstream Debian package repository.
+
+                    Did you mean?
+                    {suggestions}
+
+                    Suggestions:
+                    - Verify the package name is correct and exists for the target distribution at \
+                    {package_search_url}
+                " })
+                .call()
+        }
+
+        DeterminePackagesToInstallError::VirtualPackageMustBeSpecified(package, providers) => {
+            let package = style::value(package);
+            let body_start = indoc! { "
+                Sometimes there are several packages which offer more-or-less the same functionality. \
+                In this case, Debian repositories define a virtual package and one or more actual \
+                packages provide an implementation for this virtual package. When multiple providers \
+                are found for a requested package, this buildpack can't automatically choose which \
+                one is the desired implementation.
+
+                Providing packages:
+            " };
+            let body_provider_details = providers
+                .iter()
+                .collect::<BTreeSet<_>>()
+                .iter()
+                .map(|provider| format!("- {provider}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let body_end = formatdoc! { "
+                Suggestions:
+                - Replace the virtual package {package} with one of the above providers.
+            " };
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header(format!(
+                    "Multiple providers were found for the package {package}"
+                ))
+                .body(format!("{body_start}{body_provider_details}\n\n{body_end}"))
+                .call()
+        }
+
+        DeterminePackagesToInstallError::VirtualPackageResolutionDisabled(package, providers) => {
+            let package = style::value(package);
+            let resolve_virtual_packages_key = style::value("resolve_virtual_packages");
+            let providers = providers
+                .iter()
+                .collect::<BTreeSet<_>>()
+                .iter()
+                .map(|provider| format!("- {provider}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header(format!("The package {package} is a virtual package"))
+                .body(formatdoc! { "
+                    The requested package {package} is a virtual package which is implemented by one \
+                    or more actual packages. Since {resolve_virtual_packages_key} is set to false, \
+                    this buildpack won't choose a provider for it.
+
+                    Providing packages:
+                    {providers}
+
+                    Suggestions:
+                    - Replace the virtual package {package} with one of the above providers.
+                " })
+                .call()
+        }
+
+        DeterminePackagesToInstallError::TooManyDependencies {
+            package,
+            dependency_count,
+            max_dependencies,
+            largest_subtrees,
+        } => {
+            let package = style::value(package);
+            let max_dependencies_key = style::value("max_dependencies");
+            let skip_dependencies_key = style::value("skip_dependencies");
+            let largest_subtrees = largest_subtrees
+                .iter()
+                .map(|(dependency, count)| format!("- {} ({count})", style::value(dependency)))
+                .collect::<Vec<_>>()
+                .join("\n");
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header(format!("Too many dependencies for the package {package}"))
+                .body(formatdoc! { "
+                    Installing {package} would add {dependency_count} dependencies which is more than \
+                    the limit of {max_dependencies}. This usually means the package name is not the one \
+                    intended and would produce a very large layer.
+
+                    Largest dependencies (with their own dependency counts):
+                    {largest_subtrees}
+
+                    Suggestions:
+                    - Verify {package} is the package you intended to install.
+                    - Set {skip_dependencies_key} for {package} and list the required dependencies explicitly.
+                    - Raise the limit by setting {max_dependencies_key} in the buildpack configuration \
+                    (0 disables the limit).
+                " })
+                .call()
+        }
+
+        DeterminePackagesToInstallError::SystemTimeError(e) => create_error()
+            .error_type(Internal)
+            .header("Failed to get system time in DeterminePackagesToInstall")
+            .body(format!("System time error: {}", e))
+            .debug_info(e.to_string())
+            .call(),
+
+        DeterminePackagesToInstallError::ReadLockfile(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to read lockfile")
+                .body(formatdoc! { "
+                    An unexpected I/O error occurred while reading the lockfile at {file}.
+                "})
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        DeterminePackagesToInstallError::ParseLockfile(file, e) => {
+            let file = file_value(file);
+            let ignore_lockfile_env_var = style::value(IGNORE_LOCKFILE_ENV_VAR);
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header("Error parsing lockfile")
+                .body(formatdoc! { "
+                    The {BUILDPACK_NAME} pins the installed packages to the versions listed in \
+                    {file} but the file isn't a valid lockfile.
+
+                    Suggestions:
+                    - Replace {file} with the lockfile written to the packages layer by a previous \
+                    build.
+                    - Set {ignore_lockfile_env_var} to true to install packages without the lockfile.
+                " })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        DeterminePackagesToInstallError::LockfileDistroMismatch(locked_distro, distro) => {
+            let lockfile = style::value(LOCKFILE_NAME);
+            let ignore_lockfile_env_var = style::value(IGNORE_LOCKFILE_ENV_VAR);
+            let locked_distro = style::value(format!(
+                "{} {} ({})",
+                locked_distro.name, locked_distro.version, locked_distro.architecture
+            ));
+            let distro = style::value(format!(
+                "{} {} ({})",
+                distro.name, distro.version, distro.architecture
+            ));
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header("Lockfile was written for a different distribution")
+                .body(formatdoc! { "
+                    The packages in {lockfile} were locked for {locked_distro} but this build is \
+                    for {distro}. The locked packages can't be installed on a different distribution.
+
+                    Suggestions:
+                    - Set {ignore_lockfile_env_var} to true and replace {lockfile} with the lockfile \
+                    written to the packages layer by that build.
+                " })
+                .call()
+        }
+
+        DeterminePackagesToInstallError::LockedPackagesUnavailable(locked_packages) => {
+            let lockfile = style::value(LOCKFILE_NAME);
+            let ignore_lockfile_env_var = style::value(IGNORE_LOCKFILE_ENV_VAR);
+            let locked_packages = locked_packages
+                .iter()
+                .map(|locked_package| format!("- {}", style::value(locked_package.to_string())))
+                .collect::<Vec<_>>()
+                .join("\n");
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header("Locked packages are no longer available")
+                .body(formatdoc! { "
+                    The following packages locked in {lockfile} aren't offered by the package \
+                    repositories anymore. Repositories usually only keep the latest version of a \
+                    package so locked versions are removed once they're updated.
+
+                    {locked_packages}
+
+                    Suggestions:
+                    - Set {ignore_lockfile_env_var} to true and replace {lockfile} with the lockfile \
+                    written to the packages layer by that build.
+                " })
+                .call()
+        }
+
+        DeterminePackagesToInstallError::PackagesNotLocked(package_names) => {
+            let lockfile = style::value(LOCKFILE_NAME);
+            let ignore_lockfile_env_var = style::value(IGNORE_LOCKFILE_ENV_VAR);
+            let package_names = package_names
+                .iter()
+                .map(|package_name| format!("- {}", style::value(package_name)))
+                .collect::<Vec<_>>()
+                .join("\n");
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header("Packages are missing from the lockfile")
+                .body(formatdoc! { "
+                    The following packages would be installed but aren't locked in {lockfile}. \
+                    This usually means packages were added to project.toml since the lockfile was \
+                    written.
+
+                    {package_names}
+
+                    Suggestions:
+                    - Set {ignore_lockfile_env_var} to true and replace {lockfile} with the lockfile \
+                    written to the packages layer by that build.
+                " })
+                .call()
+        }
+
+        DeterminePackagesToInstallError::VersionConstraintNotSatisfied {
+            package,
+            version_constraint,
+            available_versions,
+        } => {
+            let package = style::value(package);
+            let version_constraint = style::value(version_constraint.to_string());
+            let version_key = style::value("version");
+            let available_versions = available_versions
+                .iter()
+                .map(|version| format!("- {}", style::value(version)))
+                .collect::<Vec<_>>()
+                .join("\n");
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header(format!(
+                    "No version of {package} satisfies {version_constraint}"
+                ))
+                .body(formatdoc! { "
+                    The package {package} was requested with the {version_key} constraint \
+                    {version_constraint} but none of the versions offered by the package \
+                    repositories satisfy it. The available versions are:
+
+                    {available_versions}
+
+                    Suggestions:
+                    - Change the {version_key} of {package} in project.toml to include one of the \
+                    available versions.
+                    - Add a source that publishes a matching version of {package}.
+                " })
+                .call()
+        }
+    }
+}
+
+#[allow(clippy::too_many_lines)]
+fn on_install_packages_error(error: InstallPackagesError) -> ErrorMessage {
+    let canonical_status_url = get_canonical_status_url();
+
+    match error {
+        InstallPackagesError::TaskFailed(e) => create_error()
+            .error_type(Internal)
+            .header("Task failure while installing packages")
+            .body(indoc! { "
+                A background task responsible for installing failed to complete.
+            " })
+            .debug_info(e.to_string())
+            .call(),
+
+        InstallPackagesError::InvalidFilename(package, filename) => {
+            let package = style::value(package);
+            let filename = style::value(filename);
+            create_error()
+                .error_type(Internal)
+                .header(format!("Could not determine file name for {package}"))
+                .body(formatdoc! { "
+                    The package information for {package} contains a Filename field of {filename} \
+                    which produces an invalid name to use as a download path.
+                " })
+                .call()
+        }
+
+        InstallPackagesError::RequestPackage(package, e) => {
+            let package = style::value(package.name);
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::Yes))
+                .header("Failed to request package")
+                .body(formatdoc! { "
+                    While installing packages, an error occurred while downloading {package}. \
+                    This error can occur due to an unstable network connection or an issue \
+                    with the upstream Debian package repository.
+
+                    Suggestions:
+                    - Check the status of {canonical_status_url} for any reported issues.
+                " })
+                .debug_info(describe_request_error(&e))
+                .call()
+        }
+
+        InstallPackagesError::WritePackage(package, download_url, destination_path, e) => {
+            let package = style::value(package.name);
+            let download_url = style::url(download_url);
+            let destination_path = file_value(destination_path);
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::Yes))
+                .header("Failed to download package")
+                .body(formatdoc! { "
+                    An unexpected I/O error occured while downloading {package} from {download_url} \
+                    to {destination_path}. This error can occur due to an unstable network connection or an issue \
+                    with the upstream Debian package repository.
+
+                    Suggestions:
+                    - Check the status of {canonical_status_url} for any reported issues.
+                " })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        InstallPackagesError::ChecksumFailed {
+            url,
+            expected,
+            actual,
+        } => {
+            let url = style::url(url);
+            let expected = style::value(expected);
+            let actual = style::value(actual);
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header("Package checksum verification failed")
+                .body(formatdoc! { "
+                    An error occurred while verifying the checksum of the package at {url}. \
+                    The package was downloaded again after the first checksum failure but the \
+                    checksum still didn't match. This error can occur due to an issue with the \
+                    upstream Debian package repository.
+
+                    Checksum:
+                    - Expected: {expected}
+                    - Actual: {actual}
+                " })
+                .call()
+        }
+
+        InstallPackagesError::OpenPackageArchive(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to open package archive")
+                .body(formatdoc! {
+                    "An unexpected I/O error occurred while trying to open the archive at {file}."
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        InstallPackagesError::OpenPackageArchiveEntry(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to read package archive entry")
+                .body(formatdoc! {
+                    "An unexpected I/O error occurred while trying to read the entries of the \
+                    archive at {file}."
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        InstallPackagesError::UnpackTarball(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to unpack package archive")
+                .body(formatdoc! {
+                    "An unexpected I/O error occurred while trying to unpack the archive at {file}."
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        InstallPackagesError::UnsupportedCompression(file, format) => {
+            let file = file_value(file);
+            let format = style::value(format);
+            create_error()
+                .error_type(Internal)
+                .header("Unsupported compression format for package archive")
+                .body(formatdoc! {
+                    "An unexpected compression format ({format}) was used for the package archive at {file}."
+                })
+                .call()
+        }
+
+        InstallPackagesError::ReadPackageConfig(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to read package config file")
+                .body(formatdoc! {
+                    "An unexpected I/O error occurred while reading the package config file at {file}."
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        InstallPackagesError::WritePackageConfig(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to write package config file")
+                .body(formatdoc! {
+                    "An unexpected I/O error occurred while writing the package config file to {file}."
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        InstallPackagesError::SetPermissions(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to set permissions for postinst script")
+                .body(formatdoc! {
+                    "An unexpected I/O error occurred while setting permissions for the postinst script at {file}."
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        InstallPackagesError::ExecutePostinstScript(e) => create_error()
+            .error_type(Internal)
+            .header("Failed to execute postinst script")
+            .body(formatdoc! {
+                "An error occurred while trying to execute the postinst script."
+            })
+            .debug_info(e.to_string())
+            .call(),
+
+        InstallPackagesError::SystemTimeError(e) => create_error()
+            .error_type(Internal)
+            .header("Failed to get system time in InstallPackages")
+            .body(format!("System time error: {}", e))
+            .debug_info(e.to_string())
+            .call(),
+
+        InstallPackagesError::MultiplePackagesFailed(failed_packages) => {
+            let failed_packages = failed_packages
+                .into_iter()
+                .map(|(package, error)| {
+                    let package = style::value(package);
+                    let error_message = on_install_packages_error(error);
+                    let reason = error_message
+                        .message
+                        .lines()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string();
+                    match error_message.debug_info {
+                        Some(debug_info) => format!("- {package}: {reason} ({debug_info})"),
+                        None => format!("- {package}: {reason}"),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::Yes))
+                .header("Failed to install multiple packages")
+                .body(formatdoc! { "
+                    While installing packages, errors occurred for each of the following packages. \
+                    This can occur due to an unstable network connection or an issue with the upstream \
+                    Debian package repository.
+
+                    Failed packages:
+                    {failed_packages}
+
+                    Suggestions:
+                    - Check the status of {canonical_status_url} for any reported issues.
+                " })
+                .call()
+        }
+
+        InstallPackagesError::SerializeSnapshot(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to serialize installation snapshot")
+                .body(formatdoc! {
+                    "An unexpected error occurred while serializing the installation snapshot for {file}."
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        InstallPackagesError::SerializeLockfile(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to serialize lockfile")
+                .body(formatdoc! {
+                    "An unexpected error occurred while serializing the lockfile for {file}."
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        InstallPackagesError::WriteLockfile(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to write lockfile")
+                .body(formatdoc! {
+                    "An unexpected I/O error occurred while writing the lockfile to {file}."
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        InstallPackagesError::WriteSelfCheck(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to write self-check")
+                .body(formatdoc! {
+                    "An unexpected I/O error occurred while writing the launch self-check to {file}."
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        InstallPackagesError::CreateDownloadDir(dir, e) => {
+            let dir = file_value(dir);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to create download directory")
+                .body(formatdoc! {
+                    "An unexpected I/O error occurred while creating {dir} to download the packages to reinstall."
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        InstallPackagesError::SerializeProvenance(e) => create_error()
+            .error_type(Internal)
+            .header("Failed to serialize provenance")
+            .body(indoc! { "
+                An unexpected error occurred while serializing the provenance statement for the \
+                packages layer.
+            " })
+            .debug_info(e.to_string())
+            .call(),
+
+        InstallPackagesError::WriteProvenance(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to write provenance")
+                .body(formatdoc! {
+                    "An unexpected I/O error occurred while writing the provenance statement to {file}."
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        InstallPackagesError::RemovePackageFiles(e) => create_error()
+            .error_type(Internal)
+            .header("Failed to remove package files")
+            .body(indoc! { "
+                An unexpected I/O error occurred while removing the files of packages that are no \
+                longer installed from the packages layer.
+            " })
+            .debug_info(e.to_string())
+            .call(),
+
+        InstallPackagesError::WriteInstalledFiles(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to write installed files")
+                .body(formatdoc! {
+                    "An unexpected I/O error occurred while writing the files installed by each package to {file}."
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        InstallPackagesError::SerializeSbom(e) => create_error()
+            .error_type(Internal)
+            .header("Failed to serialize SBOMs")
+            .body(indoc! { "
+                An unexpected error occurred while serializing the SBOMs for the installed packages.
+            " })
+            .debug_info(e.to_string())
+            .call(),
+
+        InstallPackagesError::WriteSnapshot(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to write installation snapshot")
+                .body(formatdoc! {
+                    "An unexpected I/O error occurred while writing the installation snapshot to {file}."
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        InstallPackagesError::ExtractionIssues(extraction_issues) => {
+            let strict_extraction_key = style::value("strict_extraction");
+            let extraction_issues = extraction_issues
+                .iter()
+                .flat_map(|(package_name, package_issues)| {
+                    package_issues.iter().map(move |extraction_issue| {
+                        format!("- {}: {extraction_issue}", style::value(package_name))
+                    })
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header("Installed packages contain files that may not work when relocated")
+                .body(formatdoc! { "
+                    Packages are installed into a layer instead of the root filesystem they were built \
+                    for. Since {strict_extraction_key} is set to true, the build failed because the \
+                    following files may not work from that location:
+
+                    {extraction_issues}
+
+                    Suggestions:
+                    - Install the packages that provide the missing symlink targets.
+                    - Remove {strict_extraction_key} from project.toml to report these files without \
+                    failing the build.
+                " })
+                .call()
+        }
+
+        InstallPackagesError::ExistingFiles(package_name, existing_files) => {
+            let existing_files_key = style::value("existing_files");
+            let package_name = style::value(package_name);
+            let existing_files = existing_files
+                .iter()
+                .map(|file| format!("- {}", file.to_string_lossy().trim_start_matches("./")))
+                .collect::<Vec<_>>()
+                .join("\n");
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header(format!("Package {package_name} contains files that already exist"))
+                .body(formatdoc! { "
+                    Since {existing_files_key} is set to \"error\", the build failed because the \
+                    following files from {package_name} already exist in the packages layer, in the app \
+                    directory, or in a package installed before it:
+
+                    {existing_files}
+
+                    Suggestions:
+                    - Remove the package that provides these files from the packages to install.
+                    - Set {existing_files_key} to \"skip\" to keep the existing files or \"overwrite\" \
+                    to replace them.
+                " })
+                .call()
+        }
+
+        InstallPackagesError::PostinstScriptFailed(package_name, script_failure) => {
+            let on_script_failure_key = style::value("on_script_failure");
+            let run_scripts_key = style::value("run_scripts");
+            let exit = script_failure.exit_description();
+            let package_name = style::value(package_name);
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header(format!("Postinst script for {package_name} failed"))
+                .body(formatdoc! { "
+                    The postinst script of {package_name} failed with {exit}. Since \
+                    {on_script_failure_key} is set to \"error\" for this package, the build was stopped.
+
+                    Suggestions:
+                    - Use the debug information above to find out why the script failed.
+                    - Set {on_script_failure_key} to \"warn\" to continue the build when the script fails.
+                    - Remove {run_scripts_key} from the package if the script isn't needed.
+                " })
+                .debug_info(describe_script_output(&script_failure))
+                .call()
+        }
+
+        InstallPackagesError::PackageIntegration(error) => on_package_integration_error(error),
+
+        InstallPackagesError::CreateAlternatives(CreateAlternativesError(link, e)) => {
+            let link = file_value(link);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to create alternative")
+                .body(formatdoc! {
+                    "An unexpected I/O error occurred while creating the alternative link at {link}."
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        InstallPackagesError::LayoutIssues(layout_issues) => {
+            let verify_layout_key = style::value("verify_layout");
+            let layout_issues = layout_issues
+                .iter()
+                .flat_map(|(package_name, package_issues)| {
+                    package_issues.iter().map(move |layout_issue| {
+                        format!("- {}: {layout_issue}", style::value(package_name))
+                    })
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::Yes))
+                .header("Installed packages failed layout verification")
+                .body(formatdoc! { "
+                    Since {verify_layout_key} is set to true, the installed packages were checked \
+                    after extraction and the following problems were found:
+
+                    {layout_issues}
+
+                    This usually means a package wasn't extracted correctly.
+                " })
+                .call()
+        }
+    }
+}
+
+fn on_package_integration_error(error: PackageIntegrationError) -> ErrorMessage {
+    match error {
+        PackageIntegrationError::JavaKeystore(error) => on_java_keystore_error(error),
+
+        PackageIntegrationError::WriteFontconfig(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to write fontconfig configuration")
+                .body(formatdoc! {
+                    "An unexpected I/O error occurred while writing the fontconfig configuration \
+                    to {file}."
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        PackageIntegrationError::GenerateLocales(error) => on_generate_locales_error(error),
+
+        PackageIntegrationError::UnknownTimezone(timezone) => {
+            let tz = style::value("TZ");
+            let timezone = style::value(timezone);
+            let tzdata = style::value("tzdata");
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header("Unknown time zone")
+                .body(formatdoc! { "
+                    The time zone {timezone} configured by {tz} isn't a time zone provided by the \
+                    installed {tzdata} package or a POSIX time zone specification. Programs will \
+                    fall back to UTC when it's used.
+
+                    Suggestions:
+                    - Verify the time zone name is correct (e.g.; America/New_York).
+                    - Verify the installed version of {tzdata} includes the time zone.
+                " })
+                .call()
+        }
+
+        PackageIntegrationError::ImageMagick(error) => on_imagemagick_error(error),
+    }
+}
+
+fn on_java_keystore_error(error: JavaKeystoreError) -> ErrorMessage {
+    match error {
+        JavaKeystoreError::ReadCertificates(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to read CA certificates")
+                .body(formatdoc! {
+                    "An unexpected I/O error occurred while reading the CA certificates at {file} \
+                    to generate the Java keystore."
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        JavaKeystoreError::NoCertificates => {
+            let java_package = style::value("ca-certificates-java");
+            let ca_certificates_package = style::value("ca-certificates");
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header("No CA certificates found for the Java keystore")
+                .body(formatdoc! { "
+                    The {java_package} package was installed so a Java keystore is generated from \
+                    the installed CA certificates but no certificates were found in the layer or on \
+                    the base image.
+
+                    Suggestions:
+                    - Add {ca_certificates_package} to the packages to install in project.toml.
+                " })
+                .call()
+        }
+
+        JavaKeystoreError::WriteKeystore(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to write Java keystore")
+                .body(formatdoc! {
+                    "An unexpected I/O error occurred while writing the Java keystore to {file}."
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+    }
+}
+
+fn on_imagemagick_error(error: ImageMagickError) -> ErrorMessage {
+    let imagemagick_policy_key = style::value("imagemagick_policy");
+    match error {
+        ImageMagickError::NotInstalled => {
+            let imagemagick_package = style::value("imagemagick");
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header("ImageMagick isn't installed")
+                .body(formatdoc! { "
+                    An ImageMagick policy was configured with {imagemagick_policy_key} in \
+                    project.toml but no ImageMagick configuration directories were found in the \
+                    installed packages.
+
+                    Suggestions:
+                    - Add {imagemagick_package} to the packages to install in project.toml.
+                    - Remove {imagemagick_policy_key} from project.toml.
+                " })
+                .call()
+        }
+
+        ImageMagickError::ReadPolicy(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::Yes, SuggestSubmitIssue::No))
+                .header("Failed to read ImageMagick policy")
+                .body(formatdoc! { "
+                    An error occurred while reading the ImageMagick policy at {file} configured \
+                    with {imagemagick_policy_key} in project.toml.
+
+                    Suggestions:
+                    - Verify the file exists and that the path is relative to the application \
+                    directory.
+                " })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        ImageMagickError::WritePolicy(file, e) => {
+            let file = file_value(file);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to write ImageMagick policy")
+                .body(formatdoc! {
+                    "An unexpected I/O error occurred while writing the ImageMagick policy to {file}."
+                })
+                .debug_info(e.to_string())
+                .call()
+        }
+    }
+}
+
+fn on_generate_locales_error(error: GenerateLocalesError) -> ErrorMessage {
+    let locales_key = style::value("locales");
+
+    match error {
+        GenerateLocalesError::LocalesNotInstalled => {
+            let locales_package = style::value("locales");
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header("Locales package not installed")
+                .body(formatdoc! { "
+                    The locales configured in {locales_key} are compiled from the locale definitions \
+                    in the {locales_package} package but it isn't installed in the layer or on the \
+                    base image.
+
+                    Suggestions:
+                    - Add {locales_package} to the packages to install in project.toml.
+                " })
+                .call()
+        }
+
+        GenerateLocalesError::CreateLocaleDir(locale_dir, e) => {
+            let locale_dir = file_value(locale_dir);
+            create_error()
+                .error_type(Internal)
+                .header("Failed to create locale directory")
+                .body(formatdoc! { "
+                    An unexpected I/O error occurred while creating the directory for the generated \
+                    locales at {locale_dir}.
+                " })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        GenerateLocalesError::RunLocaledef(locale, e) => {
+            let locale = style::value(locale.to_string());
+            let localedef = style::value("localedef");
+            create_error()
+                .error_type(Internal)
+                .header("Failed to run localedef")
+                .body(formatdoc! { "
+                    An unexpected error occurred while running {localedef} to generate the locale \
+                    {locale}.
+                " })
+                .debug_info(e.to_string())
+                .call()
+        }
+
+        GenerateLocalesError::LocaledefFailed(locale, stderr) => {
+            let locale_name = style::value(locale.to_string());
+            let locale_input = style::value(locale.input());
+            let charmap = style::value(locale.charmap());
+            create_error()
+                .error_type(UserFacing(SuggestRetryBuild::No, SuggestSubmitIssue::No))
+                .header(format!("Failed to generate locale {locale_name}"))
+                .body(formatdoc! { "
+                    The locale {locale_name} configured in {locales_key} couldn't be compiled from the \
+                    locale definition {locale_input} with the charmap {charmap}.
+
+                    Suggestions:
+                    - Verify the locale is listed in /usr/share/i18n/SUPPORTED.
+                " })
+                .debug_info(stderr)
+                .call()
+        }
+    }
+}
+
+fn on_framework_error(error: &Error<DebianPackagesBuildpackError>) -> ErrorMessage {
+    create_error()
+        .error_type(Framework)
+        .header("Heroku Deb Packages Buildpack internal error")
+        .body(formatdoc! {"
+            The framework used by this buildpack encountered an unexpected error.
+
+            If you can’t deploy to Heroku due to this issue, check the official Heroku Status page at \
+            status.heroku.com for any ongoing incidents. After all incidents resolve, retry your build.
+
+            Use the debug information above to troubleshoot and retry your build. If you think you found a \
+            bug in the buildpack, reproduce the issue locally with a minimal example and file an issue here:
+            https://github.com/heroku/buildpacks-deb-packages/issues/new
+        "})
+        .debug_info(error.to_string())
+        .call()
+}
+
+#[builder]
+fn create_error(
+    header: impl AsRef<str>,
+    body: impl AsRef<str>,
+    error_type: ErrorType,
+    debug_info: Option<String>,
+) -> ErrorMessage {
+    let mut message_parts = vec![
+        header.as_ref().trim().to_string(),
+        body.as_ref().trim().to_string(),
+    ];
+    let issues_url = style::url("https://github.com/heroku/buildpacks-deb-packages/issues/new");
+    let pack = style::value("pack");
+    let pack_url =
+        style::url("https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/");
+
+    match error_type {
+        Framework => {}
+        Internal => {
+            message_parts.push(formatdoc! { "
+                The causes for this error are unknown. We do not have suggestions for diagnosis or a \
+                workaround at this time. You can help our understanding by sharing your buildpack log \
+                and a description of the issue at:
+                {issues_url}
+
+                If you're able to reproduce the problem with an example application and the {pack} \
+                build tool ({pack_url}), adding that information to the discussion will also help. Once \
+                we have more information around the causes of this error we may update this message.
+            "});
+        }
+        UserFacing(suggest_retry_build, suggest_submit_issue) => {
+            if let SuggestRetryBuild::Yes = suggest_retry_build {
+                message_parts.push(
+                    formatdoc! { "
+                    Use the debug information above to troubleshoot and retry your build.
+                "}
+                    .trim()
+                    .to_string(),
+                );
+            }
+
+            if let SuggestSubmitIssue::Yes = suggest_submit_issue {
+                message_parts.push(formatdoc! { "
+                    If the issue persists and you think you found a bug in the buildpack, reproduce \
+                    the issue locally with a minimal example. Open an issue in the buildpack's GitHub \
+                    repository and include the details here:
+                    {issues_url}
+                "}.trim().to_string());
+            }
+        }
+    }
+
+    let message = message_parts.join("\n\n");
+
+    ErrorMessage {
+        debug_info,
+        message,
+    }
+}
+
+fn print_error<W>(error_message: ErrorMessage, writer: W)
+where
+    W: Write + Send + Sync + 'static,
+{
+    let mut log = Print::new(writer).without_header();
+    if let Some(debug_info) = error_message.debug_info {
+        log = log
+            .bullet(style::important("Debug Info:"))
+            .sub_bullet(debug_info)
+            .done();
+    }
+    log.error(error_message.message);
+}
+
+fn file_value(value: impl AsRef<Path>) -> String {
+    style::value(value.as_ref().to_string_lossy())
+}
+
+fn get_canonical_status_url() -> String {
+    style::url("https://status.canonical.com/")
+}
+
+fn get_package_search_url() -> String {
+    style::url("https://packages.ubuntu.com/")
+}
+
+#[derive(Debug)]
+struct ErrorMessage {
+    debug_info: Option<String>,
+    message: String,
+}
+
+#[derive(Debug, PartialEq)]
+enum ErrorType {
+    Framework,
+    Internal,
+    UserFacing(SuggestRetryBuild, SuggestSubmitIssue),
+}
+
+#[derive(Debug, PartialEq)]
+enum SuggestRetryBuild {
+    Yes,
+    No,
+}
+
+#[derive(Debug, PartialEq)]
+enum SuggestSubmitIssue {
+    Yes,
+    No,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check_sources::{UnreachableReason, UnreachableSource};
+    use crate::config::{Deprecation, InvalidProxyError, Locale, ParsePpaError, Ppa, Preset};
+    use crate::debian::{
+        ArchitectureName, ChecksumAlgorithm, Distro, DistroCodename, ParsePackageNameError,
+        ParseRepositoryPackageError, RepositoryPackage, RepositoryUri,
+    };
+    use crate::extraction_report::ExtractionIssue;
+    use crate::layout_verification::LayoutIssue;
+    use crate::lockfile::LockedPackage;
+    use crate::test_support::create_repository_package;
+    use crate::DebianPackagesBuildpackError::{LoadSigningKey, UnsupportedDistro};
+    use anyhow::anyhow;
+    use libcnb::data::layer::LayerNameError;
+    use libcnb_test::assert_contains_match;
+    use reqwest::StatusCode;
+    use std::collections::{BTreeMap, HashSet};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_config_check_exists_errors() {
+        test_error_output(
+            "
+                Context
+                -------
+                When detect is executed on this buildpack, we check to see if a project.toml
+                file exists since that's where configuration for this buildpack would be found.
+                I/O operations can fail for a number of reasons which we can't anticipate and
+                the best we can do here is report the error message.
+            ",
+            ConfigError::CheckExists(
+                "/path/to/project.toml".into(),
+                create_io_error("test I/O error"),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - test I/O error
+
+                ! Unable to complete buildpack detection
+                !
+                ! An unexpected I/O error occurred while checking `/path/to/project.toml` to \
+                determine if the Heroku .deb Packages buildpack is compatible for this application.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_read_config_error() {
+        test_error_output(
+            "
+                Context
+                -------
+                We read the buildpack configuration from project.toml. I/O operations can fail
+                for a number of reasons which we can't anticipate but the most likely one here would
+                be that we don't have read permissions.
+            ",
+            ConfigError::ReadConfig(
+                "/path/to/project.toml".into(),
+                create_io_error("test I/O error"),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - test I/O error
+
+                ! Error reading `/path/to/project.toml`
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from `/path/to/project.toml` \
+                to complete the build but the file can't be read.
+                !
+                ! Suggestions:
+                ! - Ensure the file has read permissions.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_wrong_config_type() {
+        test_error_output("
+                Context
+                -------
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but the value supplied using the configuration key for this buildpack
+                is not a TOML table then the configuration is incorrect and we can provide details to the
+                user on how they can fix this.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::WrongConfigType,
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with invalid key
+                !
+                ! The Heroku .deb Packages buildpack reads the configuration from `/path/to/project.toml` \
+                to complete the build but the configuration for the key `[com.heroku.buildpacks.deb-packages]` \
+                isn't the correct type. The value of this key must be a TOML table.
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                ! - See the TOML documentation for more details on the TOML table type at \
+                https://toml.io/en/v1.0.0
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_error_removed_options() {
+        test_error_output(
+            "
+                Context
+                -------
+                Deprecated config options fail the build once the buildpack reaches the major
+                version they were removed in. Each removed option is listed with a hint on what to
+                use instead.
+            ",
+            ConfigError::RemovedOptions(
+                "/path/to/project.toml".into(),
+                vec![Deprecation {
+                    option: "old_option",
+                    hint: "Use `new_option` instead.",
+                    removed_in_major_version: 1,
+                }],
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with removed options
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from `/path/to/project.toml` \
+                to complete the build but the following options in `[com.heroku.buildpacks.deb-packages]` \
+                are no longer supported:
+                !
+                ! - `old_option` Use `new_option` instead.
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_error_parse_install_env() {
+        test_error_output(
+            "
+                Context
+                -------
+                Packages can be listed in the BP_DEB_PACKAGES_INSTALL environment variable instead
+                of project.toml. Each comma-separated name is validated the same way as the names
+                in project.toml.
+            ",
+            ConfigError::ParseInstallEnv(ParsePackageNameError {
+                package_name: "ffmpeg;".to_string(),
+            }),
+            indoc! {"
+                ! Error parsing `BP_DEB_PACKAGES_INSTALL` with invalid package name
+                !
+                ! The Heroku .deb Packages buildpack reads the packages to install from the \
+                `BP_DEB_PACKAGES_INSTALL` environment variable but we found an invalid package name \
+                `ffmpeg;`.
+                !
+                ! Package names must consist only of lowercase letters (a-z), digits (0-9), plus (+) \
+                and minus (-) signs, and periods (.). Names must be at least two characters long and \
+                must start with an alphanumeric character. \
+                See https://www.debian.org/doc/debian-policy/ch-controlfields.html#s-f-source
+                !
+                ! Suggestions:
+                ! - Verify the package names are separated by commas (e.g.; `ffmpeg, libvips-tools`)
+                ! - Verify the package name is correct and exists for the target distribution at https://packages.ubuntu.com/
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_build_plan_error() {
+        test_error_output("
+                Context
+                -------
+                Other buildpacks can request packages by requiring deb-packages in the build plan with
+                a list of packages in the metadata. If the metadata has the wrong shape the user can't
+                fix it so we point them to the buildpack that required it.
+            ",
+            ConfigError::ParseBuildPlan(serde::de::Error::custom(
+                "invalid type: string \"libgdal-dev\", expected a sequence",
+            )),
+            indoc! {"
+                - Debug Info:
+                  - invalid type: string \"libgdal-dev\", expected a sequence
+
+                ! Error parsing the build plan
+                !
+                ! Another buildpack in this build requires `deb-packages` with metadata the Heroku \
+                .deb Packages buildpack can't read. The `packages` key in the metadata must be an \
+                array of package names (e.g.; packages = [\"libgdal-dev\"]).
+                !
+                ! Suggestions:
+                ! - Contact the maintainers of the buildpack that requires `deb-packages`.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_build_plan_package_error() {
+        test_error_output(
+            "
+                Context
+                -------
+                Package names requested through the build plan are validated the same way as the
+                names in project.toml.
+            ",
+            ConfigError::ParseBuildPlanPackage(ParsePackageNameError {
+                package_name: "libgdal dev".to_string(),
+            }),
+            indoc! {"
+                ! Error parsing the build plan with invalid package name
+                !
+                ! Another buildpack in this build requires `deb-packages` to install the package \
+                `libgdal dev` but it's not a valid package name.
+                !
+                ! Suggestions:
+                ! - Contact the maintainers of the buildpack that requires `deb-packages`.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_invalid_proxy_error() {
+        test_error_output(
+            "
+                Context
+                -------
+                Proxies are read from the HTTP_PROXY, HTTPS_PROXY, and NO_PROXY variables of the
+                platform or process environment. Credentials in the value are redacted.
+            ",
+            ConfigError::InvalidProxy(InvalidProxyError {
+                name: "HTTPS_PROXY",
+                value: "http://redacted@proxy:not-a-port".to_string(),
+                reason: "builder error".to_string(),
+            }),
+            indoc! {"
+                - Debug Info:
+                  - builder error
+
+                ! Invalid proxy configured in `HTTPS_PROXY`
+                !
+                ! The Heroku .deb Packages buildpack sends requests for package repositories \
+                through the proxy configured in the `HTTPS_PROXY` environment variable but the \
+                value `http://redacted@proxy:not-a-port` isn't a valid proxy URL.
+                !
+                ! Suggestions:
+                ! - Set `HTTPS_PROXY` to the URL of the proxy (e.g.; \
+                `http://proxy.example.com:3128`).
+            "},
+        );
+    }
+
+    #[test]
+    fn config_client_certificate_read_file_error() {
+        test_error_output(
+            "
+                Context
+                -------
+                A source configured with client_certificate and client_key reads both files from the
+                application directory (or an absolute path) to build a client for mutual TLS.
+            ",
+            ConfigError::ClientCertificate(ClientCertificateError::ReadFile(
+                RepositoryUri::from("https://repo.example.com/ubuntu"),
+                "/app/certs/client.crt".into(),
+                create_io_error("not found"),
+            )),
+            indoc! {"
+                - Debug Info:
+                  - not found
+
+                ! Error reading client certificate for https://repo.example.com/ubuntu
+                !
+                ! The Heroku .deb Packages buildpack presents the client certificate configured \
+                for the source https://repo.example.com/ubuntu to the repository but \
+                `/app/certs/client.crt` can't be read.
+                !
+                ! Suggestions:
+                ! - Ensure the `client_certificate` and `client_key` paths are relative to the \
+                application directory or absolute.
+                ! - Ensure the file has read permissions.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_read_sources_file_error() {
+        test_error_output(
+            "
+                Context
+                -------
+                Sources in the deb822 format can be read from a file in the application directory
+                configured with sources_file in project.toml.
+            ",
+            ConfigError::ReadSourcesFile(
+                "/app/config/example.sources".into(),
+                create_io_error("not found"),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - not found
+
+                ! Error reading `/app/config/example.sources`
+                !
+                ! The Heroku .deb Packages buildpack reads additional sources from \
+                `/app/config/example.sources` (configured with `sources_file` in project.toml) but \
+                the file can't be read.
+                !
+                ! Suggestions:
+                ! - Ensure the `sources_file` path is relative to the application directory.
+                ! - Ensure the file has read permissions.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_read_signing_key_file_error() {
+        test_error_output(
+            "
+                Context
+                -------
+                The signing key of a source can be read from a file in the application directory
+                configured with signed_by_file in project.toml.
+            ",
+            ConfigError::ReadSigningKeyFile(
+                "/app/keys/example.asc".into(),
+                create_io_error("not found"),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - not found
+
+                ! Error reading `/app/keys/example.asc`
+                !
+                ! The Heroku .deb Packages buildpack reads the signing key of a source from \
+                `/app/keys/example.asc` (configured with `signed_by_file` in project.toml) but the \
+                file can't be read.
+                !
+                ! Suggestions:
+                ! - Ensure the `signed_by_file` path is relative to the application directory.
+                ! - Ensure the file has read permissions.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_invalid_signing_key_file_error() {
+        test_error_output(
+            "
+                Context
+                -------
+                The file configured with signed_by_file must contain an OpenPGP public key.
+            ",
+            ConfigError::InvalidSigningKeyFile(
+                "/app/keys/example.asc".into(),
+                anyhow!("Malformed Cert: No data"),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - Malformed Cert: No data
+
+                ! Invalid signing key in `/app/keys/example.asc`
+                !
+                ! The Heroku .deb Packages buildpack reads the signing key of a source from \
+                `/app/keys/example.asc` (configured with `signed_by_file` in project.toml) but the \
+                file doesn't contain an OpenPGP public key.
+                !
+                ! Suggestions:
+                ! - Ensure the file contains the ASCII-armored or binary public key used to sign \
+                the repository (e.g.; the output of `gpg --export --armor <key-id>`).
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_sources_file_error() {
+        test_error_output(
+            "
+                Context
+                -------
+                Each stanza in the sources file is validated the same way as a source table in
+                project.toml but errors name the deb822 fields.
+            ",
+            ConfigError::ParseSourcesFile(
+                "/app/config/example.sources".into(),
+                ParseCustomSourceError::InvalidDeb822(
+                    "deb https://repo.example.com/ubuntu noble main".to_string(),
+                ),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - deb https://repo.example.com/ubuntu noble main
+
+                ! Error parsing `/app/config/example.sources` with invalid sources
+                !
+                ! The Heroku .deb Packages buildpack reads additional sources from \
+                `/app/config/example.sources` to complete the build but a source in the deb822 \
+                format has a line that isn't a field or a continuation of one.
+                !
+                ! Each source must be a deb822 stanza with `Types`, `URIs`, `Suites`, and \
+                `Components` fields and a `Signed-By` field containing the ASCII-armored public \
+                key used to sign the repository (with a ` .` for each empty line). The `Signed-By` \
+                field can be left out when the optional `Signature-Policy` field is set to \
+                `optional` or `disabled`. For example:
+                !
+                ! Types: deb
+                ! URIs: https://repo.example.com/ubuntu
+                ! Suites: noble
+                ! Components: main
+                ! Signed-By:
+                !  -----BEGIN PGP PUBLIC KEY BLOCK-----
+                !  .
+                !  ...
+                !  -----END PGP PUBLIC KEY BLOCK-----
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_client_certificate_invalid_identity_error() {
+        test_error_output(
+            "
+                Context
+                -------
+                The client certificate and private key configured for a source must be PEM encoded
+                and usable by the TLS implementation.
+            ",
+            ConfigError::ClientCertificate(ClientCertificateError::InvalidIdentity(
+                RepositoryUri::from("https://repo.example.com/ubuntu"),
+                create_reqwest_error(),
+            )),
+            indoc! {"
+                - Debug Info:
+                  - error sending request for url (https://test/error)
+
+                ! Invalid client certificate for https://repo.example.com/ubuntu
+                !
+                ! The Heroku .deb Packages buildpack presents the client certificate configured \
+                for the source https://repo.example.com/ubuntu to the repository but the \
+                certificate and private key can't be used.
+                !
+                ! Suggestions:
+                ! - Ensure `client_certificate` is a PEM encoded certificate and `client_key` is \
+                its PEM encoded private key.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_network_env_error() {
+        test_error_output(
+            "
+                Context
+                -------
+                The network settings can be overridden with environment variables (e.g.;
+                BP_DEB_PACKAGES_MAX_RETRIES) which must be non-negative integers.
+            ",
+            ConfigError::ParseNetworkEnv(ParseNetworkSettingsError::InvalidValue(
+                MAX_RETRIES_ENV_VAR,
+                "many".to_string(),
+            )),
+            indoc! {"
+                ! Invalid network settings
+                !
+                ! The Heroku .deb Packages buildpack reads network settings from the environment \
+                to complete the build but the `BP_DEB_PACKAGES_MAX_RETRIES` environment variable \
+                has the invalid value `many`.
+                !
+                ! Suggestions:
+                ! - Set `BP_DEB_PACKAGES_TIMEOUT` to the request timeout in seconds (greater than \
+                0).
+                ! - Set `BP_DEB_PACKAGES_MAX_RETRIES` to the number of times failed requests are \
+                retried.
+                ! - Set `BP_DEB_PACKAGES_MIN_RETRY_INTERVAL` and \
+                `BP_DEB_PACKAGES_MAX_RETRY_INTERVAL` to the bounds of the wait between retries in \
+                seconds.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_unsupported_target_codename_error() {
+        test_error_output("
+                Context
+                -------
+                The target_codename is checked against the distributions in buildpack.toml once the
+                build image is detected since the sources depend on the architecture being built for.
+            ",
+            ConfigError::UnsupportedTargetCodename(UnsupportedTargetCodenameError {
+                codename: "bionic".into(),
+                architecture: ArchitectureName::AMD_64,
+                supported_codenames: vec![
+                    "noble".to_string(),
+                    "jammy".to_string(),
+                    "focal".to_string(),
+                ],
+            }),
+            indoc! {"
+                ! Unsupported target codename `bionic`
+                !
+                ! The Heroku .deb Packages buildpack was configured with `target_codename` to \
+                resolve packages for the `bionic` release but it doesn't have package sources for \
+                that release on amd64.
+                !
+                ! Suggestions:
+                ! - Set `target_codename` to the codename of the release the run image is built \
+                from. The supported codenames for amd64 are: `noble`, `jammy`, `focal`.
+                ! - Remove `target_codename` from project.toml if the run image is built from the \
+                same release as the build image.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_invalid_toml() {
+        test_error_output("
+                Context
+                -------
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                This error will be reported if the file is not valid TOML.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidToml(Box::new(
+                    toml_edit::DocumentMut::from_str("[com.heroku").unwrap_err(),
+                )),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - TOML parse error at line 1, column 12
+                      |
+                    1 | [com.heroku
+                      |            ^
+                    invalid table header
+                    expected `.`, `]`
+
+                ! Error parsing `/path/to/project.toml` with invalid TOML file
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from `/path/to/project.toml` \
+                to complete the build but this file isn't a valid TOML file.
+                !
+                ! Suggestions:
+                ! - Ensure the file follows the TOML format described at https://toml.io/en/v1.0.0
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_invalid_package_name() {
+        test_error_output("
+                Context
+                -------
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but the value supplied using the configuration for this buildpack
+                contains a package name that would be invalid according to Debian package naming policies,
+                we report this package name to the user and ask them to verify it.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::ParseRequestedPackage(
+                    ParseRequestedPackageError::InvalidPackageName(ParsePackageNameError {
+                        package_name: "invalid!package!name".to_string(),
+                    }),
+                ),
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with invalid package name
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from `/path/to/project.toml` \
+                to complete the build but we found an invalid package name `invalid!package!name` \
+                in the key `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! Package names must consist only of lowercase letters (a-z), digits (0-9), plus (+) \
+                and minus (-) signs, and periods (.). Names must be at least two characters long and \
+                must start with an alphanumeric character. \
+                See https://www.debian.org/doc/debian-policy/ch-controlfields.html#s-f-source
+                !
+                ! Suggestions:
+                ! - Verify the package name is correct and exists for the target distribution at https://packages.ubuntu.com/
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_unsupported_package_architecture() {
+        test_error_output("
+                Context
+                -------
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                A package can be requested for a foreign architecture with a `name:architecture` entry
+                but the architecture must be one the buildpack has sources for.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::ParseRequestedPackage(
+                    ParseRequestedPackageError::UnsupportedArchitecture("x86".to_string()),
+                ),
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with unsupported package architecture
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found a package requested for \
+                the unsupported architecture `x86` in the key \
+                `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! A package can be requested for another architecture by adding the Debian \
+                architecture name after its name (e.g.; `libc6:i386`). The architecture must be \
+                one of \"amd64\", \"arm64\", \"armhf\", \"ppc64el\", \"s390x\", \"riscv64\", or \"i386\".
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_invalid_package_name_config_type() {
+        test_error_output("
+                Context
+                -------
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but the value supplied using the configuration for this buildpack
+                contains a package entry that is neither a string nor inline table value, it is invalid.
+                We report this to the user and request they check the buildpack documentation for proper
+                usage.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::ParseRequestedPackage(
+                    ParseRequestedPackageError::UnexpectedTomlValue(Box::new(
+                        toml_edit::value(37).into_value().unwrap(),
+                    )),
+                ),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - Invalid type `integer` with value `37`
+
+                ! Error parsing `/path/to/project.toml` with invalid package format
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from `/path/to/project.toml` \
+                to complete the build but we found an invalid package format in the key \
+                `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! Packages must either be the following TOML values:
+                ! - String (e.g.; \"package-name\")
+                ! - Inline table (e.g.; { name = \"package-name\", skip_dependencies = true })
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                ! - See the TOML documentation for more details on the TOML string and inline \
+                table types at https://toml.io/en/v1.0.0
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_invalid_exclude_pattern() {
+        test_error_output("
+                Context
+                -------
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but an exclude pattern contains characters that can never match
+                a Debian package name, we report the pattern to the user and ask them to verify it.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::ParseExclude(ParsePackageGlobError::InvalidPackageGlob(
+                    "*_doc".to_string(),
+                )),
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with invalid exclude pattern
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from `/path/to/project.toml` \
+                to complete the build but we found an invalid exclude pattern `*_doc` \
+                in the key `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! Exclude patterns must consist only of lowercase letters (a-z), digits (0-9), plus (+) \
+                and minus (-) signs, periods (.), and the wildcards (*) and (?).
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_invalid_source_priority() {
+        test_error_output("
+                Context
+                -------
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but the source priority isn't a list of repository URIs we report
+                the invalid value to the user and point them to the documentation.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidSourcePriority("1".to_string()),
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with invalid source_priority
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from `/path/to/project.toml` \
+                to complete the build but we found an invalid value `1` for the key `source_priority` \
+                in `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! The value must be an array of repository URIs (e.g.; [\"http://archive.ubuntu.com/ubuntu\"]) \
+                ordered from most to least preferred.
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_conflicting_source_keys() {
+        test_error_output(
+            "
+                Context
+                -------
+                A source can set its signing key inline with signed_by or read it from a file with
+                signed_by_file but not both.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::ParseSource(ParseCustomSourceError::ConflictingKeys(
+                    "signed_by",
+                    "signed_by_file",
+                )),
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with invalid sources
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but a source sets both `signed_by` \
+                and `signed_by_file` but only one of them can be used in \
+                `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! Each source must be a table with a `uri` string, non-empty `suites` and \
+                `components` arrays, and a `signed_by` string containing the ASCII-armored public \
+                key used to sign the repository (or a `signed_by_file` string with the path of a \
+                key file in the application directory or a `key_fingerprint` string to download \
+                the key from the Ubuntu keyserver). The `signed_by` string can be left out when \
+                the optional `signature_policy` string is set to `optional` or `disabled` instead \
+                of `required` (the default) or when `trusted` is `true`. For example:
+                !
+                ! [[com.heroku.buildpacks.deb-packages.sources]]
+                ! uri = \"https://repo.example.com/ubuntu\"
+                ! suites = [\"noble\"]
+                ! components = [\"main\"]
+                ! signed_by = \"\"\"
+                ! -----BEGIN PGP PUBLIC KEY BLOCK-----
+                ! ...
+                ! -----END PGP PUBLIC KEY BLOCK-----
+                ! \"\"\"
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_invalid_sources() {
+        test_error_output("
+                Context
+                -------
+                Additional repositories can be configured as sources in project.toml. Each source needs
+                a uri, suites, components, and the public key used to verify the repository. If any of
+                these are missing or invalid we report the problem along with an example source.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::ParseSource(ParseCustomSourceError::MissingKey("components")),
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with invalid sources
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but a source is missing the required \
+                key `components` in `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! Each source must be a table with a `uri` string, non-empty `suites` and \
+                `components` arrays, and a `signed_by` string containing the ASCII-armored public \
+                key used to sign the repository (or a `signed_by_file` string with the path of a \
+                key file in the application directory or a `key_fingerprint` string to download \
+                the key from the Ubuntu keyserver). The `signed_by` string can be left out when \
+                the optional `signature_policy` string is set to `optional` or `disabled` instead \
+                of `required` (the default) or when `trusted` is `true`. For example:
+                !
+                ! [[com.heroku.buildpacks.deb-packages.sources]]
+                ! uri = \"https://repo.example.com/ubuntu\"
+                ! suites = [\"noble\"]
+                ! components = [\"main\"]
+                ! signed_by = \"\"\"
+                ! -----BEGIN PGP PUBLIC KEY BLOCK-----
+                ! ...
+                ! -----END PGP PUBLIC KEY BLOCK-----
+                ! \"\"\"
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_invalid_network() {
+        test_error_output(
+            "
+                Context
+                -------
+                The network table in project.toml sets the request timeout and the retry policy for
+                failed requests. Invalid values are reported along with an example table.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::ParseNetwork(ParseNetworkSettingsError::InvalidValue(
+                    "timeout",
+                    " 0".to_string(),
+                )),
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with invalid network settings
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value `0` \
+                for the key `timeout` in the `network` table in \
+                `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! The `network` table can set the request `timeout` and the `min_retry_interval` \
+                and `max_retry_interval` between retries in seconds along with the `max_retries` \
+                for failed requests. Each value must be a non-negative integer and the `timeout` \
+                must be greater than 0. For example:
+                !
+                ! [com.heroku.buildpacks.deb-packages.network]
+                ! timeout = 300
+                ! max_retries = 5
+                ! min_retry_interval = 1
+                ! max_retry_interval = 1800
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_invalid_ppas() {
+        test_error_output(
+            "
+                Context
+                -------
+                PPAs in project.toml must use the `ppa:<owner>/<name>` shorthand.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::ParsePpa(ParsePpaError("deadsnakes/ppa".to_string())),
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with invalid ppas
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value \
+                `deadsnakes/ppa` for the key `ppas` in `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! The value must be an array of Launchpad PPAs written as `ppa:<owner>/<name>` \
+                (e.g.; [\"ppa:deadsnakes/ppa\"]).
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_invalid_tasks() {
+        test_error_output(
+            "
+                Context
+                -------
+                Tasks in project.toml must be an array of task names.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidTasks("\"ubuntu-server\"".to_string()),
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with invalid tasks
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value \
+                `\"ubuntu-server\"` for the key `tasks` in `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! The value must be an array of task names (e.g.; [\"ubuntu-server\"]) as listed in \
+                the Task field of the packages in the task.
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_invalid_env_scope() {
+        test_error_output("
+                Context
+                -------
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                The env scope can be set for all packages or for a single requested package and must be
+                one of the supported scopes. Either way we report the invalid value to the user.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::ParseRequestedPackage(ParseRequestedPackageError::InvalidEnvScope(
+                    ParseEnvScopeError("\"runtime\"".to_string()),
+                )),
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with invalid env_scope
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from `/path/to/project.toml` \
+                to complete the build but we found an invalid value `\"runtime\"` for the key `env_scope` \
+                in `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! The value must be one of \"build\", \"launch\", or \"all\".
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_invalid_on_script_failure() {
+        test_error_output("
+                Context
+                -------
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                A requested package that opts into running its maintainer scripts can set the policy
+                for a failing script which must be one of the supported policies.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::ParseRequestedPackage(
+                    ParseRequestedPackageError::InvalidScriptFailurePolicy(
+                        ParseScriptFailurePolicyError("\"ignore\"".to_string()),
+                    ),
+                ),
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with invalid on_script_failure
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value \
+                `\"ignore\"` for the key `on_script_failure` in \
+                `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! The value must be one of \"warn\" or \"error\".
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_invalid_version() {
+        test_error_output("
+                Context
+                -------
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                A requested package can set a version constraint which must be a list of Debian
+                versions with an optional comparison operator.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::ParseRequestedPackage(
+                    ParseRequestedPackageError::InvalidVersionConstraint(
+                        ParseVersionConstraintError("~> 7.0".to_string()),
+                    ),
+                ),
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with invalid version
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value `~> \
+                7.0` for the key `version` in `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! The value must be a comma-separated list of Debian versions that can each start \
+                with one of the operators \"=\", \"!=\", \">\", \">=\", \"<\", or \"<=\" (e.g.; \">= 7.0, < \
+                8\").
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_invalid_package_group() {
+        test_error_output("
+                Context
+                -------
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                Package groups are arrays of packages in the same format as the install list.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidPackageGroup("media".to_string()),
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with invalid package group
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value for \
+                `media` in the `groups` table of `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! The `groups` key must be a table where each group is an array of packages in the \
+                same format as the install list (e.g.; media = [\"ffmpeg\", \"libvips-tools\"]).
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_nested_package_group() {
+        test_error_output("
+                Context
+                -------
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                A package group can only list packages, not other groups.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::NestedPackageGroup {
+                    group: "media".to_string(),
+                    referenced_group: "geo".to_string(),
+                },
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with nested package group
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but the package group `media` in the \
+                `groups` table of `[com.heroku.buildpacks.deb-packages]` references the group \
+                `@geo`. Package groups can't reference other groups.
+                !
+                ! Suggestions:
+                ! - List the packages of `@geo` in `media` instead.
+                ! - Reference both groups from the install list.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_unknown_package_group() {
+        test_error_output("
+                Context
+                -------
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                The install list can reference a package group which must be defined in the groups
+                table.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::UnknownPackageGroup {
+                    group: "meda".to_string(),
+                    available_groups: vec!["media".to_string(), "geo".to_string()],
+                },
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with unknown package group
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but the install list in \
+                `[com.heroku.buildpacks.deb-packages]` references the package group `@meda` which \
+                isn't defined in the `groups` table.
+                !
+                ! Defined package groups: `media`, `geo`
+                !
+                ! Suggestions:
+                ! - Verify the group name is spelled correctly.
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_invalid_preset() {
+        test_error_output("
+                Context
+                -------
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                The preset key selects curated package lists by name.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidPreset(ParsePresetError("gis".to_string())),
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with invalid preset
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value `gis` \
+                for the key `preset` in `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! The value must be one of \"geo\", \"imaging\", or \"media\" or an array of them (e.g.; \
+                [\"geo\", \"imaging\"]).
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_invalid_package_regex() {
+        test_error_output("
+                Context
+                -------
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                A requested package can set a pattern which must be a valid regular expression.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::ParseRequestedPackage(
+                    ParseRequestedPackageError::InvalidPackageRegex(ParsePackageRegexError {
+                        value: "^libreoffice-(core".to_string(),
+                        reason: "regex parse error: unclosed group".to_string(),
+                    }),
+                ),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - regex parse error: unclosed group
+
+                ! Error parsing `/path/to/project.toml` with invalid package regex
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value \
+                `^libreoffice-(core` for the key `pattern` in \
+                `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! The value must be a string containing a regular expression that's matched \
+                against package names (e.g.; \"^libreoffice-core.*\").
+                !
+                ! Suggestions:
+                ! - See the regular expression syntax at \
+                https://docs.rs/regex/latest/regex/#syntax
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_name_and_pattern() {
+        test_error_output("
+                Context
+                -------
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                A requested package is either named or selected with a pattern.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::ParseRequestedPackage(ParseRequestedPackageError::NameAndPattern),
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with both name and pattern
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found a package in the key \
+                `[com.heroku.buildpacks.deb-packages]` that sets both `name` and `pattern`.
+                !
+                ! A package is either requested by its `name` or selected with a regular \
+                expression set as its `pattern`, but not both.
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_name_and_version() {
+        test_error_output("
+                Context
+                -------
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                A version can be added to the name of a requested package or set as its version.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::ParseRequestedPackage(ParseRequestedPackageError::NameAndVersion),
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with a version in both name and version
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found a package in the key \
+                `[com.heroku.buildpacks.deb-packages]` that sets a version in both `name` and \
+                `version`.
+                !
+                ! A version can either be added to the `name` of a package (e.g.; \
+                \"redis-tools=5:7.0.15-1build2\") or set as its `version`, but not both.
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_invalid_max_matches() {
+        test_error_output("
+                Context
+                -------
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                A requested package pattern can set max_matches which must be a positive integer.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::ParseRequestedPackage(
+                    ParseRequestedPackageError::InvalidMaxMatches("0".to_string()),
+                ),
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with invalid max_matches
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value `0` \
+                for the key `max_matches` in `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! The value must be a positive integer.
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_invalid_package_glob() {
+        test_error_output("
+                Context
+                -------
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                A requested package name can contain the wildcards * and ? but must otherwise use the
+                characters allowed in package names.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::ParseRequestedPackage(
+                    ParseRequestedPackageError::InvalidPackageGlob("LibICU*".to_string()),
+                ),
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with invalid package pattern
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid package \
+                pattern `LibICU*` in the key `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! Package patterns must consist only of lowercase letters (a-z), digits (0-9), \
+                plus (+) and minus (-) signs, periods (.), and the wildcards (*) and (?).
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn validate_requested_packages_error_package_not_available_for_architecture() {
+        test_error_output(
+            "
+                Context
+                -------
+                Some packages are only published for certain architectures (e.g.; Intel media drivers
+                are amd64 only). When a requested package is missing for the target architecture but
+                exists for another one supported by the distribution, we report that instead of a
+                generic package not found error.
+            ",
+            ValidateRequestedPackagesError::PackageNotAvailableForArchitecture {
+                package: "intel-media-va-driver".to_string(),
+                architecture: ArchitectureName::ARM_64,
+                available_architecture: ArchitectureName::AMD_64,
+            },
+            indoc! {"
+                ! Package not available for architecture
+                !
+                ! The package `intel-media-va-driver` is available on `amd64` but not on `arm64` which \
+                is the architecture this application is being built for.
+                !
+                ! Suggestions:
+                ! - Remove `intel-media-va-driver` from the packages to install when building for `arm64`.
+                ! - Check if an alternative package is available for `arm64` at \
+                https://packages.ubuntu.com/
+            "},
+        );
+    }
+
+    #[test]
+    fn validate_requested_packages_error_foreign_architecture_not_available() {
+        test_error_output(
+            "
+                Context
+                -------
+                Packages can be requested for a foreign architecture (e.g.; `libc6:i386`) which is
+                only possible when the distribution has sources for that architecture.
+            ",
+            ValidateRequestedPackagesError::ForeignArchitectureNotAvailable {
+                package: "libc6:armhf".to_string(),
+                architecture: ArchitectureName::ARM_HF,
+                available_architectures: vec![ArchitectureName::I_386],
+            },
+            indoc! {"
+                ! Foreign architecture not available
+                !
+                ! The package `libc6:armhf` was requested for `armhf` but the distribution this \
+                application is being built for has no sources for that architecture.
+                !
+                ! Foreign architectures available for this distribution: `i386`
+                !
+                ! Suggestions:
+                ! - Request the package for one of the available architectures or remove the \
+                architecture from its name to install it for the architecture being built for.
+            "},
+        );
+    }
+
+    #[test]
+    fn validate_requested_packages_error_task_not_found() {
+        test_error_output(
+            "
+                Context
+                -------
+                Tasks configured in project.toml are expanded into the packages that list the task in
+                their Task field. A task that no package lists is most likely misspelled or not
+                available for the distribution.
+            ",
+            ValidateRequestedPackagesError::TaskNotFound("ubuntu-sever".to_string()),
+            indoc! {"
+                ! Task not found
+                !
+                ! The task `ubuntu-sever` was requested in `tasks` but no package in the package \
+                index lists it in its Task field.
+                !
+                ! Suggestions:
+                ! - Verify the task name is spelled correctly (e.g.; `ubuntu-server`).
+                ! - Check that the task is available for the distribution this application is \
+                being built for.
+            "},
+        );
+    }
+
+    #[test]
+    fn validate_requested_packages_error_preset_not_available() {
+        test_error_output(
+            "
+                Context
+                -------
+                Presets list their packages for every supported distribution since library package
+                names change between releases. A distribution without a list is a bug in the buildpack.
+            ",
+            ValidateRequestedPackagesError::PresetNotAvailable {
+                preset: Preset::Geo,
+                codename: DistroCodename::from("bionic"),
+            },
+            indoc! {"
+                ! Preset not available for distribution
+                !
+                ! The preset `geo` doesn't have a list of packages for `bionic` which is the \
+                distribution this application is being built for.
+                !
+                ! Suggestions:
+                ! - Replace the preset with the packages it should install in `install`.
+                !
+                ! If the issue persists and you think you found a bug in the buildpack, reproduce \
+                the issue locally with a minimal example. Open an issue in the buildpack's GitHub \
+                repository and include the details here:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+            "},
+        );
+    }
+
+    #[test]
+    fn validate_requested_packages_error_too_many_pattern_matches() {
+        test_error_output(
+            "
+                Context
+                -------
+                A regular expression in the install list can match far more packages than intended
+                (e.g.; `lib.*`) so the number of packages it can match is limited unless max_matches
+                is set for it.
+            ",
+            ValidateRequestedPackagesError::TooManyPatternMatches {
+                pattern: "^libreoffice-.*".to_string(),
+                matched_packages: vec![
+                    "libreoffice-base".to_string(),
+                    "libreoffice-calc".to_string(),
+                    "libreoffice-core".to_string(),
+                ],
+                max_matches: 2,
+            },
+            indoc! {"
+                - Debug Info:
+                  - Matched packages: libreoffice-base, libreoffice-calc, libreoffice-core
+
+                ! Package pattern matched too many packages
+                !
+                ! The package pattern `^libreoffice-.*` matched 3 packages but at most 2 are \
+                allowed. Patterns are limited so a pattern that's broader than intended doesn't \
+                install an unexpected number of packages.
+                !
+                ! Suggestions:
+                ! - Make the pattern more specific (e.g.; anchor it with \"^\" and \"$\").
+                ! - Set `max_matches` for the pattern if every matched package should be \
+                installed.
+            "},
+        );
+    }
+
+    #[test]
+    fn validate_requested_packages_error_package_pattern_not_matched() {
+        test_error_output(
+            "
+                Context
+                -------
+                Package patterns in the install list are expanded to every package in the package
+                index with a matching name. A pattern that matches nothing is most likely misspelled
+                unless it's marked as optional.
+            ",
+            ValidateRequestedPackagesError::PackagePatternNotMatched(
+                "postgresql-client-1?".to_string(),
+            ),
+            indoc! {"
+                ! Package pattern not matched
+                !
+                ! The package pattern `postgresql-client-1?` was requested but it doesn't match \
+                the name of any package in the package index.
+                !
+                ! Suggestions:
+                ! - Verify the pattern is spelled correctly and check which packages are available \
+                for the distribution this application is being built for at \
+                https://packages.ubuntu.com/
+                ! - Add `optional = true` to the pattern if it's allowed to match no packages.
+            "},
+        );
+    }
+
+    #[test]
+    fn load_signing_key_error_unknown_signing_key() {
+        test_error_output(
+            "
+                Context
+                -------
+                The signing keys referenced by the distro table of buildpack.toml are read from the
+                `keys` directory of the buildpack when the build starts.
+            ",
+            LoadSigningKey(LoadSigningKeyError::Unknown(
+                "ubuntu_24.04.asc".to_string(),
+                "/cnb/buildpacks/heroku_deb-packages/keys/ubuntu_24.04.asc".into(),
+            )),
+            indoc! {"
+                ! Missing signing key
+                !
+                ! The Heroku .deb Packages buildpack verifies the release files of the \
+                distribution's sources with the signing key `ubuntu_24.04.asc` but it wasn't found \
+                at `/cnb/buildpacks/heroku_deb-packages/keys/ubuntu_24.04.asc`.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis \
+                or a workaround at this time. You can help our understanding by sharing your \
+                buildpack log and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the \
+                `pack` build tool \
+                (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more \
+                information around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn load_signing_key_error_read_signing_key() {
+        test_error_output(
+            "
+                Context
+                -------
+                An I/O error occurred while reading a signing key from the `keys` directory of the
+                buildpack.
+            ",
+            LoadSigningKey(LoadSigningKeyError::Read(
+                "/cnb/buildpacks/heroku_deb-packages/keys/ubuntu_24.04.asc".into(),
+                create_io_error("permission denied"),
+            )),
+            indoc! {"
+                - Debug Info:
+                  - permission denied
+
+                ! Unable to read signing key
+                !
+                ! An unexpected I/O error occurred while reading the signing key at \
+                `/cnb/buildpacks/heroku_deb-packages/keys/ubuntu_24.04.asc`.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis \
+                or a workaround at this time. You can help our understanding by sharing your \
+                buildpack log and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the \
+                `pack` build tool \
+                (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more \
+                information around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn load_signing_key_error_invalid_signing_key() {
+        test_error_output(
+            "
+                Context
+                -------
+                A signing key in the `keys` directory of the buildpack couldn't be parsed.
+            ",
+            LoadSigningKey(LoadSigningKeyError::Invalid(
+                "/cnb/buildpacks/heroku_deb-packages/keys/ubuntu_24.04.asc".into(),
+                anyhow!("Malformed armored data"),
+            )),
+            indoc! {"
+                - Debug Info:
+                  - Malformed armored data
+
+                ! Invalid signing key
+                !
+                ! The signing key at `/cnb/buildpacks/heroku_deb-packages/keys/ubuntu_24.04.asc` \
+                isn't a valid OpenPGP certificate.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis \
+                or a workaround at this time. You can help our understanding by sharing your \
+                buildpack log and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the \
+                `pack` build tool \
+                (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more \
+                information around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn unsupported_distro_error() {
+        test_error_output("
+                Context
+                -------
+                This buildpack only supports the following distributions:
+                - Ubuntu 20.04 (amd64)
+                - Ubuntu 22.04 (amd64)
+                - Ubuntu 24.04 (amd64, arm64)
+                - Debian 12 (amd64, arm64)
+                - Debian 13 (amd64, arm64)
+
+                Anything else is unsupported. This error is unlikely to be seen by an end-user but may
+                be helpful for developers hacking on this buildpack. Tools like pack also validate
+                buildpacks against their target distribution metadata to prevent this exact scenario.
+            ",
+            UnsupportedDistro(UnsupportedDistroError {
+                name: "Windows".to_string(),
+                version: "XP".to_string(),
+                architecture: "x86".to_string(),
+                supported_distros: vec![
+                    "Ubuntu 24.04 (amd64, arm64)".to_string(),
+                    "Ubuntu 22.04 (amd64)".to_string(),
+                    "Ubuntu 20.04 (amd64)".to_string(),
+                    "Debian 13 (amd64, arm64)".to_string(),
+                    "Debian 12 (amd64, arm64)".to_string(),
+                ],
+            }),
+            indoc! {"
+                ! Unsupported distribution
+                !
+                ! The Heroku .deb Packages buildpack doesn't support the Windows XP (x86) distribution.
+                !
+                ! Supported distributions:
+                ! - Ubuntu 24.04 (amd64, arm64)
+                ! - Ubuntu 22.04 (amd64)
+                ! - Ubuntu 20.04 (amd64)
+                ! - Debian 13 (amd64, arm64)
+                ! - Debian 12 (amd64, arm64)
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                a workaround at this time. You can help our understanding by sharing your buildpack log \
+                and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the `pack` \
+                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more information \
+                around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn check_sources_error_task_failed() {
+        test_error_output_with_custom_assertion(
+            "
+                Context
+                -------
+                This is a developer error. It should never be seen by an end-user. Sources are
+                checked using async tasks which can fail if the task panics or is cancelled but
+                we don't cancel running tasks and we handle all errors.
+            ",
+            CheckSourcesError::TaskFailed(create_join_error()),
+            |actual_text| {
+                assert_contains_match!(
+                    actual_text,
+                    indoc! {"
+                        - Debug Info:
+                          - task \\d+ panicked with message \"uh oh!\"
+
+                        ! Task failure while checking sources
+                        !
+                        ! A background task responsible for checking sources failed to complete.
+                    "}
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn check_sources_error_unreachable_sources() {
+        test_error_output(
+            "
+                Context
+                -------
+                Every source is checked with a HEAD request before the package index is updated.
+                All the sources that couldn't be reached are reported together.
+            ",
+            CheckSourcesError::UnreachableSources(vec![
+                UnreachableSource {
+                    url: "http://archive.ubuntu.com/ubuntu/dists/noble/InRelease".to_string(),
+                    reason: UnreachableReason::HttpStatus(StatusCode::SERVICE_UNAVAILABLE),
+                },
+                UnreachableSource {
+                    url: "https://repo.example.com/ubuntu/dists/noble/InRelease".to_string(),
+                    reason: UnreachableReason::Dns(
+                        "failed to lookup address information".to_string(),
+                    ),
+                },
+            ]),
+            indoc! {"
+                ! Unreachable package sources
+                !
+                ! Before updating package sources, each source is checked to make sure it can be \
+                reached. The following sources could not be reached:
+                !
+                ! - http://archive.ubuntu.com/ubuntu/dists/noble/InRelease: HTTP status 503 Service \
+                Unavailable
+                ! - https://repo.example.com/ubuntu/dists/noble/InRelease: DNS lookup failed \
+                (failed to lookup address information)
+                !
+                ! This error can occur due to an unstable network connection, a misconfigured source \
+                in project.toml, or an issue with the upstream Debian package repository.
+                !
+                ! Suggestions:
+                ! - Verify the `uri` and `suites` of any sources configured in project.toml.
+                ! - Check the status of https://status.canonical.com/ for any reported issues.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn resolve_ppas_error_unsupported_distro() {
+        test_error_output(
+            "
+                Context
+                -------
+                PPAs are only published for Ubuntu.
+            ",
+            ResolvePpasError::UnsupportedDistro("debian".to_string()),
+            indoc! {"
+                ! PPAs aren't supported on this distribution
+                !
+                ! The Heroku .deb Packages buildpack was configured to add the PPAs listed in \
+                `ppas` but Launchpad only publishes PPAs for Ubuntu and this build is running on \
+                debian.
+                !
+                ! Suggestions:
+                ! - Remove `ppas` from project.toml.
+                ! - Configure the repository with `sources` instead if it publishes packages for \
+                debian.
+            "},
+        );
+    }
+
+    #[test]
+    fn resolve_ppas_error_get_archive_request() {
+        test_error_output(
+            "
+                Context
+                -------
+                The signing key fingerprint of a PPA is requested from the Launchpad API.
+            ",
+            ResolvePpasError::GetArchiveRequest(
+                Ppa::from_str("ppa:deadsnakes/ppa").unwrap(),
+                create_reqwest_middleware_error(),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - error sending request for url (https://test/error)
+
+                ! Failed to look up `ppa:deadsnakes/ppa`
+                !
+                ! The Heroku .deb Packages buildpack requests the details of each PPA listed in \
+                `ppas` from the Launchpad API to find its signing key but the request for \
+                `ppa:deadsnakes/ppa` failed. This error can occur when the PPA doesn't exist, due \
+                to an unstable network connection, or an issue with Launchpad.
+                !
+                ! Suggestions:
+                ! - Verify the owner and name of `ppa:deadsnakes/ppa` on https://launchpad.net/.
+                ! - Check the status of https://status.launchpad.net/ for any reported issues.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn resolve_ppas_error_missing_signing_key_fingerprint() {
+        test_error_output(
+            "
+                Context
+                -------
+                Launchpad doesn't report a fingerprint for archives that haven't been signed yet.
+            ",
+            ResolvePpasError::MissingSigningKeyFingerprint(
+                Ppa::from_str("ppa:deadsnakes/ppa").unwrap(),
+            ),
+            indoc! {"
+                ! No signing key for `ppa:deadsnakes/ppa`
+                !
+                ! The Heroku .deb Packages buildpack verifies the packages from each PPA listed in \
+                `ppas` with the PPA's signing key but Launchpad doesn't report a signing key for \
+                `ppa:deadsnakes/ppa`. A PPA is only signed once its first package has been \
+                published.
+                !
+                ! Suggestions:
+                ! - Verify `ppa:deadsnakes/ppa` has published packages on https://launchpad.net/.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn fetch_signing_keys_error_get_signing_key_request() {
+        test_error_output(
+            "
+                Context
+                -------
+                Sources configured with key_fingerprint download their signing key from the Ubuntu
+                keyserver.
+            ",
+            FetchSigningKeysError::GetSigningKey(
+                RepositoryUri::from("https://repo.example.com/ubuntu"),
+                "F23C5A6CF475977595C89F51BA6932366A755776".to_string(),
+                KeyserverError::Request(create_reqwest_middleware_error()),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - error sending request for url (https://test/error)
+
+                ! Failed to download the signing key `F23C5A6CF475977595C89F51BA6932366A755776`
+                !
+                ! The Heroku .deb Packages buildpack downloads the signing key configured with \
+                `key_fingerprint` for the source https://repo.example.com/ubuntu from \
+                https://keyserver.ubuntu.com/ but the request for \
+                `F23C5A6CF475977595C89F51BA6932366A755776` failed. This error can occur when the \
+                key hasn't been published to the keyserver, due to an unstable network connection, \
+                or an issue with the keyserver.
+                !
+                ! Suggestions:
+                ! - Verify the key can be found by searching for \
+                `F23C5A6CF475977595C89F51BA6932366A755776` on https://keyserver.ubuntu.com/.
+                ! - Use `signed_by` or `signed_by_file` to provide the key instead.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn fetch_signing_keys_error_fingerprint_mismatch() {
+        test_error_output(
+            "
+                Context
+                -------
+                The key downloaded from the keyserver must have the configured fingerprint.
+            ",
+            FetchSigningKeysError::GetSigningKey(
+                RepositoryUri::from("https://repo.example.com/ubuntu"),
+                "F23C5A6CF475977595C89F51BA6932366A755776".to_string(),
+                KeyserverError::FingerprintMismatch {
+                    expected: "F23C5A6CF475977595C89F51BA6932366A755776".to_string(),
+                    actual: "BA6932366A755776F23C5A6CF475977595C89F51".to_string(),
+                },
+            ),
+            indoc! {"
+                ! Signing key mismatch for https://repo.example.com/ubuntu
+                !
+                ! The Heroku .deb Packages buildpack downloads the signing key configured with \
+                `key_fingerprint` for the source https://repo.example.com/ubuntu from \
+                https://keyserver.ubuntu.com/. The key `F23C5A6CF475977595C89F51BA6932366A755776` \
+                was requested but the downloaded key has the fingerprint \
+                `BA6932366A755776F23C5A6CF475977595C89F51`.
+                !
+                ! The key wasn't used to protect the build from an untrusted signing key.
+                !
+                ! Suggestions:
+                ! - Verify `key_fingerprint` is the full fingerprint of the key used to sign the \
+                repository.
+            "},
+        );
+    }
+
+    #[test]
+    fn fetch_signing_keys_error_write_signing_key() {
+        test_error_output(
+            "
+                Context
+                -------
+                Downloaded signing keys are cached in a layer.
+            ",
+            FetchSigningKeysError::WriteSigningKey(
+                "/layers/signing_keys/F23C5A6CF475977595C89F51BA6932366A755776.asc".into(),
+                create_io_error("Permission denied"),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - Permission denied
+
+                ! Failed to write signing key
+                !
+                ! An unexpected I/O error occurred while writing the downloaded signing key to \
+                `/layers/signing_keys/F23C5A6CF475977595C89F51BA6932366A755776.asc`.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis \
+                or a workaround at this time. You can help our understanding by sharing your \
+                buildpack log and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the \
+                `pack` build tool \
+                (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more \
+                information around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_no_sources() {
+        test_error_output("
+                Context
+                -------
+                This is a developer error. It should never be seen by an end-user but could occur if
+                the registered sources for a distribution were modified or someone forgot to register
+                ones for a specific architecture. Our testing processes should always catch this but,
+                if not, we should direct users to file an issue.
+            ",
+            CreatePackageIndexError::NoSources,
+            indoc! {"
+                ! No sources to update
+                !
+                ! The distribution has no sources to update packages from.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                a workaround at this time. You can help our understanding by sharing your buildpack log \
+                and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the `pack` \
+                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more information \
+                around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_task_failed() {
+        test_error_output_with_custom_assertion(
+            "
+                Context
+                -------
+                This is a developer error. It should never be seen by an end-user. Package indexes
+                are updated using async tasks which can fail if the task panics or is cancelled but
+                we don't cancel running tasks and we handle all errors.
+            ",
+            CreatePackageIndexError::TaskFailed(create_join_error()),
+            |actual_text| {
+                assert_contains_match!(
+                    actual_text,
+                    indoc! {"
+                        - Debug Info:
+                          - task \\d+ panicked with message \"uh oh!\"
+
+                        ! Task failure while updating sources
+                        !
+                        ! A background task responsible for updating sources failed to complete.
+                        !
+                        ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                        a workaround at this time. You can help our understanding by sharing your buildpack log \
+                        and a description of the issue at:
+                        ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                        !
+                        ! If you're able to reproduce the problem with an example application and the `pack` \
+                        build tool \\(https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/\\), \
+                        adding that information to the discussion will also help. Once we have more information \
+                        around the causes of this error we may update this message.
+                    "}
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_invalid_layer_name() {
+        test_error_output(
+            "
+                Context
+                -------
+                This is a developer error. It should never be seen by an end-user. The only restrictions
+                on layer naming are that it can't be named 'build', 'launch', or 'cache' and it must be
+                a valid filename. The hexidecimal character set used here should be safe. If a bug is
+                introduced in how this name is generated then we report this and ask the user to file
+                an issue against this buildpack.
+            ",
+            CreatePackageIndexError::InvalidLayerName(
+                "http://archive.ubuntu.com/ubuntu/dists/jammy/InRelease".to_string(),
+                LayerNameError::InvalidValue(
+                    "623e1a2085e65abc3dc626a97909466ce19efe37f7a6529a842c290fcfc65b3b".to_string(),
+                ),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - Invalid Value: 623e1a2085e65abc3dc626a97909466ce19efe37f7a6529a842c290fcfc65b3b
+
+                ! Invalid layer name
+                !
+                ! For caching purposes, a unique layer name is generated for Debian Release files \
+                and Package indices based on their download urls. The generated name for the following \
+                url was invalid:
+                ! - http://archive.ubuntu.com/ubuntu/dists/jammy/InRelease
+                !
+                ! You can find the invalid layer name in the debug information above.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                a workaround at this time. You can help our understanding by sharing your buildpack log \
+                and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the `pack` \
+                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more information \
+                around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_get_release_request() {
+        test_error_output(
+            "
+                Context
+                -------
+                Package sources are requested from a Debian repository starting with a download of
+                the repository's release file. Network I/O can fail for any number of reasons but
+                the most likely here is that there is a problem with the upstream repository which
+                does have a status page we can direct the user to.
+            ",
+            CreatePackageIndexError::GetReleaseRequest(create_reqwest_middleware_error()),
+            indoc! {"
+                - Debug Info:
+                  - error sending request for url (https://test/error)
+
+                ! Failed to request Release file
+                !
+                ! While updating package sources, a request to download a Release file failed. This error \
+                can occur due to an unstable network connection or an issue with the upstream Debian \
+                package repository.
+                !
+                ! Suggestions:
+                ! - Check the status of https://status.canonical.com/ for any reported issues.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+                !
+                ! If the issue persists and you think you found a bug in the buildpack, reproduce the \
+                issue locally with a minimal example. Open an issue in the buildpack's GitHub repository \
+                and include the details here:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_read_get_release_response() {
+        test_error_output(
+            "
+                Context
+                -------
+                Package sources are requested from a Debian repository starting with a download of
+                the repository's release file. This error happens after the request has been initiated
+                and we start reading the response body. Network I/O can fail for any number of reasons but
+                the most likely here is that there is a problem with the upstream repository which
+                does have a status page we can direct the user to.
+            ",
+            CreatePackageIndexError::ReadGetReleaseResponse(create_reqwest_error()),
+            indoc! {"
+                - Debug Info:
+                  - error sending request for url (https://test/error)
+
+                ! Failed to download Release file
+                !
+                ! While updating package sources, an error occurred while downloading a Release \
+                file. This error can occur due to an unstable network connection or an issue with the upstream \
+                Debian package repository.
+                !
+                ! Suggestions:
+                ! - Check the status of https://status.canonical.com/ for any reported issues.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+                !
+                ! If the issue persists and you think you found a bug in the buildpack, reproduce the \
+                issue locally with a minimal example. Open an issue in the buildpack's GitHub repository \
+                and include the details here:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_create_pgp_certificate() {
+        test_error_output(
+            "
+                Context
+                -------
+                This is a developer error. It should never be seen by an end-user. We validate
+                release files using PGP certificates from the supported distributions and if there
+                is a problem with the certificate file, this error would happen. Our testing processes
+                should always catch this but, if not, we should direct users to file an issue.
+            ",
+            CreatePackageIndexError::CreatePgpCertificate(anyhow!(
+                "Additional packets found, is this a keyring?"
+            )),
+            indoc! {"
+                - Debug Info:
+                  - Additional packets found, is this a keyring?
+
+                ! Failed to load verifying PGP certificate
+                !
+                ! The PGP certificate used to verify downloaded release files failed to load. This error \
+                indicates there's a problem with the format of the certificate file the \
+                distribution uses.
+                !
+                ! Suggestions:
+                ! - Verify the format of the certificates found in the ./keys directory of this \
+                buildpack's repository. See https://cirw.in/gpg-decoder
+                ! - Extract new certificates by running the ./scripts/extract_keys.sh script found \
+                in this buildpack's repository.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                a workaround at this time. You can help our understanding by sharing your buildpack log \
+                and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the `pack` \
+                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more information \
+                around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_create_pgp_verifier() {
+        test_error_output(
+            "
+                Context
+                -------
+                This is a developer error. It should never be seen by an end-user. We validate
+                release files using PGP certificates from the supported distributions and if there
+                is a problem with how the release files are signed, this error would happen. Our
+                testing processes should always catch this but, if not, we should direct users to file
+                an issue.
+            ",
+            CreatePackageIndexError::CreatePgpVerifier(anyhow!("Malformed OpenPGP message")),
+            indoc! {"
+                - Debug Info:
+                  - Malformed OpenPGP message
+
+                ! Failed to verify Release file
+                !
+                ! The PGP signature of the downloaded release file failed verification. This error can \
+                occur if the maintainers of the Debian repository changed the process \
+                for signing release files.
+                !
+                ! Suggestions:
+                ! - Verify if the keys changed by running the ./scripts/extract_keys.sh script \
+                found in this buildpack's repository.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                a workaround at this time. You can help our understanding by sharing your buildpack log \
+                and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the `pack` \
+                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more information \
+                around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_missing_release_signature() {
+        test_error_output(
+            "
+                Context
+                -------
+                A repository without an InRelease file must publish a detached signature for its
+                Release file when the signature policy requires one.
+            ",
+            CreatePackageIndexError::MissingReleaseSignature(
+                "https://repo.example.com/debian/dists/stable/Release".to_string(),
+            ),
+            indoc! {"
+                ! Missing Release file signature
+                !
+                ! The repository doesn't publish an InRelease file so the Heroku .deb Packages \
+                buildpack downloaded https://repo.example.com/debian/dists/stable/Release instead \
+                but there's no detached signature at \
+                https://repo.example.com/debian/dists/stable/Release.gpg to verify it with. The \
+                signature policy of the source is `required` so the Release file can't be used \
+                without verifying it.
+                !
+                ! Suggestions:
+                ! - Verify the repository is signed and the source has the right URI.
+                ! - If the repository isn't signed, set `signature_policy` to `optional` or \
+                `disabled` on the source in project.toml.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_missing_signing_key() {
+        test_error_output(
+            "
+                Context
+                -------
+                A source with a `required` signature policy must have a signing key to verify its
+                Release file with.
+            ",
+            CreatePackageIndexError::MissingSigningKey(
+                "https://repo.example.com/debian/dists/stable".to_string(),
+            ),
+            indoc! {"
+                ! Missing signing key
+                !
+                ! The signature policy of the source for \
+                https://repo.example.com/debian/dists/stable is `required` but no signing key is \
+                configured so the Heroku .deb Packages buildpack can't verify its Release file.
+                !
+                ! Suggestions:
+                ! - Set `signed_by`, `signed_by_file`, or `key_fingerprint` on the source in \
+                project.toml.
+                ! - If the repository isn't signed, set `signature_policy` to `optional` or \
+                `disabled` on the source in project.toml.
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_write_release_layer() {
+        test_error_output(
+            "
+                Context
+                -------
+                We write downloaded release files and other information into the release layer. I/O
+                operations can fail for any number of reasons which we can't anticipate and since this
+                file-system area is managed by the buildpack process there is nothing the user can do
+                here other than report it.
+            ",
+            CreatePackageIndexError::WriteReleaseLayer(
+                "/path/to/layer/file".into(),
+                create_io_error("out of memory"),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - out of memory
+
+                ! Failed to write Release file to layer
+                !
+                ! An unexpected I/O error occurred while writing release data to `/path/to/layer/file`.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                a workaround at this time. You can help our understanding by sharing your buildpack log \
+                and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the `pack` \
+                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more information \
+                around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_read_release_file() {
+        test_error_output(
+            "
+                Context
+                -------
+                We read cached release files and other information from the release layer. I/O
+                operations can fail for any number of reasons which we can't anticipate and since this
+                file-system area is managed by the buildpack process there is nothing the user can do
+                here other than report it.
+            ",
+            CreatePackageIndexError::ReadReleaseFile(
+                "/path/to/layer/release-file".into(),
+                create_io_error("not found"),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - not found
+
+                ! Failed to read Release file from layer
+                !
+                ! An unexpected I/O error occurred while reading Release data from `/path/to/layer/release-file`.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                a workaround at this time. You can help our understanding by sharing your buildpack log \
+                and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the `pack` \
+                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more information \
+                around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_parse_release_file() {
+        test_error_output(
+            "
+                Context
+                -------
+                If the release file cannot be parsed, this is either a developer error or a serious
+                problem with the downloaded release file. Running the build with a clean cache
+                should force the file to be re-downloaded which may correct the issue.
+            ",
+            CreatePackageIndexError::ParseReleaseFile(
+                "/path/to/layer/release-file".into(),
+                apt_parser::errors::ParseError.into(),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - Failed to parse an APT value
+
+                ! Failed to parse Release file data
+                !
+                ! We couldn't parse the Release file data stored in `/path/to/layer/release-file`. \
+                This error is most likely a buildpack bug. It can also be caused by cached data \
+                that's no longer valid or an issue with the upstream repository.
+                !
+                ! Suggestions:
+                ! - Run the build again with a clean cache.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+                !
+                ! If the issue persists and you think you found a bug in the buildpack, reproduce the \
+                issue locally with a minimal example. Open an issue in the buildpack's GitHub repository \
+                and include the details here:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_expired_release_file() {
+        test_error_output(
+            "
+                Context
+                -------
+                If the release file downloaded from the Debian repository has a Valid-Until date that
+                has already passed then the repository or a mirror is serving an outdated copy.
+            ",
+            CreatePackageIndexError::ExpiredReleaseFile(
+                "http://deb.debian.org/debian-security/dists/bookworm-security/InRelease".to_string(),
+                "Sat, 19 Oct 2024 08:29:10 UTC".to_string(),
+            ),
+            indoc! {"
+                ! Expired Release file
+                !
+                ! The Release file downloaded from \
+                http://deb.debian.org/debian-security/dists/bookworm-security/InRelease expired on \
+                `Sat, 19 Oct 2024 08:29:10 UTC` (see its `Valid-Until` field). This usually means \
+                the repository or a mirror in front of it is serving an outdated copy that can't \
+                be trusted to have the latest security updates.
+                !
+                ! Suggestions:
+                ! - Retry the build once the repository has been updated.
+                ! - For repositories that publish snapshots that are never updated (e.g.; \
+                snapshot.debian.org), set `check_release_dates = false` in project.toml.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_release_file_not_valid_yet() {
+        test_error_output(
+            "
+                Context
+                -------
+                If the release file downloaded from the Debian repository has a Date in the future then
+                the clock of the build environment is most likely wrong.
+            ",
+            CreatePackageIndexError::ReleaseFileNotValidYet(
+                "http://archive.ubuntu.com/ubuntu/dists/noble/InRelease".to_string(),
+                "Sat, 12 Oct 2030 08:29:10 UTC".to_string(),
+            ),
+            indoc! {"
+                ! Release file not valid yet
+                !
+                ! The Release file downloaded from \
+                http://archive.ubuntu.com/ubuntu/dists/noble/InRelease was published on `Sat, 12 \
+                Oct 2030 08:29:10 UTC` (see its `Date` field) which is in the future. This usually \
+                means the clock of the build environment is wrong.
+                !
+                ! Suggestions:
+                ! - Check the date and time of the build environment.
+                ! - Set `check_release_dates = false` in project.toml to skip this check.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_missing_sha256_release_hashes() {
+        test_error_output(
+            "
+                Context
+                -------
+                If the release file downloaded from the Debian repository is missing the SHA256 release
+                key then that's a serious problem with the repository.
+            ",
+            CreatePackageIndexError::MissingSha256ReleaseHashes(RepositoryUri::from(
+                "http://archive.ubuntu.com/ubuntu/dists/jammy/InRelease",
+            )),
+            indoc! {"
+                ! Missing SHA256 Release hash
+                !
+                ! The Release file from http://archive.ubuntu.com/ubuntu/dists/jammy/InRelease is \
+                missing the SHA256 key which is required according to the documented Debian \
+                repository format. This error is most likely an issue with the upstream \
+                repository. See https://wiki.debian.org/DebianRepository/Format
+                !
+                ! Suggestions:
+                ! - If this is an additional source that only publishes `MD5Sum` or `SHA1` hashes, \
+                set `allow_weak_hashes = true` on the source in project.toml to verify it with \
+                those instead. Only do this for repositories you trust.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+                !
+                ! If the issue persists and you think you found a bug in the buildpack, reproduce \
+                the issue locally with a minimal example. Open an issue in the buildpack's GitHub \
+                repository and include the details here:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_missing_package_index_release_hash() {
+        test_error_output(
+            "
+                Context
+                -------
+                If the release file downloaded from the Debian repository is missing the package index
+                entry for a component then there's a serious problem with the repository.
+            ",
+            CreatePackageIndexError::MissingPackageIndexReleaseHash(
+                RepositoryUri::from("http://archive.ubuntu.com/ubuntu/dists/jammy/InRelease"),
+                vec![
+                    "main/binary-amd64/Packages.gz".to_string(),
+                    "universe/binary-amd64/Packages.gz".to_string(),
+                ],
+            ),
+            indoc! {"
+                ! Missing Package Index
+                !
+                ! The Release file from http://archive.ubuntu.com/ubuntu/dists/jammy/InRelease is \
+                missing entries within the SHA256 section for the following package indexes. This error \
+                is most likely a buildpack bug but can also be an issue with the upstream \
+                repository.
+                !
+                ! Missing package indexes:
+                ! - `main/binary-amd64/Packages.gz`
+                ! - `universe/binary-amd64/Packages.gz`
+                !
+                ! Suggestions:
+                ! - Verify if the package indexes are under the SHA256 section of \
+                http://archive.ubuntu.com/ubuntu/dists/jammy/InRelease
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+                !
+                ! If the issue persists and you think you found a bug in the buildpack, reproduce the \
+                issue locally with a minimal example. Open an issue in the buildpack's GitHub repository \
+                and include the details here:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_get_packages_request() {
+        test_error_output(
+            "
+                Context
+                -------
+                After downloading the release file, the next step for updating sources is to fetch
+                the package indexes. Network I/O can fail for any number of reasons but
+                the most likely here is that there is a problem with the upstream repository which
+                does have a status page we can direct the user to.
+            ",
+            CreatePackageIndexError::GetPackagesRequest(create_reqwest_middleware_error()),
+            indoc! {"
+                - Debug Info:
+                  - error sending request for url (https://test/error)
+
+                ! Failed to request Package Index file
+                !
+                ! While updating package sources, a request to download a Package Index file failed. \
+                This error can occur due to an unstable network connection or an issue with the upstream Debian \
+                package repository.
+                !
+                ! Suggestions:
+                ! - Check the status of https://status.canonical.com/ for any reported issues.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+                !
+                ! If the issue persists and you think you found a bug in the buildpack, reproduce the \
+                issue locally with a minimal example. Open an issue in the buildpack's GitHub repository \
+                and include the details here:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_write_package_layer() {
+        test_error_output(
+            "
+                Context
+                -------
+                When downloading a package index the response body stream is written directly to the
+                file-system. This error could happen if the file to write to could not be opened.
+            ",
+            CreatePackageIndexError::WritePackagesLayer(
+                "/path/to/layer/package".into(),
+                create_io_error("entity already exists"),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - entity already exists
+
+                ! Failed to write Package Index file to layer
+                !
+                ! An unexpected I/O error occurred while writing Package Index data to `/path/to/layer/package`.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                a workaround at this time. You can help our understanding by sharing your buildpack log \
+                and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the `pack` \
+                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more information \
+                around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_write_package_index_from_response() {
+        test_error_output(
+            "
+                Context
+                -------
+                When downloading a package index the response body stream is written directly to the
+                file-system. File and network I/O can fail for any number of reasons but the most
+                likely here would be a connection interruption.
+            ",
+            CreatePackageIndexError::WritePackageIndexFromResponse(
+                "/path/to/layer/package-index".into(),
+                create_io_error("stream closed"),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - stream closed
+
+                ! Failed to download Package Index file
+                !
+                ! While updating package sources, an error occurred while downloading a Package Index \
+                file to `/path/to/layer/package-index`. This error can occur due to an unstable network connection \
+                or an issue with the upstream Debian package repository.
+                !
+                ! Suggestions:
+                ! - Check the status of https://status.canonical.com/ for any reported issues.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+                !
+                ! If the issue persists and you think you found a bug in the buildpack, reproduce the \
+                issue locally with a minimal example. Open an issue in the buildpack's GitHub repository \
+                and include the details here:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_checksum_failed() {
+        test_error_output(
+            "
+                Context
+                -------
+                All downloaded package indexes are verified according to the checksum given by the
+                owning release file. If these checksums don't match then the download is invalid. When
+                this happens a rebuild typically fixes the issue.
+            ",
+            CreatePackageIndexError::ChecksumFailed {
+                url: "http://ports.ubuntu.com/ubuntu-ports/dists/noble/main/binary-arm64/by-hash/SHA256/d41d8cd98f00b204e9800998ecf8427e".to_string(),
+                expected: "d41d8cd98f00b204e9800998ecf8427e".to_string(),
+                actual: "e62ff0123a74adfc6903d59a449cbdb0".to_string()
+            },
+            indoc! {"
+                ! Package Index checksum verification failed
+                !
+                ! While updating package sources, an error occurred while verifying the checksum of \
+                the Package Index at http://ports.ubuntu.com/ubuntu-ports/dists/noble/main/binary-arm64/by-hash/SHA256/d41d8cd98f00b204e9800998ecf8427e. \
+                This error can occur due to an issue with the upstream Debian package repository.
+                !
+                ! Checksum:
+                ! - Expected: `d41d8cd98f00b204e9800998ecf8427e`
+                ! - Actual: `e62ff0123a74adfc6903d59a449cbdb0`
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_cpu_task_failed() {
+        test_error_output(
+            "
+                Context
+                -------
+                This is a developer error. It should never be seen by an end-user. Processing package
+                index files is a CPU-intensive operation and happens in parallel worker tasks for
+                efficiency. This error can happen if a worker task panics and the error isn't handled.
+                Our testing processes should always catch this but, if not, we should direct users to file
+                an issue.
+            ",
+            CreatePackageIndexError::CpuTaskFailed(create_recv_error()),
+            indoc! {"
+                - Debug Info:
+                  - channel closed
+
+                ! Task failure while reading Package Index data
+                !
+                ! A background task responsible for reading Package Index data failed to complete.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                a workaround at this time. You can help our understanding by sharing your buildpack log \
+                and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the `pack` \
+                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more information \
+                around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_read_packages_file() {
+        test_error_output(
+            "
+                Context
+                -------
+                This is a developer error. It should never be seen by an end-user. We read from
+                a package index file stored in the layer directory managed by the buildpack process.
+                I/O errors can happen for any number of reasons and there's nothing the user can
+                do here.
+            ",
+            CreatePackageIndexError::ReadPackagesFile(
+                "/path/to/layer/packages-file".into(),
+                create_io_error("entity not found"),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - entity not found
+
+                ! Failed to read Package Index file
+                !
+                ! An unexpected I/O error occurred while reading Package Index data from \
+                `/path/to/layer/packages-file`.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                a workaround at this time. You can help our understanding by sharing your buildpack log \
+                and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the `pack` \
+                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more information \
+                around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn create_package_index_error_parse_packages() {
+        test_error_output(
+            "
+                Context
+                -------
+                We need to parse all packages contained in a package index file. This error could happen
+                if the package index contained bad data which would indicate a problem with the
+                upstream repository but it's more likely to be a bug with the buildpack. Running the
+                build with a fresh cache would force the package index to be re-downloaded which
+                might fix the issue.
+            ",
+            CreatePackageIndexError::ParsePackages(
+                "/path/to/layer/packages-file".into(),
+                vec![
+                    ParseRepositoryPackageError::MissingPackageName,
+                    ParseRepositoryPackageError::MissingVersion("package-a".to_string()),
+                    ParseRepositoryPackageError::MissingFilename("package-b".to_string()),
+                    ParseRepositoryPackageError::MissingSha256("package-c".to_string()),
+                ],
+            ),
+            indoc! {"
+                ! Failed to parse Package Index file
+                !
+                ! We can't parse the Package Index file data stored in `/path/to/layer/packages-file`. \
+                This error is most likely a buildpack bug. It can also be caused by \
+                cached data that's no longer valid or an issue with the upstream repository.
+                !
+                ! Parsing errors:
+                ! - There's an entry that's missing the required `Package` key.
+                ! - Package `package-a` is missing the required `Version` key.
+                ! - Package `package-b` is missing the required `Filename` key.
+                ! - Package `package-c` is missing the required `SHA256` key.
+                !
+                ! Suggestions:
+                ! - Run the build again with a clean cache.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                a workaround at this time. You can help our understanding by sharing your buildpack log \
+                and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the `pack` \
+                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more information \
+                around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn determine_packages_to_install_error_read_system_packages() {
+        test_error_output(
+            "
+                Context
+                -------
+                This is a developer error. It should never be seen by an end-user. All system packages
+                are stored in /var/lib/dpkg/status. I/O errors can happen for any number of reasons
+                but the most likely here is that the file doesn't exist for some reason or the file
+                path was accidentally modified. Our testing processes should always catch this but,
+                if not, we should direct users to file an issue.
+            ",
+            DeterminePackagesToInstallError::ReadSystemPackages(
+                "/var/lib/dpkg/status".into(),
+                create_io_error("entity not found"),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - entity not found
+
+                ! Failed to read system packages
+                !
+                ! An unexpected I/O error occurred while reading system packages from `/var/lib/dpkg/status`.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                a workaround at this time. You can help our understanding by sharing your buildpack log \
+                and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the `pack` \
+                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more information \
+                around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn determine_packages_to_install_error_parse_system_packages() {
+        test_error_output(
+            "
+                Context
+                -------
+                This is a developer error. It should never be seen by an end-user. All system packages
+                are stored in /var/lib/dpkg/status and it's unlikely for this file to be malformed. More
+                likely is there is a bug with the parsing logic. Our testing processes should always catch
+                this but, if not, we should direct users to file an issue.
+            ",
+            DeterminePackagesToInstallError::ParseSystemPackage(
+                "/var/lib/dpkg/status".into(),
+                "some-package".to_string(),
+                Box::new(apt_parser::errors::APTError::KVError(
+                    apt_parser::errors::KVError,
+                )),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - Failed to parse APT key-value data
+
+                    Package:
+                    some-package
+
+                ! Failed to parse system package
+                !
+                ! An unexpected parsing error occurred while reading system packages from `/var/lib/dpkg/status`.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                a workaround at this time. You can help our understanding by sharing your buildpack log \
+                and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the `pack` \
+                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more information \
+                around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn determine_packages_to_install_error_package_not_found() {
+        test_error_output(
+            "
+                Context
+                -------
+                We're installing a list of packages given by the user in the buildpack configuration.
+                It's possible to provide a valid name of a package that doesn't actually exist in the
+                Debian repositories used by the distribution. If this happens we direct the user to
+                the package search site for Ubuntu to verify the package name.
+            ",
+            DeterminePackagesToInstallError::PackageNotFound("some-package".to_string(), vec!["some-package1".to_string(), "some-package2".to_string()]),
+            indoc! {"
+                ! Package not found
+                !
+                ! We can't find `some-package` in the Package Index. If \
+                this package is listed in the packages to install for this buildpack then the name is most \
+                likely misspelled. Otherwise, it can be an issue with the \
+                upstream Debian package repository.
+                !
+                ! Did you mean?
+                ! - `some-package1`
+                ! - `some-package2`
+                !
+                ! Suggestions:
+                ! - Verify the package name is correct and exists for the target distribution at \
+                https://packages.ubuntu.com/
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn determine_packages_to_install_error_package_not_found_with_no_suggestions() {
+        test_error_output(
+            "
+                Context
+                -------
+                We're installing a list of packages given by the user in the buildpack configuration.
+                It's possible to provide a valid name of a package that doesn't actually exist in the
+                Debian repositories used by the distribution. If this happens we direct the user to
+                the package search site for Ubuntu to verify the package name.
+            ",
+            DeterminePackagesToInstallError::PackageNotFound("some-package".to_string(), vec![]),
+            indoc! {"
+                ! Package not found
+                !
+                ! We can't find `some-package` in the Package Index. If \
+                this package is listed in the packages to install for this buildpack then the name is most \
+                likely misspelled. Otherwise, it can be an issue with the \
+                upstream Debian package repository.
+                !
+                ! Did you mean?
+                ! - No similarly named packages found
+                !
+                ! Suggestions:
+                ! - Verify the package name is correct and exists for the target distribution at \
+                https://packages.ubuntu.com/
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn determine_packages_to_install_error_virtual_package_must_be_specified() {
+        test_error_output(
+            "
+                Context
+                -------
+                We're installing a list of packages given by the user in the buildpack configuration.
+                There is a special type of package name in a Debian repository that represents a
+                'virtual package' which may be implemented by one or more actual packages. This error
+                is shown when there is more than one provider because we can't automatically determine
+                which one should be used without the user's input.
+            ",
+            DeterminePackagesToInstallError::VirtualPackageMustBeSpecified(
+                "some-package".to_string(),
+                HashSet::from(["package-b".to_string(), "package-a".to_string()]),
+            ),
+            indoc! {"
+                ! Multiple providers were found for the package `some-package`
+                !
+                ! Sometimes there are several packages which offer more-or-less the same functionality. \
+                In this case, Debian repositories define a virtual package and one or more actual packages \
+                provide an implementation for this virtual package. When multiple providers are found for \
+                a requested package, this buildpack can't automatically choose which one is the desired \
+                implementation.
+                !
+                ! Providing packages:
+                ! - package-a
+                ! - package-b
+                !
+                ! Suggestions:
+                ! - Replace the virtual package `some-package` with one of the above providers.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn determine_packages_to_install_error_virtual_package_resolution_disabled() {
+        test_error_output(
+            "
+                Context
+                -------
+                Users can opt out of virtual package resolution so a requested virtual package always
+                fails, even when there is only one provider, instead of quietly installing a package
+                they didn't name. The providers are listed so the user can pick one explicitly.
+            ",
+            DeterminePackagesToInstallError::VirtualPackageResolutionDisabled(
+                "some-package".to_string(),
+                HashSet::from(["package-a".to_string()]),
+            ),
+            indoc! {"
+                ! The package `some-package` is a virtual package
+                !
+                ! The requested package `some-package` is a virtual package which is implemented by one \
+                or more actual packages. Since `resolve_virtual_packages` is set to false, this buildpack \
+                won't choose a provider for it.
+                !
+                ! Providing packages:
+                ! - package-a
+                !
+                ! Suggestions:
+                ! - Replace the virtual package `some-package` with one of the above providers.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn determine_packages_to_install_error_too_many_dependencies() {
+        test_error_output(
+            "
+                Context
+                -------
+                A single requested package can pull in a huge number of transitive dependencies (e.g.;
+                requesting a desktop meta-package by mistake). When the number of dependencies added
+                for one package exceeds the configured limit we stop and show the user where the
+                dependencies are coming from.
+            ",
+            DeterminePackagesToInstallError::TooManyDependencies {
+                package: "ubuntu-desktop".to_string(),
+                dependency_count: 1234,
+                max_dependencies: 500,
+                largest_subtrees: vec![
+                    ("gnome-shell".to_string(), 612),
+                    ("libreoffice-writer".to_string(), 301),
+                ],
+            },
+            indoc! {"
+                ! Too many dependencies for the package `ubuntu-desktop`
+                !
+                ! Installing `ubuntu-desktop` would add 1234 dependencies which is more than the limit \
+                of 500. This usually means the package name is not the one intended and would produce \
+                a very large layer.
+                !
+                ! Largest dependencies (with their own dependency counts):
+                ! - `gnome-shell` (612)
+                ! - `libreoffice-writer` (301)
+                !
+                ! Suggestions:
+                ! - Verify `ubuntu-desktop` is the package you intended to install.
+                ! - Set `skip_dependencies` for `ubuntu-desktop` and list the required dependencies explicitly.
+                ! - Raise the limit by setting `max_dependencies` in the buildpack configuration \
+                (0 disables the limit).
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn determine_packages_to_install_error_read_lockfile() {
+        test_error_output(
+            "
+                Context
+                -------
+                A deb-packages.lock in the app directory is read to pin the installed packages. This
+                should only fail from an unexpected I/O error.
+            ",
+            DeterminePackagesToInstallError::ReadLockfile(
+                "/workspace/deb-packages.lock".into(),
+                create_io_error("permission denied"),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - permission denied
+
+                ! Failed to read lockfile
+                !
+                ! An unexpected I/O error occurred while reading the lockfile at \
+                `/workspace/deb-packages.lock`.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis \
+                or a workaround at this time. You can help our understanding by sharing your \
+                buildpack log and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the \
+                `pack` build tool \
+                (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more \
+                information around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn determine_packages_to_install_error_parse_lockfile() {
+        test_error_output(
+            "
+                Context
+                -------
+                The lockfile is usually copied from a previous build but it may have been edited by
+                hand. If it can't be parsed we suggest replacing it or ignoring it.
+            ",
+            DeterminePackagesToInstallError::ParseLockfile(
+                "/workspace/deb-packages.lock".into(),
+                Box::new(serde::de::Error::custom("missing field `distro`")),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - missing field `distro`
+
+                ! Error parsing lockfile
+                !
+                ! The Heroku .deb Packages buildpack pins the installed packages to the versions \
+                listed in `/workspace/deb-packages.lock` but the file isn't a valid lockfile.
+                !
+                ! Suggestions:
+                ! - Replace `/workspace/deb-packages.lock` with the lockfile written to the \
+                packages layer by a previous build.
+                ! - Set `BP_DEB_PACKAGES_IGNORE_LOCKFILE` to true to install packages without the \
+                lockfile.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn determine_packages_to_install_error_lockfile_distro_mismatch() {
+        test_error_output(
+            "
+                Context
+                -------
+                Locked packages are specific to a distribution so a lockfile written for a different
+                one (e.g.; after a builder upgrade) can't be used.
+            ",
+            DeterminePackagesToInstallError::LockfileDistroMismatch(
+                Box::new(Distro {
+                    name: "ubuntu".to_string(),
+                    version: "22.04".to_string(),
+                    codename: "jammy".into(),
+                    architecture: ArchitectureName::AMD_64,
+                }),
+                Box::new(Distro {
+                    name: "ubuntu".to_string(),
+                    version: "24.04".to_string(),
+                    codename: "noble".into(),
+                    architecture: ArchitectureName::AMD_64,
+                }),
+            ),
+            indoc! {"
+                ! Lockfile was written for a different distribution
+                !
+                ! The packages in `deb-packages.lock` were locked for `ubuntu 22.04 (amd64)` but \
+                this build is for `ubuntu 24.04 (amd64)`. The locked packages can't be installed \
+                on a different distribution.
+                !
+                ! Suggestions:
+                ! - Set `BP_DEB_PACKAGES_IGNORE_LOCKFILE` to true and replace `deb-packages.lock` \
+                with the lockfile written to the packages layer by that build.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn determine_packages_to_install_error_locked_packages_unavailable() {
+        test_error_output(
+            "
+                Context
+                -------
+                Repositories drop old versions of packages when they're updated so the versions in a
+                lockfile eventually become unavailable. We list them and explain how to move on.
+            ",
+            DeterminePackagesToInstallError::LockedPackagesUnavailable(vec![LockedPackage {
+                name: "libxml2".to_string(),
+                version: "2.9.14+dfsg-1.3ubuntu3".to_string(),
+                repository: "http://archive.ubuntu.com/ubuntu".to_string(),
+                filename: "pool/main/libx/libxml2/libxml2_2.9.14+dfsg-1.3ubuntu3_amd64.deb"
+                    .to_string(),
+                checksum: "abc123".to_string(),
+                checksum_algorithm: ChecksumAlgorithm::Sha256,
+            }]),
+            indoc! {"
+                ! Locked packages are no longer available
+                !
+                ! The following packages locked in `deb-packages.lock` aren't offered by the \
+                package repositories anymore. Repositories usually only keep the latest version of \
+                a package so locked versions are removed once they're updated.
+                !
+                ! - `libxml2 2.9.14+dfsg-1.3ubuntu3 (http://archive.ubuntu.com/ubuntu)`
+                !
+                ! Suggestions:
+                ! - Set `BP_DEB_PACKAGES_IGNORE_LOCKFILE` to true and replace `deb-packages.lock` \
+                with the lockfile written to the packages layer by that build.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn determine_packages_to_install_error_packages_not_locked() {
+        test_error_output(
+            "
+                Context
+                -------
+                When a package is added to the configuration after the lockfile was written, it would
+                be installed at an unlocked version so the build fails instead.
+            ",
+            DeterminePackagesToInstallError::PackagesNotLocked(vec![
+                "ffmpeg".to_string(),
+                "libavcodec60".to_string(),
+            ]),
+            indoc! {"
+                ! Packages are missing from the lockfile
+                !
+                ! The following packages would be installed but aren't locked in \
+                `deb-packages.lock`. This usually means packages were added to project.toml since \
+                the lockfile was written.
+                !
+                ! - `ffmpeg`
+                ! - `libavcodec60`
+                !
+                ! Suggestions:
+                ! - Set `BP_DEB_PACKAGES_IGNORE_LOCKFILE` to true and replace `deb-packages.lock` \
+                with the lockfile written to the packages layer by that build.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn determine_packages_to_install_error_version_constraint_not_satisfied() {
+        test_error_output(
+            "
+                Context
+                -------
+                A requested package can limit the versions it's installed at. When none of the
+                versions in the package index satisfy the constraint the available versions are
+                listed so the constraint can be adjusted.
+            ",
+            DeterminePackagesToInstallError::VersionConstraintNotSatisfied {
+                package: "redis-tools".to_string(),
+                version_constraint: crate::config::VersionConstraint::from_str(">= 8").unwrap(),
+                available_versions: vec![
+                    "5:7.0.15-1ubuntu0.24.04.1".to_string(),
+                    "5:7.0.15-1build2".to_string(),
+                ],
+            },
+            indoc! {"
+                ! No version of `redis-tools` satisfies `>= 8`
+                !
+                ! The package `redis-tools` was requested with the `version` constraint `>= 8` but \
+                none of the versions offered by the package repositories satisfy it. The available \
+                versions are:
+                !
+                ! - `5:7.0.15-1ubuntu0.24.04.1`
+                ! - `5:7.0.15-1build2`
+                !
+                ! Suggestions:
+                ! - Change the `version` of `redis-tools` in project.toml to include one of the \
+                available versions.
+                ! - Add a source that publishes a matching version of `redis-tools`.
+            "},
+        );
+    }
+
+    #[test]
+    fn install_packages_error_task_failed() {
+        test_error_output_with_custom_assertion(
+            "
+                Context
+                -------
+                This is a developer error. It should never be seen by an end-user. Package installations
+                are performed using async tasks which can fail if the task panics or is cancelled but
+                we don't cancel running tasks and we handle all errors.
+            ",
+            InstallPackagesError::TaskFailed(create_join_error()),
+            |actual_text| {
+                assert_contains_match!(
+                    actual_text,
+                    indoc! {"
+                    - Debug Info:
+                      - task \\d+ panicked with message \"uh oh!\"
+
+                    ! Task failure while installing packages
+                    !
+                    ! A background task responsible for installing failed to complete.
+                    !
+                    ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                    a workaround at this time. You can help our understanding by sharing your buildpack log \
+                    and a description of the issue at:
+                    ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                    !
+                    ! If you're able to reproduce the problem with an example application and the `pack` \
+                    build tool \\(https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/\\), \
+                    adding that information to the discussion will also help. Once we have more information \
+                    around the causes of this error we may update this message.
+                "}
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn install_packages_error_invalid_filename() {
+        test_error_output(
+            "
+                Context
+                -------
+                This is a developer error. It should never be seen by an end-user. Packages
+                specify a Filename field that can be used to form a filename to download the
+                package to. This error can only happen if that Filename field contains a value
+                of '..' which is unlikely since that would cause serious problems for the upstream
+                repository.
+            ",
+            InstallPackagesError::InvalidFilename("some-package".to_string(), "..".to_string()),
+            indoc! {"
+                ! Could not determine file name for `some-package`
+                !
+                ! The package information for `some-package` contains a Filename field of `..` which \
+                produces an invalid name to use as a download path.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                a workaround at this time. You can help our understanding by sharing your buildpack log \
+                and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the `pack` \
+                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more information \
+                around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn install_packages_error_request_package() {
+        test_error_output(
+            "
+                Context
+                -------
+                Packages are downloaded from a Debian repository. Network I/O can fail for any number
+                of reasons but the most likely here would be a problem with the upstream.
+            ",
+            InstallPackagesError::RequestPackage(
+                Box::new(repository_package("some-package")),
+                create_reqwest_middleware_error(),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - error sending request for url (https://test/error)
+
+                ! Failed to request package
+                !
+                ! While installing packages, an error occurred while downloading `some-package`. This error \
+                can occur due to an unstable network connection or an issue with the upstream Debian package \
+                repository.
+                !
+                ! Suggestions:
+                ! - Check the status of https://status.canonical.com/ for any reported issues.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+                !
+                ! If the issue persists and you think you found a bug in the buildpack, reproduce the \
+                issue locally with a minimal example. Open an issue in the buildpack's GitHub repository \
+                and include the details here:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+            "},
+        );
+    }
+
+    #[test]
+    fn install_packages_error_write_package() {
+        test_error_output(
+            "
+                Context
+                -------
+                Packages downloaded from a Debian repository are written directly to disk. Network I/O
+                can fail for any number of reasons but the most likely here would be a problem with the upstream.
+            ",
+            InstallPackagesError::WritePackage(
+                Box::new(repository_package("some-package")),
+                "https://test/error".to_string(),
+                "/path/to/layer/download-file".into(),
+                create_io_error("stream closed"),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - stream closed
+
+                ! Failed to download package
+                !
+                ! An unexpected I/O error occured while downloading `some-package` from https://test/error \
+                to `/path/to/layer/download-file`. This error can occur due to an unstable network connection or \
+                an issue with the upstream Debian package repository.
+                !
+                ! Suggestions:
+                ! - Check the status of https://status.canonical.com/ for any reported issues.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+                !
+                ! If the issue persists and you think you found a bug in the buildpack, reproduce the \
+                issue locally with a minimal example. Open an issue in the buildpack's GitHub repository \
+                and include the details here:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+            "},
+        );
+    }
 
-    ErrorMessage {
-        debug_info,
-        message,
+    #[test]
+    fn install_packages_error_checksum_failed() {
+        test_error_output(
+            "
+                Context
+                -------
+                Packages downloaded from a Debian repository are validated against a checksum given by
+                their owning package index. If this error happens then there was a problem with the
+                download. When this happens, running the build again typically fixes it.
+            ",
+            InstallPackagesError::ChecksumFailed {
+                url: "http://archive.ubuntu.com/ubuntu/dists/jammy/some-package.tgz".to_string(),
+                expected: "7931f51fd704f93171f36f5f6f1d7b7b".into(),
+                actual: "19a47cdb280539511523382fa1cabbe5".to_string(),
+            },
+            indoc! {"
+                ! Package checksum verification failed
+                !
+                ! An error occurred while verifying the checksum of the package at \
+                http://archive.ubuntu.com/ubuntu/dists/jammy/some-package.tgz. The package was \
+                downloaded again after the first checksum failure but the checksum still didn't \
+                match. This error can occur due to an issue with the upstream Debian package \
+                repository.
+                !
+                ! Checksum:
+                ! - Expected: `7931f51fd704f93171f36f5f6f1d7b7b`
+                ! - Actual: `19a47cdb280539511523382fa1cabbe5`
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
     }
-}
 
-fn print_error<W>(error_message: ErrorMessage, writer: W)
-where
-    W: Write + Send + Sync + 'static,
-{
-    let mut log = Print::new(writer).without_header();
-    if let Some(debug_info) = error_message.debug_info {
-        log = log
-            .bullet(style::important("Debug Info:"))
-            .sub_bullet(debug_info)
-            .done();
+    #[test]
+    fn install_packages_error_open_package_archive() {
+        test_error_output(
+            "
+                Context
+                -------
+                Packages downloaded from a Debian repository are stored as tarballs which need to be
+                opened for extraction. I/O can fail for any number of reasons but since the buildpack
+                process owns this content, there's nothing the user can do here.
+            ",
+            InstallPackagesError::OpenPackageArchive(
+                "/path/to/layer/archive-file.tgz".into(),
+                create_io_error("permission denied"),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - permission denied
+
+                ! Failed to open package archive
+                !
+                ! An unexpected I/O error occurred while trying to open the archive at `/path/to/layer/archive-file.tgz`.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                a workaround at this time. You can help our understanding by sharing your buildpack log \
+                and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the `pack` \
+                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more information \
+                around the causes of this error we may update this message.
+            "},
+        );
     }
-    log.error(error_message.message);
-}
 
-fn file_value(value: impl AsRef<Path>) -> String {
-    style::value(value.as_ref().to_string_lossy())
-}
+    #[test]
+    fn install_packages_error_open_package_archive_entry() {
+        test_error_output(
+            "
+                Context
+                -------
+                Packages downloaded from a Debian repository are stored as tarballs which stores file
+                information in individual file entries which need to be read for extraction. I/O
+                can fail for any number of reasons but since the buildpack process owns this content,
+                there's nothing the user can do here.
+            ",
+            InstallPackagesError::OpenPackageArchiveEntry(
+                "/path/to/layer/archive-file.tgz".into(),
+                create_io_error("invalid header entry"),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - invalid header entry
 
-fn get_canonical_status_url() -> String {
-    style::url("https://status.canonical.com/")
-}
+                ! Failed to read package archive entry
+                !
+                ! An unexpected I/O error occurred while trying to read the entries of the archive at \
+                `/path/to/layer/archive-file.tgz`.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                a workaround at this time. You can help our understanding by sharing your buildpack log \
+                and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the `pack` \
+                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more information \
+                around the causes of this error we may update this message.
+            "},
+        );
+    }
 
-fn get_package_search_url() -> String {
-    style::url("https://packages.ubuntu.com/")
-}
+    #[test]
+    fn install_packages_error_unpack_tarball() {
+        test_error_output(
+            "
+                Context
+                -------
+                Packages downloaded from a Debian repository are stored as tarballs which need to be
+                extracted. This is done by iterating through each archive entry and writing it's
+                content out as a file to the file-system. I/O can fail for any number of reasons but
+                since the buildpack process owns this content, there's nothing the user can do here.
+            ",
+            InstallPackagesError::UnpackTarball(
+                "/path/to/layer/archive-file.tgz".into(),
+                create_io_error("directory not empty"),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - directory not empty
 
-#[derive(Debug)]
-struct ErrorMessage {
-    debug_info: Option<String>,
-    message: String,
-}
+                ! Failed to unpack package archive
+                !
+                ! An unexpected I/O error occurred while trying to unpack the archive at `/path/to/layer/archive-file.tgz`.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                a workaround at this time. You can help our understanding by sharing your buildpack log \
+                and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the `pack` \
+                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more information \
+                around the causes of this error we may update this message.
+            "},
+        );
+    }
 
-#[derive(Debug, PartialEq)]
-enum ErrorType {
-    Framework,
-    Internal,
-    UserFacing(SuggestRetryBuild, SuggestSubmitIssue),
-}
+    #[test]
+    fn install_packages_error_read_certificates() {
+        test_error_output(
+            "
+                Context
+                -------
+                When ca-certificates-java is installed, its postinst script that builds the Java
+                keystore isn't run so the keystore is generated from the installed CA certificates.
+                Reading those should only fail from an unexpected I/O error.
+            ",
+            InstallPackagesError::PackageIntegration(PackageIntegrationError::JavaKeystore(
+                JavaKeystoreError::ReadCertificates(
+                    "/etc/ssl/certs/ca-certificates.crt".into(),
+                    create_io_error("permission denied"),
+                ),
+            )),
+            indoc! {"
+                - Debug Info:
+                  - permission denied
+
+                ! Failed to read CA certificates
+                !
+                ! An unexpected I/O error occurred while reading the CA certificates at \
+                `/etc/ssl/certs/ca-certificates.crt` to generate the Java keystore.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis \
+                or a workaround at this time. You can help our understanding by sharing your \
+                buildpack log and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the \
+                `pack` build tool \
+                (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more \
+                information around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn install_packages_error_imagemagick_not_installed() {
+        test_error_output(
+            "
+                Context
+                -------
+                A policy file configured with imagemagick_policy replaces the policy.xml installed
+                into the layer. If ImageMagick wasn't installed there is nothing to apply it to.
+            ",
+            InstallPackagesError::PackageIntegration(PackageIntegrationError::ImageMagick(
+                ImageMagickError::NotInstalled,
+            )),
+            indoc! {"
+                ! ImageMagick isn't installed
+                !
+                ! An ImageMagick policy was configured with `imagemagick_policy` in project.toml \
+                but no ImageMagick configuration directories were found in the installed packages.
+                !
+                ! Suggestions:
+                ! - Add `imagemagick` to the packages to install in project.toml.
+                ! - Remove `imagemagick_policy` from project.toml.
+            "},
+        );
+    }
+
+    #[test]
+    fn install_packages_error_read_imagemagick_policy() {
+        test_error_output(
+            "
+                Context
+                -------
+                The policy file configured with imagemagick_policy is read from the app directory.
+                It's most likely missing or the path is wrong.
+            ",
+            InstallPackagesError::PackageIntegration(PackageIntegrationError::ImageMagick(
+                ImageMagickError::ReadPolicy(
+                    "/workspace/config/policy.xml".into(),
+                    create_io_error("not found"),
+                ),
+            )),
+            indoc! {"
+                - Debug Info:
+                  - not found
+
+                ! Failed to read ImageMagick policy
+                !
+                ! An error occurred while reading the ImageMagick policy at \
+                `/workspace/config/policy.xml` configured with `imagemagick_policy` in \
+                project.toml.
+                !
+                ! Suggestions:
+                ! - Verify the file exists and that the path is relative to the application \
+                directory.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn install_packages_error_write_imagemagick_policy() {
+        test_error_output(
+            "
+                Context
+                -------
+                The configured ImageMagick policy is written over the policy.xml in the layer. This
+                should only fail from an unexpected I/O error.
+            ",
+            InstallPackagesError::PackageIntegration(PackageIntegrationError::ImageMagick(
+                ImageMagickError::WritePolicy(
+                    "/layers/packages/etc/ImageMagick-6/policy.xml".into(),
+                    create_io_error("disk full"),
+                ),
+            )),
+            indoc! {"
+                - Debug Info:
+                  - disk full
+
+                ! Failed to write ImageMagick policy
+                !
+                ! An unexpected I/O error occurred while writing the ImageMagick policy to \
+                `/layers/packages/etc/ImageMagick-6/policy.xml`.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis \
+                or a workaround at this time. You can help our understanding by sharing your \
+                buildpack log and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the \
+                `pack` build tool \
+                (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more \
+                information around the causes of this error we may update this message.
+            "},
+        );
+    }
 
-#[derive(Debug, PartialEq)]
-enum SuggestRetryBuild {
-    Yes,
-    No,
-}
+    #[test]
+    fn install_packages_error_write_fontconfig() {
+        test_error_output(
+            "
+                Context
+                -------
+                When fontconfig-config is installed, a configuration file adding the fonts installed
+                into the layer is written. This should only fail from an unexpected I/O error.
+            ",
+            InstallPackagesError::PackageIntegration(PackageIntegrationError::WriteFontconfig(
+                "/layers/packages/etc/fonts/conf.d/99-deb-packages.conf".into(),
+                create_io_error("disk full"),
+            )),
+            indoc! {"
+                - Debug Info:
+                  - disk full
 
-#[derive(Debug, PartialEq)]
-enum SuggestSubmitIssue {
-    Yes,
-    No,
-}
+                ! Failed to write fontconfig configuration
+                !
+                ! An unexpected I/O error occurred while writing the fontconfig configuration to \
+                `/layers/packages/etc/fonts/conf.d/99-deb-packages.conf`.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis \
+                or a workaround at this time. You can help our understanding by sharing your \
+                buildpack log and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the \
+                `pack` build tool \
+                (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more \
+                information around the causes of this error we may update this message.
+            "},
+        );
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::debian::{
-        ParsePackageNameError, ParseRepositoryPackageError, RepositoryPackage, RepositoryUri,
-    };
-    use crate::DebianPackagesBuildpackError::UnsupportedDistro;
-    use anyhow::anyhow;
-    use libcnb::data::layer::LayerNameError;
-    use libcnb_test::assert_contains_match;
-    use std::collections::HashSet;
-    use std::str::FromStr;
+    #[test]
+    fn install_packages_error_no_certificates() {
+        test_error_output(
+            "
+                Context
+                -------
+                When ca-certificates-java is installed, the Java keystore is generated from the CA
+                certificates installed into the layer or on the base image. If there are none we ask
+                the user to install ca-certificates.
+            ",
+            InstallPackagesError::PackageIntegration(PackageIntegrationError::JavaKeystore(
+                JavaKeystoreError::NoCertificates,
+            )),
+            indoc! {"
+                ! No CA certificates found for the Java keystore
+                !
+                ! The `ca-certificates-java` package was installed so a Java keystore is generated \
+                from the installed CA certificates but no certificates were found in the layer or \
+                on the base image.
+                !
+                ! Suggestions:
+                ! - Add `ca-certificates` to the packages to install in project.toml.
+            "},
+        );
+    }
 
     #[test]
-    fn test_config_check_exists_errors() {
+    fn install_packages_error_write_keystore() {
         test_error_output(
             "
                 Context
                 -------
-                When detect is executed on this buildpack, we check to see if a project.toml
-                file exists since that's where configuration for this buildpack would be found.
-                I/O operations can fail for a number of reasons which we can't anticipate and
-                the best we can do here is report the error message.
+                When ca-certificates-java is installed, the generated Java keystore is written into
+                the layer. This should only fail from an unexpected I/O error.
             ",
-            ConfigError::CheckExists(
-                "/path/to/project.toml".into(),
-                create_io_error("test I/O error"),
-            ),
+            InstallPackagesError::PackageIntegration(PackageIntegrationError::JavaKeystore(
+                JavaKeystoreError::WriteKeystore(
+                    "/layers/packages/etc/ssl/certs/java/cacerts".into(),
+                    create_io_error("disk full"),
+                ),
+            )),
             indoc! {"
                 - Debug Info:
-                  - test I/O error
+                  - disk full
 
-                ! Unable to complete buildpack detection
+                ! Failed to write Java keystore
                 !
-                ! An unexpected I/O error occurred while checking `/path/to/project.toml` to \
-                determine if the Heroku .deb Packages buildpack is compatible for this application.
+                ! An unexpected I/O error occurred while writing the Java keystore to \
+                `/layers/packages/etc/ssl/certs/java/cacerts`.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis \
+                or a workaround at this time. You can help our understanding by sharing your \
+                buildpack log and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the \
+                `pack` build tool \
+                (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more \
+                information around the causes of this error we may update this message.
             "},
         );
     }
 
     #[test]
-    fn config_read_config_error() {
+    fn install_packages_error_serialize_lockfile() {
         test_error_output(
             "
                 Context
                 -------
-                We read the buildpack configuration from project.toml. I/O operations can fail
-                for a number of reasons which we can't anticipate but the most likely one here would
-                be that we don't have read permissions.
+                The resolved packages are written to a lockfile in the layer. Serializing it should
+                only fail from a bug in the buildpack.
             ",
-            ConfigError::ReadConfig(
-                "/path/to/project.toml".into(),
-                create_io_error("test I/O error"),
+            InstallPackagesError::SerializeLockfile(
+                "/path/to/layer/deb-packages.lock".into(),
+                serde::ser::Error::custom("unsupported value"),
             ),
             indoc! {"
                 - Debug Info:
-                  - test I/O error
+                  - unsupported value
 
-                ! Error reading `/path/to/project.toml`
+                ! Failed to serialize lockfile
                 !
-                ! The Heroku .deb Packages buildpack reads configuration from `/path/to/project.toml` \
-                to complete the build but the file can't be read.
+                ! An unexpected error occurred while serializing the lockfile for \
+                `/path/to/layer/deb-packages.lock`.
                 !
-                ! Suggestions:
-                ! - Ensure the file has read permissions.
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis \
+                or a workaround at this time. You can help our understanding by sharing your \
+                buildpack log and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
                 !
-                ! Use the debug information above to troubleshoot and retry your build.
+                ! If you're able to reproduce the problem with an example application and the \
+                `pack` build tool \
+                (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more \
+                information around the causes of this error we may update this message.
             "},
         );
     }
 
     #[test]
-    fn config_parse_config_error_for_wrong_config_type() {
-        test_error_output("
+    fn install_packages_error_write_lockfile() {
+        test_error_output(
+            "
                 Context
                 -------
-                We read the buildpack configuration from project.toml which must be a valid TOML file.
-                If the file is valid but the value supplied using the configuration key for this buildpack
-                is not a TOML table then the configuration is incorrect and we can provide details to the
-                user on how they can fix this.
+                The resolved packages are written to a lockfile in the layer. I/O can fail for any
+                number of reasons but since the buildpack owns the layer, there's nothing the user
+                can do here.
             ",
-            ConfigError::ParseConfig(
-                "/path/to/project.toml".into(),
-                ParseConfigError::WrongConfigType,
+            InstallPackagesError::WriteLockfile(
+                "/path/to/layer/deb-packages.lock".into(),
+                create_io_error("operation interrupted"),
             ),
             indoc! {"
-                ! Error parsing `/path/to/project.toml` with invalid key
+                - Debug Info:
+                  - operation interrupted
+
+                ! Failed to write lockfile
                 !
-                ! The Heroku .deb Packages buildpack reads the configuration from `/path/to/project.toml` \
-                to complete the build but the configuration for the key `[com.heroku.buildpacks.deb-packages]` \
-                isn't the correct type. The value of this key must be a TOML table.
+                ! An unexpected I/O error occurred while writing the lockfile to \
+                `/path/to/layer/deb-packages.lock`.
                 !
-                ! Suggestions:
-                ! - See the buildpack documentation for the proper usage for this configuration at \
-                https://github.com/heroku/buildpacks-deb-packages#configuration
-                ! - See the TOML documentation for more details on the TOML table type at \
-                https://toml.io/en/v1.0.0
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis \
+                or a workaround at this time. You can help our understanding by sharing your \
+                buildpack log and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
                 !
-                ! Use the debug information above to troubleshoot and retry your build.
+                ! If you're able to reproduce the problem with an example application and the \
+                `pack` build tool \
+                (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more \
+                information around the causes of this error we may update this message.
             "},
         );
     }
 
     #[test]
-    fn config_parse_config_error_for_invalid_toml() {
-        test_error_output("
+    fn install_packages_error_remove_package_files() {
+        test_error_output(
+            "
                 Context
                 -------
-                We read the buildpack configuration from project.toml which must be a valid TOML file.
-                This error will be reported if the file is not valid TOML.
+                When a restored packages layer is updated in place, the files of the packages that
+                were removed or upgraded are deleted from it. Since the buildpack owns the layer,
+                there's nothing the user can do here.
             ",
-            ConfigError::ParseConfig(
-                "/path/to/project.toml".into(),
-                ParseConfigError::InvalidToml(
-                    toml_edit::DocumentMut::from_str("[com.heroku").unwrap_err(),
-                ),
-            ),
+            InstallPackagesError::RemovePackageFiles(create_io_error("permission denied")),
             indoc! {"
                 - Debug Info:
-                  - TOML parse error at line 1, column 12
-                      |
-                    1 | [com.heroku
-                      |            ^
-                    invalid table header
-                    expected `.`, `]`
+                  - permission denied
 
-                ! Error parsing `/path/to/project.toml` with invalid TOML file
+                ! Failed to remove package files
                 !
-                ! The Heroku .deb Packages buildpack reads configuration from `/path/to/project.toml` \
-                to complete the build but this file isn't a valid TOML file.
+                ! An unexpected I/O error occurred while removing the files of packages that are \
+                no longer installed from the packages layer.
                 !
-                ! Suggestions:
-                ! - Ensure the file follows the TOML format described at https://toml.io/en/v1.0.0
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis \
+                or a workaround at this time. You can help our understanding by sharing your \
+                buildpack log and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
                 !
-                ! Use the debug information above to troubleshoot and retry your build.
+                ! If you're able to reproduce the problem with an example application and the \
+                `pack` build tool \
+                (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more \
+                information around the causes of this error we may update this message.
             "},
         );
     }
 
     #[test]
-    fn config_parse_config_error_for_invalid_package_name() {
-        test_error_output("
+    fn install_packages_existing_files_error() {
+        test_error_output(
+            "
                 Context
                 -------
-                We read the buildpack configuration from project.toml which must be a valid TOML file.
-                If the file is valid but the value supplied using the configuration for this buildpack
-                contains a package name that would be invalid according to Debian package naming policies,
-                we report this package name to the user and ask them to verify it.
+                When existing_files is set to \"error\", the build fails if a package contains files
+                that already exist in the packages layer.
             ",
-            ConfigError::ParseConfig(
-                "/path/to/project.toml".into(),
-                ParseConfigError::ParseRequestedPackage(
-                    ParseRequestedPackageError::InvalidPackageName(ParsePackageNameError {
-                        package_name: "invalid!package!name".to_string(),
-                    }),
-                ),
+            InstallPackagesError::ExistingFiles(
+                "libgwenhywfar79".to_string(),
+                vec![
+                    "./usr/lib/libgwenhywfar.so".into(),
+                    "./usr/share/doc/libgwenhywfar79/copyright".into(),
+                ],
             ),
             indoc! {"
-                ! Error parsing `/path/to/project.toml` with invalid package name
+                ! Package `libgwenhywfar79` contains files that already exist
                 !
-                ! The Heroku .deb Packages buildpack reads configuration from `/path/to/project.toml` \
-                to complete the build but we found an invalid package name `invalid!package!name` \
-                in the key `[com.heroku.buildpacks.deb-packages]`.
+                ! Since `existing_files` is set to \"error\", the build failed because the following \
+                files from `libgwenhywfar79` already exist in the packages layer, in the app directory, or \
+                in a package installed before it:
                 !
-                ! Package names must consist only of lowercase letters (a-z), digits (0-9), plus (+) \
-                and minus (-) signs, and periods (.). Names must be at least two characters long and \
-                must start with an alphanumeric character. \
-                See https://www.debian.org/doc/debian-policy/ch-controlfields.html#s-f-source
+                ! - usr/lib/libgwenhywfar.so
+                ! - usr/share/doc/libgwenhywfar79/copyright
                 !
                 ! Suggestions:
-                ! - Verify the package name is correct and exists for the target distribution at https://packages.ubuntu.com/
-                !
-                ! Use the debug information above to troubleshoot and retry your build.
+                ! - Remove the package that provides these files from the packages to install.
+                ! - Set `existing_files` to \"skip\" to keep the existing files or \"overwrite\" to \
+                replace them.
             "},
         );
     }
 
     #[test]
-    fn config_parse_config_error_for_invalid_package_name_config_type() {
-        test_error_output("
+    fn install_packages_postinst_script_failed() {
+        test_error_output(
+            "
                 Context
                 -------
-                We read the buildpack configuration from project.toml which must be a valid TOML file.
-                If the file is valid but the value supplied using the configuration for this buildpack
-                contains a package entry that is neither a string nor inline table value, it is invalid.
-                We report this to the user and request they check the buildpack documentation for proper
-                usage.
+                Packages that opt into running their maintainer scripts fail the build when the
+                postinst script exits with a non-zero status unless on_script_failure is \"warn\".
+                The exit code and output of the script are reported to help troubleshoot it.
             ",
-            ConfigError::ParseConfig(
-                "/path/to/project.toml".into(),
-                ParseConfigError::ParseRequestedPackage(
-                    ParseRequestedPackageError::UnexpectedTomlValue(
-                        toml_edit::value(37).into_value().unwrap(),
-                    ),
-                ),
+            InstallPackagesError::PostinstScriptFailed(
+                "ca-certificates-java".to_string(),
+                ScriptFailure {
+                    status: std::os::unix::process::ExitStatusExt::from_raw(1 << 8),
+                    stdout: "Adding debian:ISRG_Root_X1.pem\n".to_string(),
+                    stderr: "error: no java runtime found\n".to_string(),
+                },
             ),
             indoc! {"
                 - Debug Info:
-                  - Invalid type `integer` with value `37`
+                  - exit code 1
 
-                ! Error parsing `/path/to/project.toml` with invalid package format
-                !
-                ! The Heroku .deb Packages buildpack reads configuration from `/path/to/project.toml` \
-                to complete the build but we found an invalid package format in the key \
-                `[com.heroku.buildpacks.deb-packages]`.
+                    stdout:
+                    Adding debian:ISRG_Root_X1.pem
+
+                    stderr:
+                    error: no java runtime found
+
+                ! Postinst script for `ca-certificates-java` failed
                 !
-                ! Packages must either be the following TOML values:
-                ! - String (e.g.; \"package-name\")
-                ! - Inline table (e.g.; { name = \"package-name\", skip_dependencies = true })
+                ! The postinst script of `ca-certificates-java` failed with exit code 1. Since \
+                `on_script_failure` is set to \"error\" for this package, the build was stopped.
                 !
                 ! Suggestions:
-                ! - See the buildpack documentation for the proper usage for this configuration at \
-                https://github.com/heroku/buildpacks-deb-packages#configuration
-                ! - See the TOML documentation for more details on the TOML string and inline \
-                table types at https://toml.io/en/v1.0.0
-                !
-                ! Use the debug information above to troubleshoot and retry your build.
+                ! - Use the debug information above to find out why the script failed.
+                ! - Set `on_script_failure` to \"warn\" to continue the build when the script fails.
+                ! - Remove `run_scripts` from the package if the script isn't needed.
             "},
         );
     }
 
     #[test]
-    fn unsupported_distro_error() {
-        test_error_output("
+    fn install_packages_serialize_provenance_error() {
+        test_error_output(
+            "
                 Context
                 -------
-                This buildpack only supports the following distributions:
-                - Ubuntu 22.04 (amd64)
-                - Ubuntu 24.04 (amd64, arm64)
-
-                Anything else is unsupported. This error is unlikely to be seen by an end-user but may
-                be helpful for developers hacking on this buildpack. Tools like pack also validate
-                buildpacks against their target distribution metadata to prevent this exact scenario.
+                When provenance is enabled, an in-toto statement describing the inputs and outputs
+                of the packages layer is written into the layer. It's built entirely from data the
+                buildpack already has so there's nothing the user can do if serializing it fails.
             ",
-            UnsupportedDistro(UnsupportedDistroError {
-                name: "Windows".to_string(),
-                version: "XP".to_string(),
-                architecture: "x86".to_string(),
-            }),
+            InstallPackagesError::SerializeProvenance(
+                serde_json::from_str::<serde_json::Value>("{").unwrap_err(),
+            ),
             indoc! {"
-                ! Unsupported distribution
+                - Debug Info:
+                  - EOF while parsing an object at line 1 column 1
+
+                ! Failed to serialize provenance
                 !
-                ! The Heroku .deb Packages buildpack doesn't support the Windows XP (x86) distribution.
+                ! An unexpected error occurred while serializing the provenance statement for the \
+                packages layer.
                 !
-                ! Supported distributions:
-                ! - Ubuntu 24.04 (amd64, arm64)
-                ! - Ubuntu 22.04 (amd64)
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis \
+                or a workaround at this time. You can help our understanding by sharing your \
+                buildpack log and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
                 !
-                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
-                a workaround at this time. You can help our understanding by sharing your buildpack log \
-                and a description of the issue at:
+                ! If you're able to reproduce the problem with an example application and the \
+                `pack` build tool \
+                (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more \
+                information around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn install_packages_write_provenance_error() {
+        test_error_output(
+            "
+                Context
+                -------
+                When provenance is enabled, the digest of the packages layer is computed and the
+                provenance statement is written into the layer.
+            ",
+            InstallPackagesError::WriteProvenance(
+                "/layers/heroku_deb-packages/packages/.deb-packages-provenance.json".into(),
+                create_io_error("operation interrupted"),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - operation interrupted
+
+                ! Failed to write provenance
+                !
+                ! An unexpected I/O error occurred while writing the provenance statement to \
+                `/layers/heroku_deb-packages/packages/.deb-packages-provenance.json`.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis \
+                or a workaround at this time. You can help our understanding by sharing your \
+                buildpack log and a description of the issue at:
                 ! https://github.com/heroku/buildpacks-deb-packages/issues/new
                 !
-                ! If you're able to reproduce the problem with an example application and the `pack` \
-                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
-                adding that information to the discussion will also help. Once we have more information \
-                around the causes of this error we may update this message.
+                ! If you're able to reproduce the problem with an example application and the \
+                `pack` build tool \
+                (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more \
+                information around the causes of this error we may update this message.
             "},
         );
     }
 
     #[test]
-    fn create_package_index_error_no_sources() {
-        test_error_output("
+    fn install_packages_write_self_check_error() {
+        test_error_output(
+            "
                 Context
                 -------
-                This is a developer error. It should never be seen by an end-user but could occur if
-                the registered sources for a distribution were modified or someone forgot to register
-                ones for a specific architecture. Our testing processes should always catch this but,
-                if not, we should direct users to file an issue.
+                When self_check is enabled, a script that checks the run image is written and copied
+                into the exec.d directory of the packages layer.
             ",
-            CreatePackageIndexError::NoSources,
+            InstallPackagesError::WriteSelfCheck(
+                "/tmp/deb-packages-check".into(),
+                create_io_error("operation interrupted"),
+            ),
             indoc! {"
-                ! No sources to update
+                - Debug Info:
+                  - operation interrupted
+
+                ! Failed to write self-check
                 !
-                ! The distribution has no sources to update packages from.
+                ! An unexpected I/O error occurred while writing the launch self-check to \
+                `/tmp/deb-packages-check`.
                 !
-                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
-                a workaround at this time. You can help our understanding by sharing your buildpack log \
-                and a description of the issue at:
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis \
+                or a workaround at this time. You can help our understanding by sharing your \
+                buildpack log and a description of the issue at:
                 ! https://github.com/heroku/buildpacks-deb-packages/issues/new
                 !
-                ! If you're able to reproduce the problem with an example application and the `pack` \
-                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
-                adding that information to the discussion will also help. Once we have more information \
-                around the causes of this error we may update this message.
+                ! If you're able to reproduce the problem with an example application and the \
+                `pack` build tool \
+                (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more \
+                information around the causes of this error we may update this message.
             "},
         );
     }
 
     #[test]
-    fn create_package_index_error_task_failed() {
-        test_error_output_with_custom_assertion(
+    fn install_packages_create_download_dir_error() {
+        test_error_output(
             "
                 Context
                 -------
-                This is a developer error. It should never be seen by an end-user. Package indexes
-                are updated using async tasks which can fail if the task panics or is cancelled but
-                we don't cancel running tasks and we handle all errors.
+                Packages requested with BP_DEB_PACKAGES_REINSTALL are downloaded to a temporary
+                directory instead of the download cache.
             ",
-            CreatePackageIndexError::TaskFailed(create_join_error()),
-            |actual_text| {
-                assert_contains_match!(
-                    actual_text,
-                    indoc! {"
-                        - Debug Info:
-                          - task \\d+ panicked with message \"uh oh!\"
+            InstallPackagesError::CreateDownloadDir(
+                "/tmp".into(),
+                create_io_error("No space left on device"),
+            ),
+            indoc! {"
+                - Debug Info:
+                  - No space left on device
 
-                        ! Task failure while updating sources
-                        !
-                        ! A background task responsible for updating sources failed to complete.
-                        !
-                        ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
-                        a workaround at this time. You can help our understanding by sharing your buildpack log \
-                        and a description of the issue at:
-                        ! https://github.com/heroku/buildpacks-deb-packages/issues/new
-                        !
-                        ! If you're able to reproduce the problem with an example application and the `pack` \
-                        build tool \\(https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/\\), \
-                        adding that information to the discussion will also help. Once we have more information \
-                        around the causes of this error we may update this message.
-                    "}
-                );
-            },
+                ! Failed to create download directory
+                !
+                ! An unexpected I/O error occurred while creating `/tmp` to download the packages \
+                to reinstall.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis \
+                or a workaround at this time. You can help our understanding by sharing your \
+                buildpack log and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the \
+                `pack` build tool \
+                (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more \
+                information around the causes of this error we may update this message.
+            "},
         );
     }
 
     #[test]
-    fn create_package_index_error_invalid_layer_name() {
+    fn install_packages_error_write_installed_files() {
         test_error_output(
             "
                 Context
                 -------
-                This is a developer error. It should never be seen by an end-user. The only restrictions
-                on layer naming are that it can't be named 'build', 'launch', or 'cache' and it must be
-                a valid filename. The hexidecimal character set used here should be safe. If a bug is
-                introduced in how this name is generated then we report this and ask the user to file
-                an issue against this buildpack.
+                The files extracted by each package are recorded in the layer so it can be updated
+                in place by later builds. I/O can fail for any number of reasons but since the
+                buildpack owns the layer, there's nothing the user can do here.
             ",
-            CreatePackageIndexError::InvalidLayerName(
-                "http://archive.ubuntu.com/ubuntu/dists/jammy/InRelease".to_string(),
-                LayerNameError::InvalidValue(
-                    "623e1a2085e65abc3dc626a97909466ce19efe37f7a6529a842c290fcfc65b3b".to_string(),
-                ),
+            InstallPackagesError::WriteInstalledFiles(
+                "/path/to/layer/.deb-packages-files.toml".into(),
+                create_io_error("operation interrupted"),
             ),
             indoc! {"
                 - Debug Info:
-                  - Invalid Value: 623e1a2085e65abc3dc626a97909466ce19efe37f7a6529a842c290fcfc65b3b
+                  - operation interrupted
 
-                ! Invalid layer name
-                !
-                ! For caching purposes, a unique layer name is generated for Debian Release files \
-                and Package indices based on their download urls. The generated name for the following \
-                url was invalid:
-                ! - http://archive.ubuntu.com/ubuntu/dists/jammy/InRelease
+                ! Failed to write installed files
                 !
-                ! You can find the invalid layer name in the debug information above.
+                ! An unexpected I/O error occurred while writing the files installed by each \
+                package to `/path/to/layer/.deb-packages-files.toml`.
                 !
-                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
-                a workaround at this time. You can help our understanding by sharing your buildpack log \
-                and a description of the issue at:
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis \
+                or a workaround at this time. You can help our understanding by sharing your \
+                buildpack log and a description of the issue at:
                 ! https://github.com/heroku/buildpacks-deb-packages/issues/new
                 !
-                ! If you're able to reproduce the problem with an example application and the `pack` \
-                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
-                adding that information to the discussion will also help. Once we have more information \
-                around the causes of this error we may update this message.
+                ! If you're able to reproduce the problem with an example application and the \
+                `pack` build tool \
+                (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more \
+                information around the causes of this error we may update this message.
             "},
         );
     }
 
     #[test]
-    fn create_package_index_error_get_release_request() {
+    fn install_packages_error_unknown_timezone() {
         test_error_output(
             "
                 Context
                 -------
-                Package sources are requested from a Debian repository starting with a download of
-                the repository's release file. Network I/O can fail for any number of reasons but
-                the most likely here is that there is a problem with the upstream repository which
-                does have a status page we can direct the user to.
+                When tzdata is installed into the layer, TZDIR points programs at its time zones. A
+                TZ that doesn't name one of those zones is reported since programs silently fall back
+                to UTC.
             ",
-            CreatePackageIndexError::GetReleaseRequest(create_reqwest_middleware_error()),
+            InstallPackagesError::PackageIntegration(PackageIntegrationError::UnknownTimezone(
+                "America/Boston".to_string(),
+            )),
             indoc! {"
-                - Debug Info:
-                  - error sending request for url (https://test/error)
-
-                ! Failed to request Release file
+                ! Unknown time zone
                 !
-                ! While updating package sources, a request to download a Release file failed. This error \
-                can occur due to an unstable network connection or an issue with the upstream Debian \
-                package repository.
+                ! The time zone `America/Boston` configured by `TZ` isn't a time zone provided by \
+                the installed `tzdata` package or a POSIX time zone specification. Programs will \
+                fall back to UTC when it's used.
                 !
                 ! Suggestions:
-                ! - Check the status of https://status.canonical.com/ for any reported issues.
-                !
-                ! Use the debug information above to troubleshoot and retry your build.
-                !
-                ! If the issue persists and you think you found a bug in the buildpack, reproduce the \
-                issue locally with a minimal example. Open an issue in the buildpack's GitHub repository \
-                and include the details here:
-                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                ! - Verify the time zone name is correct (e.g.; America/New_York).
+                ! - Verify the installed version of `tzdata` includes the time zone.
             "},
         );
     }
 
     #[test]
-    fn create_package_index_error_read_get_release_response() {
+    fn install_packages_error_create_alternatives() {
         test_error_output(
             "
                 Context
                 -------
-                Package sources are requested from a Debian repository starting with a download of
-                the repository's release file. This error happens after the request has been initiated
-                and we start reading the response body. Network I/O can fail for any number of reasons but
-                the most likely here is that there is a problem with the upstream repository which
-                does have a status page we can direct the user to.
+                Alternatives registered by a package's postinst script with update-alternatives are
+                emulated by linking the selected alternative into the layer. I/O can fail for any
+                number of reasons but since the buildpack owns the layer, there's nothing the user
+                can do here.
             ",
-            CreatePackageIndexError::ReadGetReleaseResponse(create_reqwest_error()),
+            InstallPackagesError::CreateAlternatives(CreateAlternativesError(
+                "/path/to/layer/usr/bin/editor".into(),
+                create_io_error("permission denied"),
+            )),
             indoc! {"
                 - Debug Info:
-                  - error sending request for url (https://test/error)
+                  - permission denied
 
-                ! Failed to download Release file
+                ! Failed to create alternative
                 !
-                ! While updating package sources, an error occurred while downloading a Release \
-                file. This error can occur due to an unstable network connection or an issue with the upstream \
-                Debian package repository.
+                ! An unexpected I/O error occurred while creating the alternative link at `/path/to/layer/usr/bin/editor`.
                 !
-                ! Suggestions:
-                ! - Check the status of https://status.canonical.com/ for any reported issues.
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                a workaround at this time. You can help our understanding by sharing your buildpack log \
+                and a description of the issue at:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
                 !
-                ! Use the debug information above to troubleshoot and retry your build.
+                ! If you're able to reproduce the problem with an example application and the `pack` \
+                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more information \
+                around the causes of this error we may update this message.
+            "},
+        );
+    }
+
+    #[test]
+    fn install_packages_error_unsupported_compression() {
+        test_error_output(
+            "
+                Context
+                -------
+                This is a developer error. It should not be seen by an end-user. Packages from a Debian
+                repository can be stored using several different compression formats. Should we encounter
+                an unexpected one that we aren't handling then this is a buildpack bug.
+            ",
+            InstallPackagesError::UnsupportedCompression(
+                "/path/to/layer/archive-file.tgz".into(),
+                "lz".to_string(),
+            ),
+            indoc! {"
+                ! Unsupported compression format for package archive
                 !
-                ! If the issue persists and you think you found a bug in the buildpack, reproduce the \
-                issue locally with a minimal example. Open an issue in the buildpack's GitHub repository \
-                and include the details here:
+                ! An unexpected compression format (`lz`) was used for the package archive at \
+                `/path/to/layer/archive-file.tgz`.
+                !
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
+                a workaround at this time. You can help our understanding by sharing your buildpack log \
+                and a description of the issue at:
                 ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the `pack` \
+                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more information \
+                around the causes of this error we may update this message.
             "},
         );
     }
 
     #[test]
-    fn create_package_index_error_create_pgp_certificate() {
+    fn install_packages_error_read_package_config() {
         test_error_output(
             "
                 Context
                 -------
-                This is a developer error. It should never be seen by an end-user. We validate
-                release files using PGP certificates from the supported distributions and if there
-                is a problem with the certificate file, this error would happen. Our testing processes
-                should always catch this but, if not, we should direct users to file an issue.
+                After a package is extracted, we need to update any hardcoded references to it's standard
+                install location that might be referenced in any package-config (*.pc) files from the
+                package to reflect the install location within the layer directory by reading these files.
+                I/O can fail for any number of reasons but since the buildpack process owns this content,
+                there's nothing  the user can do here.
             ",
-            CreatePackageIndexError::CreatePgpCertificate(anyhow!(
-                "Additional packets found, is this a keyring?"
-            )),
+            InstallPackagesError::ReadPackageConfig(
+                "/path/to/layer/pkgconfig/somepackage.pc".into(),
+                create_io_error("invalid filename"),
+            ),
             indoc! {"
                 - Debug Info:
-                  - Additional packets found, is this a keyring?
+                  - invalid filename
 
-                ! Failed to load verifying PGP certificate
-                !
-                ! The PGP certificate used to verify downloaded release files failed to load. This error \
-                indicates there's a problem with the format of the certificate file the \
-                distribution uses.
+                ! Failed to read package config file
                 !
-                ! Suggestions:
-                ! - Verify the format of the certificates found in the ./keys directory of this \
-                buildpack's repository. See https://cirw.in/gpg-decoder
-                ! - Extract new certificates by running the ./scripts/extract_keys.sh script found \
-                in this buildpack's repository.
+                ! An unexpected I/O error occurred while reading the package config file at \
+                `/path/to/layer/pkgconfig/somepackage.pc`.
                 !
                 ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
                 a workaround at this time. You can help our understanding by sharing your buildpack log \
@@ -1412,31 +7725,29 @@ mod tests {
     }
 
     #[test]
-    fn create_package_index_error_create_pgp_verifier() {
+    fn install_packages_error_write_package_config() {
         test_error_output(
             "
                 Context
                 -------
-                This is a developer error. It should never be seen by an end-user. We validate
-                release files using PGP certificates from the supported distributions and if there
-                is a problem with how the release files are signed, this error would happen. Our
-                testing processes should always catch this but, if not, we should direct users to file
-                an issue.
+                After a package is extracted, we need to update any hardcoded references to it's standard
+                install location that might be referenced in any package-config (*.pc) files from the
+                package to reflect the install location within the layer directory by writing these files.
+                I/O can fail for any number of reasons but since the buildpack process owns this content,
+                there's nothing  the user can do here.
             ",
-            CreatePackageIndexError::CreatePgpVerifier(anyhow!("Malformed OpenPGP message")),
+            InstallPackagesError::WritePackageConfig(
+                "/path/to/layer/pkgconfig/somepackage.pc".into(),
+                create_io_error("operation interrupted"),
+            ),
             indoc! {"
                 - Debug Info:
-                  - Malformed OpenPGP message
+                  - operation interrupted
 
-                ! Failed to verify Release file
-                !
-                ! The PGP signature of the downloaded release file failed verification. This error can \
-                occur if the maintainers of the Debian repository changed the process \
-                for signing release files.
+                ! Failed to write package config file
                 !
-                ! Suggestions:
-                ! - Verify if the keys changed by running the ./scripts/extract_keys.sh script \
-                found in this buildpack's repository.
+                ! An unexpected I/O error occurred while writing the package config file to \
+                `/path/to/layer/pkgconfig/somepackage.pc`.
                 !
                 ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
                 a workaround at this time. You can help our understanding by sharing your buildpack log \
@@ -1452,27 +7763,28 @@ mod tests {
     }
 
     #[test]
-    fn create_package_index_error_write_release_layer() {
+    fn install_packages_error_write_snapshot() {
         test_error_output(
             "
                 Context
                 -------
-                We write downloaded release files and other information into the release layer. I/O
-                operations can fail for any number of reasons which we can't anticipate and since this
-                file-system area is managed by the buildpack process there is nothing the user can do
-                here other than report it.
+                A snapshot of the inputs used to produce the installation is written into the layer
+                so the image can be traced back to how it was built. I/O can fail for any number of
+                reasons but since the buildpack process owns this content, there's nothing the user
+                can do here.
             ",
-            CreatePackageIndexError::WriteReleaseLayer(
-                "/path/to/layer/file".into(),
-                create_io_error("out of memory"),
+            InstallPackagesError::WriteSnapshot(
+                "/path/to/layer/.deb-packages-snapshot.toml".into(),
+                create_io_error("operation interrupted"),
             ),
             indoc! {"
                 - Debug Info:
-                  - out of memory
+                  - operation interrupted
 
-                ! Failed to write Release file to layer
+                ! Failed to write installation snapshot
                 !
-                ! An unexpected I/O error occurred while writing release data to `/path/to/layer/file`.
+                ! An unexpected I/O error occurred while writing the installation snapshot to \
+                `/path/to/layer/.deb-packages-snapshot.toml`.
                 !
                 ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
                 a workaround at this time. You can help our understanding by sharing your buildpack log \
@@ -1488,27 +7800,26 @@ mod tests {
     }
 
     #[test]
-    fn create_package_index_error_read_release_file() {
+    fn install_packages_error_serialize_sbom() {
         test_error_output(
             "
                 Context
                 -------
-                We read cached release files and other information from the release layer. I/O
-                operations can fail for any number of reasons which we can't anticipate and since this
-                file-system area is managed by the buildpack process there is nothing the user can do
-                here other than report it.
+                CycloneDX and SPDX SBOMs listing the installed packages are attached to the packages
+                layer.
+                The SBOM is built entirely from package index data so there's nothing the user can
+                do if serializing it fails.
             ",
-            CreatePackageIndexError::ReadReleaseFile(
-                "/path/to/layer/release-file".into(),
-                create_io_error("not found"),
+            InstallPackagesError::SerializeSbom(
+                serde_json::from_str::<serde_json::Value>("{").unwrap_err(),
             ),
             indoc! {"
                 - Debug Info:
-                  - not found
+                  - EOF while parsing an object at line 1 column 1
 
-                ! Failed to read Release file from layer
+                ! Failed to serialize SBOMs
                 !
-                ! An unexpected I/O error occurred while reading Release data from `/path/to/layer/release-file`.
+                ! An unexpected error occurred while serializing the SBOMs for the installed packages.
                 !
                 ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
                 a workaround at this time. You can help our understanding by sharing your buildpack log \
@@ -1524,238 +7835,239 @@ mod tests {
     }
 
     #[test]
-    fn create_package_index_error_parse_release_file() {
+    fn install_packages_error_extraction_issues() {
         test_error_output(
             "
                 Context
                 -------
-                If the release file cannot be parsed, this is either a developer error or a serious
-                problem with the downloaded release file. Running the build with a clean cache
-                should force the file to be re-downloaded which may correct the issue.
+                After the packages are extracted, the files are inspected for anything that may not
+                work once the layer is mounted somewhere other than the root filesystem. This only
+                fails the build when strict_extraction is enabled.
             ",
-            CreatePackageIndexError::ParseReleaseFile(
-                "/path/to/layer/release-file".into(),
-                apt_parser::errors::ParseError.into(),
-            ),
+            InstallPackagesError::ExtractionIssues(BTreeMap::from([(
+                "vim".to_string(),
+                vec![
+                    ExtractionIssue::WorldWritable("/usr/share/vim/vimrc".into()),
+                    ExtractionIssue::DanglingAlternative {
+                        path: "/usr/bin/editor".into(),
+                        target: "/etc/alternatives/editor".into(),
+                    },
+                ],
+            )])),
             indoc! {"
-                - Debug Info:
-                  - Failed to parse an APT value
-
-                ! Failed to parse Release file data
-                !
-                ! We couldn't parse the Release file data stored in `/path/to/layer/release-file`. \
-                This error is most likely a buildpack bug. It can also be caused by cached data \
-                that's no longer valid or an issue with the upstream repository.
+                ! Installed packages contain files that may not work when relocated
                 !
-                ! Suggestions:
-                ! - Run the build again with a clean cache.
+                ! Packages are installed into a layer instead of the root filesystem they were built \
+                for. Since `strict_extraction` is set to true, the build failed because the following \
+                files may not work from that location:
                 !
-                ! Use the debug information above to troubleshoot and retry your build.
+                ! - `vim`: world-writable /usr/share/vim/vimrc
+                ! - `vim`: dangling alternative /usr/bin/editor -> /etc/alternatives/editor
                 !
-                ! If the issue persists and you think you found a bug in the buildpack, reproduce the \
-                issue locally with a minimal example. Open an issue in the buildpack's GitHub repository \
-                and include the details here:
-                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                ! Suggestions:
+                ! - Install the packages that provide the missing symlink targets.
+                ! - Remove `strict_extraction` from project.toml to report these files without failing \
+                the build.
             "},
         );
     }
 
     #[test]
-    fn create_package_index_error_missing_sha256_release_hashes() {
+    fn install_packages_error_layout_issues() {
         test_error_output(
             "
                 Context
                 -------
-                If the release file downloaded from the Debian repository is missing the SHA256 release
-                key then that's a serious problem with the repository.
+                When verify_layout is enabled the installed layer is checked for packages that
+                extracted no files and for package environment variables that reference paths
+                missing from the layer. These point to bugs in extraction so an issue is suggested.
             ",
-            CreatePackageIndexError::MissingSha256ReleaseHashes(RepositoryUri::from(
-                "http://archive.ubuntu.com/ubuntu/dists/jammy/InRelease",
-            )),
+            InstallPackagesError::LayoutIssues(BTreeMap::from([(
+                "git".to_string(),
+                vec![
+                    LayoutIssue::NoExtractedFiles,
+                    LayoutIssue::MissingEnvPath {
+                        name: "GIT_EXEC_PATH".to_string(),
+                        path: "/layers/packages/usr/lib/git-core".into(),
+                    },
+                ],
+            )])),
             indoc! {"
-                ! Missing SHA256 Release hash
+                ! Installed packages failed layout verification
                 !
-                ! The Release file from http://archive.ubuntu.com/ubuntu/dists/jammy/InRelease \
-                is missing the SHA256 key which is required according to the documented Debian repository format. \
-                This error is most likely an issue with the upstream repository. See \
-                https://wiki.debian.org/DebianRepository/Format
+                ! Since `verify_layout` is set to true, the installed packages were checked after \
+                extraction and the following problems were found:
                 !
-                ! Use the debug information above to troubleshoot and retry your build.
+                ! - `git`: no files were extracted
+                ! - `git`: GIT_EXEC_PATH contains missing path /layers/packages/usr/lib/git-core
                 !
-                ! If the issue persists and you think you found a bug in the buildpack, reproduce the \
-                issue locally with a minimal example. Open an issue in the buildpack's GitHub repository \
-                and include the details here:
+                ! This usually means a package wasn't extracted correctly.
+                !
+                ! If the issue persists and you think you found a bug in the buildpack, reproduce \
+                the issue locally with a minimal example. Open an issue in the buildpack's GitHub \
+                repository and include the details here:
                 ! https://github.com/heroku/buildpacks-deb-packages/issues/new
             "},
         );
     }
 
     #[test]
-    fn create_package_index_error_missing_package_index_release_hash() {
+    fn install_packages_error_locales_not_installed() {
         test_error_output(
             "
                 Context
                 -------
-                If the release file downloaded from the Debian repository is missing the package index
-                entry for a component then there's a serious problem with the repository.
+                Configured locales are compiled from the definitions in the locales package. If it
+                isn't installed into the layer or available on the base image we ask the user to add
+                it to the packages to install.
             ",
-            CreatePackageIndexError::MissingPackageIndexReleaseHash(
-                RepositoryUri::from("http://archive.ubuntu.com/ubuntu/dists/jammy/InRelease"),
-                "main/binary-amd64/Packages.gz".to_string(),
-            ),
+            InstallPackagesError::PackageIntegration(PackageIntegrationError::GenerateLocales(
+                GenerateLocalesError::LocalesNotInstalled,
+            )),
             indoc! {"
-                ! Missing Package Index
+                ! Locales package not installed
                 !
-                ! The Release file from http://archive.ubuntu.com/ubuntu/dists/jammy/InRelease is \
-                missing an entry for `main/binary-amd64/Packages.gz` within the SHA256 section. This error \
-                is most likely a buildpack bug but can also be an issue with the upstream \
-                repository.
+                ! The locales configured in `locales` are compiled from the locale definitions in \
+                the `locales` package but it isn't installed in the layer or on the base image.
                 !
                 ! Suggestions:
-                ! - Verify if `main/binary-amd64/Packages.gz` is under the SHA256 section of \
-                http://archive.ubuntu.com/ubuntu/dists/jammy/InRelease
-                !
-                ! Use the debug information above to troubleshoot and retry your build.
-                !
-                ! If the issue persists and you think you found a bug in the buildpack, reproduce the \
-                issue locally with a minimal example. Open an issue in the buildpack's GitHub repository \
-                and include the details here:
-                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                ! - Add `locales` to the packages to install in project.toml.
             "},
         );
     }
 
     #[test]
-    fn create_package_index_error_get_packages_request() {
+    fn install_packages_error_create_locale_dir() {
         test_error_output(
             "
                 Context
                 -------
-                After downloading the release file, the next step for updating sources is to fetch
-                the package indexes. Network I/O can fail for any number of reasons but
-                the most likely here is that there is a problem with the upstream repository which
-                does have a status page we can direct the user to.
+                Configured locales are compiled into a directory in the layer that's exported as
+                LOCPATH. Creating that directory should only fail from an unexpected I/O error.
             ",
-            CreatePackageIndexError::GetPackagesRequest(create_reqwest_middleware_error()),
+            InstallPackagesError::PackageIntegration(PackageIntegrationError::GenerateLocales(
+                GenerateLocalesError::CreateLocaleDir(
+                    "/layers/packages/usr/lib/locale".into(),
+                    create_io_error("Permission denied"),
+                ),
+            )),
             indoc! {"
                 - Debug Info:
-                  - error sending request for url (https://test/error)
+                  - Permission denied
 
-                ! Failed to request Package Index file
-                !
-                ! While updating package sources, a request to download a Package Index file failed. \
-                This error can occur due to an unstable network connection or an issue with the upstream Debian \
-                package repository.
-                !
-                ! Suggestions:
-                ! - Check the status of https://status.canonical.com/ for any reported issues.
+                ! Failed to create locale directory
                 !
-                ! Use the debug information above to troubleshoot and retry your build.
+                ! An unexpected I/O error occurred while creating the directory for the generated \
+                locales at `/layers/packages/usr/lib/locale`.
                 !
-                ! If the issue persists and you think you found a bug in the buildpack, reproduce the \
-                issue locally with a minimal example. Open an issue in the buildpack's GitHub repository \
-                and include the details here:
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis \
+                or a workaround at this time. You can help our understanding by sharing your \
+                buildpack log and a description of the issue at:
                 ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                !
+                ! If you're able to reproduce the problem with an example application and the \
+                `pack` build tool \
+                (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more \
+                information around the causes of this error we may update this message.
             "},
         );
     }
 
     #[test]
-    fn create_package_index_error_write_package_layer() {
+    fn install_packages_error_run_localedef() {
         test_error_output(
             "
                 Context
                 -------
-                When downloading a package index the response body stream is written directly to the
-                file-system. This error could happen if the file to write to could not be opened.
+                Configured locales are compiled with localedef. Failing to spawn it is unexpected
+                since it's provided by libc-bin on every supported base image.
             ",
-            CreatePackageIndexError::WritePackagesLayer(
-                "/path/to/layer/package".into(),
-                create_io_error("entity already exists"),
-            ),
+            InstallPackagesError::PackageIntegration(PackageIntegrationError::GenerateLocales(
+                GenerateLocalesError::RunLocaledef(
+                    Box::new(Locale::from_str("en_US.UTF-8").unwrap()),
+                    create_io_error("No such file or directory"),
+                ),
+            )),
             indoc! {"
                 - Debug Info:
-                  - entity already exists
+                  - No such file or directory
 
-                ! Failed to write Package Index file to layer
+                ! Failed to run localedef
                 !
-                ! An unexpected I/O error occurred while writing Package Index data to `/path/to/layer/package`.
+                ! An unexpected error occurred while running `localedef` to generate the locale \
+                `en_US.UTF-8`.
                 !
-                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
-                a workaround at this time. You can help our understanding by sharing your buildpack log \
-                and a description of the issue at:
+                ! The causes for this error are unknown. We do not have suggestions for diagnosis \
+                or a workaround at this time. You can help our understanding by sharing your \
+                buildpack log and a description of the issue at:
                 ! https://github.com/heroku/buildpacks-deb-packages/issues/new
                 !
-                ! If you're able to reproduce the problem with an example application and the `pack` \
-                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
-                adding that information to the discussion will also help. Once we have more information \
-                around the causes of this error we may update this message.
+                ! If you're able to reproduce the problem with an example application and the \
+                `pack` build tool \
+                (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
+                adding that information to the discussion will also help. Once we have more \
+                information around the causes of this error we may update this message.
             "},
         );
     }
 
     #[test]
-    fn create_package_index_error_write_package_index_from_response() {
+    fn install_packages_error_localedef_failed() {
         test_error_output(
             "
                 Context
                 -------
-                When downloading a package index the response body stream is written directly to the
-                file-system. File and network I/O can fail for any number of reasons but the most
-                likely here would be a connection interruption.
+                Configured locales are compiled with localedef. If it can't compile a locale (e.g.;
+                the locale definition doesn't exist) we show the output from localedef.
             ",
-            CreatePackageIndexError::WritePackageIndexFromResponse(
-                "/path/to/layer/package-index".into(),
-                create_io_error("stream closed"),
-            ),
+            InstallPackagesError::PackageIntegration(PackageIntegrationError::GenerateLocales(
+                GenerateLocalesError::LocaledefFailed(
+                    Box::new(Locale::from_str("xx_XX.UTF-8").unwrap()),
+                    "[error] cannot open locale definition file `xx_XX': No such file or directory"
+                        .to_string(),
+                ),
+            )),
             indoc! {"
                 - Debug Info:
-                  - stream closed
+                  - [error] cannot open locale definition file `xx_XX': No such file or directory
 
-                ! Failed to download Package Index file
+                ! Failed to generate locale `xx_XX.UTF-8`
                 !
-                ! While updating package sources, an error occurred while downloading a Package Index \
-                file to `/path/to/layer/package-index`. This error can occur due to an unstable network connection \
-                or an issue with the upstream Debian package repository.
+                ! The locale `xx_XX.UTF-8` configured in `locales` couldn't be compiled from the \
+                locale definition `xx_XX` with the charmap `UTF-8`.
                 !
                 ! Suggestions:
-                ! - Check the status of https://status.canonical.com/ for any reported issues.
-                !
-                ! Use the debug information above to troubleshoot and retry your build.
-                !
-                ! If the issue persists and you think you found a bug in the buildpack, reproduce the \
-                issue locally with a minimal example. Open an issue in the buildpack's GitHub repository \
-                and include the details here:
-                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                ! - Verify the locale is listed in /usr/share/i18n/SUPPORTED.
             "},
         );
     }
 
     #[test]
-    fn create_package_index_error_checksum_failed() {
-        test_error_output(
-            "
+    fn config_parse_config_error_for_invalid_verify_layout() {
+        test_error_output("
                 Context
                 -------
-                All downloaded package indexes are verified according to the checksum given by the
-                owning release file. If these checksums don't match then the download is invalid. When
-                this happens a rebuild typically fixes the issue.
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but verify_layout isn't a boolean we report the invalid value
+                to the user and point them to the documentation.
             ",
-            CreatePackageIndexError::ChecksumFailed {
-                url: "http://ports.ubuntu.com/ubuntu-ports/dists/noble/main/binary-arm64/by-hash/SHA256/d41d8cd98f00b204e9800998ecf8427e".to_string(),
-                expected: "d41d8cd98f00b204e9800998ecf8427e".to_string(),
-                actual: "e62ff0123a74adfc6903d59a449cbdb0".to_string()
-            },
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidVerifyLayout("1".to_string()),
+            ),
             indoc! {"
-                ! Package Index checksum verification failed
+                ! Error parsing `/path/to/project.toml` with invalid verify_layout
                 !
-                ! While updating package sources, an error occurred while verifying the checksum of \
-                the Package Index at http://ports.ubuntu.com/ubuntu-ports/dists/noble/main/binary-arm64/by-hash/SHA256/d41d8cd98f00b204e9800998ecf8427e. \
-                This error can occur due to an issue with the upstream Debian package repository.
+                ! The Heroku .deb Packages buildpack reads configuration from `/path/to/project.toml` \
+                to complete the build but we found an invalid value `1` for the key \
+                `verify_layout` in `[com.heroku.buildpacks.deb-packages]`.
                 !
-                ! Checksum:
-                ! - Expected: `d41d8cd98f00b204e9800998ecf8427e`
-                ! - Actual: `e62ff0123a74adfc6903d59a449cbdb0`
+                ! The value must be a boolean (true or false).
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
                 !
                 ! Use the debug information above to troubleshoot and retry your build.
             "},
@@ -1763,230 +8075,219 @@ mod tests {
     }
 
     #[test]
-    fn create_package_index_error_cpu_task_failed() {
-        test_error_output(
-            "
+    fn config_parse_config_error_for_invalid_backports() {
+        test_error_output("
                 Context
                 -------
-                This is a developer error. It should never be seen by an end-user. Processing package
-                index files is a CPU-intensive operation and happens in parallel worker tasks for
-                efficiency. This error can happen if a worker task panics and the error isn't handled.
-                Our testing processes should always catch this but, if not, we should direct users to file
-                an issue.
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but backports isn't a boolean we report the invalid value
+                to the user and point them to the documentation.
             ",
-            CreatePackageIndexError::CpuTaskFailed(create_recv_error()),
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidBackports("\"yes\"".to_string()),
+            ),
             indoc! {"
-                - Debug Info:
-                  - channel closed
-
-                ! Task failure while reading Package Index data
+                ! Error parsing `/path/to/project.toml` with invalid backports
                 !
-                ! A background task responsible for reading Package Index data failed to complete.
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value \
+                `\"yes\"` for the key `backports` in `[com.heroku.buildpacks.deb-packages]`.
                 !
-                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
-                a workaround at this time. You can help our understanding by sharing your buildpack log \
-                and a description of the issue at:
-                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                ! The value must be a boolean (true or false).
                 !
-                ! If you're able to reproduce the problem with an example application and the `pack` \
-                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
-                adding that information to the discussion will also help. Once we have more information \
-                around the causes of this error we may update this message.
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
             "},
         );
     }
 
     #[test]
-    fn create_package_index_error_read_packages_file() {
-        test_error_output(
-            "
+    fn config_parse_config_error_for_invalid_proposed() {
+        test_error_output("
                 Context
                 -------
-                This is a developer error. It should never be seen by an end-user. We read from
-                a package index file stored in the layer directory managed by the buildpack process.
-                I/O errors can happen for any number of reasons and there's nothing the user can
-                do here.
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but proposed isn't a boolean we report the invalid value
+                to the user and point them to the documentation.
             ",
-            CreatePackageIndexError::ReadPackagesFile(
-                "/path/to/layer/packages-file".into(),
-                create_io_error("entity not found"),
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidProposed("\"yes\"".to_string()),
             ),
             indoc! {"
-                - Debug Info:
-                  - entity not found
-
-                ! Failed to read Package Index file
+                ! Error parsing `/path/to/project.toml` with invalid proposed
                 !
-                ! An unexpected I/O error occurred while reading Package Index data from \
-                `/path/to/layer/packages-file`.
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value \
+                `\"yes\"` for the key `proposed` in `[com.heroku.buildpacks.deb-packages]`.
                 !
-                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
-                a workaround at this time. You can help our understanding by sharing your buildpack log \
-                and a description of the issue at:
-                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                ! The value must be a boolean (true or false).
                 !
-                ! If you're able to reproduce the problem with an example application and the `pack` \
-                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
-                adding that information to the discussion will also help. Once we have more information \
-                around the causes of this error we may update this message.
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
             "},
         );
     }
 
     #[test]
-    fn create_package_index_error_parse_packages() {
-        test_error_output(
-            "
+    fn config_parse_config_error_for_invalid_provenance() {
+        test_error_output("
                 Context
                 -------
-                We need to parse all packages contained in a package index file. This error could happen
-                if the package index contained bad data which would indicate a problem with the
-                upstream repository but it's more likely to be a bug with the buildpack. Running the
-                build with a fresh cache would force the package index to be re-downloaded which
-                might fix the issue.
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but provenance isn't a boolean we report the invalid value
+                to the user and point them to the documentation.
             ",
-            CreatePackageIndexError::ParsePackages(
-                "/path/to/layer/packages-file".into(),
-                vec![
-                    ParseRepositoryPackageError::MissingPackageName,
-                    ParseRepositoryPackageError::MissingVersion("package-a".to_string()),
-                    ParseRepositoryPackageError::MissingFilename("package-b".to_string()),
-                    ParseRepositoryPackageError::MissingSha256("package-c".to_string()),
-                ],
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidProvenance("\"yes\"".to_string()),
             ),
             indoc! {"
-                ! Failed to parse Package Index file
+                ! Error parsing `/path/to/project.toml` with invalid provenance
                 !
-                ! We can't parse the Package Index file data stored in `/path/to/layer/packages-file`. \
-                This error is most likely a buildpack bug. It can also be caused by \
-                cached data that's no longer valid or an issue with the upstream repository.
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value \
+                `\"yes\"` for the key `provenance` in `[com.heroku.buildpacks.deb-packages]`.
                 !
-                ! Parsing errors:
-                ! - There's an entry that's missing the required `Package` key.
-                ! - Package `package-a` is missing the required `Version` key.
-                ! - Package `package-b` is missing the required `Filename` key.
-                ! - Package `package-c` is missing the required `SHA256` key.
+                ! The value must be a boolean (true or false).
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
+        );
+    }
+
+    #[test]
+    fn config_parse_config_error_for_invalid_self_check() {
+        test_error_output("
+                Context
+                -------
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but self_check isn't a boolean we report the invalid value
+                to the user and point them to the documentation.
+            ",
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidSelfCheck("\"yes\"".to_string()),
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with invalid self_check
                 !
-                ! Suggestions:
-                ! - Run the build again with a clean cache.
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value \
+                `\"yes\"` for the key `self_check` in `[com.heroku.buildpacks.deb-packages]`.
                 !
-                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
-                a workaround at this time. You can help our understanding by sharing your buildpack log \
-                and a description of the issue at:
-                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                ! The value must be a boolean (true or false).
                 !
-                ! If you're able to reproduce the problem with an example application and the `pack` \
-                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
-                adding that information to the discussion will also help. Once we have more information \
-                around the causes of this error we may update this message.
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
             "},
         );
     }
 
     #[test]
-    fn determine_packages_to_install_error_read_system_packages() {
-        test_error_output(
-            "
+    fn config_parse_config_error_for_invalid_check_release_dates() {
+        test_error_output("
                 Context
                 -------
-                This is a developer error. It should never be seen by an end-user. All system packages
-                are stored in /var/lib/dpkg/status. I/O errors can happen for any number of reasons
-                but the most likely here is that the file doesn't exist for some reason or the file
-                path was accidentally modified. Our testing processes should always catch this but,
-                if not, we should direct users to file an issue.
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but check_release_dates isn't a boolean we report the invalid value
+                to the user and point them to the documentation.
             ",
-            DeterminePackagesToInstallError::ReadSystemPackages(
-                "/var/lib/dpkg/status".into(),
-                create_io_error("entity not found"),
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidCheckReleaseDates("\"no\"".to_string()),
             ),
             indoc! {"
-                - Debug Info:
-                  - entity not found
-
-                ! Failed to read system packages
+                ! Error parsing `/path/to/project.toml` with invalid check_release_dates
                 !
-                ! An unexpected I/O error occurred while reading system packages from `/var/lib/dpkg/status`.
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value `\"no\"` \
+                for the key `check_release_dates` in `[com.heroku.buildpacks.deb-packages]`.
                 !
-                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
-                a workaround at this time. You can help our understanding by sharing your buildpack log \
-                and a description of the issue at:
-                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                ! The value must be a boolean (true or false).
                 !
-                ! If you're able to reproduce the problem with an example application and the `pack` \
-                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
-                adding that information to the discussion will also help. Once we have more information \
-                around the causes of this error we may update this message.
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
             "},
         );
     }
 
     #[test]
-    fn determine_packages_to_install_error_parse_system_packages() {
-        test_error_output(
-            "
+    fn config_parse_config_error_for_invalid_package_index_cache_days() {
+        test_error_output("
                 Context
                 -------
-                This is a developer error. It should never be seen by an end-user. All system packages
-                are stored in /var/lib/dpkg/status and it's unlikely for this file to be malformed. More
-                likely is there is a bug with the parsing logic. Our testing processes should always catch
-                this but, if not, we should direct users to file an issue.
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but package_index_cache_days isn't a non-negative integer we report
+                the invalid value to the user and point them to the documentation.
             ",
-            DeterminePackagesToInstallError::ParseSystemPackage(
-                "/var/lib/dpkg/status".into(),
-                "some-package".to_string(),
-                apt_parser::errors::APTError::KVError(apt_parser::errors::KVError),
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidPackageIndexCacheDays("-1".to_string()),
             ),
             indoc! {"
-                - Debug Info:
-                  - Failed to parse APT key-value data
-
-                    Package:
-                    some-package
-
-                ! Failed to parse system package
+                ! Error parsing `/path/to/project.toml` with invalid package_index_cache_days
                 !
-                ! An unexpected parsing error occurred while reading system packages from `/var/lib/dpkg/status`.
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value `-1` \
+                for the key `package_index_cache_days` in `[com.heroku.buildpacks.deb-packages]`.
                 !
-                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
-                a workaround at this time. You can help our understanding by sharing your buildpack log \
-                and a description of the issue at:
-                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                ! The value must be a non-negative integer. Use 0 to download the package indexes \
+                on every build.
                 !
-                ! If you're able to reproduce the problem with an example application and the `pack` \
-                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
-                adding that information to the discussion will also help. Once we have more information \
-                around the causes of this error we may update this message.
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
             "},
         );
     }
 
     #[test]
-    fn determine_packages_to_install_error_package_not_found() {
-        test_error_output(
-            "
+    fn config_parse_config_error_for_invalid_sources_file() {
+        test_error_output("
                 Context
                 -------
-                We're installing a list of packages given by the user in the buildpack configuration.
-                It's possible to provide a valid name of a package that doesn't actually exist in the
-                Debian repositories used by the distribution. If this happens we direct the user to
-                the package search site for Ubuntu to verify the package name.
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but sources_file isn't a path we report the invalid value to the
+                user and point them to the documentation.
             ",
-            DeterminePackagesToInstallError::PackageNotFound("some-package".to_string(), vec!["some-package1".to_string(), "some-package2".to_string()]),
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidSourcesFile("[\"example.sources\"]".to_string()),
+            ),
             indoc! {"
-                ! Package not found
+                ! Error parsing `/path/to/project.toml` with invalid sources_file
                 !
-                ! We can't find `some-package` in the Package Index. If \
-                this package is listed in the packages to install for this buildpack then the name is most \
-                likely misspelled. Otherwise, it can be an issue with the \
-                upstream Debian package repository.
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value \
+                `[\"example.sources\"]` for the key `sources_file` in \
+                `[com.heroku.buildpacks.deb-packages]`.
                 !
-                ! Did you mean?
-                ! - `some-package1`
-                ! - `some-package2`
+                ! The value must be the path to a file of sources in the deb822 format relative to \
+                the application directory (e.g.; \"config/example.sources\").
                 !
                 ! Suggestions:
-                ! - Verify the package name is correct and exists for the target distribution at \
-                https://packages.ubuntu.com/
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
                 !
                 ! Use the debug information above to troubleshoot and retry your build.
             "},
@@ -1994,31 +8295,31 @@ mod tests {
     }
 
     #[test]
-    fn determine_packages_to_install_error_package_not_found_with_no_suggestions() {
-        test_error_output(
-            "
+    fn config_parse_config_error_for_invalid_snapshot() {
+        test_error_output("
                 Context
                 -------
-                We're installing a list of packages given by the user in the buildpack configuration.
-                It's possible to provide a valid name of a package that doesn't actually exist in the
-                Debian repositories used by the distribution. If this happens we direct the user to
-                the package search site for Ubuntu to verify the package name.
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but snapshot isn't a timestamp in the format of the snapshot
+                services we report the invalid value to the user and point them to the documentation.
             ",
-            DeterminePackagesToInstallError::PackageNotFound("some-package".to_string(), vec![]),
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidSnapshot(ParseSnapshotError("2024-03-01".to_string())),
+            ),
             indoc! {"
-                ! Package not found
+                ! Error parsing `/path/to/project.toml` with invalid snapshot
                 !
-                ! We can't find `some-package` in the Package Index. If \
-                this package is listed in the packages to install for this buildpack then the name is most \
-                likely misspelled. Otherwise, it can be an issue with the \
-                upstream Debian package repository.
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value \
+                `2024-03-01` for the key `snapshot` in `[com.heroku.buildpacks.deb-packages]`.
                 !
-                ! Did you mean?
-                ! - No similarly named packages found
+                ! The value must be a UTC timestamp in the format used by the Ubuntu and Debian \
+                snapshot services (e.g.; `20240301T000000Z`).
                 !
                 ! Suggestions:
-                ! - Verify the package name is correct and exists for the target distribution at \
-                https://packages.ubuntu.com/
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
                 !
                 ! Use the debug information above to troubleshoot and retry your build.
             "},
@@ -2026,36 +8327,32 @@ mod tests {
     }
 
     #[test]
-    fn determine_packages_to_install_error_virtual_package_must_be_specified() {
-        test_error_output(
-            "
+    fn config_parse_config_error_for_invalid_components() {
+        test_error_output("
                 Context
                 -------
-                We're installing a list of packages given by the user in the buildpack configuration.
-                There is a special type of package name in a Debian repository that represents a
-                'virtual package' which may be implemented by one or more actual packages. This error
-                is shown when there is more than one provider because we can't automatically determine
-                which one should be used without the user's input.
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but components isn't a non-empty array of component names we
+                report the invalid value to the user and point them to the documentation.
             ",
-            DeterminePackagesToInstallError::VirtualPackageMustBeSpecified(
-                "some-package".to_string(),
-                HashSet::from(["package-b".to_string(), "package-a".to_string()]),
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidComponents("\"non free\"".to_string()),
             ),
             indoc! {"
-                ! Multiple providers were found for the package `some-package`
+                ! Error parsing `/path/to/project.toml` with invalid components
                 !
-                ! Sometimes there are several packages which offer more-or-less the same functionality. \
-                In this case, Debian repositories define a virtual package and one or more actual packages \
-                provide an implementation for this virtual package. When multiple providers are found for \
-                a requested package, this buildpack can't automatically choose which one is the desired \
-                implementation.
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value `\"non \
+                free\"` for the key `components` in `[com.heroku.buildpacks.deb-packages]`.
                 !
-                ! Providing packages:
-                ! - package-a
-                ! - package-b
+                ! The value must be a non-empty array of component names used for the \
+                distribution's package sources (e.g.; [\"main\", \"universe\", \"multiverse\", \
+                \"restricted\"]).
                 !
                 ! Suggestions:
-                ! - Replace the virtual package `some-package` with one of the above providers.
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
                 !
                 ! Use the debug information above to troubleshoot and retry your build.
             "},
@@ -2063,173 +8360,161 @@ mod tests {
     }
 
     #[test]
-    fn install_packages_error_task_failed() {
-        test_error_output_with_custom_assertion(
-            "
+    fn config_parse_config_error_for_invalid_locales() {
+        test_error_output("
                 Context
                 -------
-                This is a developer error. It should never be seen by an end-user. Package installations
-                are performed using async tasks which can fail if the task panics or is cancelled but
-                we don't cancel running tasks and we handle all errors.
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but locales isn't an array we report the invalid value to the
+                user and point them to the documentation.
             ",
-            InstallPackagesError::TaskFailed(create_join_error()),
-            |actual_text| {
-                assert_contains_match!(
-                    actual_text,
-                    indoc! {"
-                    - Debug Info:
-                      - task \\d+ panicked with message \"uh oh!\"
-
-                    ! Task failure while installing packages
-                    !
-                    ! A background task responsible for installing failed to complete.
-                    !
-                    ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
-                    a workaround at this time. You can help our understanding by sharing your buildpack log \
-                    and a description of the issue at:
-                    ! https://github.com/heroku/buildpacks-deb-packages/issues/new
-                    !
-                    ! If you're able to reproduce the problem with an example application and the `pack` \
-                    build tool \\(https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/\\), \
-                    adding that information to the discussion will also help. Once we have more information \
-                    around the causes of this error we may update this message.
-                "}
-                );
-            },
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidLocales("\"en_US.UTF-8\"".to_string()),
+            ),
+            indoc! {"
+                ! Error parsing `/path/to/project.toml` with invalid locales
+                !
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value \
+                `\"en_US.UTF-8\"` for the key `locales` in `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! The value must be an array of locale names (e.g.; [\"en_US.UTF-8\", \
+                \"de_DE.UTF-8\"]).
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+            "},
         );
     }
 
     #[test]
-    fn install_packages_error_invalid_filename() {
-        test_error_output(
-            "
+    fn config_parse_config_error_for_invalid_phased_updates() {
+        test_error_output("
                 Context
                 -------
-                This is a developer error. It should never be seen by an end-user. Packages
-                specify a Filename field that can be used to form a filename to download the
-                package to. This error can only happen if that Filename field contains a value
-                of '..' which is unlikely since that would cause serious problems for the upstream
-                repository.
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but phased_updates isn't one of the supported policies we report
+                the invalid value to the user and point them to the documentation.
             ",
-            InstallPackagesError::InvalidFilename("some-package".to_string(), "..".to_string()),
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidPhasedUpdates(ParsePhasedUpdatesPolicyError(
+                    "force".to_string(),
+                )),
+            ),
             indoc! {"
-                ! Could not determine file name for `some-package`
+                ! Error parsing `/path/to/project.toml` with invalid phased_updates
                 !
-                ! The package information for `some-package` contains a Filename field of `..` which \
-                produces an invalid name to use as a download path.
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value \
+                `force` for the key `phased_updates` in `[com.heroku.buildpacks.deb-packages]`.
                 !
-                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
-                a workaround at this time. You can help our understanding by sharing your buildpack log \
-                and a description of the issue at:
-                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                ! The value must be one of \"skip\" or \"include\".
                 !
-                ! If you're able to reproduce the problem with an example application and the `pack` \
-                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
-                adding that information to the discussion will also help. Once we have more information \
-                around the causes of this error we may update this message.
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
             "},
         );
     }
 
     #[test]
-    fn install_packages_error_request_package() {
-        test_error_output(
-            "
+    fn config_parse_config_error_for_invalid_existing_files() {
+        test_error_output("
                 Context
                 -------
-                Packages are downloaded from a Debian repository. Network I/O can fail for any number
-                of reasons but the most likely here would be a problem with the upstream.
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but existing_files isn't one of the supported policies we report
+                the invalid value to the user and point them to the documentation.
             ",
-            InstallPackagesError::RequestPackage(
-                repository_package("some-package"),
-                create_reqwest_middleware_error(),
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidExistingFiles(ParseExistingFilesPolicyError(
+                    "replace".to_string(),
+                )),
             ),
             indoc! {"
-                - Debug Info:
-                  - error sending request for url (https://test/error)
-
-                ! Failed to request package
+                ! Error parsing `/path/to/project.toml` with invalid existing_files
                 !
-                ! While installing packages, an error occurred while downloading `some-package`. This error \
-                can occur due to an unstable network connection or an issue with the upstream Debian package \
-                repository.
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value \
+                `replace` for the key `existing_files` in `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! The value must be one of \"overwrite\", \"skip\", or \"error\".
                 !
                 ! Suggestions:
-                ! - Check the status of https://status.canonical.com/ for any reported issues.
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
                 !
                 ! Use the debug information above to troubleshoot and retry your build.
-                !
-                ! If the issue persists and you think you found a bug in the buildpack, reproduce the \
-                issue locally with a minimal example. Open an issue in the buildpack's GitHub repository \
-                and include the details here:
-                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
             "},
         );
     }
 
     #[test]
-    fn install_packages_error_write_package() {
-        test_error_output(
-            "
+    fn config_parse_config_error_for_invalid_imagemagick_policy() {
+        test_error_output("
                 Context
                 -------
-                Packages downloaded from a Debian repository are written directly to disk. Network I/O
-                can fail for any number of reasons but the most likely here would be a problem with the upstream.
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but imagemagick_policy isn't a path we report the invalid value
+                to the user and point them to the documentation.
             ",
-            InstallPackagesError::WritePackage(
-                repository_package("some-package"),
-                "https://test/error".to_string(),
-                "/path/to/layer/download-file".into(),
-                create_io_error("stream closed"),
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidImageMagickPolicy("true".to_string()),
             ),
             indoc! {"
-                - Debug Info:
-                  - stream closed
-
-                ! Failed to download package
+                ! Error parsing `/path/to/project.toml` with invalid imagemagick_policy
                 !
-                ! An unexpected I/O error occured while downloading `some-package` from https://test/error \
-                to `/path/to/layer/download-file`. This error can occur due to an unstable network connection or \
-                an issue with the upstream Debian package repository.
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value `true` \
+                for the key `imagemagick_policy` in `[com.heroku.buildpacks.deb-packages]`.
+                !
+                ! The value must be the path to a policy file relative to the application \
+                directory (e.g.; \"config/imagemagick-policy.xml\") that doesn't point outside of \
+                it.
                 !
                 ! Suggestions:
-                ! - Check the status of https://status.canonical.com/ for any reported issues.
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
                 !
                 ! Use the debug information above to troubleshoot and retry your build.
-                !
-                ! If the issue persists and you think you found a bug in the buildpack, reproduce the \
-                issue locally with a minimal example. Open an issue in the buildpack's GitHub repository \
-                and include the details here:
-                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
             "},
         );
     }
 
     #[test]
-    fn install_packages_error_checksum_failed() {
-        test_error_output(
-            "
+    fn config_parse_config_error_for_invalid_exclude() {
+        test_error_output("
                 Context
                 -------
-                Packages downloaded from a Debian repository are validated against a checksum given by
-                their owning package index. If this error happens then there was a problem with the
-                download. When this happens, running the build again typically fixes it.
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but exclude isn't an array of package patterns we report the
+                invalid value to the user and point them to the documentation.
             ",
-            InstallPackagesError::ChecksumFailed {
-                url: "http://archive.ubuntu.com/ubuntu/dists/jammy/some-package.tgz".to_string(),
-                expected: "7931f51fd704f93171f36f5f6f1d7b7b".into(),
-                actual: "19a47cdb280539511523382fa1cabbe5".to_string(),
-            },
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidExclude("\"*-doc\"".to_string()),
+            ),
             indoc! {"
-                ! Package checksum verification failed
+                ! Error parsing `/path/to/project.toml` with invalid exclude
                 !
-                ! An error occurred while verifying the checksum of the package at \
-                http://archive.ubuntu.com/ubuntu/dists/jammy/some-package.tgz. This error can occur due to an \
-                issue with the upstream Debian package repository.
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value \
+                `\"*-doc\"` for the key `exclude` in `[com.heroku.buildpacks.deb-packages]`.
                 !
-                ! Checksum:
-                ! - Expected: `7931f51fd704f93171f36f5f6f1d7b7b`
-                ! - Actual: `19a47cdb280539511523382fa1cabbe5`
+                ! The value must be an array of package patterns (e.g.; [\"*-doc\"]).
+                !
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
                 !
                 ! Use the debug information above to troubleshoot and retry your build.
             "},
@@ -2237,218 +8522,209 @@ mod tests {
     }
 
     #[test]
-    fn install_packages_error_open_package_archive() {
-        test_error_output(
-            "
+    fn config_parse_config_error_for_invalid_remove() {
+        test_error_output("
                 Context
                 -------
-                Packages downloaded from a Debian repository are stored as tarballs which need to be
-                opened for extraction. I/O can fail for any number of reasons but since the buildpack
-                process owns this content, there's nothing the user can do here.
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but remove isn't an array of package names we report the invalid
+                value to the user and point them to the documentation.
             ",
-            InstallPackagesError::OpenPackageArchive(
-                "/path/to/layer/archive-file.tgz".into(),
-                create_io_error("permission denied"),
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidRemove("\"not a package\"".to_string()),
             ),
             indoc! {"
-                - Debug Info:
-                  - permission denied
-
-                ! Failed to open package archive
+                ! Error parsing `/path/to/project.toml` with invalid remove
                 !
-                ! An unexpected I/O error occurred while trying to open the archive at `/path/to/layer/archive-file.tgz`.
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value `\"not \
+                a package\"` for the key `remove` in `[com.heroku.buildpacks.deb-packages]`.
                 !
-                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
-                a workaround at this time. You can help our understanding by sharing your buildpack log \
-                and a description of the issue at:
-                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                ! The value must be an array of package names (e.g.; [\"perl\"]).
                 !
-                ! If you're able to reproduce the problem with an example application and the `pack` \
-                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
-                adding that information to the discussion will also help. Once we have more information \
-                around the causes of this error we may update this message.
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
             "},
         );
     }
 
     #[test]
-    fn install_packages_error_open_package_archive_entry() {
-        test_error_output(
-            "
+    fn config_parse_config_error_for_invalid_target_codename() {
+        test_error_output("
                 Context
                 -------
-                Packages downloaded from a Debian repository are stored as tarballs which stores file
-                information in individual file entries which need to be read for extraction. I/O
-                can fail for any number of reasons but since the buildpack process owns this content,
-                there's nothing the user can do here.
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but target_codename isn't a codename we report the invalid value
+                to the user and point them to the documentation.
             ",
-            InstallPackagesError::OpenPackageArchiveEntry(
-                "/path/to/layer/archive-file.tgz".into(),
-                create_io_error("invalid header entry"),
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidTargetCodename("\"22.04\"".to_string()),
             ),
             indoc! {"
-                - Debug Info:
-                  - invalid header entry
-
-                ! Failed to read package archive entry
+                ! Error parsing `/path/to/project.toml` with invalid target_codename
                 !
-                ! An unexpected I/O error occurred while trying to read the entries of the archive at \
-                `/path/to/layer/archive-file.tgz`.
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid value \
+                `\"22.04\"` for the key `target_codename` in `[com.heroku.buildpacks.deb-packages]`.
                 !
-                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
-                a workaround at this time. You can help our understanding by sharing your buildpack log \
-                and a description of the issue at:
-                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                ! The value must be the codename of the distribution release the run image is \
+                built from (e.g.; \"jammy\").
                 !
-                ! If you're able to reproduce the problem with an example application and the `pack` \
-                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
-                adding that information to the discussion will also help. Once we have more information \
-                around the causes of this error we may update this message.
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
             "},
         );
     }
 
     #[test]
-    fn install_packages_error_unpack_tarball() {
+    fn config_parse_config_error_for_invalid_locale() {
         test_error_output(
             "
                 Context
                 -------
-                Packages downloaded from a Debian repository are stored as tarballs which need to be
-                extracted. This is done by iterating through each archive entry and writing it's
-                content out as a file to the file-system. I/O can fail for any number of reasons but
-                since the buildpack process owns this content, there's nothing the user can do here.
+                Each entry in locales must be a locale name with a codeset so we know which charmap
+                to compile it with. Invalid names are reported along with the expected format.
             ",
-            InstallPackagesError::UnpackTarball(
-                "/path/to/layer/archive-file.tgz".into(),
-                create_io_error("directory not empty"),
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::ParseLocale(ParseLocaleError::InvalidLocale("en_US".to_string())),
             ),
             indoc! {"
-                - Debug Info:
-                  - directory not empty
-
-                ! Failed to unpack package archive
+                ! Error parsing `/path/to/project.toml` with invalid locale
                 !
-                ! An unexpected I/O error occurred while trying to unpack the archive at `/path/to/layer/archive-file.tgz`.
+                ! The Heroku .deb Packages buildpack reads configuration from \
+                `/path/to/project.toml` to complete the build but we found an invalid locale \
+                `en_US` in the key `locales` in `[com.heroku.buildpacks.deb-packages]`.
                 !
-                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
-                a workaround at this time. You can help our understanding by sharing your buildpack log \
-                and a description of the issue at:
-                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                ! Locales must be TOML strings in the form language_TERRITORY.codeset[@modifier] \
+                (e.g.; \"en_US.UTF-8\"). The codeset is required.
                 !
-                ! If you're able to reproduce the problem with an example application and the `pack` \
-                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
-                adding that information to the discussion will also help. Once we have more information \
-                around the causes of this error we may update this message.
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
             "},
         );
     }
 
     #[test]
-    fn install_packages_error_unsupported_compression() {
-        test_error_output(
-            "
+    fn config_parse_config_error_for_invalid_strict_extraction() {
+        test_error_output("
                 Context
                 -------
-                This is a developer error. It should not be seen by an end-user. Packages from a Debian
-                repository can be stored using several different compression formats. Should we encounter
-                an unexpected one that we aren't handling then this is a buildpack bug.
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but strict_extraction isn't a boolean we report the invalid value
+                to the user and point them to the documentation.
             ",
-            InstallPackagesError::UnsupportedCompression(
-                "/path/to/layer/archive-file.tgz".into(),
-                "lz".to_string(),
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidStrictExtraction("\"yes\"".to_string()),
             ),
             indoc! {"
-                ! Unsupported compression format for package archive
+                ! Error parsing `/path/to/project.toml` with invalid strict_extraction
                 !
-                ! An unexpected compression format (`lz`) was used for the package archive at \
-                `/path/to/layer/archive-file.tgz`.
+                ! The Heroku .deb Packages buildpack reads configuration from `/path/to/project.toml` \
+                to complete the build but we found an invalid value `\"yes\"` for the key \
+                `strict_extraction` in `[com.heroku.buildpacks.deb-packages]`.
                 !
-                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
-                a workaround at this time. You can help our understanding by sharing your buildpack log \
-                and a description of the issue at:
-                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                ! The value must be a boolean (true or false).
                 !
-                ! If you're able to reproduce the problem with an example application and the `pack` \
-                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
-                adding that information to the discussion will also help. Once we have more information \
-                around the causes of this error we may update this message.
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
             "},
         );
     }
 
     #[test]
-    fn install_packages_error_read_package_config() {
-        test_error_output(
-            "
+    fn config_parse_config_error_for_invalid_subset_package_index() {
+        test_error_output("
                 Context
                 -------
-                After a package is extracted, we need to update any hardcoded references to it's standard
-                install location that might be referenced in any package-config (*.pc) files from the
-                package to reflect the install location within the layer directory by reading these files.
-                I/O can fail for any number of reasons but since the buildpack process owns this content,
-                there's nothing  the user can do here.
+                We read the buildpack configuration from project.toml which must be a valid TOML file.
+                If the file is valid but subset_package_index isn't a boolean we report the invalid value
+                to the user and point them to the documentation.
             ",
-            InstallPackagesError::ReadPackageConfig(
-                "/path/to/layer/pkgconfig/somepackage.pc".into(),
-                create_io_error("invalid filename"),
+            ConfigError::ParseConfig(
+                "/path/to/project.toml".into(),
+                ParseConfigError::InvalidSubsetPackageIndex("1".to_string()),
             ),
             indoc! {"
-                - Debug Info:
-                  - invalid filename
-
-                ! Failed to read package config file
+                ! Error parsing `/path/to/project.toml` with invalid subset_package_index
                 !
-                ! An unexpected I/O error occurred while reading the package config file at \
-                `/path/to/layer/pkgconfig/somepackage.pc`.
+                ! The Heroku .deb Packages buildpack reads configuration from `/path/to/project.toml` \
+                to complete the build but we found an invalid value `1` for the key \
+                `subset_package_index` in `[com.heroku.buildpacks.deb-packages]`.
                 !
-                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
-                a workaround at this time. You can help our understanding by sharing your buildpack log \
-                and a description of the issue at:
-                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                ! The value must be a boolean (true or false).
                 !
-                ! If you're able to reproduce the problem with an example application and the `pack` \
-                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
-                adding that information to the discussion will also help. Once we have more information \
-                around the causes of this error we may update this message.
+                ! Suggestions:
+                ! - See the buildpack documentation for the proper usage for this configuration at \
+                https://github.com/heroku/buildpacks-deb-packages#configuration
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
             "},
         );
     }
 
     #[test]
-    fn install_packages_error_write_package_config() {
+    fn install_packages_error_multiple_packages_failed() {
         test_error_output(
             "
                 Context
                 -------
-                After a package is extracted, we need to update any hardcoded references to it's standard
-                install location that might be referenced in any package-config (*.pc) files from the
-                package to reflect the install location within the layer directory by writing these files.
-                I/O can fail for any number of reasons but since the buildpack process owns this content,
-                there's nothing  the user can do here.
+                Packages are downloaded and extracted concurrently so more than one of them can fail
+                during the same build. Each failure is listed so the user doesn't have to fix them one
+                build at a time.
             ",
-            InstallPackagesError::WritePackageConfig(
-                "/path/to/layer/pkgconfig/somepackage.pc".into(),
-                create_io_error("operation interrupted"),
-            ),
+            InstallPackagesError::MultiplePackagesFailed(vec![
+                (
+                    "package-a".to_string(),
+                    InstallPackagesError::OpenPackageArchive(
+                        "/path/to/layer/package-a.deb".into(),
+                        create_io_error("permission denied"),
+                    ),
+                ),
+                (
+                    "package-b".to_string(),
+                    InstallPackagesError::ChecksumFailed {
+                        url: "http://archive.ubuntu.com/ubuntu/pool/main/package-b.deb".to_string(),
+                        expected: "7931f51fd8f9db7d1ed3c4d1d0bd6b2b".to_string(),
+                        actual: "bd15b2bd0d1d4c3ed1d7bd9f8df15f1a".to_string(),
+                    },
+                ),
+            ]),
             indoc! {"
-                - Debug Info:
-                  - operation interrupted
-
-                ! Failed to write package config file
+                ! Failed to install multiple packages
                 !
-                ! An unexpected I/O error occurred while writing the package config file to \
-                `/path/to/layer/pkgconfig/somepackage.pc`.
+                ! While installing packages, errors occurred for each of the following packages. \
+                This can occur due to an unstable network connection or an issue with the upstream \
+                Debian package repository.
                 !
-                ! The causes for this error are unknown. We do not have suggestions for diagnosis or \
-                a workaround at this time. You can help our understanding by sharing your buildpack log \
-                and a description of the issue at:
-                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
+                ! Failed packages:
+                ! - `package-a`: Failed to open package archive (permission denied)
+                ! - `package-b`: Package checksum verification failed
                 !
-                ! If you're able to reproduce the problem with an example application and the `pack` \
-                build tool (https://buildpacks.io/docs/for-platform-operators/how-to/integrate-ci/pack/), \
-                adding that information to the discussion will also help. Once we have more information \
-                around the causes of this error we may update this message.
+                ! Suggestions:
+                ! - Check the status of https://status.canonical.com/ for any reported issues.
+                !
+                ! Use the debug information above to troubleshoot and retry your build.
+                !
+                ! If the issue persists and you think you found a bug in the buildpack, reproduce the \
+                issue locally with a minimal example. Open an issue in the buildpack's GitHub repository \
+                and include the details here:
+                ! https://github.com/heroku/buildpacks-deb-packages/issues/new
             "},
         );
     }
@@ -2560,15 +8836,10 @@ mod tests {
     }
 
     fn repository_package(package_name: &str) -> RepositoryPackage {
-        RepositoryPackage {
-            name: package_name.to_string(),
-            version: "1.0.0".to_string(),
-            filename: format!("{package_name}.tgz"),
-            repository_uri: RepositoryUri::from("https://test/path/to/repository"),
-            sha256sum: String::new(),
-            depends: None,
-            pre_depends: None,
-            provides: None,
-        }
+        create_repository_package()
+            .name(package_name)
+            .filename(&format!("{package_name}.tgz"))
+            .repository_uri("https://test/path/to/repository")
+            .call()
     }
 }
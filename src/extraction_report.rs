@@ -0,0 +1,231 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::Stdout;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Component, Path, PathBuf};
+
+use bullet_stream::state::Bullet;
+use bullet_stream::{style, Print};
+
+// Files that were extracted into the layer but may not work once the layer is mounted somewhere
+// other than the root filesystem the package was built for.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum ExtractionIssue {
+    WorldWritable(PathBuf),
+    BrokenSymlink { path: PathBuf, target: PathBuf },
+    // links into /etc/alternatives for an alternative that couldn't be emulated (e.g.; the
+    // update-alternatives call used shell variables)
+    DanglingAlternative { path: PathBuf, target: PathBuf },
+}
+
+impl Display for ExtractionIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtractionIssue::WorldWritable(path) => {
+                write!(f, "world-writable {}", path.display())
+            }
+            ExtractionIssue::BrokenSymlink { path, target } => write!(
+                f,
+                "broken symlink {} -> {}",
+                path.display(),
+                target.display()
+            ),
+            ExtractionIssue::DanglingAlternative { path, target } => write!(
+                f,
+                "dangling alternative {} -> {}",
+                path.display(),
+                target.display()
+            ),
+        }
+    }
+}
+
+// Inspects the files extracted for each package once every package has been extracted (symlinks
+// often point to files from another package). Paths shared by several packages, like directories,
+// are only reported for the first package.
+pub(crate) fn find_extraction_issues(
+    install_dir: &Path,
+    extracted_files: &BTreeMap<String, Vec<PathBuf>>,
+) -> BTreeMap<String, Vec<ExtractionIssue>> {
+    let mut inspected = HashSet::new();
+    let mut extraction_issues = BTreeMap::new();
+
+    for (package_name, files) in extracted_files {
+        let package_issues = files
+            .iter()
+            .map(|file| normalize(file))
+            .filter(|file| !file.as_os_str().is_empty() && inspected.insert(file.clone()))
+            .filter_map(|file| inspect_file(install_dir, &file))
+            .collect::<Vec<_>>();
+
+        if !package_issues.is_empty() {
+            extraction_issues.insert(package_name.clone(), package_issues);
+        }
+    }
+
+    extraction_issues
+}
+
+pub(crate) fn print_extraction_issues(
+    extraction_issues: &BTreeMap<String, Vec<ExtractionIssue>>,
+    log: Print<Bullet<Stdout>>,
+) -> Print<Bullet<Stdout>> {
+    if extraction_issues.is_empty() {
+        return log;
+    }
+
+    extraction_issues
+        .iter()
+        .fold(
+            log.bullet("Found files that may not work when relocated"),
+            |log, (package_name, package_issues)| {
+                package_issues.iter().fold(log, |log, extraction_issue| {
+                    log.sub_bullet(format!(
+                        "{package}: {extraction_issue}",
+                        package = style::value(package_name)
+                    ))
+                })
+            },
+        )
+        .done()
+}
+
+fn inspect_file(install_dir: &Path, file: &Path) -> Option<ExtractionIssue> {
+    let path = install_dir.join(file);
+    let metadata = fs::symlink_metadata(&path).ok()?;
+    let display_path = Path::new("/").join(file);
+
+    if metadata.file_type().is_symlink() {
+        let target = fs::read_link(&path).ok()?;
+        // absolute targets are resolved inside the layer since that's where the package was
+        // installed instead of the root filesystem
+        let resolved_target = match target.strip_prefix("/") {
+            Ok(absolute_target) => install_dir.join(absolute_target),
+            Err(_) => path.parent()?.join(&target),
+        };
+        if resolved_target.exists() {
+            None
+        } else if target.starts_with("/etc/alternatives") {
+            Some(ExtractionIssue::DanglingAlternative {
+                path: display_path,
+                target,
+            })
+        } else {
+            Some(ExtractionIssue::BrokenSymlink {
+                path: display_path,
+                target,
+            })
+        }
+    } else {
+        let mode = metadata.permissions().mode();
+        let world_writable = mode & 0o002 != 0;
+        // directories like /tmp are expected to be world-writable when the sticky bit is set
+        let sticky_dir = metadata.is_dir() && mode & 0o1000 != 0;
+        (world_writable && !sticky_dir).then_some(ExtractionIssue::WorldWritable(display_path))
+    }
+}
+
+// tar entries are usually relative to the current directory (e.g.; ./usr/bin/git)
+fn normalize(file: &Path) -> PathBuf {
+    file.components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::symlink;
+
+    use super::*;
+
+    #[test]
+    fn test_find_extraction_issues() {
+        let install_dir = tempfile::tempdir().unwrap();
+        let install_path = install_dir.path();
+        fs::create_dir_all(install_path.join("usr/bin")).unwrap();
+        fs::create_dir_all(install_path.join("usr/lib")).unwrap();
+        fs::create_dir_all(install_path.join("tmp")).unwrap();
+        fs::write(install_path.join("usr/bin/tool"), "").unwrap();
+        fs::write(install_path.join("usr/lib/libtool.so.1"), "").unwrap();
+        fs::set_permissions(
+            install_path.join("usr/bin/tool"),
+            fs::Permissions::from_mode(0o777),
+        )
+        .unwrap();
+        fs::set_permissions(install_path.join("tmp"), fs::Permissions::from_mode(0o1777)).unwrap();
+        symlink("libtool.so.1", install_path.join("usr/lib/libtool.so")).unwrap();
+        symlink(
+            "libmissing.so.1",
+            install_path.join("usr/lib/libmissing.so"),
+        )
+        .unwrap();
+        symlink(
+            "/usr/lib/libtool.so.1",
+            install_path.join("usr/lib/libabsolute.so"),
+        )
+        .unwrap();
+        symlink(
+            "/etc/alternatives/editor",
+            install_path.join("usr/bin/editor"),
+        )
+        .unwrap();
+
+        let extracted_files = BTreeMap::from([
+            (
+                "tool".to_string(),
+                vec![
+                    PathBuf::from("./"),
+                    PathBuf::from("./tmp/"),
+                    PathBuf::from("./usr/bin/tool"),
+                    PathBuf::from("./usr/bin/editor"),
+                ],
+            ),
+            (
+                "libtool1".to_string(),
+                vec![
+                    PathBuf::from("./usr/bin/tool"),
+                    PathBuf::from("./usr/lib/libtool.so.1"),
+                    PathBuf::from("./usr/lib/libtool.so"),
+                    PathBuf::from("./usr/lib/libabsolute.so"),
+                    PathBuf::from("./usr/lib/libmissing.so"),
+                ],
+            ),
+        ]);
+
+        assert_eq!(
+            find_extraction_issues(install_path, &extracted_files),
+            BTreeMap::from([
+                (
+                    "libtool1".to_string(),
+                    vec![
+                        ExtractionIssue::WorldWritable(PathBuf::from("/usr/bin/tool")),
+                        ExtractionIssue::BrokenSymlink {
+                            path: PathBuf::from("/usr/lib/libmissing.so"),
+                            target: PathBuf::from("libmissing.so.1"),
+                        }
+                    ]
+                ),
+                (
+                    "tool".to_string(),
+                    vec![ExtractionIssue::DanglingAlternative {
+                        path: PathBuf::from("/usr/bin/editor"),
+                        target: PathBuf::from("/etc/alternatives/editor"),
+                    }]
+                )
+            ])
+        );
+    }
+
+    #[test]
+    fn test_extraction_issue_display() {
+        assert_eq!(
+            ExtractionIssue::BrokenSymlink {
+                path: PathBuf::from("/usr/lib/libmissing.so"),
+                target: PathBuf::from("libmissing.so.1"),
+            }
+            .to_string(),
+            "broken symlink /usr/lib/libmissing.so -> libmissing.so.1"
+        );
+    }
+}
@@ -0,0 +1,414 @@
+use std::collections::{BTreeMap, HashSet};
+use std::io::{ErrorKind, Stdout};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bullet_stream::state::Bullet;
+use bullet_stream::{style, Print};
+use libcnb::build::BuildContext;
+use libcnb::data::layer_name;
+use libcnb::layer::{
+    CachedLayerDefinition, InvalidMetadataAction, LayerState, RestoredLayerAction,
+};
+use reqwest_middleware::ClientWithMiddleware;
+use sequoia_openpgp::policy::StandardPolicy;
+use sequoia_openpgp::Cert;
+use serde::{Deserialize, Serialize};
+use tokio::fs::{read_dir, read_to_string, remove_file, write};
+
+use crate::config::CustomSource;
+use crate::debian::RepositoryUri;
+use crate::instrumentation::record_cache_event;
+use crate::keyserver::{self, check_signing_key, KeyserverError};
+use crate::{BuildpackResult, DebianPackagesBuildpack, DebianPackagesBuildpackError};
+
+// Downloaded keys are cached by fingerprint so later builds don't depend on the keyserver being
+// available. The fingerprint recorded for each source is kept so a changed key can be reported.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub(crate) struct SigningKeysMetadata {
+    // keyed by `source_key` since sources sharing a URI can use a different key for each suite
+    source_fingerprints: BTreeMap<String, String>,
+}
+
+// Sources configured with `key_fingerprint` have their signing key downloaded from the keyserver
+// and stored in `signed_by` so the Release file is verified with it the same as an inline key.
+#[allow(clippy::too_many_lines)]
+pub(crate) async fn fetch_signing_keys(
+    context: &Arc<BuildContext<DebianPackagesBuildpack>>,
+    client: &ClientWithMiddleware,
+    sources: &mut [CustomSource],
+    log: Print<Bullet<Stdout>>,
+) -> BuildpackResult<Print<Bullet<Stdout>>> {
+    let source_fingerprints = source_fingerprints(sources);
+    if source_fingerprints.is_empty() {
+        return Ok(log);
+    }
+
+    // the layer is always kept since keys are cached by fingerprint, the previous fingerprints
+    // are only needed to report which sources now use a different key
+    let signing_keys_layer = context.cached_layer(
+        layer_name!("signing_keys"),
+        CachedLayerDefinition {
+            build: false,
+            launch: false,
+            invalid_metadata_action: &|_| InvalidMetadataAction::DeleteLayer,
+            restored_layer_action: &|old_metadata: &SigningKeysMetadata, _| {
+                (
+                    RestoredLayerAction::KeepLayer,
+                    old_metadata.source_fingerprints.clone(),
+                )
+            },
+        },
+    )?;
+    let previous_fingerprints = match &signing_keys_layer.state {
+        LayerState::Restored { cause } => {
+            record_cache_event("signing_keys", true);
+            cause.clone()
+        }
+        LayerState::Empty { .. } => {
+            record_cache_event("signing_keys", false);
+            BTreeMap::new()
+        }
+    };
+    let signing_keys_dir = signing_keys_layer.path();
+    let now = SystemTime::now();
+
+    let mut log = log.bullet("Fetching signing keys");
+    for source in sources.iter_mut() {
+        let Some(fingerprint) = source.key_fingerprint.clone() else {
+            continue;
+        };
+        let uri = source.uri.as_str();
+
+        if let Some(previous_fingerprint) = previous_fingerprints.get(&source_key(source)) {
+            if !previous_fingerprint.eq_ignore_ascii_case(&fingerprint) {
+                log = log.warning(format!(
+                    "The signing key for {uri} ({suites}) changed from {previous_fingerprint} to \
+                    {fingerprint}",
+                    uri = style::url(uri),
+                    suites = source.suites.join(", "),
+                    previous_fingerprint = style::value(previous_fingerprint),
+                    fingerprint = style::value(&fingerprint)
+                ));
+            }
+        }
+
+        let signing_key_path = signing_key_path(&signing_keys_dir, &fingerprint);
+        let cached_signing_key = read_cached_signing_key(&signing_key_path, &fingerprint).await;
+        let cached_expiration = cached_signing_key
+            .as_deref()
+            .and_then(|signing_key| signing_key_expiration(signing_key, now));
+
+        let signing_key = match (cached_signing_key, cached_expiration) {
+            (Some(signing_key), None) => {
+                log = log.sub_bullet(format!(
+                    "Reusing {fingerprint} for {uri}",
+                    fingerprint = style::value(&fingerprint),
+                    uri = style::url(uri)
+                ));
+                signing_key
+            }
+            (cached_signing_key, cached_expiration) => {
+                let signing_key = keyserver::get_signing_key(client, &fingerprint)
+                    .await
+                    .map_err(|e| {
+                        FetchSigningKeysError::GetSigningKey(
+                            source.uri.clone(),
+                            fingerprint.clone(),
+                            e,
+                        )
+                    })?;
+                write(&signing_key_path, &signing_key).await.map_err(|e| {
+                    FetchSigningKeysError::WriteSigningKey(signing_key_path.clone(), e)
+                })?;
+                log = log.sub_bullet(match (cached_signing_key, cached_expiration) {
+                    (Some(_), Some(expiration)) => format!(
+                        "Downloaded {fingerprint} for {uri} again since the cached copy expired on {date}",
+                        fingerprint = style::value(&fingerprint),
+                        uri = style::url(uri),
+                        date = format_date(expiration)
+                    ),
+                    _ => format!(
+                        "Downloaded {fingerprint} for {uri}",
+                        fingerprint = style::value(&fingerprint),
+                        uri = style::url(uri)
+                    ),
+                });
+                if let Some(expiration) = signing_key_expiration(&signing_key, now) {
+                    log = log.warning(format!(
+                        "The signing key {fingerprint} for {uri} expired on {date}, the Release file may fail verification",
+                        fingerprint = style::value(&fingerprint),
+                        uri = style::url(uri),
+                        date = format_date(expiration)
+                    ));
+                }
+                signing_key
+            }
+        };
+        source.signed_by = Some(signing_key);
+    }
+
+    log = match prune_signing_keys(&signing_keys_dir, &source_fingerprints).await {
+        Ok(0) => log,
+        Ok(removed) => log.sub_bullet(format!(
+            "Removed {removed} unused signing {keys} from the cache",
+            keys = if removed == 1 { "key" } else { "keys" }
+        )),
+        Err(e) => log.warning(format!(
+            "Failed to remove unused signing keys from the cache: {e}"
+        )),
+    };
+
+    signing_keys_layer.write_metadata(SigningKeysMetadata {
+        source_fingerprints,
+    })?;
+
+    Ok(log.done())
+}
+
+fn source_fingerprints(sources: &[CustomSource]) -> BTreeMap<String, String> {
+    sources
+        .iter()
+        .filter_map(|source| {
+            source
+                .key_fingerprint
+                .clone()
+                .map(|fingerprint| (source_key(source), fingerprint))
+        })
+        .collect()
+}
+
+// Identifies a source by its URI and suites the same way as a line in `sources.list`.
+fn source_key(source: &CustomSource) -> String {
+    format!("{} {}", source.uri.as_str(), source.suites.join(" "))
+}
+
+fn signing_key_path(signing_keys_dir: &Path, fingerprint: &str) -> PathBuf {
+    signing_keys_dir.join(format!("{fingerprint}.asc"))
+}
+
+// A cached key is verified again so a damaged file is downloaded instead of failing the build.
+async fn read_cached_signing_key(signing_key_path: &Path, fingerprint: &str) -> Option<String> {
+    let signing_key = read_to_string(signing_key_path).await.ok()?;
+    check_signing_key(fingerprint, &signing_key)
+        .ok()
+        .map(|()| signing_key)
+}
+
+// Returns when the key expired if it has already expired at `now`. A key that can't be parsed is
+// treated as not expired since it was already checked against its fingerprint.
+fn signing_key_expiration(signing_key: &str, now: SystemTime) -> Option<SystemTime> {
+    let cert = Cert::from_str(signing_key).ok()?;
+    let policy = StandardPolicy::new();
+    let valid_cert = cert.with_policy(&policy, now).ok()?;
+    if valid_cert.alive().is_ok() {
+        return None;
+    }
+    valid_cert
+        .primary_key()
+        .key_expiration_time()
+        .filter(|expiration| *expiration <= now)
+}
+
+fn format_date(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .ok()
+        .and_then(|duration| {
+            chrono::DateTime::from_timestamp(i64::try_from(duration.as_secs()).ok()?, 0)
+        })
+        .map_or_else(
+            || "an unknown date".to_string(),
+            |date| date.format("%Y-%m-%d").to_string(),
+        )
+}
+
+// Keys for fingerprints that are no longer configured are removed so the layer doesn't grow.
+async fn prune_signing_keys(
+    signing_keys_dir: &Path,
+    source_fingerprints: &BTreeMap<String, String>,
+) -> std::io::Result<usize> {
+    let keep = source_fingerprints
+        .values()
+        .map(|fingerprint| signing_key_path(signing_keys_dir, fingerprint))
+        .collect::<HashSet<_>>();
+
+    let mut entries = match read_dir(signing_keys_dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+
+    let mut removed = 0;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let is_signing_key = path.extension().is_some_and(|extension| extension == "asc");
+        if is_signing_key && !keep.contains(&path) {
+            remove_file(&path).await?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+#[derive(Debug)]
+pub(crate) enum FetchSigningKeysError {
+    GetSigningKey(RepositoryUri, String, KeyserverError),
+    WriteSigningKey(PathBuf, std::io::Error),
+}
+
+impl From<FetchSigningKeysError> for libcnb::Error<DebianPackagesBuildpackError> {
+    fn from(value: FetchSigningKeysError) -> Self {
+        Self::BuildpackError(DebianPackagesBuildpackError::FetchSigningKeys(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use sequoia_openpgp::cert::CertBuilder;
+    use sequoia_openpgp::serialize::SerializeInto;
+
+    use crate::debian::SignaturePolicy;
+
+    use super::*;
+
+    const SIGNED_BY: &str = include_str!("../keys/ubuntu_24.04.asc");
+
+    #[tokio::test]
+    async fn test_read_cached_signing_key() {
+        let signing_keys_dir = tempfile::tempdir().unwrap();
+        let fingerprint = Cert::from_str(SIGNED_BY).unwrap().fingerprint().to_hex();
+        let signing_key_path = signing_key_path(signing_keys_dir.path(), &fingerprint);
+
+        assert_eq!(
+            read_cached_signing_key(&signing_key_path, &fingerprint).await,
+            None
+        );
+
+        write(&signing_key_path, SIGNED_BY).await.unwrap();
+        assert_eq!(
+            read_cached_signing_key(&signing_key_path, &fingerprint).await,
+            Some(SIGNED_BY.to_string())
+        );
+        assert_eq!(
+            read_cached_signing_key(
+                &signing_key_path,
+                "F23C5A6CF475977595C89F51BA6932366A755776"
+            )
+            .await,
+            None
+        );
+    }
+
+    #[test]
+    fn test_signing_key_expiration() {
+        let created = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let (cert, _) = CertBuilder::general_purpose(None, Some("Example <repo@example.com>"))
+            .set_creation_time(created)
+            .set_validity_period(Some(Duration::from_secs(86_400)))
+            .generate()
+            .unwrap();
+        let signing_key = String::from_utf8(cert.armored().to_vec().unwrap()).unwrap();
+
+        assert_eq!(
+            signing_key_expiration(&signing_key, created + Duration::from_secs(3_600)),
+            None
+        );
+        assert_eq!(
+            signing_key_expiration(&signing_key, created + Duration::from_secs(2 * 86_400)),
+            Some(created + Duration::from_secs(86_400))
+        );
+        assert_eq!(signing_key_expiration(SIGNED_BY, SystemTime::now()), None);
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(
+            format_date(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            "2023-11-14"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prune_signing_keys() {
+        let signing_keys_dir = tempfile::tempdir().unwrap();
+        let fingerprint = Cert::from_str(SIGNED_BY).unwrap().fingerprint().to_hex();
+        let unused_fingerprint = "F23C5A6CF475977595C89F51BA6932366A755776";
+        let other_file = signing_keys_dir.path().join("notes.txt");
+        for path in [
+            signing_key_path(signing_keys_dir.path(), &fingerprint),
+            signing_key_path(signing_keys_dir.path(), unused_fingerprint),
+            other_file.clone(),
+        ] {
+            write(&path, SIGNED_BY).await.unwrap();
+        }
+
+        let source_fingerprints = BTreeMap::from([(
+            "http://archive.ubuntu.com/ubuntu noble".to_string(),
+            fingerprint.clone(),
+        )]);
+        assert_eq!(
+            prune_signing_keys(signing_keys_dir.path(), &source_fingerprints)
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(signing_key_path(signing_keys_dir.path(), &fingerprint).exists());
+        assert!(!signing_key_path(signing_keys_dir.path(), unused_fingerprint).exists());
+        assert!(other_file.exists());
+
+        assert_eq!(
+            prune_signing_keys(
+                &signing_keys_dir.path().join("missing"),
+                &source_fingerprints
+            )
+            .await
+            .unwrap(),
+            0
+        );
+    }
+
+    fn custom_source(uri: &str, suite: &str, key_fingerprint: Option<&str>) -> CustomSource {
+        CustomSource {
+            uri: RepositoryUri::from(uri),
+            suites: vec![suite.to_string()],
+            components: vec!["main".to_string()],
+            signed_by: None,
+            signed_by_file: None,
+            key_fingerprint: key_fingerprint.map(ToString::to_string),
+            signature_policy: SignaturePolicy::Required,
+            trusted: false,
+            allow_weak_hashes: false,
+            client_identity: None,
+            mirrors: vec![],
+            download_url: None,
+            architectures: vec![],
+        }
+    }
+
+    #[test]
+    fn test_source_fingerprints_are_kept_for_each_suite() {
+        let sources = [
+            custom_source("https://repo.example.com/apt", "stable", Some("AAAA")),
+            custom_source("https://repo.example.com/apt", "legacy", Some("BBBB")),
+            custom_source("https://other.example.com/apt", "stable", None),
+        ];
+
+        assert_eq!(
+            source_fingerprints(&sources),
+            BTreeMap::from([
+                (
+                    "https://repo.example.com/apt legacy".to_string(),
+                    "BBBB".to_string()
+                ),
+                (
+                    "https://repo.example.com/apt stable".to_string(),
+                    "AAAA".to_string()
+                ),
+            ])
+        );
+    }
+}
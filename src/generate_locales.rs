@@ -0,0 +1,121 @@
+use std::fs::create_dir_all;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::Locale;
+
+// The locales package normally compiles the locales selected in `/etc/locale.gen` from its
+// postinst script by calling `locale-gen` which writes to the system locale archive. Since that
+// can't run against the layer, the requested locales are compiled with `localedef` into a
+// directory in the layer that's exported as `LOCPATH`.
+pub(crate) fn locale_path(install_path: &Path) -> PathBuf {
+    install_path.join("usr/lib/locale")
+}
+
+// Returns each requested locale along with whether it was compiled in this build (locales restored
+// with a cached layer are reused).
+pub(crate) fn generate_locales(
+    install_path: &Path,
+    locales: &[Locale],
+) -> Result<Vec<(Locale, bool)>, GenerateLocalesError> {
+    let i18n_dir = find_i18n_dir(&[
+        install_path.join("usr/share/i18n"),
+        PathBuf::from("/usr/share/i18n"),
+    ])
+    .ok_or(GenerateLocalesError::LocalesNotInstalled)?;
+
+    let locale_path = locale_path(install_path);
+    create_dir_all(&locale_path)
+        .map_err(|e| GenerateLocalesError::CreateLocaleDir(locale_path.clone(), e))?;
+
+    let mut generated_locales = vec![];
+    for locale in locales {
+        let output_dir = locale_path.join(locale.directory_name());
+        if is_compiled_locale(&output_dir) {
+            generated_locales.push((locale.clone(), false));
+            continue;
+        }
+
+        let output = Command::new("localedef")
+            .arg("--no-archive")
+            .arg("--force")
+            .args(["--inputfile", &locale.input()])
+            .args(["--charmap", locale.charmap()])
+            .arg(&output_dir)
+            .env("I18NPATH", &i18n_dir)
+            .output()
+            .map_err(|e| GenerateLocalesError::RunLocaledef(Box::new(locale.clone()), e))?;
+
+        // with `--force`, localedef still writes the locale when the definition has warnings but
+        // exits with a non-zero status so the output is checked instead
+        if !is_compiled_locale(&output_dir) {
+            Err(GenerateLocalesError::LocaledefFailed(
+                Box::new(locale.clone()),
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ))?;
+        }
+        generated_locales.push((locale.clone(), true));
+    }
+
+    Ok(generated_locales)
+}
+
+// The locale definitions and charmaps are provided by the locales package which may be installed
+// into the layer or already be on the base image.
+fn find_i18n_dir(candidates: &[PathBuf]) -> Option<PathBuf> {
+    candidates
+        .iter()
+        .find(|dir| dir.join("locales").is_dir())
+        .cloned()
+}
+
+fn is_compiled_locale(locale_dir: &Path) -> bool {
+    locale_dir.join("LC_CTYPE").exists()
+}
+
+#[derive(Debug)]
+pub(crate) enum GenerateLocalesError {
+    LocalesNotInstalled,
+    CreateLocaleDir(PathBuf, std::io::Error),
+    RunLocaledef(Box<Locale>, std::io::Error),
+    LocaledefFailed(Box<Locale>, String),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_find_i18n_dir() {
+        let install_dir = tempfile::tempdir().unwrap();
+        let layer_i18n_dir = install_dir.path().join("layer/usr/share/i18n");
+        let system_i18n_dir = install_dir.path().join("usr/share/i18n");
+        fs::create_dir_all(system_i18n_dir.join("locales")).unwrap();
+
+        let candidates = [layer_i18n_dir.clone(), system_i18n_dir.clone()];
+        assert_eq!(find_i18n_dir(&candidates), Some(system_i18n_dir));
+
+        fs::create_dir_all(layer_i18n_dir.join("locales")).unwrap();
+        assert_eq!(find_i18n_dir(&candidates), Some(layer_i18n_dir));
+
+        assert_eq!(find_i18n_dir(&[install_dir.path().join("missing")]), None);
+    }
+
+    #[test]
+    fn test_generate_locales_reuses_compiled_locales() {
+        let install_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(install_dir.path().join("usr/share/i18n/locales")).unwrap();
+        let compiled_locale_dir = locale_path(install_dir.path()).join("en_US.utf8");
+        fs::create_dir_all(&compiled_locale_dir).unwrap();
+        fs::write(compiled_locale_dir.join("LC_CTYPE"), "").unwrap();
+
+        let locale = Locale::from_str("en_US.UTF-8").unwrap();
+        assert_eq!(
+            generate_locales(install_dir.path(), std::slice::from_ref(&locale)).unwrap(),
+            vec![(locale, false)]
+        );
+    }
+}
@@ -0,0 +1,749 @@
+use std::collections::HashSet;
+use std::io::ErrorKind;
+use std::io::Stdout;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use bullet_stream::state::Bullet;
+use bullet_stream::Print;
+use chrono::DateTime;
+use http::{Extensions, HeaderMap, HeaderName, HeaderValue, StatusCode};
+use libcnb::build::BuildContext;
+use libcnb::data::layer_name;
+use libcnb::layer::{
+    CachedLayerDefinition, InvalidMetadataAction, LayerState, RestoredLayerAction,
+};
+use reqwest::header::{
+    AGE, CACHE_CONTROL, CONTENT_LENGTH, DATE, ETAG, EXPIRES, IF_MATCH, IF_MODIFIED_SINCE,
+    IF_NONE_MATCH, IF_UNMODIFIED_SINCE, LAST_MODIFIED, RANGE, TRANSFER_ENCODING, VARY,
+};
+use reqwest::{Method, Request, Response, ResponseBuilderExt, Url};
+use reqwest_middleware::{Middleware, Next};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::fs::{read, read_dir, remove_file, write};
+
+use crate::instrumentation::record_cache_event;
+use crate::{BuildpackResult, DebianPackagesBuildpack};
+
+// Responses are stored in a cache-only layer so they can be reused by later builds. Bumping the
+// layout version discards the responses stored by an older version of the buildpack.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub(crate) struct HttpCacheMetadata {
+    layout_version: u32,
+}
+
+const HTTP_CACHE_LAYOUT_VERSION: u32 = 1;
+
+// Package indexes are already cached by their hash in their own layers and are parsed while
+// they're downloaded so only responses up to this size (e.g.; Release files) are buffered and
+// stored. Larger responses, or ones without a `Content-Length`, are passed through untouched.
+pub(crate) const MAX_CACHED_RESPONSE_SIZE: u64 = 1024 * 1024;
+
+const ENTRY_EXTENSION: &str = "json";
+const BODY_EXTENSION: &str = "body";
+
+pub(crate) fn http_cache_layer(
+    context: &BuildContext<DebianPackagesBuildpack>,
+) -> BuildpackResult<PathBuf> {
+    let new_metadata = HttpCacheMetadata {
+        layout_version: HTTP_CACHE_LAYOUT_VERSION,
+    };
+
+    let http_cache_layer = context.cached_layer(
+        layer_name!("http_cache"),
+        CachedLayerDefinition {
+            build: false,
+            launch: false,
+            invalid_metadata_action: &|_| InvalidMetadataAction::DeleteLayer,
+            restored_layer_action: &|old_metadata: &HttpCacheMetadata, _| {
+                if old_metadata == &new_metadata {
+                    RestoredLayerAction::KeepLayer
+                } else {
+                    RestoredLayerAction::DeleteLayer
+                }
+            },
+        },
+    )?;
+
+    match http_cache_layer.state {
+        LayerState::Restored { .. } => record_cache_event("http_cache", true),
+        LayerState::Empty { .. } => {
+            record_cache_event("http_cache", false);
+            http_cache_layer.write_metadata(new_metadata)?;
+        }
+    }
+
+    Ok(http_cache_layer.path())
+}
+
+// A private HTTP cache (https://www.rfc-editor.org/rfc/rfc9111) for the requests made while
+// building the package index. Stored responses are reused without contacting the repository while
+// they're fresh according to their `Cache-Control` or `Expires` headers and are revalidated with
+// their `ETag` or `Last-Modified` validators once they're stale, so an unchanged file only costs a
+// `304 Not Modified` response. This is registered before the retry, mirror, and authentication
+// middleware so fresh responses are never sent. Failing to read or write the cache is never an
+// error since the request can always be sent instead.
+#[derive(Debug)]
+pub(crate) struct HttpCacheMiddleware {
+    cache_dir: PathBuf,
+    state: Mutex<HttpCacheState>,
+}
+
+#[derive(Debug, Default)]
+struct HttpCacheState {
+    used_entries: HashSet<String>,
+    fresh_responses: usize,
+    revalidated_responses: usize,
+}
+
+impl HttpCacheMiddleware {
+    pub(crate) fn new(cache_dir: PathBuf) -> Self {
+        HttpCacheMiddleware {
+            cache_dir,
+            state: Mutex::default(),
+        }
+    }
+
+    fn update_state(&self, f: impl FnOnce(&mut HttpCacheState)) {
+        f(&mut self
+            .state
+            .lock()
+            .expect("The HTTP cache lock shouldn't be poisoned"));
+    }
+
+    // Removes the stored responses that weren't requested by this build (e.g.; the Release files of
+    // a source that was removed) so the cache doesn't grow with every configuration change. Returns
+    // the number of responses removed.
+    pub(crate) async fn prune(&self) -> std::io::Result<usize> {
+        let used_entries = self
+            .state
+            .lock()
+            .expect("The HTTP cache lock shouldn't be poisoned")
+            .used_entries
+            .clone();
+
+        let mut entries = match read_dir(&self.cache_dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+
+        let mut removed = 0;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let Some(key) = path.file_stem().and_then(|file_stem| file_stem.to_str()) else {
+                continue;
+            };
+            if used_entries.contains(key) {
+                continue;
+            }
+            match path.extension().and_then(|extension| extension.to_str()) {
+                Some(ENTRY_EXTENSION) => {
+                    remove_file(&path).await?;
+                    removed += 1;
+                }
+                Some(BODY_EXTENSION) => remove_file(&path).await?,
+                _ => {}
+            }
+        }
+        Ok(removed)
+    }
+
+    async fn read_entry(&self, key: &str) -> Option<(CachedResponse, Vec<u8>)> {
+        let entry = read(self.cache_dir.join(format!("{key}.{ENTRY_EXTENSION}")))
+            .await
+            .ok()
+            .and_then(|entry| serde_json::from_slice::<CachedResponse>(&entry).ok())?;
+        let body = read(self.cache_dir.join(format!("{key}.{BODY_EXTENSION}")))
+            .await
+            .ok()?;
+        Some((entry, body))
+    }
+
+    // The body is written first so an entry is never found without one.
+    async fn write_entry(&self, key: &str, entry: &CachedResponse, body: Option<&[u8]>) {
+        if let Some(body) = body {
+            if write(self.cache_dir.join(format!("{key}.{BODY_EXTENSION}")), body)
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+        if let Ok(entry) = serde_json::to_vec_pretty(entry) {
+            let _ = write(
+                self.cache_dir.join(format!("{key}.{ENTRY_EXTENSION}")),
+                entry,
+            )
+            .await;
+        }
+    }
+
+    async fn store(
+        &self,
+        key: &str,
+        response: Response,
+        now: u64,
+    ) -> reqwest_middleware::Result<Response> {
+        // a response that can't be stored replaces the stored one (e.g.; it's now `no-store`)
+        if !is_storable(&response) {
+            for extension in [ENTRY_EXTENSION, BODY_EXTENSION] {
+                let _ = remove_file(self.cache_dir.join(format!("{key}.{extension}"))).await;
+            }
+            return Ok(response);
+        }
+        let status = response.status();
+        let url = response.url().clone();
+        let headers = response.headers().clone();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| reqwest_middleware::Error::Reqwest(e.with_url(url.clone())))?;
+        let entry = CachedResponse::new(&url, status, &headers, now);
+        self.write_entry(key, &entry, Some(&body)).await;
+        build_response(status, url, &headers, body.to_vec())
+    }
+}
+
+#[async_trait]
+impl Middleware for HttpCacheMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        if !is_cacheable_request(&req) {
+            return next.run(req, extensions).await;
+        }
+
+        let key = cache_key(req.url());
+        self.update_state(|state| {
+            state.used_entries.insert(key.clone());
+        });
+        let now = unix_timestamp();
+
+        let Some((entry, body)) = self.read_entry(&key).await else {
+            let response = next.run(req, extensions).await?;
+            return self.store(&key, response, now).await;
+        };
+
+        let headers = entry.headers();
+        if is_fresh(&headers, entry.stored_at, now) {
+            if let Ok(response) = entry.to_response(&headers, body.clone()) {
+                self.update_state(|state| state.fresh_responses += 1);
+                return Ok(response);
+            }
+        }
+
+        if let Some(etag) = headers.get(ETAG) {
+            req.headers_mut().insert(IF_NONE_MATCH, etag.clone());
+        }
+        if let Some(last_modified) = headers.get(LAST_MODIFIED) {
+            req.headers_mut()
+                .insert(IF_MODIFIED_SINCE, last_modified.clone());
+        }
+
+        let response = next.run(req, extensions).await?;
+        if response.status() != StatusCode::NOT_MODIFIED {
+            return self.store(&key, response, now).await;
+        }
+
+        let entry = entry.revalidated(response.headers(), now);
+        let headers = entry.headers();
+        self.write_entry(&key, &entry, None).await;
+        self.update_state(|state| state.revalidated_responses += 1);
+        entry.to_response(&headers, body)
+    }
+}
+
+// Conditional and range requests are sent by callers that manage their own copy of the response.
+fn is_cacheable_request(req: &Request) -> bool {
+    req.method() == Method::GET
+        && ![
+            IF_NONE_MATCH,
+            IF_MODIFIED_SINCE,
+            IF_MATCH,
+            IF_UNMODIFIED_SINCE,
+            RANGE,
+        ]
+        .iter()
+        .any(|header| req.headers().contains_key(header))
+}
+
+fn is_storable(response: &Response) -> bool {
+    let headers = response.headers();
+    let cache_control = CacheControl::from(headers);
+    let has_validators = headers.contains_key(ETAG) || headers.contains_key(LAST_MODIFIED);
+    let is_small = response
+        .content_length()
+        .is_some_and(|content_length| content_length <= MAX_CACHED_RESPONSE_SIZE);
+    let varies = headers
+        .get_all(VARY)
+        .iter()
+        .any(|vary| vary.to_str().is_ok_and(|vary| vary.trim() == "*"));
+    response.status() == StatusCode::OK
+        && !cache_control.no_store
+        && !varies
+        && is_small
+        && (has_validators || freshness_lifetime(headers).is_some_and(|lifetime| lifetime > 0))
+}
+
+fn cache_key(url: &Url) -> String {
+    format!("{:x}", Sha256::digest(url.as_str()))
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+struct CachedResponse {
+    url: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+    // when the response was received (or last revalidated) as a Unix timestamp
+    stored_at: u64,
+}
+
+impl CachedResponse {
+    fn new(url: &Url, status: StatusCode, headers: &HeaderMap, stored_at: u64) -> Self {
+        CachedResponse {
+            url: url.to_string(),
+            status: status.as_u16(),
+            headers: header_pairs(headers),
+            stored_at,
+        }
+    }
+
+    fn headers(&self) -> HeaderMap {
+        self.headers
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((
+                    HeaderName::from_bytes(name.as_bytes()).ok()?,
+                    HeaderValue::from_str(value).ok()?,
+                ))
+            })
+            .fold(HeaderMap::new(), |mut headers, (name, value)| {
+                headers.append(name, value);
+                headers
+            })
+    }
+
+    // The headers of a `304 Not Modified` response replace the stored ones with the same name
+    // (e.g.; a new `Date` or `Cache-Control`) while the stored body is kept.
+    fn revalidated(self, not_modified_headers: &HeaderMap, stored_at: u64) -> Self {
+        let mut headers = self.headers();
+        for name in not_modified_headers.keys() {
+            if name == CONTENT_LENGTH || name == TRANSFER_ENCODING {
+                continue;
+            }
+            headers.remove(name);
+            for value in not_modified_headers.get_all(name) {
+                headers.append(name, value.clone());
+            }
+        }
+        CachedResponse {
+            headers: header_pairs(&headers),
+            stored_at,
+            ..self
+        }
+    }
+
+    fn to_response(
+        &self,
+        headers: &HeaderMap,
+        body: Vec<u8>,
+    ) -> reqwest_middleware::Result<Response> {
+        build_response(
+            StatusCode::from_u16(self.status)
+                .map_err(|e| reqwest_middleware::Error::Middleware(e.into()))?,
+            Url::parse(&self.url).map_err(|e| reqwest_middleware::Error::Middleware(e.into()))?,
+            headers,
+            body,
+        )
+    }
+}
+
+fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter(|(name, _)| *name != TRANSFER_ENCODING)
+        .filter_map(|(name, value)| {
+            value
+                .to_str()
+                .ok()
+                .map(|value| (name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+fn build_response(
+    status: StatusCode,
+    url: Url,
+    headers: &HeaderMap,
+    body: Vec<u8>,
+) -> reqwest_middleware::Result<Response> {
+    let mut builder = http::Response::builder().status(status).url(url);
+    if let Some(response_headers) = builder.headers_mut() {
+        response_headers.extend(headers.clone());
+    }
+    builder
+        .body(body)
+        .map(Response::from)
+        .map_err(|e| reqwest_middleware::Error::Middleware(anyhow!(e)))
+}
+
+// Only the response directives that matter to a private cache are read.
+#[derive(Debug, Default, Eq, PartialEq)]
+struct CacheControl {
+    no_store: bool,
+    no_cache: bool,
+    max_age: Option<u64>,
+}
+
+impl From<&HeaderMap> for CacheControl {
+    fn from(headers: &HeaderMap) -> Self {
+        headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .fold(CacheControl::default(), |mut cache_control, directive| {
+                let (name, value) = directive
+                    .split_once('=')
+                    .map_or((directive, None), |(name, value)| (name, Some(value)));
+                match name.trim().to_ascii_lowercase().as_str() {
+                    "no-store" => cache_control.no_store = true,
+                    // `no-cache` with a list of header names only restricts those headers but
+                    // revalidating the whole response is always allowed
+                    "no-cache" => cache_control.no_cache = true,
+                    "max-age" => {
+                        cache_control.max_age = value
+                            .map(|value| value.trim().trim_matches('"'))
+                            .and_then(|value| value.parse().ok())
+                            // an invalid max-age means the response is stale
+                            .or(Some(0));
+                    }
+                    _ => {}
+                }
+                cache_control
+            })
+    }
+}
+
+// https://www.rfc-editor.org/rfc/rfc9111#section-4.2.1
+fn freshness_lifetime(headers: &HeaderMap) -> Option<u64> {
+    if let Some(max_age) = CacheControl::from(headers).max_age {
+        return Some(max_age);
+    }
+    let expires = headers.get(EXPIRES)?;
+    // an invalid `Expires` date (e.g.; `0`) means the response is already stale
+    let Some(expires) = parse_http_date(expires) else {
+        return Some(0);
+    };
+    let date = headers.get(DATE).and_then(parse_http_date)?;
+    Some(expires.saturating_sub(date))
+}
+
+// https://www.rfc-editor.org/rfc/rfc9111#section-4.2.3
+fn is_fresh(headers: &HeaderMap, stored_at: u64, now: u64) -> bool {
+    if CacheControl::from(headers).no_cache {
+        return false;
+    }
+    let age = headers
+        .get(AGE)
+        .and_then(|age| age.to_str().ok())
+        .and_then(|age| age.trim().parse::<u64>().ok())
+        .unwrap_or_default();
+    let current_age = age + now.saturating_sub(stored_at);
+    freshness_lifetime(headers).is_some_and(|lifetime| lifetime > current_age)
+}
+
+// HTTP dates use the RFC 1123 format (e.g.; `Thu, 25 Apr 2024 15:10:33 GMT`) which is a subset of
+// RFC 2822.
+fn parse_http_date(value: &HeaderValue) -> Option<u64> {
+    value
+        .to_str()
+        .ok()
+        .and_then(|value| DateTime::parse_from_rfc2822(value.trim()).ok())
+        .and_then(|date| u64::try_from(date.timestamp()).ok())
+}
+
+pub(crate) async fn log_http_cache(
+    http_cache: &HttpCacheMiddleware,
+    log: Print<Bullet<Stdout>>,
+) -> Print<Bullet<Stdout>> {
+    let (fresh_responses, revalidated_responses) = {
+        let state = http_cache
+            .state
+            .lock()
+            .expect("The HTTP cache lock shouldn't be poisoned");
+        (state.fresh_responses, state.revalidated_responses)
+    };
+    let pruned = http_cache.prune().await;
+    if fresh_responses + revalidated_responses == 0 && matches!(pruned, Ok(0)) {
+        return log;
+    }
+
+    let responses = |count: usize| {
+        if count == 1 {
+            "1 response".to_string()
+        } else {
+            format!("{count} responses")
+        }
+    };
+    let mut log = log.bullet("HTTP cache");
+    if fresh_responses + revalidated_responses > 0 {
+        log = log.sub_bullet(format!(
+            "Reused {reused} from the cache ({fresh} still fresh, {revalidated} revalidated with the repository)",
+            reused = responses(fresh_responses + revalidated_responses),
+            fresh = fresh_responses,
+            revalidated = revalidated_responses
+        ));
+    }
+    match pruned {
+        Ok(0) => log,
+        Ok(removed) => log.sub_bullet(format!(
+            "Removed {removed} unused from the cache",
+            removed = responses(removed)
+        )),
+        Err(e) => log.warning(format!(
+            "Failed to remove unused responses from the cache: {e}"
+        )),
+    }
+    .done()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn headers(pairs: &[(HeaderName, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .fold(HeaderMap::new(), |mut headers, (name, value)| {
+                headers.append(name, HeaderValue::from_str(value).unwrap());
+                headers
+            })
+    }
+
+    fn response(status: u16, pairs: &[(HeaderName, &str)], body: &str) -> Response {
+        let mut builder = http::Response::builder()
+            .status(status)
+            .url(Url::parse("http://archive.ubuntu.com/ubuntu/dists/noble/InRelease").unwrap());
+        for (name, value) in pairs {
+            builder = builder.header(name, *value);
+        }
+        Response::from(builder.body(body.to_string()).unwrap())
+    }
+
+    #[test]
+    fn test_cache_control() {
+        assert_eq!(
+            CacheControl::from(&headers(&[(CACHE_CONTROL, "public, max-age=120")])),
+            CacheControl {
+                no_store: false,
+                no_cache: false,
+                max_age: Some(120)
+            }
+        );
+        assert_eq!(
+            CacheControl::from(&headers(&[
+                (CACHE_CONTROL, "No-Cache"),
+                (CACHE_CONTROL, "no-store, max-age=\"abc\"")
+            ])),
+            CacheControl {
+                no_store: true,
+                no_cache: true,
+                max_age: Some(0)
+            }
+        );
+        assert_eq!(
+            CacheControl::from(&HeaderMap::new()),
+            CacheControl::default()
+        );
+    }
+
+    #[test]
+    fn test_freshness_lifetime() {
+        assert_eq!(
+            freshness_lifetime(&headers(&[
+                (CACHE_CONTROL, "max-age=60"),
+                (EXPIRES, "Thu, 25 Apr 2024 16:10:33 GMT"),
+                (DATE, "Thu, 25 Apr 2024 15:10:33 GMT")
+            ])),
+            Some(60)
+        );
+        assert_eq!(
+            freshness_lifetime(&headers(&[
+                (EXPIRES, "Thu, 25 Apr 2024 16:10:33 GMT"),
+                (DATE, "Thu, 25 Apr 2024 15:10:33 GMT")
+            ])),
+            Some(3600)
+        );
+        assert_eq!(freshness_lifetime(&headers(&[(EXPIRES, "0")])), Some(0));
+        assert_eq!(freshness_lifetime(&headers(&[(ETAG, "\"abc\"")])), None);
+    }
+
+    #[test]
+    fn test_is_fresh() {
+        let max_age = headers(&[(CACHE_CONTROL, "max-age=60"), (AGE, "10")]);
+        assert!(is_fresh(&max_age, 1000, 1049));
+        assert!(!is_fresh(&max_age, 1000, 1050));
+        assert!(!is_fresh(
+            &headers(&[(CACHE_CONTROL, "no-cache, max-age=60")]),
+            1000,
+            1000
+        ));
+        assert!(!is_fresh(&headers(&[(ETAG, "\"abc\"")]), 1000, 1000));
+    }
+
+    #[test]
+    fn test_is_cacheable_request() {
+        let url = Url::parse("http://archive.ubuntu.com/ubuntu/dists/noble/InRelease").unwrap();
+        assert!(is_cacheable_request(&Request::new(
+            Method::GET,
+            url.clone()
+        )));
+        assert!(!is_cacheable_request(&Request::new(
+            Method::HEAD,
+            url.clone()
+        )));
+
+        let mut conditional = Request::new(Method::GET, url);
+        conditional
+            .headers_mut()
+            .insert(IF_NONE_MATCH, HeaderValue::from_static("\"abc\""));
+        assert!(!is_cacheable_request(&conditional));
+    }
+
+    #[test]
+    fn test_is_storable() {
+        assert!(is_storable(&response(
+            200,
+            &[(ETAG, "\"abc\"")],
+            "Origin: Ubuntu"
+        )));
+        assert!(is_storable(&response(
+            200,
+            &[(CACHE_CONTROL, "max-age=60")],
+            "Origin: Ubuntu"
+        )));
+        // nothing to revalidate with and never fresh
+        assert!(!is_storable(&response(200, &[], "Origin: Ubuntu")));
+        assert!(!is_storable(&response(
+            200,
+            &[(ETAG, "\"abc\""), (CACHE_CONTROL, "no-store")],
+            "Origin: Ubuntu"
+        )));
+        assert!(!is_storable(&response(
+            404,
+            &[(ETAG, "\"abc\"")],
+            "Not Found"
+        )));
+        assert!(!is_storable(&response(
+            200,
+            &[(ETAG, "\"abc\"")],
+            &"a".repeat(usize::try_from(MAX_CACHED_RESPONSE_SIZE).unwrap() + 1)
+        )));
+    }
+
+    #[test]
+    fn test_revalidated() {
+        let url = Url::parse("http://archive.ubuntu.com/ubuntu/dists/noble/InRelease").unwrap();
+        let entry = CachedResponse::new(
+            &url,
+            StatusCode::OK,
+            &headers(&[
+                (ETAG, "\"abc\""),
+                (CACHE_CONTROL, "max-age=60"),
+                (CONTENT_LENGTH, "14"),
+            ]),
+            1000,
+        );
+        let revalidated = entry.revalidated(
+            &headers(&[(CACHE_CONTROL, "max-age=120"), (CONTENT_LENGTH, "0")]),
+            2000,
+        );
+        assert_eq!(revalidated.stored_at, 2000);
+        let headers = revalidated.headers();
+        assert_eq!(headers[ETAG], "\"abc\"");
+        assert_eq!(headers[CACHE_CONTROL], "max-age=120");
+        assert_eq!(headers[CONTENT_LENGTH], "14");
+    }
+
+    #[tokio::test]
+    async fn test_store_and_read_entry() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let http_cache = HttpCacheMiddleware::new(cache_dir.path().to_path_buf());
+
+        let stored = http_cache
+            .store(
+                "entry",
+                response(200, &[(ETAG, "\"abc\"")], "Origin: Ubuntu"),
+                1000,
+            )
+            .await
+            .unwrap();
+        assert_eq!(stored.text().await.unwrap(), "Origin: Ubuntu");
+
+        let (entry, body) = http_cache.read_entry("entry").await.unwrap();
+        assert_eq!(entry.stored_at, 1000);
+        assert_eq!(body, b"Origin: Ubuntu");
+        let replayed = entry.to_response(&entry.headers(), body).unwrap();
+        assert_eq!(replayed.status(), StatusCode::OK);
+        assert_eq!(replayed.headers()[ETAG], "\"abc\"");
+        assert_eq!(
+            replayed.url().as_str(),
+            "http://archive.ubuntu.com/ubuntu/dists/noble/InRelease"
+        );
+
+        http_cache
+            .store(
+                "entry",
+                response(200, &[(CACHE_CONTROL, "no-store")], "Origin: Ubuntu"),
+                2000,
+            )
+            .await
+            .unwrap();
+        assert!(http_cache.read_entry("entry").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_prune() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let http_cache = HttpCacheMiddleware::new(cache_dir.path().to_path_buf());
+        for key in ["used", "unused"] {
+            http_cache
+                .store(
+                    key,
+                    response(200, &[(ETAG, "\"abc\"")], "Origin: Ubuntu"),
+                    1000,
+                )
+                .await
+                .unwrap();
+        }
+        http_cache.update_state(|state| {
+            state.used_entries.insert("used".to_string());
+        });
+
+        assert_eq!(http_cache.prune().await.unwrap(), 1);
+        assert!(http_cache.read_entry("used").await.is_some());
+        assert!(!Path::new(&cache_dir.path().join("unused.body")).exists());
+        assert!(!Path::new(&cache_dir.path().join("unused.json")).exists());
+
+        let missing = HttpCacheMiddleware::new(cache_dir.path().join("missing"));
+        assert_eq!(missing.prune().await.unwrap(), 0);
+    }
+}
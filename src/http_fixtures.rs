@@ -0,0 +1,231 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use http::{Extensions, StatusCode};
+use libcnb::Env;
+use reqwest::header::{IF_MODIFIED_SINCE, IF_NONE_MATCH};
+use reqwest::{Request, Response, ResponseBuilderExt, Url};
+use reqwest_middleware::{Middleware, Next};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::fs::{create_dir_all, read, write};
+
+pub(crate) const RECORD_ENV_VAR: &str = "BP_DEB_PACKAGES_RECORD_HTTP_FIXTURES";
+pub(crate) const REPLAY_ENV_VAR: &str = "BP_DEB_PACKAGES_REPLAY_HTTP_FIXTURES";
+
+// Records the responses from package repositories into a directory of fixtures or replays them
+// from one so builds can run without network access (e.g.; the integration tests in CI). This is
+// only compiled into the buildpack with the `http-fixtures` feature and is registered as the first
+// middleware so replayed requests never reach the retry, mirror, or authentication middleware.
+// Fixtures are matched on the request method and URL. Conditional request headers are dropped
+// while recording so every fixture holds a complete response that can be replayed without a cache.
+pub(crate) enum HttpFixturesMiddleware {
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+impl HttpFixturesMiddleware {
+    // Relative directories are resolved against the application directory. Replaying takes
+    // precedence if both variables are set.
+    pub(crate) fn from_env(env: &Env, app_dir: &Path) -> Option<Self> {
+        let lookup = |name: &str| {
+            env.get(name)
+                .filter(|value| !value.is_empty())
+                .map(|value| app_dir.join(value))
+        };
+        lookup(REPLAY_ENV_VAR)
+            .map(HttpFixturesMiddleware::Replay)
+            .or_else(|| lookup(RECORD_ENV_VAR).map(HttpFixturesMiddleware::Record))
+    }
+}
+
+impl Display for HttpFixturesMiddleware {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HttpFixturesMiddleware::Record(fixtures_dir) => {
+                write!(f, "Recording responses to {}", fixtures_dir.display())
+            }
+            HttpFixturesMiddleware::Replay(fixtures_dir) => {
+                write!(f, "Replaying responses from {}", fixtures_dir.display())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Middleware for HttpFixturesMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let fixture_name = fixture_name(&req);
+        let url = req.url().clone();
+        match self {
+            HttpFixturesMiddleware::Replay(fixtures_dir) => {
+                read_fixture(fixtures_dir, &fixture_name).await
+            }
+            HttpFixturesMiddleware::Record(fixtures_dir) => {
+                req.headers_mut().remove(IF_NONE_MATCH);
+                req.headers_mut().remove(IF_MODIFIED_SINCE);
+                let method = req.method().to_string();
+                let response = next.run(req, extensions).await?;
+                write_fixture(fixtures_dir, &fixture_name, method, url, response).await
+            }
+        }
+        .map_err(reqwest_middleware::Error::Middleware)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HttpFixture {
+    method: String,
+    url: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+}
+
+fn fixture_name(req: &Request) -> String {
+    format!(
+        "{:x}",
+        Sha256::digest(format!("{} {}", req.method(), req.url()))
+    )
+}
+
+async fn read_fixture(fixtures_dir: &Path, fixture_name: &str) -> anyhow::Result<Response> {
+    let fixture_path = fixtures_dir.join(format!("{fixture_name}.json"));
+    let fixture = read(&fixture_path)
+        .await
+        .map_err(|e| anyhow!("No HTTP fixture found at {}: {e}", fixture_path.display()))?;
+    let fixture = serde_json::from_slice::<HttpFixture>(&fixture)?;
+    let body = read(fixtures_dir.join(format!("{fixture_name}.body"))).await?;
+    to_response(&fixture, body)
+}
+
+async fn write_fixture(
+    fixtures_dir: &Path,
+    fixture_name: &str,
+    method: String,
+    url: Url,
+    response: Response,
+) -> anyhow::Result<Response> {
+    let fixture = HttpFixture {
+        method,
+        url: url.to_string(),
+        status: response.status().as_u16(),
+        headers: response
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).to_string(),
+                )
+            })
+            .collect(),
+    };
+    let body = response.bytes().await?.to_vec();
+    create_dir_all(fixtures_dir).await?;
+    write(fixtures_dir.join(format!("{fixture_name}.body")), &body).await?;
+    write(
+        fixtures_dir.join(format!("{fixture_name}.json")),
+        serde_json::to_vec_pretty(&fixture)?,
+    )
+    .await?;
+    to_response(&fixture, body)
+}
+
+fn to_response(fixture: &HttpFixture, body: Vec<u8>) -> anyhow::Result<Response> {
+    let mut builder = http::Response::builder()
+        .status(StatusCode::from_u16(fixture.status)?)
+        .url(Url::parse(&fixture.url)?);
+    for (name, value) in &fixture.headers {
+        builder = builder.header(name, value);
+    }
+    Ok(Response::from(builder.body(body)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Method;
+
+    use super::*;
+
+    fn request(url: &str) -> Request {
+        Request::new(Method::GET, Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn test_from_env() {
+        let app_dir = Path::new("/workspace");
+        let mut env = Env::new();
+        assert!(HttpFixturesMiddleware::from_env(&env, app_dir).is_none());
+
+        env.insert(RECORD_ENV_VAR, ".http-fixtures");
+        assert!(matches!(
+            HttpFixturesMiddleware::from_env(&env, app_dir),
+            Some(HttpFixturesMiddleware::Record(path)) if path == Path::new("/workspace/.http-fixtures")
+        ));
+
+        env.insert(REPLAY_ENV_VAR, "/tmp/http-fixtures");
+        assert!(matches!(
+            HttpFixturesMiddleware::from_env(&env, app_dir),
+            Some(HttpFixturesMiddleware::Replay(path)) if path == Path::new("/tmp/http-fixtures")
+        ));
+    }
+
+    #[test]
+    fn test_fixture_name() {
+        let url = "http://archive.ubuntu.com/ubuntu/dists/noble/InRelease";
+        assert_eq!(fixture_name(&request(url)), fixture_name(&request(url)));
+        assert_ne!(
+            fixture_name(&request(url)),
+            fixture_name(&request(
+                "http://archive.ubuntu.com/ubuntu/dists/jammy/InRelease"
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay_fixture() {
+        let fixtures_dir = tempfile::tempdir().unwrap();
+        let url = Url::parse("http://archive.ubuntu.com/ubuntu/dists/noble/InRelease").unwrap();
+        let response = Response::from(
+            http::Response::builder()
+                .status(200)
+                .header("etag", "\"abc123\"")
+                .body("Origin: Ubuntu")
+                .unwrap(),
+        );
+
+        let recorded = write_fixture(
+            fixtures_dir.path(),
+            "fixture",
+            "GET".to_string(),
+            url.clone(),
+            response,
+        )
+        .await
+        .unwrap();
+        assert_eq!(recorded.text().await.unwrap(), "Origin: Ubuntu");
+
+        let replayed = read_fixture(fixtures_dir.path(), "fixture").await.unwrap();
+        assert_eq!(replayed.status(), StatusCode::OK);
+        assert_eq!(replayed.url(), &url);
+        assert_eq!(replayed.headers()["etag"], "\"abc123\"");
+        assert_eq!(replayed.text().await.unwrap(), "Origin: Ubuntu");
+    }
+
+    #[tokio::test]
+    async fn test_replay_missing_fixture() {
+        let fixtures_dir = tempfile::tempdir().unwrap();
+        let error = read_fixture(fixtures_dir.path(), "missing")
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().starts_with("No HTTP fixture found at"));
+    }
+}
@@ -0,0 +1,121 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// ImageMagick reads `policy.xml` and its other configuration files from the `/etc` directory it
+// was built for (e.g.; `/etc/ImageMagick-6`) which doesn't exist on the base image when it's only
+// installed into the layer. `MAGICK_CONFIGURE_PATH` points it at the copies in the layer instead.
+pub(crate) fn find_config_dirs(install_path: &Path) -> Vec<PathBuf> {
+    let mut config_dirs = fs::read_dir(install_path.join("etc"))
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with("ImageMagick-")
+                && entry.path().is_dir()
+        })
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    config_dirs.sort();
+    config_dirs
+}
+
+pub(crate) fn magick_configure_path(config_dirs: &[PathBuf]) -> String {
+    config_dirs
+        .iter()
+        .map(|config_dir| config_dir.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+// The policy from the app replaces the one installed by the package so its defaults (e.g.; the
+// disabled PDF coder) don't apply.
+pub(crate) fn apply_policy(
+    config_dirs: &[PathBuf],
+    policy_file: &Path,
+) -> Result<(), ImageMagickError> {
+    if config_dirs.is_empty() {
+        Err(ImageMagickError::NotInstalled)?;
+    }
+
+    let policy = fs::read(policy_file)
+        .map_err(|e| ImageMagickError::ReadPolicy(policy_file.to_path_buf(), e))?;
+
+    for config_dir in config_dirs {
+        let installed_policy = config_dir.join("policy.xml");
+        // the packaged policy may be a symlink into another package's files
+        if installed_policy.is_symlink() {
+            fs::remove_file(&installed_policy)
+                .map_err(|e| ImageMagickError::WritePolicy(installed_policy.clone(), e))?;
+        }
+        fs::write(&installed_policy, &policy)
+            .map_err(|e| ImageMagickError::WritePolicy(installed_policy.clone(), e))?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub(crate) enum ImageMagickError {
+    NotInstalled,
+    ReadPolicy(PathBuf, std::io::Error),
+    WritePolicy(PathBuf, std::io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_config_dirs() {
+        let install_dir = tempfile::tempdir().unwrap();
+        assert!(find_config_dirs(install_dir.path()).is_empty());
+
+        fs::create_dir_all(install_dir.path().join("etc/ImageMagick-7")).unwrap();
+        fs::create_dir_all(install_dir.path().join("etc/ImageMagick-6")).unwrap();
+        fs::create_dir_all(install_dir.path().join("etc/fonts")).unwrap();
+        fs::write(install_dir.path().join("etc/ImageMagick-notes"), "").unwrap();
+
+        assert_eq!(
+            find_config_dirs(install_dir.path()),
+            vec![
+                install_dir.path().join("etc/ImageMagick-6"),
+                install_dir.path().join("etc/ImageMagick-7"),
+            ]
+        );
+        assert_eq!(
+            magick_configure_path(&find_config_dirs(install_dir.path())),
+            format!(
+                "{0}/etc/ImageMagick-6:{0}/etc/ImageMagick-7",
+                install_dir.path().display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_apply_policy() {
+        let install_dir = tempfile::tempdir().unwrap();
+        let config_dir = install_dir.path().join("etc/ImageMagick-6");
+        fs::create_dir_all(&config_dir).unwrap();
+        fs::write(config_dir.join("policy.xml"), "<policymap/>").unwrap();
+        let policy_file = install_dir.path().join("policy.xml");
+        fs::write(&policy_file, "<policymap><policy/></policymap>").unwrap();
+
+        apply_policy(std::slice::from_ref(&config_dir), &policy_file).unwrap();
+        assert_eq!(
+            fs::read_to_string(config_dir.join("policy.xml")).unwrap(),
+            "<policymap><policy/></policymap>"
+        );
+
+        assert!(matches!(
+            apply_policy(&[], &policy_file),
+            Err(ImageMagickError::NotInstalled)
+        ));
+        assert!(matches!(
+            apply_policy(&[config_dir], &install_dir.path().join("missing.xml")),
+            Err(ImageMagickError::ReadPolicy(_, _))
+        ));
+    }
+}
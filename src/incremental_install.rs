@@ -0,0 +1,307 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::fs::{remove_dir, remove_file, symlink_metadata, write};
+
+use crate::alternatives::Alternative;
+
+// The files extracted by each package are recorded in the packages layer so that, when a restored
+// layer only differs from the new package set by a few packages, the files of the removed and
+// upgraded packages can be deleted and only the added and upgraded packages need to be downloaded
+// and extracted instead of reinstalling everything.
+pub(crate) const INSTALLED_FILES_NAME: &str = ".deb-packages-files.toml";
+
+#[derive(Debug, Default, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub(crate) struct InstalledFiles {
+    pub(crate) packages: BTreeMap<String, Vec<PathBuf>>,
+    // kept so the alternatives can be created again for a layer imported from the shared cache
+    // since the layer is exported before they're created
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) alternatives: BTreeMap<String, Vec<Alternative>>,
+}
+
+impl InstalledFiles {
+    // Paths are stored relative to the layer without the `./` prefix used in the data tarballs.
+    pub(crate) fn from_extracted_files(
+        extracted_files: &BTreeMap<String, Vec<PathBuf>>,
+        alternatives: &BTreeMap<String, Vec<Alternative>>,
+    ) -> Self {
+        InstalledFiles {
+            packages: extracted_files
+                .iter()
+                .map(|(package_name, files)| {
+                    let files = files
+                        .iter()
+                        .map(|file| normalize(file))
+                        .filter(|file| !file.as_os_str().is_empty())
+                        .collect();
+                    (package_name.clone(), files)
+                })
+                .collect(),
+            alternatives: alternatives.clone(),
+        }
+    }
+
+    // Returns `None` when the layer was installed before the files were recorded or the file can't
+    // be read, in which case the layer can't be updated incrementally.
+    pub(crate) fn read(install_path: &Path) -> Option<InstalledFiles> {
+        let contents = std::fs::read_to_string(install_path.join(INSTALLED_FILES_NAME)).ok()?;
+        toml::from_str(&contents).ok()
+    }
+
+    pub(crate) async fn write(&self, install_path: &Path) -> std::io::Result<()> {
+        let contents =
+            toml::to_string(self).map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))?;
+        write(install_path.join(INSTALLED_FILES_NAME), contents).await
+    }
+
+    pub(crate) fn extend(&mut self, other: InstalledFiles) {
+        self.packages.extend(other.packages);
+        self.alternatives.extend(other.alternatives);
+    }
+
+    // Deletes the files of the given packages that aren't also installed by one of the other
+    // packages along with any directories left empty.
+    pub(crate) async fn remove_packages(
+        &mut self,
+        install_path: &Path,
+        package_names: &[String],
+    ) -> std::io::Result<RemovedFiles> {
+        for package_name in package_names {
+            self.alternatives.remove(package_name);
+        }
+        let removed_files = package_names
+            .iter()
+            .filter_map(|package_name| self.packages.remove(package_name))
+            .flatten()
+            .collect::<HashSet<_>>();
+        let kept_files = self.packages.values().flatten().collect::<HashSet<_>>();
+
+        let mut removed = RemovedFiles::default();
+        let mut directories = vec![];
+        for file in removed_files
+            .iter()
+            .filter(|file| !kept_files.contains(file))
+        {
+            let path = install_path.join(file);
+            match symlink_metadata(&path).await {
+                Ok(metadata) if metadata.is_dir() => directories.push(path),
+                Ok(metadata) => {
+                    remove_file(&path).await?;
+                    removed.files += 1;
+                    removed.bytes += metadata.len();
+                }
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        // deepest first so the parents of nested directories are empty by the time they're reached
+        directories.sort_by_key(|directory| std::cmp::Reverse(directory.components().count()));
+        for directory in directories {
+            // directories still holding files from other packages or generated during the build
+            // (e.g.; locales) are left in place
+            let _ = remove_dir(directory).await;
+        }
+
+        Ok(removed)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub(crate) struct RemovedFiles {
+    pub(crate) files: usize,
+    pub(crate) bytes: u64,
+}
+
+fn normalize(file: &Path) -> PathBuf {
+    file.components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect()
+}
+
+// The difference between the packages in a restored layer and the packages to install.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub(crate) struct PackageChanges {
+    pub(crate) added: Vec<String>,
+    // packages with a different checksum (e.g.; a new version)
+    pub(crate) upgraded: Vec<String>,
+    pub(crate) removed: Vec<String>,
+}
+
+impl PackageChanges {
+    pub(crate) fn new(
+        old_checksums: &HashMap<String, String>,
+        new_checksums: &HashMap<String, String>,
+    ) -> PackageChanges {
+        let mut changes = PackageChanges::default();
+        for (package_name, checksum) in new_checksums {
+            match old_checksums.get(package_name) {
+                None => changes.added.push(package_name.clone()),
+                Some(old_checksum) if old_checksum != checksum => {
+                    changes.upgraded.push(package_name.clone());
+                }
+                Some(_) => {}
+            }
+        }
+        changes.removed = old_checksums
+            .keys()
+            .filter(|package_name| !new_checksums.contains_key(*package_name))
+            .cloned()
+            .collect();
+        changes.added.sort();
+        changes.upgraded.sort();
+        changes.removed.sort();
+        changes
+    }
+
+    // The packages whose files need to be removed from the layer.
+    pub(crate) fn outdated(&self) -> Vec<String> {
+        self.removed.iter().chain(&self.upgraded).cloned().collect()
+    }
+
+    // The packages that need to be downloaded and extracted.
+    pub(crate) fn is_pending(&self, package_name: &str) -> bool {
+        self.added
+            .iter()
+            .chain(&self.upgraded)
+            .any(|name| name == package_name)
+    }
+}
+
+impl Display for PackageChanges {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} added, {} upgraded, {} removed",
+            self.added.len(),
+            self.upgraded.len(),
+            self.removed.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_package_changes() {
+        let old_checksums = HashMap::from([
+            ("curl".to_string(), "aaa".to_string()),
+            ("libcurl4".to_string(), "bbb".to_string()),
+            ("wget".to_string(), "ccc".to_string()),
+        ]);
+        let new_checksums = HashMap::from([
+            ("curl".to_string(), "aaa".to_string()),
+            ("libcurl4".to_string(), "ddd".to_string()),
+            ("jq".to_string(), "eee".to_string()),
+        ]);
+
+        let changes = PackageChanges::new(&old_checksums, &new_checksums);
+        assert_eq!(
+            changes,
+            PackageChanges {
+                added: vec!["jq".to_string()],
+                upgraded: vec!["libcurl4".to_string()],
+                removed: vec!["wget".to_string()],
+            }
+        );
+        assert_eq!(changes.outdated(), vec!["wget", "libcurl4"]);
+        assert!(changes.is_pending("jq"));
+        assert!(changes.is_pending("libcurl4"));
+        assert!(!changes.is_pending("curl"));
+        assert_eq!(changes.to_string(), "1 added, 1 upgraded, 1 removed");
+    }
+
+    #[tokio::test]
+    async fn test_remove_packages() {
+        let install_dir = tempfile::tempdir().unwrap();
+        let install_path = install_dir.path();
+        fs::create_dir_all(install_path.join("usr/bin")).unwrap();
+        fs::create_dir_all(install_path.join("usr/share/wget")).unwrap();
+        fs::write(install_path.join("usr/bin/curl"), "").unwrap();
+        fs::write(install_path.join("usr/bin/wget"), "").unwrap();
+        fs::write(install_path.join("usr/share/wget/wgetrc"), "quiet = on\n").unwrap();
+
+        let mut installed_files = InstalledFiles::from_extracted_files(
+            &BTreeMap::from([
+                (
+                    "curl".to_string(),
+                    vec![
+                        PathBuf::from("./"),
+                        PathBuf::from("./usr/"),
+                        PathBuf::from("./usr/bin/"),
+                        PathBuf::from("./usr/bin/curl"),
+                    ],
+                ),
+                (
+                    "wget".to_string(),
+                    vec![
+                        PathBuf::from("./"),
+                        PathBuf::from("./usr/"),
+                        PathBuf::from("./usr/bin/"),
+                        PathBuf::from("./usr/bin/wget"),
+                        PathBuf::from("./usr/share/"),
+                        PathBuf::from("./usr/share/wget/"),
+                        PathBuf::from("./usr/share/wget/wgetrc"),
+                    ],
+                ),
+            ]),
+            &BTreeMap::new(),
+        );
+
+        let removed = installed_files
+            .remove_packages(install_path, &["wget".to_string()])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            removed,
+            RemovedFiles {
+                files: 2,
+                bytes: 11
+            }
+        );
+        assert!(install_path.join("usr/bin/curl").exists());
+        assert!(!install_path.join("usr/bin/wget").exists());
+        assert!(!install_path.join("usr/share").exists());
+        assert_eq!(
+            installed_files.packages.keys().collect::<Vec<_>>(),
+            vec!["curl"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_and_read() {
+        let install_dir = tempfile::tempdir().unwrap();
+        assert_eq!(InstalledFiles::read(install_dir.path()), None);
+
+        let installed_files = InstalledFiles {
+            packages: BTreeMap::from([(
+                "vim".to_string(),
+                vec![PathBuf::from("usr/bin/vim.basic")],
+            )]),
+            alternatives: BTreeMap::from([(
+                "vim".to_string(),
+                vec![Alternative {
+                    name: "editor".to_string(),
+                    link: PathBuf::from("/usr/bin/editor"),
+                    path: PathBuf::from("/usr/bin/vim.basic"),
+                    priority: 30,
+                    slaves: vec![],
+                }],
+            )]),
+        };
+        installed_files.write(install_dir.path()).await.unwrap();
+        assert_eq!(
+            InstalledFiles::read(install_dir.path()),
+            Some(installed_files)
+        );
+    }
+}
@@ -1,35 +1,40 @@
-use std::collections::HashMap;
-use std::env::temp_dir;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::ffi::OsString;
-use std::fs::File;
+use std::fs::{read_to_string, remove_file, write, File};
 use std::io::{ErrorKind, Stdout, Write};
 use std::os::unix::ffi::OsStringExt;
 use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
 use std::sync::Arc;
-use std::time::{SystemTime, SystemTimeError, UNIX_EPOCH, Duration};
+use std::time::{Duration, SystemTime, SystemTimeError, UNIX_EPOCH};
 
 use ar::Archive as ArArchive;
 use async_compression::tokio::bufread::{GzipDecoder, XzDecoder, ZstdDecoder};
 use bullet_stream::state::{Bullet, SubBullet};
 use bullet_stream::{style, Print};
-use futures::StreamExt;
 use futures::io::AllowStdIo;
+use futures::StreamExt;
 use futures::TryStreamExt;
 use indexmap::IndexSet;
-use libcnb::Env;
 use libcnb::build::BuildContext;
 use libcnb::data::layer_name;
 use libcnb::layer::{
     CachedLayerDefinition, EmptyLayerCause, InvalidMetadataAction, LayerState, RestoredLayerAction,
 };
 use libcnb::layer_env::{LayerEnv, ModificationBehavior, Scope};
+use libcnb::Env;
+use libcnb::Platform;
 use reqwest_middleware::ClientWithMiddleware;
 use reqwest_middleware::Error::Reqwest;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
-use tokio::fs::{read_to_string as async_read_to_string, write as async_write, File as AsyncFile, set_permissions};
-use tokio::io::{copy as async_copy, BufReader as AsyncBufReader, BufWriter as AsyncBufWriter};
+use tokio::fs::{
+    read_to_string as async_read_to_string, rename as async_rename, set_permissions,
+    write as async_write, File as AsyncFile,
+};
+use tokio::io::{
+    copy as async_copy, AsyncRead, BufReader as AsyncBufReader, BufWriter as AsyncBufWriter,
+};
 use tokio::process::Command;
 use tokio::task::{JoinError, JoinSet};
 use tokio_tar::Archive as TarArchive;
@@ -38,88 +43,245 @@ use tokio_util::io::InspectReader;
 use walkdir::{DirEntry, WalkDir};
 
 // use crate::main::get_cache_retention_days;
-use crate::config::RequestedPackage;
+use crate::alternatives::{
+    create_alternatives, parse_alternatives, Alternative, CreateAlternativesError,
+};
 use crate::config::environment::Environment;
-use crate::debian::{Distro, MultiarchName, RepositoryPackage};
+use crate::config::{
+    EnvScopes, ExistingFilesPolicy, Locale, MaintainerScripts, RequestedPackage,
+    ScriptFailurePolicy, DOWNLOAD_URL_FILENAME,
+};
+use crate::create_package_index::IndexedSource;
+use crate::debian::{ArchitectureName, Distro, MultiarchName, PackageName, RepositoryPackage};
+use crate::download_cache::{
+    cached_archive, cached_archive_path, partial_archive_path, prune_download_cache,
+    DownloadCacheMetadata, DOWNLOAD_CACHE_LAYOUT_VERSION,
+};
+use crate::extraction_report::{find_extraction_issues, print_extraction_issues, ExtractionIssue};
+use crate::incremental_install::{
+    InstalledFiles, PackageChanges, RemovedFiles, INSTALLED_FILES_NAME,
+};
+use crate::instrumentation::record_cache_event;
+use crate::layout_verification::{verify_layout, LayoutIssue};
+use crate::lockfile::{Lockfile, LOCKFILE_NAME};
+use crate::package_integrations::{
+    package_env_vars, package_integrations, run_post_install, IntegrationSettings,
+    PackageIntegrationError,
+};
+use crate::sbom::{
+    create_cyclonedx_sbom, create_provenance, create_spdx_sbom, file_digest, layer_digest,
+    PROVENANCE_FILE_NAME,
+};
+use crate::self_check::{write_self_check_script, SelfCheck, SELF_CHECK_PROGRAM_NAME};
+use crate::shared_cache::{
+    export_packages, import_packages, shared_cache_archive_path, shared_cache_key,
+};
 use crate::{
-    is_buildpack_debug_logging_enabled, get_package_cache_days, BuildpackResult, DebianPackagesBuildpack,
+    get_package_cache_days, get_reinstall_packages, get_shared_cache_dir,
+    is_buildpack_debug_logging_enabled, BuildpackResult, DebianPackagesBuildpack,
     DebianPackagesBuildpackError,
 };
 
-// Define a mapping of packages to their required environment variables
-const PACKAGE_ENV_VARS: &[(&str, &[(&str, &str)])] = &[
-    ("git", &[("GIT_EXEC_PATH", "{install_dir}/usr/lib/git-core"), ("GIT_TEMPLATE_DIR", "{install_dir}/usr/share/git-core/templates")]),
-    ("ghostscript", &[("GS_LIB", "{install_dir}/var/lib/ghostscript")]),
-    // Add more package mappings here
-];
-
-fn package_env_vars() -> HashMap<&'static str, HashMap<&'static str, &'static str>> {
-    let mut map = HashMap::new();
-    for &(package, vars) in PACKAGE_ENV_VARS.iter() {
-        let mut var_map = HashMap::new();
-        for &(key, value) in vars.iter() {
-            var_map.insert(key, value);
-        }
-        map.insert(package, var_map);
-    }
-    map
+// The settings from project.toml and the platform that control how the resolved packages are
+// installed, along with what's recorded about the build in the layer metadata.
+// The bools are independent on/off settings from project.toml.
+#[allow(clippy::struct_excessive_bools)]
+pub(crate) struct InstallOptions {
+    pub(crate) foreign_architectures: Vec<ArchitectureName>,
+    pub(crate) env_scopes: EnvScopes,
+    pub(crate) launch_only: bool,
+    pub(crate) maintainer_scripts: MaintainerScripts,
+    pub(crate) strict_extraction: bool,
+    pub(crate) existing_files_policy: ExistingFilesPolicy,
+    pub(crate) verify_layout: bool,
+    pub(crate) self_check: bool,
+    pub(crate) locales: Vec<Locale>,
+    pub(crate) imagemagick_policy: Option<PathBuf>,
+    pub(crate) remove: IndexSet<PackageName>,
+    pub(crate) locked: bool,
+    pub(crate) provenance: bool,
+    pub(crate) system_packages_sha256: Option<String>,
+    pub(crate) snapshot: InstallSnapshot,
 }
 
+#[allow(clippy::too_many_lines)]
 pub(crate) async fn install_packages(
     context: &Arc<BuildContext<DebianPackagesBuildpack>>,
     client: &ClientWithMiddleware,
     distro: &Distro,
     packages_to_install: Vec<RepositoryPackage>,
-    skipped_packages: Vec<RequestedPackage>, 
+    skipped_packages: Vec<RequestedPackage>,
+    options: InstallOptions,
     mut log: Print<Bullet<Stdout>>,
 ) -> BuildpackResult<Print<Bullet<Stdout>>> {
+    let InstallOptions {
+        foreign_architectures,
+        env_scopes,
+        launch_only,
+        maintainer_scripts,
+        strict_extraction,
+        existing_files_policy,
+        verify_layout,
+        self_check,
+        locales,
+        imagemagick_policy,
+        remove,
+        locked,
+        provenance,
+        system_packages_sha256,
+        snapshot,
+    } = options;
+
     log = log.h2("Installing packages");
 
     let new_metadata = InstallationMetadata {
         package_checksums: packages_to_install
             .iter()
-            .map(|package| (package.name.to_string(), package.sha256sum.to_string()))
+            .map(|package| (package.name.to_string(), package.checksum.to_string()))
             .collect(),
         distro: distro.clone(),
         timestamp: SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .map_err(InstallPackagesError::SystemTimeError)?
-            .as_secs(), // Adding the timestamp here        
+            .as_secs(), // Adding the timestamp here
         dependencies: packages_to_install
             .iter()
             // get_dependencies() returns HashSet<&str>
-            .map(|package| (package.name.to_string(), package.get_dependencies()
-                .into_iter().map(|s| s.to_string()).collect::<Vec<String>>()))            
-            .collect(),        
+            .map(|package| {
+                (
+                    package.name.to_string(),
+                    package
+                        .get_dependencies()
+                        .into_iter()
+                        .map(|s| s.to_string())
+                        .collect::<Vec<String>>(),
+                )
+            })
+            .collect(),
+        removed_packages: remove.iter().map(ToString::to_string).collect(),
+        system_packages_sha256,
+        maintainer_scripts: maintainer_scripts.policies(),
+        imagemagick_policy: imagemagick_policy.clone(),
+        snapshot: Some(snapshot),
     };
 
+    let expected_lockfile = Lockfile::new(distro, &packages_to_install);
+
     let install_layer = context.cached_layer(
         layer_name!("packages"),
         CachedLayerDefinition {
-            build: true,
+            build: !launch_only,
             launch: true,
             invalid_metadata_action: &|_| InvalidMetadataAction::DeleteLayer,
-            restored_layer_action: &|old_metadata: &InstallationMetadata, _| {
-                let now = SystemTime::now();
-                let old_timestamp = UNIX_EPOCH + Duration::from_secs(old_metadata.timestamp);
-                let duration = now.duration_since(old_timestamp).unwrap_or(Duration::new(0, 0));
-                let x_days = get_package_cache_days(); // Default to 7 days if the environment variable is not set
-                println!("Cache retention days: {}", x_days);
-    
-                if x_days > 0 && duration <= Duration::from_secs(x_days * 24 * 60 * 60) && old_metadata == &new_metadata {
-                    println!("Restoring layer: duration is less than or equal to {} days and metadata matches", x_days);
-                    RestoredLayerAction::KeepLayer
-                } else {
-                    println!("Deleting layer: x_days = 0; duration is less than or equal to {} days and metadata matches", x_days);
-                    RestoredLayerAction::DeleteLayer
-                }                
+            restored_layer_action: &|old_metadata: &InstallationMetadata, layer_path| {
+                if old_metadata.is_distro_migration(&new_metadata) {
+                    return (
+                        RestoredLayerAction::DeleteLayer,
+                        (
+                            Some(DistroMigration::new(old_metadata, &new_metadata)),
+                            None,
+                        ),
+                    );
+                }
+
+                // the packages are the same but were built for another architecture so none of
+                // them can be kept
+                if old_metadata.distro.architecture != new_metadata.distro.architecture {
+                    return (RestoredLayerAction::DeleteLayer, (None, None));
+                }
+
+                // packages that were installed into the layer may now be provided by the base
+                // image (or be missing from it) so everything is installed again
+                if !old_metadata.has_same_system_packages(&new_metadata) {
+                    return (RestoredLayerAction::DeleteLayer, (None, None));
+                }
+
+                // the postinst scripts only run when a package is extracted so packages that
+                // opted in (or changed their failure policy) have to be installed again
+                if !old_metadata.has_same_maintainer_scripts(&new_metadata) {
+                    return (RestoredLayerAction::DeleteLayer, (None, None));
+                }
+
+                // the packaged policy is reinstalled since the one from the app replaced it
+                if old_metadata.removes_imagemagick_policy(&new_metadata) {
+                    return (RestoredLayerAction::DeleteLayer, (None, None));
+                }
+
+                if !old_metadata.is_within_cache_retention(
+                    SystemTime::now(),
+                    get_package_cache_days(context.platform.env()),
+                ) {
+                    return (RestoredLayerAction::DeleteLayer, (None, None));
+                }
+
+                // the files of packages that are no longer configured to be removed have to be
+                // extracted again
+                if old_metadata
+                    .removed_packages
+                    .iter()
+                    .any(|package_name| !new_metadata.removed_packages.contains(package_name))
+                {
+                    return (RestoredLayerAction::DeleteLayer, (None, None));
+                }
+
+                // the lockfile is written after everything else so a layer without a matching one
+                // is from a build that failed partway through installing
+                if old_metadata.is_same_installation(&new_metadata)
+                    && has_installed_lockfile(layer_path, &expected_lockfile)
+                {
+                    return (RestoredLayerAction::KeepLayer, (None, None));
+                }
+
+                // otherwise the layer is updated in place when the files of every installed
+                // package are known
+                match InstalledFiles::read(layer_path) {
+                    Some(installed_files) if layer_path.join(LOCKFILE_NAME).is_file() => (
+                        RestoredLayerAction::KeepLayer,
+                        (
+                            None,
+                            Some(IncrementalInstall {
+                                changes: PackageChanges::new(
+                                    &old_metadata.package_checksums,
+                                    &new_metadata.package_checksums,
+                                ),
+                                installed_files,
+                                installed_at: old_metadata.timestamp,
+                            }),
+                        ),
+                    ),
+                    _ => (RestoredLayerAction::DeleteLayer, (None, None)),
+                }
             },
         },
     )?;
 
-    match install_layer.state {
-        LayerState::Restored { .. } => {
-            log = packages_to_install
+    // the snapshot file mirrors the snapshot in the layer metadata so it's only written along with it
+    let mut installed_snapshot = None;
+    let (cached_packages, mut extracted_packages) = match &install_layer.state {
+        LayerState::Restored {
+            cause: (_, incremental_install),
+        } => {
+            record_cache_event("packages", true);
+
+            let (packages_to_update, packages_to_keep): (Vec<_>, Vec<_>) =
+                packages_to_install.iter().partition(|package_to_install| {
+                    incremental_install
+                        .as_ref()
+                        .is_some_and(|incremental_install| {
+                            incremental_install
+                                .changes
+                                .is_pending(&package_to_install.name)
+                        })
+                });
+
+            let requested_reinstalls = get_reinstall_packages(context.platform.env());
+            let (packages_to_reinstall, packages_to_restore): (Vec<_>, Vec<_>) = packages_to_keep
+                .into_iter()
+                .partition(|package_to_install| {
+                    requested_reinstalls.contains(&package_to_install.name)
+                });
+
+            log = packages_to_restore
                 .iter()
                 .fold(
                     log.bullet("Restoring packages from cache"),
@@ -132,87 +294,431 @@ pub(crate) async fn install_packages(
                     },
                 )
                 .done();
+
+            let cached_packages = packages_to_restore
+                .iter()
+                .map(|package| package.name.clone())
+                .collect::<IndexSet<_>>();
+
+            let mut extracted_packages = ExtractedPackages::default();
+            let mut installed_files = match incremental_install {
+                Some(incremental_install) => {
+                    let mut installed_files = incremental_install.installed_files.clone();
+                    let (updated_packages, update_log) = update_packages(
+                        context,
+                        client,
+                        &install_layer.path(),
+                        &incremental_install.changes,
+                        &mut installed_files,
+                        packages_to_update,
+                        existing_files_policy,
+                        &maintainer_scripts,
+                        log,
+                    )
+                    .await?;
+                    log = update_log;
+                    extracted_packages = updated_packages;
+
+                    // the packages that were kept are still refreshed when the original
+                    // installation falls out of the cache retention period
+                    install_layer.write_metadata(InstallationMetadata {
+                        timestamp: incremental_install.installed_at,
+                        ..new_metadata.clone()
+                    })?;
+                    installed_snapshot = new_metadata.snapshot.as_ref();
+                    Some(installed_files)
+                }
+                // layers installed before the files were recorded can't be updated in place
+                // so they're left without the record until they're reinstalled
+                None => InstalledFiles::read(&install_layer.path()),
+            };
+
+            let (reinstalled_packages, reinstall_log) = reinstall_packages(
+                context,
+                client,
+                &install_layer.path(),
+                &requested_reinstalls,
+                packages_to_reinstall,
+                &maintainer_scripts,
+                log,
+            )
+            .await?;
+            log = reinstall_log;
+
+            extracted_packages.extend(reinstalled_packages);
+            if let Some(installed_files) = &mut installed_files {
+                installed_files.extend(InstalledFiles::from_extracted_files(
+                    &extracted_packages.files,
+                    &extracted_packages.alternatives,
+                ));
+                write_installed_files(&install_layer.path(), installed_files).await?;
+            }
+
+            (cached_packages, extracted_packages)
         }
         LayerState::Empty { cause } => {
-            let install_log = packages_to_install.iter().fold(
-                log.bullet(match cause {
-                    EmptyLayerCause::NewlyCreated => "Requesting packages",
-                    EmptyLayerCause::InvalidMetadataAction { .. } => {
-                        "Requesting packages (invalid metadata)"
-                    }
-                    EmptyLayerCause::RestoredLayerAction { .. } => {
-                        "Requesting packages (packages changed)"
+            record_cache_event("packages", false);
+
+            if let EmptyLayerCause::RestoredLayerAction {
+                cause: (Some(distro_migration), _),
+            } = cause
+            {
+                log = log_distro_migration(distro_migration, log);
+            }
+
+            install_layer.write_metadata(new_metadata.clone())?;
+            installed_snapshot = new_metadata.snapshot.as_ref();
+
+            let shared_cache =
+                get_shared_cache_dir(context.platform.env()).map(|shared_cache_dir| {
+                    let cache_key = shared_cache_key(
+                        distro,
+                        &packages_to_install,
+                        &maintainer_scripts,
+                        existing_files_policy,
+                    );
+                    (shared_cache_dir, cache_key)
+                });
+
+            let imported = match &shared_cache {
+                Some((shared_cache_dir, cache_key)) => {
+                    match import_packages(shared_cache_dir, cache_key, &install_layer.path()).await
+                    {
+                        Ok(imported) => imported,
+                        Err(e) => {
+                            log = log.warning(format!("{e}. Packages will be downloaded instead."));
+                            false
+                        }
                     }
-                }),
-                |log, package_to_install| {
-                    log.sub_bullet(format!(
-                        "{name_with_version} from {url}",
-                        name_with_version = style::value(format!(
-                            "{name}@{version}",
-                            name = package_to_install.name,
-                            version = package_to_install.version
-                        )),
-                        url = style::url(build_download_url(package_to_install))
+                }
+                None => false,
+            };
+
+            if let (true, Some((shared_cache_dir, cache_key))) = (imported, &shared_cache) {
+                log = log
+                    .bullet("Imported packages from shared cache")
+                    .sub_bullet(style::value(
+                        shared_cache_archive_path(shared_cache_dir, cache_key).to_string_lossy(),
                     ))
-                },
-            );
+                    .done();
 
-            let timer = install_log.start_timer("Downloading");
-            install_layer.write_metadata(new_metadata)?;
+                // the imported packages were already inspected by the build that exported them
+                let imported_packages = packages_to_install
+                    .iter()
+                    .map(|package| package.name.clone())
+                    .collect::<IndexSet<_>>();
+                (
+                    imported_packages,
+                    imported_extracted_packages(&install_layer.path()),
+                )
+            } else {
+                let install_log = packages_to_install.iter().fold(
+                    log.bullet(match cause {
+                        EmptyLayerCause::NewlyCreated => "Requesting packages",
+                        EmptyLayerCause::InvalidMetadataAction { .. } => {
+                            "Requesting packages (invalid metadata)"
+                        }
+                        EmptyLayerCause::RestoredLayerAction {
+                            cause: (Some(_), _),
+                        } => "Requesting packages (distribution changed)",
+                        EmptyLayerCause::RestoredLayerAction { cause: (None, _) } => {
+                            "Requesting packages (packages changed)"
+                        }
+                    }),
+                    |log, package_to_install| {
+                        log.sub_bullet(format!(
+                            "{name_with_version} from {url}",
+                            name_with_version = style::value(format!(
+                                "{name}@{version}",
+                                name = package_to_install.name,
+                                version = package_to_install.version
+                            )),
+                            url = style::url(build_download_url(package_to_install))
+                        ))
+                    },
+                );
 
-            let mut download_and_extract_handles = JoinSet::new();
+                let download_cache_dir = download_cache_layer(context)?;
 
-            for repository_package in &packages_to_install {
-                download_and_extract_handles.spawn(download_and_extract(
-                    client.clone(),
-                    repository_package.clone(),
-                    install_layer.path(),
-                ));
-            }
+                let timer = install_log.start_timer("Downloading");
 
-            while let Some(download_and_extract_handle) =
-                download_and_extract_handles.join_next().await
-            {
-                download_and_extract_handle.map_err(InstallPackagesError::TaskFailed)??;
-            }
+                let extracted_packages = download_and_extract_all(
+                    client,
+                    packages_to_install.iter().cloned(),
+                    &install_layer.path(),
+                    &context.app_dir,
+                    &download_cache_dir,
+                    existing_files_policy,
+                    &maintainer_scripts,
+                )
+                .await?;
+
+                write_installed_files(
+                    &install_layer.path(),
+                    &InstalledFiles::from_extracted_files(
+                        &extracted_packages.files,
+                        &extracted_packages.alternatives,
+                    ),
+                )
+                .await?;
+
+                let mut download_log = log_checksum_retries(
+                    context.platform.env(),
+                    &extracted_packages,
+                    log_reused_downloads(&extracted_packages, timer.done()),
+                );
+                download_log =
+                    match prune_download_cache(&download_cache_dir, &packages_to_install).await {
+                        Ok(0) => download_log,
+                        Ok(removed) => download_log.sub_bullet(format!(
+                            "Removed {removed} unused {archives} from the download cache",
+                            archives = if removed == 1 { "archive" } else { "archives" }
+                        )),
+                        Err(e) => download_log.warning(format!(
+                            "Failed to remove unused archives from the download cache: {e}"
+                        )),
+                    };
+                log = download_log.done();
+
+                // exported before the layer environment and package configs are written since
+                // those are written on every build
+                if let Some((shared_cache_dir, cache_key)) = &shared_cache {
+                    log = match export_packages(shared_cache_dir, cache_key, &install_layer.path())
+                        .await
+                    {
+                        Ok(()) => log
+                            .bullet("Exported packages to shared cache")
+                            .sub_bullet(style::value(
+                                shared_cache_archive_path(shared_cache_dir, cache_key)
+                                    .to_string_lossy(),
+                            ))
+                            .done(),
+                        Err(e) => log.warning(e.to_string()),
+                    };
+                }
 
-            log = timer.done().done();
+                (IndexSet::new(), extracted_packages)
+            }
         }
+    };
+
+    // pruned before anything else inspects the layer since the files of these packages are gone
+    let removed_packages = if remove.is_empty() {
+        IndexSet::new()
+    } else {
+        let (removed_packages, remove_log) = remove_packages(
+            &install_layer.path(),
+            &remove,
+            &packages_to_install,
+            &mut extracted_packages,
+            log,
+        )
+        .await?;
+        log = remove_log;
+        removed_packages
+    };
+
+    log = print_existing_files(
+        existing_files_policy,
+        &extracted_packages.existing_files,
+        log,
+    );
+    log = print_script_failures(&extracted_packages.script_failures, log);
+
+    // created before inspecting the extracted files so links into /etc/alternatives shipped by a
+    // package are replaced by then
+    let created_alternatives =
+        create_alternatives(&install_layer.path(), &extracted_packages.alternatives)
+            .map_err(InstallPackagesError::CreateAlternatives)?;
+    if !created_alternatives.is_empty() {
+        log = created_alternatives
+            .iter()
+            .fold(log.bullet("Creating alternatives"), |log, alternative| {
+                log.sub_bullet(alternative.to_string())
+            })
+            .done();
     }
 
-    // Convert package_env_vars to the correct type and replace {install_dir} with the actual path
-    let install_dir = install_layer.path().to_string_lossy().to_string();
-    let package_env_vars: HashMap<String, HashMap<String, String>> = package_env_vars()
-        .into_iter()
-        .map(|(k, v)| {
-            (
-                k.to_string(),
-                v.into_iter()
-                    .map(|(k, v)| (k.to_string(), v.replace("{install_dir}", &install_dir)))
-                    .collect(),
+    let reused_downloads = extracted_packages.reused_downloads;
+
+    // packages restored from the cache were already inspected when they were extracted
+    let extracted_files = extracted_packages.files;
+    let extraction_issues = find_extraction_issues(&install_layer.path(), &extracted_files);
+    log = print_extraction_issues(&extraction_issues, log);
+    if strict_extraction && !extraction_issues.is_empty() {
+        Err(InstallPackagesError::ExtractionIssues(extraction_issues))?;
+    }
+
+    // run on every build since the layer (e.g.; the generated Java keystore) is kept with the
+    // cached packages but the files they write may have been produced by an older version
+    let integrations = package_integrations(&IntegrationSettings {
+        locales,
+        timezone: context.platform.env().get_string_lossy("TZ"),
+        imagemagick_policy: imagemagick_policy.map(|policy_file| context.app_dir.join(policy_file)),
+    });
+    let installed_package_names = packages_to_install
+        .iter()
+        .map(|package| package.name.as_str())
+        .filter(|package_name| !removed_packages.contains(*package_name))
+        .collect::<Vec<_>>();
+    let post_installs = run_post_install(
+        &integrations,
+        &install_layer.path(),
+        &installed_package_names,
+    )
+    .map_err(InstallPackagesError::PackageIntegration)?;
+    for (integration, _, post_install) in &post_installs {
+        log = post_install
+            .messages
+            .iter()
+            .fold(
+                log.bullet(format!("Configuring {}", integration.name())),
+                Print::sub_bullet,
             )
-        })
-        .collect();    
+            .done();
+    }
+
+    let package_env_vars = package_env_vars(
+        &integrations,
+        &install_layer.path(),
+        installed_package_names
+            .iter()
+            .copied()
+            .chain(skipped_packages.iter().map(|package| package.name.as_str())),
+    );
 
     // go ahead and read/load the project.toml file
     let env_file_path = context.app_dir.join("project.toml");
     let env = Environment::load_from_toml(&env_file_path, &install_layer.path().to_string_lossy());
 
     // Define layer_env before using it
-    let layer_env = configure_layer_environment(
+    let mut layer_env = configure_layer_environment(
         &install_layer.path(),
-        &MultiarchName::from(&distro.architecture),
+        &std::iter::once(&distro.architecture)
+            .chain(&foreign_architectures)
+            .map(MultiarchName::from)
+            .collect::<Vec<_>>(),
         &package_env_vars,
         &packages_to_install,
         &skipped_packages,
         &env,
+        &env_scopes,
     );
 
+    for (_, package_name, post_install) in &post_installs {
+        let scope = package_name.map_or_else(
+            || env_scopes.default_scope(),
+            |package_name| env_scopes.package_scope(package_name),
+        );
+        for (name, option) in &post_install.options {
+            layer_env.insert(scope.clone(), ModificationBehavior::Delimiter, name, " ");
+            layer_env.insert(scope.clone(), ModificationBehavior::Append, name, option);
+        }
+        for (name, value) in &post_install.env_vars {
+            layer_env.insert(scope.clone(), ModificationBehavior::Override, name, value);
+        }
+    }
+
+    if verify_layout {
+        log = verify_installed_layout(
+            &install_layer.path(),
+            &extracted_files,
+            &package_env_vars,
+            &env,
+            &packages_to_install,
+            &layer_env,
+            log,
+        )?;
+    }
+
+    // the exec.d directory is always replaced so disabling the self-check removes it from a
+    // restored layer
+    if self_check {
+        let self_check = SelfCheck::new(distro, &install_layer.path(), &layer_env);
+        let self_check_dir = tempfile::tempdir()
+            .map_err(|e| InstallPackagesError::WriteSelfCheck(std::env::temp_dir(), e))?;
+        let script_path = self_check_dir.path().join(SELF_CHECK_PROGRAM_NAME);
+        write_self_check_script(&self_check, &script_path)
+            .map_err(|e| InstallPackagesError::WriteSelfCheck(script_path.clone(), e))?;
+        install_layer.write_exec_d_programs([(SELF_CHECK_PROGRAM_NAME, script_path)])?;
+        log = log
+            .bullet("Adding launch self-check")
+            .sub_bullet(format!(
+                "Checking the run image is {} with {} exported paths and {} binaries",
+                style::value(format!(
+                    "{} {}",
+                    self_check.distro_id, self_check.distro_codename
+                )),
+                self_check.paths.len(),
+                self_check.binaries.len()
+            ))
+            .done();
+    } else {
+        install_layer.write_exec_d_programs(HashMap::<String, PathBuf>::new())?;
+    }
+
+    if is_buildpack_debug_logging_enabled(context.platform.env()) {
+        log = print_layer_env(&layer_env, log);
+    }
     install_layer.write_env(layer_env)?;
     rewrite_package_configs(&install_layer.path()).await?;
 
+    // a layer that's reused unchanged keeps the snapshot of the build that installed it
+    write_snapshot_file(&install_layer.path(), installed_snapshot).await?;
+    let lockfile_path = write_lockfile(&install_layer.path(), distro, &packages_to_install).await?;
+
+    let sboms = [
+        create_cyclonedx_sbom(distro, &packages_to_install),
+        create_spdx_sbom(distro, &packages_to_install, &install_layer.path()),
+    ]
+    .into_iter()
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(InstallPackagesError::SerializeSbom)?;
+    install_layer.write_sboms(&sboms)?;
+
+    // written last since the layer digest it contains covers everything else in the layer
+    let provenance_path = install_layer.path().join(PROVENANCE_FILE_NAME);
+    let provenance_snapshot = new_metadata.snapshot.as_ref().filter(|_| provenance);
+    if let Some(snapshot) = provenance_snapshot {
+        write_provenance_file(
+            &install_layer.path(),
+            &provenance_path,
+            distro,
+            &packages_to_install,
+            snapshot,
+            &lockfile_path,
+        )?;
+    } else if provenance_path.exists() {
+        remove_file(&provenance_path)
+            .map_err(|e| InstallPackagesError::WriteProvenance(provenance_path.clone(), e))?;
+    }
+
+    log = print_install_summary(
+        &packages_to_install,
+        &cached_packages,
+        &reused_downloads,
+        log,
+    );
+
     let mut install_log = log.bullet("Installation complete");
-    if is_buildpack_debug_logging_enabled() {
+    // the packages were pinned to the app's lockfile so the one written here has the same contents
+    if locked {
+        install_log = install_log.sub_bullet(format!(
+            "Installed the versions locked in {lockfile}",
+            lockfile = style::value(LOCKFILE_NAME)
+        ));
+    }
+    install_log = install_log.sub_bullet(format!(
+        "Wrote lockfile to {lockfile_path}",
+        lockfile_path = style::value(lockfile_path.to_string_lossy())
+    ));
+    if provenance_snapshot.is_some() {
+        install_log = install_log.sub_bullet(format!(
+            "Wrote provenance to {provenance_path}",
+            provenance_path = style::value(provenance_path.to_string_lossy())
+        ));
+    }
+    if is_buildpack_debug_logging_enabled(context.platform.env()) {
         install_log = print_layer_contents(&install_layer.path(), install_log);
     }
     log = install_log.done();
@@ -220,6 +726,376 @@ pub(crate) async fn install_packages(
     Ok(log)
 }
 
+// A restored packages layer that's updated in place by removing the files of the packages that
+// are no longer installed (or were upgraded) and extracting the added and upgraded packages.
+#[derive(Debug, Clone)]
+struct IncrementalInstall {
+    changes: PackageChanges,
+    installed_files: InstalledFiles,
+    installed_at: u64,
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn update_packages(
+    context: &Arc<BuildContext<DebianPackagesBuildpack>>,
+    client: &ClientWithMiddleware,
+    install_path: &Path,
+    changes: &PackageChanges,
+    installed_files: &mut InstalledFiles,
+    packages_to_update: Vec<&RepositoryPackage>,
+    existing_files_policy: ExistingFilesPolicy,
+    maintainer_scripts: &MaintainerScripts,
+    log: Print<Bullet<Stdout>>,
+) -> BuildpackResult<(ExtractedPackages, Print<Bullet<Stdout>>)> {
+    let mut update_log = log.bullet(format!("Updating packages ({changes})"));
+
+    for removed_package in &changes.removed {
+        update_log = update_log.sub_bullet(format!(
+            "Removing {package}",
+            package = style::value(removed_package)
+        ));
+    }
+    for package_to_update in &packages_to_update {
+        update_log = update_log.sub_bullet(format!(
+            "{action} {name_with_version} from {url}",
+            action = if changes.upgraded.contains(&package_to_update.name) {
+                "Upgrading"
+            } else {
+                "Adding"
+            },
+            name_with_version = style::value(format!(
+                "{name}@{version}",
+                name = package_to_update.name,
+                version = package_to_update.version
+            )),
+            url = style::url(build_download_url(package_to_update))
+        ));
+    }
+
+    installed_files
+        .remove_packages(install_path, &changes.outdated())
+        .await
+        .map_err(InstallPackagesError::RemovePackageFiles)?;
+
+    if packages_to_update.is_empty() {
+        return Ok((ExtractedPackages::default(), update_log.done()));
+    }
+
+    let download_cache_dir = download_cache_layer(context)?;
+    let timer = update_log.start_timer("Downloading");
+    let updated_packages = download_and_extract_all(
+        client,
+        packages_to_update.into_iter().cloned(),
+        install_path,
+        &context.app_dir,
+        &download_cache_dir,
+        existing_files_policy,
+        maintainer_scripts,
+    )
+    .await?;
+    let update_log = log_checksum_retries(
+        context.platform.env(),
+        &updated_packages,
+        log_reused_downloads(&updated_packages, timer.done()),
+    );
+    Ok((updated_packages, update_log.done()))
+}
+
+// Prunes the files of packages that were pulled in as dependencies but aren't needed at runtime.
+// The record of installed files is updated so these packages are only pruned once when the layer is
+// reused and their files are removed again whenever they're extracted (e.g.; after an upgrade).
+async fn remove_packages(
+    install_path: &Path,
+    remove: &IndexSet<PackageName>,
+    packages_to_install: &[RepositoryPackage],
+    extracted_packages: &mut ExtractedPackages,
+    log: Print<Bullet<Stdout>>,
+) -> BuildpackResult<(IndexSet<String>, Print<Bullet<Stdout>>)> {
+    let mut remove_log = log.bullet("Removing packages");
+
+    let Some(mut installed_files) = InstalledFiles::read(install_path) else {
+        let log = remove_log
+            .warning(
+                "The installed files aren't recorded for the cached packages so none were removed",
+            )
+            .done();
+        return Ok((IndexSet::new(), log));
+    };
+
+    let mut removed_packages = IndexSet::new();
+    for package_name in remove.iter().map(PackageName::as_str) {
+        if !packages_to_install
+            .iter()
+            .any(|package| package.name == package_name)
+        {
+            remove_log = remove_log.sub_bullet(format!(
+                "Skipping {package} since it isn't installed",
+                package = style::value(package_name)
+            ));
+            continue;
+        }
+
+        removed_packages.insert(package_name.to_string());
+        if !installed_files.packages.contains_key(package_name) {
+            remove_log = remove_log.sub_bullet(format!(
+                "Already removed {package} from the cached packages",
+                package = style::value(package_name)
+            ));
+            continue;
+        }
+
+        let RemovedFiles { files, bytes } = installed_files
+            .remove_packages(install_path, &[package_name.to_string()])
+            .await
+            .map_err(InstallPackagesError::RemovePackageFiles)?;
+        extracted_packages.files.remove(package_name);
+        extracted_packages.alternatives.remove(package_name);
+        remove_log = remove_log.sub_bullet(format!(
+            "Removed {package} ({files} {noun}, {size} freed)",
+            package = style::value(package_name),
+            noun = if files == 1 { "file" } else { "files" },
+            size = format_size(bytes)
+        ));
+    }
+
+    write_installed_files(install_path, &installed_files).await?;
+    Ok((removed_packages, remove_log.done()))
+}
+
+// The archive in the shared cache is exported before the alternatives are created so they're
+// read back from the installed files record to be created for the imported layer.
+fn imported_extracted_packages(install_path: &Path) -> ExtractedPackages {
+    ExtractedPackages {
+        alternatives: InstalledFiles::read(install_path)
+            .map(|installed_files| installed_files.alternatives)
+            .unwrap_or_default(),
+        ..ExtractedPackages::default()
+    }
+}
+
+async fn write_installed_files(
+    install_path: &Path,
+    installed_files: &InstalledFiles,
+) -> Result<(), InstallPackagesError> {
+    installed_files.write(install_path).await.map_err(|e| {
+        InstallPackagesError::WriteInstalledFiles(install_path.join(INSTALLED_FILES_NAME), e)
+    })
+}
+
+async fn reinstall_packages(
+    context: &Arc<BuildContext<DebianPackagesBuildpack>>,
+    client: &ClientWithMiddleware,
+    install_path: &Path,
+    requested_reinstalls: &IndexSet<String>,
+    packages_to_reinstall: Vec<&RepositoryPackage>,
+    maintainer_scripts: &MaintainerScripts,
+    log: Print<Bullet<Stdout>>,
+) -> BuildpackResult<(ExtractedPackages, Print<Bullet<Stdout>>)> {
+    if requested_reinstalls.is_empty() {
+        return Ok((ExtractedPackages::default(), log));
+    }
+
+    let mut reinstall_log = log.bullet(format!(
+        "Reinstalling packages requested by {env_var}",
+        env_var = style::value("BP_DEB_PACKAGES_REINSTALL")
+    ));
+
+    for package_name in requested_reinstalls {
+        if !packages_to_reinstall
+            .iter()
+            .any(|package| &package.name == package_name)
+        {
+            reinstall_log = reinstall_log.sub_bullet(format!(
+                "Skipping {package} because it's not in the set of packages to install",
+                package = style::value(package_name)
+            ));
+        }
+    }
+
+    for package_to_reinstall in &packages_to_reinstall {
+        reinstall_log = reinstall_log.sub_bullet(format!(
+            "{name_with_version} from {url}",
+            name_with_version = style::value(format!(
+                "{name}@{version}",
+                name = package_to_reinstall.name,
+                version = package_to_reinstall.version
+            )),
+            url = style::url(build_download_url(package_to_reinstall))
+        ));
+    }
+
+    if packages_to_reinstall.is_empty() {
+        return Ok((ExtractedPackages::default(), reinstall_log.done()));
+    }
+
+    // the archives are downloaded again instead of being taken from the download cache since a
+    // reinstall is usually requested because the cached copy is suspect
+    let download_dir = tempfile::tempdir()
+        .map_err(|e| InstallPackagesError::CreateDownloadDir(std::env::temp_dir(), e))?;
+    let timer = reinstall_log.start_timer("Downloading");
+    let reinstalled_packages = download_and_extract_all(
+        client,
+        packages_to_reinstall.into_iter().cloned(),
+        install_path,
+        &context.app_dir,
+        download_dir.path(),
+        // the files being replaced are the package's own
+        ExistingFilesPolicy::Overwrite,
+        maintainer_scripts,
+    )
+    .await?;
+    let reinstall_log = log_checksum_retries(
+        context.platform.env(),
+        &reinstalled_packages,
+        log_reused_downloads(&reinstalled_packages, timer.done()),
+    );
+    Ok((reinstalled_packages, reinstall_log.done()))
+}
+
+// Prints one aligned row per installed package so the contents of the layer and where each package
+// came from can be audited at a glance from the build output.
+fn print_install_summary(
+    packages_to_install: &[RepositoryPackage],
+    cached_packages: &IndexSet<String>,
+    reused_downloads: &IndexSet<String>,
+    log: Print<Bullet<Stdout>>,
+) -> Print<Bullet<Stdout>> {
+    let header = [
+        "Package".to_string(),
+        "Version".to_string(),
+        "Source".to_string(),
+        "Suite".to_string(),
+        "Size".to_string(),
+        "Cache".to_string(),
+    ];
+    let rows = packages_to_install
+        .iter()
+        .map(|package| {
+            [
+                package.name.clone(),
+                package.version.clone(),
+                package.repository_uri.as_str().to_string(),
+                if package.suite.is_empty() {
+                    "-".to_string()
+                } else {
+                    package.suite.clone()
+                },
+                package.size.map_or("-".to_string(), format_size),
+                if cached_packages.contains(&package.name) {
+                    "cached".to_string()
+                } else if reused_downloads.contains(&package.name) {
+                    "download cache".to_string()
+                } else {
+                    "downloaded".to_string()
+                },
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let widths = rows.iter().fold(
+        header.clone().map(|column| column.len()),
+        |mut widths, row| {
+            for (width, column) in widths.iter_mut().zip(row) {
+                *width = (*width).max(column.len());
+            }
+            widths
+        },
+    );
+
+    std::iter::once(&header)
+        .chain(rows.iter())
+        .fold(log.bullet("Installation summary"), |log, row| {
+            log.sub_bullet(
+                row.iter()
+                    .zip(widths)
+                    .map(|(column, width)| format!("{column:<width$}"))
+                    .collect::<Vec<_>>()
+                    .join("  ")
+                    .trim_end(),
+            )
+        })
+        .done()
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} {}", UNITS[unit])
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+// Written into the layer alongside the installed packages so the inputs used to produce the layer
+// can be inspected from the resulting image.
+pub(crate) const SNAPSHOT_FILE_NAME: &str = ".deb-packages-snapshot.toml";
+
+async fn write_snapshot_file(
+    install_path: &Path,
+    snapshot: Option<&InstallSnapshot>,
+) -> BuildpackResult<()> {
+    let Some(snapshot) = snapshot else {
+        return Ok(());
+    };
+    let snapshot_path = install_path.join(SNAPSHOT_FILE_NAME);
+    let contents = toml::to_string(snapshot)
+        .map_err(|e| InstallPackagesError::SerializeSnapshot(snapshot_path.clone(), e))?;
+    async_write(&snapshot_path, contents)
+        .await
+        .map_err(|e| InstallPackagesError::WriteSnapshot(snapshot_path, e))?;
+    Ok(())
+}
+
+fn has_installed_lockfile(install_path: &Path, expected_lockfile: &Lockfile) -> bool {
+    read_to_string(install_path.join(LOCKFILE_NAME))
+        .ok()
+        .and_then(|contents| Lockfile::from_toml(&contents).ok())
+        .is_some_and(|lockfile| lockfile == *expected_lockfile)
+}
+
+async fn write_lockfile(
+    install_path: &Path,
+    distro: &Distro,
+    packages_to_install: &[RepositoryPackage],
+) -> BuildpackResult<PathBuf> {
+    let lockfile_path = install_path.join(LOCKFILE_NAME);
+    let contents = Lockfile::new(distro, packages_to_install)
+        .to_toml()
+        .map_err(|e| InstallPackagesError::SerializeLockfile(lockfile_path.clone(), e))?;
+    async_write(&lockfile_path, contents)
+        .await
+        .map_err(|e| InstallPackagesError::WriteLockfile(lockfile_path.clone(), e))?;
+    Ok(lockfile_path)
+}
+
+fn write_provenance_file(
+    install_path: &Path,
+    provenance_path: &Path,
+    distro: &Distro,
+    packages_to_install: &[RepositoryPackage],
+    snapshot: &InstallSnapshot,
+    lockfile_path: &Path,
+) -> BuildpackResult<()> {
+    let to_error = |e| InstallPackagesError::WriteProvenance(provenance_path.to_path_buf(), e);
+    let contents = create_provenance(
+        distro,
+        packages_to_install,
+        snapshot,
+        &layer_digest(install_path).map_err(to_error)?,
+        &file_digest(lockfile_path).map_err(to_error)?,
+    )
+    .map_err(InstallPackagesError::SerializeProvenance)?;
+    write(provenance_path, contents).map_err(to_error)?;
+    Ok(())
+}
+
 fn print_layer_contents(
     install_path: &Path,
     log: Print<SubBullet<Stdout>>,
@@ -245,45 +1121,550 @@ fn print_layer_contents(
     directory_log.done()
 }
 
-async fn download_and_extract(
+// Shows the values this layer exports so problems like a binary that isn't found at launch can be
+// debugged from the build output. Other layers and the base image can add to these at runtime.
+fn print_layer_env(layer_env: &LayerEnv, log: Print<Bullet<Stdout>>) -> Print<Bullet<Stdout>> {
+    let build_env = layer_env_values(layer_env, Scope::Build);
+    let launch_env = layer_env_values(layer_env, Scope::Launch);
+    if build_env == launch_env {
+        print_env_values("Exported environment", &launch_env, log)
+    } else {
+        let log = print_env_values("Exported environment at build", &build_env, log);
+        print_env_values("Exported environment at launch", &launch_env, log)
+    }
+}
+
+fn print_env_values(
+    title: &str,
+    env_values: &[(String, String)],
+    log: Print<Bullet<Stdout>>,
+) -> Print<Bullet<Stdout>> {
+    let log = log.bullet(title);
+    if env_values.is_empty() {
+        return log.sub_bullet("Nothing exported").done();
+    }
+    env_values
+        .iter()
+        .fold(log, |log, (name, value)| {
+            log.sub_bullet(style::value(format!("{name}={value}")))
+        })
+        .done()
+}
+
+// Sorted by name since the layer environment doesn't keep the order variables were added in.
+fn layer_env_values(layer_env: &LayerEnv, scope: Scope) -> Vec<(String, String)> {
+    let mut env_values = layer_env
+        .apply_to_empty(scope)
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string_lossy().to_string(),
+                value.to_string_lossy().to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+    env_values.sort();
+    env_values
+}
+
+// The files extracted and alternatives registered by each package.
+#[derive(Debug, Default)]
+struct ExtractedPackages {
+    files: BTreeMap<String, Vec<PathBuf>>,
+    // files that were already in the layer when the package was extracted
+    existing_files: BTreeMap<String, Vec<PathBuf>>,
+    alternatives: BTreeMap<String, Vec<Alternative>>,
+    // postinst scripts that failed for packages whose policy is to only warn about it
+    script_failures: BTreeMap<String, ScriptFailure>,
+    // packages whose archive was found in the download cache instead of being downloaded
+    reused_downloads: IndexSet<String>,
+    // packages that were downloaded again after the first download failed checksum verification
+    checksum_retries: IndexSet<String>,
+}
+
+impl ExtractedPackages {
+    fn extend(&mut self, other: ExtractedPackages) {
+        self.files.extend(other.files);
+        self.existing_files.extend(other.existing_files);
+        self.alternatives.extend(other.alternatives);
+        self.script_failures.extend(other.script_failures);
+        self.reused_downloads.extend(other.reused_downloads);
+        self.checksum_retries.extend(other.checksum_retries);
+    }
+}
+
+// The download cache is only needed when packages are downloaded so it's not restored for builds
+// that reuse the packages layer.
+fn download_cache_layer(
+    context: &Arc<BuildContext<DebianPackagesBuildpack>>,
+) -> BuildpackResult<PathBuf> {
+    let new_metadata = DownloadCacheMetadata {
+        layout_version: DOWNLOAD_CACHE_LAYOUT_VERSION,
+    };
+
+    let download_cache_layer = context.cached_layer(
+        layer_name!("downloads"),
+        CachedLayerDefinition {
+            build: false,
+            launch: false,
+            invalid_metadata_action: &|_| InvalidMetadataAction::DeleteLayer,
+            restored_layer_action: &|old_metadata: &DownloadCacheMetadata, _| {
+                if old_metadata == &new_metadata {
+                    RestoredLayerAction::KeepLayer
+                } else {
+                    RestoredLayerAction::DeleteLayer
+                }
+            },
+        },
+    )?;
+
+    match download_cache_layer.state {
+        LayerState::Restored { .. } => record_cache_event("downloads", true),
+        LayerState::Empty { .. } => {
+            record_cache_event("downloads", false);
+            download_cache_layer.write_metadata(new_metadata)?;
+        }
+    }
+
+    Ok(download_cache_layer.path())
+}
+
+fn log_reused_downloads(
+    extracted_packages: &ExtractedPackages,
+    log: Print<SubBullet<Stdout>>,
+) -> Print<SubBullet<Stdout>> {
+    match extracted_packages.reused_downloads.len() {
+        0 => log,
+        1 => log.sub_bullet("Reused 1 package from the download cache"),
+        reused => log.sub_bullet(format!("Reused {reused} packages from the download cache")),
+    }
+}
+
+// Only reported when debug logging is enabled since the retried download was verified.
+fn log_checksum_retries(
+    env: &Env,
+    extracted_packages: &ExtractedPackages,
+    log: Print<SubBullet<Stdout>>,
+) -> Print<SubBullet<Stdout>> {
+    if !is_buildpack_debug_logging_enabled(env) || extracted_packages.checksum_retries.is_empty() {
+        return log;
+    }
+    log.sub_bullet(format!(
+        "Downloaded {packages} again after the checksum of the first download didn't match",
+        packages = extracted_packages
+            .checksum_retries
+            .iter()
+            .map(style::value)
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+// Existing files are reported by package with only the first few paths listed since a package
+// colliding with a vendored library usually ships many related files.
+fn print_existing_files(
+    existing_files_policy: ExistingFilesPolicy,
+    existing_files: &BTreeMap<String, Vec<PathBuf>>,
+    log: Print<Bullet<Stdout>>,
+) -> Print<Bullet<Stdout>> {
+    if existing_files.is_empty() {
+        return log;
+    }
+
+    existing_files
+        .iter()
+        .fold(
+            log.bullet(format!(
+                "Found existing files ({key} = {policy})",
+                key = style::value("existing_files"),
+                policy = style::value(existing_files_policy.to_string())
+            )),
+            |log, (package_name, files)| {
+                let listed_files = files
+                    .iter()
+                    .take(MAX_LISTED_EXISTING_FILES)
+                    .map(|file| file.to_string_lossy().trim_start_matches("./").to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let listed_files = match files.len().saturating_sub(MAX_LISTED_EXISTING_FILES) {
+                    0 => listed_files,
+                    more => format!("{listed_files} and {more} more"),
+                };
+                let count = match files.len() {
+                    1 => "1 existing file".to_string(),
+                    n => format!("{n} existing files"),
+                };
+                let package = style::value(package_name);
+                log.sub_bullet(match existing_files_policy {
+                    ExistingFilesPolicy::Overwrite => {
+                        format!("Replaced {count} with the ones from {package}: {listed_files}")
+                    }
+                    ExistingFilesPolicy::Skip | ExistingFilesPolicy::Error => {
+                        format!("Kept {count} instead of the ones from {package}: {listed_files}")
+                    }
+                })
+            },
+        )
+        .done()
+}
+
+const MAX_LISTED_EXISTING_FILES: usize = 3;
+
+// Only the last line of the script's output is shown since it usually explains the failure. The
+// full output is included in the error when the policy is to fail the build instead.
+fn print_script_failures(
+    script_failures: &BTreeMap<String, ScriptFailure>,
+    log: Print<Bullet<Stdout>>,
+) -> Print<Bullet<Stdout>> {
+    if script_failures.is_empty() {
+        return log;
+    }
+
+    script_failures
+        .iter()
+        .fold(
+            log.bullet(format!(
+                "Continued after failing postinst scripts ({key} = {policy})",
+                key = style::value("on_script_failure"),
+                policy = style::value(ScriptFailurePolicy::Warn.to_string())
+            )),
+            |log, (package_name, script_failure)| {
+                let message = format!(
+                    "The postinst script of {package} failed with {exit}",
+                    package = style::value(package_name),
+                    exit = script_failure.exit_description()
+                );
+                log.sub_bullet(match script_failure.last_output_line() {
+                    Some(line) => format!("{message}: {line}"),
+                    None => message,
+                })
+            },
+        )
+        .done()
+}
+
+#[allow(clippy::too_many_lines)]
+async fn download_and_extract_all(
+    client: &ClientWithMiddleware,
+    repository_packages: impl IntoIterator<Item = RepositoryPackage>,
+    install_dir: &Path,
+    app_dir: &Path,
+    download_cache_dir: &Path,
+    existing_files_policy: ExistingFilesPolicy,
+    maintainer_scripts: &MaintainerScripts,
+) -> BuildpackResult<ExtractedPackages> {
+    let repository_packages = repository_packages.into_iter().collect::<Vec<_>>();
+    let mut download_handles = JoinSet::new();
+
+    for repository_package in &repository_packages {
+        download_handles.spawn(download_with_retry(
+            client.clone(),
+            repository_package.clone(),
+            download_cache_dir.to_path_buf(),
+        ));
+    }
+
+    // wait for every package to finish so all the failures can be reported at once instead of
+    // only the first one
+    let mut failed_packages = vec![];
+    let mut downloaded_packages = HashMap::new();
+    while let Some(download_handle) = download_handles.join_next().await {
+        let (package_name, result) = download_handle.map_err(InstallPackagesError::TaskFailed)?;
+        match result {
+            Ok(downloaded_package) => {
+                downloaded_packages.insert(package_name, downloaded_package);
+            }
+            Err(error) => failed_packages.push((package_name, error)),
+        }
+    }
+
+    // the packages are extracted in parallel so the files that already exist and the package that
+    // writes a file shipped by several of them are decided up front, in package order, instead of
+    // depending on which extraction happens to finish first
+    let mut package_files = vec![];
+    for repository_package in &repository_packages {
+        if let Some(downloaded_package) = downloaded_packages.get(&repository_package.name) {
+            match list_data_files(&downloaded_package.path).await {
+                Ok(files) => package_files.push((repository_package.name.clone(), files)),
+                Err(error) => failed_packages.push((repository_package.name.clone(), error)),
+            }
+        }
+    }
+    let mut extraction_plans = plan_extraction(
+        &package_files,
+        |path| is_existing_file(install_dir, path) || is_existing_file(app_dir, path),
+        existing_files_policy,
+    );
+
+    let mut extract_handles = JoinSet::new();
+    for repository_package in repository_packages {
+        let package_name = repository_package.name;
+        let (Some(downloaded_package), Some(extraction_plan)) = (
+            downloaded_packages.remove(&package_name),
+            extraction_plans.remove(&package_name),
+        ) else {
+            continue;
+        };
+        if existing_files_policy == ExistingFilesPolicy::Error
+            && !extraction_plan.existing_files.is_empty()
+        {
+            failed_packages.push((
+                package_name.clone(),
+                InstallPackagesError::ExistingFiles(package_name, extraction_plan.existing_files),
+            ));
+            continue;
+        }
+        let script_failure_policy = maintainer_scripts.failure_policy(&package_name);
+        extract_handles.spawn(extract_package(
+            package_name,
+            downloaded_package,
+            extraction_plan,
+            install_dir.to_path_buf(),
+            existing_files_policy,
+            script_failure_policy,
+        ));
+    }
+
+    let mut extracted_packages = ExtractedPackages::default();
+    while let Some(extract_handle) = extract_handles.join_next().await {
+        let (package_name, result) = extract_handle.map_err(InstallPackagesError::TaskFailed)?;
+        match result {
+            Ok(extracted_package) => {
+                if extracted_package.reused_download {
+                    extracted_packages
+                        .reused_downloads
+                        .insert(package_name.clone());
+                }
+                if extracted_package.checksum_retried {
+                    extracted_packages
+                        .checksum_retries
+                        .insert(package_name.clone());
+                }
+                if !extracted_package.alternatives.is_empty() {
+                    extracted_packages
+                        .alternatives
+                        .insert(package_name.clone(), extracted_package.alternatives);
+                }
+                if !extracted_package.existing_files.is_empty() {
+                    extracted_packages
+                        .existing_files
+                        .insert(package_name.clone(), extracted_package.existing_files);
+                }
+                if let Some(script_failure) = extracted_package.script_failure {
+                    extracted_packages
+                        .script_failures
+                        .insert(package_name.clone(), script_failure);
+                }
+                extracted_packages
+                    .files
+                    .insert(package_name, extracted_package.files);
+            }
+            Err(error) => failed_packages.push((package_name, error)),
+        }
+    }
+    failed_packages.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    match failed_packages.len() {
+        0 => Ok(extracted_packages),
+        1 => Err(failed_packages.remove(0).1)?,
+        _ => Err(InstallPackagesError::MultiplePackagesFailed(
+            failed_packages,
+        ))?,
+    }
+}
+
+// The files of a package that already exist in the layer or the app directory and the files it
+// shouldn't write because they're kept or written by another package.
+#[derive(Debug, Default, PartialEq)]
+struct ExtractionPlan {
+    existing_files: Vec<PathBuf>,
+    skipped_files: HashSet<PathBuf>,
+}
+
+// A file is existing when it was there before the extraction or an earlier package ships it. When
+// the policy is to overwrite, the last package shipping a file writes it, otherwise the file is
+// only written when it doesn't exist yet, so by the first package shipping it.
+fn plan_extraction(
+    package_files: &[(String, Vec<PathBuf>)],
+    is_existing: impl Fn(&Path) -> bool,
+    existing_files_policy: ExistingFilesPolicy,
+) -> HashMap<String, ExtractionPlan> {
+    let mut writers = HashMap::new();
+    let mut extraction_plans: HashMap<String, ExtractionPlan> = HashMap::new();
+    for (package_name, files) in package_files {
+        let extraction_plan = extraction_plans.entry(package_name.clone()).or_default();
+        for file in files {
+            if writers.contains_key(file) || is_existing(file) {
+                extraction_plan.existing_files.push(file.clone());
+                if existing_files_policy != ExistingFilesPolicy::Overwrite {
+                    continue;
+                }
+            }
+            writers.insert(file, package_name);
+        }
+    }
+    for (package_name, files) in package_files {
+        if let Some(extraction_plan) = extraction_plans.get_mut(package_name) {
+            extraction_plan.skipped_files.extend(
+                files
+                    .iter()
+                    .filter(|file| writers.get(file) != Some(&package_name))
+                    .cloned(),
+            );
+        }
+    }
+    extraction_plans
+}
+
+fn is_existing_file(dir: &Path, path: &Path) -> bool {
+    std::fs::symlink_metadata(dir.join(path)).is_ok_and(|metadata| !metadata.is_dir())
+}
+
+#[derive(Debug, Default)]
+struct ExtractedPackage {
+    files: Vec<PathBuf>,
+    existing_files: Vec<PathBuf>,
+    alternatives: Vec<Alternative>,
+    script_failure: Option<ScriptFailure>,
+    reused_download: bool,
+    checksum_retried: bool,
+}
+
+// The exit status and output of a postinst script that didn't succeed.
+#[derive(Debug)]
+pub(crate) struct ScriptFailure {
+    pub(crate) status: ExitStatus,
+    pub(crate) stdout: String,
+    pub(crate) stderr: String,
+}
+
+impl ScriptFailure {
+    pub(crate) fn exit_description(&self) -> String {
+        match self.status.code() {
+            Some(code) => format!("exit code {code}"),
+            // scripts killed by a signal don't have an exit code
+            None => self.status.to_string(),
+        }
+    }
+
+    fn last_output_line(&self) -> Option<&str> {
+        [&self.stderr, &self.stdout]
+            .into_iter()
+            .find_map(|output| output.lines().map(str::trim).rfind(|line| !line.is_empty()))
+    }
+}
+
+#[derive(Debug)]
+struct DownloadedPackage {
+    path: PathBuf,
+    reused_download: bool,
+    checksum_retried: bool,
+}
+
+async fn download_with_retry(
     client: ClientWithMiddleware,
     repository_package: RepositoryPackage,
+    download_cache_dir: PathBuf,
+) -> (String, Result<DownloadedPackage, InstallPackagesError>) {
+    // mirrors occasionally serve truncated archives so a checksum failure is retried once before
+    // failing the build
+    let mut checksum_retried = false;
+    let mut download_result =
+        download(client.clone(), &repository_package, &download_cache_dir).await;
+    if let Err(InstallPackagesError::ChecksumFailed { .. }) = download_result {
+        checksum_retried = true;
+        download_result = download(client, &repository_package, &download_cache_dir).await;
+    }
+
+    let result = download_result.map(|(path, reused_download)| DownloadedPackage {
+        path,
+        reused_download,
+        checksum_retried,
+    });
+    (repository_package.name, result)
+}
+
+async fn extract_package(
+    package_name: String,
+    downloaded_package: DownloadedPackage,
+    extraction_plan: ExtractionPlan,
     install_dir: PathBuf,
-) -> BuildpackResult<()> {
-    let download_path = download(client, &repository_package).await?;
-    extract(download_path, install_dir).await
+    existing_files_policy: ExistingFilesPolicy,
+    script_failure_policy: Option<ScriptFailurePolicy>,
+) -> (String, Result<ExtractedPackage, InstallPackagesError>) {
+    let result = extract(
+        downloaded_package.path,
+        install_dir,
+        extraction_plan.skipped_files,
+        existing_files_policy,
+        script_failure_policy.is_some(),
+    )
+    .await
+    .and_then(|extracted_package| match extracted_package.script_failure {
+        Some(script_failure) if script_failure_policy == Some(ScriptFailurePolicy::Error) => Err(
+            InstallPackagesError::PostinstScriptFailed(package_name.clone(), script_failure),
+        ),
+        script_failure => Ok(ExtractedPackage {
+            existing_files: extraction_plan.existing_files,
+            script_failure,
+            reused_download: downloaded_package.reused_download,
+            checksum_retried: downloaded_package.checksum_retried,
+            ..extracted_package
+        }),
+    });
+    (package_name, result)
 }
 
+// Returns the path of the verified archive and whether it was already in the download cache.
 async fn download(
     client: ClientWithMiddleware,
     repository_package: &RepositoryPackage,
-) -> BuildpackResult<PathBuf> {
+    download_cache_dir: &Path,
+) -> Result<(PathBuf, bool), InstallPackagesError> {
     let download_url = build_download_url(repository_package);
 
-    let download_file_name = PathBuf::from(repository_package.filename.as_str())
+    PathBuf::from(repository_package.filename.as_str())
         .file_name()
-        .map(ToOwned::to_owned)
         .ok_or(InstallPackagesError::InvalidFilename(
             repository_package.name.clone(),
             repository_package.filename.clone(),
         ))?;
 
-    let download_path = temp_dir().join::<&Path>(download_file_name.as_ref());
+    if let Some(cached_path) = cached_archive(download_cache_dir, repository_package)
+        .await
+        .map_err(|e| {
+            InstallPackagesError::OpenPackageArchive(
+                cached_archive_path(download_cache_dir, &repository_package.checksum),
+                e,
+            )
+        })?
+    {
+        return Ok((cached_path, true));
+    }
+
+    let cached_path = cached_archive_path(download_cache_dir, &repository_package.checksum);
+
+    let download_path = partial_archive_path(download_cache_dir, &repository_package.checksum);
 
     let response = client
         .get(&download_url)
         .send()
         .await
         .and_then(|res| res.error_for_status().map_err(Reqwest))
-        .map_err(|e| InstallPackagesError::RequestPackage(repository_package.clone(), e))?;
+        .map_err(|e| {
+            InstallPackagesError::RequestPackage(Box::new(repository_package.clone()), e)
+        })?;
 
-    let mut hasher = Sha256::new();
+    let mut hasher = repository_package.checksum_algorithm.hasher();
+    // errors reading the body don't include the URL, which can differ from the download URL when
+    // the request was redirected
+    let final_url = response.url().clone();
 
     let mut writer = AsyncFile::create(&download_path)
         .await
         .map_err(|e| {
             InstallPackagesError::WritePackage(
-                repository_package.clone(),
+                Box::new(repository_package.clone()),
                 download_url.clone(),
                 download_path.clone(),
                 e,
@@ -297,7 +1678,7 @@ async fn download(
         FuturesAsyncReadCompatExt::compat(
             response
                 .bytes_stream()
-                .map_err(|e| std::io::Error::new(ErrorKind::Other, e))
+                .map_err(|e| std::io::Error::new(ErrorKind::Other, e.with_url(final_url.clone())))
                 .into_async_read(),
         ),
         |bytes| hasher.update(bytes),
@@ -305,42 +1686,64 @@ async fn download(
 
     async_copy(&mut reader, &mut writer).await.map_err(|e| {
         InstallPackagesError::WritePackage(
-            repository_package.clone(),
+            Box::new(repository_package.clone()),
             download_url.clone(),
             download_path.clone(),
             e,
         )
     })?;
 
-    let calculated_hash = format!("{:x}", hasher.finalize());
-    let hash = repository_package.sha256sum.to_string();
+    let calculated_hash = hasher.finalize();
+    let hash = repository_package.checksum.to_string();
 
     if hash != calculated_hash {
         Err(InstallPackagesError::ChecksumFailed {
-            url: download_url,
+            url: download_url.clone(),
             expected: hash,
             actual: calculated_hash,
         })?;
     }
 
-    Ok(download_path)
+    async_rename(&download_path, &cached_path)
+        .await
+        .map_err(|e| {
+            InstallPackagesError::WritePackage(
+                Box::new(repository_package.clone()),
+                download_url,
+                cached_path.clone(),
+                e,
+            )
+        })?;
+
+    Ok((cached_path, false))
 }
 
-async fn extract(download_path: PathBuf, output_dir: PathBuf) -> BuildpackResult<()> {
+#[allow(clippy::too_many_lines)]
+async fn extract(
+    download_path: PathBuf,
+    output_dir: PathBuf,
+    skipped_files: HashSet<PathBuf>,
+    existing_files_policy: ExistingFilesPolicy,
+    run_postinst_script: bool,
+) -> Result<ExtractedPackage, InstallPackagesError> {
     // a .deb file is an ar archive
     // https://manpages.ubuntu.com/manpages/jammy/en/man5/deb.5.html
-    let mut debian_archive = File::open(&download_path).map_err(|e| {
-        println!("Failed to open package archive: {:?}", e);
-        InstallPackagesError::OpenPackageArchive(download_path.clone(), e)
-    }).map(ArArchive::new)?;    
+    let mut debian_archive = File::open(&download_path)
+        .map_err(|e| {
+            println!("Failed to open package archive: {:?}", e);
+            InstallPackagesError::OpenPackageArchive(download_path.clone(), e)
+        })
+        .map(ArArchive::new)?;
 
-    let mut postinst_script_path: Option<PathBuf> = None;
+    let mut extracted_files = vec![];
+    let mut alternatives = vec![];
+    let mut script_failure = None;
 
     while let Some(entry) = debian_archive.next_entry() {
         let entry = entry.map_err(|e| {
-            println!("Failed to open package archive entry: {:?}", e);            
+            println!("Failed to open package archive entry: {:?}", e);
             InstallPackagesError::OpenPackageArchiveEntry(download_path.clone(), e)
-        })?;        
+        })?;
         let entry_path = PathBuf::from(OsString::from_vec(entry.header().identifier().to_vec()));
         let entry_reader =
             AsyncBufReader::new(FuturesAsyncReadCompatExt::compat(AllowStdIo::new(entry)));
@@ -351,22 +1754,43 @@ async fn extract(download_path: PathBuf, output_dir: PathBuf) -> BuildpackResult
             entry_path.extension().and_then(|v| v.to_str()),
         ) {
             (Some("data.tar"), Some("gz")) => {
-                let mut tar_archive = TarArchive::new(GzipDecoder::new(entry_reader));
-                tar_archive.unpack(&output_dir).await.map_err(|e| {
+                let tar_archive = TarArchive::new(GzipDecoder::new(entry_reader));
+                extracted_files = unpack_data_tar(
+                    tar_archive,
+                    &output_dir,
+                    &skipped_files,
+                    existing_files_policy,
+                )
+                .await
+                .map_err(|e| {
                     println!("Failed to unpack gzipped tar archive: {:?}", e);
                     InstallPackagesError::UnpackTarball(download_path.clone(), e)
                 })?;
             }
             (Some("data.tar"), Some("zstd" | "zst")) => {
-                let mut tar_archive = TarArchive::new(ZstdDecoder::new(entry_reader));
-                tar_archive.unpack(&output_dir).await.map_err(|e| {
+                let tar_archive = TarArchive::new(ZstdDecoder::new(entry_reader));
+                extracted_files = unpack_data_tar(
+                    tar_archive,
+                    &output_dir,
+                    &skipped_files,
+                    existing_files_policy,
+                )
+                .await
+                .map_err(|e| {
                     println!("Failed to unpack zstd compressed tar archive: {:?}", e);
                     InstallPackagesError::UnpackTarball(download_path.clone(), e)
                 })?;
             }
             (Some("data.tar"), Some("xz")) => {
-                let mut tar_archive = TarArchive::new(XzDecoder::new(entry_reader));
-                tar_archive.unpack(&output_dir).await.map_err(|e| {
+                let tar_archive = TarArchive::new(XzDecoder::new(entry_reader));
+                extracted_files = unpack_data_tar(
+                    tar_archive,
+                    &output_dir,
+                    &skipped_files,
+                    existing_files_policy,
+                )
+                .await
+                .map_err(|e| {
                     println!("Failed to unpack xz compressed tar archive: {:?}", e);
                     InstallPackagesError::UnpackTarball(download_path.clone(), e)
                 })?;
@@ -380,168 +1804,392 @@ async fn extract(download_path: PathBuf, output_dir: PathBuf) -> BuildpackResult
             }
             (Some("control.tar"), Some("gz")) => {
                 let mut tar_archive = TarArchive::new(GzipDecoder::new(entry_reader));
-                let mut entries = tar_archive.entries().map_err(
-                    |e| InstallPackagesError::UnpackTarball(download_path.clone(), e))?;
+                let mut entries = tar_archive
+                    .entries()
+                    .map_err(|e| InstallPackagesError::UnpackTarball(download_path.clone(), e))?;
                 while let Some(entry) = entries.next().await {
-                    let mut entry = entry.map_err(
-                        |e| InstallPackagesError::UnpackTarball(download_path.clone(), e))?;
-                    let entry_path = entry.path().map_err(|e| InstallPackagesError::UnpackTarball(
-                        download_path.clone(), e))?;
+                    let mut entry = entry.map_err(|e| {
+                        InstallPackagesError::UnpackTarball(download_path.clone(), e)
+                    })?;
+                    let entry_path = entry.path().map_err(|e| {
+                        InstallPackagesError::UnpackTarball(download_path.clone(), e)
+                    })?;
                     if entry_path.ends_with("postinst") {
                         let mut postinst_path = output_dir.clone();
-                        postinst_path.push(entry.path().map_err(
-                            |e| InstallPackagesError::UnpackTarball(download_path.clone(), e))?);
-                        async_copy(&mut entry, &mut AsyncFile::create(&postinst_path).await.map_err(
-                            |e| InstallPackagesError::UnpackTarball(download_path.clone(), e))?).await.map_err(|e| InstallPackagesError::UnpackTarball(download_path.clone(), e))?;
-                        postinst_script_path = Some(postinst_path);
-
-                        // Call execute_postinst_script here
-                        execute_postinst_script(postinst_script_path.clone().unwrap()).await?;                            
+                        postinst_path.push(entry.path().map_err(|e| {
+                            InstallPackagesError::UnpackTarball(download_path.clone(), e)
+                        })?);
+                        async_copy(
+                            &mut entry,
+                            &mut AsyncFile::create(&postinst_path).await.map_err(|e| {
+                                InstallPackagesError::UnpackTarball(download_path.clone(), e)
+                            })?,
+                        )
+                        .await
+                        .map_err(|e| {
+                            InstallPackagesError::UnpackTarball(download_path.clone(), e)
+                        })?;
+                        alternatives = read_alternatives(&download_path, &postinst_path).await?;
+                        if run_postinst_script {
+                            script_failure = execute_postinst_script(postinst_path).await?;
+                        }
                     }                
                 }
             }
             (Some("control.tar"), Some("zstd" | "zst")) => {
                 let mut tar_archive = TarArchive::new(ZstdDecoder::new(entry_reader));
-                let mut entries = tar_archive.entries().map_err(
-                    |e| InstallPackagesError::UnpackTarball(download_path.clone(), e))?;
+                let mut entries = tar_archive
+                    .entries()
+                    .map_err(|e| InstallPackagesError::UnpackTarball(download_path.clone(), e))?;
                 while let Some(entry) = entries.next().await {
-                    let mut entry = entry.map_err(
-                        |e| InstallPackagesError::UnpackTarball(download_path.clone(), e))?;
-                    let entry_path = entry.path().map_err(|e| InstallPackagesError::UnpackTarball(
-                        download_path.clone(), e))?;
+                    let mut entry = entry.map_err(|e| {
+                        InstallPackagesError::UnpackTarball(download_path.clone(), e)
+                    })?;
+                    let entry_path = entry.path().map_err(|e| {
+                        InstallPackagesError::UnpackTarball(download_path.clone(), e)
+                    })?;
                     if entry_path.ends_with("postinst") {
                         let mut postinst_path = output_dir.clone();
-                        postinst_path.push(entry.path().map_err(
-                            |e| InstallPackagesError::UnpackTarball(download_path.clone(), e))?);
-                        async_copy(&mut entry, &mut AsyncFile::create(&postinst_path).await.map_err(
-                            |e| InstallPackagesError::UnpackTarball(download_path.clone(), e))?).await.map_err(|e| InstallPackagesError::UnpackTarball(download_path.clone(), e))?;
-                        postinst_script_path = Some(postinst_path);
-
-                        // Call execute_postinst_script here
-                        execute_postinst_script(postinst_script_path.clone().unwrap()).await?;                            
-                    }                
+                        postinst_path.push(entry.path().map_err(|e| {
+                            InstallPackagesError::UnpackTarball(download_path.clone(), e)
+                        })?);
+                        async_copy(
+                            &mut entry,
+                            &mut AsyncFile::create(&postinst_path).await.map_err(|e| {
+                                InstallPackagesError::UnpackTarball(download_path.clone(), e)
+                            })?,
+                        )
+                        .await
+                        .map_err(|e| {
+                            InstallPackagesError::UnpackTarball(download_path.clone(), e)
+                        })?;
+                        alternatives = read_alternatives(&download_path, &postinst_path).await?;
+                        if run_postinst_script {
+                            script_failure = execute_postinst_script(postinst_path).await?;
+                        }
+                    }
                 }
             }
             (Some("control.tar"), Some("xz")) => {
                 let mut tar_archive = TarArchive::new(XzDecoder::new(entry_reader));
-                let mut entries = tar_archive.entries().map_err(
-                    |e| InstallPackagesError::UnpackTarball(download_path.clone(), e))?;
+                let mut entries = tar_archive
+                    .entries()
+                    .map_err(|e| InstallPackagesError::UnpackTarball(download_path.clone(), e))?;
                 while let Some(entry) = entries.next().await {
-                    let mut entry = entry.map_err(
-                        |e| InstallPackagesError::UnpackTarball(download_path.clone(), e))?;
-                    let entry_path = entry.path().map_err(|e| InstallPackagesError::UnpackTarball(
-                        download_path.clone(), e))?;
+                    let mut entry = entry.map_err(|e| {
+                        InstallPackagesError::UnpackTarball(download_path.clone(), e)
+                    })?;
+                    let entry_path = entry.path().map_err(|e| {
+                        InstallPackagesError::UnpackTarball(download_path.clone(), e)
+                    })?;
                     if entry_path.ends_with("postinst") {
                         let mut postinst_path = output_dir.clone();
-                        postinst_path.push(entry.path().map_err(
-                            |e| InstallPackagesError::UnpackTarball(download_path.clone(), e))?);
-                        async_copy(&mut entry, &mut AsyncFile::create(&postinst_path).await.map_err(
-                            |e| InstallPackagesError::UnpackTarball(download_path.clone(), e))?).await.map_err(|e| InstallPackagesError::UnpackTarball(download_path.clone(), e))?;
-                        postinst_script_path = Some(postinst_path);
-
-                        // Call execute_postinst_script here
-                        execute_postinst_script(postinst_script_path.clone().unwrap()).await?;                            
-                    }                
+                        postinst_path.push(entry.path().map_err(|e| {
+                            InstallPackagesError::UnpackTarball(download_path.clone(), e)
+                        })?);
+                        async_copy(
+                            &mut entry,
+                            &mut AsyncFile::create(&postinst_path).await.map_err(|e| {
+                                InstallPackagesError::UnpackTarball(download_path.clone(), e)
+                            })?,
+                        )
+                        .await
+                        .map_err(|e| {
+                            InstallPackagesError::UnpackTarball(download_path.clone(), e)
+                        })?;
+                        alternatives = read_alternatives(&download_path, &postinst_path).await?;
+                        if run_postinst_script {
+                            script_failure = execute_postinst_script(postinst_path).await?;
+                        }
+                    }
                 }
-            }            
+            }
             (Some("control.tar"), Some(compression)) => {
-                println!("Unknown compression data.tar entry");                
+                println!("Unknown compression data.tar entry");
                 Err(InstallPackagesError::UnsupportedCompression(
                     download_path.clone(),
                     compression.to_string(),
                 ))?;
-            }            
+            }
             _ => {
                 // ignore other potential file entries (e.g., debian-binary)
             }
         };
     }
 
-    Ok(())        
+    Ok(ExtractedPackage {
+        files: extracted_files,
+        alternatives,
+        script_failure,
+        ..ExtractedPackage::default()
+    })
 }
 
-async fn execute_postinst_script(postinst_path: PathBuf) -> Result<(), InstallPackagesError> {    
+// Lists the files in the `data.tar` entry of a package archive, leaving out the directories since
+// they're shared between packages.
+async fn list_data_files(download_path: &Path) -> Result<Vec<PathBuf>, InstallPackagesError> {
+    let mut debian_archive = File::open(download_path)
+        .map_err(|e| InstallPackagesError::OpenPackageArchive(download_path.to_path_buf(), e))
+        .map(ArArchive::new)?;
+
+    while let Some(entry) = debian_archive.next_entry() {
+        let entry = entry.map_err(|e| {
+            InstallPackagesError::OpenPackageArchiveEntry(download_path.to_path_buf(), e)
+        })?;
+        let entry_path = PathBuf::from(OsString::from_vec(entry.header().identifier().to_vec()));
+        let entry_reader =
+            AsyncBufReader::new(FuturesAsyncReadCompatExt::compat(AllowStdIo::new(entry)));
+
+        let data_tar: Box<dyn AsyncRead + Unpin + Send> = match (
+            entry_path.file_stem().and_then(|v| v.to_str()),
+            entry_path.extension().and_then(|v| v.to_str()),
+        ) {
+            (Some("data.tar"), Some("gz")) => Box::new(GzipDecoder::new(entry_reader)),
+            (Some("data.tar"), Some("zstd" | "zst")) => Box::new(ZstdDecoder::new(entry_reader)),
+            (Some("data.tar"), Some("xz")) => Box::new(XzDecoder::new(entry_reader)),
+            (Some("data.tar"), Some(compression)) => {
+                Err(InstallPackagesError::UnsupportedCompression(
+                    download_path.to_path_buf(),
+                    compression.to_string(),
+                ))?
+            }
+            _ => continue,
+        };
+
+        let mut files = vec![];
+        let mut entries = TarArchive::new(data_tar)
+            .entries()
+            .map_err(|e| InstallPackagesError::UnpackTarball(download_path.to_path_buf(), e))?;
+        while let Some(entry) = entries.next().await {
+            let entry = entry
+                .map_err(|e| InstallPackagesError::UnpackTarball(download_path.to_path_buf(), e))?;
+            if !entry.header().entry_type().is_dir() {
+                files.push(
+                    entry
+                        .path()
+                        .map_err(|e| {
+                            InstallPackagesError::UnpackTarball(download_path.to_path_buf(), e)
+                        })?
+                        .to_path_buf(),
+                );
+            }
+        }
+        return Ok(files);
+    }
+    Ok(vec![])
+}
+
+// Equivalent to `Archive::unpack` but also returns the path of every entry so the extracted files
+// can be attributed to the package they came from. The skipped files aren't written, but when the
+// policy is to overwrite them they're still attributed to the package since another package that
+// ships the same file writes it.
+async fn unpack_data_tar<R>(
+    mut tar_archive: TarArchive<R>,
+    output_dir: &Path,
+    skipped_files: &HashSet<PathBuf>,
+    existing_files_policy: ExistingFilesPolicy,
+) -> std::io::Result<Vec<PathBuf>>
+where
+    R: AsyncRead + Unpin + Send,
+{
+    let mut extracted_files = vec![];
+    let mut entries = tar_archive.entries()?;
+    while let Some(entry) = entries.next().await {
+        let mut entry = entry?;
+        let path = entry.path()?.to_path_buf();
+        let skipped = skipped_files.contains(&path);
+        if skipped && existing_files_policy != ExistingFilesPolicy::Overwrite {
+            continue;
+        }
+        extracted_files.push(path);
+        if !skipped {
+            entry.unpack_in(output_dir).await?;
+        }
+    }
+    Ok(extracted_files)
+}
+
+async fn read_alternatives(
+    download_path: &Path,
+    postinst_path: &Path,
+) -> Result<Vec<Alternative>, InstallPackagesError> {
+    async_read_to_string(postinst_path)
+        .await
+        .map(|postinst_script| parse_alternatives(&postinst_script))
+        .map_err(|e| InstallPackagesError::UnpackTarball(download_path.to_path_buf(), e))
+}
+
+// Failing to start the script is always an error but a script that exits with a non-zero status is
+// returned so the caller can apply the policy of the package it belongs to.
+async fn execute_postinst_script(
+    postinst_path: PathBuf,
+) -> Result<Option<ScriptFailure>, InstallPackagesError> {
     // Make the postinst script executable
-    set_permissions(&postinst_path, PermissionsExt::from_mode(0o755)).await
+    set_permissions(&postinst_path, PermissionsExt::from_mode(0o755))
+        .await
         .map_err(|e| InstallPackagesError::SetPermissions(postinst_path.clone(), e))?;
 
     // Run the postinst script
-    let _output = Command::new(postinst_path)
+    let output = Command::new(postinst_path)
         .output()
         .await
-        .map_err(|e| InstallPackagesError::ExecutePostinstScript(e))?;
+        .map_err(InstallPackagesError::ExecutePostinstScript)?;
 
-    Ok(())        
+    if output.status.success() {
+        return Ok(None);
+    }
+    Ok(Some(ScriptFailure {
+        status: output.status,
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+    }))
+}
+
+fn verify_installed_layout(
+    install_path: &Path,
+    extracted_files: &BTreeMap<String, Vec<PathBuf>>,
+    package_env_vars: &HashMap<String, HashMap<String, String>>,
+    env: &Environment,
+    packages_to_install: &[RepositoryPackage],
+    layer_env: &LayerEnv,
+    log: Print<Bullet<Stdout>>,
+) -> BuildpackResult<Print<Bullet<Stdout>>> {
+    let mut env_vars: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+    for package in packages_to_install {
+        if let Some(vars) = package_env_vars.get(package.name.as_str()) {
+            env_vars
+                .entry(package.name.clone())
+                .or_default()
+                .extend(vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+    }
+    for (key, value) in env.get_variables() {
+        if let Some(package) = env.get_package(key).filter(|name| {
+            packages_to_install
+                .iter()
+                .any(|package| package.name == *name)
+        }) {
+            env_vars
+                .entry(package.to_string())
+                .or_default()
+                .push((key.clone(), value.clone()));
+        }
+    }
+
+    // every variable the layer exports is checked as well since a restored layer or a package that
+    // removed a directory can leave paths like `PATH` or `LD_LIBRARY_PATH` pointing at nothing
+    let layer_env_vars = layer_env_values(layer_env, Scope::Build)
+        .into_iter()
+        .chain(layer_env_values(layer_env, Scope::Launch))
+        .collect::<BTreeSet<_>>();
+
+    let layout_issues = verify_layout(install_path, extracted_files, &env_vars, &layer_env_vars);
+    if !layout_issues.is_empty() {
+        Err(InstallPackagesError::LayoutIssues(layout_issues))?;
+    }
+
+    Ok(log
+        .bullet("Verified layout")
+        .sub_bullet(format!(
+            "{} extracted packages and {} packages with environment variables",
+            extracted_files.len(),
+            env_vars.len()
+        ))
+        .done())
 }
 
 fn configure_layer_environment(
     install_path: &Path,
-    multiarch_name: &MultiarchName,
+    multiarch_names: &[MultiarchName],
     package_env_vars: &HashMap<String, HashMap<String, String>>,
     packages_to_install: &[RepositoryPackage],
     skipped_packages: &[RequestedPackage],
     env: &Environment,
+    env_scopes: &EnvScopes,
 ) -> LayerEnv {
 
     let mut layer_env = LayerEnv::new();
 
+    // only the search directories the installed packages created are exported
     let bin_paths = [
         install_path.join("bin"),
         install_path.join("usr/bin"),
         install_path.join("usr/sbin"),
-    ];
-    prepend_to_env_var(&mut layer_env, "PATH", &bin_paths);
+    ]
+    .into_iter()
+    .filter(|bin_path| bin_path.is_dir())
+    .collect::<Vec<_>>();
+    let scope = env_scopes.default_scope();
+
+    prepend_to_env_var(&mut layer_env, &scope, "PATH", &bin_paths);
 
     // Load and apply environment variables from the project.toml file
     for (key, value) in env.get_variables() {
-        prepend_to_env_var(&mut layer_env, key, vec![value.clone()]);
+        let scope = env
+            .get_package(key)
+            .map_or(env_scopes.default_scope(), |package| {
+                env_scopes.package_scope(package)
+            });
+        prepend_to_env_var(&mut layer_env, &scope, key, vec![value.clone()]);
     }
 
-    // Support multi-arch and legacy filesystem layouts for debian packages
-    let library_paths = [
-        install_path.join(format!("usr/lib/{multiarch_name}")),
-        install_path.join("usr/lib"),
-        install_path.join(format!("lib/{multiarch_name}")),
-        install_path.join("lib"),
-    ]
-    .iter()
-    .fold(IndexSet::new(), |mut acc, lib_dir| {
-        for dir in find_all_dirs_containing(lib_dir, shared_library_file) {
-            acc.insert(dir);
-        }
-        acc.insert(lib_dir.clone());
-        acc
-    });
-    prepend_to_env_var(&mut layer_env, "LD_LIBRARY_PATH", &library_paths);
-    prepend_to_env_var(&mut layer_env, "LIBRARY_PATH", &library_paths);
-
-    let include_paths = [
-        install_path.join(format!("usr/include/{multiarch_name}")),
-        install_path.join("usr/include"),
-    ]
-    .iter()
-    .fold(IndexSet::new(), |mut acc, include_dir| {
-        for dir in find_all_dirs_containing(include_dir, header_file) {
-            acc.insert(dir);
-        }
-        acc.insert(include_dir.clone());
-        acc
-    });
-    prepend_to_env_var(&mut layer_env, "INCLUDE_PATH", &include_paths);
-    prepend_to_env_var(&mut layer_env, "CPATH", &include_paths);
-    prepend_to_env_var(&mut layer_env, "CPPPATH", &include_paths);
-
-    let pkg_config_paths = [
-        install_path.join(format!("usr/lib/{multiarch_name}/pkgconfig")),
-        install_path.join("usr/lib/pkgconfig"),
-    ];
-    prepend_to_env_var(&mut layer_env, "PKG_CONFIG_PATH", &pkg_config_paths);
+    // Support multi-arch and legacy filesystem layouts for debian packages. The target architecture
+    // is listed first followed by any foreign architectures packages were installed for.
+    let library_paths = multiarch_names
+        .iter()
+        .map(|multiarch_name| install_path.join(format!("usr/lib/{multiarch_name}")))
+        .chain([install_path.join("usr/lib")])
+        .chain(
+            multiarch_names
+                .iter()
+                .map(|multiarch_name| install_path.join(format!("lib/{multiarch_name}"))),
+        )
+        .chain([install_path.join("lib")])
+        .collect::<Vec<_>>()
+        .iter()
+        .filter(|lib_dir| lib_dir.is_dir())
+        .fold(IndexSet::new(), |mut acc, lib_dir| {
+            for dir in find_all_dirs_containing(lib_dir, shared_library_file) {
+                acc.insert(dir);
+            }
+            acc.insert(lib_dir.clone());
+            acc
+        });
+    prepend_to_env_var(&mut layer_env, &scope, "LD_LIBRARY_PATH", &library_paths);
+    prepend_to_env_var(&mut layer_env, &scope, "LIBRARY_PATH", &library_paths);
 
-    // Load the env vars from PACKAGE_ENV_VARS if the package is in the project.toml
+    let include_paths = multiarch_names
+        .iter()
+        .map(|multiarch_name| install_path.join(format!("usr/include/{multiarch_name}")))
+        .chain([install_path.join("usr/include")])
+        .collect::<Vec<_>>()
+        .iter()
+        .filter(|include_dir| include_dir.is_dir())
+        .fold(IndexSet::new(), |mut acc, include_dir| {
+            for dir in find_all_dirs_containing(include_dir, header_file) {
+                acc.insert(dir);
+            }
+            acc.insert(include_dir.clone());
+            acc
+        });
+    prepend_to_env_var(&mut layer_env, &scope, "INCLUDE_PATH", &include_paths);
+    prepend_to_env_var(&mut layer_env, &scope, "CPATH", &include_paths);
+    prepend_to_env_var(&mut layer_env, &scope, "CPPPATH", &include_paths);
+
+    let pkg_config_paths = multiarch_names
+        .iter()
+        .map(|multiarch_name| install_path.join(format!("usr/lib/{multiarch_name}/pkgconfig")))
+        .chain([install_path.join("usr/lib/pkgconfig")])
+        .filter(|pkg_config_path| pkg_config_path.is_dir())
+        .collect::<Vec<_>>();
+    prepend_to_env_var(&mut layer_env, &scope, "PKG_CONFIG_PATH", &pkg_config_paths);
+
+    // Load the env vars from the package integrations if the package is in the project.toml
     for package in packages_to_install {
         if let Some(vars) = package_env_vars.get(package.name.as_str()) {
+            let scope = env_scopes.package_scope(&package.name);
             for (key, value) in vars {
-                prepend_to_env_var(&mut layer_env, key, vec![value.to_string()]);
+                prepend_to_env_var(&mut layer_env, &scope, key, vec![value.to_string()]);
             }
         }
     }
@@ -549,8 +2197,9 @@ fn configure_layer_environment(
     // Iterate through skipped_packages and add their environment variables if they are in the project.toml
     for skipped_package in skipped_packages {
         if let Some(vars) = package_env_vars.get(skipped_package.name.as_str()) {
+            let scope = env_scopes.package_scope(skipped_package.name.as_str());
             for (key, value) in vars {
-                prepend_to_env_var(&mut layer_env, key, vec![value.to_string()]);
+                prepend_to_env_var(&mut layer_env, &scope, key, vec![value.to_string()]);
             }
         }
     }
@@ -596,20 +2245,33 @@ fn header_file(path: &Path) -> bool {
     matches!(path.extension(), Some(ext) if ext == "h")
 }
 
-fn prepend_to_env_var<I, T>(layer_env: &mut LayerEnv, name: &str, paths: I)
+fn prepend_to_env_var<I, T>(layer_env: &mut LayerEnv, scope: &Scope, name: &str, paths: I)
 where
     I: IntoIterator<Item = T>,
     T: Into<OsString>,
 {
     let separator = ":";
     let paths_vec: Vec<_> = paths.into_iter().map(Into::into).collect();
+    if paths_vec.is_empty() {
+        return;
+    }
     let paths_str = paths_vec.join(separator.as_ref());
 
     // Log the environment variable being added
     // println!("Adding env var: {}={:?}", name, paths_str);
 
-    layer_env.insert(Scope::All, ModificationBehavior::Delimiter, name, separator);
-    layer_env.insert(Scope::All, ModificationBehavior::Prepend, name, paths_str);
+    layer_env.insert(
+        scope.clone(),
+        ModificationBehavior::Delimiter,
+        name,
+        separator,
+    );
+    layer_env.insert(
+        scope.clone(),
+        ModificationBehavior::Prepend,
+        name,
+        paths_str,
+    );
 }
 
 async fn rewrite_package_configs(install_path: &Path) -> BuildpackResult<()> {
@@ -660,20 +2322,26 @@ async fn rewrite_package_config(package_config: &Path, install_path: &Path) -> B
         .map_err(|e| InstallPackagesError::WritePackageConfig(package_config.to_path_buf(), e))?)
 }
 
-fn build_download_url(repository_package: &RepositoryPackage) -> String {
-    format!(
-        "{}/{}",
-        repository_package.repository_uri.as_str(),
-        repository_package.filename.as_str()
-    )
+// The checksum is always the one from the package index even when the source downloads packages
+// from somewhere else (e.g.; a CDN) so the download is still verified by the Release file chain.
+pub(crate) fn build_download_url(repository_package: &RepositoryPackage) -> String {
+    match &repository_package.download_url_template {
+        Some(download_url_template) => download_url_template
+            .replace(DOWNLOAD_URL_FILENAME, repository_package.filename.as_str()),
+        None => format!(
+            "{}/{}",
+            repository_package.repository_uri.as_str(),
+            repository_package.filename.as_str()
+        ),
+    }
 }
 
 #[derive(Debug)]
 pub(crate) enum InstallPackagesError {
     TaskFailed(JoinError),
     InvalidFilename(String, String),
-    RequestPackage(RepositoryPackage, reqwest_middleware::Error),
-    WritePackage(RepositoryPackage, String, PathBuf, std::io::Error),
+    RequestPackage(Box<RepositoryPackage>, reqwest_middleware::Error),
+    WritePackage(Box<RepositoryPackage>, String, PathBuf, std::io::Error),
     ChecksumFailed {
         url: String,
         expected: String,
@@ -688,6 +2356,24 @@ pub(crate) enum InstallPackagesError {
     SetPermissions(PathBuf, std::io::Error),
     ExecutePostinstScript(std::io::Error),
     SystemTimeError(SystemTimeError),
+    MultiplePackagesFailed(Vec<(String, InstallPackagesError)>),
+    SerializeSnapshot(PathBuf, toml::ser::Error),
+    SerializeSbom(serde_json::Error),
+    SerializeProvenance(serde_json::Error),
+    WriteProvenance(PathBuf, std::io::Error),
+    WriteSnapshot(PathBuf, std::io::Error),
+    ExtractionIssues(BTreeMap<String, Vec<ExtractionIssue>>),
+    LayoutIssues(BTreeMap<String, Vec<LayoutIssue>>),
+    CreateAlternatives(CreateAlternativesError),
+    PackageIntegration(PackageIntegrationError),
+    SerializeLockfile(PathBuf, toml::ser::Error),
+    WriteLockfile(PathBuf, std::io::Error),
+    RemovePackageFiles(std::io::Error),
+    WriteInstalledFiles(PathBuf, std::io::Error),
+    WriteSelfCheck(PathBuf, std::io::Error),
+    CreateDownloadDir(PathBuf, std::io::Error),
+    ExistingFiles(String, Vec<PathBuf>),
+    PostinstScriptFailed(String, ScriptFailure),
 }
 
 impl From<InstallPackagesError> for libcnb::Error<DebianPackagesBuildpackError> {
@@ -702,62 +2388,254 @@ impl From<std::io::Error> for InstallPackagesError {
     }
 }
 
+// Describes how the packages cached for a previous distribution (e.g.; Ubuntu 22.04) relate to the
+// packages being installed for the current one so users upgrading their builder can see what changed.
+#[derive(Debug)]
+struct DistroMigration {
+    previous_distro: Distro,
+    current_distro: Distro,
+    renamed_packages: Vec<(String, String)>,
+    removed_packages: Vec<String>,
+}
+
+impl DistroMigration {
+    fn new(old_metadata: &InstallationMetadata, new_metadata: &InstallationMetadata) -> Self {
+        let mut renamed_packages = vec![];
+        let mut removed_packages = vec![];
+
+        let mut previous_packages = old_metadata.package_checksums.keys().collect::<Vec<_>>();
+        previous_packages.sort();
+
+        for previous_package in previous_packages {
+            if new_metadata
+                .package_checksums
+                .contains_key(previous_package)
+            {
+                continue;
+            }
+            match find_renamed_package(previous_package, &new_metadata.package_checksums) {
+                Some(current_package) => {
+                    renamed_packages.push((previous_package.clone(), current_package));
+                }
+                None => removed_packages.push(previous_package.clone()),
+            }
+        }
+
+        Self {
+            previous_distro: old_metadata.distro.clone(),
+            current_distro: new_metadata.distro.clone(),
+            renamed_packages,
+            removed_packages,
+        }
+    }
+}
+
+// Ubuntu 24.04 renamed many libraries with a `t64` suffix as part of the 64-bit time_t transition
+// (e.g.; `libssl3` became `libssl3t64`) so these are the most common renames between releases.
+// See https://wiki.debian.org/ReleaseGoals/64bit-time
+fn find_renamed_package(
+    previous_package: &str,
+    current_packages: &HashMap<String, String>,
+) -> Option<String> {
+    let upgraded_name = format!("{previous_package}t64");
+    if current_packages.contains_key(&upgraded_name) {
+        return Some(upgraded_name);
+    }
+    previous_package
+        .strip_suffix("t64")
+        .filter(|downgraded_name| current_packages.contains_key(*downgraded_name))
+        .map(ToString::to_string)
+}
+
+fn log_distro_migration(
+    distro_migration: &DistroMigration,
+    log: Print<Bullet<Stdout>>,
+) -> Print<Bullet<Stdout>> {
+    let DistroMigration {
+        previous_distro,
+        current_distro,
+        renamed_packages,
+        removed_packages,
+    } = distro_migration;
+
+    let mut log = log.bullet(format!(
+        "Distribution changed from {previous} to {current}, the cached packages will be replaced",
+        previous = style::value(format!(
+            "{} {} ({})",
+            previous_distro.name, previous_distro.version, previous_distro.codename
+        )),
+        current = style::value(format!(
+            "{} {} ({})",
+            current_distro.name, current_distro.version, current_distro.codename
+        )),
+    ));
+
+    for (previous_package, current_package) in renamed_packages {
+        log = log.sub_bullet(format!(
+            "{previous_package} was renamed to {current_package}",
+            previous_package = style::value(previous_package),
+            current_package = style::value(current_package),
+        ));
+    }
+
+    for removed_package in removed_packages {
+        log = log.sub_bullet(format!(
+            "{removed_package} is no longer installed, verify the package name is still correct for {current}",
+            removed_package = style::value(removed_package),
+            current = style::value(current_distro.codename.to_string()),
+        ));
+    }
+
+    log.done()
+}
+
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
 struct InstallationMetadata {
     package_checksums: HashMap<String, String>,
     distro: Distro,
     timestamp: u64,  // Timestamp to track when the package was cached
     dependencies: HashMap<String, Vec<String>>,  // Track dependencies
+    // the packages configured with `remove` whose files are pruned after extraction
+    #[serde(default)]
+    removed_packages: Vec<String>,
+    // a hash of the dpkg status file of the base image the packages were resolved against
+    #[serde(default)]
+    system_packages_sha256: Option<String>,
+    // the packages whose maintainer scripts were run mapped to their failure policy
+    #[serde(default)]
+    maintainer_scripts: BTreeMap<String, String>,
+    // the `imagemagick_policy` that replaced the `policy.xml` installed by ImageMagick
+    #[serde(default)]
+    imagemagick_policy: Option<PathBuf>,
+    snapshot: Option<InstallSnapshot>,
+}
+
+impl InstallationMetadata {
+    // The snapshot and timestamp describe how and when the installation was produced, not what was
+    // installed, so they're not considered when deciding if the cached packages can be reused.
+    fn is_same_installation(&self, other: &InstallationMetadata) -> bool {
+        self.package_checksums == other.package_checksums
+            && self.distro == other.distro
+            && self.dependencies == other.dependencies
+            && self.removed_packages == other.removed_packages
+            && self.has_same_maintainer_scripts(other)
+    }
+
+    // Only a different release is a migration since packages can be renamed between releases (see
+    // `find_renamed_package`), the same release for another architecture has the same packages.
+    fn is_distro_migration(&self, other: &InstallationMetadata) -> bool {
+        self.distro.name != other.distro.name
+            || self.distro.version != other.distro.version
+            || self.distro.codename != other.distro.codename
+    }
+
+    fn has_same_maintainer_scripts(&self, other: &InstallationMetadata) -> bool {
+        self.maintainer_scripts == other.maintainer_scripts
+    }
+
+    // A policy that's added or changed is applied over the installed one but the packaged policy
+    // can only be restored by installing ImageMagick again.
+    fn removes_imagemagick_policy(&self, other: &InstallationMetadata) -> bool {
+        self.imagemagick_policy.is_some() && other.imagemagick_policy.is_none()
+    }
+
+    // Layers installed before the status file was hashed are assumed to match.
+    fn has_same_system_packages(&self, other: &InstallationMetadata) -> bool {
+        self.system_packages_sha256.is_none()
+            || self.system_packages_sha256 == other.system_packages_sha256
+    }
+
+    // The timestamp is only written when the packages are downloaded so cached packages are
+    // refreshed at least every `cache_days` days. A value of 0 disables reuse.
+    fn is_within_cache_retention(&self, now: SystemTime, cache_days: u64) -> bool {
+        let installed_at = UNIX_EPOCH + Duration::from_secs(self.timestamp);
+        let age = now.duration_since(installed_at).unwrap_or(Duration::ZERO);
+        cache_days > 0 && age <= Duration::from_secs(cache_days.saturating_mul(24 * 60 * 60))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub(crate) struct InstallSnapshot {
+    pub(crate) buildpack_version: String,
+    pub(crate) config_sha256: String,
+    pub(crate) sources: Vec<IndexedSource>,
+    // The chain of packages that caused each installed package to be added, starting from the
+    // requested package. Packages that were requested directly have an empty chain.
+    pub(crate) dependency_paths: BTreeMap<String, Vec<String>>,
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use libcnb::layer_env::Scope;
-    use std::env;
     use std::ffi::OsString;
     use std::fs::{self};
     use std::os::unix::fs::PermissionsExt;
     use std::path::{Path, PathBuf};
 
+    use mockall::predicate::*;
     use tempfile::TempDir;
     use tokio::process::Command;
-    use mockall::predicate::*;
 
-    use crate::debian::MultiarchName;
-    use crate::install_packages::{configure_layer_environment, package_env_vars};
-    use crate::install_packages::HashMap;
     use crate::config::requested_package::RequestedPackage;
-    use crate::debian::repository_package::RepositoryPackage;
+    use crate::config::EnvScope;
     use crate::debian::package_name::PackageName;
-    use crate::debian::RepositoryUri;
-    
+    use crate::debian::repository_package::RepositoryPackage;
+    use crate::debian::{ArchitectureName, DistroCodename, MultiarchName};
+    use crate::install_packages::configure_layer_environment;
+    use crate::install_packages::{BTreeMap, HashMap};
+    use crate::test_support::create_repository_package;
+
     #[tokio::test]
     async fn test_execute_postinst_script() -> Result<(), InstallPackagesError> {
-    
         // Manually extract the postinst script for testing
         let postinst_path = "tests/fixtures/scripts/postinst";
-    
+
         // Call the execute_postinst_script function
         println!("Calling execute_postinst_script function");
-        execute_postinst_script(postinst_path.into()).await?;
+        assert!(execute_postinst_script(postinst_path.into())
+            .await?
+            .is_none());
         println!("Called execute_postinst_script function");
-    
+
         let permissions = fs::metadata(&postinst_path)?.permissions();
         assert_eq!(permissions.mode() & 0o777, 0o755);
         println!("Verified permissions of postinst script");
-    
+
         let output = Command::new(postinst_path).output().await?;
         assert!(output.status.success());
         assert_eq!(output.status.code(), Some(0));
         println!("Verified execution of postinst script with exit code 0");
-    
+
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_execute_failing_postinst_script() {
+        let temp_dir = TempDir::new().unwrap();
+        let postinst_path = temp_dir.path().join("postinst");
+        fs::write(
+            &postinst_path,
+            "#!/bin/sh\necho 'Configuring package'\necho 'missing dependency' >&2\nexit 3\n",
+        )
+        .unwrap();
+
+        let script_failure = execute_postinst_script(postinst_path)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(script_failure.status.code(), Some(3));
+        assert_eq!(script_failure.exit_description(), "exit code 3");
+        assert_eq!(script_failure.stdout, "Configuring package\n");
+        assert_eq!(script_failure.stderr, "missing dependency\n");
+        assert_eq!(
+            script_failure.last_output_line(),
+            Some("missing dependency")
+        );
+    }
+
     #[test]
     fn configure_layer_environment_adds_nested_directories_with_shared_libraries_to_library_path() {
-
         let arch = MultiarchName::X86_64_LINUX_GNU;
         let install_dir = create_installation(vec![
             format!("usr/lib/{arch}/nested-1/shared-library.so.2"),
@@ -775,56 +2653,64 @@ mod test {
         let install_dir_str = install_path.to_string_lossy().to_string();
         let env = Environment::load_from_toml(Path::new(project_toml_path), &install_dir_str);
 
-        // Convert package_env_vars to the correct type and replace {install_dir} with the actual path
-        let initial_package_env_vars = package_env_vars();
-        let package_env_vars: HashMap<String, HashMap<String, String>> = initial_package_env_vars
-            .into_iter()
-            .map(|(k, v)| {
-                (
-                    k.to_string(),
-                    v.into_iter()
-                        .map(|(k, v)| (k.to_string(), v.replace("{install_dir}", &install_dir_str)))
-                        .collect(),
-                )
-            })
-            .collect();        
+        let package_env_vars = package_env_vars(
+            &package_integrations(&IntegrationSettings::default()),
+            install_path,
+            ["ghostscript", "git"],
+        );
 
         // Create dummy packages to install and skipped packages
         let packages_to_install = vec![RepositoryPackage {
-            repository_uri: RepositoryUri("http://security.ubuntu.com/ubuntu".to_string()),
-            name: "ghostscript".to_string(),
-            version: "10.02.1~dfsg1-0ubuntu7.4".to_string(),
-            filename: "pool/main/g/ghostscript/ghostscript_10.02.1~dfsg1-0ubuntu7.4_amd64.deb".to_string(),
-            sha256sum: "1d46e4995d9361029b8d672403b745a31c7c977a5ae314de6342e26c79fc6a3f".to_string(),
             depends: Some("libgs10 (= 10.02.1~dfsg1-0ubuntu7.4), libc6 (>= 2.34)".to_string()),
-            pre_depends: None,
-            provides: Some("ghostscript-x (= 10.02.1~dfsg1-0ubuntu7.4), postscript-viewer".to_string()),            
+            ..create_repository_package()
+                .name("ghostscript")
+                .version("10.02.1~dfsg1-0ubuntu7.4")
+                .repository_uri("http://security.ubuntu.com/ubuntu")
+                .filename("pool/main/g/ghostscript/ghostscript_10.02.1~dfsg1-0ubuntu7.4_amd64.deb")
+                .checksum("1d46e4995d9361029b8d672403b745a31c7c977a5ae314de6342e26c79fc6a3f")
+                .provides(vec![
+                    "ghostscript-x (= 10.02.1~dfsg1-0ubuntu7.4)",
+                    "postscript-viewer",
+                ])
+                .call()
         }];
 
         let skipped_packages = vec![
             RequestedPackage {
                 name: PackageName("package2".to_string()),
+                architecture: None,
                 skip_dependencies: false,
                 force: false,
+                env_scope: None,
+                optional: false,
+                maintainer_scripts: None,
+                version: None,
             },
             RequestedPackage {
                 name: PackageName("git".to_string()),
+                architecture: None,
                 skip_dependencies: false,
                 force: false,
+                env_scope: None,
+                optional: false,
+                maintainer_scripts: None,
+                version: None,
             },
         ];
 
         let layer_env = configure_layer_environment(
             &install_path,
-            &arch,
+            std::slice::from_ref(&arch),
             &package_env_vars,
             &packages_to_install,
             &skipped_packages,
             &env,
-        );        
+            &EnvScopes::default(),
+        );
 
         // Get the actual and expected values for LD_LIBRARY_PATH
-        let actual_ld_library_path = split_into_paths(layer_env.apply_to_empty(Scope::All).get("LD_LIBRARY_PATH"));
+        let actual_ld_library_path =
+            split_into_paths(layer_env.apply_to_empty(Scope::All).get("LD_LIBRARY_PATH"));
         let expected_ld_library_path = vec![
             install_path.join(format!("usr/lib/{arch}/nested-1")),
             install_path.join(format!("usr/lib/{arch}")),
@@ -840,26 +2726,38 @@ mod test {
         // Correct assertion for LD_LIBRARY_PATH
         assert_eq!(actual_ld_library_path, expected_ld_library_path);
 
-        // Check that the environment variables from PACKAGE_ENV_VARS are correctly applied
+        // Check that the environment variables from the package integrations are correctly applied
         let applied_env = layer_env.apply_to_empty(Scope::All);
 
         assert_eq!(
             applied_env.get("GIT_EXEC_PATH"),
-            Some(&OsString::from(format!("{}/usr/lib/git-core", install_dir_str)))
+            Some(&OsString::from(format!(
+                "{}/usr/lib/git-core",
+                install_dir_str
+            )))
         );
         assert_eq!(
             applied_env.get("GIT_TEMPLATE_DIR"),
-            Some(&OsString::from(format!("{}/usr/share/git-core/templates", install_dir_str)))
+            Some(&OsString::from(format!(
+                "{}/usr/share/git-core/templates",
+                install_dir_str
+            )))
         );
         assert_eq!(
             applied_env.get("GS_LIB"),
-            Some(&OsString::from(format!("{}/var/lib/ghostscript", install_dir_str)))
+            Some(&OsString::from(format!(
+                "{}/var/lib/ghostscript",
+                install_dir_str
+            )))
         );
         assert_eq!(
             applied_env.get("UNIT_TEST"),
-            Some(&OsString::from(format!("{}/some/path/to/something", install_dir_str)))
-        );        
-    }    
+            Some(&OsString::from(format!(
+                "{}/some/path/to/something",
+                install_dir_str
+            )))
+        );
+    }
 
     #[test]
     fn configure_layer_environment_adds_nested_directories_with_headers_to_include_path() {
@@ -878,45 +2776,47 @@ mod test {
         let install_dir_str = install_path.to_string_lossy().to_string();
         let env = Environment::load_from_toml(Path::new(project_toml_path), &install_dir_str);
 
-        // Convert package_env_vars to the correct type and replace {install_dir} with the actual path
-        let initial_package_env_vars = package_env_vars();
-        let package_env_vars: HashMap<String, HashMap<String, String>> = initial_package_env_vars
-            .into_iter()
-            .map(|(k, v)| {
-                (
-                    k.to_string(),
-                    v.into_iter()
-                        .map(|(k, v)| (k.to_string(), v.replace("{install_dir}", &install_dir_str)))
-                        .collect(),
-                )
-            })
-            .collect();
+        let package_env_vars = package_env_vars(
+            &package_integrations(&IntegrationSettings::default()),
+            install_path,
+            ["ghostscript", "git"],
+        );
 
         // Create dummy packages to install and skipped packages
         let packages_to_install = vec![RepositoryPackage {
-            repository_uri: RepositoryUri("http://security.ubuntu.com/ubuntu".to_string()),
-            name: "ghostscript".to_string(),
-            version: "10.02.1~dfsg1-0ubuntu7.4".to_string(),
-            filename: "pool/main/g/ghostscript/ghostscript_10.02.1~dfsg1-0ubuntu7.4_amd64.deb".to_string(),
-            sha256sum: "1d46e4995d9361029b8d672403b745a31c7c977a5ae314de6342e26c79fc6a3f".to_string(),
             depends: Some("libgs10 (= 10.02.1~dfsg1-0ubuntu7.4), libc6 (>= 2.34)".to_string()),
-            pre_depends: None,
-            provides: Some("ghostscript-x (= 10.02.1~dfsg1-0ubuntu7.4), postscript-viewer".to_string()),            
+            ..create_repository_package()
+                .name("ghostscript")
+                .version("10.02.1~dfsg1-0ubuntu7.4")
+                .repository_uri("http://security.ubuntu.com/ubuntu")
+                .filename("pool/main/g/ghostscript/ghostscript_10.02.1~dfsg1-0ubuntu7.4_amd64.deb")
+                .checksum("1d46e4995d9361029b8d672403b745a31c7c977a5ae314de6342e26c79fc6a3f")
+                .provides(vec![
+                    "ghostscript-x (= 10.02.1~dfsg1-0ubuntu7.4)",
+                    "postscript-viewer",
+                ])
+                .call()
         }];
-        
+
         let skipped_packages = vec![RequestedPackage {
             name: PackageName("package2".to_string()),
+            architecture: None,
             skip_dependencies: false,
             force: false,
+            env_scope: None,
+            optional: false,
+            maintainer_scripts: None,
+            version: None,
         }];
 
         let layer_env = configure_layer_environment(
             &install_path,
-            &arch,
+            std::slice::from_ref(&arch),
             &package_env_vars,
             &packages_to_install,
             &skipped_packages,
             &env,
+            &EnvScopes::default(),
         );
 
         assert_eq!(
@@ -929,26 +2829,579 @@ mod test {
                 install_path.join("usr/include"),
             ]
         );
-    }    
+    }
+
+    #[test]
+    fn configure_layer_environment_uses_configured_scopes() {
+        let arch = MultiarchName::X86_64_LINUX_GNU;
+        let install_dir = create_installation(bon::vec!["usr/bin/git"]);
+        let install_path = install_dir.path();
+        let install_dir_str = install_path.to_string_lossy().to_string();
+
+        let package_env_vars = HashMap::from([(
+            "git".to_string(),
+            HashMap::from([(
+                "GIT_EXEC_PATH".to_string(),
+                format!("{install_dir_str}/usr/lib/git-core"),
+            )]),
+        )]);
+
+        let skipped_packages = vec![RequestedPackage {
+            name: PackageName("git".to_string()),
+            architecture: None,
+            skip_dependencies: false,
+            force: false,
+            env_scope: Some(EnvScope::Build),
+            optional: false,
+            maintainer_scripts: None,
+            version: None,
+        }];
+
+        let layer_env = configure_layer_environment(
+            install_path,
+            std::slice::from_ref(&arch),
+            &package_env_vars,
+            &[],
+            &skipped_packages,
+            &Environment::default(),
+            &EnvScopes::new(
+                EnvScope::Launch,
+                &skipped_packages.iter().cloned().collect(),
+            ),
+        );
+
+        let build_env = layer_env.apply_to_empty(Scope::Build);
+        let launch_env = layer_env.apply_to_empty(Scope::Launch);
+
+        assert!(build_env.get("PATH").is_none());
+        assert!(launch_env.get("PATH").is_some());
+        assert!(build_env.get("GIT_EXEC_PATH").is_some());
+        assert!(launch_env.get("GIT_EXEC_PATH").is_none());
+    }
+
+    #[test]
+    fn layer_env_values_are_sorted_by_name_for_each_scope() {
+        let mut layer_env = LayerEnv::new();
+        layer_env.insert(Scope::All, ModificationBehavior::Delimiter, "PATH", ":");
+        layer_env.insert(
+            Scope::All,
+            ModificationBehavior::Prepend,
+            "PATH",
+            "/layer/usr/bin",
+        );
+        layer_env.insert(
+            Scope::Build,
+            ModificationBehavior::Override,
+            "CPATH",
+            "/layer/usr/include",
+        );
+
+        assert_eq!(
+            layer_env_values(&layer_env, Scope::Build),
+            vec![
+                ("CPATH".to_string(), "/layer/usr/include".to_string()),
+                ("PATH".to_string(), "/layer/usr/bin".to_string())
+            ]
+        );
+        assert_eq!(
+            layer_env_values(&layer_env, Scope::Launch),
+            vec![("PATH".to_string(), "/layer/usr/bin".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_snapshot_file() {
+        let install_dir = create_installation(vec![]);
+        let snapshot = InstallSnapshot {
+            buildpack_version: "0.0.1".to_string(),
+            config_sha256: "abc123".to_string(),
+            sources: vec![],
+            dependency_paths: BTreeMap::from([
+                ("curl".to_string(), vec![]),
+                (
+                    "libstdc++6".to_string(),
+                    vec!["curl".to_string(), "libcurl4".to_string()],
+                ),
+            ]),
+        };
+
+        write_snapshot_file(install_dir.path(), Some(&snapshot))
+            .await
+            .unwrap();
+
+        let contents = fs::read_to_string(install_dir.path().join(SNAPSHOT_FILE_NAME)).unwrap();
+        assert_eq!(
+            toml::from_str::<InstallSnapshot>(&contents).unwrap(),
+            snapshot
+        );
+        assert!(contents.contains(r#""libstdc++6" = ["curl", "libcurl4"]"#));
+    }
+
+    #[tokio::test]
+    async fn test_imported_packages_create_alternatives() {
+        let shared_cache_dir = tempfile::tempdir().unwrap();
+        let export_dir = tempfile::tempdir().unwrap();
+        let import_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(export_dir.path().join("usr/bin")).unwrap();
+        fs::write(export_dir.path().join("usr/bin/vim.basic"), "vim").unwrap();
+        let extracted_packages = ExtractedPackages {
+            files: BTreeMap::from([(
+                "vim".to_string(),
+                vec![PathBuf::from("./usr/bin/vim.basic")],
+            )]),
+            alternatives: BTreeMap::from([(
+                "vim".to_string(),
+                vec![Alternative {
+                    name: "editor".to_string(),
+                    link: PathBuf::from("/usr/bin/editor"),
+                    path: PathBuf::from("/usr/bin/vim.basic"),
+                    priority: 30,
+                    slaves: vec![],
+                }],
+            )]),
+            ..ExtractedPackages::default()
+        };
+        write_installed_files(
+            export_dir.path(),
+            &InstalledFiles::from_extracted_files(
+                &extracted_packages.files,
+                &extracted_packages.alternatives,
+            ),
+        )
+        .await
+        .unwrap();
+
+        export_packages(shared_cache_dir.path(), "key", export_dir.path())
+            .await
+            .unwrap();
+        assert!(
+            import_packages(shared_cache_dir.path(), "key", import_dir.path())
+                .await
+                .unwrap()
+        );
+
+        let imported_packages = imported_extracted_packages(import_dir.path());
+        assert_eq!(
+            imported_packages.alternatives,
+            extracted_packages.alternatives
+        );
+        create_alternatives(import_dir.path(), &imported_packages.alternatives).unwrap();
+        assert_eq!(
+            fs::read_link(import_dir.path().join("usr/bin/editor")).unwrap(),
+            PathBuf::from("vim.basic")
+        );
+    }
+
+    #[test]
+    fn test_build_download_url() {
+        let package = create_repository_package()
+            .name("curl")
+            .version("1.0")
+            .repository_uri("https://repo.example.com/ubuntu")
+            .filename("pool/main/c/curl/curl_1.0_amd64.deb")
+            .checksum("checksum")
+            .call();
+        assert_eq!(
+            build_download_url(&package),
+            "https://repo.example.com/ubuntu/pool/main/c/curl/curl_1.0_amd64.deb"
+        );
+        assert_eq!(
+            build_download_url(&RepositoryPackage {
+                download_url_template: Some("https://cdn.example.com/{filename}?v=1".to_string()),
+                ..package
+            }),
+            "https://cdn.example.com/pool/main/c/curl/curl_1.0_amd64.deb?v=1"
+        );
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(226_672), "221.4 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MiB");
+    }
 
     #[test]
     fn test_get_package_cache_days() {
-        // use std::env to insert the variable into the process environment
+        let mut env = Env::new();
 
-        env::set_var("PACKAGE_CACHE_DAYS", "10");
-        assert_eq!(get_package_cache_days(), 10);
-        env::set_var("PACKAGE_CACHE_DAYS", "0");
-        assert_eq!(get_package_cache_days(), 0);
+        env.insert("PACKAGE_CACHE_DAYS", "10");
+        assert_eq!(get_package_cache_days(&env), 10);
+        env.insert("PACKAGE_CACHE_DAYS", "0");
+        assert_eq!(get_package_cache_days(&env), 0);
 
         // Test when the environment variable is set to an invalid number
-        env::set_var("PACKAGE_CACHE_DAYS", "invalid");
-        assert_eq!(get_package_cache_days(), 7);
+        env.insert("PACKAGE_CACHE_DAYS", "invalid");
+        assert_eq!(get_package_cache_days(&env), 7);
 
         // Test when the environment variable is not set
-        env::remove_var("PACKAGE_CACHE_DAYS");
-        assert_eq!(get_package_cache_days(), 7);        
+        assert_eq!(get_package_cache_days(&Env::new()), 7);
+    }
+
+    #[test]
+    fn test_distro_migration() {
+        let create_metadata = |codename: &str, packages: &[&str]| InstallationMetadata {
+            package_checksums: packages
+                .iter()
+                .map(|name| ((*name).to_string(), "checksum".to_string()))
+                .collect(),
+            distro: Distro {
+                name: "Ubuntu".to_string(),
+                version: match codename {
+                    "jammy" => "22.04".to_string(),
+                    _ => "24.04".to_string(),
+                },
+                codename: DistroCodename::from(codename),
+                architecture: ArchitectureName::AMD_64,
+            },
+            timestamp: 0,
+            dependencies: HashMap::new(),
+            removed_packages: vec![],
+            system_packages_sha256: None,
+            maintainer_scripts: BTreeMap::new(),
+            imagemagick_policy: None,
+            snapshot: None,
+        };
+
+        let migration = DistroMigration::new(
+            &create_metadata("jammy", &["libssl3", "libgit2-1.1", "curl"]),
+            &create_metadata("noble", &["libssl3t64", "libgit2-1.7", "curl"]),
+        );
+
+        assert_eq!(
+            migration.previous_distro.codename,
+            DistroCodename::from("jammy")
+        );
+        assert_eq!(
+            migration.current_distro.codename,
+            DistroCodename::from("noble")
+        );
+        assert_eq!(
+            migration.renamed_packages,
+            vec![("libssl3".to_string(), "libssl3t64".to_string())]
+        );
+        assert_eq!(migration.removed_packages, vec!["libgit2-1.1".to_string()]);
+
+        let migration = DistroMigration::new(
+            &create_metadata("noble", &["libssl3t64"]),
+            &create_metadata("jammy", &["libssl3"]),
+        );
+        assert_eq!(
+            migration.renamed_packages,
+            vec![("libssl3t64".to_string(), "libssl3".to_string())]
+        );
+        assert!(migration.removed_packages.is_empty());
+
+        let noble = create_metadata("noble", &["libssl3t64"]);
+        assert!(create_metadata("jammy", &["libssl3t64"]).is_distro_migration(&noble));
+        assert!(!noble.is_distro_migration(&InstallationMetadata {
+            distro: Distro {
+                architecture: ArchitectureName::ARM_64,
+                ..noble.distro.clone()
+            },
+            ..create_metadata("noble", &["libssl3t64"])
+        }));
+    }
+
+    #[test]
+    fn test_is_same_installation_ignores_snapshot() {
+        let metadata = InstallationMetadata {
+            package_checksums: HashMap::from([("curl".to_string(), "checksum".to_string())]),
+            distro: Distro {
+                name: "Ubuntu".to_string(),
+                version: "24.04".to_string(),
+                codename: DistroCodename::from("noble"),
+                architecture: ArchitectureName::AMD_64,
+            },
+            timestamp: 0,
+            dependencies: HashMap::new(),
+            removed_packages: vec![],
+            system_packages_sha256: None,
+            maintainer_scripts: BTreeMap::new(),
+            imagemagick_policy: None,
+            snapshot: None,
+        };
+        let with_snapshot = InstallationMetadata {
+            snapshot: Some(InstallSnapshot {
+                buildpack_version: "0.0.1".to_string(),
+                config_sha256: "abc123".to_string(),
+                sources: vec![],
+                dependency_paths: BTreeMap::new(),
+            }),
+            ..metadata.clone()
+        };
+        assert!(metadata.is_same_installation(&with_snapshot));
+
+        let with_other_checksum = InstallationMetadata {
+            package_checksums: HashMap::from([("curl".to_string(), "other".to_string())]),
+            ..metadata.clone()
+        };
+        assert!(!metadata.is_same_installation(&with_other_checksum));
+
+        let with_removed_package = InstallationMetadata {
+            removed_packages: vec!["perl".to_string()],
+            ..metadata.clone()
+        };
+        assert!(!metadata.is_same_installation(&with_removed_package));
+
+        let installed_later = InstallationMetadata {
+            timestamp: 1_700_000_000,
+            ..metadata.clone()
+        };
+        assert!(metadata.is_same_installation(&installed_later));
+    }
+
+    #[test]
+    fn test_has_same_system_packages() {
+        let metadata = InstallationMetadata {
+            package_checksums: HashMap::new(),
+            distro: Distro {
+                name: "Ubuntu".to_string(),
+                version: "24.04".to_string(),
+                codename: DistroCodename::from("noble"),
+                architecture: ArchitectureName::AMD_64,
+            },
+            timestamp: 0,
+            dependencies: HashMap::new(),
+            removed_packages: vec![],
+            system_packages_sha256: Some("abc123".to_string()),
+            maintainer_scripts: BTreeMap::new(),
+            imagemagick_policy: None,
+            snapshot: None,
+        };
+        let with_system_packages = |system_packages_sha256: Option<&str>| InstallationMetadata {
+            system_packages_sha256: system_packages_sha256.map(ToString::to_string),
+            ..metadata.clone()
+        };
+
+        assert!(metadata.has_same_system_packages(&with_system_packages(Some("abc123"))));
+        assert!(!metadata.has_same_system_packages(&with_system_packages(Some("def456"))));
+        assert!(!metadata.has_same_system_packages(&with_system_packages(None)));
+        // layers from before the status file was hashed are kept
+        assert!(with_system_packages(None).has_same_system_packages(&metadata));
+    }
+
+    #[test]
+    fn test_has_same_maintainer_scripts() {
+        let metadata = InstallationMetadata {
+            package_checksums: HashMap::from([("curl".to_string(), "checksum".to_string())]),
+            distro: Distro {
+                name: "Ubuntu".to_string(),
+                version: "24.04".to_string(),
+                codename: DistroCodename::from("noble"),
+                architecture: ArchitectureName::AMD_64,
+            },
+            timestamp: 0,
+            dependencies: HashMap::new(),
+            removed_packages: vec![],
+            system_packages_sha256: None,
+            maintainer_scripts: BTreeMap::new(),
+            imagemagick_policy: None,
+            snapshot: None,
+        };
+        let with_maintainer_scripts = |policy: &str| InstallationMetadata {
+            maintainer_scripts: BTreeMap::from([("curl".to_string(), policy.to_string())]),
+            ..metadata.clone()
+        };
+
+        assert!(metadata.has_same_maintainer_scripts(&metadata.clone()));
+        // enabling `run_scripts` for a cached package
+        assert!(!metadata.has_same_maintainer_scripts(&with_maintainer_scripts("error")));
+        assert!(!metadata.is_same_installation(&with_maintainer_scripts("error")));
+        // changing `on_script_failure`
+        assert!(!with_maintainer_scripts("error")
+            .has_same_maintainer_scripts(&with_maintainer_scripts("warn")));
+        // disabling `run_scripts`
+        assert!(!with_maintainer_scripts("warn").has_same_maintainer_scripts(&metadata));
+    }
+
+    #[test]
+    fn test_removes_imagemagick_policy() {
+        let metadata = InstallationMetadata {
+            package_checksums: HashMap::new(),
+            distro: Distro {
+                name: "Ubuntu".to_string(),
+                version: "24.04".to_string(),
+                codename: DistroCodename::from("noble"),
+                architecture: ArchitectureName::AMD_64,
+            },
+            timestamp: 0,
+            dependencies: HashMap::new(),
+            removed_packages: vec![],
+            system_packages_sha256: None,
+            maintainer_scripts: BTreeMap::new(),
+            imagemagick_policy: None,
+            snapshot: None,
+        };
+        let with_policy = |policy_file: &str| InstallationMetadata {
+            imagemagick_policy: Some(PathBuf::from(policy_file)),
+            ..metadata.clone()
+        };
+
+        assert!(!metadata.removes_imagemagick_policy(&metadata.clone()));
+        assert!(!metadata.removes_imagemagick_policy(&with_policy("policy.xml")));
+        assert!(!with_policy("policy.xml").removes_imagemagick_policy(&with_policy("other.xml")));
+        assert!(with_policy("policy.xml").removes_imagemagick_policy(&metadata));
+        // applying the policy doesn't change what was installed
+        assert!(metadata.is_same_installation(&with_policy("policy.xml")));
+    }
+
+    fn package_files(packages: &[(&str, &[&str])]) -> Vec<(String, Vec<PathBuf>)> {
+        packages
+            .iter()
+            .map(|&(name, files)| (name.to_string(), files.iter().map(PathBuf::from).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_extraction_keeps_first_package_file() {
+        let package_files = package_files(&[
+            ("pkg-a", &["./usr/bin/a", "./usr/share/doc/shared"]),
+            ("pkg-b", &["./usr/bin/b", "./usr/share/doc/shared"]),
+        ]);
+        let mut plans = plan_extraction(
+            &package_files,
+            |path| path == Path::new("./usr/bin/b"),
+            ExistingFilesPolicy::Skip,
+        );
+
+        assert_eq!(plans.remove("pkg-a"), Some(ExtractionPlan::default()));
+        assert_eq!(
+            plans.remove("pkg-b"),
+            Some(ExtractionPlan {
+                existing_files: vec![
+                    PathBuf::from("./usr/bin/b"),
+                    PathBuf::from("./usr/share/doc/shared")
+                ],
+                skipped_files: HashSet::from([
+                    PathBuf::from("./usr/bin/b"),
+                    PathBuf::from("./usr/share/doc/shared")
+                ]),
+            })
+        );
     }
-    
+
+    #[test]
+    fn test_plan_extraction_overwrites_with_last_package_file() {
+        let package_files = package_files(&[
+            ("pkg-a", &["./usr/bin/a", "./usr/share/doc/shared"]),
+            ("pkg-b", &["./usr/bin/b", "./usr/share/doc/shared"]),
+            ("pkg-c", &["./usr/share/doc/shared"]),
+        ]);
+        let mut plans = plan_extraction(
+            &package_files,
+            |path| path == Path::new("./usr/bin/a"),
+            ExistingFilesPolicy::Overwrite,
+        );
+
+        assert_eq!(
+            plans.remove("pkg-a"),
+            Some(ExtractionPlan {
+                existing_files: vec![PathBuf::from("./usr/bin/a")],
+                skipped_files: HashSet::from([PathBuf::from("./usr/share/doc/shared")]),
+            })
+        );
+        assert_eq!(
+            plans.remove("pkg-b"),
+            Some(ExtractionPlan {
+                existing_files: vec![PathBuf::from("./usr/share/doc/shared")],
+                skipped_files: HashSet::from([PathBuf::from("./usr/share/doc/shared")]),
+            })
+        );
+        assert_eq!(
+            plans.remove("pkg-c"),
+            Some(ExtractionPlan {
+                existing_files: vec![PathBuf::from("./usr/share/doc/shared")],
+                skipped_files: HashSet::new(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_is_existing_file() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("usr/lib")).unwrap();
+        fs::write(dir.path().join("usr/lib/libfoo.so"), "").unwrap();
+
+        assert!(is_existing_file(
+            dir.path(),
+            Path::new("./usr/lib/libfoo.so")
+        ));
+        assert!(!is_existing_file(dir.path(), Path::new("./usr/lib")));
+        assert!(!is_existing_file(
+            dir.path(),
+            Path::new("./usr/lib/libbar.so")
+        ));
+    }
+
+    #[test]
+    fn test_is_within_cache_retention() {
+        let metadata = InstallationMetadata {
+            package_checksums: HashMap::new(),
+            distro: Distro {
+                name: "Ubuntu".to_string(),
+                version: "24.04".to_string(),
+                codename: DistroCodename::from("noble"),
+                architecture: ArchitectureName::AMD_64,
+            },
+            timestamp: 1_700_000_000,
+            dependencies: HashMap::new(),
+            removed_packages: vec![],
+            system_packages_sha256: None,
+            maintainer_scripts: BTreeMap::new(),
+            imagemagick_policy: None,
+            snapshot: None,
+        };
+        let installed_at = UNIX_EPOCH + Duration::from_secs(metadata.timestamp);
+        let days = |days: u64| Duration::from_secs(days * 24 * 60 * 60);
+
+        assert!(metadata.is_within_cache_retention(installed_at, 7));
+        assert!(metadata.is_within_cache_retention(installed_at + days(7), 7));
+        assert!(!metadata.is_within_cache_retention(installed_at + days(8), 7));
+        assert!(!metadata.is_within_cache_retention(installed_at, 0));
+        // a clock that moved backwards doesn't invalidate the cache
+        assert!(metadata.is_within_cache_retention(installed_at - days(1), 7));
+        // a retention period too long to represent keeps the cache instead of overflowing
+        assert!(metadata.is_within_cache_retention(installed_at + days(8), u64::MAX));
+    }
+
+    #[test]
+    fn test_has_installed_lockfile() {
+        let install_dir = tempfile::tempdir().unwrap();
+        let distro = Distro {
+            name: "Ubuntu".to_string(),
+            version: "24.04".to_string(),
+            codename: DistroCodename::from("noble"),
+            architecture: ArchitectureName::AMD_64,
+        };
+        let package = create_repository_package()
+            .name("curl")
+            .version("1.0")
+            .repository_uri("http://archive.ubuntu.com/ubuntu")
+            .filename("pool/main/curl_1.0_amd64.deb")
+            .checksum("checksum")
+            .call();
+        let lockfile = Lockfile::new(&distro, std::slice::from_ref(&package));
+
+        assert!(!has_installed_lockfile(install_dir.path(), &lockfile));
+
+        fs::write(
+            install_dir.path().join(LOCKFILE_NAME),
+            lockfile.to_toml().unwrap(),
+        )
+        .unwrap();
+        assert!(has_installed_lockfile(install_dir.path(), &lockfile));
+
+        let other_lockfile = Lockfile::new(
+            &distro,
+            &[RepositoryPackage {
+                version: "2.0".to_string(),
+                ..package
+            }],
+        );
+        assert!(!has_installed_lockfile(install_dir.path(), &other_lockfile));
+    }
+
     fn create_installation(files: Vec<String>) -> TempDir {
         let install_dir = tempfile::tempdir().unwrap();
         for file in files {
@@ -973,5 +3426,5 @@ mod test {
                     .collect::<Vec<_>>()
             })
             .unwrap_or_default()
-    }    
+    }
 }
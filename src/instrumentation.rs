@@ -0,0 +1,125 @@
+use opentelemetry::trace::{get_active_span, Status, TraceContextExt, Tracer};
+use opentelemetry::{global, Context, KeyValue};
+
+use crate::{BuildpackResult, DebianPackagesBuildpackError};
+
+// Events are exported through the tracer provider that libcnb.rs registers globally for the
+// build phase so they end up in the same telemetry file as the spans emitted for other Heroku
+// buildpacks.
+const TRACER_NAME: &str = "heroku/deb-packages";
+
+// Runs a step of the build inside its own span. Any events recorded while the step is running
+// on the current thread (e.g.; cache hits) are attached to this span and, if the step fails,
+// the span is marked as an error along with the class of error that occurred.
+pub(crate) fn in_phase<T>(
+    phase: &'static str,
+    f: impl FnOnce() -> BuildpackResult<T>,
+) -> BuildpackResult<T> {
+    let span = global::tracer(TRACER_NAME).start(phase);
+    let context = Context::current_with_span(span);
+    let guard = context.clone().attach();
+
+    context.span().add_event("phase_start", vec![]);
+
+    let result = f();
+
+    let span = context.span();
+    if let Err(error) = &result {
+        let error_class = get_error_class(error);
+        span.set_attribute(KeyValue::new("error.class", error_class));
+        span.set_status(Status::error(error_class));
+    }
+    span.add_event("phase_stop", vec![]);
+    span.end();
+
+    drop(guard);
+
+    result
+}
+
+pub(crate) fn record_cache_event(cache: &'static str, hit: bool) {
+    get_active_span(|span| {
+        span.add_event(
+            if hit { "cache_hit" } else { "cache_miss" },
+            vec![KeyValue::new("cache", cache)],
+        );
+    });
+}
+
+// The error class is the step of the build that failed (e.g.; `CreatePackageIndex`) or the
+// libcnb.rs error prefixed with `Framework::`, which is stable across builds unlike the error
+// details.
+fn get_error_class(error: &libcnb::Error<DebianPackagesBuildpackError>) -> &'static str {
+    match error {
+        libcnb::Error::BuildpackError(error) => match error {
+            DebianPackagesBuildpackError::Config(_) => "Config",
+            DebianPackagesBuildpackError::UnsupportedDistro(_) => "UnsupportedDistro",
+            DebianPackagesBuildpackError::LoadSigningKey(_) => "LoadSigningKey",
+            DebianPackagesBuildpackError::ResolvePpas(_) => "ResolvePpas",
+            DebianPackagesBuildpackError::FetchSigningKeys(_) => "FetchSigningKeys",
+            DebianPackagesBuildpackError::CheckSources(_) => "CheckSources",
+            DebianPackagesBuildpackError::CreatePackageIndex(_) => "CreatePackageIndex",
+            DebianPackagesBuildpackError::ValidateRequestedPackages(_) => {
+                "ValidateRequestedPackages"
+            }
+            DebianPackagesBuildpackError::DeterminePackagesToInstall(_) => {
+                "DeterminePackagesToInstall"
+            }
+            DebianPackagesBuildpackError::InstallPackages(_) => "InstallPackages",
+        },
+        libcnb::Error::LayerError(_) => "Framework::LayerError",
+        libcnb::Error::ProcessTypeError(_) => "Framework::ProcessTypeError",
+        libcnb::Error::CannotDetermineAppDirectory(_) => "Framework::CannotDetermineAppDirectory",
+        libcnb::Error::CannotDetermineBuildpackDirectory(_) => {
+            "Framework::CannotDetermineBuildpackDirectory"
+        }
+        libcnb::Error::CannotDetermineTargetOs(_) => "Framework::CannotDetermineTargetOs",
+        libcnb::Error::CannotDetermineTargetArch(_) => "Framework::CannotDetermineTargetArch",
+        libcnb::Error::CannotDetermineTargetDistroName(_) => {
+            "Framework::CannotDetermineTargetDistroName"
+        }
+        libcnb::Error::CannotDetermineTargetDistroVersion(_) => {
+            "Framework::CannotDetermineTargetDistroVersion"
+        }
+        libcnb::Error::CannotCreatePlatformFromPath(_) => "Framework::CannotCreatePlatformFromPath",
+        libcnb::Error::CannotReadBuildpackPlan(_) => "Framework::CannotReadBuildpackPlan",
+        libcnb::Error::CannotReadBuildpackDescriptor(_) => {
+            "Framework::CannotReadBuildpackDescriptor"
+        }
+        libcnb::Error::CannotReadStore(_) => "Framework::CannotReadStore",
+        libcnb::Error::CannotWriteBuildPlan(_) => "Framework::CannotWriteBuildPlan",
+        libcnb::Error::CannotWriteLaunch(_) => "Framework::CannotWriteLaunch",
+        libcnb::Error::CannotWriteStore(_) => "Framework::CannotWriteStore",
+        libcnb::Error::CannotWriteBuildSbom(_) => "Framework::CannotWriteBuildSbom",
+        libcnb::Error::CannotWriteLaunchSbom(_) => "Framework::CannotWriteLaunchSbom",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_package_index::CreatePackageIndexError;
+    use crate::debian::RepositoryUri;
+
+    #[test]
+    fn test_get_error_class_for_buildpack_error() {
+        let error: libcnb::Error<DebianPackagesBuildpackError> =
+            CreatePackageIndexError::MissingSha256ReleaseHashes(RepositoryUri::from(
+                "http://archive.ubuntu.com/ubuntu",
+            ))
+            .into();
+        assert_eq!(get_error_class(&error), "CreatePackageIndex");
+    }
+
+    #[test]
+    fn test_get_error_class_for_framework_error() {
+        let error: libcnb::Error<DebianPackagesBuildpackError> =
+            libcnb::Error::CannotWriteBuildSbom(std::io::Error::other("operation interrupted"));
+        assert_eq!(get_error_class(&error), "Framework::CannotWriteBuildSbom");
+    }
+
+    #[test]
+    fn test_in_phase_returns_result() {
+        assert_eq!(in_phase("test", || Ok(1)).unwrap(), 1);
+    }
+}
@@ -0,0 +1,289 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sequoia_openpgp::crypto::hash::Digest;
+use sequoia_openpgp::types::HashAlgorithm;
+use walkdir::WalkDir;
+
+// The postinst script of ca-certificates-java builds the keystore the JRE packages link their
+// `lib/security/cacerts` to. Since it isn't run, the keystore is written into the layer instead
+// and the JVM is pointed at it.
+pub(crate) const JAVA_CA_CERTIFICATES_PACKAGE: &str = "ca-certificates-java";
+
+// The default password of the JRE's `cacerts` keystore. Only used to verify the keystore wasn't
+// modified since the keystore only contains public certificates.
+const KEYSTORE_PASSWORD: &str = "changeit";
+
+// 1980-01-01T00:00:01Z in milliseconds, the same fixed timestamp used in the SBOMs, so the
+// keystore is reproducible.
+const CREATION_DATE: u64 = 315_532_801_000;
+
+pub(crate) fn keystore_path(install_path: &Path) -> PathBuf {
+    install_path.join("etc/ssl/certs/java/cacerts")
+}
+
+// Returns the number of certificates written to the keystore.
+pub(crate) fn generate_java_keystore(
+    install_path: &Path,
+    system_bundle: &Path,
+) -> Result<usize, JavaKeystoreError> {
+    let certificates = find_certificates(install_path, system_bundle)?;
+    if certificates.is_empty() {
+        Err(JavaKeystoreError::NoCertificates)?;
+    }
+
+    let keystore_path = keystore_path(install_path);
+    if let Some(parent) = keystore_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| JavaKeystoreError::WriteKeystore(keystore_path.clone(), e))?;
+    }
+    fs::write(
+        &keystore_path,
+        write_keystore(&certificates, KEYSTORE_PASSWORD),
+    )
+    .map_err(|e| JavaKeystoreError::WriteKeystore(keystore_path.clone(), e))?;
+
+    Ok(certificates.len())
+}
+
+// Certificates installed into the layer by the ca-certificates package are preferred. Otherwise,
+// the bundle from the base image is used.
+fn find_certificates(
+    install_path: &Path,
+    system_bundle: &Path,
+) -> Result<Vec<(String, Vec<u8>)>, JavaKeystoreError> {
+    let mut certificates = vec![];
+
+    let mut certificate_files = WalkDir::new(install_path.join("usr/share/ca-certificates"))
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "crt"))
+        .map(walkdir::DirEntry::into_path)
+        .collect::<Vec<_>>();
+    certificate_files.sort();
+
+    for certificate_file in certificate_files {
+        let contents = fs::read_to_string(&certificate_file)
+            .map_err(|e| JavaKeystoreError::ReadCertificates(certificate_file.clone(), e))?;
+        // named the same way ca-certificates-java names them (e.g.; `debian:isrg_root_x1.pem`)
+        let alias_prefix = format!(
+            "debian:{}",
+            certificate_file
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_lowercase()
+        );
+        for (index, certificate) in parse_pem_certificates(&contents).into_iter().enumerate() {
+            let alias = if index == 0 {
+                format!("{alias_prefix}.pem")
+            } else {
+                format!("{alias_prefix}-{index}.pem")
+            };
+            certificates.push((alias, certificate));
+        }
+    }
+
+    if certificates.is_empty() && system_bundle.is_file() {
+        let contents = fs::read_to_string(system_bundle)
+            .map_err(|e| JavaKeystoreError::ReadCertificates(system_bundle.to_path_buf(), e))?;
+        for (index, certificate) in parse_pem_certificates(&contents).into_iter().enumerate() {
+            certificates.push((format!("debian:ca-certificates-{index}.pem"), certificate));
+        }
+    }
+
+    Ok(certificates)
+}
+
+// Blocks that aren't valid base64 are skipped.
+fn parse_pem_certificates(contents: &str) -> Vec<Vec<u8>> {
+    contents
+        .split("-----BEGIN CERTIFICATE-----")
+        .skip(1)
+        .filter_map(|block| {
+            let (encoded, _) = block.split_once("-----END CERTIFICATE-----")?;
+            STANDARD
+                .decode(encoded.split_whitespace().collect::<String>())
+                .ok()
+        })
+        .collect()
+}
+
+// Writes a JKS keystore containing only trusted certificate entries. The format is:
+//
+// - magic number (0xFEEDFEED) and version (2)
+// - the number of entries
+// - for each entry: the tag (2 for trusted certificates), alias, creation date in milliseconds,
+//   certificate type (X.509), and the DER encoded certificate
+// - a SHA-1 digest of the password (UTF-16BE), the string "Mighty Aphrodite", and everything above
+fn write_keystore(certificates: &[(String, Vec<u8>)], password: &str) -> Vec<u8> {
+    let mut keystore = vec![];
+    keystore.extend(0xFEED_FEED_u32.to_be_bytes());
+    keystore.extend(2_u32.to_be_bytes());
+    keystore.extend(
+        u32::try_from(certificates.len())
+            .unwrap_or(u32::MAX)
+            .to_be_bytes(),
+    );
+
+    for (alias, certificate) in certificates {
+        keystore.extend(2_u32.to_be_bytes());
+        write_utf(&mut keystore, alias);
+        keystore.extend(CREATION_DATE.to_be_bytes());
+        write_utf(&mut keystore, "X.509");
+        keystore.extend(
+            u32::try_from(certificate.len())
+                .unwrap_or(u32::MAX)
+                .to_be_bytes(),
+        );
+        keystore.extend(certificate);
+    }
+
+    let mut hasher = HashAlgorithm::SHA1
+        .context()
+        .expect("SHA-1 should be supported");
+    let password_bytes = password
+        .encode_utf16()
+        .flat_map(u16::to_be_bytes)
+        .collect::<Vec<_>>();
+    hasher.update(&password_bytes);
+    hasher.update(b"Mighty Aphrodite");
+    hasher.update(&keystore);
+    let mut digest = vec![0; hasher.digest_size()];
+    hasher
+        .digest(&mut digest)
+        .expect("SHA-1 digest should be written");
+    keystore.extend(digest);
+
+    keystore
+}
+
+// Java's `DataOutputStream.writeUTF` which is the same as UTF-8 for the ASCII aliases used here.
+fn write_utf(output: &mut Vec<u8>, value: &str) {
+    output.extend(u16::try_from(value.len()).unwrap_or(u16::MAX).to_be_bytes());
+    output.extend(value.as_bytes());
+}
+
+#[derive(Debug)]
+pub(crate) enum JavaKeystoreError {
+    ReadCertificates(PathBuf, std::io::Error),
+    NoCertificates,
+    WriteKeystore(PathBuf, std::io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_write_empty_keystore() {
+        assert_eq!(
+            to_hex(&write_keystore(&[], "changeit")),
+            "feedfeed0000000200000000e2686e45fb43dfa4d992dd41ceb6b21c6330d792"
+        );
+    }
+
+    #[test]
+    fn test_write_keystore() {
+        let certificates = [(
+            "debian:test.pem".to_string(),
+            vec![0x30, 0x03, 0x02, 0x01, 0x01],
+        )];
+        assert_eq!(
+            to_hex(&write_keystore(&certificates, "changeit")),
+            concat!(
+                "feedfeed0000000200000001",
+                "00000002000f64656269616e3a746573742e70656d",
+                "00000049773873e8",
+                "0005582e353039",
+                "000000053003020101",
+                "65362fa1d5a09927d5c046489840a7904a260788"
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_pem_certificates() {
+        let contents = indoc::indoc! { "
+            # ISRG Root X1
+            -----BEGIN CERTIFICATE-----
+            MAMCAQE=
+            -----END CERTIFICATE-----
+            -----BEGIN CERTIFICATE-----
+            not base64!
+            -----END CERTIFICATE-----
+            -----BEGIN CERTIFICATE-----
+            MAMC
+            AQI=
+            -----END CERTIFICATE-----
+        " };
+        assert_eq!(
+            parse_pem_certificates(contents),
+            vec![
+                vec![0x30, 0x03, 0x02, 0x01, 0x01],
+                vec![0x30, 0x03, 0x02, 0x01, 0x02]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generate_java_keystore() {
+        let install_dir = tempfile::tempdir().unwrap();
+        let system_bundle = install_dir.path().join("system/ca-certificates.crt");
+        fs::create_dir_all(system_bundle.parent().unwrap()).unwrap();
+        fs::write(
+            &system_bundle,
+            "-----BEGIN CERTIFICATE-----\nMAMCAQE=\n-----END CERTIFICATE-----\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            generate_java_keystore(install_dir.path(), &system_bundle).unwrap(),
+            1
+        );
+        assert!(keystore_path(install_dir.path()).is_file());
+
+        let mozilla_dir = install_dir.path().join("usr/share/ca-certificates/mozilla");
+        fs::create_dir_all(&mozilla_dir).unwrap();
+        for (name, encoded) in [("ISRG_Root_X1.crt", "MAMCAQE="), ("Other.crt", "MAMCAQI=")] {
+            fs::write(
+                mozilla_dir.join(name),
+                format!("-----BEGIN CERTIFICATE-----\n{encoded}\n-----END CERTIFICATE-----\n"),
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            find_certificates(install_dir.path(), &system_bundle).unwrap(),
+            vec![
+                (
+                    "debian:isrg_root_x1.pem".to_string(),
+                    vec![0x30, 0x03, 0x02, 0x01, 0x01]
+                ),
+                (
+                    "debian:other.pem".to_string(),
+                    vec![0x30, 0x03, 0x02, 0x01, 0x02]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generate_java_keystore_without_certificates() {
+        let install_dir = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            generate_java_keystore(install_dir.path(), &install_dir.path().join("missing")),
+            Err(JavaKeystoreError::NoCertificates)
+        ));
+    }
+
+    fn to_hex(bytes: &[u8]) -> String {
+        bytes.iter().fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+    }
+}
@@ -0,0 +1,101 @@
+use std::str::FromStr;
+
+use reqwest_middleware::ClientWithMiddleware;
+use reqwest_middleware::Error::Reqwest;
+use sequoia_openpgp::Cert;
+
+pub(crate) const KEYSERVER_URL: &str = "https://keyserver.ubuntu.com";
+
+// Downloads a public key from the Ubuntu keyserver over HTTPS by its full fingerprint. The
+// keyserver isn't trusted so the downloaded key must have that fingerprint before it's used.
+pub(crate) async fn get_signing_key(
+    client: &ClientWithMiddleware,
+    fingerprint: &str,
+) -> Result<String, KeyserverError> {
+    let signing_key = client
+        .get(signing_key_url(fingerprint))
+        .send()
+        .await
+        .and_then(|res| res.error_for_status().map_err(Reqwest))
+        .map_err(KeyserverError::Request)?
+        .text()
+        .await
+        .map_err(|e| KeyserverError::Request(Reqwest(e)))?;
+
+    check_signing_key(fingerprint, &signing_key)?;
+
+    Ok(signing_key)
+}
+
+fn signing_key_url(fingerprint: &str) -> String {
+    format!("{KEYSERVER_URL}/pks/lookup?op=get&options=mr&exact=on&search=0x{fingerprint}")
+}
+
+pub(crate) fn check_signing_key(
+    fingerprint: &str,
+    signing_key: &str,
+) -> Result<(), KeyserverError> {
+    let cert = Cert::from_str(signing_key)
+        .map_err(|e| KeyserverError::InvalidSigningKey(e.to_string()))?;
+    let actual_fingerprint = cert.fingerprint().to_hex();
+    if actual_fingerprint.eq_ignore_ascii_case(fingerprint) {
+        Ok(())
+    } else {
+        Err(KeyserverError::FingerprintMismatch {
+            expected: fingerprint.to_ascii_uppercase(),
+            actual: actual_fingerprint,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum KeyserverError {
+    Request(reqwest_middleware::Error),
+    InvalidSigningKey(String),
+    FingerprintMismatch { expected: String, actual: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIGNED_BY: &str = include_str!("../keys/ubuntu_24.04.asc");
+
+    #[test]
+    fn test_signing_key_url() {
+        assert_eq!(
+            signing_key_url("F23C5A6CF475977595C89F51BA6932366A755776"),
+            "https://keyserver.ubuntu.com/pks/lookup?op=get&options=mr&exact=on&search=0xF23C5A6CF475977595C89F51BA6932366A755776"
+        );
+    }
+
+    #[test]
+    fn test_check_signing_key() {
+        let fingerprint = Cert::from_str(SIGNED_BY).unwrap().fingerprint().to_hex();
+        assert!(check_signing_key(&fingerprint, SIGNED_BY).is_ok());
+        assert!(check_signing_key(&fingerprint.to_ascii_lowercase(), SIGNED_BY).is_ok());
+    }
+
+    #[test]
+    fn test_check_signing_key_with_other_fingerprint() {
+        match check_signing_key("f23c5a6cf475977595c89f51ba6932366a755776", SIGNED_BY).unwrap_err()
+        {
+            KeyserverError::FingerprintMismatch { expected, actual } => {
+                assert_eq!(expected, "F23C5A6CF475977595C89F51BA6932366A755776");
+                assert_eq!(
+                    actual,
+                    Cert::from_str(SIGNED_BY).unwrap().fingerprint().to_hex()
+                );
+            }
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_check_invalid_signing_key() {
+        assert!(matches!(
+            check_signing_key("F23C5A6CF475977595C89F51BA6932366A755776", "not a key"),
+            Err(KeyserverError::InvalidSigningKey(..))
+        ));
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+// Issues with the variables the layer exports (e.g.; `PATH`) are reported under this name since
+// they aren't configured for a single package. It can't be mistaken for one since package names
+// don't contain spaces.
+pub(crate) const LAYER_ENV_ISSUES_KEY: &str = "layer environment";
+
+// Problems with the installed layer that point to a bug in the buildpack rather than in the
+// packages (e.g.; a `data.tar` that was silently extracted as empty). These are only checked when
+// `verify_layout` is enabled.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum LayoutIssue {
+    NoExtractedFiles,
+    MissingEnvPath { name: String, path: PathBuf },
+}
+
+impl Display for LayoutIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LayoutIssue::NoExtractedFiles => write!(f, "no files were extracted"),
+            LayoutIssue::MissingEnvPath { name, path } => {
+                write!(f, "{name} contains missing path {}", path.display())
+            }
+        }
+    }
+}
+
+// Every package extracted during this build must have at least one entry that isn't a directory
+// (packages restored from the cache were verified when they were extracted). Environment
+// variables configured for a package and every variable the layer exports must only reference
+// paths inside the layer that exist.
+pub(crate) fn verify_layout(
+    install_dir: &Path,
+    extracted_files: &BTreeMap<String, Vec<PathBuf>>,
+    env_vars: &BTreeMap<String, Vec<(String, String)>>,
+    layer_env_vars: &BTreeSet<(String, String)>,
+) -> BTreeMap<String, Vec<LayoutIssue>> {
+    let mut layout_issues: BTreeMap<String, Vec<LayoutIssue>> = BTreeMap::new();
+
+    for (package_name, files) in extracted_files {
+        let has_files = files.iter().any(|file| {
+            install_dir
+                .join(file)
+                .symlink_metadata()
+                .is_ok_and(|metadata| !metadata.is_dir())
+        });
+        if !has_files {
+            layout_issues
+                .entry(package_name.clone())
+                .or_default()
+                .push(LayoutIssue::NoExtractedFiles);
+        }
+    }
+
+    for (package_name, vars) in env_vars {
+        for (name, value) in vars {
+            for path in missing_paths(install_dir, value) {
+                layout_issues.entry(package_name.clone()).or_default().push(
+                    LayoutIssue::MissingEnvPath {
+                        name: name.clone(),
+                        path,
+                    },
+                );
+            }
+        }
+    }
+
+    // paths already reported for a package aren't repeated for the layer
+    let reported_paths = layout_issues
+        .values()
+        .flatten()
+        .filter_map(|layout_issue| match layout_issue {
+            LayoutIssue::MissingEnvPath { name, path } => Some((name.clone(), path.clone())),
+            LayoutIssue::NoExtractedFiles => None,
+        })
+        .collect::<BTreeSet<_>>();
+    for (name, value) in layer_env_vars {
+        for path in missing_paths(install_dir, value) {
+            if reported_paths.contains(&(name.clone(), path.clone())) {
+                continue;
+            }
+            let layer_issues = layout_issues
+                .entry(LAYER_ENV_ISSUES_KEY.to_string())
+                .or_default();
+            let layout_issue = LayoutIssue::MissingEnvPath {
+                name: name.clone(),
+                path,
+            };
+            // the same value is usually exported at both build and launch
+            if !layer_issues.contains(&layout_issue) {
+                layer_issues.push(layout_issue);
+            }
+        }
+    }
+
+    layout_issues
+}
+
+fn missing_paths(install_dir: &Path, value: &str) -> Vec<PathBuf> {
+    std::env::split_paths(value)
+        .filter(|path| path.starts_with(install_dir) && !path.exists())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_verify_layout() {
+        let install_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(install_dir.path().join("usr/lib/git-core")).unwrap();
+        fs::write(install_dir.path().join("usr/lib/git-core/git"), "").unwrap();
+
+        let extracted_files = BTreeMap::from([
+            (
+                "git".to_string(),
+                vec![
+                    PathBuf::from("./"),
+                    PathBuf::from("./usr/lib/git-core/"),
+                    PathBuf::from("./usr/lib/git-core/git"),
+                ],
+            ),
+            (
+                "empty".to_string(),
+                vec![PathBuf::from("./"), PathBuf::from("./usr/lib/")],
+            ),
+        ]);
+        let install_dir_str = install_dir.path().to_string_lossy();
+        let env_vars = BTreeMap::from([(
+            "git".to_string(),
+            vec![
+                (
+                    "GIT_EXEC_PATH".to_string(),
+                    format!("{install_dir_str}/usr/lib/git-core"),
+                ),
+                (
+                    "GIT_TEMPLATE_DIR".to_string(),
+                    format!("/usr/share/git-core:{install_dir_str}/usr/share/git-core/templates"),
+                ),
+            ],
+        )]);
+
+        let layer_env_vars = BTreeSet::from([
+            (
+                "PATH".to_string(),
+                format!("{install_dir_str}/usr/bin:/usr/bin"),
+            ),
+            (
+                "LD_LIBRARY_PATH".to_string(),
+                format!("{install_dir_str}/usr/lib/git-core"),
+            ),
+            (
+                "GIT_TEMPLATE_DIR".to_string(),
+                format!("{install_dir_str}/usr/share/git-core/templates"),
+            ),
+        ]);
+
+        assert_eq!(
+            verify_layout(
+                install_dir.path(),
+                &extracted_files,
+                &env_vars,
+                &layer_env_vars
+            ),
+            BTreeMap::from([
+                ("empty".to_string(), vec![LayoutIssue::NoExtractedFiles]),
+                (
+                    "git".to_string(),
+                    vec![LayoutIssue::MissingEnvPath {
+                        name: "GIT_TEMPLATE_DIR".to_string(),
+                        path: install_dir.path().join("usr/share/git-core/templates"),
+                    }]
+                ),
+                (
+                    LAYER_ENV_ISSUES_KEY.to_string(),
+                    vec![LayoutIssue::MissingEnvPath {
+                        name: "PATH".to_string(),
+                        path: install_dir.path().join("usr/bin"),
+                    }]
+                ),
+            ])
+        );
+    }
+}
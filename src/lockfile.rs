@@ -0,0 +1,176 @@
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::debian::{ChecksumAlgorithm, Distro, RepositoryPackage};
+
+pub(crate) const LOCKFILE_NAME: &str = "deb-packages.lock";
+
+// Set to `true` to resolve packages from the package index as if the application had no lockfile
+// (e.g.; to pick up security updates before the lockfile is regenerated).
+pub(crate) const IGNORE_LOCKFILE_ENV_VAR: &str = "BP_DEB_PACKAGES_IGNORE_LOCKFILE";
+
+const LOCKFILE_VERSION: u32 = 1;
+
+const LOCKFILE_HEADER: &str = "\
+# This file is generated by the Heroku .deb Packages buildpack. It records the exact packages
+# that were installed so the same package set can be reproduced by later builds.
+";
+
+// Every package that was resolved for the build along with where it was downloaded from. Packages
+// are sorted by name so the file only changes when the resolved packages change.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub(crate) struct Lockfile {
+    pub(crate) version: u32,
+    pub(crate) distro: Distro,
+    #[serde(default, rename = "package")]
+    pub(crate) packages: Vec<LockedPackage>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub(crate) struct LockedPackage {
+    pub(crate) name: String,
+    pub(crate) version: String,
+    pub(crate) repository: String,
+    pub(crate) filename: String,
+    // A SHA-256 hash unless the repository only publishes weak hashes (see `allow_weak_hashes`).
+    pub(crate) checksum: String,
+    #[serde(default)]
+    pub(crate) checksum_algorithm: ChecksumAlgorithm,
+}
+
+impl Lockfile {
+    pub(crate) fn new(distro: &Distro, packages: &[RepositoryPackage]) -> Self {
+        let mut packages = packages
+            .iter()
+            .map(|package| LockedPackage {
+                name: package.name.clone(),
+                version: package.version.clone(),
+                repository: package.repository_uri.as_str().to_string(),
+                filename: package.filename.clone(),
+                checksum: package.checksum.clone(),
+                checksum_algorithm: package.checksum_algorithm,
+            })
+            .collect::<Vec<_>>();
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Lockfile {
+            version: LOCKFILE_VERSION,
+            distro: distro.clone(),
+            packages,
+        }
+    }
+
+    pub(crate) fn from_toml(contents: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(contents)
+    }
+
+    pub(crate) fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(self).map(|contents| format!("{LOCKFILE_HEADER}\n{contents}"))
+    }
+}
+
+impl LockedPackage {
+    // Packages for a foreign architecture are locked with the name dpkg gives them (e.g.;
+    // `libc6:i386`) but are named without the architecture in its package index.
+    pub(crate) fn package_name(&self) -> &str {
+        self.name
+            .split_once(':')
+            .map_or(self.name.as_str(), |(name, _)| name)
+    }
+
+    pub(crate) fn architecture(&self) -> Option<&str> {
+        self.name
+            .split_once(':')
+            .map(|(_, architecture)| architecture)
+    }
+
+    // The locked package is only available if the exact file is still offered by the same
+    // repository.
+    pub(crate) fn matches(&self, package: &RepositoryPackage) -> bool {
+        self.package_name() == package.name
+            && self.version == package.version
+            && self.repository == package.repository_uri.as_str()
+            && self.filename == package.filename
+            && self.checksum == package.checksum
+            && self.checksum_algorithm == package.checksum_algorithm
+    }
+}
+
+impl Display for LockedPackage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} ({})", self.name, self.version, self.repository)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::debian::{ArchitectureName, ChecksumAlgorithm};
+    use crate::test_support::create_repository_package;
+
+    use super::*;
+
+    #[test]
+    fn test_lockfile() {
+        let distro = Distro {
+            name: "ubuntu".to_string(),
+            version: "24.04".to_string(),
+            codename: "noble".into(),
+            architecture: ArchitectureName::AMD_64,
+        };
+        let package = |name: &str| {
+            create_repository_package()
+                .name(name)
+                .version("1.0")
+                .repository_uri("http://archive.ubuntu.com/ubuntu")
+                .filename(&format!("pool/main/{name}_1.0_amd64.deb"))
+                .checksum(&format!("{name}-sha256"))
+                .call()
+        };
+
+        let lockfile = Lockfile::new(&distro, &[package("libxml2"), package("curl")]);
+        let contents = lockfile.to_toml().unwrap();
+
+        assert_eq!(
+            contents,
+            indoc::indoc! { r#"
+                # This file is generated by the Heroku .deb Packages buildpack. It records the exact packages
+                # that were installed so the same package set can be reproduced by later builds.
+
+                version = 1
+
+                [distro]
+                name = "ubuntu"
+                version = "24.04"
+                codename = "noble"
+                architecture = "amd64"
+
+                [[package]]
+                name = "curl"
+                version = "1.0"
+                repository = "http://archive.ubuntu.com/ubuntu"
+                filename = "pool/main/curl_1.0_amd64.deb"
+                checksum = "curl-sha256"
+                checksum_algorithm = "sha256"
+
+                [[package]]
+                name = "libxml2"
+                version = "1.0"
+                repository = "http://archive.ubuntu.com/ubuntu"
+                filename = "pool/main/libxml2_1.0_amd64.deb"
+                checksum = "libxml2-sha256"
+                checksum_algorithm = "sha256"
+            "# }
+        );
+        assert_eq!(Lockfile::from_toml(&contents).unwrap(), lockfile);
+        assert!(lockfile.packages[0].matches(&package("curl")));
+        assert!(!lockfile.packages[0].matches(&RepositoryPackage {
+            checksum: "other-sha256".to_string(),
+            ..package("curl")
+        }));
+        assert!(!lockfile.packages[0].matches(&RepositoryPackage {
+            checksum_algorithm: ChecksumAlgorithm::Md5,
+            ..package("curl")
+        }));
+    }
+}
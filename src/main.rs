@@ -1,63 +1,139 @@
+use std::collections::HashSet;
 use std::fmt::Debug;
 use std::io::stdout;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
-use bullet_stream::Print;
+use bullet_stream::{style, Print};
+use indexmap::IndexSet;
 use indoc::formatdoc;
 use libcnb::build::{BuildContext, BuildResult, BuildResultBuilder};
+use libcnb::data::build_plan::BuildPlanBuilder;
 use libcnb::detect::{DetectContext, DetectResult, DetectResultBuilder};
-use libcnb::generic::{GenericMetadata, GenericPlatform};
-use libcnb::{buildpack_main, Buildpack, Env};
+use libcnb::generic::GenericPlatform;
+use libcnb::{buildpack_main, Buildpack, Env, Platform};
 use reqwest::Client;
 use reqwest_middleware::ClientBuilder;
-use reqwest_retry::policies::ExponentialBackoff;
 use reqwest_retry::RetryTransientMiddleware;
 
-use crate::config::{BuildpackConfig, ConfigError};
-use crate::create_package_index::{create_package_index, CreatePackageIndexError};
-use crate::debian::{Distro, UnsupportedDistroError};
+use crate::authentication::{AuthenticationMiddleware, Credentials};
+use crate::check_sources::{check_sources, CheckSourcesError};
+use crate::client_certificates::ClientCertificateMiddleware;
+use crate::config::{
+    deprecation_warning, partition_deprecations, redact_credentials, BuildpackConfig, ConfigError,
+    EnvScopes, MaintainerScripts, NetworkSettings, PhasedUpdatesPolicy, ProxySettings,
+    INSTALL_ENV_VAR,
+};
+use crate::create_package_index::{
+    create_foreign_package_indexes, create_package_index, get_source_list, CacheFreshness,
+    CreatePackageIndexError, DistroSourceOptions,
+};
+use crate::debian::{
+    ArchitectureName, BuildpackMetadata, Distro, OptionalSuite, UnsupportedDistroError,
+};
 use crate::determine_packages_to_install::{
-    determine_packages_to_install, DeterminePackagesToInstallError,
+    apply_version_constraints, check_locked_packages, determine_packages_to_install,
+    enforce_lockfile, system_packages_sha256, DeterminePackagesToInstallError,
+    DEFAULT_MAX_DEPENDENCIES,
+};
+use crate::fetch_signing_keys::{fetch_signing_keys, FetchSigningKeysError};
+use crate::http_cache::{http_cache_layer, log_http_cache, HttpCacheMiddleware};
+#[cfg(feature = "http-fixtures")]
+use crate::http_fixtures::HttpFixturesMiddleware;
+use crate::install_packages::{
+    install_packages, InstallOptions, InstallPackagesError, InstallSnapshot,
+};
+use crate::instrumentation::in_phase;
+use crate::mirror_fallback::MirrorFallbackMiddleware;
+use crate::pgp::LoadSigningKeyError;
+use crate::redirects::{log_redirects, RedirectTracker};
+use crate::resolve_ppas::{resolve_ppas, ResolvePpasError};
+use crate::validate_requested_packages::{
+    expand_package_patterns, expand_presets, expand_tasks, foreign_architectures,
+    validate_requested_packages, ValidateRequestedPackagesError,
 };
-use crate::install_packages::{install_packages, InstallPackagesError};
 
 #[cfg(test)]
 use libcnb_test as _;
-#[cfg(test)]
-use regex as _;
 
+mod alternatives;
+mod authentication;
+mod check_sources;
+mod client_certificates;
 mod config;
 mod create_package_index;
 mod debian;
 mod determine_packages_to_install;
+mod download_cache;
 mod errors;
+mod extraction_report;
+mod fetch_signing_keys;
+mod generate_locales;
+mod http_cache;
+#[cfg(feature = "http-fixtures")]
+mod http_fixtures;
+mod imagemagick;
+mod incremental_install;
 mod install_packages;
+mod instrumentation;
+mod java_keystore;
+mod keyserver;
+mod layout_verification;
+mod lockfile;
+mod mirror_fallback;
+mod package_index_cache;
+mod package_integrations;
 mod pgp;
+mod redirects;
+mod resolve_ppas;
+mod sbom;
+mod self_check;
+mod shared_cache;
+#[cfg(test)]
+mod test_support;
+mod timezone;
+mod validate_requested_packages;
 
 buildpack_main!(DebianPackagesBuildpack);
 
+// The build plan entry this buildpack provides so other buildpacks can require it.
+const BUILD_PLAN_NAME: &str = "deb-packages";
+
 type BuildpackResult<T> = Result<T, libcnb::Error<DebianPackagesBuildpackError>>;
 
 struct DebianPackagesBuildpack;
 
 impl Buildpack for DebianPackagesBuildpack {
     type Platform = GenericPlatform;
-    type Metadata = GenericMetadata;
+    type Metadata = BuildpackMetadata;
     type Error = DebianPackagesBuildpackError;
 
     fn detect(&self, context: DetectContext<Self>) -> libcnb::Result<DetectResult, Self::Error> {
-        if BuildpackConfig::exists(context.app_dir.join("project.toml"))? {
-            DetectResultBuilder::pass().build()
+        let build_plan = BuildPlanBuilder::new().provides(BUILD_PLAN_NAME);
+
+        if BuildpackConfig::exists(context.app_dir.join("project.toml"))?
+            || BuildpackConfig::has_install_env(context.platform.env())
+        {
+            DetectResultBuilder::pass()
+                .build_plan(build_plan.requires(BUILD_PLAN_NAME).build())
+                .build()
         } else {
             Print::new(stdout())
                 .without_header()
-                .important("No project.toml file found.")
+                .important(format!(
+                    "No project.toml file found and {INSTALL_ENV_VAR} is not set."
+                ))
                 .done();
-            DetectResultBuilder::fail().build()
+            // only provided so the buildpack still participates when another buildpack in the
+            // group requires it, otherwise the build plan for the group fails to resolve
+            DetectResultBuilder::pass()
+                .build_plan(build_plan.build())
+                .build()
         }
     }
 
+    #[allow(clippy::too_many_lines)]
     fn build(&self, context: BuildContext<Self>) -> libcnb::Result<BuildResult, Self::Error> {
         let mut log = Print::new(stdout()).h1(format!(
             "{buildpack_name} (v{buildpack_version})",
@@ -70,9 +146,40 @@ impl Buildpack for DebianPackagesBuildpack {
             buildpack_version = context.buildpack_descriptor.buildpack.version
         ));
 
-        let config = BuildpackConfig::try_from(context.app_dir.join("project.toml"))?;
+        let mut config =
+            BuildpackConfig::load(context.app_dir.join("project.toml"), context.platform.env())?;
+        config.merge_build_plan(&context.buildpack_plan, BUILD_PLAN_NAME)?;
+        let config_sha256 = BuildpackConfig::sha256(context.app_dir.join("project.toml"))?;
+
+        let (deprecated_options, removed_options) = partition_deprecations(
+            &config.deprecations,
+            &context.buildpack_descriptor.buildpack.version,
+        );
+        if !removed_options.is_empty() {
+            Err(ConfigError::RemovedOptions(
+                context.app_dir.join("project.toml"),
+                removed_options,
+            ))?;
+        }
+        if !deprecated_options.is_empty() {
+            log = log.warning(deprecation_warning(&deprecated_options));
+        }
+        for source in config.sources.iter().filter(|source| source.trusted) {
+            log = log.warning(formatdoc! {"
+                The source {uri} is configured with {trusted} so its Release file won't be \
+                verified with a signing key. Packages from this source can't be checked for \
+                tampering and should only be used from repositories you control.
+            ",
+                uri = style::url(source.uri.as_str()),
+                trusted = style::value("trusted = true"),
+            });
+        }
 
-        if config.install.is_empty() {
+        if config.install.is_empty()
+            && config.install_patterns.is_empty()
+            && config.tasks.is_empty()
+            && config.presets.is_empty()
+        {
             log.important(
                 formatdoc! {"
                     No configured packages to install found in project.toml file. You may need to \
@@ -82,6 +189,9 @@ impl Buildpack for DebianPackagesBuildpack {
                 install = [
                     \"package-name\",
                 ]
+
+                Or set the {INSTALL_ENV_VAR} environment variable to a comma-separated list of \
+                packages to install.
             " }
                 .trim(),
             )
@@ -89,21 +199,75 @@ impl Buildpack for DebianPackagesBuildpack {
             return BuildResultBuilder::new().build();
         }
 
-        let distro = Distro::try_from(&context.target)?;
+        let mut distro = Distro::detect(
+            &context.target,
+            &context.buildpack_descriptor.targets,
+            &context.buildpack_descriptor.metadata.distros,
+        )?;
+
+        // packages are resolved for the release of the run image when it differs from the one the
+        // build image was detected as
+        let build_image_codename = distro.codename.clone();
+        if let Some(target_codename) = &config.target_codename {
+            distro = distro
+                .with_target_codename(
+                    target_codename,
+                    &context.buildpack_descriptor.targets,
+                    &context.buildpack_descriptor.metadata.distros,
+                )
+                .map_err(ConfigError::UnsupportedTargetCodename)?;
+        }
+
+        let signing_keys = context
+            .buildpack_descriptor
+            .metadata
+            .load_signing_keys(&context.buildpack_dir)
+            .map_err(DebianPackagesBuildpackError::LoadSigningKey)?;
+
+        let foreign_architectures = foreign_architectures(
+            &distro,
+            &context.buildpack_descriptor.metadata.distros,
+            &mut config.install,
+            &mut config.install_patterns,
+        )?;
 
         let shared_context = Arc::new(context);
 
-        let client = ClientBuilder::new(
-            Client::builder()
-                .use_rustls_tls()
-                .timeout(Duration::from_secs(60 * 5))
-                .build()
-                .expect("Should be able to construct the HTTP Client"),
+        let proxy_settings =
+            ProxySettings::from_env(shared_context.platform.env(), &Env::from_current());
+
+        let redirect_tracker = Arc::new(RedirectTracker::default());
+
+        let new_http_client_builder = || {
+            proxy_settings.configure(
+                Client::builder()
+                    .use_rustls_tls()
+                    .timeout(config.network.timeout)
+                    .redirect(redirect_tracker.policy()),
+            )
+        };
+
+        let http_client = new_http_client_builder()
+            .map_err(ConfigError::InvalidProxy)?
+            .build()
+            .expect("Should be able to construct the HTTP Client");
+
+        #[cfg(feature = "http-fixtures")]
+        let http_fixtures = HttpFixturesMiddleware::from_env(
+            shared_context.platform.env(),
+            &shared_context.app_dir,
         )
-        .with(RetryTransientMiddleware::new_with_policy(
-            ExponentialBackoff::builder().build_with_max_retries(5),
-        ))
-        .build();
+        .map(Arc::new);
+
+        // recorded or replayed fixtures wrap every other middleware so replayed requests are never
+        // sent and recorded responses are the ones the buildpack sees
+        let new_middleware_client_builder = |http_client: Client| {
+            #[cfg(feature = "http-fixtures")]
+            if let Some(http_fixtures) = &http_fixtures {
+                return ClientBuilder::new(http_client).with_arc(http_fixtures.clone());
+            }
+            ClientBuilder::new(http_client)
+        };
 
         let runtime = tokio::runtime::Builder::new_multi_thread()
             .enable_io()
@@ -119,30 +283,404 @@ impl Buildpack for DebianPackagesBuildpack {
             .sub_bullet(format!("Architecture: {}", &distro.architecture))
             .done();
 
-        let (package_index, log) =
-            runtime.block_on(create_package_index(&shared_context, &client, &distro, log))?;
+        // packages built for another release may not run in the build image so they're only made
+        // available to the run image they were resolved for
+        let launch_only = distro.codename != build_image_codename;
+        if launch_only {
+            log = log
+                .bullet("Target Distribution")
+                .sub_bullet(format!(
+                    "Resolving packages for {codename} set in {target_codename_key} instead of \
+                    the {build_image_codename} release of the build image",
+                    codename = style::value(distro.codename.to_string()),
+                    target_codename_key = style::value("target_codename"),
+                    build_image_codename = style::value(build_image_codename.to_string()),
+                ))
+                .sub_bullet(
+                    "The packages are only available at launch since they may not run in the \
+                    build image",
+                )
+                .done();
+        }
+
+        log = expand_presets(&distro, &config.presets, &mut config.install, log)?;
+
+        // PPAs are resolved first since the sources they expand to are needed to configure the
+        // client used for every other request
+        let keyserver_client = new_middleware_client_builder(http_client.clone())
+            .with(RetryTransientMiddleware::new_with_policy(
+                config.network.retry_policy(),
+            ))
+            .build();
+        let (ppa_sources, new_log) = in_phase("resolve_ppas", || {
+            runtime.block_on(resolve_ppas(&keyserver_client, &distro, &config.ppas, log))
+        })?;
+        log = new_log;
+        config.sources.extend(ppa_sources);
+
+        log = in_phase("fetch_signing_keys", || {
+            runtime.block_on(fetch_signing_keys(
+                &shared_context,
+                &keyserver_client,
+                &mut config.sources,
+                log,
+            ))
+        })?;
+
+        let credentials =
+            Credentials::from_env(shared_context.platform.env(), &Env::from_current());
+
+        let client_certificates = Arc::new(
+            ClientCertificateMiddleware::new(&shared_context.app_dir, &config.sources, || {
+                new_http_client_builder().expect("The proxy settings should already be validated")
+            })
+            .map_err(ConfigError::ClientCertificate)?,
+        );
+
+        let optional_suites = [
+            (config.backports, OptionalSuite::Backports),
+            (config.proposed, OptionalSuite::Proposed),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, optional_suite)| enabled.unwrap_or(false).then_some(optional_suite))
+        .collect::<Vec<_>>();
+        let distro_source_options = DistroSourceOptions {
+            snapshot: config.snapshot.as_ref(),
+            components: &config.components,
+            optional_suites: &optional_suites,
+        };
+
+        let source_list = get_source_list(
+            &distro,
+            &shared_context.buildpack_descriptor.metadata.distros,
+            &signing_keys,
+            &config.sources,
+            distro_source_options,
+        );
+
+        let mirror_fallbacks = Arc::new(MirrorFallbackMiddleware::new(&source_list));
+
+        let client = with_client_certificates(
+            with_authentication(
+                with_mirror_fallbacks(
+                    new_middleware_client_builder(http_client.clone()).with(
+                        RetryTransientMiddleware::new_with_policy(config.network.retry_policy()),
+                    ),
+                    &mirror_fallbacks,
+                ),
+                &credentials,
+            ),
+            &client_certificates,
+        )
+        .build();
+
+        // requests for the package index are answered from the HTTP cache first so fresh responses
+        // are never sent and stale ones are revalidated with the repository
+        let http_cache = Arc::new(HttpCacheMiddleware::new(http_cache_layer(&shared_context)?));
+        let index_client = with_client_certificates(
+            with_authentication(
+                with_mirror_fallbacks(
+                    new_middleware_client_builder(http_client.clone())
+                        .with_arc(http_cache.clone())
+                        .with(RetryTransientMiddleware::new_with_policy(
+                            config.network.retry_policy(),
+                        )),
+                    &mirror_fallbacks,
+                ),
+                &credentials,
+            ),
+            &client_certificates,
+        )
+        .build();
+
+        // requests that check if sources are reachable aren't retried so failures are reported quickly
+        let check_sources_client = with_client_certificates(
+            with_authentication(
+                with_mirror_fallbacks(
+                    new_middleware_client_builder(http_client),
+                    &mirror_fallbacks,
+                ),
+                &credentials,
+            ),
+            &client_certificates,
+        )
+        .build();
+
+        if !proxy_settings.is_empty() {
+            let mut proxy_log = log.bullet("Proxy");
+            for (scheme, proxy) in [
+                ("HTTP", &proxy_settings.http),
+                ("HTTPS", &proxy_settings.https),
+            ] {
+                if let Some(proxy) = proxy {
+                    proxy_log = proxy_log.sub_bullet(format!(
+                        "{scheme}: {}",
+                        style::url(redact_credentials(proxy))
+                    ));
+                }
+            }
+            if let Some(no_proxy) = &proxy_settings.no_proxy {
+                proxy_log = proxy_log.sub_bullet(format!("Excluded hosts: {no_proxy}"));
+            }
+            log = proxy_log.done();
+        }
+
+        if config.network != NetworkSettings::default() {
+            log = log
+                .bullet("Network")
+                .sub_bullet(format!("Timeout: {}s", config.network.timeout.as_secs()))
+                .sub_bullet(format!(
+                    "Retries: {} ({}s to {}s between attempts)",
+                    config.network.max_retries,
+                    config.network.min_retry_interval.as_secs(),
+                    config.network.max_retry_interval.as_secs()
+                ))
+                .done();
+        }
+
+        if let Some(snapshot) = &config.snapshot {
+            log = log
+                .bullet("Snapshot")
+                .sub_bullet(format!(
+                    "Resolving distribution packages as of {}",
+                    style::value(snapshot.as_str())
+                ))
+                .done();
+        }
+
+        #[cfg(feature = "http-fixtures")]
+        if let Some(http_fixtures) = &http_fixtures {
+            log = log
+                .bullet("HTTP fixtures")
+                .sub_bullet(http_fixtures.to_string())
+                .done();
+        }
+
+        if !client_certificates.is_empty() {
+            log = client_certificates
+                .sources()
+                .fold(log.bullet("Client certificates"), |log, uri| {
+                    log.sub_bullet(style::url(uri.as_str()))
+                })
+                .done();
+        }
+
+        let log = in_phase("check_sources", || {
+            runtime.block_on(check_sources(&check_sources_client, &source_list, log))
+        })?;
+
+        let cache_freshness = CacheFreshness {
+            // snapshots are never updated after they're taken so their Release files carry
+            // `Valid-Until` dates that have long since passed
+            check_release_dates: config
+                .check_release_dates
+                .unwrap_or(config.snapshot.is_none()),
+            max_package_index_age: config
+                .package_index_cache_days
+                .map(|days| Duration::from_secs(days.saturating_mul(24 * 60 * 60))),
+        };
+
+        // the package index can be limited to the requested packages and their dependencies which
+        // is faster to build but some packages (e.g.; other providers of a virtual package) may be
+        // missing from it. The packages in a task or matched by a pattern aren't known until every
+        // entry is parsed so the full index is always built when either is configured.
+        let package_subset = (config.subset_package_index.unwrap_or(false)
+            && config.tasks.is_empty()
+            && config.install_patterns.is_empty())
+        .then(|| {
+            config
+                .install
+                .iter()
+                .map(|requested_package| requested_package.name.to_string())
+                .collect::<HashSet<_>>()
+        });
+
+        let (mut package_index, indexed_sources, log) = in_phase("create_package_index", || {
+            runtime.block_on(create_package_index(
+                &shared_context,
+                &index_client,
+                &distro,
+                &config.sources,
+                &signing_keys,
+                distro_source_options,
+                package_subset,
+                cache_freshness,
+                log,
+            ))
+        })?;
+
+        let (mut foreign_package_indexes, log) =
+            in_phase("create_foreign_package_indexes", || {
+                runtime.block_on(create_foreign_package_indexes(
+                    &shared_context,
+                    &index_client,
+                    &distro,
+                    &foreign_architectures,
+                    &signing_keys,
+                    distro_source_options,
+                    cache_freshness,
+                    log,
+                ))
+            })?;
+
+        let log = log_redirects(&redirect_tracker, log);
+
+        for foreign_package_index in &mut foreign_package_indexes {
+            foreign_package_index
+                .package_index
+                .set_source_priority(config.source_priority.clone());
+            foreign_package_index
+                .package_index
+                .set_skip_phased_updates(config.phased_updates == PhasedUpdatesPolicy::Skip);
+        }
+        package_index.set_source_priority(config.source_priority);
+        package_index.set_skip_phased_updates(config.phased_updates == PhasedUpdatesPolicy::Skip);
+
+        let log = in_phase("expand_package_patterns", || {
+            expand_package_patterns(
+                &package_index,
+                &foreign_package_indexes,
+                &config.install_patterns,
+                &mut config.install,
+                log,
+            )
+        })?;
+
+        let log = in_phase("apply_version_constraints", || {
+            apply_version_constraints(
+                &mut package_index,
+                &mut foreign_package_indexes,
+                &mut config.install,
+                log,
+            )
+        })?;
+
+        let (lockfile, log) = in_phase("enforce_lockfile", || {
+            enforce_lockfile(
+                &shared_context.app_dir,
+                shared_context.platform.env(),
+                &distro,
+                &mut package_index,
+                &mut foreign_package_indexes,
+                log,
+            )
+        })?;
+
+        let log = in_phase("expand_tasks", || {
+            expand_tasks(&package_index, &config.tasks, &mut config.install, log)
+        })?;
+
+        let log = in_phase("validate_requested_packages", || {
+            runtime.block_on(validate_requested_packages(
+                &shared_context,
+                &index_client,
+                &distro,
+                &package_index,
+                &config.install,
+                &signing_keys,
+                distro_source_options,
+                cache_freshness,
+                log,
+            ))
+        })?;
+
+        // the package index is complete once the requested packages are validated so responses
+        // that weren't requested by now are no longer needed
+        let log = runtime.block_on(log_http_cache(&http_cache, log));
+
+        // packages matched by a pattern are only requested once the package index is built
+        let env_scopes = EnvScopes::new(config.env_scope, &config.install);
+        let maintainer_scripts = MaintainerScripts::new(&config.install);
+
+        // packages from suites like backports are only chosen over the release suite for the
+        // packages that were requested, not for their dependencies
+        let requested_names = |architecture: Option<&ArchitectureName>| {
+            config
+                .install
+                .iter()
+                .filter(|requested_package| requested_package.architecture.as_ref() == architecture)
+                .map(|requested_package| requested_package.name.to_string())
+                .collect()
+        };
+        package_index.set_requested_packages(requested_names(None));
+        for foreign_package_index in &mut foreign_package_indexes {
+            let foreign_requested_names =
+                requested_names(Some(&foreign_package_index.architecture));
+            foreign_package_index
+                .package_index
+                .set_requested_packages(foreign_requested_names);
+        }
+
+        let (packages_to_install, skipped_packages, dependency_paths, log) =
+            in_phase("determine_packages_to_install", || {
+                let determined_packages = determine_packages_to_install(
+                    &package_index,
+                    &distro.architecture,
+                    &foreign_package_indexes,
+                    config.install,
+                    &config.exclude,
+                    config.max_dependencies.unwrap_or(DEFAULT_MAX_DEPENDENCIES),
+                    config.resolve_virtual_packages.unwrap_or(true),
+                    shared_context.platform.env(),
+                    log,
+                )?;
+                if let Some(lockfile) = &lockfile {
+                    check_locked_packages(lockfile, &determined_packages.0)?;
+                }
+                Ok(determined_packages)
+            })?;
 
-        let (packages_to_install, skipped_packages, log) =
-            determine_packages_to_install(&package_index, config.install, log)?;
-        
         for package in &packages_to_install {
             if let Some(provides) = &package.provides {
                 println!("Package {} provides: {}", package.name, provides);
             } else {
-                println!("Package {} does not provide any additional packages", package.name);
+                println!(
+                    "Package {} does not provide any additional packages",
+                    package.name
+                );
             }
-        }        
+        }
 
-        let log = runtime.block_on(install_packages(
-            &shared_context,
-            &client,
-            &distro,
-            packages_to_install,
-            skipped_packages, 
-            log,
-        ))?;
+        let install_options = InstallOptions {
+            foreign_architectures,
+            env_scopes,
+            launch_only,
+            maintainer_scripts,
+            strict_extraction: config.strict_extraction.unwrap_or(false),
+            existing_files_policy: config.existing_files,
+            verify_layout: config.verify_layout.unwrap_or(false),
+            self_check: config.self_check.unwrap_or(false),
+            locales: config.locales,
+            imagemagick_policy: config.imagemagick_policy,
+            remove: config.remove,
+            locked: lockfile.is_some(),
+            provenance: config.provenance.unwrap_or(false),
+            system_packages_sha256: system_packages_sha256(shared_context.platform.env()),
+            snapshot: InstallSnapshot {
+                buildpack_version: shared_context
+                    .buildpack_descriptor
+                    .buildpack
+                    .version
+                    .to_string(),
+                config_sha256,
+                sources: indexed_sources,
+                dependency_paths,
+            },
+        };
+
+        let log = in_phase("install_packages", || {
+            runtime.block_on(install_packages(
+                &shared_context,
+                &client,
+                &distro,
+                packages_to_install,
+                skipped_packages,
+                install_options,
+                log,
+            ))
+        })?;
 
-        log.done();
+        log_redirects(&redirect_tracker, log).done();
 
         BuildResultBuilder::new().build()
     }
@@ -156,7 +694,12 @@ impl Buildpack for DebianPackagesBuildpack {
 pub(crate) enum DebianPackagesBuildpackError {
     Config(ConfigError),
     UnsupportedDistro(UnsupportedDistroError),
+    LoadSigningKey(LoadSigningKeyError),
+    ResolvePpas(ResolvePpasError),
+    FetchSigningKeys(FetchSigningKeysError),
+    CheckSources(CheckSourcesError),
     CreatePackageIndex(CreatePackageIndexError),
+    ValidateRequestedPackages(ValidateRequestedPackagesError),
     DeterminePackagesToInstall(DeterminePackagesToInstallError),
     InstallPackages(InstallPackagesError),
 }
@@ -167,15 +710,87 @@ impl From<DebianPackagesBuildpackError> for libcnb::Error<DebianPackagesBuildpac
     }
 }
 
-pub(crate) fn is_buildpack_debug_logging_enabled() -> bool {
-    Env::from_current()
-        .get("BP_LOG_LEVEL")
+fn with_authentication(client_builder: ClientBuilder, credentials: &Credentials) -> ClientBuilder {
+    if credentials.is_empty() {
+        client_builder
+    } else {
+        client_builder.with(AuthenticationMiddleware::new(credentials.clone()))
+    }
+}
+
+// Registered before authentication so credentials are added for the host of the mirror a request
+// is retried against.
+fn with_mirror_fallbacks(
+    client_builder: ClientBuilder,
+    mirror_fallbacks: &Arc<MirrorFallbackMiddleware>,
+) -> ClientBuilder {
+    if mirror_fallbacks.is_empty() {
+        client_builder
+    } else {
+        client_builder.with_arc(mirror_fallbacks.clone())
+    }
+}
+
+// Registered after every other middleware since requests for sources with a client certificate
+// are sent by this middleware instead of being passed along.
+fn with_client_certificates(
+    client_builder: ClientBuilder,
+    client_certificates: &Arc<ClientCertificateMiddleware>,
+) -> ClientBuilder {
+    if client_certificates.is_empty() {
+        client_builder
+    } else {
+        client_builder.with_arc(client_certificates.clone())
+    }
+}
+
+pub(crate) fn is_buildpack_debug_logging_enabled(env: &Env) -> bool {
+    env.get("BP_LOG_LEVEL")
         .is_some_and(|value| value.to_ascii_lowercase() == "debug")
 }
 
-pub(crate) fn get_package_cache_days() -> u64 {
-    Env::from_current()
-        .get("PACKAGE_CACHE_DAYS")
+pub(crate) fn get_package_cache_days(env: &Env) -> u64 {
+    env.get("PACKAGE_CACHE_DAYS")
         .and_then(|value| value.to_str().and_then(|s| s.parse::<u64>().ok()))
         .unwrap_or(7)
-}
\ No newline at end of file
+}
+
+// A directory shared by the builds of different applications (e.g.; a mounted volume) to export
+// installed packages to and import them from.
+pub(crate) fn get_shared_cache_dir(env: &Env) -> Option<PathBuf> {
+    env.get("BP_DEB_PACKAGES_SHARED_CACHE")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+pub(crate) fn get_reinstall_packages(env: &Env) -> IndexSet<String> {
+    env.get("BP_DEB_PACKAGES_REINSTALL")
+        .and_then(|value| value.to_str().map(parse_package_list))
+        .unwrap_or_default()
+}
+
+fn parse_package_list(value: &str) -> IndexSet<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_package_list() {
+        assert_eq!(
+            parse_package_list(" curl,git , ,libvips42,"),
+            IndexSet::from([
+                "curl".to_string(),
+                "git".to_string(),
+                "libvips42".to_string()
+            ])
+        );
+        assert!(parse_package_list("").is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+use async_trait::async_trait;
+use http::Extensions;
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{Middleware, Next};
+
+use crate::debian::{RepositoryUri, Source};
+
+// Retries requests for a source that has mirrors configured against each mirror in order when the
+// request to the source's URI fails (e.g.; a Release file, package index, or package that can't be
+// downloaded). The remaining middleware, including authentication, is applied to each request so
+// credentials for the source aren't sent to its mirrors. If every mirror also fails, the result
+// from the source's URI is returned so errors still refer to the configured source.
+pub(crate) struct MirrorFallbackMiddleware {
+    sources: Vec<(RepositoryUri, Vec<RepositoryUri>)>,
+}
+
+impl MirrorFallbackMiddleware {
+    pub(crate) fn new(sources: &[Source]) -> Self {
+        let mut mirrors: Vec<(RepositoryUri, Vec<RepositoryUri>)> = vec![];
+        for source in sources.iter().filter(|source| !source.mirrors.is_empty()) {
+            if !mirrors.iter().any(|(uri, _)| uri == &source.uri) {
+                mirrors.push((source.uri.clone(), source.mirrors.clone()));
+            }
+        }
+        Self { sources: mirrors }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    // The URLs to try after the given URL fails, in order.
+    fn mirror_urls_for(&self, url: &Url) -> Vec<Url> {
+        self.sources
+            .iter()
+            .find_map(|(uri, mirrors)| {
+                url.as_str()
+                    .strip_prefix(uri.as_str())
+                    .filter(|path| path.is_empty() || path.starts_with('/'))
+                    .map(|path| {
+                        mirrors
+                            .iter()
+                            .filter_map(|mirror| {
+                                Url::parse(&format!("{}{path}", mirror.as_str())).ok()
+                            })
+                            .collect()
+                    })
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl Middleware for MirrorFallbackMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let mirror_requests = self
+            .mirror_urls_for(req.url())
+            .into_iter()
+            .filter_map(|url| {
+                let mut mirror_request = req.try_clone()?;
+                *mirror_request.url_mut() = url;
+                Some(mirror_request)
+            })
+            .collect::<Vec<_>>();
+
+        let result = next.clone().run(req, extensions).await;
+        if !is_failed_request(&result) {
+            return result;
+        }
+
+        for mirror_request in mirror_requests {
+            let mirror_result = next.clone().run(mirror_request, extensions).await;
+            if !is_failed_request(&mirror_result) {
+                return mirror_result;
+            }
+        }
+
+        result
+    }
+}
+
+fn is_failed_request(result: &reqwest_middleware::Result<Response>) -> bool {
+    result.as_ref().map_or(true, |response| {
+        response.status().is_client_error() || response.status().is_server_error()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::debian::ArchitectureName;
+
+    use super::*;
+
+    fn source(uri: &str, mirrors: &[&str]) -> Source {
+        Source {
+            mirrors: mirrors
+                .iter()
+                .map(|mirror| RepositoryUri::from(*mirror))
+                .collect(),
+            ..Source::new(uri, ["noble"], ["main"], "", ArchitectureName::AMD_64)
+        }
+    }
+
+    #[test]
+    fn test_mirror_urls_for() {
+        let middleware = MirrorFallbackMiddleware::new(&[
+            source(
+                "https://repo.example.com/ubuntu",
+                &[
+                    "https://mirror-1.example.com/ubuntu",
+                    "https://mirror-2.example.com/ubuntu",
+                ],
+            ),
+            source("http://archive.ubuntu.com/ubuntu", &[]),
+        ]);
+        assert!(!middleware.is_empty());
+        assert_eq!(
+            middleware
+                .mirror_urls_for(
+                    &Url::from_str("https://repo.example.com/ubuntu/dists/noble/InRelease")
+                        .unwrap()
+                )
+                .iter()
+                .map(Url::as_str)
+                .collect::<Vec<_>>(),
+            vec![
+                "https://mirror-1.example.com/ubuntu/dists/noble/InRelease",
+                "https://mirror-2.example.com/ubuntu/dists/noble/InRelease"
+            ]
+        );
+        assert!(middleware
+            .mirror_urls_for(
+                &Url::from_str("https://repo.example.com/ubuntu-ports/dists/noble/InRelease")
+                    .unwrap()
+            )
+            .is_empty());
+        assert!(middleware
+            .mirror_urls_for(
+                &Url::from_str("http://archive.ubuntu.com/ubuntu/dists/noble/InRelease").unwrap()
+            )
+            .is_empty());
+    }
+
+    #[test]
+    fn test_new_without_mirrors() {
+        let middleware =
+            MirrorFallbackMiddleware::new(&[source("http://archive.ubuntu.com/ubuntu", &[])]);
+        assert!(middleware.is_empty());
+    }
+}
@@ -0,0 +1,135 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::fs::{read, write};
+
+use crate::debian::PackageIndex;
+
+// Parsing every package index is the slowest part of creating the package index so the parsed
+// result is serialized into a cache-only layer. It's reused as long as the package indexes it was
+// built from (identified by their url and the hash listed in the release file) are the same and in
+// the same order since the order decides which of any duplicate entries is kept.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub(crate) struct PackageIndexCacheMetadata {
+    pub(crate) format_version: u32,
+    pub(crate) package_indexes: Vec<CachedPackageIndex>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub(crate) struct CachedPackageIndex {
+    pub(crate) url: String,
+    pub(crate) hash: String,
+    // entries are parsed differently when weak hashes are allowed
+    #[serde(default)]
+    pub(crate) allow_weak_hashes: bool,
+    // every parsed entry is given the download url of its source
+    #[serde(default)]
+    pub(crate) download_url: Option<String>,
+    // every parsed entry is marked when the Release file sets `NotAutomatic`
+    #[serde(default)]
+    pub(crate) not_automatic: bool,
+    // every parsed entry records the suite it was read from
+    #[serde(default)]
+    pub(crate) suite: String,
+}
+
+// Changed whenever the serialized fields of `PackageIndex` or `RepositoryPackage` change since the
+// binary format isn't self-describing.
+pub(crate) const PACKAGE_INDEX_CACHE_FORMAT_VERSION: u32 = 8;
+
+const PACKAGE_INDEX_CACHE_FILE: &str = "package_index.bin";
+
+pub(crate) fn package_index_cache_path(package_index_cache_dir: &Path) -> PathBuf {
+    package_index_cache_dir.join(PACKAGE_INDEX_CACHE_FILE)
+}
+
+// Returns `None` when the cache is missing or can't be deserialized (e.g.; it was written by a build
+// that failed partway through) so the package indexes are parsed again instead.
+pub(crate) async fn read_package_index_cache(
+    package_index_cache_dir: &Path,
+) -> Option<PackageIndex> {
+    let contents = read(package_index_cache_path(package_index_cache_dir))
+        .await
+        .ok()?;
+    bincode::deserialize(&contents).ok()
+}
+
+pub(crate) async fn write_package_index_cache(
+    package_index_cache_dir: &Path,
+    package_index: &PackageIndex,
+) -> std::io::Result<()> {
+    let contents = bincode::serialize(package_index)
+        .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))?;
+    write(package_index_cache_path(package_index_cache_dir), contents).await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::debian::RepositoryPackage;
+    use crate::test_support::create_repository_package;
+
+    use super::*;
+
+    fn package(name: &str, provides: Option<&str>) -> RepositoryPackage {
+        RepositoryPackage {
+            depends: Some("libc6 (>= 2.34)".to_string()),
+            provides: provides.map(ToString::to_string),
+            ..create_repository_package()
+                .name(name)
+                .version("1.0")
+                .repository_uri("http://archive.ubuntu.com/ubuntu")
+                .filename(&format!("pool/main/{name}.deb"))
+                .checksum("abc123")
+                .size(1024)
+                .call()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_and_read_package_index_cache() {
+        let package_index_cache_dir = tempfile::tempdir().unwrap();
+        let mut package_index = PackageIndex::default();
+        package_index.add_package(package("libgl1-mesa-dri", Some("libgl1")));
+        package_index.add_package(package("curl", None));
+        package_index.add_package(package("curl", None));
+
+        write_package_index_cache(package_index_cache_dir.path(), &package_index)
+            .await
+            .unwrap();
+        let cached_package_index = read_package_index_cache(package_index_cache_dir.path())
+            .await
+            .unwrap();
+
+        assert_eq!(cached_package_index.packages_indexed, 2);
+        assert_eq!(cached_package_index.duplicate_packages.len(), 1);
+        assert_eq!(
+            cached_package_index.get_highest_available_version("curl"),
+            package_index.get_highest_available_version("curl")
+        );
+        assert_eq!(
+            cached_package_index
+                .get_providers("libgl1")
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec!["libgl1-mesa-dri"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_invalid_package_index_cache() {
+        let package_index_cache_dir = tempfile::tempdir().unwrap();
+        assert!(read_package_index_cache(package_index_cache_dir.path())
+            .await
+            .is_none());
+
+        std::fs::write(
+            package_index_cache_path(package_index_cache_dir.path()),
+            "not a package index",
+        )
+        .unwrap();
+        assert!(read_package_index_cache(package_index_cache_dir.path())
+            .await
+            .is_none());
+    }
+}
@@ -0,0 +1,84 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bullet_stream::style;
+
+use crate::package_integrations::{PackageIntegration, PackageIntegrationError, PostInstall};
+
+// fontconfig-config installs `fonts.conf` which only lists the system font directories (e.g.;
+// `/usr/share/fonts`). `FONTCONFIG_PATH` points fontconfig at the copy in the layer and a config
+// file added to its `conf.d` directory includes the fonts installed into the layer.
+pub(crate) struct Fontconfig;
+
+const FONTCONFIG_PACKAGE: &str = "fontconfig-config";
+
+const LAYER_FONTS_CONF: &str = "99-deb-packages.conf";
+
+impl PackageIntegration for Fontconfig {
+    fn name(&self) -> &'static str {
+        "fontconfig"
+    }
+
+    fn applies(&self, package_name: &str) -> bool {
+        package_name == FONTCONFIG_PACKAGE
+    }
+
+    fn env_vars(&self, install_path: &Path) -> Vec<(&'static str, PathBuf)> {
+        vec![("FONTCONFIG_PATH", install_path.join("etc/fonts"))]
+    }
+
+    fn post_install(&self, install_path: &Path) -> Result<PostInstall, PackageIntegrationError> {
+        let fonts_dir = install_path.join("usr/share/fonts");
+        let conf_file = install_path.join("etc/fonts/conf.d").join(LAYER_FONTS_CONF);
+        if let Some(parent) = conf_file.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| PackageIntegrationError::WriteFontconfig(conf_file.clone(), e))?;
+        }
+        fs::write(
+            &conf_file,
+            format!(
+                concat!(
+                    "<?xml version=\"1.0\"?>\n",
+                    "<!DOCTYPE fontconfig SYSTEM \"urn:fontconfig:fonts.dtd\">\n",
+                    "<!-- Generated by the Heroku .deb Packages buildpack -->\n",
+                    "<fontconfig>\n",
+                    "  <dir>{}</dir>\n",
+                    "</fontconfig>\n"
+                ),
+                fonts_dir.to_string_lossy()
+            ),
+        )
+        .map_err(|e| PackageIntegrationError::WriteFontconfig(conf_file.clone(), e))?;
+
+        Ok(PostInstall {
+            messages: vec![format!(
+                "Added {fonts_dir} to the font directories",
+                fonts_dir = style::value(fonts_dir.to_string_lossy())
+            )],
+            ..PostInstall::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_post_install() {
+        let install_dir = tempfile::tempdir().unwrap();
+        Fontconfig.post_install(install_dir.path()).unwrap();
+
+        let contents = fs::read_to_string(
+            install_dir
+                .path()
+                .join("etc/fonts/conf.d")
+                .join(LAYER_FONTS_CONF),
+        )
+        .unwrap();
+        assert!(contents.contains(&format!(
+            "<dir>{}/usr/share/fonts</dir>",
+            install_dir.path().display()
+        )));
+    }
+}
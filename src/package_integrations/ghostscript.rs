@@ -0,0 +1,20 @@
+use std::path::{Path, PathBuf};
+
+use crate::package_integrations::PackageIntegration;
+
+// Ghostscript searches the compiled in library path for its fonts and resource files.
+pub(crate) struct Ghostscript;
+
+impl PackageIntegration for Ghostscript {
+    fn name(&self) -> &'static str {
+        "Ghostscript"
+    }
+
+    fn applies(&self, package_name: &str) -> bool {
+        package_name == "ghostscript"
+    }
+
+    fn env_vars(&self, install_path: &Path) -> Vec<(&'static str, PathBuf)> {
+        vec![("GS_LIB", install_path.join("var/lib/ghostscript"))]
+    }
+}
@@ -0,0 +1,27 @@
+use std::path::{Path, PathBuf};
+
+use crate::package_integrations::PackageIntegration;
+
+// git looks for its helper programs (e.g.; `git-remote-https`) and repository templates at the
+// paths it was compiled with.
+pub(crate) struct Git;
+
+impl PackageIntegration for Git {
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    fn applies(&self, package_name: &str) -> bool {
+        package_name == "git"
+    }
+
+    fn env_vars(&self, install_path: &Path) -> Vec<(&'static str, PathBuf)> {
+        vec![
+            ("GIT_EXEC_PATH", install_path.join("usr/lib/git-core")),
+            (
+                "GIT_TEMPLATE_DIR",
+                install_path.join("usr/share/git-core/templates"),
+            ),
+        ]
+    }
+}
@@ -0,0 +1,56 @@
+use std::path::{Path, PathBuf};
+
+use bullet_stream::style;
+
+use crate::imagemagick::{apply_policy, find_config_dirs, magick_configure_path};
+use crate::package_integrations::{PackageIntegration, PackageIntegrationError, PostInstall};
+
+// Points `MAGICK_CONFIGURE_PATH` at the configuration installed into the layer by the
+// `imagemagick-*-common` package and applies the `imagemagick_policy` from project.toml. It's
+// required when a policy is configured so a missing ImageMagick installation is reported.
+pub(crate) struct ImageMagick {
+    pub(crate) policy_file: Option<PathBuf>,
+}
+
+impl PackageIntegration for ImageMagick {
+    fn name(&self) -> &'static str {
+        "ImageMagick"
+    }
+
+    fn applies(&self, package_name: &str) -> bool {
+        package_name.starts_with("imagemagick-") && package_name.ends_with("-common")
+    }
+
+    fn is_required(&self) -> bool {
+        self.policy_file.is_some()
+    }
+
+    fn post_install(&self, install_path: &Path) -> Result<PostInstall, PackageIntegrationError> {
+        let config_dirs = find_config_dirs(install_path);
+        if let Some(policy_file) = &self.policy_file {
+            apply_policy(&config_dirs, policy_file)
+                .map_err(PackageIntegrationError::ImageMagick)?;
+        }
+        if config_dirs.is_empty() {
+            return Ok(PostInstall::default());
+        }
+
+        let mut messages = vec![format!(
+            "Setting {magick_configure_path} to {config_dirs}",
+            magick_configure_path = style::value("MAGICK_CONFIGURE_PATH"),
+            config_dirs = style::value(magick_configure_path(&config_dirs))
+        )];
+        if let Some(policy_file) = &self.policy_file {
+            messages.push(format!(
+                "Applied policy from {policy_file}",
+                policy_file = style::value(policy_file.to_string_lossy())
+            ));
+        }
+
+        Ok(PostInstall {
+            messages,
+            env_vars: vec![("MAGICK_CONFIGURE_PATH", magick_configure_path(&config_dirs))],
+            ..PostInstall::default()
+        })
+    }
+}
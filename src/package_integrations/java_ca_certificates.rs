@@ -0,0 +1,41 @@
+use std::path::Path;
+
+use bullet_stream::style;
+
+use crate::java_keystore::{generate_java_keystore, keystore_path, JAVA_CA_CERTIFICATES_PACKAGE};
+use crate::package_integrations::{PackageIntegration, PackageIntegrationError, PostInstall};
+
+const SYSTEM_CA_BUNDLE: &str = "/etc/ssl/certs/ca-certificates.crt";
+
+pub(crate) struct JavaCaCertificates;
+
+impl PackageIntegration for JavaCaCertificates {
+    fn name(&self) -> &'static str {
+        "Java keystore"
+    }
+
+    fn applies(&self, package_name: &str) -> bool {
+        package_name == JAVA_CA_CERTIFICATES_PACKAGE
+    }
+
+    fn post_install(&self, install_path: &Path) -> Result<PostInstall, PackageIntegrationError> {
+        let certificate_count = generate_java_keystore(install_path, Path::new(SYSTEM_CA_BUNDLE))
+            .map_err(PackageIntegrationError::JavaKeystore)?;
+        let keystore_path = keystore_path(install_path);
+
+        Ok(PostInstall {
+            messages: vec![format!(
+                "Added {certificate_count} certificates to {keystore_path}",
+                keystore_path = style::value(keystore_path.to_string_lossy())
+            )],
+            options: vec![(
+                "JAVA_TOOL_OPTIONS",
+                format!(
+                    "-Djavax.net.ssl.trustStore={}",
+                    keystore_path.to_string_lossy()
+                ),
+            )],
+            env_vars: vec![],
+        })
+    }
+}
@@ -0,0 +1,48 @@
+use std::path::Path;
+
+use bullet_stream::style;
+
+use crate::config::Locale;
+use crate::generate_locales::{generate_locales, locale_path};
+use crate::package_integrations::{PackageIntegration, PackageIntegrationError, PostInstall};
+
+// Compiles the `locales` from project.toml into the layer. The locale definitions can come from
+// the base image as well so this runs whether or not the locales package was installed.
+pub(crate) struct Locales(pub(crate) Vec<Locale>);
+
+impl PackageIntegration for Locales {
+    fn name(&self) -> &'static str {
+        "locales"
+    }
+
+    fn applies(&self, package_name: &str) -> bool {
+        package_name == "locales"
+    }
+
+    fn is_required(&self) -> bool {
+        true
+    }
+
+    fn post_install(&self, install_path: &Path) -> Result<PostInstall, PackageIntegrationError> {
+        let generated_locales = generate_locales(install_path, &self.0)
+            .map_err(PackageIntegrationError::GenerateLocales)?;
+
+        Ok(PostInstall {
+            messages: generated_locales
+                .iter()
+                .map(|(locale, generated)| {
+                    if *generated {
+                        format!("Generated {}", style::value(locale.to_string()))
+                    } else {
+                        format!("Reusing {}", style::value(locale.to_string()))
+                    }
+                })
+                .collect(),
+            env_vars: vec![(
+                "LOCPATH",
+                locale_path(install_path).to_string_lossy().to_string(),
+            )],
+            ..PostInstall::default()
+        })
+    }
+}
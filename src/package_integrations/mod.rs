@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::config::Locale;
+use crate::generate_locales::GenerateLocalesError;
+use crate::imagemagick::ImageMagickError;
+use crate::java_keystore::JavaKeystoreError;
+
+pub(crate) use fontconfig::*;
+pub(crate) use ghostscript::*;
+pub(crate) use git::*;
+pub(crate) use imagemagick::*;
+pub(crate) use java_ca_certificates::*;
+pub(crate) use locales::*;
+pub(crate) use tzdata::*;
+
+mod fontconfig;
+mod ghostscript;
+mod git;
+mod imagemagick;
+mod java_ca_certificates;
+mod locales;
+mod tzdata;
+
+// Packages are extracted into the layer without running their maintainer scripts so some of them
+// need extra setup (e.g.; environment variables pointing at their files or generated data) to work
+// from there. Each integration handles one of these.
+pub(crate) trait PackageIntegration {
+    // Used in the build log (e.g.; `Configuring Java keystore`).
+    fn name(&self) -> &'static str;
+
+    fn applies(&self, package_name: &str) -> bool;
+
+    // Integrations configured in project.toml (e.g.; the `locales` to generate) run even when none
+    // of the packages they apply to were installed since those may be on the base image.
+    fn is_required(&self) -> bool {
+        false
+    }
+
+    // Paths exported for the package. These are exported for requested packages that were skipped
+    // as well so they're limited to values that can be derived from the layer path.
+    fn env_vars(&self, _install_path: &Path) -> Vec<(&'static str, PathBuf)> {
+        vec![]
+    }
+
+    fn post_install(&self, _install_path: &Path) -> Result<PostInstall, PackageIntegrationError> {
+        Ok(PostInstall::default())
+    }
+}
+
+#[derive(Debug, Default, Eq, PartialEq)]
+pub(crate) struct PostInstall {
+    pub(crate) messages: Vec<String>,
+    // appended to the variable separated by a space (e.g.; JVM flags in `JAVA_TOOL_OPTIONS`)
+    pub(crate) options: Vec<(&'static str, String)>,
+    // set to the value, replacing any value from the base image (e.g.; `TZDIR`)
+    pub(crate) env_vars: Vec<(&'static str, String)>,
+}
+
+// The settings from project.toml and the platform that some of the integrations depend on.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct IntegrationSettings {
+    pub(crate) locales: Vec<Locale>,
+    pub(crate) timezone: Option<String>,
+    pub(crate) imagemagick_policy: Option<PathBuf>,
+}
+
+pub(crate) fn package_integrations(
+    settings: &IntegrationSettings,
+) -> Vec<Box<dyn PackageIntegration>> {
+    let mut integrations: Vec<Box<dyn PackageIntegration>> = vec![
+        Box::new(Git),
+        Box::new(Ghostscript),
+        Box::new(Fontconfig),
+        Box::new(JavaCaCertificates),
+        Box::new(Tzdata {
+            timezone: settings.timezone.clone(),
+        }),
+        Box::new(ImageMagick {
+            policy_file: settings.imagemagick_policy.clone(),
+        }),
+    ];
+    if !settings.locales.is_empty() {
+        integrations.push(Box::new(Locales(settings.locales.clone())));
+    }
+    integrations
+}
+
+// Keyed by package name and then variable name.
+pub(crate) fn package_env_vars<'a>(
+    integrations: &[Box<dyn PackageIntegration>],
+    install_path: &Path,
+    package_names: impl IntoIterator<Item = &'a str>,
+) -> HashMap<String, HashMap<String, String>> {
+    let mut env_vars: HashMap<String, HashMap<String, String>> = HashMap::new();
+    for package_name in package_names {
+        for integration in integrations {
+            if integration.applies(package_name) {
+                env_vars
+                    .entry(package_name.to_string())
+                    .or_default()
+                    .extend(
+                        integration
+                            .env_vars(install_path)
+                            .into_iter()
+                            .map(|(name, value)| {
+                                (name.to_string(), value.to_string_lossy().to_string())
+                            }),
+                    );
+            }
+        }
+    }
+    env_vars
+}
+
+// Each integration runs at most once, for the first installed package it applies to. Required
+// integrations run without a package when none of the ones they apply to were installed.
+pub(crate) fn run_post_install<'a, 'b>(
+    integrations: &'a [Box<dyn PackageIntegration>],
+    install_path: &Path,
+    package_names: &[&'b str],
+) -> Result<Vec<PostInstallResult<'a, 'b>>, PackageIntegrationError> {
+    let mut results = vec![];
+    for integration in integrations {
+        let package_name = package_names
+            .iter()
+            .find(|package_name| integration.applies(package_name))
+            .copied();
+        if package_name.is_some() || integration.is_required() {
+            let post_install = integration.post_install(install_path)?;
+            results.push((integration.as_ref(), package_name, post_install));
+        }
+    }
+    Ok(results)
+}
+
+pub(crate) type PostInstallResult<'a, 'b> =
+    (&'a dyn PackageIntegration, Option<&'b str>, PostInstall);
+
+#[derive(Debug)]
+pub(crate) enum PackageIntegrationError {
+    JavaKeystore(JavaKeystoreError),
+    WriteFontconfig(PathBuf, std::io::Error),
+    GenerateLocales(GenerateLocalesError),
+    UnknownTimezone(String),
+    ImageMagick(ImageMagickError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_env_vars() {
+        let install_path = Path::new("/layers/packages");
+        let env_vars = package_env_vars(
+            &package_integrations(&IntegrationSettings::default()),
+            install_path,
+            ["git", "ghostscript", "curl"],
+        );
+
+        assert_eq!(
+            env_vars,
+            HashMap::from([
+                (
+                    "git".to_string(),
+                    HashMap::from([
+                        (
+                            "GIT_EXEC_PATH".to_string(),
+                            "/layers/packages/usr/lib/git-core".to_string()
+                        ),
+                        (
+                            "GIT_TEMPLATE_DIR".to_string(),
+                            "/layers/packages/usr/share/git-core/templates".to_string()
+                        ),
+                    ])
+                ),
+                (
+                    "ghostscript".to_string(),
+                    HashMap::from([(
+                        "GS_LIB".to_string(),
+                        "/layers/packages/var/lib/ghostscript".to_string()
+                    )])
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_run_post_install() {
+        struct Counting(std::cell::Cell<usize>);
+
+        impl PackageIntegration for Counting {
+            fn name(&self) -> &'static str {
+                "counting"
+            }
+
+            fn applies(&self, package_name: &str) -> bool {
+                package_name.starts_with("lib")
+            }
+
+            fn post_install(
+                &self,
+                install_path: &Path,
+            ) -> Result<PostInstall, PackageIntegrationError> {
+                self.0.set(self.0.get() + 1);
+                Ok(PostInstall {
+                    messages: vec![install_path.display().to_string()],
+                    ..PostInstall::default()
+                })
+            }
+        }
+
+        let integrations: Vec<Box<dyn PackageIntegration>> =
+            vec![Box::new(Counting(std::cell::Cell::new(0))), Box::new(Git)];
+        let install_path = Path::new("/layers/packages");
+
+        let results = run_post_install(
+            &integrations,
+            install_path,
+            &["curl", "libcurl4", "libssl3"],
+        )
+        .unwrap();
+        assert_eq!(results.len(), 1);
+        let (integration, package_name, post_install) = &results[0];
+        assert_eq!(integration.name(), "counting");
+        assert_eq!(*package_name, Some("libcurl4"));
+        assert_eq!(post_install.messages, vec!["/layers/packages".to_string()]);
+
+        assert!(run_post_install(&integrations, install_path, &["curl"])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_run_post_install_required_integration() {
+        let install_dir = tempfile::tempdir().unwrap();
+        let integrations = package_integrations(&IntegrationSettings {
+            imagemagick_policy: Some(install_dir.path().join("policy.xml")),
+            ..IntegrationSettings::default()
+        });
+
+        // the policy can't be applied without an ImageMagick installation
+        assert!(matches!(
+            run_post_install(&integrations, install_dir.path(), &["curl"]),
+            Err(PackageIntegrationError::ImageMagick(
+                ImageMagickError::NotInstalled
+            ))
+        ));
+        assert!(run_post_install(
+            &package_integrations(&IntegrationSettings::default()),
+            install_dir.path(),
+            &["curl"]
+        )
+        .unwrap()
+        .is_empty());
+    }
+}
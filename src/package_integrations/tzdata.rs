@@ -0,0 +1,90 @@
+use std::path::Path;
+
+use bullet_stream::style;
+
+use crate::package_integrations::{PackageIntegration, PackageIntegrationError, PostInstall};
+use crate::timezone::{resolves_timezone, zoneinfo_path};
+
+// Points `TZDIR` at the time zones installed into the layer. The `TZ` configured for the build is
+// validated against them since programs silently fall back to UTC for an unknown time zone.
+pub(crate) struct Tzdata {
+    pub(crate) timezone: Option<String>,
+}
+
+impl PackageIntegration for Tzdata {
+    fn name(&self) -> &'static str {
+        "timezone data"
+    }
+
+    fn applies(&self, package_name: &str) -> bool {
+        package_name == "tzdata"
+    }
+
+    fn post_install(&self, install_path: &Path) -> Result<PostInstall, PackageIntegrationError> {
+        let Some(zoneinfo_dir) = zoneinfo_path(install_path) else {
+            return Ok(PostInstall::default());
+        };
+
+        let mut messages = vec![format!(
+            "Setting {tzdir} to {zoneinfo_dir}",
+            tzdir = style::value("TZDIR"),
+            zoneinfo_dir = style::value(zoneinfo_dir.to_string_lossy())
+        )];
+        if let Some(timezone) = self
+            .timezone
+            .as_ref()
+            .filter(|timezone| !timezone.trim().is_empty())
+        {
+            if !resolves_timezone(&zoneinfo_dir, timezone) {
+                Err(PackageIntegrationError::UnknownTimezone(timezone.clone()))?;
+            }
+            messages.push(format!(
+                "Found time zone {timezone} configured by {tz}",
+                timezone = style::value(timezone),
+                tz = style::value("TZ")
+            ));
+        }
+
+        Ok(PostInstall {
+            messages,
+            env_vars: vec![("TZDIR", zoneinfo_dir.to_string_lossy().to_string())],
+            ..PostInstall::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_post_install() {
+        let install_dir = tempfile::tempdir().unwrap();
+        let tzdata = |timezone: &str| Tzdata {
+            timezone: Some(timezone.to_string()),
+        };
+        assert_eq!(
+            tzdata("America/New_York")
+                .post_install(install_dir.path())
+                .unwrap(),
+            PostInstall::default()
+        );
+
+        let zoneinfo_dir = install_dir.path().join("usr/share/zoneinfo");
+        fs::create_dir_all(zoneinfo_dir.join("America")).unwrap();
+        fs::write(zoneinfo_dir.join("America/New_York"), "").unwrap();
+        assert_eq!(
+            tzdata("America/New_York")
+                .post_install(install_dir.path())
+                .unwrap()
+                .env_vars,
+            vec![("TZDIR", zoneinfo_dir.to_string_lossy().to_string())]
+        );
+        assert!(matches!(
+            tzdata("America/Boston").post_install(install_dir.path()),
+            Err(PackageIntegrationError::UnknownTimezone(timezone)) if timezone == "America/Boston"
+        ));
+    }
+}
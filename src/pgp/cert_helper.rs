@@ -1,13 +1,36 @@
 use sequoia_openpgp::parse::stream::{MessageLayer, MessageStructure, VerificationHelper};
-use sequoia_openpgp::{Cert, KeyHandle, Result};
+use sequoia_openpgp::{Cert, Fingerprint, KeyHandle, Result};
 
 pub(crate) struct CertHelper {
     cert: Cert,
+    signing_key_fingerprint: Option<Fingerprint>,
 }
 
 impl CertHelper {
     pub(crate) fn new(cert: Cert) -> CertHelper {
-        CertHelper { cert }
+        CertHelper {
+            cert,
+            signing_key_fingerprint: None,
+        }
+    }
+
+    pub(crate) fn certificate_fingerprint(&self) -> Fingerprint {
+        self.cert.fingerprint()
+    }
+
+    // The primary user id is a human-readable description of who owns the certificate
+    // (e.g.; "Ubuntu Archive Automatic Signing Key (2018) <ftpmaster@ubuntu.com>").
+    pub(crate) fn certificate_user_id(&self) -> Option<String> {
+        self.cert
+            .userids()
+            .next()
+            .map(|user_id| String::from_utf8_lossy(user_id.userid().value()).to_string())
+    }
+
+    // Only available after the message has been verified. This can be the primary key of the
+    // certificate or one of its signing-capable subkeys.
+    pub(crate) fn signing_key_fingerprint(&self) -> Option<&Fingerprint> {
+        self.signing_key_fingerprint.as_ref()
     }
 }
 
@@ -21,17 +44,26 @@ impl VerificationHelper for CertHelper {
     fn check(&mut self, structure: MessageStructure) -> Result<()> {
         for (i, layer) in structure.into_iter().enumerate() {
             match (i, layer) {
-                // Consider only level 0 signatures (signatures over the data)
+                // Consider only level 0 signatures (signatures over the data). Release files can be
+                // signed by multiple keys (e.g.; Debian signs with the keys for the current and previous
+                // releases) so verification succeeds if any of the signatures are from our certificate.
                 (0, MessageLayer::SignatureGroup { results }) => {
-                    return results
-                        .into_iter()
-                        .next()
-                        .ok_or(anyhow::anyhow!("No signature"))
-                        .and_then(|verification_result| {
-                            verification_result
-                                .map(|_| ())
-                                .map_err(|e| sequoia_openpgp::Error::from(e).into())
-                        });
+                    let mut first_error = None;
+                    for verification_result in results {
+                        match verification_result {
+                            Ok(good_checksum) => {
+                                self.signing_key_fingerprint = Some(good_checksum.ka.fingerprint());
+                                return Ok(());
+                            }
+                            Err(e) => {
+                                first_error.get_or_insert(e);
+                            }
+                        }
+                    }
+                    return Err(first_error.map_or_else(
+                        || anyhow::anyhow!("No signature"),
+                        |e| sequoia_openpgp::Error::from(e).into(),
+                    ));
                 }
                 _ => Err(anyhow::anyhow!("Unexpected message structure"))?,
             }
@@ -0,0 +1,68 @@
+const SIGNED_MESSAGE_HEADER: &str = "-----BEGIN PGP SIGNED MESSAGE-----";
+const SIGNATURE_HEADER: &str = "-----BEGIN PGP SIGNATURE-----";
+
+pub(crate) fn is_clearsigned(message: &str) -> bool {
+    message.trim_start().starts_with(SIGNED_MESSAGE_HEADER)
+}
+
+// Returns the text of a cleartext signed message (e.g.; an `InRelease` file) without checking the
+// signature. This is only used for sources whose signature policy allows unverified release
+// files. Messages that aren't signed are returned as-is.
+//
+// See https://www.rfc-editor.org/rfc/rfc4880#section-7
+pub(crate) fn strip_clearsigned(message: &str) -> String {
+    if !is_clearsigned(message) {
+        return message.to_string();
+    }
+
+    let mut text = String::new();
+    message
+        .trim_start()
+        .lines()
+        .skip(1)
+        // armor headers (e.g.; `Hash: SHA512`) end at the first empty line
+        .skip_while(|line| !line.trim().is_empty())
+        .skip(1)
+        .take_while(|line| *line != SIGNATURE_HEADER)
+        .for_each(|line| {
+            // lines starting with a dash are escaped with `- `
+            text.push_str(line.strip_prefix("- ").unwrap_or(line));
+            text.push('\n');
+        });
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_strip_clearsigned() {
+        let message = indoc! { "
+            -----BEGIN PGP SIGNED MESSAGE-----
+            Hash: SHA512
+
+            Origin: Example
+            Suite: stable
+            - -----not a header
+            -----BEGIN PGP SIGNATURE-----
+
+            iQIzBAEBCgAdFiEE
+            -----END PGP SIGNATURE-----
+        " };
+        assert!(is_clearsigned(message));
+        assert_eq!(
+            strip_clearsigned(message),
+            "Origin: Example\nSuite: stable\n-----not a header\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_unsigned() {
+        let message = "Origin: Example\nSuite: stable\n";
+        assert!(!is_clearsigned(message));
+        assert_eq!(strip_clearsigned(message), message);
+    }
+}
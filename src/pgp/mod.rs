@@ -1,3 +1,7 @@
 pub(crate) use cert_helper::*;
+pub(crate) use clearsigned::*;
+pub(crate) use signing_keys::*;
 
 mod cert_helper;
+mod clearsigned;
+mod signing_keys;
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use sequoia_openpgp::parse::Parse;
+use sequoia_openpgp::serialize::SerializeInto;
+use sequoia_openpgp::Cert;
+
+// The certificates for verifying the release files of the supported distributions are read from
+// the `keys` directory of the buildpack. These are referenced by file name from the `signed_by`
+// field of the distro table (see `[[metadata.distros]]` in `buildpack.toml`) and only the keys it
+// references are loaded.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct SigningKeys(HashMap<String, String>);
+
+impl SigningKeys {
+    pub(crate) fn load<'a>(
+        keys_dir: &Path,
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self, LoadSigningKeyError> {
+        let mut signing_keys = HashMap::new();
+        for name in names {
+            if signing_keys.contains_key(name) {
+                continue;
+            }
+            let path = keys_dir.join(name);
+            let contents = fs::read(&path).map_err(|e| {
+                if e.kind() == ErrorKind::NotFound {
+                    LoadSigningKeyError::Unknown(name.to_string(), path.clone())
+                } else {
+                    LoadSigningKeyError::Read(path.clone(), e)
+                }
+            })?;
+            let signing_key = armor_signing_key(&contents)
+                .map_err(|e| LoadSigningKeyError::Invalid(path.clone(), e))?;
+            signing_keys.insert(name.to_string(), signing_key);
+        }
+        Ok(Self(signing_keys))
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum LoadSigningKeyError {
+    Unknown(String, PathBuf),
+    Read(PathBuf, std::io::Error),
+    Invalid(PathBuf, anyhow::Error),
+}
+
+// Signing key files configured for a source can be ASCII-armored or binary (e.g.; a keyring
+// exported with `gpg --export`). Either is converted to the ASCII-armored form used by `signed_by`.
+pub(crate) fn armor_signing_key(key: &[u8]) -> sequoia_openpgp::Result<String> {
+    let cert = Cert::from_bytes(key)?;
+    let armored = cert.armored().to_vec()?;
+    Ok(String::from_utf8(armored)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn keys_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("keys")
+    }
+
+    #[test]
+    fn test_load_signing_keys() {
+        let signing_keys = SigningKeys::load(
+            &keys_dir(),
+            ["ubuntu_24.04.asc", "debian_12.asc", "ubuntu_24.04.asc"],
+        )
+        .unwrap();
+        assert_eq!(signing_keys.0.len(), 2);
+        let key = signing_keys.get("ubuntu_24.04.asc").unwrap();
+        assert!(Cert::from_str(key).is_ok());
+        assert!(signing_keys.get("ubuntu_22.04.asc").is_none());
+    }
+
+    #[test]
+    fn test_load_unknown_signing_key() {
+        match SigningKeys::load(&keys_dir(), ["ubuntu_18.04.asc"]).unwrap_err() {
+            LoadSigningKeyError::Unknown(name, path) => {
+                assert_eq!(name, "ubuntu_18.04.asc");
+                assert_eq!(path, keys_dir().join("ubuntu_18.04.asc"));
+            }
+            e => panic!("Unexpected error: {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_load_invalid_signing_key() {
+        let keys_dir = tempfile::tempdir().unwrap();
+        fs::write(keys_dir.path().join("invalid.asc"), "not a key").unwrap();
+        match SigningKeys::load(keys_dir.path(), ["invalid.asc"]).unwrap_err() {
+            LoadSigningKeyError::Invalid(path, _) => {
+                assert_eq!(path, keys_dir.path().join("invalid.asc"));
+            }
+            e => panic!("Unexpected error: {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_armor_signing_key() {
+        let key = fs::read_to_string(keys_dir().join("ubuntu_24.04.asc")).unwrap();
+        let cert = Cert::from_str(&key).unwrap();
+
+        let armored = armor_signing_key(key.as_bytes()).unwrap();
+        assert_eq!(Cert::from_str(&armored).unwrap(), cert);
+
+        let armored = armor_signing_key(&cert.to_vec().unwrap()).unwrap();
+        assert_eq!(Cert::from_str(&armored).unwrap(), cert);
+
+        assert!(armor_signing_key(b"not a key").is_err());
+    }
+}
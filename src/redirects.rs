@@ -0,0 +1,273 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::Stdout;
+use std::sync::{Arc, Mutex};
+
+use bullet_stream::state::Bullet;
+use bullet_stream::{style, Print};
+use indexmap::IndexMap;
+use reqwest::redirect::Policy;
+use reqwest::Url;
+
+// Mirrors and CDNs often redirect requests (e.g.; to a server close to the build) so redirects are
+// followed, but only up to the same limit as the reqwest default.
+pub(crate) const MAX_REDIRECTS: usize = 10;
+
+// Records the redirects followed by every client built with its policy so they can be reported
+// after the phase that sent the requests. The chain for each requested URL is kept so a
+// misconfigured mirror can be traced back to the source that was requested.
+#[derive(Debug, Default)]
+pub(crate) struct RedirectTracker {
+    chains: Mutex<IndexMap<Url, Vec<Url>>>,
+}
+
+impl RedirectTracker {
+    pub(crate) fn policy(self: &Arc<Self>) -> Policy {
+        let tracker = self.clone();
+        Policy::custom(
+            move |attempt| match check_redirect(attempt.url(), attempt.previous()) {
+                Ok(()) => {
+                    tracker.record(attempt.previous(), attempt.url());
+                    attempt.follow()
+                }
+                Err(e) => attempt.error(e),
+            },
+        )
+    }
+
+    // Each redirect replaces the chain recorded for the original URL so only the complete chain is
+    // kept once the request finishes.
+    fn record(&self, previous: &[Url], next: &Url) {
+        let Some(original_url) = previous.first() else {
+            return;
+        };
+        let mut chain = previous.to_vec();
+        chain.push(next.clone());
+        self.chains
+            .lock()
+            .expect("The redirect tracker lock shouldn't be poisoned")
+            .insert(original_url.clone(), chain);
+    }
+
+    // Redirects are grouped by the hosts they went through since every request to a repository is
+    // usually redirected the same way (e.g.; `http://archive.ubuntu.com → https://mirror.example.com`).
+    // The recorded chains are cleared so each phase only reports its own redirects.
+    pub(crate) fn take_summary(&self) -> Vec<(Vec<String>, usize)> {
+        let chains = std::mem::take(
+            &mut *self
+                .chains
+                .lock()
+                .expect("The redirect tracker lock shouldn't be poisoned"),
+        );
+        let mut summary: IndexMap<Vec<String>, usize> = IndexMap::new();
+        for chain in chains.into_values() {
+            let mut origins = chain
+                .iter()
+                .map(|url| url.origin().ascii_serialization())
+                .collect::<Vec<_>>();
+            origins.dedup();
+            *summary.entry(origins).or_default() += 1;
+        }
+        summary.into_iter().collect()
+    }
+}
+
+pub(crate) fn log_redirects(
+    tracker: &RedirectTracker,
+    log: Print<Bullet<Stdout>>,
+) -> Print<Bullet<Stdout>> {
+    let summary = tracker.take_summary();
+    if summary.is_empty() {
+        return log;
+    }
+    summary
+        .into_iter()
+        .fold(log.bullet("Followed redirects"), |log, (origins, count)| {
+            log.sub_bullet(format!(
+                "{origins} ({count} {requests})",
+                origins = origins
+                    .iter()
+                    .map(style::url)
+                    .collect::<Vec<_>>()
+                    .join(" → "),
+                requests = if count == 1 { "request" } else { "requests" }
+            ))
+        })
+        .done()
+}
+
+// The previous URLs start with the one that was requested.
+fn check_redirect(next: &Url, previous: &[Url]) -> Result<(), RedirectError> {
+    let chain = || {
+        let mut chain = previous.to_vec();
+        chain.push(next.clone());
+        chain
+    };
+    if previous.contains(next) {
+        Err(RedirectError::Loop(chain()))
+    } else if previous.len() > MAX_REDIRECTS {
+        Err(RedirectError::TooManyRedirects(chain()))
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum RedirectError {
+    Loop(Vec<Url>),
+    TooManyRedirects(Vec<Url>),
+}
+
+impl Display for RedirectError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let format_chain = |chain: &[Url]| {
+            chain
+                .iter()
+                .map(Url::as_str)
+                .collect::<Vec<_>>()
+                .join(" → ")
+        };
+        match self {
+            RedirectError::Loop(chain) => {
+                write!(f, "redirect loop detected: {}", format_chain(chain))
+            }
+            RedirectError::TooManyRedirects(chain) => write!(
+                f,
+                "stopped after {MAX_REDIRECTS} redirects: {}",
+                format_chain(chain)
+            ),
+        }
+    }
+}
+
+impl Error for RedirectError {}
+
+// Request errors only describe the URL that failed so, when the failure was caused by following
+// redirects, the full chain is added to make a misconfigured mirror easier to find.
+pub(crate) fn describe_request_error(error: &(dyn Error + 'static)) -> String {
+    let redirect_error = std::iter::successors(Some(error), |&e| e.source())
+        .find_map(|e| e.downcast_ref::<RedirectError>());
+    match redirect_error {
+        Some(redirect_error) => format!("{error}: {redirect_error}"),
+        None => error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn url(value: &str) -> Url {
+        Url::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_check_redirect() {
+        let requested_url = url("http://archive.ubuntu.com/ubuntu/dists/noble/InRelease");
+        assert_eq!(
+            check_redirect(
+                &url("https://mirror.example.com/ubuntu/dists/noble/InRelease"),
+                std::slice::from_ref(&requested_url)
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            check_redirect(&requested_url, std::slice::from_ref(&requested_url)),
+            Err(RedirectError::Loop(vec![
+                requested_url.clone(),
+                requested_url.clone()
+            ]))
+        );
+
+        let previous = (0..=MAX_REDIRECTS)
+            .map(|i| url(&format!("https://mirror-{i}.example.com/ubuntu")))
+            .collect::<Vec<_>>();
+        let next = url("https://cdn.example.com/ubuntu");
+        match check_redirect(&next, &previous) {
+            Err(RedirectError::TooManyRedirects(chain)) => {
+                assert_eq!(chain.len(), MAX_REDIRECTS + 2);
+                assert_eq!(chain.last(), Some(&next));
+            }
+            result => panic!("Not the expected result - {result:?}"),
+        }
+        assert_eq!(check_redirect(&next, &previous[1..]), Ok(()));
+    }
+
+    #[test]
+    fn test_take_summary() {
+        let tracker = RedirectTracker::default();
+        for package in ["curl", "git"] {
+            let requested_url = url(&format!("http://archive.ubuntu.com/ubuntu/{package}.deb"));
+            tracker.record(
+                std::slice::from_ref(&requested_url),
+                &url(&format!("https://archive.ubuntu.com/ubuntu/{package}.deb")),
+            );
+            tracker.record(
+                &[
+                    requested_url,
+                    url(&format!("https://archive.ubuntu.com/ubuntu/{package}.deb")),
+                ],
+                &url(&format!("https://cdn.example.com/ubuntu/{package}.deb")),
+            );
+        }
+        tracker.record(
+            &[url("http://ppa.launchpadcontent.net/a/b/ubuntu/InRelease")],
+            &url("http://ppa.launchpadcontent.net/a/b/ubuntu/dists/InRelease"),
+        );
+
+        assert_eq!(
+            tracker.take_summary(),
+            vec![
+                (
+                    vec![
+                        "http://archive.ubuntu.com".to_string(),
+                        "https://archive.ubuntu.com".to_string(),
+                        "https://cdn.example.com".to_string()
+                    ],
+                    2
+                ),
+                (vec!["http://ppa.launchpadcontent.net".to_string()], 1)
+            ]
+        );
+        assert!(tracker.take_summary().is_empty());
+    }
+
+    // Stands in for the reqwest error that wraps the error returned by the redirect policy.
+    #[derive(Debug)]
+    struct FollowRedirectError(RedirectError);
+
+    impl Display for FollowRedirectError {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(
+                f,
+                "error following redirect for url (https://a.example.com/)"
+            )
+        }
+    }
+
+    impl Error for FollowRedirectError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    #[test]
+    fn test_describe_request_error() {
+        let error = FollowRedirectError(RedirectError::Loop(vec![
+            url("https://a.example.com/"),
+            url("https://b.example.com/"),
+            url("https://a.example.com/"),
+        ]));
+        assert_eq!(
+            describe_request_error(&error),
+            "error following redirect for url (https://a.example.com/): redirect loop detected: \
+            https://a.example.com/ → https://b.example.com/ → https://a.example.com/"
+        );
+        assert_eq!(
+            describe_request_error(&std::io::Error::other("connection reset")),
+            "connection reset"
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use std::io::Stdout;
+
+use bullet_stream::state::Bullet;
+use bullet_stream::{style, Print};
+use reqwest_middleware::ClientWithMiddleware;
+use reqwest_middleware::Error::Reqwest;
+use serde::Deserialize;
+
+use crate::config::{CustomSource, Ppa};
+use crate::debian::{Distro, SignaturePolicy};
+use crate::{BuildpackResult, DebianPackagesBuildpackError};
+
+const UBUNTU_DISTRO_NAME: &str = "ubuntu";
+
+// Expands each configured PPA into an additional source the same way `add-apt-repository` does.
+// The suite is the codename of the detected distribution and the source's `key_fingerprint` is the
+// fingerprint Launchpad reports for the archive so the key is downloaded from the Ubuntu keyserver
+// and cached with the keys of the other sources (see `fetch_signing_keys`).
+pub(crate) async fn resolve_ppas(
+    client: &ClientWithMiddleware,
+    distro: &Distro,
+    ppas: &[Ppa],
+    log: Print<Bullet<Stdout>>,
+) -> BuildpackResult<(Vec<CustomSource>, Print<Bullet<Stdout>>)> {
+    if ppas.is_empty() {
+        return Ok((vec![], log));
+    }
+
+    if distro.name != UBUNTU_DISTRO_NAME {
+        Err(ResolvePpasError::UnsupportedDistro(distro.name.clone()))?;
+    }
+
+    let mut log = log.bullet("Resolving PPAs");
+    let mut sources = vec![];
+    for ppa in ppas {
+        let key_fingerprint = get_signing_key_fingerprint(client, ppa).await?;
+        log = log.sub_bullet(format!(
+            "{ppa} → {uri} {suite}",
+            ppa = style::value(ppa.to_string()),
+            uri = style::url(ppa.uri().as_str()),
+            suite = distro.codename
+        ));
+        sources.push(CustomSource {
+            uri: ppa.uri(),
+            suites: vec![distro.codename.to_string()],
+            components: vec![PPA_COMPONENT.to_string()],
+            signed_by: None,
+            signed_by_file: None,
+            key_fingerprint: Some(key_fingerprint),
+            signature_policy: SignaturePolicy::Required,
+            trusted: false,
+            allow_weak_hashes: false,
+            client_identity: None,
+            mirrors: vec![],
+            download_url: None,
+            architectures: vec![],
+        });
+    }
+
+    Ok((sources, log.done()))
+}
+
+// PPAs only publish a single component.
+const PPA_COMPONENT: &str = "main";
+
+#[derive(Debug, Deserialize)]
+struct LaunchpadArchive {
+    signing_key_fingerprint: Option<String>,
+}
+
+async fn get_signing_key_fingerprint(
+    client: &ClientWithMiddleware,
+    ppa: &Ppa,
+) -> BuildpackResult<String> {
+    let archive = client
+        .get(ppa.launchpad_api_url())
+        .send()
+        .await
+        .and_then(|res| res.error_for_status().map_err(Reqwest))
+        .map_err(|e| ResolvePpasError::GetArchiveRequest(ppa.clone(), e))?
+        .bytes()
+        .await
+        .map_err(|e| ResolvePpasError::GetArchiveRequest(ppa.clone(), Reqwest(e)))?;
+    let archive = serde_json::from_slice::<LaunchpadArchive>(&archive)
+        .map_err(|e| ResolvePpasError::ParseArchiveResponse(ppa.clone(), e))?;
+
+    Ok(archive
+        .signing_key_fingerprint
+        .filter(|fingerprint| !fingerprint.is_empty())
+        .ok_or_else(|| ResolvePpasError::MissingSigningKeyFingerprint(ppa.clone()))?)
+}
+
+#[derive(Debug)]
+pub(crate) enum ResolvePpasError {
+    UnsupportedDistro(String),
+    GetArchiveRequest(Ppa, reqwest_middleware::Error),
+    ParseArchiveResponse(Ppa, serde_json::Error),
+    MissingSigningKeyFingerprint(Ppa),
+}
+
+impl From<ResolvePpasError> for libcnb::Error<DebianPackagesBuildpackError> {
+    fn from(value: ResolvePpasError) -> Self {
+        Self::BuildpackError(DebianPackagesBuildpackError::ResolvePpas(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bullet_stream::Print;
+
+    use super::*;
+    use crate::debian::{ArchitectureName, DistroCodename};
+
+    #[tokio::test]
+    async fn test_resolve_ppas_on_debian() {
+        let distro = Distro {
+            name: "debian".to_string(),
+            version: "12".to_string(),
+            codename: DistroCodename::from("bookworm"),
+            architecture: ArchitectureName::AMD_64,
+        };
+        let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new()).build();
+        let log = Print::new(std::io::stdout()).h1("test");
+
+        match resolve_ppas(
+            &client,
+            &distro,
+            &[Ppa::from_str("ppa:deadsnakes/ppa").unwrap()],
+            log,
+        )
+        .await
+        .unwrap_err()
+        {
+            libcnb::Error::BuildpackError(DebianPackagesBuildpackError::ResolvePpas(
+                ResolvePpasError::UnsupportedDistro(name),
+            )) => assert_eq!(name, "debian"),
+            e => panic!("Not the expected error - {e:?}"),
+        }
+    }
+}
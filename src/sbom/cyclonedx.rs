@@ -0,0 +1,136 @@
+use libcnb::data::sbom::SbomFormat;
+use libcnb::sbom::Sbom;
+use serde::Serialize;
+
+use crate::debian::{ChecksumAlgorithm, Distro, RepositoryPackage};
+use crate::install_packages::build_download_url;
+use crate::sbom::package_url;
+
+// A CycloneDX SBOM listing every installed package so the packages layer can be checked by image
+// scanners. Only the fields needed to identify each package are included.
+//
+// See: https://cyclonedx.org/docs/1.4/json/
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDxBom {
+    bom_format: &'static str,
+    spec_version: &'static str,
+    version: u32,
+    components: Vec<CycloneDxComponent>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDxComponent {
+    #[serde(rename = "type")]
+    component_type: &'static str,
+    #[serde(rename = "bom-ref")]
+    bom_ref: String,
+    name: String,
+    version: String,
+    purl: String,
+    hashes: Vec<CycloneDxHash>,
+    external_references: Vec<CycloneDxExternalReference>,
+}
+
+#[derive(Debug, Serialize)]
+struct CycloneDxHash {
+    alg: &'static str,
+    content: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CycloneDxExternalReference {
+    #[serde(rename = "type")]
+    reference_type: &'static str,
+    url: String,
+}
+
+pub(crate) fn create_cyclonedx_sbom(
+    distro: &Distro,
+    installed_packages: &[RepositoryPackage],
+) -> Result<Sbom, serde_json::Error> {
+    let mut components = installed_packages
+        .iter()
+        .map(|package| {
+            let purl = package_url(distro, package);
+            CycloneDxComponent {
+                component_type: "library",
+                bom_ref: purl.clone(),
+                name: package.name.clone(),
+                version: package.version.clone(),
+                purl,
+                hashes: vec![CycloneDxHash {
+                    alg: match package.checksum_algorithm {
+                        ChecksumAlgorithm::Sha256 => "SHA-256",
+                        ChecksumAlgorithm::Sha1 => "SHA-1",
+                        ChecksumAlgorithm::Md5 => "MD5",
+                    },
+                    content: package.checksum.clone(),
+                }],
+                external_references: vec![CycloneDxExternalReference {
+                    reference_type: "distribution",
+                    url: build_download_url(package),
+                }],
+            }
+        })
+        .collect::<Vec<_>>();
+    components.sort_by(|a, b| a.bom_ref.cmp(&b.bom_ref));
+
+    let bom = CycloneDxBom {
+        bom_format: "CycloneDX",
+        spec_version: "1.4",
+        version: 1,
+        components,
+    };
+
+    serde_json::to_vec_pretty(&bom).map(|data| Sbom::from_bytes(SbomFormat::CycloneDxJson, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::debian::{ArchitectureName, DistroCodename};
+    use crate::test_support::create_repository_package;
+
+    use super::*;
+
+    #[test]
+    fn test_create_sbom() {
+        let distro = Distro {
+            name: "ubuntu".to_string(),
+            version: "24.04".to_string(),
+            codename: DistroCodename::from("noble"),
+            architecture: ArchitectureName::AMD_64,
+        };
+        let package = create_repository_package()
+            .name("libgnutls30t64")
+            .version("3.8.3-1.1ubuntu3+esm1")
+            .repository_uri("http://archive.ubuntu.com/ubuntu")
+            .filename("pool/main/g/gnutls28/libgnutls30t64_3.8.3-1.1ubuntu3_amd64.deb")
+            .checksum("abc123")
+            .call();
+
+        let sbom = create_cyclonedx_sbom(&distro, &[package]).unwrap();
+        assert_eq!(sbom.format, SbomFormat::CycloneDxJson);
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&sbom.data).unwrap(),
+            serde_json::json!({
+                "bomFormat": "CycloneDX",
+                "specVersion": "1.4",
+                "version": 1,
+                "components": [{
+                    "type": "library",
+                    "bom-ref": "pkg:deb/ubuntu/libgnutls30t64@3.8.3-1.1ubuntu3%2Besm1?arch=amd64&distro=ubuntu-24.04",
+                    "name": "libgnutls30t64",
+                    "version": "3.8.3-1.1ubuntu3+esm1",
+                    "purl": "pkg:deb/ubuntu/libgnutls30t64@3.8.3-1.1ubuntu3%2Besm1?arch=amd64&distro=ubuntu-24.04",
+                    "hashes": [{ "alg": "SHA-256", "content": "abc123" }],
+                    "externalReferences": [{
+                        "type": "distribution",
+                        "url": "http://archive.ubuntu.com/ubuntu/pool/main/g/gnutls28/libgnutls30t64_3.8.3-1.1ubuntu3_amd64.deb"
+                    }]
+                }]
+            })
+        );
+    }
+}
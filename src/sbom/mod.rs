@@ -0,0 +1,9 @@
+pub(crate) use cyclonedx::*;
+pub(crate) use package_url::*;
+pub(crate) use provenance::*;
+pub(crate) use spdx::*;
+
+mod cyclonedx;
+mod package_url;
+mod provenance;
+mod spdx;
@@ -0,0 +1,76 @@
+use crate::debian::{Distro, RepositoryPackage};
+
+// See: https://github.com/package-url/purl-spec/blob/master/PURL-TYPES.rst#deb
+pub(crate) fn package_url(distro: &Distro, package: &RepositoryPackage) -> String {
+    // packages for a foreign architecture are installed with their qualified name (e.g.; `libc6:i386`)
+    let (name, arch) = match package.name.split_once(':') {
+        Some((name, arch)) => (name, arch.to_string()),
+        None => (package.name.as_str(), distro.architecture.to_string()),
+    };
+    format!(
+        "pkg:deb/{namespace}/{name}@{version}?arch={arch}&distro={distro}",
+        namespace = percent_encode(&distro.name.to_lowercase()),
+        name = percent_encode(name),
+        version = percent_encode(&package.version),
+        distro = percent_encode(&format!(
+            "{}-{}",
+            distro.name.to_lowercase(),
+            distro.version
+        ))
+    )
+}
+
+// versions can contain characters like `:` (epochs) and `+` that must be encoded in a package url
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'-' | b'_' | b'~' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debian::ArchitectureName;
+    use crate::test_support::create_repository_package;
+
+    #[test]
+    fn test_package_url() {
+        let distro = Distro {
+            name: "Ubuntu".to_string(),
+            version: "24.04".to_string(),
+            codename: "noble".into(),
+            architecture: ArchitectureName::AMD_64,
+        };
+        let mut package = create_repository_package()
+            .name("libc6")
+            .version("2.39-0ubuntu8")
+            .repository_uri("http://archive.ubuntu.com/ubuntu")
+            .filename("pool/main/g/glibc/libc6_2.39-0ubuntu8_amd64.deb")
+            .checksum("abc123")
+            .call();
+        assert_eq!(
+            package_url(&distro, &package),
+            "pkg:deb/ubuntu/libc6@2.39-0ubuntu8?arch=amd64&distro=ubuntu-24.04"
+        );
+
+        package.name = "libc6:i386".to_string();
+        assert_eq!(
+            package_url(&distro, &package),
+            "pkg:deb/ubuntu/libc6@2.39-0ubuntu8?arch=i386&distro=ubuntu-24.04"
+        );
+    }
+
+    #[test]
+    fn test_percent_encode() {
+        assert_eq!(
+            percent_encode("1:2.3+dfsg-1~ubuntu"),
+            "1%3A2.3%2Bdfsg-1~ubuntu"
+        );
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::BTreeMap;
+use std::fs::{read_link, File};
+use std::io::copy;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::debian::{ChecksumAlgorithm, Distro, RepositoryPackage};
+use crate::install_packages::{build_download_url, InstallSnapshot};
+use crate::lockfile::LOCKFILE_NAME;
+use crate::sbom::package_url;
+
+// Written into the layer alongside the lockfile and snapshot so platform supply-chain tooling can
+// attest the packages layer from the resulting image.
+pub(crate) const PROVENANCE_FILE_NAME: &str = ".deb-packages-provenance.json";
+
+const STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+const PREDICATE_TYPE: &str = "https://slsa.dev/provenance/v1";
+const BUILD_TYPE: &str = "https://github.com/heroku/buildpacks-deb-packages/provenance/v1";
+const BUILDER_ID: &str = "https://github.com/heroku/buildpacks-deb-packages";
+const BUILDPACK_ID: &str = "heroku/deb-packages";
+const LAYER_NAME: &str = "packages";
+
+// An in-toto statement with a SLSA provenance predicate describing the packages layer. The inputs
+// are the release files (with the keys that verified them), the package indexes, and the package
+// archives that were installed. The output is the layer itself, identified by a digest of its
+// contents since the digest of the exported layer isn't known until the image is created.
+//
+// See: https://github.com/in-toto/attestation/blob/main/spec/v1/statement.md
+// See: https://slsa.dev/spec/v1.0/provenance
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Statement {
+    #[serde(rename = "_type")]
+    type_uri: &'static str,
+    subject: Vec<ResourceDescriptor>,
+    predicate_type: &'static str,
+    predicate: Provenance,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Provenance {
+    build_definition: BuildDefinition,
+    run_details: RunDetails,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BuildDefinition {
+    build_type: &'static str,
+    external_parameters: Value,
+    resolved_dependencies: Vec<ResourceDescriptor>,
+}
+
+#[derive(Debug, Serialize)]
+struct RunDetails {
+    builder: Builder,
+    byproducts: Vec<ResourceDescriptor>,
+}
+
+#[derive(Debug, Serialize)]
+struct Builder {
+    id: &'static str,
+    version: BTreeMap<&'static str, String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResourceDescriptor {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uri: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    digest: BTreeMap<&'static str, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    download_location: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    annotations: BTreeMap<&'static str, Value>,
+}
+
+pub(crate) fn create_provenance(
+    distro: &Distro,
+    installed_packages: &[RepositoryPackage],
+    snapshot: &InstallSnapshot,
+    layer_digest: &str,
+    lockfile_digest: &str,
+) -> Result<Vec<u8>, serde_json::Error> {
+    let mut resolved_dependencies = vec![];
+
+    for source in &snapshot.sources {
+        let mut annotations = BTreeMap::from([
+            (
+                "signaturePolicy",
+                serde_json::to_value(source.signature_policy)?,
+            ),
+            ("verified", json!(source.verified)),
+        ]);
+        if let Some(fingerprint) = &source.signing_key_fingerprint {
+            annotations.insert("signingKeyFingerprint", json!(fingerprint));
+        }
+        if let Some(fingerprint) = &source.certificate_fingerprint {
+            annotations.insert("certificateFingerprint", json!(fingerprint));
+        }
+        resolved_dependencies.push(ResourceDescriptor {
+            uri: Some(source.release_file_url.clone()),
+            annotations,
+            ..ResourceDescriptor::default()
+        });
+
+        for package_index in &source.package_indexes {
+            resolved_dependencies.push(ResourceDescriptor {
+                uri: Some(package_index.url.clone()),
+                digest: BTreeMap::from([(
+                    digest_algorithm(package_index.checksum_algorithm),
+                    package_index.checksum.clone(),
+                )]),
+                ..ResourceDescriptor::default()
+            });
+        }
+    }
+
+    let mut packages = installed_packages.iter().collect::<Vec<_>>();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    for package in packages {
+        resolved_dependencies.push(ResourceDescriptor {
+            name: Some(package.name.clone()),
+            uri: Some(package_url(distro, package)),
+            digest: BTreeMap::from([(
+                digest_algorithm(package.checksum_algorithm),
+                package.checksum.clone(),
+            )]),
+            download_location: Some(build_download_url(package)),
+            ..ResourceDescriptor::default()
+        });
+    }
+
+    let statement = Statement {
+        type_uri: STATEMENT_TYPE,
+        subject: vec![ResourceDescriptor {
+            name: Some(LAYER_NAME.to_string()),
+            digest: BTreeMap::from([("sha256", layer_digest.to_string())]),
+            ..ResourceDescriptor::default()
+        }],
+        predicate_type: PREDICATE_TYPE,
+        predicate: Provenance {
+            build_definition: BuildDefinition {
+                build_type: BUILD_TYPE,
+                external_parameters: json!({
+                    "configSha256": snapshot.config_sha256,
+                    "distro": {
+                        "name": distro.name,
+                        "version": distro.version,
+                        "codename": distro.codename.to_string(),
+                        "architecture": distro.architecture.to_string(),
+                    },
+                }),
+                resolved_dependencies,
+            },
+            run_details: RunDetails {
+                builder: Builder {
+                    id: BUILDER_ID,
+                    version: BTreeMap::from([(BUILDPACK_ID, snapshot.buildpack_version.clone())]),
+                },
+                byproducts: vec![ResourceDescriptor {
+                    name: Some(LOCKFILE_NAME.to_string()),
+                    digest: BTreeMap::from([("sha256", lockfile_digest.to_string())]),
+                    ..ResourceDescriptor::default()
+                }],
+            },
+        },
+    };
+
+    serde_json::to_vec_pretty(&statement)
+}
+
+// The in-toto digest set uses the lowercase algorithm names.
+fn digest_algorithm(checksum_algorithm: ChecksumAlgorithm) -> &'static str {
+    match checksum_algorithm {
+        ChecksumAlgorithm::Sha256 => "sha256",
+        ChecksumAlgorithm::Sha1 => "sha1",
+        ChecksumAlgorithm::Md5 => "md5",
+    }
+}
+
+// Hashes the path, type, and permissions of every entry in the layer along with the contents of
+// files and the targets of links. Entries are visited in a fixed order so the same layer always has
+// the same digest. The provenance file itself is skipped since it contains the digest.
+pub(crate) fn layer_digest(layer_path: &Path) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+
+    for entry in WalkDir::new(layer_path).min_depth(1).sort_by_file_name() {
+        let entry = entry?;
+        let relative_path = entry
+            .path()
+            .strip_prefix(layer_path)
+            .unwrap_or(entry.path());
+        if relative_path == Path::new(PROVENANCE_FILE_NAME) {
+            continue;
+        }
+
+        let file_type = entry.file_type();
+        let mode = entry.metadata()?.permissions().mode() & 0o7777;
+        if file_type.is_symlink() {
+            hasher.update(b"l ");
+            hasher.update(relative_path.as_os_str().as_bytes());
+            hasher.update(b"\0");
+            hasher.update(read_link(entry.path())?.as_os_str().as_bytes());
+        } else if file_type.is_file() {
+            hasher.update(format!("f {mode:o} {} ", entry.metadata()?.len()));
+            hasher.update(relative_path.as_os_str().as_bytes());
+            hasher.update(b"\0");
+            copy(&mut File::open(entry.path())?, &mut hasher)?;
+        } else {
+            hasher.update(format!("d {mode:o} "));
+            hasher.update(relative_path.as_os_str().as_bytes());
+        }
+        hasher.update(b"\0");
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+pub(crate) fn file_digest(path: &Path) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::os::unix::fs::symlink;
+
+    use crate::create_package_index::{IndexedPackageIndex, IndexedSource};
+    use crate::debian::{ArchitectureName, DistroCodename, SignaturePolicy};
+    use crate::test_support::create_repository_package;
+
+    use super::*;
+
+    #[test]
+    fn test_create_provenance() {
+        let distro = Distro {
+            name: "ubuntu".to_string(),
+            version: "24.04".to_string(),
+            codename: DistroCodename::from("noble"),
+            architecture: ArchitectureName::AMD_64,
+        };
+        let package = create_repository_package()
+            .name("curl")
+            .version("8.5.0-2ubuntu10")
+            .repository_uri("http://archive.ubuntu.com/ubuntu")
+            .filename("pool/main/c/curl/curl_8.5.0-2ubuntu10_amd64.deb")
+            .checksum("abc123")
+            .call();
+        let snapshot = InstallSnapshot {
+            buildpack_version: "0.0.3".to_string(),
+            config_sha256: "config123".to_string(),
+            sources: vec![IndexedSource {
+                release_file_url: "http://archive.ubuntu.com/ubuntu/dists/noble/InRelease"
+                    .to_string(),
+                certificate_fingerprint: None,
+                signing_key_fingerprint: Some(
+                    "F6ECB3762474EDA9D21B7022871920D1991BC93C".to_string(),
+                ),
+                signature_policy: SignaturePolicy::Required,
+                verified: true,
+                package_indexes: vec![IndexedPackageIndex {
+                    url:
+                        "http://archive.ubuntu.com/ubuntu/dists/noble/main/binary-amd64/Packages.gz"
+                            .to_string(),
+                    checksum: "def456".to_string(),
+                    checksum_algorithm: ChecksumAlgorithm::Sha256,
+                }],
+            }],
+            dependency_paths: BTreeMap::new(),
+        };
+
+        let provenance =
+            create_provenance(&distro, &[package], &snapshot, "layer789", "lock000").unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&provenance).unwrap(),
+            json!({
+                "_type": "https://in-toto.io/Statement/v1",
+                "subject": [{ "name": "packages", "digest": { "sha256": "layer789" } }],
+                "predicateType": "https://slsa.dev/provenance/v1",
+                "predicate": {
+                    "buildDefinition": {
+                        "buildType": "https://github.com/heroku/buildpacks-deb-packages/provenance/v1",
+                        "externalParameters": {
+                            "configSha256": "config123",
+                            "distro": {
+                                "name": "ubuntu",
+                                "version": "24.04",
+                                "codename": "noble",
+                                "architecture": "amd64"
+                            }
+                        },
+                        "resolvedDependencies": [
+                            {
+                                "uri": "http://archive.ubuntu.com/ubuntu/dists/noble/InRelease",
+                                "annotations": {
+                                    "signaturePolicy": "required",
+                                    "signingKeyFingerprint": "F6ECB3762474EDA9D21B7022871920D1991BC93C",
+                                    "verified": true
+                                }
+                            },
+                            {
+                                "uri": "http://archive.ubuntu.com/ubuntu/dists/noble/main/binary-amd64/Packages.gz",
+                                "digest": { "sha256": "def456" }
+                            },
+                            {
+                                "name": "curl",
+                                "uri": "pkg:deb/ubuntu/curl@8.5.0-2ubuntu10?arch=amd64&distro=ubuntu-24.04",
+                                "digest": { "sha256": "abc123" },
+                                "downloadLocation": "http://archive.ubuntu.com/ubuntu/pool/main/c/curl/curl_8.5.0-2ubuntu10_amd64.deb"
+                            }
+                        ]
+                    },
+                    "runDetails": {
+                        "builder": {
+                            "id": "https://github.com/heroku/buildpacks-deb-packages",
+                            "version": { "heroku/deb-packages": "0.0.3" }
+                        },
+                        "byproducts": [{ "name": "deb-packages.lock", "digest": { "sha256": "lock000" } }]
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_layer_digest() {
+        let layer_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(layer_dir.path().join("usr/bin")).unwrap();
+        fs::write(layer_dir.path().join("usr/bin/curl"), "curl").unwrap();
+        symlink("curl", layer_dir.path().join("usr/bin/curl-link")).unwrap();
+        let digest = layer_digest(layer_dir.path()).unwrap();
+
+        // the provenance file isn't part of the digest
+        fs::write(layer_dir.path().join(PROVENANCE_FILE_NAME), "{}").unwrap();
+        assert_eq!(layer_digest(layer_dir.path()).unwrap(), digest);
+
+        fs::write(layer_dir.path().join("usr/bin/curl"), "curl2").unwrap();
+        assert_ne!(layer_digest(layer_dir.path()).unwrap(), digest);
+        fs::write(layer_dir.path().join("usr/bin/curl"), "curl").unwrap();
+        assert_eq!(layer_digest(layer_dir.path()).unwrap(), digest);
+
+        fs::set_permissions(
+            layer_dir.path().join("usr/bin/curl"),
+            fs::Permissions::from_mode(0o755),
+        )
+        .unwrap();
+        assert_ne!(layer_digest(layer_dir.path()).unwrap(), digest);
+    }
+}
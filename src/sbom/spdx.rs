@@ -0,0 +1,518 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use libcnb::data::sbom::SbomFormat;
+use libcnb::sbom::Sbom;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::debian::{ChecksumAlgorithm, Copyright, Distro, RepositoryPackage};
+use crate::install_packages::build_download_url;
+use crate::sbom::package_url;
+use crate::shared_cache::package_set_hash;
+
+// The SBOM is generated on every build so a fixed creation time is used to keep the layer
+// reproducible. This is the same timestamp the lifecycle uses for reproducible images.
+const CREATED: &str = "1980-01-01T00:00:01Z";
+
+const NO_ASSERTION: &str = "NOASSERTION";
+
+// An SPDX SBOM listing every installed package along with the licenses declared in the
+// machine-readable copyright file the package installs so the packages layer can be checked by
+// license compliance tools.
+//
+// See: https://spdx.github.io/spdx-spec/v2.3/
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxDocument {
+    spdx_version: &'static str,
+    data_license: &'static str,
+    #[serde(rename = "SPDXID")]
+    spdx_id: &'static str,
+    name: &'static str,
+    document_namespace: String,
+    creation_info: SpdxCreationInfo,
+    packages: Vec<SpdxPackage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    has_extracted_licensing_infos: Vec<SpdxExtractedLicensingInfo>,
+}
+
+#[derive(Debug, Serialize)]
+struct SpdxCreationInfo {
+    created: &'static str,
+    creators: Vec<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxPackage {
+    name: String,
+    #[serde(rename = "SPDXID")]
+    spdx_id: String,
+    version_info: String,
+    download_location: String,
+    files_analyzed: bool,
+    checksums: Vec<SpdxChecksum>,
+    license_concluded: &'static str,
+    license_declared: String,
+    copyright_text: &'static str,
+    external_refs: Vec<SpdxExternalRef>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxChecksum {
+    algorithm: &'static str,
+    checksum_value: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SpdxExternalRef {
+    #[serde(rename = "referenceCategory")]
+    category: &'static str,
+    #[serde(rename = "referenceType")]
+    ref_type: &'static str,
+    #[serde(rename = "referenceLocator")]
+    locator: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxExtractedLicensingInfo {
+    license_id: String,
+    name: String,
+    extracted_text: String,
+}
+
+pub(crate) fn create_spdx_sbom(
+    distro: &Distro,
+    installed_packages: &[RepositoryPackage],
+    install_path: &Path,
+) -> Result<Sbom, serde_json::Error> {
+    let mut extracted_licenses = BTreeMap::new();
+
+    let mut packages = installed_packages
+        .iter()
+        .map(|package| {
+            // packages for a foreign architecture are named with it (e.g.; `libc6:i386`) but
+            // install their documentation under the package name
+            let doc_dir = package
+                .name
+                .split_once(':')
+                .map_or(package.name.as_str(), |(name, _)| name);
+            let copyright = Copyright::read(
+                &install_path
+                    .join("usr/share/doc")
+                    .join(doc_dir)
+                    .join("copyright"),
+            )
+            .unwrap_or_default();
+
+            let license_declared = license_expression(&copyright, &mut extracted_licenses);
+
+            SpdxPackage {
+                name: package.name.clone(),
+                spdx_id: format!("SPDXRef-Package-{}", spdx_id_string(&package.name)),
+                version_info: package.version.clone(),
+                download_location: build_download_url(package),
+                files_analyzed: false,
+                checksums: vec![SpdxChecksum {
+                    algorithm: match package.checksum_algorithm {
+                        ChecksumAlgorithm::Sha256 => "SHA256",
+                        ChecksumAlgorithm::Sha1 => "SHA1",
+                        ChecksumAlgorithm::Md5 => "MD5",
+                    },
+                    checksum_value: package.checksum.clone(),
+                }],
+                license_concluded: NO_ASSERTION,
+                license_declared,
+                copyright_text: NO_ASSERTION,
+                external_refs: vec![SpdxExternalRef {
+                    category: "PACKAGE-MANAGER",
+                    ref_type: "purl",
+                    locator: package_url(distro, package),
+                }],
+            }
+        })
+        .collect::<Vec<_>>();
+    packages.sort_by(|a, b| a.spdx_id.cmp(&b.spdx_id));
+
+    let document = SpdxDocument {
+        spdx_version: "SPDX-2.3",
+        data_license: "CC0-1.0",
+        spdx_id: "SPDXRef-DOCUMENT",
+        name: "deb-packages",
+        document_namespace: format!(
+            "https://github.com/heroku/buildpacks-deb-packages/spdx/{}",
+            package_set_hash(distro, installed_packages)
+        ),
+        creation_info: SpdxCreationInfo {
+            created: CREATED,
+            creators: vec!["Tool: heroku/deb-packages"],
+        },
+        packages,
+        has_extracted_licensing_infos: extracted_licenses.into_values().collect(),
+    };
+
+    serde_json::to_vec_pretty(&document).map(|data| Sbom::from_bytes(SbomFormat::SpdxJson, data))
+}
+
+// Combines the licenses from each file paragraph of the copyright file. Any license that doesn't
+// have an SPDX identifier is recorded as a `LicenseRef-` with the license text from the copyright
+// file (when available) since SPDX requires the text of every custom license to be included.
+fn license_expression(
+    copyright: &Copyright,
+    extracted_licenses: &mut BTreeMap<String, SpdxExtractedLicensingInfo>,
+) -> String {
+    let expressions = copyright
+        .licenses
+        .iter()
+        .map(|license| {
+            let (expression, license_refs) = spdx_expression(license);
+            for (license_id, name) in license_refs {
+                extracted_licenses
+                    .entry(license_id.clone())
+                    .or_insert_with(|| SpdxExtractedLicensingInfo {
+                        license_id,
+                        extracted_text: copyright
+                            .license_texts
+                            .get(&name)
+                            .cloned()
+                            .unwrap_or_else(|| name.clone()),
+                        name,
+                    });
+            }
+            expression
+        })
+        .collect::<Vec<_>>();
+
+    match expressions.as_slice() {
+        [] => NO_ASSERTION.to_string(),
+        [expression] => expression.clone(),
+        expressions => expressions
+            .iter()
+            .map(|expression| {
+                if expression.contains(' ') {
+                    format!("({expression})")
+                } else {
+                    expression.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" AND "),
+    }
+}
+
+// Converts a license short name from a copyright file (e.g.; `GPL-2+ or Artistic`) into an SPDX
+// license expression along with any `LicenseRef-` identifiers it uses. Short names with exceptions
+// (e.g.; `GPL-2+ with OpenSSL exception`) don't have a standard form so they're kept as a single
+// `LicenseRef-`.
+fn spdx_expression(license: &str) -> (String, Vec<(String, String)>) {
+    let mut license_refs = vec![];
+    let mut license_ref = |name: &str| {
+        let license_id = format!("LicenseRef-{}", spdx_id_string(name));
+        if !license_refs.iter().any(|(id, _)| id == &license_id) {
+            license_refs.push((license_id.clone(), name.to_string()));
+        }
+        license_id
+    };
+
+    if license.to_lowercase().contains(" with ") {
+        let expression = license_ref(license);
+        return (expression, license_refs);
+    }
+
+    let expression = license
+        .split_whitespace()
+        .map(|token| token.trim_end_matches(','))
+        .filter(|token| !token.is_empty())
+        .map(|token| match token.to_lowercase().as_str() {
+            "or" => "OR".to_string(),
+            "and" => "AND".to_string(),
+            _ => spdx_license_id(token).unwrap_or_else(|| license_ref(token)),
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    (expression, license_refs)
+}
+
+// The Debian short names that differ from (or are spelled differently than) their SPDX
+// identifiers.
+//
+// See: https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/#license-short-name
+const SPDX_LICENSE_IDS: &[(&str, &str)] = &[
+    ("apache-2.0", "Apache-2.0"),
+    ("artistic", "Artistic-1.0"),
+    ("artistic-2.0", "Artistic-2.0"),
+    ("bsd-2-clause", "BSD-2-Clause"),
+    ("bsd-3-clause", "BSD-3-Clause"),
+    ("bsd-4-clause", "BSD-4-Clause"),
+    ("cc0-1.0", "CC0-1.0"),
+    ("curl", "curl"),
+    ("expat", "MIT"),
+    ("isc", "ISC"),
+    ("mit", "MIT"),
+    ("mpl-1.1", "MPL-1.1"),
+    ("mpl-2.0", "MPL-2.0"),
+    ("openssl", "OpenSSL"),
+    ("psf-2", "PSF-2.0"),
+    ("python-2.0", "Python-2.0"),
+    ("x11", "X11"),
+    ("zlib", "Zlib"),
+];
+
+// The GNU licenses use a `+` suffix for "or any later version" in Debian short names which SPDX
+// spells out in the identifier (e.g.; `GPL-2+` is `GPL-2.0-or-later`).
+fn spdx_license_id(name: &str) -> Option<String> {
+    let (name, or_later) = match name.strip_suffix('+') {
+        Some(name) => (name, true),
+        None => (name, false),
+    };
+
+    for family in ["AGPL", "LGPL", "GPL", "GFDL"] {
+        if let Some(version) = name
+            .get(..=family.len())
+            .filter(|prefix| prefix.eq_ignore_ascii_case(&format!("{family}-")))
+            .map(|prefix| &name[prefix.len()..])
+        {
+            if version.is_empty() || !version.chars().all(|c| c.is_ascii_digit() || c == '.') {
+                return None;
+            }
+            let version = if version.contains('.') {
+                version.to_string()
+            } else {
+                format!("{version}.0")
+            };
+            let suffix = if or_later { "or-later" } else { "only" };
+            return Some(format!("{family}-{version}-{suffix}"));
+        }
+    }
+
+    if or_later {
+        return None;
+    }
+
+    SPDX_LICENSE_IDS
+        .iter()
+        .find(|(short_name, _)| short_name.eq_ignore_ascii_case(name))
+        .map(|(_, spdx_id)| (*spdx_id).to_string())
+}
+
+// SPDX identifiers may only contain letters, numbers, `.`, and `-`. Other characters are replaced
+// with `-` and the start of a hash of the value is appended so values that only differ in those
+// characters still have different identifiers (e.g.; `libbar++` and `libbar--`).
+fn spdx_id_string(value: &str) -> String {
+    let id = value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+    if id == value {
+        id
+    } else {
+        format!("{id}-{:.8}", format!("{:x}", Sha256::digest(value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::debian::{ArchitectureName, DistroCodename};
+    use crate::test_support::create_repository_package;
+
+    use super::*;
+
+    fn package(name: &str) -> RepositoryPackage {
+        create_repository_package()
+            .name(name)
+            .version("1.0+dfsg-1")
+            .repository_uri("http://archive.ubuntu.com/ubuntu")
+            .filename(&format!("pool/main/{name}_1.0+dfsg-1_amd64.deb"))
+            .checksum("abc123")
+            .call()
+    }
+
+    #[test]
+    fn test_create_spdx_sbom() {
+        let distro = Distro {
+            name: "ubuntu".to_string(),
+            version: "24.04".to_string(),
+            codename: DistroCodename::from("noble"),
+            architecture: ArchitectureName::AMD_64,
+        };
+        let install_dir = tempfile::tempdir().unwrap();
+        let doc_dir = install_dir.path().join("usr/share/doc/libfoo1");
+        fs::create_dir_all(&doc_dir).unwrap();
+        fs::write(
+            doc_dir.join("copyright"),
+            indoc::indoc! { "
+                Format: https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/
+
+                Files: *
+                Copyright: 2024, Someone
+                License: GPL-2+ or Foo
+
+                License: Foo
+                 Do what you like.
+            " },
+        )
+        .unwrap();
+
+        let sbom = create_spdx_sbom(
+            &distro,
+            &[package("libfoo1"), package("libbar++")],
+            install_dir.path(),
+        )
+        .unwrap();
+        assert_eq!(sbom.format, SbomFormat::SpdxJson);
+
+        let document = serde_json::from_slice::<serde_json::Value>(&sbom.data).unwrap();
+        assert_eq!(document["spdxVersion"], "SPDX-2.3");
+        assert_eq!(document["creationInfo"]["created"], CREATED);
+        assert_eq!(
+            document["packages"],
+            serde_json::json!([
+                {
+                    "name": "libbar++",
+                    "SPDXID": "SPDXRef-Package-libbar---02bae192",
+                    "versionInfo": "1.0+dfsg-1",
+                    "downloadLocation": "http://archive.ubuntu.com/ubuntu/pool/main/libbar++_1.0+dfsg-1_amd64.deb",
+                    "filesAnalyzed": false,
+                    "checksums": [{ "algorithm": "SHA256", "checksumValue": "abc123" }],
+                    "licenseConcluded": "NOASSERTION",
+                    "licenseDeclared": "NOASSERTION",
+                    "copyrightText": "NOASSERTION",
+                    "externalRefs": [{
+                        "referenceCategory": "PACKAGE-MANAGER",
+                        "referenceType": "purl",
+                        "referenceLocator": "pkg:deb/ubuntu/libbar%2B%2B@1.0%2Bdfsg-1?arch=amd64&distro=ubuntu-24.04"
+                    }]
+                },
+                {
+                    "name": "libfoo1",
+                    "SPDXID": "SPDXRef-Package-libfoo1",
+                    "versionInfo": "1.0+dfsg-1",
+                    "downloadLocation": "http://archive.ubuntu.com/ubuntu/pool/main/libfoo1_1.0+dfsg-1_amd64.deb",
+                    "filesAnalyzed": false,
+                    "checksums": [{ "algorithm": "SHA256", "checksumValue": "abc123" }],
+                    "licenseConcluded": "NOASSERTION",
+                    "licenseDeclared": "GPL-2.0-or-later OR LicenseRef-Foo",
+                    "copyrightText": "NOASSERTION",
+                    "externalRefs": [{
+                        "referenceCategory": "PACKAGE-MANAGER",
+                        "referenceType": "purl",
+                        "referenceLocator": "pkg:deb/ubuntu/libfoo1@1.0%2Bdfsg-1?arch=amd64&distro=ubuntu-24.04"
+                    }]
+                }
+            ])
+        );
+        assert_eq!(
+            document["hasExtractedLicensingInfos"],
+            serde_json::json!([{
+                "licenseId": "LicenseRef-Foo",
+                "name": "Foo",
+                "extractedText": "Do what you like."
+            }])
+        );
+    }
+
+    #[test]
+    fn test_create_spdx_sbom_for_foreign_package() {
+        let distro = Distro {
+            name: "ubuntu".to_string(),
+            version: "24.04".to_string(),
+            codename: DistroCodename::from("noble"),
+            architecture: ArchitectureName::AMD_64,
+        };
+        let install_dir = tempfile::tempdir().unwrap();
+        let doc_dir = install_dir.path().join("usr/share/doc/libc6");
+        fs::create_dir_all(&doc_dir).unwrap();
+        fs::write(
+            doc_dir.join("copyright"),
+            indoc::indoc! { "
+                Format: https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/
+
+                Files: *
+                Copyright: 2024, Someone
+                License: LGPL-2.1+
+            " },
+        )
+        .unwrap();
+
+        let sbom = create_spdx_sbom(
+            &distro,
+            &[RepositoryPackage {
+                filename: "pool/main/libc6_1.0+dfsg-1_i386.deb".to_string(),
+                ..package("libc6:i386")
+            }],
+            install_dir.path(),
+        )
+        .unwrap();
+
+        let document = serde_json::from_slice::<serde_json::Value>(&sbom.data).unwrap();
+        assert_eq!(document["packages"][0]["name"], "libc6:i386");
+        assert_eq!(
+            document["packages"][0]["licenseDeclared"],
+            "LGPL-2.1-or-later"
+        );
+    }
+
+    #[test]
+    fn test_spdx_id_string() {
+        assert_eq!(spdx_id_string("libfoo1.2-dev"), "libfoo1.2-dev");
+        assert_eq!(spdx_id_string("libbar++"), "libbar---02bae192");
+        assert_ne!(spdx_id_string("libbar++"), spdx_id_string("libbar+-"));
+        assert_ne!(spdx_id_string("libbar++"), spdx_id_string("libbar--"));
+    }
+
+    #[test]
+    fn test_spdx_expression() {
+        assert_eq!(
+            spdx_expression("GPL-2+ or Artistic"),
+            ("GPL-2.0-or-later OR Artistic-1.0".to_string(), vec![])
+        );
+        assert_eq!(
+            spdx_expression("LGPL-2.1 and Expat"),
+            ("LGPL-2.1-only AND MIT".to_string(), vec![])
+        );
+        assert_eq!(
+            spdx_expression("public-domain"),
+            (
+                "LicenseRef-public-domain".to_string(),
+                vec![(
+                    "LicenseRef-public-domain".to_string(),
+                    "public-domain".to_string()
+                )]
+            )
+        );
+        assert_eq!(
+            spdx_expression("GPL-2+ with OpenSSL exception"),
+            (
+                "LicenseRef-GPL-2--with-OpenSSL-exception-a0de130f".to_string(),
+                vec![(
+                    "LicenseRef-GPL-2--with-OpenSSL-exception-a0de130f".to_string(),
+                    "GPL-2+ with OpenSSL exception".to_string()
+                )]
+            )
+        );
+    }
+
+    #[test]
+    fn test_license_expression_combines_file_licenses() {
+        let copyright = Copyright {
+            licenses: vec!["GPL-2+ or Artistic".to_string(), "BSD-3-clause".to_string()],
+            ..Copyright::default()
+        };
+        assert_eq!(
+            license_expression(&copyright, &mut BTreeMap::new()),
+            "(GPL-2.0-or-later OR Artistic-1.0) AND BSD-3-Clause"
+        );
+    }
+}
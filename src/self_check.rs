@@ -0,0 +1,222 @@
+use std::collections::BTreeSet;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use indoc::formatdoc;
+use libcnb::layer_env::{LayerEnv, Scope};
+
+use crate::debian::Distro;
+
+// The name of the exec.d program written to the packages layer when `self_check` is enabled.
+pub(crate) const SELF_CHECK_PROGRAM_NAME: &str = "deb-packages-check";
+
+// Installed packages are linked against the libraries of the distribution they were built for so
+// an image whose run image was swapped for another distribution (e.g.; rebased onto a different
+// stack) fails in confusing ways at runtime. The self-check runs as an exec.d program when the
+// container starts and exits with an error naming the problem when the run image isn't the
+// distribution the packages were installed for, a path exported by the layer is missing, or a
+// binary installed by the packages isn't executable.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct SelfCheck {
+    pub(crate) distro_id: String,
+    pub(crate) distro_codename: String,
+    pub(crate) paths: BTreeSet<PathBuf>,
+    pub(crate) binaries: BTreeSet<PathBuf>,
+}
+
+impl SelfCheck {
+    pub(crate) fn new(distro: &Distro, install_path: &Path, layer_env: &LayerEnv) -> Self {
+        let launch_env = layer_env.apply_to_empty(Scope::Launch);
+        let paths = ["PATH", "LD_LIBRARY_PATH"]
+            .into_iter()
+            .filter_map(|name| launch_env.get(name))
+            .flat_map(|value| std::env::split_paths(&value).collect::<Vec<_>>())
+            .filter(|path| path.starts_with(install_path) && path.is_dir())
+            .collect();
+
+        let binaries = ["bin", "usr/bin", "usr/sbin"]
+            .into_iter()
+            .filter_map(|bin_dir| std::fs::read_dir(install_path.join(bin_dir)).ok())
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| is_executable(path))
+            .collect();
+
+        SelfCheck {
+            distro_id: distro.name.clone(),
+            distro_codename: distro.codename.to_string(),
+            paths,
+            binaries,
+        }
+    }
+
+    pub(crate) fn script(&self) -> String {
+        let expected = format!("{} {}", self.distro_id, self.distro_codename);
+        formatdoc! { r#"
+            #!/bin/sh
+            # Written by the Heroku .deb Packages buildpack when `self_check` is enabled.
+            status=0
+            fail() {{ echo "deb-packages-check: $1" >&2; status=1; }}
+
+            id=$(. /etc/os-release 2>/dev/null && echo "$ID $VERSION_CODENAME")
+            if [ "$id" != {quoted_expected} ]; then
+              fail "packages were installed for {expected} but the run image is ${{id:-unknown}}"
+            fi
+
+            for path in {paths}; do
+              [ -d "$path" ] || fail "missing exported path $path"
+            done
+
+            for binary in {binaries}; do
+              [ -x "$binary" ] || fail "binary $binary is missing or not executable"
+            done
+
+            if [ "$status" -ne 0 ]; then
+              echo "deb-packages-check: rebuild the image against the run image it's deployed with" >&2
+            fi
+            exit "$status"
+        "#,
+            quoted_expected = quote(&expected),
+            paths = quote_all(&self.paths),
+            binaries = quote_all(&self.binaries),
+        }
+    }
+}
+
+// Symlinks are checked through to their target since alternatives are installed as symlinks.
+fn is_executable(path: &Path) -> bool {
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+fn quote_all(paths: &BTreeSet<PathBuf>) -> String {
+    paths
+        .iter()
+        .map(|path| quote(&path.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// The script is written outside the layer and copied into its `exec.d` directory by
+// `LayerRef::write_exec_d_programs`.
+pub(crate) fn write_self_check_script(
+    self_check: &SelfCheck,
+    script_path: &Path,
+) -> std::io::Result<()> {
+    std::fs::write(script_path, self_check.script())?;
+    std::fs::set_permissions(script_path, std::fs::Permissions::from_mode(0o755))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::process::Command;
+
+    use libcnb::layer_env::ModificationBehavior;
+
+    use crate::debian::{ArchitectureName, DistroCodename};
+
+    use super::*;
+
+    fn distro() -> Distro {
+        Distro {
+            name: "ubuntu".to_string(),
+            version: "24.04".to_string(),
+            codename: DistroCodename::from("noble"),
+            architecture: ArchitectureName::AMD_64,
+        }
+    }
+
+    #[test]
+    fn test_self_check() {
+        let install_dir = tempfile::tempdir().unwrap();
+        let install_path = install_dir.path();
+        fs::create_dir_all(install_path.join("usr/bin")).unwrap();
+        fs::create_dir_all(install_path.join("usr/lib")).unwrap();
+        fs::write(install_path.join("usr/bin/curl"), "").unwrap();
+        fs::set_permissions(
+            install_path.join("usr/bin/curl"),
+            fs::Permissions::from_mode(0o755),
+        )
+        .unwrap();
+        fs::write(install_path.join("usr/bin/README"), "").unwrap();
+
+        let mut layer_env = LayerEnv::new();
+        layer_env.insert(
+            Scope::All,
+            ModificationBehavior::Override,
+            "PATH",
+            format!(
+                "{}:{}",
+                install_path.join("usr/bin").display(),
+                install_path.join("bin").display()
+            ),
+        );
+        layer_env.insert(
+            Scope::Build,
+            ModificationBehavior::Override,
+            "LD_LIBRARY_PATH",
+            install_path.join("usr/lib"),
+        );
+
+        let self_check = SelfCheck::new(&distro(), install_path, &layer_env);
+        assert_eq!(
+            self_check.paths,
+            BTreeSet::from([install_path.join("usr/bin")])
+        );
+        assert_eq!(
+            self_check.binaries,
+            BTreeSet::from([install_path.join("usr/bin/curl")])
+        );
+    }
+
+    #[test]
+    fn test_self_check_script() {
+        let install_dir = tempfile::tempdir().unwrap();
+        let install_path = install_dir.path();
+        let self_check = SelfCheck {
+            distro_id: "ubuntu".to_string(),
+            distro_codename: "noble".to_string(),
+            paths: BTreeSet::from([install_path.join("usr/bin")]),
+            binaries: BTreeSet::from([install_path.join("usr/bin/it's-missing")]),
+        };
+        let script_path = install_path.join(SELF_CHECK_PROGRAM_NAME);
+        write_self_check_script(&self_check, &script_path).unwrap();
+
+        let output = Command::new(&script_path).output().unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success());
+        assert!(stderr.contains(&format!(
+            "missing exported path {}",
+            install_path.join("usr/bin").display()
+        )));
+        assert!(stderr.contains(&format!(
+            "binary {} is missing or not executable",
+            install_path.join("usr/bin/it's-missing").display()
+        )));
+        assert!(stderr.contains("rebuild the image"));
+    }
+
+    #[test]
+    fn test_self_check_script_without_paths() {
+        let install_dir = tempfile::tempdir().unwrap();
+        let self_check = SelfCheck {
+            distro_id: "ubuntu".to_string(),
+            distro_codename: "noble".to_string(),
+            paths: BTreeSet::new(),
+            binaries: BTreeSet::new(),
+        };
+        let script_path = install_dir.path().join(SELF_CHECK_PROGRAM_NAME);
+        write_self_check_script(&self_check, &script_path).unwrap();
+
+        let output = Command::new(&script_path).output().unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!stderr.contains("exported path"));
+        assert!(!stderr.contains("not executable"));
+    }
+}
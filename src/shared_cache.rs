@@ -0,0 +1,488 @@
+use std::fmt::{Display, Formatter};
+use std::io::{ErrorKind, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+use tokio::fs::{
+    create_dir_all, read_dir, read_to_string, remove_dir_all, remove_file, rename,
+    symlink_metadata, try_exists, write, File as AsyncFile,
+};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio_tar::{Archive as TarArchive, Builder as TarBuilder};
+
+use crate::config::{ExistingFilesPolicy, MaintainerScripts};
+use crate::debian::{Distro, RepositoryPackage};
+
+// The packages layer can be exported to a directory shared by the builds of different applications
+// (e.g.; a volume mounted into the build container) so that a fleet of applications installing the
+// same packages only downloads them once. Each export is an archive of the extracted packages named
+// after a key derived from the resolved package set so it's only imported by builds that resolve to
+// exactly the same packages for the same distribution.
+pub(crate) fn package_set_hash(distro: &Distro, packages: &[RepositoryPackage]) -> String {
+    let mut package_checksums = packages
+        .iter()
+        .map(|package| {
+            format!(
+                "{} {}:{}",
+                package.name,
+                package.checksum_algorithm.release_field(),
+                package.checksum
+            )
+        })
+        .collect::<Vec<_>>();
+    package_checksums.sort();
+
+    let mut hasher = Sha256::new();
+    hasher.update(format!(
+        "{}/{}/{}\n",
+        distro.name, distro.codename, distro.architecture
+    ));
+    for package_checksum in package_checksums {
+        hasher.update(package_checksum);
+        hasher.update("\n");
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+// The extracted files also depend on which postinst scripts were run (and how their failures were
+// handled) and on how files that already existed were treated, so builds that differ in either
+// never share an export.
+pub(crate) fn shared_cache_key(
+    distro: &Distro,
+    packages: &[RepositoryPackage],
+    maintainer_scripts: &MaintainerScripts,
+    existing_files_policy: ExistingFilesPolicy,
+) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(package_set_hash(distro, packages));
+    hasher.update("\n");
+    for (package_name, policy) in maintainer_scripts.policies() {
+        hasher.update(format!("run_scripts {package_name}:{policy}\n"));
+    }
+    hasher.update(format!("existing_files {existing_files_policy}\n"));
+    format!("{:x}", hasher.finalize())
+}
+
+pub(crate) fn shared_cache_archive_path(shared_cache_dir: &Path, cache_key: &str) -> PathBuf {
+    shared_cache_dir.join(format!("{cache_key}.tar"))
+}
+
+// Returns `false` when no other build has exported this package set yet.
+//
+// The archive is only used when it matches the digest recorded when it was exported and is
+// unpacked into a staging directory first so a failed import never leaves a partially filled
+// layer behind for the packages to be downloaded into.
+pub(crate) async fn import_packages(
+    shared_cache_dir: &Path,
+    cache_key: &str,
+    install_path: &Path,
+) -> Result<bool, SharedCacheError> {
+    let archive_path = shared_cache_archive_path(shared_cache_dir, cache_key);
+    let digest_path = shared_cache_digest_path(shared_cache_dir, cache_key);
+
+    let expected_digest = match read_to_string(&digest_path).await {
+        Ok(expected_digest) => expected_digest.trim().to_string(),
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(SharedCacheError::ReadArchive(digest_path, e)),
+    };
+
+    let mut archive_file = match AsyncFile::open(&archive_path).await {
+        Ok(archive_file) => archive_file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(SharedCacheError::ReadArchive(archive_path, e)),
+    };
+
+    let actual_digest = match archive_digest(&mut archive_file).await {
+        Ok(actual_digest) => actual_digest,
+        Err(e) => return Err(SharedCacheError::ReadArchive(archive_path, e)),
+    };
+    if actual_digest != expected_digest {
+        return Err(SharedCacheError::DigestMismatch {
+            path: archive_path,
+            expected: expected_digest,
+            actual: actual_digest,
+        });
+    }
+
+    let staging_dir = tempfile::Builder::new()
+        .prefix(".shared-cache-import")
+        .tempdir_in(install_path.parent().unwrap_or(install_path))
+        .map_err(|e| SharedCacheError::ReadArchive(archive_path.clone(), e))?;
+
+    let import_result = match archive_file.seek(SeekFrom::Start(0)).await {
+        Ok(_) => {
+            TarArchive::new(archive_file)
+                .unpack(staging_dir.path())
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    let import_result = match import_result {
+        Ok(()) => move_entries(staging_dir.path(), install_path).await,
+        Err(e) => Err(e),
+    };
+    import_result.map_err(|e| SharedCacheError::ReadArchive(archive_path, e))?;
+
+    Ok(true)
+}
+
+// Moves the unpacked files into the layer, removing the ones already moved if any of them fail.
+async fn move_entries(from: &Path, to: &Path) -> std::io::Result<()> {
+    let mut moved = vec![];
+    let mut move_result = Ok(());
+
+    let mut entries = read_dir(from).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let destination = to.join(entry.file_name());
+        if let Err(e) = rename(entry.path(), &destination).await {
+            move_result = Err(e);
+            break;
+        }
+        moved.push(destination);
+    }
+
+    if move_result.is_err() {
+        for path in moved {
+            let _ = match symlink_metadata(&path).await {
+                Ok(metadata) if metadata.is_dir() => remove_dir_all(&path).await,
+                _ => remove_file(&path).await,
+            };
+        }
+    }
+
+    move_result
+}
+
+async fn archive_digest(archive_file: &mut AsyncFile) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = archive_file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn shared_cache_digest_path(shared_cache_dir: &Path, cache_key: &str) -> PathBuf {
+    shared_cache_dir.join(format!("{cache_key}.tar.sha256"))
+}
+
+// The archive is written to a temporary file first and then renamed so that builds running at the
+// same time never import a partially written archive. Its digest is recorded before the archive is
+// renamed into place so an archive is never visible without one.
+pub(crate) async fn export_packages(
+    shared_cache_dir: &Path,
+    cache_key: &str,
+    install_path: &Path,
+) -> Result<(), SharedCacheError> {
+    let archive_path = shared_cache_archive_path(shared_cache_dir, cache_key);
+    let digest_path = shared_cache_digest_path(shared_cache_dir, cache_key);
+    if try_exists(&archive_path).await.unwrap_or(false)
+        && try_exists(&digest_path).await.unwrap_or(false)
+    {
+        return Ok(());
+    }
+
+    create_dir_all(shared_cache_dir)
+        .await
+        .map_err(|e| SharedCacheError::WriteArchive(shared_cache_dir.to_path_buf(), e))?;
+
+    let temp_archive_path =
+        shared_cache_dir.join(format!(".{cache_key}.{pid}.tmp", pid = std::process::id()));
+    let temp_digest_path = shared_cache_dir.join(format!(
+        ".{cache_key}.sha256.{pid}.tmp",
+        pid = std::process::id()
+    ));
+
+    let write_result = write_archive(&temp_archive_path, install_path).await;
+    let write_result = match write_result {
+        Ok(digest) => write(&temp_digest_path, digest).await,
+        Err(e) => Err(e),
+    };
+    let write_result = match write_result {
+        Ok(()) => rename(&temp_digest_path, &digest_path).await,
+        Err(e) => Err(e),
+    };
+    let write_result = match write_result {
+        Ok(()) => rename(&temp_archive_path, &archive_path).await,
+        Err(e) => Err(e),
+    };
+
+    if let Err(e) = write_result {
+        let _ = remove_file(&temp_archive_path).await;
+        let _ = remove_file(&temp_digest_path).await;
+        return Err(SharedCacheError::WriteArchive(archive_path, e));
+    }
+
+    Ok(())
+}
+
+// Returns the digest of the written archive.
+async fn write_archive(archive_path: &Path, install_path: &Path) -> std::io::Result<String> {
+    let archive_file = AsyncFile::create(archive_path).await?;
+    let mut builder = TarBuilder::new(archive_file);
+    builder.follow_symlinks(false);
+    builder.append_dir_all(".", install_path).await?;
+    let mut archive_file = builder.into_inner().await?;
+    archive_file.flush().await?;
+    archive_file.sync_all().await?;
+
+    archive_digest(&mut AsyncFile::open(archive_path).await?).await
+}
+
+#[derive(Debug)]
+pub(crate) enum SharedCacheError {
+    ReadArchive(PathBuf, std::io::Error),
+    DigestMismatch {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+    WriteArchive(PathBuf, std::io::Error),
+}
+
+impl Display for SharedCacheError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SharedCacheError::ReadArchive(path, e) => {
+                write!(f, "Failed to import {}: {e}", path.display())
+            }
+            SharedCacheError::DigestMismatch {
+                path,
+                expected,
+                actual,
+            } => {
+                write!(
+                    f,
+                    "Failed to import {}: the archive digest {actual} doesn't match the exported digest {expected}",
+                    path.display()
+                )
+            }
+            SharedCacheError::WriteArchive(path, e) => {
+                write!(f, "Failed to export {}: {e}", path.display())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::os::unix::fs::symlink;
+    use std::str::FromStr;
+
+    use indexmap::IndexSet;
+
+    use crate::config::{RequestedPackage, ScriptFailurePolicy};
+    use crate::debian::{ArchitectureName, ChecksumAlgorithm, DistroCodename, PackageName};
+    use crate::test_support::create_repository_package;
+
+    use super::*;
+
+    fn distro(architecture: ArchitectureName) -> Distro {
+        Distro {
+            name: "ubuntu".to_string(),
+            version: "24.04".to_string(),
+            codename: DistroCodename::from("noble"),
+            architecture,
+        }
+    }
+
+    fn package(name: &str, checksum: &str) -> RepositoryPackage {
+        create_repository_package()
+            .name(name)
+            .version("1.0")
+            .repository_uri("http://archive.ubuntu.com/ubuntu")
+            .filename(&format!("pool/main/{name}.deb"))
+            .checksum(checksum)
+            .call()
+    }
+
+    #[test]
+    fn test_package_set_hash() {
+        let packages = [package("curl", "abc"), package("libcurl4", "def")];
+        let reordered_packages = [package("libcurl4", "def"), package("curl", "abc")];
+        assert_eq!(
+            package_set_hash(&distro(ArchitectureName::AMD_64), &packages),
+            package_set_hash(&distro(ArchitectureName::AMD_64), &reordered_packages)
+        );
+        assert_ne!(
+            package_set_hash(&distro(ArchitectureName::AMD_64), &packages),
+            package_set_hash(&distro(ArchitectureName::ARM_64), &packages)
+        );
+        assert_ne!(
+            package_set_hash(&distro(ArchitectureName::AMD_64), &packages),
+            package_set_hash(
+                &distro(ArchitectureName::AMD_64),
+                &[package("curl", "abc"), package("libcurl4", "xyz")]
+            )
+        );
+        // the same value from a different hash algorithm is a different package
+        assert_ne!(
+            package_set_hash(&distro(ArchitectureName::AMD_64), &packages),
+            package_set_hash(
+                &distro(ArchitectureName::AMD_64),
+                &[
+                    package("curl", "abc"),
+                    RepositoryPackage {
+                        checksum_algorithm: ChecksumAlgorithm::Md5,
+                        ..package("libcurl4", "def")
+                    }
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn test_shared_cache_key() {
+        let packages = [package("ca-certificates-java", "abc")];
+        let maintainer_scripts = |policy| {
+            MaintainerScripts::new(&IndexSet::from([RequestedPackage {
+                name: PackageName::from_str("ca-certificates-java").unwrap(),
+                architecture: None,
+                skip_dependencies: false,
+                force: false,
+                env_scope: None,
+                optional: false,
+                maintainer_scripts: policy,
+                version: None,
+            }]))
+        };
+        let cache_key = shared_cache_key(
+            &distro(ArchitectureName::AMD_64),
+            &packages,
+            &maintainer_scripts(None),
+            ExistingFilesPolicy::Overwrite,
+        );
+        assert_eq!(
+            cache_key,
+            shared_cache_key(
+                &distro(ArchitectureName::AMD_64),
+                &packages,
+                &maintainer_scripts(None),
+                ExistingFilesPolicy::Overwrite,
+            )
+        );
+        assert_ne!(
+            cache_key,
+            shared_cache_key(
+                &distro(ArchitectureName::AMD_64),
+                &packages,
+                &maintainer_scripts(Some(ScriptFailurePolicy::Warn)),
+                ExistingFilesPolicy::Overwrite,
+            )
+        );
+        assert_ne!(
+            shared_cache_key(
+                &distro(ArchitectureName::AMD_64),
+                &packages,
+                &maintainer_scripts(Some(ScriptFailurePolicy::Warn)),
+                ExistingFilesPolicy::Overwrite,
+            ),
+            shared_cache_key(
+                &distro(ArchitectureName::AMD_64),
+                &packages,
+                &maintainer_scripts(Some(ScriptFailurePolicy::Error)),
+                ExistingFilesPolicy::Overwrite,
+            )
+        );
+        assert_ne!(
+            cache_key,
+            shared_cache_key(
+                &distro(ArchitectureName::AMD_64),
+                &packages,
+                &maintainer_scripts(None),
+                ExistingFilesPolicy::Skip,
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_and_import_packages() {
+        let shared_cache_dir = tempfile::tempdir().unwrap();
+        let export_dir = tempfile::tempdir().unwrap();
+        let import_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(export_dir.path().join("usr/bin")).unwrap();
+        fs::write(export_dir.path().join("usr/bin/curl"), "curl").unwrap();
+        symlink("curl", export_dir.path().join("usr/bin/curl-link")).unwrap();
+
+        assert!(
+            !import_packages(shared_cache_dir.path(), "hash", import_dir.path())
+                .await
+                .unwrap()
+        );
+
+        export_packages(shared_cache_dir.path(), "hash", export_dir.path())
+            .await
+            .unwrap();
+        assert!(
+            import_packages(shared_cache_dir.path(), "hash", import_dir.path())
+                .await
+                .unwrap()
+        );
+
+        assert_eq!(
+            fs::read_to_string(import_dir.path().join("usr/bin/curl")).unwrap(),
+            "curl"
+        );
+        assert_eq!(
+            fs::read_link(import_dir.path().join("usr/bin/curl-link")).unwrap(),
+            PathBuf::from("curl")
+        );
+        let mut shared_cache_files = fs::read_dir(shared_cache_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect::<Vec<_>>();
+        shared_cache_files.sort();
+        assert_eq!(shared_cache_files, vec!["hash.tar", "hash.tar.sha256"]);
+    }
+
+    #[tokio::test]
+    async fn test_import_packages_rejects_modified_archive() {
+        let shared_cache_dir = tempfile::tempdir().unwrap();
+        let export_dir = tempfile::tempdir().unwrap();
+        let layers_dir = tempfile::tempdir().unwrap();
+        let import_dir = layers_dir.path().join("packages");
+        fs::create_dir_all(&import_dir).unwrap();
+        fs::create_dir_all(export_dir.path().join("usr/bin")).unwrap();
+        fs::write(export_dir.path().join("usr/bin/curl"), "curl").unwrap();
+
+        export_packages(shared_cache_dir.path(), "hash", export_dir.path())
+            .await
+            .unwrap();
+        let archive_path = shared_cache_archive_path(shared_cache_dir.path(), "hash");
+        let mut archive = fs::read(&archive_path).unwrap();
+        archive.truncate(archive.len() / 2);
+        fs::write(&archive_path, archive).unwrap();
+
+        assert!(matches!(
+            import_packages(shared_cache_dir.path(), "hash", &import_dir).await,
+            Err(SharedCacheError::DigestMismatch { .. })
+        ));
+        assert_eq!(fs::read_dir(&import_dir).unwrap().count(), 0);
+        assert_eq!(fs::read_dir(layers_dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_import_packages_without_digest() {
+        let shared_cache_dir = tempfile::tempdir().unwrap();
+        let export_dir = tempfile::tempdir().unwrap();
+        let import_dir = tempfile::tempdir().unwrap();
+        fs::write(export_dir.path().join("curl"), "curl").unwrap();
+
+        export_packages(shared_cache_dir.path(), "hash", export_dir.path())
+            .await
+            .unwrap();
+        fs::remove_file(shared_cache_digest_path(shared_cache_dir.path(), "hash")).unwrap();
+
+        assert!(
+            !import_packages(shared_cache_dir.path(), "hash", import_dir.path())
+                .await
+                .unwrap()
+        );
+        assert_eq!(fs::read_dir(import_dir.path()).unwrap().count(), 0);
+    }
+}
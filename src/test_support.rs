@@ -0,0 +1,47 @@
+use bon::builder;
+
+use crate::debian::{ChecksumAlgorithm, RepositoryPackage, RepositoryUri};
+
+pub(crate) const DEFAULT_VERSION: &str = "1.0.0";
+
+// Builds a package for tests so they only need to set the fields they care about.
+#[builder]
+pub(crate) fn create_repository_package(
+    name: &str,
+    version: Option<&str>,
+    repository_uri: Option<&str>,
+    filename: Option<&str>,
+    checksum: Option<&str>,
+    checksum_algorithm: Option<ChecksumAlgorithm>,
+    provides: Option<Vec<&str>>,
+    section: Option<&str>,
+    task: Option<&str>,
+    size: Option<u64>,
+    depends: Option<Vec<&RepositoryPackage>>,
+    pre_depends: Option<Vec<&RepositoryPackage>>,
+) -> RepositoryPackage {
+    let join_deps = |vs: Vec<&RepositoryPackage>| {
+        vs.iter()
+            .map(|v| v.name.as_str())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    RepositoryPackage {
+        name: name.to_string(),
+        version: version.unwrap_or(DEFAULT_VERSION).to_string(),
+        provides: provides.map(|vs| vs.join(",")),
+        size,
+        section: section.map(ToString::to_string),
+        phased_update_percentage: None,
+        task: task.map(ToString::to_string),
+        not_automatic: false,
+        suite: String::new(),
+        repository_uri: RepositoryUri::from(repository_uri.unwrap_or_default()),
+        checksum: checksum.unwrap_or_default().to_string(),
+        checksum_algorithm: checksum_algorithm.unwrap_or_default(),
+        download_url_template: None,
+        depends: depends.map(join_deps),
+        pre_depends: pre_depends.map(join_deps),
+        filename: filename.unwrap_or_default().to_string(),
+    }
+}
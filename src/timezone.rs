@@ -0,0 +1,105 @@
+use std::path::{Component, Path, PathBuf};
+
+// tzdata installs the compiled time zones here. glibc only looks in `/usr/share/zoneinfo` unless
+// `TZDIR` is set so slim base images without tzdata need it pointed at the layer.
+pub(crate) fn zoneinfo_path(install_path: &Path) -> Option<PathBuf> {
+    Some(install_path.join("usr/share/zoneinfo")).filter(|path| path.is_dir())
+}
+
+// `TZ` is either the name of a time zone file (optionally prefixed with `:`) like
+// `America/New_York` or a POSIX time zone specification like `EST5EDT` or `<+0330>-3:30` which
+// doesn't need any zone files.
+pub(crate) fn resolves_timezone(zoneinfo_dir: &Path, timezone: &str) -> bool {
+    let timezone = timezone.trim();
+    match timezone.strip_prefix(':') {
+        Some(zone_name) => is_zone_file(zoneinfo_dir, zone_name),
+        None => is_posix_timezone(timezone) || is_zone_file(zoneinfo_dir, timezone),
+    }
+}
+
+fn is_zone_file(zoneinfo_dir: &Path, zone_name: &str) -> bool {
+    let zone_name = Path::new(zone_name);
+    // absolute paths are read as-is by glibc and are outside the layer
+    if zone_name.is_absolute() {
+        return zone_name.is_file();
+    }
+    zone_name
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+        && zoneinfo_dir.join(zone_name).is_file()
+}
+
+// The standard zone name is either quoted with `<>` or at least three letters and must be followed
+// by an offset. Zone file names like `Etc/GMT+5` don't match since the offset follows a `/`.
+fn is_posix_timezone(timezone: &str) -> bool {
+    let offset = if let Some(quoted) = timezone.strip_prefix('<') {
+        quoted.split_once('>').map(|(_, offset)| offset)
+    } else {
+        let name_length = timezone
+            .chars()
+            .take_while(char::is_ascii_alphabetic)
+            .count();
+        (name_length >= 3).then(|| &timezone[name_length..])
+    };
+
+    offset.is_some_and(|offset| {
+        offset
+            .trim_start_matches(['+', '-'])
+            .starts_with(|c: char| c.is_ascii_digit())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_zoneinfo_path() {
+        let install_dir = tempfile::tempdir().unwrap();
+        assert_eq!(zoneinfo_path(install_dir.path()), None);
+
+        fs::create_dir_all(install_dir.path().join("usr/share/zoneinfo")).unwrap();
+        assert_eq!(
+            zoneinfo_path(install_dir.path()),
+            Some(install_dir.path().join("usr/share/zoneinfo"))
+        );
+    }
+
+    #[test]
+    fn test_resolves_timezone() {
+        let zoneinfo_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(zoneinfo_dir.path().join("America")).unwrap();
+        fs::write(zoneinfo_dir.path().join("America/New_York"), "").unwrap();
+        fs::write(zoneinfo_dir.path().join("UTC"), "").unwrap();
+
+        for timezone in [
+            "America/New_York",
+            ":America/New_York",
+            "UTC",
+            "EST5EDT",
+            "CET-1CEST,M3.5.0,M10.5.0/3",
+            "<+0330>-3:30",
+        ] {
+            assert!(
+                resolves_timezone(zoneinfo_dir.path(), timezone),
+                "{timezone} should resolve"
+            );
+        }
+
+        for timezone in [
+            "America/Boston",
+            ":EST5EDT",
+            "Europe",
+            "../zoneinfo/UTC",
+            "Etc/GMT+5",
+            "",
+        ] {
+            assert!(
+                !resolves_timezone(zoneinfo_dir.path(), timezone),
+                "{timezone} should not resolve"
+            );
+        }
+    }
+}
@@ -0,0 +1,763 @@
+use std::io::Stdout;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use bullet_stream::state::Bullet;
+use bullet_stream::{style, Print};
+use indexmap::IndexSet;
+use libcnb::build::BuildContext;
+use reqwest_middleware::ClientWithMiddleware;
+
+use crate::config::{Preset, RequestedPackage, RequestedPackagePattern};
+use crate::create_package_index::{
+    create_package_index_for_architecture, CacheFreshness, DistroSourceOptions, ForeignPackageIndex,
+};
+use crate::debian::{
+    ArchitectureName, Distro, DistroCodename, PackageIndex, PackageName, SupportedDistro,
+};
+use crate::pgp::SigningKeys;
+use crate::{BuildpackResult, DebianPackagesBuildpack, DebianPackagesBuildpackError};
+
+// Checks that every requested package can be found for the target architecture before resolving
+// dependencies. Optional packages are left out since they're allowed to be missing. When a package is missing, the indexes for the other architectures supported by
+// the distribution are checked so the error can explain that the package only exists elsewhere
+// instead of reporting that it doesn't exist at all. If a missing package isn't found for any
+// other architecture, dependency resolution reports it as not found with suggestions.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn validate_requested_packages(
+    context: &Arc<BuildContext<DebianPackagesBuildpack>>,
+    client: &ClientWithMiddleware,
+    distro: &Distro,
+    package_index: &PackageIndex,
+    requested_packages: &IndexSet<RequestedPackage>,
+    signing_keys: &SigningKeys,
+    distro_source_options: DistroSourceOptions<'_>,
+    cache_freshness: CacheFreshness,
+    log: Print<Bullet<Stdout>>,
+) -> BuildpackResult<Print<Bullet<Stdout>>> {
+    let missing_packages = find_missing_packages(package_index, requested_packages);
+    let other_architectures =
+        distro.get_other_architectures(&context.buildpack_descriptor.metadata.distros);
+
+    if missing_packages.is_empty() || other_architectures.is_empty() {
+        return Ok(log);
+    }
+
+    let mut log = log.bullet(format!(
+        "Checking other architectures for {}",
+        missing_packages
+            .iter()
+            .map(style::value)
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+
+    for architecture in other_architectures {
+        let timer = log.start_timer(format!("Indexing packages for {architecture}"));
+        let other_package_index = create_package_index_for_architecture(
+            context,
+            client,
+            distro,
+            &architecture,
+            signing_keys,
+            distro_source_options,
+            cache_freshness,
+        )
+        .await?;
+        log = timer.done();
+
+        if let Some(package) = missing_packages
+            .iter()
+            .find(|package| is_available(&other_package_index, package))
+        {
+            Err(
+                ValidateRequestedPackagesError::PackageNotAvailableForArchitecture {
+                    package: package.clone(),
+                    architecture: distro.architecture.clone(),
+                    available_architecture: architecture,
+                },
+            )?;
+        }
+    }
+
+    Ok(log.done())
+}
+
+// Returns the foreign architectures packages were requested for (e.g.; `i386` for `libc6:i386`) in
+// the order they're first requested. Packages qualified with the target architecture are the same as
+// unqualified ones so their architecture is removed. Every foreign architecture must have sources
+// for the distribution.
+pub(crate) fn foreign_architectures(
+    distro: &Distro,
+    supported_distros: &[SupportedDistro],
+    requested_packages: &mut IndexSet<RequestedPackage>,
+    package_patterns: &mut IndexSet<RequestedPackagePattern>,
+) -> Result<Vec<ArchitectureName>, ValidateRequestedPackagesError> {
+    *requested_packages = std::mem::take(requested_packages)
+        .into_iter()
+        .map(|mut requested_package| {
+            if requested_package.architecture.as_ref() == Some(&distro.architecture) {
+                requested_package.architecture = None;
+            }
+            requested_package
+        })
+        .collect();
+    *package_patterns = std::mem::take(package_patterns)
+        .into_iter()
+        .map(|mut package_pattern| {
+            if package_pattern.architecture.as_ref() == Some(&distro.architecture) {
+                package_pattern.architecture = None;
+            }
+            package_pattern
+        })
+        .collect();
+
+    let available_architectures = distro.get_other_architectures(supported_distros);
+    let mut foreign_architectures = vec![];
+    let requested_architectures = requested_packages
+        .iter()
+        .map(|requested_package| {
+            (
+                requested_package.qualified_name(),
+                &requested_package.architecture,
+            )
+        })
+        .chain(package_patterns.iter().map(|package_pattern| {
+            (
+                package_pattern.qualified_pattern(),
+                &package_pattern.architecture,
+            )
+        }));
+    for (package, architecture) in requested_architectures {
+        let Some(architecture) = architecture else {
+            continue;
+        };
+        if !available_architectures.contains(architecture) {
+            Err(
+                ValidateRequestedPackagesError::ForeignArchitectureNotAvailable {
+                    package,
+                    architecture: architecture.clone(),
+                    available_architectures: available_architectures.clone(),
+                },
+            )?;
+        }
+        if !foreign_architectures.contains(architecture) {
+            foreign_architectures.push(architecture.clone());
+        }
+    }
+    Ok(foreign_architectures)
+}
+
+// Adds the packages in each configured task (e.g.; `ubuntu-server`) to the requested packages.
+// Packages that are already requested keep their options (e.g.; `force`).
+pub(crate) fn expand_tasks(
+    package_index: &PackageIndex,
+    tasks: &[String],
+    requested_packages: &mut IndexSet<RequestedPackage>,
+    log: Print<Bullet<Stdout>>,
+) -> BuildpackResult<Print<Bullet<Stdout>>> {
+    if tasks.is_empty() {
+        return Ok(log);
+    }
+
+    let mut log = log.bullet("Expanding tasks");
+    for task in tasks {
+        let package_names = package_index.get_task_packages(task);
+        if package_names.is_empty() {
+            Err(ValidateRequestedPackagesError::TaskNotFound(task.clone()))?;
+        }
+        log = log.sub_bullet(format!(
+            "Requesting {count} packages from {task}",
+            count = package_names.len(),
+            task = style::value(task)
+        ));
+        for package_name in package_names {
+            let Ok(name) = PackageName::from_str(package_name) else {
+                continue;
+            };
+            if !requested_packages.iter().any(|requested_package| {
+                requested_package.name == name && requested_package.architecture.is_none()
+            }) {
+                requested_packages.insert(RequestedPackage {
+                    name,
+                    architecture: None,
+                    skip_dependencies: false,
+                    force: false,
+                    env_scope: None,
+                    optional: false,
+                    maintainer_scripts: None,
+                    version: None,
+                });
+            }
+        }
+    }
+
+    Ok(log.done())
+}
+
+// Adds the packages in each configured preset (e.g.; `geo`) for the distribution to the requested
+// packages. Packages that are already requested keep their options (e.g.; `force`).
+pub(crate) fn expand_presets(
+    distro: &Distro,
+    presets: &[Preset],
+    requested_packages: &mut IndexSet<RequestedPackage>,
+    log: Print<Bullet<Stdout>>,
+) -> BuildpackResult<Print<Bullet<Stdout>>> {
+    if presets.is_empty() {
+        return Ok(log);
+    }
+
+    let mut log = log.bullet("Expanding presets");
+    for preset in presets {
+        let package_names = preset.packages(&distro.codename).ok_or_else(|| {
+            ValidateRequestedPackagesError::PresetNotAvailable {
+                preset: *preset,
+                codename: distro.codename.clone(),
+            }
+        })?;
+        log = log.sub_bullet(format!(
+            "Requesting {package_names} from {preset}",
+            package_names = package_names
+                .iter()
+                .map(style::value)
+                .collect::<Vec<_>>()
+                .join(", "),
+            preset = style::value(preset.to_string())
+        ));
+        for package_name in package_names {
+            let Ok(name) = PackageName::from_str(package_name) else {
+                continue;
+            };
+            if !requested_packages.iter().any(|requested_package| {
+                requested_package.name == name && requested_package.architecture.is_none()
+            }) {
+                requested_packages.insert(RequestedPackage {
+                    name,
+                    architecture: None,
+                    skip_dependencies: false,
+                    force: false,
+                    env_scope: None,
+                    optional: false,
+                    maintainer_scripts: None,
+                    version: None,
+                });
+            }
+        }
+    }
+
+    Ok(log.done())
+}
+
+// Adds every package with a name matching one of the requested patterns (e.g.; `libicu*`) to the
+// requested packages with the options given for the pattern. Patterns for a foreign architecture
+// are matched against the package index for it. Packages that are already requested keep their
+// options (e.g.; `force`). A pattern that doesn't match any package is an error unless it's
+// optional, as is one that matches more packages than its `max_matches`.
+pub(crate) fn expand_package_patterns(
+    package_index: &PackageIndex,
+    foreign_package_indexes: &[ForeignPackageIndex],
+    package_patterns: &IndexSet<RequestedPackagePattern>,
+    requested_packages: &mut IndexSet<RequestedPackage>,
+    log: Print<Bullet<Stdout>>,
+) -> BuildpackResult<Print<Bullet<Stdout>>> {
+    if package_patterns.is_empty() {
+        return Ok(log);
+    }
+
+    let mut log = log.bullet("Expanding package patterns");
+    for package_pattern in package_patterns {
+        let package_index = match &package_pattern.architecture {
+            Some(architecture) => {
+                &foreign_package_indexes
+                    .iter()
+                    .find(|foreign_package_index| {
+                        &foreign_package_index.architecture == architecture
+                    })
+                    .expect("A package index should be created for every foreign architecture")
+                    .package_index
+            }
+            None => package_index,
+        };
+        let package_names = package_index
+            .find_package_names(|package_name| package_pattern.pattern.matches(package_name));
+        if package_names.is_empty() {
+            if package_pattern.optional {
+                log = log.sub_bullet(format!(
+                    "Skipping optional pattern {pattern} since it doesn't match any packages",
+                    pattern = style::value(package_pattern.qualified_pattern())
+                ));
+                continue;
+            }
+            Err(ValidateRequestedPackagesError::PackagePatternNotMatched(
+                package_pattern.qualified_pattern(),
+            ))?;
+        }
+        if let Some(max_matches) = package_pattern.max_matches() {
+            if package_names.len() > max_matches {
+                Err(ValidateRequestedPackagesError::TooManyPatternMatches {
+                    pattern: package_pattern.qualified_pattern(),
+                    matched_packages: package_names.iter().map(ToString::to_string).collect(),
+                    max_matches,
+                })?;
+            }
+        }
+        log = log.sub_bullet(format!(
+            "Matched {pattern} to {count} {packages}: {package_names}",
+            pattern = style::value(package_pattern.qualified_pattern()),
+            count = package_names.len(),
+            packages = if package_names.len() == 1 {
+                "package"
+            } else {
+                "packages"
+            },
+            package_names = package_names
+                .iter()
+                .map(style::value)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        for package_name in package_names {
+            let Ok(name) = PackageName::from_str(package_name) else {
+                continue;
+            };
+            if !requested_packages.iter().any(|requested_package| {
+                requested_package.name == name
+                    && requested_package.architecture == package_pattern.architecture
+            }) {
+                requested_packages.insert(package_pattern.requested_package(name));
+            }
+        }
+    }
+
+    Ok(log.done())
+}
+
+fn find_missing_packages(
+    package_index: &PackageIndex,
+    requested_packages: &IndexSet<RequestedPackage>,
+) -> Vec<String> {
+    requested_packages
+        .iter()
+        .filter(|requested_package| !requested_package.optional)
+        // packages for a foreign architecture are checked against the package index for it when
+        // their dependencies are resolved
+        .filter(|requested_package| requested_package.architecture.is_none())
+        .map(|requested_package| requested_package.name.as_str())
+        .filter(|package| !is_available(package_index, package))
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn is_available(package_index: &PackageIndex, package: &str) -> bool {
+    package_index.get_package_names().contains(package)
+}
+
+#[derive(Debug)]
+pub(crate) enum ValidateRequestedPackagesError {
+    PackageNotAvailableForArchitecture {
+        package: String,
+        architecture: ArchitectureName,
+        available_architecture: ArchitectureName,
+    },
+    ForeignArchitectureNotAvailable {
+        package: String,
+        architecture: ArchitectureName,
+        available_architectures: Vec<ArchitectureName>,
+    },
+    TaskNotFound(String),
+    PackagePatternNotMatched(String),
+    PresetNotAvailable {
+        preset: Preset,
+        codename: DistroCodename,
+    },
+    TooManyPatternMatches {
+        pattern: String,
+        matched_packages: Vec<String>,
+        max_matches: usize,
+    },
+}
+
+impl From<ValidateRequestedPackagesError> for libcnb::Error<DebianPackagesBuildpackError> {
+    fn from(value: ValidateRequestedPackagesError) -> Self {
+        Self::BuildpackError(DebianPackagesBuildpackError::ValidateRequestedPackages(
+            value,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bullet_stream::Print;
+
+    use super::*;
+    use libcnb::data::buildpack::ComponentBuildpackDescriptor;
+
+    use crate::config::{EnvScope, PackagePattern, PackageRegex};
+    use crate::debian::BuildpackMetadata;
+    use crate::test_support::create_repository_package;
+
+    #[test]
+    fn test_find_missing_packages() {
+        let mut package_index = PackageIndex::default();
+        package_index.add_package(
+            create_repository_package()
+                .name("libvips42t64")
+                .version("8.15.1-1.1build4")
+                .repository_uri("http://archive.ubuntu.com/ubuntu")
+                .filename("pool/universe/v/vips/libvips42t64_8.15.1-1.1build4_amd64.deb")
+                .checksum("abc123")
+                .provides(vec!["libvips42"])
+                .call(),
+        );
+
+        let requested_packages = [
+            ("libvips42t64", false),
+            ("libvips42", false),
+            ("intel-media-va-driver", false),
+            ("nvidia-vaapi-driver", true),
+        ]
+        .into_iter()
+        .map(|(name, optional)| RequestedPackage {
+            name: PackageName::from_str(name).unwrap(),
+            architecture: None,
+            skip_dependencies: false,
+            force: false,
+            env_scope: None,
+            optional,
+            maintainer_scripts: None,
+            version: None,
+        })
+        .collect();
+
+        assert_eq!(
+            find_missing_packages(&package_index, &requested_packages),
+            vec!["intel-media-va-driver".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_foreign_architectures() {
+        let distro = Distro {
+            name: "Ubuntu".to_string(),
+            version: "24.04".to_string(),
+            codename: "noble".into(),
+            architecture: ArchitectureName::AMD_64,
+        };
+        let supported_distros = toml::from_str::<ComponentBuildpackDescriptor<BuildpackMetadata>>(
+            include_str!("../buildpack.toml"),
+        )
+        .unwrap()
+        .metadata
+        .distros;
+
+        let mut requested_packages = ["curl:amd64", "libc6:i386", "libstdc++6:i386", "git"]
+            .into_iter()
+            .map(|name| RequestedPackage::from_str(name).unwrap())
+            .collect::<IndexSet<_>>();
+        let mut package_patterns = ["libicu*:amd64", "libgcc-s?:arm64"]
+            .into_iter()
+            .map(|pattern| RequestedPackagePattern::from_str(pattern).unwrap())
+            .collect::<IndexSet<_>>();
+        assert_eq!(
+            foreign_architectures(
+                &distro,
+                &supported_distros,
+                &mut requested_packages,
+                &mut package_patterns
+            )
+            .unwrap(),
+            vec![ArchitectureName::I_386, ArchitectureName::ARM_64]
+        );
+        assert_eq!(
+            package_patterns
+                .iter()
+                .map(RequestedPackagePattern::qualified_pattern)
+                .collect::<Vec<_>>(),
+            vec!["libicu*", "libgcc-s?:arm64"]
+        );
+        assert_eq!(
+            requested_packages
+                .iter()
+                .map(RequestedPackage::qualified_name)
+                .collect::<Vec<_>>(),
+            vec!["curl", "libc6:i386", "libstdc++6:i386", "git"]
+        );
+
+        let mut requested_packages =
+            IndexSet::from([RequestedPackage::from_str("libc6:riscv64").unwrap()]);
+        let distro = Distro {
+            version: "22.04".to_string(),
+            codename: "jammy".into(),
+            ..distro
+        };
+        assert!(matches!(
+            foreign_architectures(
+                &distro,
+                &supported_distros,
+                &mut requested_packages,
+                &mut IndexSet::new()
+            )
+            .unwrap_err(),
+            ValidateRequestedPackagesError::ForeignArchitectureNotAvailable {
+                package,
+                architecture: ArchitectureName::RISCV_64,
+                available_architectures,
+            } if package == "libc6:riscv64" && available_architectures == vec![ArchitectureName::I_386]
+        ));
+    }
+
+    #[test]
+    fn test_expand_tasks() {
+        let mut package_index = PackageIndex::default();
+        for name in ["openssh-server", "curl"] {
+            package_index.add_package(
+                create_repository_package()
+                    .name(name)
+                    .version("1.0")
+                    .repository_uri("http://archive.ubuntu.com/ubuntu")
+                    .filename(&format!("pool/main/{name}_1.0_amd64.deb"))
+                    .checksum("abc123")
+                    .task("ubuntu-server")
+                    .call(),
+            );
+        }
+        let mut requested_packages = IndexSet::from([RequestedPackage {
+            name: PackageName::from_str("curl").unwrap(),
+            architecture: None,
+            skip_dependencies: false,
+            force: true,
+            env_scope: None,
+            optional: false,
+            maintainer_scripts: None,
+            version: None,
+        }]);
+
+        let log = Print::new(std::io::stdout()).h1("test");
+        expand_tasks(
+            &package_index,
+            &["ubuntu-server".to_string()],
+            &mut requested_packages,
+            log,
+        )
+        .unwrap();
+        assert_eq!(
+            requested_packages,
+            IndexSet::from([
+                RequestedPackage {
+                    name: PackageName::from_str("curl").unwrap(),
+                    architecture: None,
+                    skip_dependencies: false,
+                    force: true,
+                    env_scope: None,
+                    optional: false,
+                    maintainer_scripts: None,
+                    version: None,
+                },
+                RequestedPackage::from_str("openssh-server").unwrap(),
+            ])
+        );
+
+        let log = Print::new(std::io::stdout()).h1("test");
+        let error = expand_tasks(
+            &package_index,
+            &["ubuntu-desktop".to_string()],
+            &mut requested_packages,
+            log,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            libcnb::Error::BuildpackError(DebianPackagesBuildpackError::ValidateRequestedPackages(
+                ValidateRequestedPackagesError::TaskNotFound(task)
+            )) if task == "ubuntu-desktop"
+        ));
+    }
+
+    #[test]
+    fn test_expand_package_patterns() {
+        let mut package_index = PackageIndex::default();
+        for name in [
+            "libicu74",
+            "libicu-dev",
+            "icu-devtools",
+            "postgresql-client-16",
+        ] {
+            package_index.add_package(
+                create_repository_package()
+                    .name(name)
+                    .version("1.0")
+                    .repository_uri("http://archive.ubuntu.com/ubuntu")
+                    .filename(&format!("pool/main/{name}_1.0_amd64.deb"))
+                    .checksum("abc123")
+                    .call(),
+            );
+        }
+        let mut requested_packages = IndexSet::from([RequestedPackage {
+            force: true,
+            ..RequestedPackage::from_str("libicu74").unwrap()
+        }]);
+        let package_patterns = IndexSet::from([
+            RequestedPackagePattern {
+                env_scope: Some(EnvScope::Build),
+                ..RequestedPackagePattern::from_str("libicu*").unwrap()
+            },
+            RequestedPackagePattern::from_str("postgresql-client-1?").unwrap(),
+            RequestedPackagePattern {
+                optional: true,
+                ..RequestedPackagePattern::from_str("mysql-client-*").unwrap()
+            },
+        ]);
+
+        let log = Print::new(std::io::stdout()).h1("test");
+        expand_package_patterns(
+            &package_index,
+            &[],
+            &package_patterns,
+            &mut requested_packages,
+            log,
+        )
+        .unwrap();
+        assert_eq!(
+            requested_packages,
+            IndexSet::from([
+                RequestedPackage {
+                    force: true,
+                    ..RequestedPackage::from_str("libicu74").unwrap()
+                },
+                RequestedPackage {
+                    env_scope: Some(EnvScope::Build),
+                    ..RequestedPackage::from_str("libicu-dev").unwrap()
+                },
+                RequestedPackage::from_str("postgresql-client-16").unwrap(),
+            ])
+        );
+
+        let log = Print::new(std::io::stdout()).h1("test");
+        let error = expand_package_patterns(
+            &package_index,
+            &[],
+            &IndexSet::from([RequestedPackagePattern::from_str("mysql-client-*").unwrap()]),
+            &mut requested_packages,
+            log,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            libcnb::Error::BuildpackError(DebianPackagesBuildpackError::ValidateRequestedPackages(
+                ValidateRequestedPackagesError::PackagePatternNotMatched(pattern)
+            )) if pattern == "mysql-client-*"
+        ));
+    }
+
+    #[test]
+    fn test_expand_presets() {
+        let distro = Distro {
+            name: "Ubuntu".to_string(),
+            version: "24.04".to_string(),
+            codename: "noble".into(),
+            architecture: ArchitectureName::AMD_64,
+        };
+        let mut requested_packages = IndexSet::from([RequestedPackage {
+            force: true,
+            ..RequestedPackage::from_str("gdal-bin").unwrap()
+        }]);
+
+        let log = Print::new(std::io::stdout()).h1("test");
+        expand_presets(&distro, &[Preset::Geo], &mut requested_packages, log).unwrap();
+        assert_eq!(
+            requested_packages,
+            IndexSet::from([
+                RequestedPackage {
+                    force: true,
+                    ..RequestedPackage::from_str("gdal-bin").unwrap()
+                },
+                RequestedPackage::from_str("libgdal34t64").unwrap(),
+                RequestedPackage::from_str("libgeos-c1t64").unwrap(),
+                RequestedPackage::from_str("libproj25").unwrap(),
+            ])
+        );
+
+        let distro = Distro {
+            version: "18.04".to_string(),
+            codename: "bionic".into(),
+            ..distro
+        };
+        let log = Print::new(std::io::stdout()).h1("test");
+        let error =
+            expand_presets(&distro, &[Preset::Media], &mut IndexSet::new(), log).unwrap_err();
+        assert!(matches!(
+            error,
+            libcnb::Error::BuildpackError(DebianPackagesBuildpackError::ValidateRequestedPackages(
+                ValidateRequestedPackagesError::PresetNotAvailable {
+                    preset: Preset::Media,
+                    codename,
+                }
+            )) if codename == DistroCodename::from("bionic")
+        ));
+    }
+
+    #[test]
+    fn test_expand_package_patterns_with_regex() {
+        let mut package_index = PackageIndex::default();
+        for name in [
+            "libreoffice-core",
+            "libreoffice-core-nogui",
+            "libreoffice-common",
+        ] {
+            package_index.add_package(
+                create_repository_package()
+                    .name(name)
+                    .version("1.0")
+                    .repository_uri("http://archive.ubuntu.com/ubuntu")
+                    .filename(&format!("pool/main/{name}_1.0_amd64.deb"))
+                    .checksum("abc123")
+                    .call(),
+            );
+        }
+        let package_pattern = RequestedPackagePattern {
+            pattern: PackagePattern::Regex(PackageRegex::from_str("^libreoffice-core.*").unwrap()),
+            ..RequestedPackagePattern::from_str("libreoffice-*").unwrap()
+        };
+
+        let mut requested_packages = IndexSet::new();
+        let log = Print::new(std::io::stdout()).h1("test");
+        expand_package_patterns(
+            &package_index,
+            &[],
+            &IndexSet::from([package_pattern.clone()]),
+            &mut requested_packages,
+            log,
+        )
+        .unwrap();
+        assert_eq!(
+            requested_packages,
+            IndexSet::from([
+                RequestedPackage::from_str("libreoffice-core").unwrap(),
+                RequestedPackage::from_str("libreoffice-core-nogui").unwrap(),
+            ])
+        );
+
+        let log = Print::new(std::io::stdout()).h1("test");
+        let error = expand_package_patterns(
+            &package_index,
+            &[],
+            &IndexSet::from([RequestedPackagePattern {
+                max_matches: Some(1),
+                ..package_pattern
+            }]),
+            &mut IndexSet::new(),
+            log,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            libcnb::Error::BuildpackError(DebianPackagesBuildpackError::ValidateRequestedPackages(
+                ValidateRequestedPackagesError::TooManyPatternMatches {
+                    pattern,
+                    matched_packages,
+                    max_matches: 1,
+                }
+            )) if pattern == "^libreoffice-core.*" && matched_packages.len() == 2
+        ));
+    }
+}
@@ -1,13 +1,21 @@
 //! All integration tests are skipped by default (using the `ignore` attribute)
 //! since performing builds is slow. To run them use: `cargo test -- --ignored`.
+//!
+//! Set `INTEGRATION_TEST_HTTP_FIXTURES` to `record` or `replay` to capture the responses from
+//! package repositories into `tests/http_fixtures` or to run the tests against them without
+//! network access. The buildpack is then compiled with the `http-fixtures` feature instead of
+//! being packaged by `libcnb-test`.
 
 // Required due to: https://github.com/rust-lang/rust/issues/95513
 #![allow(unused_crate_dependencies)]
 #![allow(clippy::unwrap_used)]
 
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::str::FromStr;
+use std::sync::OnceLock;
 
+use base64::prelude::*;
 use libcnb_test::{assert_contains, assert_contains_match, assert_not_contains, BuildConfig, BuildpackReference, PackResult, TestContext, TestRunner};
 use toml_edit::{value, Array, DocumentMut, InlineTable};
 
@@ -32,7 +40,22 @@ fn test_failed_detection_when_no_project_file_exists() {
             config.expected_pack_result(PackResult::Failure);
         },
         |ctx| {
-            assert_contains!(ctx.pack_stdout, "No project.toml file found.");
+            assert_contains!(ctx.pack_stdout, "No project.toml file found and BP_DEB_PACKAGES_INSTALL is not set.");
+        },
+    );
+}
+
+#[test]
+#[ignore = "integration test"]
+fn test_install_env_without_project_file() {
+    integration_test_with_config(
+        "fixtures/no_project_file",
+        |config| {
+            config.env("BP_DEB_PACKAGES_INSTALL", "xmlsec1");
+        },
+        |ctx| {
+            assert_contains!(ctx.pack_stdout, "Determining install requirements for requested package `xmlsec1`");
+            assert_contains!(ctx.pack_stdout, "Installation complete");
         },
     );
 }
@@ -111,6 +134,7 @@ fn test_general_usage_output() {
                 assert_contains!(ctx.pack_stdout, "`xmlsec1@1.2.33-1build2` from http://archive.ubuntu.com/ubuntu/pool/main/x/xmlsec1/xmlsec1_1.2.33-1build2_amd64.deb");
                 assert_contains_match!(ctx.pack_stdout, "`curl@7.81.0-.*` from http://archive.ubuntu.com/ubuntu/pool/main/c/curl/curl_7.81.0-.*_amd64.deb");
                 assert_contains!(ctx.pack_stdout, "Downloading");
+                assert_contains!(ctx.pack_stdout, "Installation summary");
                 assert_contains!(ctx.pack_stdout, "Installation complete");
 
                 assert_not_contains!(ctx.pack_stdout, "Layer file listing");
@@ -515,7 +539,7 @@ fn geo_buildpack_use_case() {
                     set_install_config(&app_dir, [requested_package_config("libgdal-dev", false)]);
                     std::fs::write(app_dir.join("requirements.txt"), "GDAL==3.4.1").unwrap();
                 })
-                .buildpacks(vec![BuildpackReference::CurrentCrate, BuildpackReference::Other("heroku/python".to_string())]);
+                .buildpacks(vec![current_buildpack(), BuildpackReference::Other("heroku/python".to_string())]);
         },
         |ctx| {
             assert_contains!(ctx.pack_stdout, "Adding `libgdal-dev@3.4.1");
@@ -558,25 +582,136 @@ fn panic_unsupported_test_configuration() -> ! {
     panic!("Unsupported test configuration:\nINTEGRATION_TEST_CNB_BUILDER={}\nINTEGRATION_TEST_CNB_ARCH={}", get_integration_test_builder(), get_integration_test_arch());
 }
 
+fn get_integration_test_target_triple() -> &'static str {
+    match get_integration_test_arch().as_str() {
+        "amd64" => "x86_64-unknown-linux-musl",
+        "arm64" => "aarch64-unknown-linux-musl",
+        _ => panic_unsupported_test_configuration(),
+    }
+}
+
 fn integration_test(fixture: &str, test_body: fn(TestContext)) {
     integration_test_with_config(fixture, |_| {}, test_body);
 }
 
 fn integration_test_with_config(fixture: &str, with_config: fn(&mut BuildConfig), test_body: fn(TestContext)) {
     let builder = get_integration_test_builder();
-    let app_dir = PathBuf::from("tests").join(fixture);
-
-    let target_triple = match get_integration_test_arch().as_str() {
-        "amd64" => "x86_64-unknown-linux-musl",
-        "arm64" => "aarch64-unknown-linux-musl",
-        _ => panic_unsupported_test_configuration(),
-    };
+    let mut app_dir = PathBuf::from("tests").join(fixture);
+    let http_fixtures_mode = get_integration_test_http_fixtures_mode();
+
+    // the recorded fixtures are copied into the application so they're available to the build
+    let replay_app_dir = tempfile::tempdir().unwrap();
+    if http_fixtures_mode == Some(HttpFixturesMode::Replay) {
+        let http_fixtures_dir = get_integration_test_http_fixtures_dir();
+        let record_hint = "Record them with INTEGRATION_TEST_HTTP_FIXTURES=record.";
+        assert!(http_fixtures_dir.is_dir(), "No HTTP fixtures recorded in {}. {record_hint}", http_fixtures_dir.display());
+        copy_dir(&app_dir, replay_app_dir.path());
+        copy_dir(&http_fixtures_dir, &replay_app_dir.path().join(HTTP_FIXTURES_DIR));
+        app_dir = replay_app_dir.path().to_path_buf();
+    }
 
     let mut build_config = BuildConfig::new(builder, app_dir);
-    build_config.target_triple(target_triple);
+    build_config.target_triple(get_integration_test_target_triple());
+    build_config.buildpacks(vec![current_buildpack()]);
+    match http_fixtures_mode {
+        Some(HttpFixturesMode::Record) => build_config.env("BP_DEB_PACKAGES_RECORD_HTTP_FIXTURES", HTTP_FIXTURES_DIR),
+        Some(HttpFixturesMode::Replay) => build_config.env("BP_DEB_PACKAGES_REPLAY_HTTP_FIXTURES", HTTP_FIXTURES_DIR),
+        None => &mut build_config,
+    };
     with_config(&mut build_config);
 
-    TestRunner::default().build(build_config, test_body);
+    TestRunner::default().build(build_config, |ctx| {
+        let built = ctx.pack_stdout.contains("Successfully built image");
+        if http_fixtures_mode == Some(HttpFixturesMode::Record) && built {
+            save_http_fixtures(&ctx);
+        }
+        test_body(ctx);
+    });
+}
+
+const HTTP_FIXTURES_DIR: &str = ".http-fixtures";
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum HttpFixturesMode {
+    Record,
+    Replay,
+}
+
+fn get_integration_test_http_fixtures_mode() -> Option<HttpFixturesMode> {
+    match std::env::var("INTEGRATION_TEST_HTTP_FIXTURES").as_deref() {
+        Ok("record") => Some(HttpFixturesMode::Record),
+        Ok("replay") => Some(HttpFixturesMode::Replay),
+        Ok("") | Err(_) => None,
+        Ok(other) => panic!("Unsupported INTEGRATION_TEST_HTTP_FIXTURES value: {other}"),
+    }
+}
+
+// `libcnb-test` compiles the buildpack without any cargo features so when recording or replaying
+// fixtures the buildpack is compiled with the `http-fixtures` feature and assembled here instead.
+fn current_buildpack() -> BuildpackReference {
+    static HTTP_FIXTURES_BUILDPACK_DIR: OnceLock<PathBuf> = OnceLock::new();
+    if get_integration_test_http_fixtures_mode().is_none() {
+        return BuildpackReference::CurrentCrate;
+    }
+    let buildpack_dir = HTTP_FIXTURES_BUILDPACK_DIR.get_or_init(package_http_fixtures_buildpack);
+    BuildpackReference::Other(buildpack_dir.to_string_lossy().to_string())
+}
+
+fn package_http_fixtures_buildpack() -> PathBuf {
+    let target_triple = get_integration_test_target_triple();
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let status = Command::new("cargo")
+        .args(["build", "--features", "http-fixtures", "--target", target_triple])
+        .env("CARGO_PROFILE_DEV_DEBUG", "false")
+        .env("CARGO_PROFILE_DEV_STRIP", "true")
+        .current_dir(&manifest_dir)
+        .status()
+        .unwrap();
+    assert!(status.success(), "Failed to compile the buildpack with the http-fixtures feature");
+
+    let target_dir = std::env::var_os("CARGO_TARGET_DIR").map_or_else(|| manifest_dir.join("target"), PathBuf::from);
+    let buildpack_dir = target_dir.join("http-fixtures-buildpack").join(target_triple);
+    let bin_dir = buildpack_dir.join("bin");
+    std::fs::create_dir_all(&bin_dir).unwrap();
+    std::fs::copy(manifest_dir.join("buildpack.toml"), buildpack_dir.join("buildpack.toml")).unwrap();
+    std::fs::copy(target_dir.join(target_triple).join("debug").join(env!("CARGO_PKG_NAME")), bin_dir.join("build")).unwrap();
+    if !bin_dir.join("detect").is_symlink() {
+        std::os::unix::fs::symlink("build", bin_dir.join("detect")).unwrap();
+    }
+    buildpack_dir
+}
+
+// Fixtures are shared by every test for a builder and architecture since they're matched on the
+// request URL.
+fn get_integration_test_http_fixtures_dir() -> PathBuf {
+    http_fixtures_dir(&get_integration_test_builder(), &get_integration_test_arch())
+}
+
+fn http_fixtures_dir(builder: &str, arch: &str) -> PathBuf {
+    PathBuf::from("tests").join("http_fixtures").join(format!("{}-{arch}", builder.replace(['/', ':'], "_")))
+}
+
+// The fixtures recorded during a build are written to the application directory of the image so
+// they're copied back out of it with the shell tools available in the image.
+fn save_http_fixtures(ctx: &TestContext) {
+    let archive = ctx.run_shell_command(format!("[ ! -d /workspace/{HTTP_FIXTURES_DIR} ] || tar -C /workspace/{HTTP_FIXTURES_DIR} -c . | base64 -w 0")).stdout;
+    if archive.trim().is_empty() {
+        return;
+    }
+    let archive = BASE64_STANDARD.decode(archive.trim()).unwrap();
+    tar::Archive::new(archive.as_slice()).unpack(get_integration_test_http_fixtures_dir()).unwrap();
+}
+
+fn copy_dir(from: &Path, to: &Path) {
+    for entry in walkdir::WalkDir::new(from) {
+        let entry = entry.unwrap();
+        let target = to.join(entry.path().strip_prefix(from).unwrap());
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(target).unwrap();
+        } else {
+            std::fs::copy(entry.path(), target).unwrap();
+        }
+    }
 }
 
 fn get_env_var(ctx: &TestContext, env_var_name: &str) -> String {
@@ -643,6 +778,24 @@ fn test_get_integration_test_arch() {
     assert_eq!(get_integration_test_arch(), DEFAULT_ARCH);
 }
 
+#[test]
+fn test_http_fixtures_dir() {
+    assert_eq!(http_fixtures_dir("heroku/builder:24", "arm64"), PathBuf::from("tests/http_fixtures/heroku_builder_24-arm64"));
+}
+
+#[test]
+fn test_copy_dir() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let from = temp_dir.path().join("from");
+    let to = temp_dir.path().join("to");
+    std::fs::create_dir_all(from.join("nested")).unwrap();
+    std::fs::write(from.join("nested").join("fixture.json"), "{}").unwrap();
+
+    copy_dir(&from, &to);
+
+    assert_eq!(std::fs::read_to_string(to.join("nested").join("fixture.json")).unwrap(), "{}");
+}
+
 #[test]
 fn test_panic_unsupported_test_configuration() {
     // This test should panic
@@ -663,4 +816,4 @@ fn test_set_install_config() {
     let contents = std::fs::read_to_string(app_dir.join("project.toml")).unwrap();
     assert!(contents.contains("ffmpeg"));
     assert!(contents.contains("skip_dependencies = true"));
-}
\ No newline at end of file
+}